@@ -0,0 +1,222 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Derivation macros for LNP/BP Core Library. Generated code refers to the
+//! library items by `::lnpbp` paths, so the library must be a dependency of
+//! the crate using the macros.
+
+#![recursion_limit = "256"]
+#![deny(
+    non_upper_case_globals,
+    non_camel_case_types,
+    non_snake_case,
+    unused_mut,
+    unused_imports,
+    dead_code,
+    missing_docs
+)]
+
+extern crate proc_macro;
+#[macro_use]
+extern crate quote;
+#[macro_use]
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use syn::spanned::Spanned;
+use syn::{Attribute, Data, DeriveInput, Error, Fields, Index, Lit, Meta, NestedMeta, Result};
+
+const ATTR_NAME: &str = "commit_encode";
+
+/// Derives commitment encoding for a type. The strategy is selected with
+/// `#[commit_encode(strategy = "strict" | "conceal" | "merklize")]`
+/// attribute, matching `UsingStrict`, `UsingConceal` and `Merklization`
+/// strategies from `lnpbp::client_side_validation::commit_strategy`.
+///
+/// Fields of a structure which must not be committed to (like caches) may be
+/// marked with `#[commit_encode(skip)]`, which is supported only by the
+/// `strict` strategy. In this case the rest of the fields are strict-encoded
+/// in the order of their declaration.
+#[proc_macro_derive(CommitEncode, attributes(commit_encode))]
+pub fn derive_commit_encode(input: TokenStream) -> TokenStream {
+    let derive_input = parse_macro_input!(input as DeriveInput);
+    commit_encode_inner(derive_input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Strategy {
+    Strict,
+    Conceal,
+    Merklize,
+}
+
+fn commit_encode_inner(input: DeriveInput) -> Result<TokenStream2> {
+    let strategy = parse_strategy(&input)?;
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match input.data {
+        Data::Struct(ref data) => Some(&data.fields),
+        Data::Enum(ref data) => {
+            for field in data
+                .variants
+                .iter()
+                .flat_map(|variant| variant.fields.iter())
+            {
+                if is_skipped(&field.attrs)? {
+                    return Err(Error::new(
+                        field.span(),
+                        "skipping fields is supported only for structures",
+                    ));
+                }
+            }
+            None
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                input.span(),
+                "deriving commitment encoding is not supported for unions",
+            ))
+        }
+    };
+
+    let mut committed = vec![];
+    let mut skips = false;
+    for (index, field) in fields.into_iter().flat_map(Fields::iter).enumerate() {
+        if is_skipped(&field.attrs)? {
+            if strategy != Strategy::Strict {
+                return Err(Error::new(
+                    field.span(),
+                    "skipping fields is supported only by the `strict` strategy",
+                ));
+            }
+            skips = true;
+            continue;
+        }
+        committed.push(match field.ident {
+            Some(ref name) => quote! { #name },
+            None => {
+                let index = Index::from(index);
+                quote! { #index }
+            }
+        });
+    }
+
+    if skips {
+        return Ok(quote! {
+            impl #impl_generics ::lnpbp::client_side_validation::CommitEncode for #ident #ty_generics #where_clause {
+                fn commit_encode<E: ::std::io::Write>(self, mut e: E) -> usize {
+                    let mut len = 0usize;
+                    #(
+                        len += ::lnpbp::strict_encoding::StrictEncode::strict_encode(&self.#committed, &mut e)
+                            .unwrap_or_else(|_| panic!("Strict encoding of committed data must not fail"));
+                    )*
+                    len
+                }
+            }
+        });
+    }
+
+    let strategy = match strategy {
+        Strategy::Strict => quote! { UsingStrict },
+        Strategy::Conceal => quote! { UsingConceal },
+        Strategy::Merklize => quote! { Merklization },
+    };
+    Ok(quote! {
+        impl #impl_generics ::lnpbp::client_side_validation::CommitEncodeWithStrategy for #ident #ty_generics #where_clause {
+            type Strategy = ::lnpbp::client_side_validation::commit_strategy::#strategy;
+        }
+    })
+}
+
+fn parse_strategy(input: &DeriveInput) -> Result<Strategy> {
+    let mut strategy = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident(ATTR_NAME))
+    {
+        for nested in attr_args(attr)? {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(ref name_value))
+                    if name_value.path.is_ident("strategy") =>
+                {
+                    let value = match name_value.lit {
+                        Lit::Str(ref value) => value,
+                        ref lit => {
+                            return Err(Error::new(
+                                lit.span(),
+                                "commitment strategy must be a string literal",
+                            ))
+                        }
+                    };
+                    strategy = Some(match value.value().as_str() {
+                        "strict" => Strategy::Strict,
+                        "conceal" => Strategy::Conceal,
+                        "merklize" => Strategy::Merklize,
+                        _ => {
+                            return Err(Error::new(
+                                value.span(),
+                                "unknown commitment strategy; expected `strict`, `conceal` \
+                                 or `merklize`",
+                            ))
+                        }
+                    });
+                }
+                other => {
+                    return Err(Error::new(
+                        other.span(),
+                        "unknown `commit_encode` attribute argument",
+                    ))
+                }
+            }
+        }
+    }
+    strategy.ok_or_else(|| {
+        Error::new(
+            input.ident.span(),
+            "`#[commit_encode(strategy = \"...\")]` attribute is required",
+        )
+    })
+}
+
+fn is_skipped(attrs: &[Attribute]) -> Result<bool> {
+    let mut skipped = false;
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident(ATTR_NAME)) {
+        for nested in attr_args(attr)? {
+            match nested {
+                NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("skip") => skipped = true,
+                other => {
+                    return Err(Error::new(
+                        other.span(),
+                        "unknown `commit_encode` field attribute argument",
+                    ))
+                }
+            }
+        }
+    }
+    Ok(skipped)
+}
+
+fn attr_args(attr: &Attribute) -> Result<Vec<NestedMeta>> {
+    match attr.parse_meta()? {
+        Meta::List(list) => Ok(list.nested.into_iter().collect()),
+        meta => Err(Error::new(
+            meta.span(),
+            "`commit_encode` attribute must have a list of arguments",
+        )),
+    }
+}
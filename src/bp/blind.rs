@@ -11,11 +11,13 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use bitcoin::hashes::{sha256d, Hash, HashEngine};
+use bitcoin::hashes::{sha256, sha256d, Hash, HashEngine};
 use bitcoin::{OutPoint, Txid};
+use rand::Rng;
 
-use crate::client_side_validation::Conceal;
-use crate::commit_verify::CommitVerify;
+use crate::client_side_validation::{commit_strategy, CommitConceal, CommitEncodeWithStrategy};
+use crate::commit_verify::{CommitVerify, Verification};
+use crate::counter::MonotonicCounter;
 
 /// Data required to generate or reveal the information about blinded
 /// transaction outpoint
@@ -24,7 +26,7 @@ use crate::commit_verify::CommitVerify;
 pub struct OutpointReveal {
     /// Blinding factor preventing rainbow table bruteforce attack based on
     /// the existing blockchain txid set
-    pub blinding: u32,
+    pub blinding: u64,
 
     /// Txid that should be blinded
     pub txid: Txid,
@@ -33,6 +35,19 @@ pub struct OutpointReveal {
     pub vout: u16,
 }
 
+#[cfg(all(test, feature = "property_tests"))]
+impl quickcheck::Arbitrary for OutpointReveal {
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use quickcheck::Arbitrary;
+        let txid: Vec<u8> = (0..32).map(|_| u8::arbitrary(g)).collect();
+        Self {
+            blinding: u64::arbitrary(g),
+            txid: Txid::from_slice(&txid).expect("fixed-size slice"),
+            vout: u16::arbitrary(g),
+        }
+    }
+}
+
 impl From<OutpointReveal> for OutPoint {
     #[inline]
     fn from(reveal: OutpointReveal) -> Self {
@@ -40,7 +55,17 @@ impl From<OutpointReveal> for OutPoint {
     }
 }
 
-impl Conceal for OutpointReveal {
+/// Transaction output number exceeding 16-bit range, which can't be used by
+/// [OutpointReveal]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display_from(Debug)]
+pub struct VoutOverflow(pub u32);
+
+impl CommitEncodeWithStrategy for OutpointReveal {
+    type Strategy = commit_strategy::UsingConceal;
+}
+
+impl CommitConceal for OutpointReveal {
     type Confidential = OutpointHash;
 
     #[inline]
@@ -49,27 +74,311 @@ impl Conceal for OutpointReveal {
     }
 }
 
+/// Outpoint reveal is equal to the outpoint hash if it conceals to it with
+/// the default (untagged) scheme
+impl PartialEq<OutpointHash> for OutpointReveal {
+    #[inline]
+    fn eq(&self, other: &OutpointHash) -> bool {
+        self.conceal() == *other
+    }
+}
+
+impl PartialEq<OutpointReveal> for OutpointHash {
+    #[inline]
+    fn eq(&self, other: &OutpointReveal) -> bool {
+        other == self
+    }
+}
+
+/// Application tag separating hash spaces of the outpoints blinded by
+/// different protocols. Default tag corresponds to the original untagged
+/// blinding scheme.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display, Default)]
+#[display_from(Debug)]
+pub struct OutpointTag(Option<sha256::Hash>);
+
+impl OutpointTag {
+    /// Constructs tag for the application with the given name
+    #[inline]
+    pub fn with(tag: &str) -> Self {
+        Self(Some(sha256::Hash::hash(tag.as_bytes())))
+    }
+
+    /// Detects whether the tag corresponds to the original untagged scheme
+    #[inline]
+    pub fn is_untagged(&self) -> bool {
+        self.0.is_none()
+    }
+}
+
 impl CommitVerify<OutpointReveal> for OutpointHash {
+    #[inline]
     fn commit(reveal: &OutpointReveal) -> Self {
+        OutpointHash::commit_tagged(reveal, &OutpointTag::default())
+    }
+}
+
+impl OutpointHash {
+    /// Creates commitment to the outpoint within the hash space of the
+    /// application identified by the `tag`. For tagged applications the
+    /// single SHA256 hash of the tag is written twice in front of the
+    /// blinding factor, txid and vout, and the whole data are then hashed
+    /// with double SHA256. This borrows the BIP-340 tag prefix, but unlike
+    /// BIP-340 the resulting hash is not a single SHA256.
+    pub fn commit_tagged(reveal: &OutpointReveal, tag: &OutpointTag) -> Self {
         let mut engine = OutpointHash::engine();
+        if let Some(tag_hash) = tag.0 {
+            engine.input(&tag_hash[..]);
+            engine.input(&tag_hash[..]);
+        }
         engine.input(&reveal.blinding.to_be_bytes()[..]);
         engine.input(&reveal.txid[..]);
         engine.input(&reveal.vout.to_be_bytes()[..]);
         OutpointHash::from_engine(engine)
     }
+
+    /// Verifies the blinded outpoint against the reveal data within the hash
+    /// space of the application identified by the `tag`
+    #[inline]
+    pub fn verify_tagged(&self, reveal: &OutpointReveal, tag: &OutpointTag) -> Verification {
+        Verification::with_match(OutpointHash::commit_tagged(reveal, tag) == *self)
+    }
 }
 
 impl OutpointReveal {
+    /// Creates reveal data for the transaction outpoint with random blinding
+    /// factor (see [OutpointReveal::with_random_blinding]). Fails if the
+    /// output number does not fit into 16 bits.
+    pub fn with_outpoint(outpoint: OutPoint) -> Result<Self, VoutOverflow> {
+        if outpoint.vout > core::u16::MAX as u32 {
+            Err(VoutOverflow(outpoint.vout))?
+        }
+        Ok(Self::with_random_blinding(
+            outpoint.txid,
+            outpoint.vout as u16,
+        ))
+    }
+
+    /// Returns transaction outpoint which is blinded by this data
+    #[inline]
+    pub fn outpoint(&self) -> OutPoint {
+        OutPoint::new(self.txid, self.vout as u32)
+    }
+
+    /// Creates outpoint reveal with the blinding factor taken from the
+    /// cryptographically secure random number generator
+    pub fn with_random_blinding(txid: Txid, vout: u16) -> Self {
+        Self {
+            blinding: rand::thread_rng().gen(),
+            txid,
+            vout,
+        }
+    }
+
+    /// Creates outpoint reveal with the blinding factor deterministically
+    /// derived from the secret and the next index provided by the counter,
+    /// guaranteeing that the blinding is not reused across restarts
+    pub fn with_derived_blinding<C: MonotonicCounter>(
+        txid: Txid,
+        vout: u16,
+        secret: &[u8],
+        counter: &mut C,
+    ) -> Result<Self, C::Error> {
+        let index = counter.next_index()?;
+        let mut engine = sha256::Hash::engine();
+        engine.input(secret);
+        engine.input(&index.to_le_bytes());
+        let mut blinding = [0u8; 8];
+        blinding.copy_from_slice(&sha256::Hash::from_engine(engine)[..8]);
+        Ok(Self {
+            blinding: u64::from_le_bytes(blinding),
+            txid,
+            vout,
+        })
+    }
+
+    /// Blinds the outpoint with the default (untagged) scheme
     #[inline]
     pub fn outpoint_hash(&self) -> OutpointHash {
         OutpointHash::commit(self)
     }
+
+    /// Verifies that the outpoint hash conceals this outpoint with the
+    /// default (untagged) scheme
+    #[inline]
+    pub fn verify(&self, hash: &OutpointHash) -> Verification {
+        Verification::with_match(self == hash)
+    }
+
+    /// Blinds the outpoint within the hash space of the application
+    /// identified by the `tag`
+    #[inline]
+    pub fn outpoint_hash_tagged(&self, tag: &OutpointTag) -> OutpointHash {
+        OutpointHash::commit_tagged(self, tag)
+    }
 }
 
 hash_newtype!(
     OutpointHash,
     sha256d::Hash,
     32,
-    doc = "Blind version of transaction outpoint"
+    doc = "Blind version of transaction outpoint. Hashes are ordered lexicographically \
+           over their bytes, in the same order as they are serialized."
 );
 impl_hashencode!(OutpointHash);
+
+impl CommitEncodeWithStrategy for OutpointHash {
+    type Strategy = commit_strategy::UsingStrict;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::strict_encoding::strict_encode;
+
+    #[test]
+    fn test_outpoint_hash_ordering() {
+        let hashes = (0u8..16)
+            .map(|no| {
+                OutpointReveal {
+                    blinding: no as u64,
+                    txid: Txid::default(),
+                    vout: 0,
+                }
+                .outpoint_hash()
+            })
+            .collect::<Vec<_>>();
+        for a in &hashes {
+            for b in &hashes {
+                assert_eq!(a.cmp(b), a.into_inner().cmp(&b.into_inner()));
+                assert_eq!(
+                    a.cmp(b),
+                    strict_encode(a).unwrap().cmp(&strict_encode(b).unwrap())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_outpoint_tag() {
+        let reveal = OutpointReveal {
+            blinding: 1,
+            txid: Txid::default(),
+            vout: 0,
+        };
+        let rgb = OutpointTag::with("RGB");
+        let storm = OutpointTag::with("Storm");
+        assert!(OutpointTag::default().is_untagged());
+        assert!(!rgb.is_untagged());
+
+        assert_eq!(
+            reveal.outpoint_hash_tagged(&OutpointTag::default()),
+            reveal.outpoint_hash()
+        );
+        let hash = reveal.outpoint_hash_tagged(&rgb);
+        assert_ne!(hash, reveal.outpoint_hash());
+        assert_ne!(hash, reveal.outpoint_hash_tagged(&storm));
+
+        assert!(hash.verify_tagged(&reveal, &rgb).is_valid());
+        assert!(hash.verify_tagged(&reveal, &storm).is_invalid());
+        assert!(hash.verify(&reveal).is_invalid());
+    }
+
+    #[test]
+    fn test_derived_blinding() {
+        struct Counter(u64);
+        impl MonotonicCounter for Counter {
+            type Error = ();
+            fn next_index(&mut self) -> Result<u64, ()> {
+                self.0 += 1;
+                Ok(self.0 - 1)
+            }
+        }
+
+        let mut counter = Counter(0);
+        let first =
+            OutpointReveal::with_derived_blinding(Txid::default(), 0, b"secret", &mut counter)
+                .unwrap();
+        let second =
+            OutpointReveal::with_derived_blinding(Txid::default(), 0, b"secret", &mut counter)
+                .unwrap();
+        assert_eq!(counter.0, 2);
+        assert_ne!(first.blinding, second.blinding);
+
+        let mut restored = Counter(0);
+        assert_eq!(
+            OutpointReveal::with_derived_blinding(Txid::default(), 0, b"secret", &mut restored),
+            Ok(first)
+        );
+    }
+
+    #[test]
+    fn test_blinding_encoding() {
+        use crate::client_side_validation::CommitEncode;
+        use crate::strict_encoding::test_helpers::test_encode_roundtrip;
+
+        let reveal = OutpointReveal {
+            blinding: 0x0102_0304_0506_0708,
+            txid: Txid::default(),
+            vout: 0x0a0b,
+        };
+        let data = strict_encode(&reveal).unwrap();
+        assert_eq!(data.len(), 8 + 32 + 2);
+        assert_eq!(data[..8], 0x0102_0304_0506_0708u64.to_le_bytes());
+        test_encode_roundtrip(&reveal).unwrap();
+
+        // Blinding factor bits beyond the 32-bit range affect the hash
+        let other = OutpointReveal {
+            blinding: reveal.blinding ^ (1 << 40),
+            ..reveal.clone()
+        };
+        assert_ne!(other.outpoint_hash(), reveal.outpoint_hash());
+
+        // Outpoint reveal commits to its concealed form
+        let mut commitment = vec![];
+        reveal.clone().commit_encode(&mut commitment);
+        let mut concealed = vec![];
+        reveal.outpoint_hash().commit_encode(&mut concealed);
+        assert_eq!(commitment, concealed);
+        assert_eq!(concealed, strict_encode(&reveal.outpoint_hash()).unwrap());
+
+        let random = OutpointReveal::with_random_blinding(Txid::default(), 1);
+        assert_eq!(random.vout, 1);
+        assert_ne!(
+            random.blinding,
+            OutpointReveal::with_random_blinding(Txid::default(), 1).blinding
+        );
+    }
+
+    #[test]
+    fn test_conceal_lifecycle() {
+        let outpoint = OutPoint::new(Txid::hash(b"txid"), 3);
+        let reveal = OutpointReveal::with_outpoint(outpoint).unwrap();
+        assert_eq!(reveal.outpoint(), outpoint);
+        assert_eq!(OutPoint::from(reveal.clone()), outpoint);
+        assert_ne!(
+            OutpointReveal::with_outpoint(outpoint).unwrap().blinding,
+            reveal.blinding
+        );
+        assert_eq!(
+            OutpointReveal::with_outpoint(OutPoint::new(outpoint.txid, 0x10000)),
+            Err(VoutOverflow(0x10000))
+        );
+
+        let hash = reveal.conceal();
+        assert_eq!(hash, reveal.outpoint_hash());
+        assert!(reveal == hash && hash == reveal);
+        assert!(reveal.verify(&hash).is_valid());
+        assert!(hash.verify(&reveal).is_valid());
+
+        let other = OutpointReveal {
+            vout: 4,
+            ..reveal.clone()
+        };
+        assert!(other != hash && hash != other);
+        assert!(other.verify(&hash).is_invalid());
+        assert!(reveal
+            .verify(&reveal.outpoint_hash_tagged(&OutpointTag::with("RGB")))
+            .is_invalid());
+    }
+}
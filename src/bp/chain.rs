@@ -0,0 +1,272 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Blockchains which may host LNP/BP protocols. Unlike [Network], which is
+//! identified by the P2P magic number and can't distinguish chains sharing
+//! it (like regtest and Liquid), chains are identified by the hash of their
+//! genesis block.
+
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::hashes::{sha256d, Hash};
+use bitcoin::BlockHash;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use super::Network;
+
+hash_newtype!(
+    AssetId,
+    sha256d::Hash,
+    32,
+    doc = "Identifier of an asset. The native asset of a chain is identified by \
+           the chain genesis block hash."
+);
+
+impl From<BlockHash> for AssetId {
+    #[inline]
+    fn from(genesis_hash: BlockHash) -> Self {
+        AssetId::from_inner(genesis_hash.into_inner())
+    }
+}
+
+/// Parameters of the chain
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ChainParams {
+    /// Chain name as used by Bitcoin Core RPC (`getblockchaininfo`)
+    pub name: &'static str,
+    /// Hash of the chain genesis block
+    pub genesis_hash: BlockHash,
+    /// Human-readable part of the bech32 segwit addresses
+    pub bech32_hrp: &'static str,
+    /// Default port of the P2P protocol
+    pub p2p_port: u16,
+    /// Default port of the node RPC interface
+    pub rpc_port: u16,
+    /// Ticker of the chain native asset
+    pub ticker: &'static str,
+    /// Whether the native asset has no value
+    pub is_testnet: bool,
+}
+
+/// Known chains
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[non_exhaustive]
+pub enum Chain {
+    Mainnet,
+    Testnet3,
+    Signet,
+    Regtest,
+    Liquid,
+}
+
+impl Chain {
+    pub fn all_known() -> Vec<Chain> {
+        use Chain::*;
+        vec![Mainnet, Testnet3, Signet, Regtest, Liquid]
+    }
+
+    /// Returns parameters of the chain
+    pub fn params(self) -> ChainParams {
+        let (name, genesis, bech32_hrp, p2p_port, rpc_port, ticker, is_testnet) = match self {
+            Chain::Mainnet => (
+                "main",
+                "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f",
+                "bc",
+                8333,
+                8332,
+                "BTC",
+                false,
+            ),
+            Chain::Testnet3 => (
+                "test",
+                "000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943",
+                "tb",
+                18333,
+                18332,
+                "tBTC",
+                true,
+            ),
+            Chain::Signet => (
+                "signet",
+                "00000008819873e925422c1ff0f99f7cc9bbb232af63a077a480a3633bee1ef6",
+                "tb",
+                38333,
+                38332,
+                "tBTC",
+                true,
+            ),
+            Chain::Regtest => (
+                "regtest",
+                "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206",
+                "bcrt",
+                18444,
+                18443,
+                "tBTC",
+                true,
+            ),
+            Chain::Liquid => (
+                "liquidv1",
+                "1466275836220db2944ca059a3a10ef6fd2ea684b0688d2c379296888a206003",
+                "ex",
+                7042,
+                7041,
+                "LBTC",
+                false,
+            ),
+        };
+        ChainParams {
+            name,
+            genesis_hash: BlockHash::from_hex(genesis).expect("hardcoded genesis hash"),
+            bech32_hrp,
+            p2p_port,
+            rpc_port,
+            ticker,
+            is_testnet,
+        }
+    }
+
+    /// Returns hash of the chain genesis block
+    #[inline]
+    pub fn genesis_hash(self) -> BlockHash {
+        self.params().genesis_hash
+    }
+
+    /// Finds known chain by its genesis block hash
+    pub fn from_genesis_hash(genesis_hash: BlockHash) -> Option<Chain> {
+        Self::all_known()
+            .into_iter()
+            .find(|chain| chain.genesis_hash() == genesis_hash)
+    }
+
+    /// Returns id of the chain native asset
+    #[inline]
+    pub fn native_asset(self) -> AssetId {
+        self.genesis_hash().into()
+    }
+
+    /// Returns P2P network of the bitcoin chains; Liquid has no
+    /// representation as [Network], since it shares magic number with
+    /// regtest
+    pub fn network(self) -> Option<Network> {
+        match self {
+            Chain::Mainnet => Some(Network::Mainnet),
+            Chain::Testnet3 => Some(Network::Testnet),
+            Chain::Signet => Some(Network::Signet),
+            Chain::Regtest => Some(Network::Regtest),
+            Chain::Liquid => None,
+        }
+    }
+}
+
+impl TryFrom<Network> for Chain {
+    type Error = ();
+
+    fn try_from(network: Network) -> Result<Self, Self::Error> {
+        Ok(match network {
+            Network::Mainnet => Chain::Mainnet,
+            Network::Testnet => Chain::Testnet3,
+            Network::Signet => Chain::Signet,
+            Network::Regtest => Chain::Regtest,
+            _ => Err(())?,
+        })
+    }
+}
+
+impl fmt::Display for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.params().name)
+    }
+}
+
+/// Error parsing chain from a string which is neither a known chain name nor
+/// a genesis block hash of a known chain
+#[derive(Debug, Display)]
+#[display_from(Debug)]
+pub struct ParseError;
+
+impl FromStr for Chain {
+    type Err = ParseError;
+
+    /// Parses chain name as returned by Bitcoin Core RPC, or genesis block
+    /// hash of a known chain
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_ascii_lowercase();
+        Self::all_known()
+            .into_iter()
+            .find(|chain| chain.params().name == s)
+            .or_else(|| {
+                BlockHash::from_hex(&s)
+                    .ok()
+                    .and_then(Chain::from_genesis_hash)
+            })
+            .ok_or(ParseError)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::strict_encoding::{strict_decode, strict_encode, Error};
+
+    #[test]
+    fn test_chain_params() {
+        let chains = Chain::all_known();
+        for chain in &chains {
+            let params = chain.params();
+            assert_eq!(Chain::from_genesis_hash(params.genesis_hash), Some(*chain));
+            assert_eq!(Chain::from_str(params.name).unwrap(), *chain);
+            assert_eq!(
+                Chain::from_str(&params.genesis_hash.to_string()).unwrap(),
+                *chain
+            );
+            assert_eq!(chain.to_string(), params.name);
+            assert_eq!(chain.native_asset()[..], params.genesis_hash[..]);
+            assert_eq!(
+                chain.network().map(Chain::try_from),
+                chain.network().map(|_| Ok(*chain))
+            );
+        }
+        for (no, chain) in chains.iter().enumerate() {
+            for other in &chains[no + 1..] {
+                assert_ne!(chain.native_asset(), other.native_asset());
+            }
+        }
+        assert_eq!(
+            Chain::Mainnet.genesis_hash().to_string(),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(Chain::from_genesis_hash(BlockHash::hash(b"")), None);
+        assert_eq!(Chain::Liquid.network(), None);
+        assert_eq!(Chain::try_from(Network::Other(0)), Err(()));
+        assert!(Chain::from_str("liquid").is_err());
+    }
+
+    #[test]
+    fn test_chain_encoding() {
+        for chain in Chain::all_known() {
+            let encoded = strict_encode(&chain).unwrap();
+            assert_eq!(encoded, chain.genesis_hash()[..].to_vec());
+            assert_eq!(strict_decode::<Chain>(&encoded).unwrap(), chain);
+
+            let asset = chain.native_asset();
+            let encoded = strict_encode(&asset).unwrap();
+            assert_eq!(encoded.len(), 32);
+            assert_eq!(strict_decode::<AssetId>(&encoded).unwrap(), asset);
+        }
+        assert!(matches!(
+            strict_decode::<Chain>(&[0u8; 32]),
+            Err(Error::UnsupportedDataStructure(_))
+        ));
+    }
+}
@@ -0,0 +1,202 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Proof-carrying address format, bundling bitcoin address with the data
+//! required to construct deterministic bitcoin commitment to the output
+//! paying to this address (like in pay-to-contract invoices).
+//!
+//! The string representation has form of `<address>#<proof>`, where `proof`
+//! is Base58-encoded strict encoding of the original public key and script
+//! information ([Proof]) followed by a 4-byte checksum. The checksum is
+//! computed as the first four bytes of double SHA256 hash of the address
+//! string concatenated with the proof data, so it protects both parts of the
+//! string.
+
+use bitcoin::hashes::{sha256, sha256d, Hash, HashEngine};
+use bitcoin::util::{address, base58};
+use bitcoin::Address;
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+
+use super::{Container, Proof, ScriptPubkeyContainer};
+use crate::bp::PubkeyScript;
+use crate::strict_encoding::{self, strict_decode, strict_encode};
+
+/// Separator between the address and the proof parts of the string
+pub const PROOF_ADDRESS_SEPARATOR: char = '#';
+
+const CHECKSUM_LEN: usize = 4;
+
+/// Bitcoin address extended with the original public key and script
+/// information, allowing the payer to construct output with deterministic
+/// bitcoin commitment
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ProofAddress {
+    /// Address of the output without the commitment
+    pub address: Address,
+    /// Data required to embed commitment into the output
+    pub proof: Proof,
+}
+
+#[derive(Debug, Display, From, Error)]
+#[display_from(Debug)]
+pub enum ProofAddressError {
+    /// The string does not contain proof part separated by `#` character
+    NoProof,
+
+    #[derive_from]
+    Address(address::Error),
+
+    #[derive_from]
+    Base58(base58::Error),
+
+    /// The proof data are too short to contain checksum
+    ProofTooShort,
+
+    ChecksumMismatch,
+
+    #[derive_from]
+    ProofEncoding(strict_encoding::Error),
+
+    /// The proof does not correspond to the address type
+    #[derive_from]
+    Incompatible(super::Error),
+}
+
+impl ProofAddress {
+    /// Constructs proof-carrying address checking that the proof structure
+    /// is compatible with the address type
+    pub fn with(address: Address, proof: Proof) -> Result<Self, ProofAddressError> {
+        let proof_address = Self { address, proof };
+        proof_address.container(&sha256::Hash::default())?;
+        Ok(proof_address)
+    }
+
+    /// Returns container for embedding commitment under the given protocol
+    /// tag into the output paying to the address
+    pub fn container(
+        &self,
+        protocol_tag: &sha256::Hash,
+    ) -> Result<ScriptPubkeyContainer, super::Error> {
+        ScriptPubkeyContainer::reconstruct(
+            &self.proof,
+            protocol_tag,
+            &PubkeyScript::from(self.address.script_pubkey()),
+        )
+    }
+
+    fn checksum(address: &str, proof_data: &[u8]) -> [u8; CHECKSUM_LEN] {
+        let mut engine = sha256d::Hash::engine();
+        engine.input(address.as_bytes());
+        engine.input(proof_data);
+        let mut checksum = [0u8; CHECKSUM_LEN];
+        checksum.copy_from_slice(&sha256d::Hash::from_engine(engine)[..CHECKSUM_LEN]);
+        checksum
+    }
+}
+
+impl Display for ProofAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let address = self.address.to_string();
+        let mut data =
+            strict_encode(&self.proof).expect("Strict encoding of DBC proof can't fail");
+        data.extend(&Self::checksum(&address, &data));
+        write!(
+            f,
+            "{}{}{}",
+            address,
+            PROOF_ADDRESS_SEPARATOR,
+            base58::encode_slice(&data)
+        )
+    }
+}
+
+impl FromStr for ProofAddress {
+    type Err = ProofAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, PROOF_ADDRESS_SEPARATOR);
+        let address = parts.next().unwrap_or_default();
+        let data = base58::from(parts.next().ok_or(ProofAddressError::NoProof)?)?;
+        if data.len() < CHECKSUM_LEN {
+            Err(ProofAddressError::ProofTooShort)?
+        }
+        let (data, checksum) = data.split_at(data.len() - CHECKSUM_LEN);
+        if Self::checksum(address, data) != checksum {
+            Err(ProofAddressError::ChecksumMismatch)?
+        }
+        Self::with(Address::from_str(address)?, strict_decode(&data)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::dbc::ScriptInfo;
+    use crate::bp::test::gen_secp_pubkeys;
+    use bitcoin::{secp256k1, Network, PublicKey};
+
+    fn pubkey() -> secp256k1::PublicKey {
+        gen_secp_pubkeys(1)[0]
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let key = PublicKey {
+            compressed: true,
+            key: pubkey(),
+        };
+        let proof_address =
+            ProofAddress::with(Address::p2wpkh(&key, Network::Bitcoin), Proof::from(pubkey()))
+                .unwrap();
+        let s = proof_address.to_string();
+        assert!(s.starts_with(&format!("{}#", proof_address.address)));
+        assert_eq!(ProofAddress::from_str(&s).unwrap(), proof_address);
+
+        let mut corrupted = s.clone();
+        let last = corrupted.pop().unwrap();
+        corrupted.push(if last == '2' { '3' } else { '2' });
+        assert!(matches!(
+            ProofAddress::from_str(&corrupted),
+            Err(ProofAddressError::ChecksumMismatch)
+        ));
+
+        let other = Address::p2pkh(&key, Network::Bitcoin).to_string();
+        let (_, proof) = s.split_at(s.find('#').unwrap());
+        assert!(matches!(
+            ProofAddress::from_str(&format!("{}{}", other, proof)),
+            Err(ProofAddressError::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            ProofAddress::from_str(&proof_address.address.to_string()),
+            Err(ProofAddressError::NoProof)
+        ));
+    }
+
+    #[test]
+    fn test_incompatible_proof() {
+        let key = PublicKey {
+            compressed: true,
+            key: pubkey(),
+        };
+        let proof = Proof {
+            pubkey: pubkey(),
+            script_info: ScriptInfo::Taproot(sha256::Hash::default()),
+            composition: None,
+        };
+        assert!(matches!(
+            ProofAddress::with(Address::p2wpkh(&key, Network::Bitcoin), proof),
+            Err(ProofAddressError::Incompatible(_))
+        ));
+    }
+}
@@ -0,0 +1,178 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Embedding of multiple commitments into different outputs of a single
+//! transaction in one pass. Used for anchoring several state transitions
+//! (possibly under different protocols) into a single funding transaction.
+
+use bitcoin::hashes::sha256;
+use bitcoin::Transaction;
+use std::collections::BTreeMap;
+
+use super::{Container, Error, Proof, ScriptPubkeyCommitment, ScriptPubkeyContainer, TxCommitment};
+use crate::commit_verify::{EmbedCommitVerify, InvalidReason, Verification};
+
+/// Combined proof for all commitments embedded into a transaction by
+/// [TxCommitment::embed_batch], indexed by the number of the output hosting
+/// each commitment
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display, Default)]
+#[display_from(Debug)]
+pub struct BatchProof {
+    pub proofs: BTreeMap<u32, Proof>,
+}
+
+impl BatchProof {
+    /// Verifies commitments in the transaction against the list of output
+    /// numbers, protocol tags and messages. The list must cover exactly the
+    /// same outputs as the proof.
+    pub fn verify<MSG>(
+        &self,
+        tx: &Transaction,
+        messages: &[(u32, sha256::Hash, MSG)],
+    ) -> Verification
+    where
+        MSG: AsRef<[u8]>,
+    {
+        if messages.len() != self.proofs.len() {
+            return Verification::Invalid(InvalidReason::ContainerMismatch);
+        }
+        for (vout, tag, msg) in messages {
+            let verification = match self.proofs.get(vout) {
+                Some(proof) => proof.verify(tx, *vout, msg, tag),
+                None => Verification::Invalid(InvalidReason::ContainerMismatch),
+            };
+            if verification.is_invalid() {
+                return verification;
+            }
+        }
+        Verification::Valid
+    }
+}
+
+impl TxCommitment {
+    /// Embeds commitments to the messages into the outputs of the transaction
+    /// template given by their numbers, using the provided `scriptPubkey`
+    /// containers. Values of the outputs are left unchanged. Fails if any of
+    /// the output numbers is out of range or is used more than once.
+    pub fn embed_batch<MSG>(
+        tx: &Transaction,
+        commitments: &[(u32, ScriptPubkeyContainer, MSG)],
+    ) -> Result<(TxCommitment, BatchProof), Error>
+    where
+        MSG: AsRef<[u8]>,
+    {
+        let mut tx = tx.clone();
+        let mut batch = BatchProof::default();
+        for (vout, container, msg) in commitments {
+            if batch.proofs.contains_key(vout) {
+                Err(Error::RepeatedBatchOutput(*vout))?
+            }
+            let txout = tx
+                .output
+                .get_mut(*vout as usize)
+                .ok_or(Error::BatchOutputOutOfRange(*vout))?;
+            txout.script_pubkey = ScriptPubkeyCommitment::embed_commit(container, msg)?
+                .into_inner()
+                .into_inner();
+            batch.proofs.insert(*vout, container.to_proof());
+        }
+        Ok((TxCommitment::from_inner(tx), batch))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::dbc::{ScriptInfo, ScriptPubkeyComposition};
+    use crate::bp::test::gen_secp_pubkeys;
+    use crate::strict_encoding::{strict_decode, strict_encode};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Script, TxIn, TxOut};
+
+    fn template() -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn::default()],
+            output: (1..4)
+                .map(|value| TxOut {
+                    value,
+                    script_pubkey: Script::new(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_batch_commit() {
+        let tag_rgb = sha256::Hash::hash(b"RGB");
+        let tag_other = sha256::Hash::hash(b"OTHER");
+        let keys = gen_secp_pubkeys(2);
+        let container = |tag: &sha256::Hash, no: usize| {
+            ScriptPubkeyContainer::construct(
+                tag,
+                keys[no],
+                ScriptInfo::None,
+                ScriptPubkeyComposition::WPubkeyHash,
+            )
+        };
+        let commitments = vec![
+            (2, container(&tag_rgb, 0), b"first".to_vec()),
+            (0, container(&tag_other, 1), b"second".to_vec()),
+        ];
+
+        let (tx, proof) = TxCommitment::embed_batch(&template(), &commitments).unwrap();
+        assert_eq!(proof.proofs.len(), 2);
+        assert_eq!(tx.output[1], template().output[1]);
+        assert_eq!(tx.output[2].value, 3);
+
+        let messages = commitments
+            .iter()
+            .map(|(vout, container, msg)| (*vout, container.tag, msg.clone()))
+            .collect::<Vec<_>>();
+        assert!(proof.verify(&tx, &messages).is_valid());
+        assert!(proof.verify(&tx, &messages[..1]).is_invalid());
+        assert!(proof
+            .verify(
+                &tx,
+                &[messages[0].clone(), (0, tag_other, b"other".to_vec())]
+            )
+            .is_invalid());
+
+        let decoded: BatchProof = strict_decode(&strict_encode(&proof).unwrap()).unwrap();
+        assert_eq!(decoded, proof);
+    }
+
+    #[test]
+    fn test_batch_errors() {
+        let tag = sha256::Hash::hash(b"RGB");
+        let container = ScriptPubkeyContainer::construct(
+            &tag,
+            gen_secp_pubkeys(1)[0],
+            ScriptInfo::None,
+            ScriptPubkeyComposition::PublicKey,
+        );
+        assert_eq!(
+            TxCommitment::embed_batch(&template(), &[(3, container.clone(), b"msg")]).err(),
+            Some(Error::BatchOutputOutOfRange(3))
+        );
+        assert_eq!(
+            TxCommitment::embed_batch(
+                &template(),
+                &[(1, container.clone(), b"msg"), (1, container, b"msg")]
+            )
+            .err(),
+            Some(Error::RepeatedBatchOutput(1))
+        );
+    }
+}
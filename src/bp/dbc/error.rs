@@ -12,7 +12,8 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 use crate::bp::{scripts::Error as ScriptPubkeyError, PubkeyParseError};
-use bitcoin::secp256k1;
+use bitcoin::util::bip32;
+use bitcoin::{secp256k1, PubkeyHash};
 
 #[derive(Clone, PartialEq, Debug, Display, Error, From)]
 #[display_from(Debug)]
@@ -25,16 +26,96 @@ pub enum Error {
     #[derive_from]
     InvalidScriptPubkey(ScriptPubkeyError),
 
-    LockscriptParseError,
+    /// LockScript can't be parsed with miniscript or the extracted keys can't
+    /// be processed
+    LockscriptParseError(PubkeyParseError),
 
+    /// LockScript does not contain any public keys or public key hashes
     LockscriptContainsNoKeys,
 
-    LockscriptContainsUnknownHashes,
+    /// LockScript contains public key hashes (`hashes` field) which can't be
+    /// resolved neither against the key that must contain the commitment nor
+    /// against `keys_found` public keys present in the script
+    LockscriptContainsUnknownHashes {
+        hashes: Vec<PubkeyHash>,
+        keys_found: usize,
+    },
 
     /// Attempt to commit into LockScript has failed: the key that must contain
-    /// the commitment/tweak was not found either in plain nor hash form in
-    /// any of the script branches
-    LockscriptKeyNotFound,
+    /// the commitment/tweak (`pubkey` field) was not found either in plain nor
+    /// hash form in any of the script branches. The script contains
+    /// `keys_found` other public keys, some of which are uncompressed if
+    /// `uncompressed_found` is set
+    LockscriptKeyNotFound {
+        pubkey: secp256k1::PublicKey,
+        keys_found: usize,
+        uncompressed_found: bool,
+    },
+
+    /// The key that must contain the commitment is present in the LockScript
+    /// both in compressed and uncompressed form (either as a plain key or
+    /// as a key hash)
+    LockscriptMixedKeyEncodings(secp256k1::PublicKey),
+
+    /// Transaction has no outputs which may host LNPBP-3 commitment
+    TxHasNoOutputs,
+
+    /// Private key does not correspond to the original public key of the
+    /// prepared commitment
+    SecretKeyMismatch,
+
+    /// Private key with the applied tweak does not correspond to the tweaked
+    /// public key of the prepared commitment
+    TweakMismatch,
+
+    /// Derivation of the host key from the extended key has failed
+    #[derive_from]
+    Bip32(bip32::Error),
+
+    /// Threshold commitment can't be made since the host key is not
+    /// controlled by the committing party
+    HostKeyNotControlled,
+
+    /// Number of the controlled keys (first field) is below the required
+    /// threshold (second field)
+    ThresholdNotMet(usize, usize),
+
+    /// Threshold tweak was already applied to one of the private keys
+    TweakAlreadyApplied,
+
+    /// Batch commitment references output number which is absent in the
+    /// transaction
+    BatchOutputOutOfRange(u32),
+
+    /// Batch commitment contains more than one commitment for the same
+    /// transaction output
+    RepeatedBatchOutput(u32),
+
+    /// Script contains truncated data push starting at the given byte offset
+    TruncatedPush(usize),
+
+    /// Tapscript contains 32-byte data push (with the data at the given byte
+    /// offset) in a position where it is neither a public key nor a hash lock
+    /// digest, so it can't be decided whether it must be committed to
+    TapscriptUnrecognizedPush(usize),
+}
+
+impl Error {
+    /// Detects whether the commitment may succeed if retried with other or
+    /// additional key material (another host key, keys resolving the script
+    /// hashes, other private keys etc), or it will fail with any keys
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            // Tweaking may fail for a specific public key only
+            Error::Secp256k1(_) => true,
+            Error::LockscriptContainsUnknownHashes { .. } => true,
+            Error::LockscriptKeyNotFound { .. } => true,
+            Error::SecretKeyMismatch => true,
+            Error::HostKeyNotControlled => true,
+            Error::ThresholdNotMet(..) => true,
+            _ => false,
+        }
+    }
 }
 
 impl From<secp256k1::Error> for Error {
@@ -44,7 +125,7 @@ impl From<secp256k1::Error> for Error {
 }
 
 impl From<PubkeyParseError> for Error {
-    fn from(_: PubkeyParseError) -> Self {
-        Self::LockscriptParseError
+    fn from(err: PubkeyParseError) -> Self {
+        Self::LockscriptParseError(err)
     }
 }
@@ -0,0 +1,202 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! LNPBP-1 commitments into the keys derived from BIP-32 extended public
+//! keys. Since the container keeps the extended key and the derivation path,
+//! wallets are able to find the private key for the tweaked output by
+//! re-deriving it and applying the same tweak.
+
+use bitcoin::hashes::sha256;
+use bitcoin::secp256k1::{self, Secp256k1};
+use bitcoin::util::bip32;
+
+use super::{Error, KeyOrigin, LNPBP1Commitment, LNPBP1Container, PreparedTweak};
+use crate::commit_verify::EmbedCommitVerify;
+
+/// Container for LNPBP-1 commitment into the public key derived from the
+/// extended public key
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display_from(Debug)]
+pub struct HdContainer {
+    /// Extended public key from which the host key is derived
+    pub xpub: bip32::ExtendedPubKey,
+    /// Origin of the extended public key (master key fingerprint and
+    /// derivation path from the master key), if known
+    pub xpub_origin: Option<KeyOrigin>,
+    /// Derivation path from the extended public key to the host key; must
+    /// contain only non-hardened steps
+    pub derivation: bip32::DerivationPath,
+    /// Single SHA256 hash of the protocol-specific tag
+    pub tag: sha256::Hash,
+}
+
+impl HdContainer {
+    /// Derives host public key for the commitment
+    pub fn pubkey(&self) -> Result<secp256k1::PublicKey, Error> {
+        let ec = Secp256k1::<secp256k1::VerifyOnly>::verification_only();
+        Ok(self.xpub.derive_pub(&ec, &self.derivation)?.public_key.key)
+    }
+
+    /// Returns origin of the host key. If the origin of the extended key is
+    /// unknown, the extended key fingerprint is used as the origin.
+    pub fn key_origin(&self) -> KeyOrigin {
+        match self.xpub_origin {
+            Some(ref origin) => KeyOrigin {
+                fingerprint: origin.fingerprint,
+                derivation: bip32::DerivationPath::from(
+                    (&origin.derivation)
+                        .into_iter()
+                        .chain(&self.derivation)
+                        .cloned()
+                        .collect::<Vec<_>>(),
+                ),
+            },
+            None => KeyOrigin {
+                fingerprint: self.xpub.fingerprint(),
+                derivation: self.derivation.clone(),
+            },
+        }
+    }
+
+    /// Constructs LNPBP-1 container for the derived host key
+    pub fn to_lnpbp1_container(&self) -> Result<LNPBP1Container, Error> {
+        Ok(LNPBP1Container {
+            pubkey: self.pubkey()?,
+            tag: self.tag,
+            key_origin: Some(self.key_origin()),
+        })
+    }
+
+    /// Commits to the message by tweaking the derived host key
+    pub fn commit<MSG>(&self, msg: &MSG) -> Result<LNPBP1Commitment, Error>
+    where
+        MSG: AsRef<[u8]>,
+    {
+        Ok(LNPBP1Commitment::embed_commit(
+            &self.to_lnpbp1_container()?,
+            msg,
+        )?)
+    }
+
+    /// Prepares commitment to the message, which tweak has to be applied to
+    /// the private key by the signer. The prepared tweak carries origin of
+    /// the host key, so the signer is able to derive the private key.
+    pub fn prepare_commit<MSG>(&self, msg: &MSG) -> Result<PreparedTweak, Error>
+    where
+        MSG: AsRef<[u8]>,
+    {
+        Ok(self.to_lnpbp1_container()?.prepare_commit(msg)?)
+    }
+
+    /// Exports raw scalar tweak for the hardware signers which can only add
+    /// a scalar to the derived private key
+    #[inline]
+    pub fn scalar_tweak<MSG>(&self, msg: &MSG) -> Result<[u8; 32], Error>
+    where
+        MSG: AsRef<[u8]>,
+    {
+        Ok(self.prepare_commit(msg)?.tweak)
+    }
+
+    /// Re-derives private key for the host key from the extended private key
+    /// corresponding to [HdContainer::xpub] and applies the commitment tweak,
+    /// producing the key able to spend the tweaked output
+    pub fn tweaked_seckey<MSG>(
+        &self,
+        xpriv: &bip32::ExtendedPrivKey,
+        msg: &MSG,
+    ) -> Result<secp256k1::SecretKey, Error>
+    where
+        MSG: AsRef<[u8]>,
+    {
+        let ec = Secp256k1::<secp256k1::SignOnly>::signing_only();
+        let mut seckey = xpriv.derive_priv(&ec, &self.derivation)?.private_key.key;
+        self.prepare_commit(msg)?.apply(&mut seckey)?;
+        Ok(seckey)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::strict_encoding::{strict_decode, strict_encode};
+    use bitcoin::hashes::Hash;
+    use bitcoin::Network;
+    use std::str::FromStr;
+
+    fn xpriv() -> bip32::ExtendedPrivKey {
+        bip32::ExtendedPrivKey::new_master(Network::Testnet, &[7u8; 32]).unwrap()
+    }
+
+    fn container(derivation: &str) -> HdContainer {
+        HdContainer {
+            xpub: bip32::ExtendedPubKey::from_private(&Secp256k1::new(), &xpriv()),
+            xpub_origin: None,
+            derivation: bip32::DerivationPath::from_str(derivation).unwrap(),
+            tag: sha256::Hash::hash(b"TEST_TAG"),
+        }
+    }
+
+    #[test]
+    fn test_hd_commitment() {
+        let ec = Secp256k1::<secp256k1::SignOnly>::signing_only();
+        let msg = b"message".to_vec();
+        let container = container("m/0/5");
+        let commitment = container.commit(&msg).unwrap();
+        assert_ne!(*commitment, container.pubkey().unwrap());
+
+        let lnpbp1 = container.to_lnpbp1_container().unwrap();
+        assert!(commitment.verify(&lnpbp1, &msg).unwrap().is_valid());
+        assert_eq!(lnpbp1.key_origin, Some(container.key_origin()));
+
+        let seckey = container.tweaked_seckey(&xpriv(), &msg).unwrap();
+        assert_eq!(
+            secp256k1::PublicKey::from_secret_key(&ec, &seckey),
+            *commitment
+        );
+
+        let mut derived = xpriv()
+            .derive_priv(&ec, &container.derivation)
+            .unwrap()
+            .private_key
+            .key;
+        derived
+            .add_assign(&container.scalar_tweak(&msg).unwrap())
+            .unwrap();
+        assert_eq!(derived, seckey);
+
+        let decoded: HdContainer = strict_decode(&strict_encode(&container).unwrap()).unwrap();
+        assert_eq!(decoded, container);
+    }
+
+    #[test]
+    fn test_key_origin() {
+        let mut hd = container("m/1/2");
+        assert_eq!(hd.key_origin().fingerprint, hd.xpub.fingerprint());
+
+        let fingerprint = bip32::Fingerprint::from(&[1u8, 2, 3, 4][..]);
+        hd.xpub_origin = Some(KeyOrigin {
+            fingerprint,
+            derivation: bip32::DerivationPath::from_str("m/84'/0'/0'").unwrap(),
+        });
+        assert_eq!(
+            hd.key_origin(),
+            KeyOrigin {
+                fingerprint,
+                derivation: bip32::DerivationPath::from_str("m/84'/0'/0'/1/2").unwrap(),
+            }
+        );
+
+        assert!(container("m/0'").pubkey().is_err());
+    }
+}
@@ -102,51 +102,62 @@ where
     fn embed_commit(keyset_container: &Self::Container, msg: &MSG) -> Result<Self, Self::Error> {
         let ec = Secp256k1::<secp256k1::All>::new();
 
-        // ! [CONSENSUS-CRITICAL]:
-        // ! [STANDARD-CRITICAL]: We commit to the sum of all public keys,
-        //                        not a single pubkey
-        let pubkey_sum = keyset_container
-            .keyset
-            .iter()
-            .try_fold(keyset_container.pubkey, |sum, pubkey| sum.combine(pubkey))?;
-
-        // ! [CONSENSUS-CRITICAL]:
-        // ! [STANDARD-CRITICAL]: HMAC engine is based on sha256 hash
-        let mut hmac_engine = HmacEngine::<sha256::Hash>::new(&pubkey_sum.serialize());
-
-        // ! [CONSENSUS-CRITICAL]:
-        // ! [STANDARD-CRITICAL]: Hash process started with consuming first
-        //                        protocol prefix: single SHA256 hash of
-        //                        ASCII "LNPBP-1" string.
-        // NB: We use the same hash as in LNPBP-1 so when there is no other
-        //     keys involved the commitment would not differ.
-        hmac_engine.input(&SHA256_LNPBP1);
-
-        // ! [CONSENSUS-CRITICAL]:
-        // ! [STANDARD-CRITICAL]: The second prefix comes from the upstream
-        //                        protocol as a part of the container
-        hmac_engine.input(&keyset_container.tag[..]);
-
-        // ! [CONSENSUS-CRITICAL]:
-        // ! [STANDARD-CRITICAL]: Next we hash the message. The message must be
-        //                        prefixed with the protocol-specific prefix:
-        //                        another single SHA256 hash of protocol name.
-        //                        However this is not the part of this function,
-        //                        the function expect that the `msg` is already
-        //                        properly prefixed
-        hmac_engine.input(msg.as_ref());
-
         // Producing tweaking factor
-        let factor = &Hmac::from_engine(hmac_engine)[..];
+        let factor = keyset_tweaking_factor(keyset_container, msg.as_ref())?;
         // Applying tweaking factor to public key
         let mut tweaked_pubkey = keyset_container.pubkey.clone();
-        tweaked_pubkey.add_exp_assign(&ec, factor)?;
+        tweaked_pubkey.add_exp_assign(&ec, &factor[..])?;
 
         // Returning tweaked public key
         Ok(LNPBP2Commitment(tweaked_pubkey))
     }
 }
 
+/// Computes LNPBP-2 tweaking factor for the keyset container and the message:
+/// `HMAC_SHA256(SHA256("LNPBP1") || tag || msg, P + sum(keyset))`
+// #[consensus_critical]
+// #[standard_critical("LNPBP-2")]
+pub(super) fn keyset_tweaking_factor(
+    keyset_container: &KeysetContainer,
+    msg: &[u8],
+) -> Result<Hmac<sha256::Hash>, secp256k1::Error> {
+    // ! [CONSENSUS-CRITICAL]:
+    // ! [STANDARD-CRITICAL]: We commit to the sum of all public keys,
+    //                        not a single pubkey
+    let pubkey_sum = keyset_container
+        .keyset
+        .iter()
+        .try_fold(keyset_container.pubkey, |sum, pubkey| sum.combine(pubkey))?;
+
+    // ! [CONSENSUS-CRITICAL]:
+    // ! [STANDARD-CRITICAL]: HMAC engine is based on sha256 hash
+    let mut hmac_engine = HmacEngine::<sha256::Hash>::new(&pubkey_sum.serialize());
+
+    // ! [CONSENSUS-CRITICAL]:
+    // ! [STANDARD-CRITICAL]: Hash process started with consuming first
+    //                        protocol prefix: single SHA256 hash of
+    //                        ASCII "LNPBP-1" string.
+    // NB: We use the same hash as in LNPBP-1 so when there is no other
+    //     keys involved the commitment would not differ.
+    hmac_engine.input(&SHA256_LNPBP1);
+
+    // ! [CONSENSUS-CRITICAL]:
+    // ! [STANDARD-CRITICAL]: The second prefix comes from the upstream
+    //                        protocol as a part of the container
+    hmac_engine.input(&keyset_container.tag[..]);
+
+    // ! [CONSENSUS-CRITICAL]:
+    // ! [STANDARD-CRITICAL]: Next we hash the message. The message must be
+    //                        prefixed with the protocol-specific prefix:
+    //                        another single SHA256 hash of protocol name.
+    //                        However this is not the part of this function,
+    //                        the function expect that the `msg` is already
+    //                        properly prefixed
+    hmac_engine.input(msg);
+
+    Ok(Hmac::from_engine(hmac_engine))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -163,8 +174,15 @@ mod test {
         let tag = sha256::Hash::hash(b"TEST_TAG2");
         let msg = "test message";
         gen_secp_pubkeys(9).into_iter().for_each(|pubkey| {
-            let lnpbp1_commitment =
-                LNPBP1Commitment::embed_commit(&LNPBP1Container { pubkey, tag }, &msg).unwrap();
+            let lnpbp1_commitment = LNPBP1Commitment::embed_commit(
+                &LNPBP1Container {
+                    pubkey,
+                    tag,
+                    key_origin: None,
+                },
+                &msg,
+            )
+            .unwrap();
             let lnpbp2_commitment = LNPBP2Commitment::embed_commit(
                 &KeysetContainer {
                     pubkey,
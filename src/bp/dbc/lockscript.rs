@@ -70,6 +70,7 @@ impl Container for LockscriptContainer {
             Proof {
                 script_info: ScriptInfo::LockScript(self.script),
                 pubkey: self.pubkey,
+                composition: None,
             },
             self.tag,
         )
@@ -80,6 +81,7 @@ impl Container for LockscriptContainer {
         Proof {
             script_info: ScriptInfo::LockScript(self.script.clone()),
             pubkey: self.pubkey.clone(),
+            composition: None,
         }
     }
 
@@ -88,6 +90,7 @@ impl Container for LockscriptContainer {
         Proof {
             script_info: ScriptInfo::LockScript(self.script),
             pubkey: self.pubkey,
+            composition: None,
         }
     }
 }
@@ -137,38 +140,70 @@ where
     // #[consensus_critical]
     // #[standard_critical("LNPBP-1")]
     fn embed_commit(container: &Self::Container, msg: &MSG) -> Result<Self, Self::Error> {
-        let original_hash = bitcoin::PublicKey {
-            compressed: true,
-            key: container.pubkey,
-        }
-        .pubkey_hash();
+        let original_hash = pubkey_hash(container.pubkey, true);
+        let original_hash_uncompressed = pubkey_hash(container.pubkey, false);
 
+        let ast = LockscriptAst::parse(&*container.script)
+            .map_err(|err| Error::LockscriptParseError(err.into()))?;
         let (keys, hashes) = container.script.extract_pubkey_hash_set()?;
         if keys.is_empty() && hashes.is_empty() {
             Err(Error::LockscriptContainsNoKeys)?;
         }
 
+        // ! [CONSENSUS-CRITICAL]:
+        // ! [STANDARD-CRITICAL]: Compressed and uncompressed versions of the
+        //                        same public key must be treated as the same
+        //                        public key, so we resolve hashes against
+        //                        both of the encodings
         let mut key_hashes: HashSet<PubkeyHash> = keys
             .iter()
-            .map(|pk| {
-                bitcoin::PublicKey {
-                    key: pk.clone(),
-                    compressed: true,
-                }
-                .pubkey_hash()
-            })
+            .flat_map(|pk| vec![pubkey_hash(*pk, true), pubkey_hash(*pk, false)])
             .collect();
         key_hashes.insert(original_hash);
+        key_hashes.insert(original_hash_uncompressed);
 
         if hashes.is_empty() {
-            keys.get(&container.pubkey)
-                .ok_or(Error::LockscriptKeyNotFound)?;
-        } else if hashes
+            if !keys.contains(&container.pubkey) {
+                Err(Error::LockscriptKeyNotFound {
+                    pubkey: container.pubkey,
+                    keys_found: keys.len(),
+                    uncompressed_found: ast.keys().into_iter().any(|item| match item {
+                        ScriptKey::Pubkey(pubkey) => !pubkey.compressed,
+                        ScriptKey::PubkeyHash(_) => false,
+                    }),
+                })?
+            }
+        } else {
+            let mut unknown = hashes
+                .into_iter()
+                .filter(|hash| !key_hashes.contains(hash))
+                .collect::<Vec<_>>();
+            if !unknown.is_empty() {
+                unknown.sort();
+                Err(Error::LockscriptContainsUnknownHashes {
+                    hashes: unknown,
+                    keys_found: keys.len(),
+                })?
+            }
+        }
+
+        // The original key must use the same encoding in all of its
+        // occurrences, otherwise the resulting script will require
+        // signatures under both of the encodings of the tweaked key
+        let encodings = ast
+            .keys()
             .into_iter()
-            .find(|hash| !key_hashes.contains(hash))
-            .is_some()
-        {
-            Err(Error::LockscriptContainsUnknownHashes)?;
+            .filter_map(|item| match item {
+                ScriptKey::Pubkey(pubkey) if pubkey.key == container.pubkey => {
+                    Some(pubkey.compressed)
+                }
+                ScriptKey::PubkeyHash(hash) if hash == original_hash => Some(true),
+                ScriptKey::PubkeyHash(hash) if hash == original_hash_uncompressed => Some(false),
+                _ => None,
+            })
+            .collect::<HashSet<bool>>();
+        if encodings.len() > 1 {
+            Err(Error::LockscriptMixedKeyEncodings(container.pubkey))?;
         }
 
         let tweaked_pubkey = LNPBP2Commitment::embed_commit(
@@ -179,11 +214,8 @@ where
             },
             msg,
         )?;
-        let tweaked_hash = bitcoin::PublicKey {
-            key: *tweaked_pubkey,
-            compressed: true,
-        }
-        .pubkey_hash();
+        let tweaked_hash = pubkey_hash(*tweaked_pubkey, true);
+        let tweaked_hash_uncompressed = pubkey_hash(*tweaked_pubkey, false);
 
         let found = RefCell::new(0);
 
@@ -191,6 +223,8 @@ where
         // ! [STANDARD-CRITICAL]: Iterate over all branches of the abstract
         //                        syntax tree generated by the Miniscript parser,
         //                        running the following algorithm for each node:
+        //                        tweaked keys and key hashes preserve the
+        //                        encoding of the original key
         let lockscript = container.script.replace_pubkeys_and_hashes(
             |pubkey: secp256k1::PublicKey| match pubkey == container.pubkey {
                 true => {
@@ -199,12 +233,16 @@ where
                 }
                 false => Some(pubkey),
             },
-            |hash: PubkeyHash| match hash == original_hash {
-                true => {
+            |hash: PubkeyHash| {
+                if hash == original_hash {
                     *found.borrow_mut() += 1;
                     Some(tweaked_hash)
+                } else if hash == original_hash_uncompressed {
+                    *found.borrow_mut() += 1;
+                    Some(tweaked_hash_uncompressed)
+                } else {
+                    Some(hash)
                 }
-                false => Some(hash),
             },
         )?;
 
@@ -212,6 +250,12 @@ where
     }
 }
 
+/// Computes hash of the public key serialized in compressed or uncompressed
+/// form
+pub(super) fn pubkey_hash(key: secp256k1::PublicKey, compressed: bool) -> PubkeyHash {
+    bitcoin::PublicKey { compressed, key }.pubkey_hash()
+}
+
 #[cfg(test)]
 mod test {
     use bitcoin::hashes::{hash160, sha256, Hash};
@@ -306,12 +350,13 @@ mod test {
             ms_str!("c:pk_k({})", keys[2]),
             ms_str!("c:pk_k({})", keys[3]),
             ms_str!("c:pk_k({})", keys[4]),
-            //ms_str!("c:pk({})", uncompressed),
+            ms_str!("c:pk_k({})", uncompressed),
         ];
 
         ms.into_iter()
             .map(|ms: Miniscript<bitcoin::PublicKey>| LockScript::from(ms.encode()))
-            .for_each(|ls| {
+            .enumerate()
+            .for_each(|(idx, ls)| {
                 assert_eq!(
                     LockscriptCommitment::embed_commit(
                         &LockscriptContainer {
@@ -322,7 +367,11 @@ mod test {
                         &"Test message"
                     )
                     .err(),
-                    Some(Error::LockscriptKeyNotFound)
+                    Some(Error::LockscriptKeyNotFound {
+                        pubkey: keys[0].key,
+                        keys_found: 1,
+                        uncompressed_found: idx == 4,
+                    })
                 );
             });
     }
@@ -342,7 +391,8 @@ mod test {
 
         ms.into_iter()
             .map(|ms: Miniscript<bitcoin::PublicKey>| LockScript::from(ms.encode()))
-            .for_each(|ls| {
+            .enumerate()
+            .for_each(|(idx, ls)| {
                 assert_eq!(
                     LockscriptCommitment::embed_commit(
                         &LockscriptContainer {
@@ -353,7 +403,10 @@ mod test {
                         &"Test message"
                     )
                     .err(),
-                    Some(Error::LockscriptContainsUnknownHashes)
+                    Some(Error::LockscriptContainsUnknownHashes {
+                        hashes: vec![key_hashes[idx + 1]],
+                        keys_found: 0,
+                    })
                 );
             });
     }
@@ -386,7 +439,7 @@ mod test {
                 let msg = "Test message";
                 let commitment =
                     LockscriptCommitment::embed_commit(&container.clone(), &msg).unwrap();
-                assert!(commitment.verify(&container, &msg).unwrap());
+                assert!(commitment.verify(&container, &msg).unwrap().is_valid());
             });
     }
 
@@ -415,10 +468,63 @@ mod test {
                 let msg = "Test message";
                 let commitment =
                     LockscriptCommitment::embed_commit(&container.clone(), &msg).unwrap();
-                assert!(commitment.verify(&container, &msg).unwrap())
+                assert!(commitment.verify(&container, &msg).unwrap().is_valid())
             });
     }
 
+    #[test]
+    fn test_uncompressed_key() {
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let (keys, _, _) = gen_test_data();
+        let mut uncompressed = keys[0];
+        uncompressed.compressed = false;
+        let msg = "Test message";
+
+        let ms = vec![
+            ms_str!("c:pk_k({})", uncompressed),
+            ms_str!("c:pk_h({})", uncompressed.pubkey_hash()),
+            ms_str!(
+                "or_d(c:pk_k({}),c:pk_h({}))",
+                uncompressed,
+                uncompressed.pubkey_hash()
+            ),
+        ];
+        ms.into_iter()
+            .map(|ms: Miniscript<bitcoin::PublicKey>| LockScript::from(ms.encode()))
+            .for_each(|ls| {
+                let container = LockscriptContainer {
+                    script: ls,
+                    pubkey: keys[0].key,
+                    tag,
+                };
+                let commitment = LockscriptCommitment::embed_commit(&container, &msg).unwrap();
+                assert!(commitment.verify(&container, &msg).unwrap().is_valid());
+                assert_ne!(commitment.as_bytes(), container.script.as_bytes());
+                // Tweaked key keeps uncompressed serialization
+                assert_eq!(
+                    commitment.as_bytes().len(),
+                    container.script.as_bytes().len()
+                );
+            });
+
+        let container = LockscriptContainer {
+            script: LockScript::from(
+                ms_str!(
+                    "or_d(c:pk_k({}),c:pk_h({}))",
+                    keys[0],
+                    uncompressed.pubkey_hash()
+                )
+                .encode(),
+            ),
+            pubkey: keys[0].key,
+            tag,
+        };
+        assert_eq!(
+            LockscriptCommitment::embed_commit(&container, &msg).err(),
+            Some(Error::LockscriptMixedKeyEncodings(keys[0].key))
+        );
+    }
+
     #[test]
     fn test_multisig() {
         let tag = sha256::Hash::hash(b"TEST_TAG");
@@ -452,7 +558,7 @@ mod test {
                 let msg = "Test message";
                 let commitment =
                     LockscriptCommitment::embed_commit(&container.clone(), &msg).unwrap();
-                assert!(commitment.verify(&container, &msg).unwrap())
+                assert!(commitment.verify(&container, &msg).unwrap().is_valid())
             });
     }
 
@@ -480,6 +586,43 @@ mod test {
         };
         let msg = "Test message";
         let commitment = LockscriptCommitment::embed_commit(&container.clone(), &msg).unwrap();
-        assert!(commitment.verify(&container, &msg).unwrap())
+        assert!(commitment.verify(&container, &msg).unwrap().is_valid())
+    }
+
+    #[test]
+    fn test_strict_encoding() {
+        use crate::strict_encoding::{strict_decode, strict_encode};
+        use miniscript::Descriptor;
+
+        let keys = pubkeys(3);
+        let ms = ms_str!("multi(2,{},{},{})", keys[0], keys[1], keys[2]);
+        let data = strict_encode(&ms).unwrap();
+        assert_eq!(
+            strict_decode::<Miniscript<bitcoin::PublicKey>>(&data).unwrap(),
+            ms
+        );
+        assert_eq!(&data[2..], ms.encode().as_bytes());
+        assert!(strict_decode::<Miniscript<bitcoin::PublicKey>>(&[1u8, 0, 0x6a]).is_err());
+
+        let descriptor = Descriptor::Wsh(ms.clone());
+        let data = strict_encode(&descriptor).unwrap();
+        assert_eq!(
+            strict_decode::<Descriptor<bitcoin::PublicKey>>(&data).unwrap(),
+            descriptor
+        );
+        assert!(strict_decode::<Descriptor<bitcoin::PublicKey>>(
+            &strict_encode(&"wsh(pk())".to_string()).unwrap()
+        )
+        .is_err());
+
+        let container = LockscriptContainer {
+            script: LockScript::from(ms.encode()),
+            pubkey: keys[0].key,
+            tag: sha256::Hash::hash(b"TEST_TAG"),
+        };
+        assert_eq!(
+            strict_decode::<LockscriptContainer>(&strict_encode(&container).unwrap()).unwrap(),
+            container
+        );
     }
 }
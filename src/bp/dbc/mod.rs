@@ -11,23 +11,51 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+mod address;
+mod batch;
 pub mod digests;
 mod error;
+mod hd;
 mod keyset;
 mod lockscript;
+mod plan;
 mod pubkey;
+mod reveal;
+mod scripthash;
 mod scriptpubkey;
+mod signature;
 mod taproot;
+mod tapscript;
+mod taptree;
+mod threshold;
+mod tweak;
 mod tx;
 mod txout;
 mod types;
 
+pub use address::{ProofAddress, ProofAddressError, PROOF_ADDRESS_SEPARATOR};
+pub use batch::BatchProof;
 pub use error::Error;
+pub use hd::HdContainer;
 pub use keyset::{KeysetContainer, LNPBP2Commitment};
 pub use lockscript::{LockscriptCommitment, LockscriptContainer};
+pub use plan::{ChangeKind, DbcPlan, PlanCommit, PlannedChange};
 pub use pubkey::{LNPBP1Commitment, LNPBP1Container};
+pub use reveal::{CommitmentReveal, CommitmentRevealError, COMMITMENT_REVEAL_HRP};
+pub use scripthash::{
+    RedeemScriptCommitment, RedeemScriptContainer, WPubkeyHashCommitment, WPubkeyHashContainer,
+    WitnessScriptCommitment, WitnessScriptContainer,
+};
 pub use scriptpubkey::{ScriptPubkeyCommitment, ScriptPubkeyComposition, ScriptPubkeyContainer};
-pub use taproot::{TaprootCommitment, TaprootContainer};
-pub use tx::{compute_lnpbp3_vout, TxCommitment, TxContainer, TxSupplement};
+pub use signature::{SignatureCommitment, SignatureContainer};
+pub use taproot::{
+    taproot_output_key, tapscript_root, TapBranchHash, TapLeafHash, TapTweakHash,
+    TaprootCommitment, TaprootContainer, TAPSCRIPT_LEAF_VERSION,
+};
+pub use tapscript::{lift_x, xonly, xonly_hash, TapscriptCommitment, TapscriptContainer};
+pub use taptree::{TapScriptLeaf, TaprootScriptTree, TaptreeError, TAPROOT_MAX_DEPTH};
+pub use threshold::ThresholdTweak;
+pub use tweak::{LockscriptTweak, PreparedTweak, TweakedPosition};
+pub use tx::{compute_lnpbp3_vout, lnpbp3_vout, TxCommitment, TxContainer, TxSupplement};
 pub use txout::{TxoutCommitment, TxoutContainer};
-pub use types::{Container, Proof, ScriptInfo};
+pub use types::{Container, KeyOrigin, Proof, ScriptInfo};
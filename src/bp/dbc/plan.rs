@@ -0,0 +1,314 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Planning of deterministic bitcoin commitments: reports which keys and
+//! hashes will be replaced in the container, resulting `scriptPubkey` and
+//! output type, without returning the commitment itself. Used by user
+//! interfaces to ask for a confirmation before the commitment is embedded.
+
+use bitcoin::blockdata::script::{Instruction, Script};
+use bitcoin::secp256k1::{self, Secp256k1};
+
+use super::{
+    compute_lnpbp3_vout, taproot_output_key, Error, LNPBP1Commitment, LNPBP1Container,
+    LockscriptCommitment, LockscriptContainer, ScriptInfo, ScriptPubkeyCommitment,
+    ScriptPubkeyComposition, ScriptPubkeyContainer, TaprootCommitment, TaprootContainer,
+    TxContainer, TxSupplement, TxoutContainer,
+};
+use crate::bp::PubkeyScript;
+use crate::commit_verify::EmbedCommitVerify;
+
+/// Kind of the data replaced by the commitment
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum ChangeKind {
+    /// Public key tweaked according to LNPBP-1 or LNPBP-2
+    Pubkey,
+    /// Hash of the public key replaced with the hash of the tweaked key
+    PubkeyHash,
+    /// Taproot (BIP-341) x-only output key
+    OutputKey,
+}
+
+/// Single replacement of a key or hash performed by the commitment
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct PlannedChange {
+    pub kind: ChangeKind,
+    /// Position of the replaced data: number of the script instruction for
+    /// lockscripts, zero for the host public key and one for taproot output
+    /// key
+    pub position: usize,
+    /// Serialized original data
+    pub original: Vec<u8>,
+    /// Serialized data after the commitment
+    pub tweaked: Vec<u8>,
+}
+
+/// Report on the changes which will be done by the commitment
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display, Default)]
+#[display_from(Debug)]
+pub struct DbcPlan {
+    pub changes: Vec<PlannedChange>,
+    /// Type of the output, if the container defines it
+    pub composition: Option<ScriptPubkeyComposition>,
+    /// Resulting `scriptPubkey`, if the container defines it
+    pub script_pubkey: Option<PubkeyScript>,
+    /// Number of the transaction output hosting the commitment, if the
+    /// container is a transaction
+    pub vout: Option<usize>,
+}
+
+/// Containers which commitment may be planned before it is embedded
+pub trait PlanCommit<MSG>
+where
+    MSG: AsRef<[u8]>,
+{
+    /// Reports changes which will be done by embedding commitment to the
+    /// message into the container; the container itself is not modified
+    fn plan_commit(&self, msg: &MSG) -> Result<DbcPlan, Error>;
+}
+
+impl<MSG> PlanCommit<MSG> for LNPBP1Container
+where
+    MSG: AsRef<[u8]>,
+{
+    fn plan_commit(&self, msg: &MSG) -> Result<DbcPlan, Error> {
+        let tweaked = LNPBP1Commitment::embed_commit(self, msg)?;
+        Ok(DbcPlan {
+            changes: vec![PlannedChange {
+                kind: ChangeKind::Pubkey,
+                position: 0,
+                original: self.pubkey.serialize().to_vec(),
+                tweaked: tweaked.serialize().to_vec(),
+            }],
+            ..DbcPlan::default()
+        })
+    }
+}
+
+impl<MSG> PlanCommit<MSG> for LockscriptContainer
+where
+    MSG: AsRef<[u8]>,
+{
+    fn plan_commit(&self, msg: &MSG) -> Result<DbcPlan, Error> {
+        let tweaked = LockscriptCommitment::embed_commit(self, msg)?;
+        let original: &Script = &*self.script;
+        let tweaked: &Script = &**tweaked;
+        let changes = original
+            .iter(false)
+            .zip(tweaked.iter(false))
+            .enumerate()
+            .filter_map(|(position, instructions)| match instructions {
+                (Instruction::PushBytes(original), Instruction::PushBytes(tweaked))
+                    if original != tweaked =>
+                {
+                    Some(PlannedChange {
+                        kind: if original.len() == 20 {
+                            ChangeKind::PubkeyHash
+                        } else {
+                            ChangeKind::Pubkey
+                        },
+                        position,
+                        original: original.to_vec(),
+                        tweaked: tweaked.to_vec(),
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+        Ok(DbcPlan {
+            changes,
+            ..DbcPlan::default()
+        })
+    }
+}
+
+impl<MSG> PlanCommit<MSG> for TaprootContainer
+where
+    MSG: AsRef<[u8]>,
+{
+    fn plan_commit(&self, msg: &MSG) -> Result<DbcPlan, Error> {
+        let ec = Secp256k1::<secp256k1::VerifyOnly>::verification_only();
+        let commitment = TaprootCommitment::embed_commit(self, msg)?;
+        let original_output_key =
+            taproot_output_key(&ec, &self.intermediate_key, Some(&self.script_root))?;
+        Ok(DbcPlan {
+            changes: vec![
+                PlannedChange {
+                    kind: ChangeKind::Pubkey,
+                    position: 0,
+                    original: self.intermediate_key.serialize().to_vec(),
+                    tweaked: commitment.intermediate_key_commitment.serialize().to_vec(),
+                },
+                PlannedChange {
+                    kind: ChangeKind::OutputKey,
+                    position: 1,
+                    original: original_output_key.serialize()[1..].to_vec(),
+                    tweaked: commitment.output_key_x().to_vec(),
+                },
+            ],
+            composition: Some(ScriptPubkeyComposition::TapRoot),
+            script_pubkey: Some(commitment.script_pubkey().into()),
+            vout: None,
+        })
+    }
+}
+
+impl<MSG> PlanCommit<MSG> for ScriptPubkeyContainer
+where
+    MSG: AsRef<[u8]>,
+{
+    fn plan_commit(&self, msg: &MSG) -> Result<DbcPlan, Error> {
+        let script_pubkey = ScriptPubkeyCommitment::embed_commit(self, msg)?.into_inner();
+        let plan = match self.script_info {
+            ScriptInfo::None => LNPBP1Container {
+                pubkey: self.pubkey,
+                tag: self.tag,
+                key_origin: self.key_origin.clone(),
+            }
+            .plan_commit(msg)?,
+            ScriptInfo::LockScript(ref script) => LockscriptContainer {
+                script: script.clone(),
+                pubkey: self.pubkey,
+                tag: self.tag,
+            }
+            .plan_commit(msg)?,
+            ScriptInfo::Taproot(script_root) => TaprootContainer {
+                script_root,
+                intermediate_key: self.pubkey,
+                tag: self.tag,
+            }
+            .plan_commit(msg)?,
+        };
+        Ok(DbcPlan {
+            composition: Some(self.scriptpubkey_composition.clone()),
+            script_pubkey: Some(script_pubkey),
+            ..plan
+        })
+    }
+}
+
+impl<MSG> PlanCommit<MSG> for TxoutContainer
+where
+    MSG: AsRef<[u8]>,
+{
+    #[inline]
+    fn plan_commit(&self, msg: &MSG) -> Result<DbcPlan, Error> {
+        self.script_container.plan_commit(msg)
+    }
+}
+
+impl<MSG> PlanCommit<MSG> for TxContainer
+where
+    MSG: AsRef<[u8]>,
+{
+    fn plan_commit(&self, msg: &MSG) -> Result<DbcPlan, Error> {
+        let vout = compute_lnpbp3_vout(
+            &self.tx,
+            &TxSupplement {
+                protocol_factor: self.protocol_factor,
+                fee: self.fee,
+                tag: self.txout_container.script_container.tag,
+            },
+        )
+        .ok_or(Error::TxHasNoOutputs)?;
+        Ok(DbcPlan {
+            vout: Some(vout),
+            ..self.txout_container.plan_commit(msg)?
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::dbc::TAPSCRIPT_LEAF_VERSION;
+    use crate::bp::test::*;
+    use crate::bp::LockScript;
+    use bitcoin::blockdata::opcodes::all::*;
+    use bitcoin::blockdata::script::Builder;
+    use bitcoin::hashes::{sha256, Hash};
+
+    #[test]
+    fn test_plan_pubkey() {
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let msg = b"message".to_vec();
+        for pubkey in gen_secp_pubkeys(9) {
+            let container = ScriptPubkeyContainer::construct(
+                &tag,
+                pubkey,
+                ScriptInfo::None,
+                ScriptPubkeyComposition::WPubkeyHash,
+            );
+            let plan = container.plan_commit(&msg).unwrap();
+            assert_eq!(plan, container.plan_commit(&msg).unwrap());
+            assert_eq!(plan.changes.len(), 1);
+            assert_eq!(plan.changes[0].kind, ChangeKind::Pubkey);
+            assert_eq!(plan.changes[0].original, pubkey.serialize().to_vec());
+            assert_eq!(plan.composition, Some(ScriptPubkeyComposition::WPubkeyHash));
+            assert_eq!(
+                plan.script_pubkey,
+                Some(
+                    ScriptPubkeyCommitment::embed_commit(&container, &msg)
+                        .unwrap()
+                        .into_inner()
+                )
+            );
+            assert_eq!(plan.vout, None);
+        }
+    }
+
+    #[test]
+    fn test_plan_lockscript() {
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let keys = gen_secp_pubkeys(2);
+        let script = Builder::new()
+            .push_opcode(OP_PUSHNUM_1)
+            .push_slice(&keys[0].serialize())
+            .push_slice(&keys[1].serialize())
+            .push_opcode(OP_PUSHNUM_2)
+            .push_opcode(OP_CHECKMULTISIG)
+            .into_script();
+        let container = LockscriptContainer {
+            script: LockScript::from(script),
+            pubkey: keys[1],
+            tag,
+        };
+        let plan = container.plan_commit(&b"message".to_vec()).unwrap();
+        assert_eq!(plan.changes.len(), 1);
+        assert_eq!(plan.changes[0].kind, ChangeKind::Pubkey);
+        assert_eq!(plan.changes[0].position, 2);
+        assert_eq!(plan.changes[0].original, keys[1].serialize().to_vec());
+        assert_eq!(plan.script_pubkey, None);
+    }
+
+    #[test]
+    fn test_plan_taproot() {
+        let container = TaprootContainer::with_script_path(
+            gen_secp_pubkeys(1)[0],
+            &Script::from(vec![0x51]),
+            TAPSCRIPT_LEAF_VERSION,
+            &[],
+            sha256::Hash::hash(b"TEST_TAG"),
+        );
+        let plan = container.plan_commit(&b"message".to_vec()).unwrap();
+        assert_eq!(
+            plan.changes.iter().map(|c| c.kind).collect::<Vec<_>>(),
+            vec![ChangeKind::Pubkey, ChangeKind::OutputKey]
+        );
+        assert_eq!(plan.changes[1].tweaked.len(), 32);
+        assert_eq!(plan.composition, Some(ScriptPubkeyComposition::TapRoot));
+        assert_eq!(plan.script_pubkey.unwrap().len(), 34);
+    }
+}
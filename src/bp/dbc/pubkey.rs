@@ -28,8 +28,8 @@
 use bitcoin::hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
 use bitcoin::secp256k1::{self, Secp256k1};
 
-use super::{Container, Error, Proof};
-use crate::commit_verify::EmbedCommitVerify;
+use super::{Container, Error, KeyOrigin, Proof};
+use crate::commit_verify::{EmbedCommitVerify, Verification};
 
 /// Single SHA256 hash of "LNPBP1" string according to LNPBP-1 acting as a
 /// prefix to the message in computing tweaking factor
@@ -49,6 +49,23 @@ pub struct LNPBP1Container {
     pub pubkey: secp256k1::PublicKey,
     /// Single SHA256 hash of the protocol-specific tag
     pub tag: sha256::Hash,
+    /// BIP-32 origin of the original public key, if known
+    pub key_origin: Option<KeyOrigin>,
+}
+
+impl LNPBP1Container {
+    /// Constructs container for the protocol with the given name (like
+    /// "RGB" or "Storm"), which is hashed into the protocol-specific tag,
+    /// so commitments made by different protocols under the same public
+    /// key can't be confused
+    #[inline]
+    pub fn with_protocol(pubkey: secp256k1::PublicKey, protocol: &str) -> Self {
+        Self {
+            pubkey,
+            tag: sha256::Hash::hash(protocol.as_bytes()),
+            key_origin: None,
+        }
+    }
 }
 
 impl Container for LNPBP1Container {
@@ -65,6 +82,7 @@ impl Container for LNPBP1Container {
         Ok(Self {
             pubkey: proof.pubkey,
             tag: supplement.clone(),
+            key_origin: None,
         })
     }
 
@@ -140,41 +158,106 @@ where
     fn embed_commit(pubkey_container: &Self::Container, msg: &MSG) -> Result<Self, Self::Error> {
         let ec = Secp256k1::<secp256k1::All>::new();
 
-        // ! [CONSENSUS-CRITICAL]:
-        // ! [STANDARD-CRITICAL]: HMAC engine is based on sha256 hash
-        let mut hmac_engine = HmacEngine::<sha256::Hash>::new(&pubkey_container.pubkey.serialize());
-
-        // ! [CONSENSUS-CRITICAL]:
-        // ! [STANDARD-CRITICAL]: Hash process started with consuming first
-        //                        protocol prefix: single SHA256 hash of
-        //                        ASCII "LNPBP-1" string
-        hmac_engine.input(&SHA256_LNPBP1);
-
-        // ! [CONSENSUS-CRITICAL]:
-        // ! [STANDARD-CRITICAL]: The second prefix comes from the upstream
-        //                        protocol as a part of the container
-        hmac_engine.input(&pubkey_container.tag[..]);
-
-        // ! [CONSENSUS-CRITICAL]:
-        // ! [STANDARD-CRITICAL]: Next we hash the message. The message must be
-        //                        prefixed with the protocol-specific prefix:
-        //                        another single SHA256 hash of protocol name.
-        //                        However this is not the part of this function,
-        //                        the function expect that the `msg` is already
-        //                        properly prefixed
-        hmac_engine.input(msg.as_ref());
-
         // Producing tweaking factor
-        let factor = &Hmac::from_engine(hmac_engine)[..];
+        let factor = tweaking_factor(
+            &pubkey_container.pubkey,
+            &pubkey_container.tag,
+            msg.as_ref(),
+        );
         // Applying tweaking factor to public key
         let mut tweaked_pubkey = pubkey_container.pubkey.clone();
-        tweaked_pubkey.add_exp_assign(&ec, factor)?;
+        tweaked_pubkey.add_exp_assign(&ec, &factor[..])?;
 
         // Returning tweaked public key
         Ok(LNPBP1Commitment(tweaked_pubkey))
     }
 }
 
+impl LNPBP1Commitment {
+    /// Makes sequential commitments of multiple protocols to the same original
+    /// public key. Each commitment is given by a hashed protocol tag and the
+    /// message; the key tweaked by each commitment acts as the original key
+    /// for the next one. Returns all of the intermediary tweaked keys, the
+    /// last of which is the final commitment.
+    pub fn embed_sequence<MSG>(
+        pubkey: secp256k1::PublicKey,
+        commitments: &[(sha256::Hash, MSG)],
+    ) -> Result<Vec<LNPBP1Commitment>, secp256k1::Error>
+    where
+        MSG: AsRef<[u8]>,
+    {
+        let mut host = pubkey;
+        commitments
+            .iter()
+            .map(|(tag, msg)| {
+                let commitment = LNPBP1Commitment::embed_commit(
+                    &LNPBP1Container {
+                        pubkey: host,
+                        tag: *tag,
+                        key_origin: None,
+                    },
+                    msg,
+                )?;
+                host = *commitment;
+                Ok(commitment)
+            })
+            .collect()
+    }
+
+    /// Verifies that the commitment was produced by the given sequence of
+    /// protocol commitments applied to the original public key in exactly
+    /// the same order
+    pub fn verify_sequence<MSG>(
+        &self,
+        pubkey: secp256k1::PublicKey,
+        commitments: &[(sha256::Hash, MSG)],
+    ) -> Result<Verification, secp256k1::Error>
+    where
+        MSG: AsRef<[u8]>,
+    {
+        let last = LNPBP1Commitment::embed_sequence(pubkey, commitments)?
+            .pop()
+            .unwrap_or(LNPBP1Commitment(pubkey));
+        Ok(Verification::with_match(last == *self))
+    }
+}
+
+/// Computes LNPBP-1 tweaking factor for the public key, hashed protocol tag
+/// and the message: `HMAC_SHA256(SHA256("LNPBP1") || tag || msg, P)`
+// #[consensus_critical]
+// #[standard_critical("LNPBP-1")]
+pub(super) fn tweaking_factor(
+    pubkey: &secp256k1::PublicKey,
+    tag: &sha256::Hash,
+    msg: &[u8],
+) -> Hmac<sha256::Hash> {
+    // ! [CONSENSUS-CRITICAL]:
+    // ! [STANDARD-CRITICAL]: HMAC engine is based on sha256 hash
+    let mut hmac_engine = HmacEngine::<sha256::Hash>::new(&pubkey.serialize());
+
+    // ! [CONSENSUS-CRITICAL]:
+    // ! [STANDARD-CRITICAL]: Hash process started with consuming first
+    //                        protocol prefix: single SHA256 hash of
+    //                        ASCII "LNPBP-1" string
+    hmac_engine.input(&SHA256_LNPBP1);
+
+    // ! [CONSENSUS-CRITICAL]:
+    // ! [STANDARD-CRITICAL]: The second prefix comes from the upstream
+    //                        protocol as a part of the container
+    hmac_engine.input(&tag[..]);
+
+    // ! [CONSENSUS-CRITICAL]:
+    // ! [STANDARD-CRITICAL]: Next we hash the message. The message must be
+    //                        prefixed with the protocol-specific prefix:
+    //                        another single SHA256 hash of protocol name.
+    //                        However this is not the part of this function,
+    //                        the function expect that the `msg` is already
+    //                        properly prefixed
+    hmac_engine.input(msg);
+
+    Hmac::from_engine(hmac_engine)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -222,11 +305,58 @@ mod test {
         gen_secp_pubkeys(9).into_iter().for_each(|pubkey| {
             embed_commit_verify_suite::<Vec<u8>, LNPBP1Commitment>(
                 gen_messages(),
-                &LNPBP1Container { pubkey, tag },
+                &LNPBP1Container {
+                    pubkey,
+                    tag,
+                    key_origin: None,
+                },
             );
         });
     }
 
+    #[test]
+    fn test_protocol_sequence() {
+        let pubkey = gen_secp_pubkeys(1)[0];
+        let rgb = LNPBP1Container::with_protocol(pubkey, "RGB");
+        let storm = LNPBP1Container::with_protocol(pubkey, "Storm");
+        let msg = b"message".to_vec();
+        assert_ne!(
+            LNPBP1Commitment::embed_commit(&rgb, &msg).unwrap(),
+            LNPBP1Commitment::embed_commit(&storm, &msg).unwrap()
+        );
+
+        let sequence = vec![(rgb.tag, b"rgb".to_vec()), (storm.tag, b"storm".to_vec())];
+        let commitments = LNPBP1Commitment::embed_sequence(pubkey, &sequence).unwrap();
+        assert_eq!(commitments.len(), 2);
+        assert_eq!(
+            commitments[0],
+            LNPBP1Commitment::embed_commit(&rgb, &b"rgb".to_vec()).unwrap()
+        );
+        assert_eq!(
+            commitments[1],
+            LNPBP1Commitment::embed_commit(
+                &LNPBP1Container::with_protocol(*commitments[0], "Storm"),
+                &b"storm".to_vec()
+            )
+            .unwrap()
+        );
+
+        let commitment = &commitments[1];
+        assert!(commitment
+            .verify_sequence(pubkey, &sequence)
+            .unwrap()
+            .is_valid());
+        let reordered = vec![sequence[1].clone(), sequence[0].clone()];
+        assert!(commitment
+            .verify_sequence(pubkey, &reordered)
+            .unwrap()
+            .is_invalid());
+        assert!(commitment
+            .verify_sequence(pubkey, &sequence[..1])
+            .unwrap()
+            .is_invalid());
+    }
+
     #[test]
     fn test_tweaking_results() {
         let tag = sha256::Hash::hash(b"TEST_TAG");
@@ -235,8 +365,15 @@ mod test {
             "0218845781f631c48f1c9709e23092067d06837f30aa0cd0544ac887fe91ddd166",
         )
         .unwrap();
-        let commitment =
-            LNPBP1Commitment::embed_commit(&LNPBP1Container { pubkey, tag }, &msg).unwrap();
+        let commitment = LNPBP1Commitment::embed_commit(
+            &LNPBP1Container {
+                pubkey,
+                tag,
+                key_origin: None,
+            },
+            &msg,
+        )
+        .unwrap();
         assert_eq!(
             commitment.as_inner().to_hex(),
             "0278565af0da38a7754d3d4551a09bf80cf98841dbec7330db53023af5503acf8d"
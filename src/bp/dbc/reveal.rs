@@ -0,0 +1,164 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Transport format for the deterministic bitcoin commitments, packaging all
+//! of the data required by a verifier: location of the output hosting the
+//! commitment, proof for the container reconstruction, protocol tag and the
+//! committed message hash. The string representation is Bech32 encoding of
+//! the strict-encoded reveal data with [COMMITMENT_REVEAL_HRP] prefix.
+
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::{OutPoint, Transaction};
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+
+use super::Proof;
+use crate::bech32::{self, Bech32Error};
+use crate::commit_verify::{InvalidReason, Verification};
+use crate::strict_encoding::{self, strict_decode, strict_encode};
+
+/// Human-readable part of the Bech32 representation of [CommitmentReveal]
+pub const COMMITMENT_REVEAL_HRP: &str = "dbc";
+
+/// All of the data required to verify deterministic bitcoin commitment
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct CommitmentReveal {
+    /// Transaction output containing the commitment
+    pub outpoint: OutPoint,
+    /// Proof for the reconstruction of the commitment container
+    pub proof: Proof,
+    /// Single SHA256 hash of the protocol-specific tag
+    pub protocol_tag: sha256::Hash,
+    /// Hash of the message, which is used as the commitment message
+    pub message_hash: sha256::Hash,
+}
+
+#[derive(Debug, Display, From, Error)]
+#[display_from(Debug)]
+pub enum CommitmentRevealError {
+    #[derive_from]
+    Bech32(Bech32Error),
+
+    /// Human-readable part of the string is not [COMMITMENT_REVEAL_HRP]
+    WrongHrp(String),
+
+    #[derive_from]
+    Encoding(strict_encoding::Error),
+}
+
+impl CommitmentReveal {
+    /// Verifies commitment against the transaction, which must be the one
+    /// referenced by [CommitmentReveal::outpoint]
+    pub fn verify(&self, tx: &Transaction) -> Verification {
+        if tx.txid() != self.outpoint.txid {
+            return Verification::Invalid(InvalidReason::ContainerMismatch);
+        }
+        self.proof.verify(
+            tx,
+            self.outpoint.vout,
+            &self.message_hash,
+            &self.protocol_tag,
+        )
+    }
+
+    /// Verifies commitment against the transaction and the original message,
+    /// which hash must match [CommitmentReveal::message_hash]
+    pub fn verify_message(&self, tx: &Transaction, msg: &[u8]) -> Verification {
+        if sha256::Hash::hash(msg) != self.message_hash {
+            return Verification::Invalid(InvalidReason::CommitmentMismatch);
+        }
+        self.verify(tx)
+    }
+}
+
+impl Display for CommitmentReveal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let data = strict_encode(self).expect("Strict encoding of commitment reveal can't fail");
+        f.write_str(&bech32::encode(COMMITMENT_REVEAL_HRP, &data))
+    }
+}
+
+impl FromStr for CommitmentReveal {
+    type Err = CommitmentRevealError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hrp, data) = bech32::decode(s)?;
+        if hrp != COMMITMENT_REVEAL_HRP {
+            Err(CommitmentRevealError::WrongHrp(hrp))?
+        }
+        Ok(strict_decode(&data)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::dbc::{
+        Container, ScriptInfo, ScriptPubkeyCommitment, ScriptPubkeyComposition,
+        ScriptPubkeyContainer,
+    };
+    use crate::bp::test::gen_secp_pubkeys;
+    use crate::commit_verify::EmbedCommitVerify;
+    use bitcoin::{Script, TxIn, TxOut};
+
+    #[test]
+    fn test_commitment_reveal() {
+        let protocol_tag = sha256::Hash::hash(b"TEST_TAG");
+        let msg = b"message";
+        let message_hash = sha256::Hash::hash(msg);
+        let container = ScriptPubkeyContainer::construct(
+            &protocol_tag,
+            gen_secp_pubkeys(1)[0],
+            ScriptInfo::None,
+            ScriptPubkeyComposition::WPubkeyHash,
+        );
+        let commitment = ScriptPubkeyCommitment::embed_commit(&container, &message_hash).unwrap();
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn::default()],
+            output: vec![TxOut {
+                value: 1000,
+                script_pubkey: commitment.into_inner().into_inner(),
+            }],
+        };
+
+        let reveal = CommitmentReveal {
+            outpoint: OutPoint::new(tx.txid(), 0),
+            proof: container.into_proof(),
+            protocol_tag,
+            message_hash,
+        };
+        assert!(reveal.verify(&tx).is_valid());
+        assert!(reveal.verify_message(&tx, msg).is_valid());
+        assert_eq!(
+            reveal.verify_message(&tx, b"other").reason(),
+            Some(InvalidReason::CommitmentMismatch)
+        );
+
+        let mut other_tx = tx.clone();
+        other_tx.output[0].script_pubkey = Script::new();
+        assert_eq!(
+            reveal.verify(&other_tx).reason(),
+            Some(InvalidReason::ContainerMismatch)
+        );
+
+        let s = reveal.to_string();
+        assert!(s.starts_with("dbc1"));
+        assert_eq!(CommitmentReveal::from_str(&s).unwrap(), reveal);
+        assert!(matches!(
+            CommitmentReveal::from_str(&bech32::encode("xyz", &strict_encode(&reveal).unwrap())),
+            Err(CommitmentRevealError::WrongHrp(_))
+        ));
+    }
+}
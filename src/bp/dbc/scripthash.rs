@@ -0,0 +1,518 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Commitments into the lockscripts hashed into P2WSH, P2SH-P2WSH and legacy
+//! P2SH outputs, and into the public keys of P2WPKH and P2SH-P2WPKH outputs.
+//! The containers wrap [LockscriptContainer] or [LNPBP1Container] and
+//! produce both the final `scriptPubkey` and the scripts which have to be
+//! revealed when the output is spent, so the whole output can be
+//! deterministically reconstructed from the proof.
+
+use bitcoin::hashes::sha256;
+use bitcoin::secp256k1;
+
+use super::{
+    Container, Error, LNPBP1Commitment, LNPBP1Container, LockscriptCommitment, LockscriptContainer,
+    Proof, ScriptInfo, ScriptPubkeyComposition,
+};
+use crate::bp::{GenerateScripts, PubkeyScript, RedeemScript, Strategy, WitnessScript};
+use crate::commit_verify::EmbedCommitVerify;
+
+/// Container for the commitment into the witness script of P2WSH or nested
+/// P2SH-P2WSH output
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct WitnessScriptContainer {
+    pub lockscript: LockscriptContainer,
+    /// Whether the witness program is nested into P2SH
+    pub nested: bool,
+}
+
+impl WitnessScriptContainer {
+    #[inline]
+    pub fn composition(&self) -> ScriptPubkeyComposition {
+        match self.nested {
+            true => ScriptPubkeyComposition::SHWScriptHash,
+            false => ScriptPubkeyComposition::WScriptHash,
+        }
+    }
+}
+
+impl Container for WitnessScriptContainer {
+    /// Out supplement is a protocol-specific tag in its hashed form
+    type Supplement = sha256::Hash;
+    type Host = Option<()>;
+
+    fn reconstruct(
+        proof: &Proof,
+        supplement: &Self::Supplement,
+        host: &Self::Host,
+    ) -> Result<Self, Error> {
+        let nested = match proof.composition {
+            Some(ScriptPubkeyComposition::WScriptHash) => false,
+            Some(ScriptPubkeyComposition::SHWScriptHash) => true,
+            _ => Err(Error::InvalidProofStructure)?,
+        };
+        Ok(Self {
+            lockscript: LockscriptContainer::reconstruct(proof, supplement, host)?,
+            nested,
+        })
+    }
+
+    #[inline]
+    fn deconstruct(self) -> (Proof, Self::Supplement) {
+        let composition = self.composition();
+        let (proof, supplement) = self.lockscript.deconstruct();
+        (
+            Proof {
+                composition: Some(composition),
+                ..proof
+            },
+            supplement,
+        )
+    }
+
+    #[inline]
+    fn to_proof(&self) -> Proof {
+        Proof {
+            composition: Some(self.composition()),
+            ..self.lockscript.to_proof()
+        }
+    }
+
+    #[inline]
+    fn into_proof(self) -> Proof {
+        self.deconstruct().0
+    }
+}
+
+/// Committed witness script together with the scripts of the output
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct WitnessScriptCommitment {
+    pub witness_script: WitnessScript,
+    /// Witness program placed into the `sigScript` of the input spending
+    /// nested P2SH-P2WSH output
+    pub redeem_script: Option<RedeemScript>,
+    pub script_pubkey: PubkeyScript,
+}
+
+impl<MSG> EmbedCommitVerify<MSG> for WitnessScriptCommitment
+where
+    MSG: AsRef<[u8]>,
+{
+    type Container = WitnessScriptContainer;
+    type Error = Error;
+
+    fn embed_commit(container: &Self::Container, msg: &MSG) -> Result<Self, Self::Error> {
+        let lockscript =
+            LockscriptCommitment::embed_commit(&container.lockscript, msg)?.into_inner();
+        let (redeem_script, script_pubkey) = match container.nested {
+            true => (
+                Some(RedeemScript::from_inner(
+                    lockscript
+                        .gen_script_pubkey(Strategy::WitnessV0)
+                        .into_inner(),
+                )),
+                lockscript.gen_script_pubkey(Strategy::WitnessScriptHash),
+            ),
+            false => (None, lockscript.gen_script_pubkey(Strategy::WitnessV0)),
+        };
+        Ok(Self {
+            witness_script: lockscript.into(),
+            redeem_script,
+            script_pubkey,
+        })
+    }
+}
+
+/// Container for the commitment into the redeem script of legacy P2SH output
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct RedeemScriptContainer {
+    pub lockscript: LockscriptContainer,
+}
+
+impl Container for RedeemScriptContainer {
+    /// Out supplement is a protocol-specific tag in its hashed form
+    type Supplement = sha256::Hash;
+    type Host = Option<()>;
+
+    fn reconstruct(
+        proof: &Proof,
+        supplement: &Self::Supplement,
+        host: &Self::Host,
+    ) -> Result<Self, Error> {
+        if proof.composition != Some(ScriptPubkeyComposition::ScriptHash) {
+            Err(Error::InvalidProofStructure)?
+        }
+        Ok(Self {
+            lockscript: LockscriptContainer::reconstruct(proof, supplement, host)?,
+        })
+    }
+
+    #[inline]
+    fn deconstruct(self) -> (Proof, Self::Supplement) {
+        let (proof, supplement) = self.lockscript.deconstruct();
+        (
+            Proof {
+                composition: Some(ScriptPubkeyComposition::ScriptHash),
+                ..proof
+            },
+            supplement,
+        )
+    }
+
+    #[inline]
+    fn to_proof(&self) -> Proof {
+        Proof {
+            composition: Some(ScriptPubkeyComposition::ScriptHash),
+            ..self.lockscript.to_proof()
+        }
+    }
+
+    #[inline]
+    fn into_proof(self) -> Proof {
+        self.deconstruct().0
+    }
+}
+
+/// Committed redeem script together with the `scriptPubkey` of the output
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct RedeemScriptCommitment {
+    pub redeem_script: RedeemScript,
+    pub script_pubkey: PubkeyScript,
+}
+
+impl<MSG> EmbedCommitVerify<MSG> for RedeemScriptCommitment
+where
+    MSG: AsRef<[u8]>,
+{
+    type Container = RedeemScriptContainer;
+    type Error = Error;
+
+    fn embed_commit(container: &Self::Container, msg: &MSG) -> Result<Self, Self::Error> {
+        let lockscript =
+            LockscriptCommitment::embed_commit(&container.lockscript, msg)?.into_inner();
+        Ok(Self {
+            script_pubkey: lockscript.gen_script_pubkey(Strategy::LegacyHashed),
+            redeem_script: lockscript.into(),
+        })
+    }
+}
+
+/// Container for the commitment into the public key of P2WPKH or nested
+/// P2SH-P2WPKH output
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct WPubkeyHashContainer {
+    pub pubkey: LNPBP1Container,
+    /// Whether the witness program is nested into P2SH
+    pub nested: bool,
+}
+
+impl WPubkeyHashContainer {
+    #[inline]
+    pub fn composition(&self) -> ScriptPubkeyComposition {
+        match self.nested {
+            true => ScriptPubkeyComposition::SHWPubkeyHash,
+            false => ScriptPubkeyComposition::WPubkeyHash,
+        }
+    }
+}
+
+impl Container for WPubkeyHashContainer {
+    /// Out supplement is a protocol-specific tag in its hashed form
+    type Supplement = sha256::Hash;
+    type Host = Option<()>;
+
+    fn reconstruct(
+        proof: &Proof,
+        supplement: &Self::Supplement,
+        host: &Self::Host,
+    ) -> Result<Self, Error> {
+        let nested = match proof.composition {
+            Some(ScriptPubkeyComposition::WPubkeyHash) => false,
+            Some(ScriptPubkeyComposition::SHWPubkeyHash) => true,
+            _ => Err(Error::InvalidProofStructure)?,
+        };
+        if proof.script_info != ScriptInfo::None {
+            Err(Error::InvalidProofStructure)?
+        }
+        Ok(Self {
+            pubkey: LNPBP1Container::reconstruct(proof, supplement, host)?,
+            nested,
+        })
+    }
+
+    #[inline]
+    fn deconstruct(self) -> (Proof, Self::Supplement) {
+        let composition = self.composition();
+        let (proof, supplement) = self.pubkey.deconstruct();
+        (
+            Proof {
+                composition: Some(composition),
+                ..proof
+            },
+            supplement,
+        )
+    }
+
+    #[inline]
+    fn to_proof(&self) -> Proof {
+        Proof {
+            composition: Some(self.composition()),
+            ..self.pubkey.to_proof()
+        }
+    }
+
+    #[inline]
+    fn into_proof(self) -> Proof {
+        self.deconstruct().0
+    }
+}
+
+/// Committed public key together with the scripts of the output
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct WPubkeyHashCommitment {
+    pub pubkey: secp256k1::PublicKey,
+    /// Witness program placed into the `sigScript` of the input spending
+    /// nested P2SH-P2WPKH output
+    pub redeem_script: Option<RedeemScript>,
+    pub script_pubkey: PubkeyScript,
+}
+
+impl<MSG> EmbedCommitVerify<MSG> for WPubkeyHashCommitment
+where
+    MSG: AsRef<[u8]>,
+{
+    type Container = WPubkeyHashContainer;
+    type Error = Error;
+
+    fn embed_commit(container: &Self::Container, msg: &MSG) -> Result<Self, Self::Error> {
+        // ! [CONSENSUS-CRITICAL]: Both P2SH and witness program layers are
+        //                         constructed from the tweaked key
+        let pubkey = *LNPBP1Commitment::embed_commit(&container.pubkey, msg)?;
+        let witness_program = pubkey.gen_script_pubkey(Strategy::WitnessV0);
+        let (redeem_script, script_pubkey) = match container.nested {
+            true => (
+                Some(RedeemScript::from_inner(witness_program.into_inner())),
+                pubkey.gen_script_pubkey(Strategy::WitnessScriptHash),
+            ),
+            false => (None, witness_program),
+        };
+        Ok(Self {
+            pubkey,
+            redeem_script,
+            script_pubkey,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::dbc::{ScriptInfo, ScriptPubkeyCommitment, ScriptPubkeyContainer};
+    use crate::bp::test::gen_secp_pubkeys;
+    use crate::bp::LockScript;
+    use crate::commit_verify::test::{embed_commit_verify_suite, gen_messages};
+    use bitcoin::blockdata::opcodes::all::*;
+    use bitcoin::blockdata::script::Builder;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Transaction, TxOut};
+
+    fn lockscript_container() -> LockscriptContainer {
+        let keys = gen_secp_pubkeys(2);
+        LockscriptContainer {
+            script: LockScript::from(
+                Builder::new()
+                    .push_opcode(OP_PUSHNUM_1)
+                    .push_slice(&keys[0].serialize())
+                    .push_slice(&keys[1].serialize())
+                    .push_opcode(OP_PUSHNUM_2)
+                    .push_opcode(OP_CHECKMULTISIG)
+                    .into_script(),
+            ),
+            pubkey: keys[1],
+            tag: sha256::Hash::hash(b"TEST_TAG"),
+        }
+    }
+
+    /// Checks that the output matches the one produced by the generic
+    /// `scriptPubkey` container with the same composition
+    fn check_script_pubkey(script_pubkey: &PubkeyScript, composition: ScriptPubkeyComposition) {
+        let lockscript = lockscript_container();
+        let container = ScriptPubkeyContainer::construct(
+            &lockscript.tag,
+            lockscript.pubkey,
+            ScriptInfo::LockScript(lockscript.script),
+            composition,
+        );
+        let commitment = ScriptPubkeyCommitment::embed_commit(&container, &b"msg".to_vec());
+        assert_eq!(*commitment.unwrap(), *script_pubkey);
+    }
+
+    #[test]
+    fn test_witness_script() {
+        for nested in vec![false, true] {
+            let container = WitnessScriptContainer {
+                lockscript: lockscript_container(),
+                nested,
+            };
+            embed_commit_verify_suite::<Vec<u8>, WitnessScriptCommitment>(
+                gen_messages(),
+                &container,
+            );
+
+            let commitment =
+                WitnessScriptCommitment::embed_commit(&container, &b"msg".to_vec()).unwrap();
+            check_script_pubkey(&commitment.script_pubkey, container.composition());
+            assert_eq!(commitment.redeem_script.is_some(), nested);
+            assert_ne!(*commitment.witness_script, *container.lockscript.script);
+
+            let (proof, supplement) = container.clone().deconstruct();
+            assert_eq!(
+                WitnessScriptContainer::reconstruct(&proof, &supplement, &None).unwrap(),
+                container
+            );
+            assert_eq!(
+                RedeemScriptContainer::reconstruct(&proof, &supplement, &None),
+                Err(Error::InvalidProofStructure)
+            );
+        }
+    }
+
+    #[test]
+    fn test_redeem_script() {
+        let container = RedeemScriptContainer {
+            lockscript: lockscript_container(),
+        };
+        embed_commit_verify_suite::<Vec<u8>, RedeemScriptCommitment>(gen_messages(), &container);
+
+        let commitment =
+            RedeemScriptCommitment::embed_commit(&container, &b"msg".to_vec()).unwrap();
+        check_script_pubkey(
+            &commitment.script_pubkey,
+            ScriptPubkeyComposition::ScriptHash,
+        );
+
+        let (proof, supplement) = container.clone().deconstruct();
+        assert_eq!(
+            RedeemScriptContainer::reconstruct(&proof, &supplement, &None).unwrap(),
+            container
+        );
+    }
+
+    #[test]
+    fn test_wpubkey_hash() {
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let pubkey = gen_secp_pubkeys(1)[0];
+        let msg = b"msg".to_vec();
+        for nested in vec![false, true] {
+            let container = WPubkeyHashContainer {
+                pubkey: LNPBP1Container {
+                    pubkey,
+                    tag,
+                    key_origin: None,
+                },
+                nested,
+            };
+            embed_commit_verify_suite::<Vec<u8>, WPubkeyHashCommitment>(gen_messages(), &container);
+
+            let commitment = WPubkeyHashCommitment::embed_commit(&container, &msg).unwrap();
+            let generic = ScriptPubkeyContainer::construct(
+                &tag,
+                pubkey,
+                ScriptInfo::None,
+                container.composition(),
+            );
+            assert_eq!(
+                *ScriptPubkeyCommitment::embed_commit(&generic, &msg).unwrap(),
+                commitment.script_pubkey
+            );
+            assert_ne!(commitment.pubkey, pubkey);
+            match commitment.redeem_script {
+                Some(ref redeem_script) => {
+                    assert!(nested);
+                    assert!(commitment.script_pubkey.is_p2sh());
+                    assert_eq!(
+                        Builder::gen_p2sh(&redeem_script.script_hash()).into_script(),
+                        *commitment.script_pubkey
+                    );
+                    assert!(redeem_script.is_v0_p2wpkh());
+                }
+                None => {
+                    assert!(!nested);
+                    assert!(commitment.script_pubkey.is_v0_p2wpkh());
+                }
+            }
+
+            let (proof, supplement) = container.clone().deconstruct();
+            assert_eq!(
+                WPubkeyHashContainer::reconstruct(&proof, &supplement, &None).unwrap(),
+                container
+            );
+            assert_eq!(
+                WitnessScriptContainer::reconstruct(&proof, &supplement, &None),
+                Err(Error::InvalidProofStructure)
+            );
+        }
+    }
+
+    #[test]
+    fn test_nested_wpubkey_hash_onchain() {
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let pubkey = gen_secp_pubkeys(1)[0];
+        let msg = b"msg".to_vec();
+        let container = WPubkeyHashContainer {
+            pubkey: LNPBP1Container {
+                pubkey,
+                tag,
+                key_origin: None,
+            },
+            nested: true,
+        };
+        let commitment = WPubkeyHashCommitment::embed_commit(&container, &msg).unwrap();
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![TxOut {
+                value: 1000,
+                script_pubkey: commitment.script_pubkey.clone().into_inner(),
+            }],
+        };
+
+        // Verification works with the P2SH script hash only, both with the
+        // proof specifying the nesting and the plain public key proof
+        for proof in vec![container.to_proof(), Proof::from(pubkey)] {
+            assert!(proof.verify_bool(&tx, 0, &msg, &tag));
+            assert!(!proof.verify_bool(&tx, 0, &b"other".to_vec(), &tag));
+            let reconstructed =
+                ScriptPubkeyContainer::reconstruct(&proof, &tag, &commitment.script_pubkey)
+                    .unwrap();
+            assert_eq!(
+                reconstructed.scriptpubkey_composition,
+                ScriptPubkeyComposition::SHWPubkeyHash
+            );
+        }
+
+        let proof = Proof {
+            composition: Some(ScriptPubkeyComposition::WPubkeyHash),
+            ..container.to_proof()
+        };
+        assert!(!proof.verify_bool(&tx, 0, &msg, &tag));
+    }
+}
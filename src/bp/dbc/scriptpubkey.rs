@@ -12,15 +12,19 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 use bitcoin::blockdata::script::Builder;
-use bitcoin::{hashes::sha256, secp256k1};
+use bitcoin::hashes::sha256;
+use bitcoin::secp256k1::{self, Secp256k1};
+use bitcoin::util::psbt;
+use bitcoin::Transaction;
 use core::convert::TryFrom;
 
 use super::{
-    Container, Error, LNPBP1Commitment, LNPBP1Container, LockscriptCommitment, LockscriptContainer,
-    Proof, ScriptInfo, TaprootCommitment, TaprootContainer,
+    taproot_output_key, Container, Error, KeyOrigin, LNPBP1Commitment, LNPBP1Container,
+    LockscriptCommitment, LockscriptContainer, Proof, ScriptInfo, TaprootCommitment,
+    TaprootContainer,
 };
-use crate::bp::{GenerateScripts, LockScript, PubkeyScript, ScriptPubkeyDescriptor, Strategy};
-use crate::commit_verify::EmbedCommitVerify;
+use crate::bp::{GenerateScripts, PubkeyScript, ScriptPubkeyDescriptor, Strategy};
+use crate::commit_verify::{EmbedCommitVerify, InvalidReason, Verification};
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
 #[display_from(Debug)]
@@ -38,6 +42,27 @@ pub enum ScriptPubkeyComposition {
     PlainScript,
 }
 
+#[cfg(all(test, feature = "property_tests"))]
+impl quickcheck::Arbitrary for ScriptPubkeyComposition {
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use quickcheck::Arbitrary;
+        use ScriptPubkeyComposition::*;
+        let variants = [
+            PublicKey,
+            PubkeyHash,
+            ScriptHash,
+            WPubkeyHash,
+            WScriptHash,
+            SHWPubkeyHash,
+            SHWScriptHash,
+            TapRoot,
+            OpReturn,
+            PlainScript,
+        ];
+        variants[usize::arbitrary(g) % variants.len()].clone()
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
 #[display_from(Debug)]
 pub struct ScriptPubkeyContainer {
@@ -46,6 +71,8 @@ pub struct ScriptPubkeyContainer {
     pub scriptpubkey_composition: ScriptPubkeyComposition,
     /// Single SHA256 hash of the protocol-specific tag
     pub tag: sha256::Hash,
+    /// BIP-32 origin of the original public key, if known
+    pub key_origin: Option<KeyOrigin>,
 }
 
 impl ScriptPubkeyContainer {
@@ -60,6 +87,36 @@ impl ScriptPubkeyContainer {
             script_info,
             scriptpubkey_composition,
             tag: protocol_tag.clone(),
+            key_origin: None,
+        }
+    }
+
+    /// Sets BIP-32 origin of the original public key
+    #[inline]
+    pub fn with_key_origin(self, key_origin: KeyOrigin) -> Self {
+        Self {
+            key_origin: Some(key_origin),
+            ..self
+        }
+    }
+
+    /// Adds BIP-32 origin of the original public key to the key paths of the
+    /// PSBT output receiving the commitment, so a hardware wallet can detect
+    /// its own key and apply the tweak
+    pub fn update_psbt_output(&self, output: &mut psbt::Output) {
+        if let Some(ref key_origin) = self.key_origin {
+            let (pubkey, keypath) = key_origin.psbt_keypath(self.pubkey);
+            output.hd_keypaths.insert(pubkey, keypath);
+        }
+    }
+
+    /// Adds BIP-32 origin of the original public key to the key paths of the
+    /// PSBT input spending the output containing the commitment, so a
+    /// hardware wallet can sign with the tweaked key
+    pub fn update_psbt_input(&self, input: &mut psbt::Input) {
+        if let Some(ref key_origin) = self.key_origin {
+            let (pubkey, keypath) = key_origin.psbt_keypath(self.pubkey);
+            input.hd_keypaths.insert(pubkey, keypath);
         }
     }
 }
@@ -77,44 +134,48 @@ impl Container for ScriptPubkeyContainer {
         use ScriptPubkeyComposition as Comp;
         use ScriptPubkeyDescriptor as Descr;
 
-        let (lockscript, _) = match &proof.script_info {
-            ScriptInfo::None => (None, None),
-            ScriptInfo::LockScript(script) => (Some(script), None),
-            ScriptInfo::Taproot(hash) => (None, Some(hash)),
-        };
-
-        let mut proof = proof.clone();
         let composition = match ScriptPubkeyDescriptor::try_from(host.clone())? {
-            Descr::P2SH(script_hash) => {
-                let script = Builder::gen_p2sh(&script_hash).into_script();
-                if let Some(lockscript) = lockscript {
-                    if *lockscript.gen_script_pubkey(Strategy::LegacyHashed) == script {
-                        Comp::ScriptHash
-                    } else if *lockscript.gen_script_pubkey(Strategy::WitnessScriptHash) == script {
+            // P2SH may host either a legacy script or a nested segwit v0
+            // program. If the host is not committed yet, the lockscript
+            // nesting is detected from the host; otherwise it can't be
+            // detected and is resolved during the verification
+            Descr::P2SH(script_hash) => match proof.script_info {
+                ScriptInfo::LockScript(ref lockscript) => {
+                    let script = Builder::gen_p2sh(&script_hash).into_script();
+                    if *lockscript.gen_script_pubkey(Strategy::WitnessScriptHash) == script {
                         Comp::SHWScriptHash
                     } else {
-                        Err(Error::InvalidProofStructure)?
-                    }
-                } else {
-                    if *proof.pubkey.gen_script_pubkey(Strategy::WitnessScriptHash) == script {
-                        Comp::SHWPubkeyHash
-                    } else {
-                        Err(Error::InvalidProofStructure)?
+                        Comp::ScriptHash
                     }
                 }
-            }
-            Descr::P2S(script) => {
-                proof.script_info = ScriptInfo::LockScript(LockScript::from(script.to_inner()));
-                Comp::PlainScript
-            }
-            Descr::P2PK(_) => Comp::PubkeyHash,
-            Descr::P2PKH(_) => Comp::PublicKey,
+                _ => Comp::SHWPubkeyHash,
+            },
+            Descr::P2S(_) => Comp::PlainScript,
+            Descr::P2PK(_) => Comp::PublicKey,
+            Descr::P2PKH(_) => Comp::PubkeyHash,
             Descr::P2OR(_) => Comp::OpReturn,
             Descr::P2WPKH(_) => Comp::WPubkeyHash,
             Descr::P2WSH(_) => Comp::WScriptHash,
             Descr::P2TR(_) => Comp::TapRoot,
         };
-        let proof = proof;
+        // Output type provided by the proof takes precedence, since it
+        // resolves P2SH nesting ambiguity for the committed hosts
+        let is_p2sh = |composition: &Comp| {
+            matches!(
+                composition,
+                Comp::ScriptHash | Comp::SHWScriptHash | Comp::SHWPubkeyHash
+            )
+        };
+        let composition = match proof.composition {
+            None => composition,
+            Some(ref proof_composition)
+                if *proof_composition == composition
+                    || (is_p2sh(proof_composition) && is_p2sh(&composition)) =>
+            {
+                proof_composition.clone()
+            }
+            _ => Err(Error::InvalidProofStructure)?,
+        };
 
         match composition {
             Comp::PublicKey
@@ -134,8 +195,8 @@ impl Container for ScriptPubkeyContainer {
                 }
             }
             Comp::TapRoot => {
-                if let ScriptInfo::Taproot(_) = proof.script_info {
-                } else {
+                // Key-path only outputs have no script tree
+                if let ScriptInfo::LockScript(_) = proof.script_info {
                     Err(Error::InvalidProofStructure)?
                 }
             }
@@ -143,9 +204,10 @@ impl Container for ScriptPubkeyContainer {
 
         Ok(Self {
             pubkey: proof.pubkey,
-            script_info: proof.script_info,
+            script_info: proof.script_info.clone(),
             scriptpubkey_composition: composition,
             tag: supplement.clone(),
+            key_origin: None,
         })
     }
 
@@ -154,6 +216,7 @@ impl Container for ScriptPubkeyContainer {
             Proof {
                 pubkey: self.pubkey,
                 script_info: self.script_info,
+                composition: Some(self.scriptpubkey_composition),
             },
             self.tag,
         )
@@ -163,6 +226,7 @@ impl Container for ScriptPubkeyContainer {
         Proof {
             pubkey: self.pubkey.clone(),
             script_info: self.script_info.clone(),
+            composition: Some(self.scriptpubkey_composition.clone()),
         }
     }
 
@@ -170,6 +234,7 @@ impl Container for ScriptPubkeyContainer {
         Proof {
             pubkey: self.pubkey,
             script_info: self.script_info,
+            composition: Some(self.scriptpubkey_composition),
         }
     }
 }
@@ -181,6 +246,18 @@ wrapper!(
     derive = [PartialEq, Eq, Hash]
 );
 
+impl ScriptPubkeyCommitment {
+    /// Verifies that the transaction output `vout` has the same
+    /// `scriptPubkey` as the commitment, so the commitment can be checked
+    /// against the blockchain data
+    pub fn verify_in_tx(&self, tx: &Transaction, vout: usize) -> Verification {
+        match tx.output.get(vout) {
+            Some(txout) => Verification::with_match(txout.script_pubkey == ***self),
+            None => Verification::Invalid(InvalidReason::ContainerMismatch),
+        }
+    }
+}
+
 impl<MSG> EmbedCommitVerify<MSG> for ScriptPubkeyCommitment
 where
     MSG: AsRef<[u8]>,
@@ -211,22 +288,22 @@ where
             if container.scriptpubkey_composition != TapRoot {
                 Err(Error::InvalidProofStructure)?
             }
-            let _taproot = TaprootCommitment::embed_commit(
+            TaprootCommitment::embed_commit(
                 &TaprootContainer {
                     script_root: taproot_hash,
                     intermediate_key: container.pubkey,
                     tag: container.tag,
                 },
                 msg,
-            )?;
-            // TODO: Finalize taproot commitments once taproot will be finalized
-            // We don't know yet how to form scripPubkey from Taproot data
-            unimplemented!()
+            )?
+            .script_pubkey()
+            .into()
         } else {
             let pubkey = *LNPBP1Commitment::embed_commit(
                 &LNPBP1Container {
                     pubkey: container.pubkey,
                     tag: container.tag,
+                    key_origin: container.key_origin.clone(),
                 },
                 msg,
             )?;
@@ -234,13 +311,194 @@ where
                 PublicKey => pubkey.gen_script_pubkey(Strategy::Exposed),
                 PubkeyHash => pubkey.gen_script_pubkey(Strategy::LegacyHashed),
                 WPubkeyHash => pubkey.gen_script_pubkey(Strategy::WitnessV0),
-                SHWScriptHash => pubkey.gen_script_pubkey(Strategy::WitnessScriptHash),
+                SHWPubkeyHash => pubkey.gen_script_pubkey(Strategy::WitnessScriptHash),
                 OpReturn => Builder::gen_op_return(&pubkey.serialize().to_vec())
                     .into_script()
                     .into(),
+                // Key-path only taproot output: the tweaked key is used as
+                // the internal key without a script tree
+                TapRoot => {
+                    let ec = Secp256k1::<secp256k1::VerifyOnly>::verification_only();
+                    ScriptPubkeyDescriptor::P2TR(taproot_output_key(&ec, &pubkey, None)?).into()
+                }
                 _ => Err(Error::InvalidProofStructure)?,
             }
         };
         Ok(ScriptPubkeyCommitment::from_inner(script_pubkey))
     }
+
+    /// Verifies commitment against the message. Since P2SH `scriptPubkey`
+    /// does not tell whether it hosts a legacy lockscript or a nested P2WSH
+    /// one, both of the options are tried for lockscript-based containers.
+    fn verify(&self, container: &Self::Container, msg: &MSG) -> Result<Verification, Self::Error> {
+        use ScriptPubkeyComposition::*;
+        let verify = |container: &ScriptPubkeyContainer| match Self::embed_commit(container, msg) {
+            Ok(commitment) => Verification::with_match(commitment == *self),
+            Err(_) => Verification::Invalid(InvalidReason::ContainerMismatch),
+        };
+        Ok(match container.scriptpubkey_composition {
+            ScriptHash | SHWScriptHash => {
+                let mut container = container.clone();
+                container.scriptpubkey_composition = ScriptHash;
+                match verify(&container) {
+                    Verification::Valid => Verification::Valid,
+                    _ => {
+                        container.scriptpubkey_composition = SHWScriptHash;
+                        verify(&container)
+                    }
+                }
+            }
+            _ => verify(container),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::test::*;
+    use crate::bp::LockScript;
+    use bitcoin::blockdata::opcodes::all::*;
+    use bitcoin::hashes::Hash;
+
+    fn roundtrip(script_info: ScriptInfo, composition: ScriptPubkeyComposition) {
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let keys = gen_secp_pubkeys(2);
+        let msg = b"message".to_vec();
+        let container =
+            ScriptPubkeyContainer::construct(&tag, keys[1], script_info, composition.clone());
+        let commitment = ScriptPubkeyCommitment::embed_commit(&container, &msg).unwrap();
+        assert_eq!(
+            commitment.verify(&container, &msg).unwrap(),
+            Verification::Valid
+        );
+
+        let (proof, supplement) = container.deconstruct();
+        let reconstructed =
+            ScriptPubkeyContainer::reconstruct(&proof, &supplement, &commitment).unwrap();
+        assert_eq!(
+            commitment.verify(&reconstructed, &msg).unwrap(),
+            Verification::Valid,
+            "{:?}",
+            composition
+        );
+        assert!(commitment
+            .verify(&reconstructed, &b"other message".to_vec())
+            .unwrap()
+            .is_invalid());
+    }
+
+    fn lockscript() -> ScriptInfo {
+        let keys = gen_secp_pubkeys(2);
+        ScriptInfo::LockScript(LockScript::from(
+            Builder::new()
+                .push_opcode(OP_PUSHNUM_1)
+                .push_slice(&keys[0].serialize())
+                .push_slice(&keys[1].serialize())
+                .push_opcode(OP_PUSHNUM_2)
+                .push_opcode(OP_CHECKMULTISIG)
+                .into_script(),
+        ))
+    }
+
+    #[test]
+    fn test_pubkey_roundtrip() {
+        use ScriptPubkeyComposition::*;
+        for composition in vec![PublicKey, PubkeyHash, WPubkeyHash, SHWPubkeyHash, OpReturn] {
+            roundtrip(ScriptInfo::None, composition);
+        }
+    }
+
+    #[test]
+    fn test_lockscript_roundtrip() {
+        use ScriptPubkeyComposition::*;
+        for composition in vec![PlainScript, ScriptHash, WScriptHash, SHWScriptHash] {
+            roundtrip(lockscript(), composition);
+        }
+    }
+
+    #[test]
+    fn test_taproot_roundtrip() {
+        // Key path only
+        roundtrip(ScriptInfo::None, ScriptPubkeyComposition::TapRoot);
+        // Script path
+        roundtrip(
+            ScriptInfo::Taproot(sha256::Hash::hash(b"script tree")),
+            ScriptPubkeyComposition::TapRoot,
+        );
+    }
+
+    #[test]
+    fn test_nested_detection() {
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let proof = Proof {
+            pubkey: gen_secp_pubkeys(2)[1],
+            script_info: lockscript(),
+            composition: None,
+        };
+        let lockscript = match proof.script_info {
+            ScriptInfo::LockScript(ref script) => script.clone(),
+            _ => unreachable!(),
+        };
+        let host = lockscript.gen_script_pubkey(Strategy::WitnessScriptHash);
+        let container = ScriptPubkeyContainer::reconstruct(&proof, &tag, &host).unwrap();
+        assert_eq!(
+            container.scriptpubkey_composition,
+            ScriptPubkeyComposition::SHWScriptHash
+        );
+        let host = lockscript.gen_script_pubkey(Strategy::LegacyHashed);
+        let container = ScriptPubkeyContainer::reconstruct(&proof, &tag, &host).unwrap();
+        assert_eq!(
+            container.scriptpubkey_composition,
+            ScriptPubkeyComposition::ScriptHash
+        );
+    }
+
+    #[test]
+    fn test_key_origin() {
+        use crate::strict_encoding::{strict_decode, strict_encode};
+        use bitcoin::util::bip32;
+        use std::str::FromStr;
+
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let pubkey = gen_secp_pubkeys(1)[0];
+        let msg = b"message".to_vec();
+        let key_origin = KeyOrigin {
+            fingerprint: bip32::Fingerprint::from(&[0xde, 0xad, 0xbe, 0xef][..]),
+            derivation: bip32::DerivationPath::from_str("m/84'/0'/0'/0/1").unwrap(),
+        };
+        let container = ScriptPubkeyContainer::construct(
+            &tag,
+            pubkey,
+            ScriptInfo::None,
+            ScriptPubkeyComposition::WPubkeyHash,
+        );
+        let with_origin = container.clone().with_key_origin(key_origin.clone());
+
+        // Key origin does not affect the commitment and is not disclosed in
+        // the proof
+        assert_eq!(
+            ScriptPubkeyCommitment::embed_commit(&container, &msg).unwrap(),
+            ScriptPubkeyCommitment::embed_commit(&with_origin, &msg).unwrap()
+        );
+        assert_eq!(with_origin.to_proof(), container.to_proof());
+
+        let decoded: ScriptPubkeyContainer =
+            strict_decode(&strict_encode(&with_origin).unwrap()).unwrap();
+        assert_eq!(decoded, with_origin);
+
+        let mut output = psbt::Output::default();
+        container.update_psbt_output(&mut output);
+        assert!(output.hd_keypaths.is_empty());
+        with_origin.update_psbt_output(&mut output);
+        let mut input = psbt::Input::default();
+        with_origin.update_psbt_input(&mut input);
+        let keypath = (key_origin.fingerprint, key_origin.derivation.clone());
+        let key = bitcoin::PublicKey {
+            compressed: true,
+            key: pubkey,
+        };
+        assert_eq!(output.hd_keypaths, bmap! { key => keypath.clone() });
+        assert_eq!(input.hd_keypaths, bmap! { key => keypath });
+    }
 }
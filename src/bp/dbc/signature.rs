@@ -0,0 +1,324 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! # Sign-to-contract
+//!
+//! Commitments placed into the nonce of an ECDSA signature. The original
+//! nonce point `R` is tweaked with the message according to LNPBP-1 procedure,
+//! `R' = R + G * HMAC_SHA256(SHA256("LNPBP1") || tag || msg, R)`, and the
+//! signature is produced with the tweaked nonce, so the x-coordinate of `R'`
+//! appears as the `r` value of the signature. This allows to place the
+//! commitment into transaction inputs instead of tweaking outputs; the
+//! verifier needs only the original nonce point as a proof.
+
+use bitcoin::hashes::sha256;
+use bitcoin::secp256k1::{self, Secp256k1};
+
+use super::pubkey::tweaking_factor;
+use super::{Container, Error, LNPBP1Commitment, LNPBP1Container, Proof};
+use crate::commit_verify::EmbedCommitVerify;
+
+/// Container for sign-to-contract commitments: the original (untweaked)
+/// public nonce of the signature and the protocol-specific tag
+#[derive(Clone, PartialEq, Eq, Debug, Display, Hash)]
+#[display_from(Debug)]
+pub struct SignatureContainer {
+    /// The original public nonce `R = G * k`: host for commitment
+    pub nonce: secp256k1::PublicKey,
+    /// Single SHA256 hash of the protocol-specific tag
+    pub tag: sha256::Hash,
+}
+
+impl Container for SignatureContainer {
+    /// Out supplement is a protocol-specific tag in its hashed form
+    type Supplement = sha256::Hash;
+    /// Our proof contains the host, so we don't need host here
+    type Host = Option<()>;
+
+    fn reconstruct(
+        proof: &Proof,
+        supplement: &Self::Supplement,
+        _: &Self::Host,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            nonce: proof.pubkey,
+            tag: supplement.clone(),
+        })
+    }
+
+    #[inline]
+    fn deconstruct(self) -> (Proof, Self::Supplement) {
+        (Proof::from(self.nonce), self.tag)
+    }
+
+    #[inline]
+    fn to_proof(&self) -> Proof {
+        Proof::from(self.nonce.clone())
+    }
+
+    #[inline]
+    fn into_proof(self) -> Proof {
+        Proof::from(self.nonce)
+    }
+}
+
+wrapper!(
+    SignatureCommitment,
+    [u8; 32],
+    doc = "The `r` value of ECDSA signature (x-coordinate of the public nonce) committed to some message via sign-to-contract procedure",
+    derive = [PartialEq, Eq, Hash]
+);
+
+impl From<secp256k1::Signature> for SignatureCommitment {
+    /// Extracts commitment from the signature, so it can be verified against
+    /// the [SignatureContainer] and the message
+    fn from(signature: secp256k1::Signature) -> Self {
+        let mut r = [0u8; 32];
+        r.copy_from_slice(&signature.serialize_compact()[..32]);
+        SignatureCommitment(r)
+    }
+}
+
+impl SignatureCommitment {
+    /// Signs `digest` with `seckey` using secret `nonce` tweaked with the
+    /// commitment to `msg`. Returns the signature, which `r` value is the
+    /// commitment, together with the container which has to be kept for the
+    /// commitment verification.
+    ///
+    /// The nonce must be unique for each signature and kept secret in the
+    /// same way as the signing key; reusing it leaks the key.
+    pub fn sign<C: secp256k1::Signing, MSG: AsRef<[u8]>>(
+        secp: &Secp256k1<C>,
+        digest: &secp256k1::Message,
+        seckey: &secp256k1::SecretKey,
+        nonce: &secp256k1::SecretKey,
+        tag: sha256::Hash,
+        msg: &MSG,
+    ) -> Result<(secp256k1::Signature, SignatureContainer), Error> {
+        let container = SignatureContainer {
+            nonce: secp256k1::PublicKey::from_secret_key(secp, nonce),
+            tag,
+        };
+
+        // k' = k + f, where f is LNPBP-1 tweaking factor for R, so R' = G * k'
+        let factor = tweaking_factor(&container.nonce, &container.tag, msg.as_ref());
+        let mut tweaked_nonce = nonce.clone();
+        tweaked_nonce.add_assign(&factor[..])?;
+        let signature = sign_with_nonce(secp, digest, seckey, &tweaked_nonce)?;
+
+        Ok((signature, container))
+    }
+
+    /// ECDSA `r` value for the public nonce: its x-coordinate reduced modulo
+    /// the curve order
+    fn from_nonce(nonce: &secp256k1::PublicKey) -> Self {
+        SignatureCommitment(reduce_scalar(&nonce.serialize()[1..]))
+    }
+}
+
+impl<MSG> EmbedCommitVerify<MSG> for SignatureCommitment
+where
+    MSG: AsRef<[u8]>,
+{
+    type Container = SignatureContainer;
+    type Error = Error;
+
+    fn embed_commit(container: &Self::Container, msg: &MSG) -> Result<Self, Self::Error> {
+        let cmt = LNPBP1Commitment::embed_commit(
+            &LNPBP1Container {
+                pubkey: container.nonce.clone(),
+                tag: container.tag.clone(),
+                key_origin: None,
+            },
+            msg,
+        )?;
+        Ok(SignatureCommitment::from_nonce(cmt.as_inner()))
+    }
+}
+
+/// Produces ECDSA signature of the digest with the given secret nonce `k`:
+/// `r = x(G * k) mod n`, `s = k^-1 * (z + r * x) mod n`, where the digest
+/// `z` is reduced modulo the curve order in the same way as libsecp256k1
+/// does. Fails with negligible probability when `r` or `s` is zero.
+fn sign_with_nonce<C: secp256k1::Signing>(
+    secp: &Secp256k1<C>,
+    digest: &secp256k1::Message,
+    seckey: &secp256k1::SecretKey,
+    nonce: &secp256k1::SecretKey,
+) -> Result<secp256k1::Signature, Error> {
+    let r = SignatureCommitment::from_nonce(&secp256k1::PublicKey::from_secret_key(secp, nonce));
+
+    let mut s = seckey.clone();
+    s.mul_assign(&r[..])?;
+    s.add_assign(&reduce_scalar(&digest[..]))?;
+    s.mul_assign(&scalar_inverse(nonce)?[..])?;
+
+    let mut compact = [0u8; 64];
+    compact[..32].copy_from_slice(&r[..]);
+    compact[32..].copy_from_slice(&s[..]);
+    let mut signature = secp256k1::Signature::from_compact(&compact)?;
+    signature.normalize_s();
+    Ok(signature)
+}
+
+/// Reduces 256-bit big-endian number modulo curve order. The number is less
+/// than twice the order, so a single subtraction is sufficient.
+fn reduce_scalar(data: &[u8]) -> [u8; 32] {
+    let order = secp256k1::constants::CURVE_ORDER;
+    let mut scalar = [0u8; 32];
+    scalar.copy_from_slice(data);
+    if scalar >= order {
+        let mut borrow = 0u16;
+        for (byte, order) in scalar.iter_mut().zip(order.iter()).rev() {
+            let diff = 0x100 + *byte as u16 - *order as u16 - borrow;
+            *byte = diff as u8;
+            borrow = 1 - (diff >> 8);
+        }
+    }
+    scalar
+}
+
+/// Computes multiplicative inverse of the scalar modulo curve order as
+/// `scalar ^ (n - 2)`
+fn scalar_inverse(scalar: &secp256k1::SecretKey) -> Result<secp256k1::SecretKey, Error> {
+    let mut exp = secp256k1::constants::CURVE_ORDER;
+    exp[31] -= 2;
+    let mut acc: Option<secp256k1::SecretKey> = None;
+    for byte in exp.iter() {
+        for bit in (0..8).rev() {
+            if let Some(ref mut acc) = acc {
+                let square = acc.clone();
+                acc.mul_assign(&square[..])?;
+            }
+            if (*byte >> bit) & 1 == 1 {
+                acc = Some(match acc {
+                    Some(mut acc) => {
+                        acc.mul_assign(&scalar[..])?;
+                        acc
+                    }
+                    None => scalar.clone(),
+                });
+            }
+        }
+    }
+    Ok(acc.expect("Curve order is not zero"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::test::*;
+    use crate::commit_verify::test::*;
+    use bitcoin::hashes::{hex::FromHex, Hash};
+
+    #[test]
+    fn test_reduce_scalar() {
+        let order = secp256k1::constants::CURVE_ORDER;
+        assert_eq!(reduce_scalar(&[0x11; 32]), [0x11; 32]);
+        assert_eq!(reduce_scalar(&order), [0u8; 32]);
+        assert_eq!(
+            reduce_scalar(&[0xFF; 32])[..],
+            Vec::from_hex("000000000000000000000000000000014551231950b75fc4402da1732fc9bebe")
+                .unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn test_sign_with_nonce() {
+        let secp = Secp256k1::new();
+        // Secret key, digest, RFC6979 nonce and the resulting signature
+        let vectors = [
+            (
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                // SHA256("Satoshi Nakamoto")
+                "a0dc65ffca799873cbea0ac274015b9526505daaaed385155425f7337704883e",
+                "8f8a276c19f4149656b280621e358cce24f5f52542772691ee69063b74f15d15",
+                "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8\
+                 2442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5",
+            ),
+            (
+                "1111111111111111111111111111111111111111111111111111111111111111",
+                // Digest exceeding the curve order
+                "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+                "8f35a54a8f53d678c23e030a1a02529d1f4d5322da5fae1619dd0a24d3142a18",
+                "45c1e80d7a6594f02ee06e4539b015822faa10f1e210abfd717cf730527e47e7\
+                 0c795c208cf7025f80e87422b183a74793f2862e4ddf8ef3c1931fd7ff9da148",
+            ),
+        ];
+        for (seckey, digest, nonce, signature) in vectors.iter() {
+            let seckey = secp256k1::SecretKey::from_slice(&Vec::from_hex(seckey).unwrap()).unwrap();
+            let digest = secp256k1::Message::from_slice(&Vec::from_hex(digest).unwrap()).unwrap();
+            let nonce = secp256k1::SecretKey::from_slice(&Vec::from_hex(nonce).unwrap()).unwrap();
+            let signature =
+                secp256k1::Signature::from_compact(&Vec::from_hex(signature).unwrap()).unwrap();
+
+            assert_eq!(
+                sign_with_nonce(&secp, &digest, &seckey, &nonce).unwrap(),
+                signature
+            );
+            // libsecp256k1 signs with RFC6979 nonce
+            assert_eq!(secp.sign(&digest, &seckey), signature);
+        }
+    }
+
+    #[test]
+    fn test_scalar_inverse() {
+        let scalar = secp256k1::SecretKey::from_slice(&[0x5a; 32]).unwrap();
+        let mut one = scalar_inverse(&scalar).unwrap();
+        one.mul_assign(&scalar[..]).unwrap();
+        let mut expected = [0u8; 32];
+        expected[31] = 1;
+        assert_eq!(one[..], expected[..]);
+    }
+
+    #[test]
+    fn test_signature_commitment() {
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        gen_secp_pubkeys(9).into_iter().for_each(|nonce| {
+            embed_commit_verify_suite::<Vec<u8>, SignatureCommitment>(
+                gen_messages(),
+                &SignatureContainer { nonce, tag },
+            );
+        });
+    }
+
+    #[test]
+    fn test_sign_to_contract() {
+        let secp = Secp256k1::new();
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let seckey = secp256k1::SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&secp, &seckey);
+
+        for (no, msg) in gen_messages().into_iter().enumerate() {
+            // Every other digest exceeds the curve order
+            let digest_byte = if no % 2 == 0 { 0x22 } else { 0xFF };
+            let digest = secp256k1::Message::from_slice(&[digest_byte; 32]).unwrap();
+            let nonce = secp256k1::SecretKey::from_slice(&[no as u8 + 1; 32]).unwrap();
+            let (signature, container) =
+                SignatureCommitment::sign(&secp, &digest, &seckey, &nonce, tag, &msg).unwrap();
+
+            assert_eq!(secp.verify(&digest, &signature, &pubkey), Ok(()));
+
+            let commitment = SignatureCommitment::from(signature);
+            assert!(commitment.verify(&container, &msg).unwrap().is_valid());
+            assert!(commitment
+                .verify(&container, &b"other message".to_vec())
+                .unwrap()
+                .is_invalid());
+
+            let (proof, supplement) = container.deconstruct();
+            let container = SignatureContainer::reconstruct(&proof, &supplement, &None).unwrap();
+            assert!(commitment.verify(&container, &msg).unwrap().is_valid());
+        }
+    }
+}
@@ -11,22 +11,147 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use bitcoin::hashes::sha256;
-use bitcoin::secp256k1;
+//! # Taproot commitments
+//!
+//! The intermediate key is tweaked with LNPBP-1 commitment to the message
+//! and then used as BIP-341 internal key: the taproot output key is computed
+//! as `Q = lift_x(P) + G * hashTapTweak(x(P) || script_root)`, where only
+//! the x-coordinate of `Q` is placed into the `scriptPubkey` and the parity of
+//! its y-coordinate goes into the control block of script path spendings.
 
-use super::{Container, Error, LNPBP1Commitment, Proof, ScriptInfo};
+use bitcoin::blockdata::script::{Builder, Script};
+use bitcoin::consensus::encode::serialize;
+use bitcoin::hashes::{sha256, sha256t, Hash, HashEngine};
+use bitcoin::secp256k1::{self, Secp256k1};
+
+use super::{Container, Error, LNPBP1Commitment, Proof, ScriptInfo, ScriptPubkeyComposition};
 use crate::bp::dbc::LNPBP1Container;
-use crate::commit_verify::EmbedCommitVerify;
+use crate::commit_verify::{EmbedCommitVerify, Verification};
+
+/// Leaf version of BIP-342 tapscript
+pub const TAPSCRIPT_LEAF_VERSION: u8 = 0xc0;
+
+/// Midstate of the tagged hash with BIP-341 "TapLeaf" tag
+static MIDSTATE_TAPLEAF: [u8; 32] = [
+    156, 224, 228, 230, 124, 17, 108, 57, 56, 179, 202, 242, 195, 15, 80, 137, 211, 243, 147, 108,
+    71, 99, 110, 96, 125, 179, 62, 234, 221, 198, 240, 201,
+];
+
+/// Midstate of the tagged hash with BIP-341 "TapBranch" tag
+static MIDSTATE_TAPBRANCH: [u8; 32] = [
+    35, 168, 101, 169, 184, 164, 13, 167, 151, 124, 30, 4, 196, 158, 36, 111, 181, 190, 19, 118,
+    157, 36, 201, 183, 181, 131, 181, 212, 168, 210, 38, 210,
+];
+
+/// Midstate of the tagged hash with BIP-341 "TapTweak" tag
+static MIDSTATE_TAPTWEAK: [u8; 32] = [
+    209, 41, 162, 243, 112, 28, 101, 93, 101, 131, 182, 195, 185, 65, 151, 39, 149, 244, 226, 50,
+    148, 253, 84, 244, 162, 174, 141, 133, 71, 202, 89, 11,
+];
+
+tagged_hash!(
+    TapLeafHash,
+    TapLeafTag,
+    MIDSTATE_TAPLEAF,
+    doc = "BIP-341 tagged hash of a script tree leaf"
+);
+
+tagged_hash!(
+    TapBranchHash,
+    TapBranchTag,
+    MIDSTATE_TAPBRANCH,
+    doc = "BIP-341 tagged hash of a script tree branch"
+);
+
+tagged_hash!(
+    TapTweakHash,
+    TapTweakTag,
+    MIDSTATE_TAPTWEAK,
+    doc = "BIP-341 tagged hash tweaking internal key into the taproot output key"
+);
+
+impl TapLeafHash {
+    /// Computes leaf hash for the script with the given leaf version
+    pub fn with_script(script: &Script, leaf_version: u8) -> Self {
+        let mut engine = TapLeafHash::engine();
+        engine.input(&[leaf_version]);
+        engine.input(&serialize(script));
+        TapLeafHash::from_engine(engine)
+    }
+}
+
+impl TapBranchHash {
+    /// Computes branch hash for two child nodes, which are ordered
+    /// lexicographically
+    pub fn with_nodes(a: &sha256::Hash, b: &sha256::Hash) -> Self {
+        let (first, second) = if a[..] <= b[..] { (a, b) } else { (b, a) };
+        let mut engine = TapBranchHash::engine();
+        engine.input(&first[..]);
+        engine.input(&second[..]);
+        TapBranchHash::from_engine(engine)
+    }
+}
+
+/// Computes root of the script tree from the leaf script and merkle path
+/// to it, listing sibling node hashes from the leaf towards the root
+pub fn tapscript_root(script: &Script, leaf_version: u8, path: &[sha256::Hash]) -> sha256::Hash {
+    let leaf =
+        sha256::Hash::from_inner(TapLeafHash::with_script(script, leaf_version).into_inner());
+    path.iter().fold(leaf, |node, sibling| {
+        sha256::Hash::from_inner(TapBranchHash::with_nodes(&node, sibling).into_inner())
+    })
+}
+
+/// Computes BIP-341 taproot output key for the internal key and (optional)
+/// script tree root. Internal key is taken with even y-coordinate; parity of
+/// the returned output key y-coordinate is kept in its serialization prefix.
+pub fn taproot_output_key<C: secp256k1::Verification>(
+    secp: &Secp256k1<C>,
+    internal_key: &secp256k1::PublicKey,
+    script_root: Option<&sha256::Hash>,
+) -> Result<secp256k1::PublicKey, Error> {
+    let mut internal_key = internal_key.serialize();
+    internal_key[0] = 0x02;
+    let mut output_key = secp256k1::PublicKey::from_slice(&internal_key)?;
+
+    let mut engine = TapTweakHash::engine();
+    engine.input(&internal_key[1..]);
+    if let Some(script_root) = script_root {
+        engine.input(&script_root[..]);
+    }
+    let tweak = TapTweakHash::from_engine(engine);
+    output_key.add_exp_assign(secp, &tweak.into_inner())?;
+    Ok(output_key)
+}
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
 #[display_from(Debug)]
 pub struct TaprootContainer {
+    /// Root of the BIP-341 script tree
     pub script_root: sha256::Hash,
     pub intermediate_key: secp256k1::PublicKey,
     /// Single SHA256 hash of the protocol-specific tag
     pub tag: sha256::Hash,
 }
 
+impl TaprootContainer {
+    /// Constructs container for the script tree given by a leaf script and
+    /// merkle path to it
+    pub fn with_script_path(
+        intermediate_key: secp256k1::PublicKey,
+        script: &Script,
+        leaf_version: u8,
+        path: &[sha256::Hash],
+        tag: sha256::Hash,
+    ) -> Self {
+        Self {
+            script_root: tapscript_root(script, leaf_version, path),
+            intermediate_key,
+            tag,
+        }
+    }
+}
+
 impl Container for TaprootContainer {
     /// Out supplement is a protocol-specific tag in its hashed form
     type Supplement = sha256::Hash;
@@ -54,6 +179,7 @@ impl Container for TaprootContainer {
             Proof {
                 pubkey: self.intermediate_key,
                 script_info: ScriptInfo::Taproot(self.script_root),
+                composition: Some(ScriptPubkeyComposition::TapRoot),
             },
             self.tag,
         )
@@ -63,6 +189,7 @@ impl Container for TaprootContainer {
         Proof {
             pubkey: self.intermediate_key.clone(),
             script_info: ScriptInfo::Taproot(self.script_root.clone()),
+            composition: Some(ScriptPubkeyComposition::TapRoot),
         }
     }
 
@@ -70,6 +197,7 @@ impl Container for TaprootContainer {
         Proof {
             pubkey: self.intermediate_key,
             script_info: ScriptInfo::Taproot(self.script_root),
+            composition: Some(ScriptPubkeyComposition::TapRoot),
         }
     }
 }
@@ -78,7 +206,52 @@ impl Container for TaprootContainer {
 #[display_from(Debug)]
 pub struct TaprootCommitment {
     pub script_root: sha256::Hash,
+    /// Intermediate key tweaked with LNPBP-1 commitment, which acts as
+    /// BIP-341 internal key
     pub intermediate_key_commitment: LNPBP1Commitment,
+    /// BIP-341 output key with the parity of y-coordinate
+    pub output_key: secp256k1::PublicKey,
+}
+
+impl TaprootCommitment {
+    /// Returns x-only output key as it is placed into the `scriptPubkey`
+    pub fn output_key_x(&self) -> [u8; 32] {
+        let mut x = [0u8; 32];
+        x.copy_from_slice(&self.output_key.serialize()[1..]);
+        x
+    }
+
+    /// Returns whether the output key y-coordinate is odd
+    #[inline]
+    pub fn output_key_parity(&self) -> bool {
+        self.output_key.serialize()[0] == 0x03
+    }
+
+    /// Generates witness v1 `scriptPubkey` with the output key
+    pub fn script_pubkey(&self) -> Script {
+        Builder::new()
+            .push_int(1)
+            .push_slice(&self.output_key_x())
+            .into_script()
+    }
+
+    /// Verifies that the on-chain P2TR `scriptPubkey` contains the output key
+    /// of this commitment
+    #[inline]
+    pub fn verify_script_pubkey(&self, script_pubkey: &Script) -> Verification {
+        Verification::with_match(*script_pubkey == self.script_pubkey())
+    }
+
+    /// Constructs BIP-341 control block for spending the output with the
+    /// script from the script tree, using merkle path from the script leaf
+    pub fn control_block(&self, leaf_version: u8, path: &[sha256::Hash]) -> Vec<u8> {
+        let mut control_block = Vec::with_capacity(33 + 32 * path.len());
+        control_block.push((leaf_version & 0xfe) | self.output_key_parity() as u8);
+        control_block.extend_from_slice(&self.intermediate_key_commitment.serialize()[1..]);
+        path.iter()
+            .for_each(|node| control_block.extend_from_slice(&node[..]));
+        control_block
+    }
 }
 
 impl<MSG> EmbedCommitVerify<MSG> for TaprootCommitment
@@ -93,12 +266,142 @@ where
             &LNPBP1Container {
                 pubkey: container.intermediate_key.clone(),
                 tag: container.tag.clone(),
+                key_origin: None,
             },
             msg,
         )?;
+        let ec = Secp256k1::<secp256k1::VerifyOnly>::verification_only();
+        let output_key = taproot_output_key(&ec, &cmt, Some(&container.script_root))?;
         Ok(Self {
             script_root: container.script_root,
             intermediate_key_commitment: cmt,
+            output_key,
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::test::*;
+    use crate::commit_verify::test::*;
+    use bitcoin::hashes::hex::FromHex;
+    use std::str::FromStr;
+
+    fn xonly(hex: &str) -> secp256k1::PublicKey {
+        secp256k1::PublicKey::from_str(&format!("02{}", hex)).unwrap()
+    }
+
+    #[test]
+    fn test_tagged_midstates() {
+        use crate::tagged_hash::midstate_for_tag;
+
+        assert_eq!(midstate_for_tag("TapLeaf").into_inner(), MIDSTATE_TAPLEAF);
+        assert_eq!(
+            midstate_for_tag("TapBranch").into_inner(),
+            MIDSTATE_TAPBRANCH
+        );
+        assert_eq!(midstate_for_tag("TapTweak").into_inner(), MIDSTATE_TAPTWEAK);
+    }
+
+    #[test]
+    fn test_bip341_vectors() {
+        let secp = Secp256k1::verification_only();
+
+        // Key path only output
+        let internal_key =
+            xonly("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d");
+        let output_key = taproot_output_key(&secp, &internal_key, None).unwrap();
+        assert_eq!(
+            output_key,
+            secp256k1::PublicKey::from_str(
+                "0353a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343"
+            )
+            .unwrap()
+        );
+
+        // Output with a single-leaf script tree
+        let script = Script::from(
+            Vec::<u8>::from_hex(
+                "20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac",
+            )
+            .unwrap(),
+        );
+        let script_root = tapscript_root(&script, TAPSCRIPT_LEAF_VERSION, &[]);
+        assert_eq!(
+            script_root,
+            sha256::Hash::from_hex(
+                "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"
+            )
+            .unwrap()
+        );
+        let internal_key =
+            xonly("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27");
+        let output_key = taproot_output_key(&secp, &internal_key, Some(&script_root)).unwrap();
+        assert_eq!(
+            output_key,
+            secp256k1::PublicKey::from_str(
+                "03147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_script_tree() {
+        let leaves = (1u8..=3)
+            .map(|no| sha256::Hash::hash(&[no]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            TapBranchHash::with_nodes(&leaves[0], &leaves[1]),
+            TapBranchHash::with_nodes(&leaves[1], &leaves[0])
+        );
+
+        let script = Script::from(vec![0x51]);
+        let leaf = sha256::Hash::from_inner(
+            TapLeafHash::with_script(&script, TAPSCRIPT_LEAF_VERSION).into_inner(),
+        );
+        let branch =
+            sha256::Hash::from_inner(TapBranchHash::with_nodes(&leaf, &leaves[0]).into_inner());
+        let root =
+            sha256::Hash::from_inner(TapBranchHash::with_nodes(&branch, &leaves[1]).into_inner());
+        assert_eq!(
+            tapscript_root(&script, TAPSCRIPT_LEAF_VERSION, &leaves[..2]),
+            root
+        );
+    }
+
+    #[test]
+    fn test_taproot_commitment() {
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let script = Script::from(vec![0x51]);
+        let path = [sha256::Hash::hash(b"sibling")];
+
+        for intermediate_key in gen_secp_pubkeys(9) {
+            let container = TaprootContainer::with_script_path(
+                intermediate_key,
+                &script,
+                TAPSCRIPT_LEAF_VERSION,
+                &path,
+                tag,
+            );
+            embed_commit_verify_suite::<Vec<u8>, TaprootCommitment>(gen_messages(), &container);
+
+            let commitment = TaprootCommitment::embed_commit(&container, &b"msg".to_vec()).unwrap();
+            let script_pubkey = commitment.script_pubkey();
+            assert_eq!(script_pubkey.len(), 34);
+            assert!(script_pubkey.is_witness_program());
+            assert!(commitment.verify_script_pubkey(&script_pubkey).is_valid());
+
+            let other = TaprootCommitment::embed_commit(&container, &b"other".to_vec()).unwrap();
+            assert!(other.verify_script_pubkey(&script_pubkey).is_invalid());
+
+            let control_block = commitment.control_block(TAPSCRIPT_LEAF_VERSION, &path);
+            assert_eq!(control_block.len(), 65);
+            assert_eq!(
+                control_block[0],
+                TAPSCRIPT_LEAF_VERSION | commitment.output_key_parity() as u8
+            );
+        }
+    }
+}
@@ -0,0 +1,375 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! # Tapscript commitments
+//!
+//! LNPBP-2 commitments into the keys of BIP-342 tapscript leaves. Tapscript
+//! uses 32-byte x-only public keys, which are recognized in the positions
+//! where miniscript Tap context places them: before `OP_CHECKSIG`,
+//! `OP_CHECKSIGVERIFY` and `OP_CHECKSIGADD` (the latter is used by
+//! `multi_a`-style multisigs). Public key hashes are recognized in the
+//! `OP_DUP OP_HASH160 <hash> OP_EQUALVERIFY` pattern and are computed over
+//! the x-only key serialization. The version of miniscript in use does not
+//! support Tap context, so the script is parsed into instructions directly:
+//! scripts with truncated data pushes are rejected, as well as scripts with
+//! 32-byte pushes in any other position except hash lock digests (following
+//! `OP_SHA256` or `OP_HASH256`), since such pushes may be keys which would
+//! be left out of the commitment.
+//!
+//! X-only keys are lifted to the points with even y-coordinate; the tweaked
+//! key is placed into the script by its x-coordinate only, so the signer has
+//! to negate the tweaked private key if the tweaked point has odd
+//! y-coordinate, as required by BIP-340.
+//!
+//! The resulting leaf script is used for the script tree construction with
+//! [super::TaprootContainer::with_script_path].
+
+use bitcoin::blockdata::opcodes::all::{
+    OP_CHECKSIG, OP_CHECKSIGVERIFY, OP_DUP, OP_EQUALVERIFY, OP_HASH160, OP_HASH256, OP_SHA256,
+};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1;
+use bitcoin::{PubkeyHash, Script};
+use std::collections::HashSet;
+
+use super::tweak::{script_items, ScriptItem};
+use super::{Error, KeysetContainer, LNPBP2Commitment, TapLeafHash, TAPSCRIPT_LEAF_VERSION};
+use crate::commit_verify::EmbedCommitVerify;
+
+/// BIP-342 `OP_CHECKSIGADD` opcode, which is not known to the version of
+/// bitcoin library in use
+const OP_CHECKSIGADD: u8 = 0xba;
+
+/// Serializes public key in the BIP-340 x-only form
+pub fn xonly(pubkey: &secp256k1::PublicKey) -> [u8; 32] {
+    let mut x = [0u8; 32];
+    x.copy_from_slice(&pubkey.serialize()[1..]);
+    x
+}
+
+/// Lifts x-only public key into the point with even y-coordinate
+pub fn lift_x(x: &[u8]) -> Result<secp256k1::PublicKey, secp256k1::Error> {
+    let mut data = [0x02u8; 33];
+    if x.len() != 32 {
+        Err(secp256k1::Error::InvalidPublicKey)?
+    }
+    data[1..].copy_from_slice(x);
+    secp256k1::PublicKey::from_slice(&data)
+}
+
+/// Computes tapscript public key hash, which is HASH160 of the x-only key
+#[inline]
+pub fn xonly_hash(pubkey: &secp256k1::PublicKey) -> PubkeyHash {
+    PubkeyHash::hash(&xonly(pubkey))
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct TapscriptContainer {
+    /// Script of the tapscript leaf
+    pub script: Script,
+    /// Key which must contain the commitment; only its x-coordinate is used
+    pub pubkey: secp256k1::PublicKey,
+    /// Single SHA256 hash of the protocol-specific tag
+    pub tag: sha256::Hash,
+}
+
+wrapper!(
+    TapscriptCommitment,
+    Script,
+    doc = "Tapscript leaf with the x-only key tweaked with LNPBP-2 commitment",
+    derive = [PartialEq, Eq, Hash]
+);
+
+impl TapscriptCommitment {
+    /// Computes BIP-341 hash of the leaf with the committed script
+    #[inline]
+    pub fn tapleaf_hash(&self) -> TapLeafHash {
+        TapLeafHash::with_script(&self.0, TAPSCRIPT_LEAF_VERSION)
+    }
+}
+
+impl<MSG> EmbedCommitVerify<MSG> for TapscriptCommitment
+where
+    MSG: AsRef<[u8]>,
+{
+    type Container = TapscriptContainer;
+    type Error = Error;
+
+    // #[consensus_critical]
+    // #[standard_critical("LNPBP-2")]
+    fn embed_commit(container: &Self::Container, msg: &MSG) -> Result<Self, Self::Error> {
+        let host = lift_x(&xonly(&container.pubkey))?;
+        let host_hash = xonly_hash(&host);
+        let (keys, hashes) = tapscript_keys(&container.script)?;
+        if keys.is_empty() && hashes.is_empty() {
+            Err(Error::LockscriptContainsNoKeys)?;
+        }
+
+        // ! [CONSENSUS-CRITICAL]:
+        // ! [STANDARD-CRITICAL]: Repeated keys are committed to only once
+        let mut keyset = keys
+            .iter()
+            .map(|(_, pubkey)| *pubkey)
+            .collect::<HashSet<_>>();
+        let key_hashes = keyset
+            .iter()
+            .map(xonly_hash)
+            .chain(vec![host_hash])
+            .collect::<HashSet<_>>();
+        let mut unknown = hashes
+            .iter()
+            .map(|(_, hash)| *hash)
+            .filter(|hash| !key_hashes.contains(hash))
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            unknown.sort();
+            unknown.dedup();
+            Err(Error::LockscriptContainsUnknownHashes {
+                hashes: unknown,
+                keys_found: keyset.len(),
+            })?
+        }
+        if !keyset.remove(&host) && !hashes.iter().any(|(_, hash)| *hash == host_hash) {
+            Err(Error::LockscriptKeyNotFound {
+                pubkey: container.pubkey,
+                keys_found: keyset.len(),
+                uncompressed_found: false,
+            })?
+        }
+
+        let tweaked = LNPBP2Commitment::embed_commit(
+            &KeysetContainer {
+                pubkey: host,
+                keyset,
+                tag: container.tag,
+            },
+            msg,
+        )?;
+
+        // ! [CONSENSUS-CRITICAL]:
+        // ! [STANDARD-CRITICAL]: All occurrences of the host key and its hash
+        //                        are replaced in place, keeping the rest of
+        //                        the script intact
+        let mut script = container.script.to_bytes();
+        for (offset, _) in keys.into_iter().filter(|(_, pubkey)| *pubkey == host) {
+            script[offset..offset + 32].copy_from_slice(&xonly(&tweaked));
+        }
+        for (offset, _) in hashes.into_iter().filter(|(_, hash)| *hash == host_hash) {
+            script[offset..offset + 20].copy_from_slice(&xonly_hash(&tweaked)[..]);
+        }
+
+        Ok(TapscriptCommitment(Script::from(script)))
+    }
+}
+
+/// Extracts x-only keys (lifted to even y-coordinate) and key hashes from
+/// the tapscript together with their byte offsets in the script
+fn tapscript_keys(
+    script: &Script,
+) -> Result<(Vec<(usize, secp256k1::PublicKey)>, Vec<(usize, PubkeyHash)>), Error> {
+    let checksig = [
+        OP_CHECKSIG.into_u8(),
+        OP_CHECKSIGVERIFY.into_u8(),
+        OP_CHECKSIGADD,
+    ];
+    let hash_lock = [OP_SHA256.into_u8(), OP_HASH256.into_u8()];
+
+    let items = script_items(script)?;
+    let opcode_at = |index: Option<usize>, opcodes: &[u8]| match index.and_then(|i| items.get(i)) {
+        Some(ScriptItem::Opcode(opcode)) => opcodes.contains(opcode),
+        _ => false,
+    };
+    let mut keys = vec![];
+    let mut hashes = vec![];
+    for (index, item) in items.iter().enumerate() {
+        let (offset, data) = match item {
+            ScriptItem::Push(offset, data) => (*offset, *data),
+            ScriptItem::Opcode(_) => continue,
+        };
+        let next = Some(index + 1);
+        match data.len() {
+            32 if opcode_at(next, &checksig) => keys.push((offset, lift_x(data)?)),
+            32 if opcode_at(index.checked_sub(1), &hash_lock) => {}
+            32 => Err(Error::TapscriptUnrecognizedPush(offset))?,
+            20 if opcode_at(index.checked_sub(2), &[OP_DUP.into_u8()])
+                && opcode_at(index.checked_sub(1), &[OP_HASH160.into_u8()])
+                && opcode_at(next, &[OP_EQUALVERIFY.into_u8()]) =>
+            {
+                hashes.push((offset, PubkeyHash::from_slice(data).expect("20-byte slice")));
+            }
+            _ => {}
+        }
+    }
+    Ok((keys, hashes))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::test::gen_secp_pubkeys;
+    use crate::commit_verify::test::{embed_commit_verify_suite, gen_messages};
+    use bitcoin::blockdata::opcodes::all::OP_NUMEQUAL;
+    use bitcoin::blockdata::script::Builder;
+
+    fn multi_a(keys: &[secp256k1::PublicKey], threshold: i64) -> Script {
+        let mut builder = Builder::new()
+            .push_slice(&xonly(&keys[0]))
+            .push_opcode(OP_CHECKSIG);
+        for key in &keys[1..] {
+            builder = builder
+                .push_slice(&xonly(key))
+                .push_opcode(OP_CHECKSIGADD.into());
+        }
+        builder
+            .push_int(threshold)
+            .push_opcode(OP_NUMEQUAL)
+            .into_script()
+    }
+
+    #[test]
+    fn test_tapscript_multi_a() {
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let keys = gen_secp_pubkeys(3);
+        let script = multi_a(&keys, 2);
+        let container = TapscriptContainer {
+            script: script.clone(),
+            pubkey: keys[1],
+            tag,
+        };
+        embed_commit_verify_suite::<Vec<u8>, TapscriptCommitment>(gen_messages(), &container);
+
+        let commitment = TapscriptCommitment::embed_commit(&container, &b"msg".to_vec()).unwrap();
+        assert_eq!(commitment.len(), script.len());
+        let (committed, _) = tapscript_keys(&commitment).unwrap();
+        assert_eq!(committed[0].1, lift_x(&xonly(&keys[0])).unwrap());
+        assert_ne!(committed[1].1, lift_x(&xonly(&keys[1])).unwrap());
+        assert_eq!(committed[2].1, lift_x(&xonly(&keys[2])).unwrap());
+        assert_ne!(
+            commitment.tapleaf_hash(),
+            TapLeafHash::with_script(&script, TAPSCRIPT_LEAF_VERSION)
+        );
+
+        let container = TapscriptContainer {
+            pubkey: gen_secp_pubkeys(4)[3],
+            ..container
+        };
+        assert!(matches!(
+            TapscriptCommitment::embed_commit(&container, &b"msg".to_vec()),
+            Err(Error::LockscriptKeyNotFound { keys_found: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_tapscript_key_hash() {
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let keys = gen_secp_pubkeys(2);
+        let pk_h = |key: &secp256k1::PublicKey| {
+            Builder::new()
+                .push_opcode(OP_DUP)
+                .push_opcode(OP_HASH160)
+                .push_slice(&xonly_hash(key)[..])
+                .push_opcode(OP_EQUALVERIFY)
+                .push_opcode(OP_CHECKSIG)
+                .into_script()
+        };
+        let container = TapscriptContainer {
+            script: pk_h(&keys[0]),
+            pubkey: keys[0],
+            tag,
+        };
+        let commitment = TapscriptCommitment::embed_commit(&container, &b"msg".to_vec()).unwrap();
+        let (_, hashes) = tapscript_keys(&commitment).unwrap();
+        assert_eq!(hashes.len(), 1);
+        assert_ne!(hashes[0].1, xonly_hash(&keys[0]));
+        assert!(commitment
+            .verify(&container, &b"msg".to_vec())
+            .unwrap()
+            .is_valid());
+
+        let container = TapscriptContainer {
+            script: pk_h(&keys[1]),
+            ..container
+        };
+        assert!(matches!(
+            TapscriptCommitment::embed_commit(&container, &b"msg".to_vec()),
+            Err(Error::LockscriptContainsUnknownHashes { .. })
+        ));
+
+        let container = TapscriptContainer {
+            script: Script::from(vec![0x51]),
+            ..container
+        };
+        assert_eq!(
+            TapscriptCommitment::embed_commit(&container, &b"msg".to_vec()),
+            Err(Error::LockscriptContainsNoKeys)
+        );
+    }
+
+    #[test]
+    fn test_tapscript_parsing() {
+        use bitcoin::blockdata::opcodes::all::{OP_DROP, OP_EQUAL, OP_PUSHDATA1};
+
+        let keys = gen_secp_pubkeys(2);
+        let hash_lock = Builder::new()
+            .push_opcode(OP_SHA256)
+            .push_slice(&sha256::Hash::hash(b"preimage")[..])
+            .push_opcode(OP_EQUALVERIFY)
+            .push_slice(&xonly(&keys[0]))
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        let (found, _) = tapscript_keys(&hash_lock).unwrap();
+        assert_eq!(found, vec![(36, lift_x(&xonly(&keys[0])).unwrap())]);
+
+        // Key which is not consumed by a signature check can't be told apart
+        // from arbitrary data
+        let dropped = Builder::new()
+            .push_slice(&xonly(&keys[1]))
+            .push_opcode(OP_DROP)
+            .push_slice(&xonly(&keys[0]))
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        assert_eq!(
+            tapscript_keys(&dropped),
+            Err(Error::TapscriptUnrecognizedPush(1))
+        );
+        let compared = Builder::new()
+            .push_slice(&xonly(&keys[1]))
+            .push_opcode(OP_EQUAL)
+            .into_script();
+        assert_eq!(
+            tapscript_keys(&compared),
+            Err(Error::TapscriptUnrecognizedPush(1))
+        );
+
+        // Truncated pushes, including truncated length prefixes
+        let mut truncated = hash_lock.to_bytes();
+        truncated.pop();
+        truncated.pop();
+        assert_eq!(
+            tapscript_keys(&Script::from(truncated)),
+            Err(Error::TruncatedPush(35))
+        );
+        for data in &[
+            vec![0x51, OP_PUSHDATA1.into_u8()],
+            vec![0x51, OP_PUSHDATA1.into_u8(), 0x01],
+            vec![0x51, 0x4d, 0x01],
+            vec![0x51, 0x4e, 0x00, 0x00, 0x00],
+        ] {
+            assert_eq!(
+                tapscript_keys(&Script::from(data.clone())),
+                Err(Error::TruncatedPush(1))
+            );
+        }
+    }
+}
@@ -0,0 +1,321 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! # Taproot script trees
+//!
+//! Builds BIP-341 script tree out of the leaf scripts with weights, which
+//! reflect the expected probability of spending with the script. Leaves are
+//! combined in Huffman order, so the scripts with larger weights get shorter
+//! merkle paths and cheaper control blocks. Ties are resolved in the order
+//! the leaves were provided, so the same set of leaves always produces the
+//! same tree.
+
+use bitcoin::blockdata::script::Script;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::{TapBranchHash, TapLeafHash, TaprootCommitment, TaprootContainer};
+
+/// Maximum depth of the script tree allowed by BIP-341
+pub const TAPROOT_MAX_DEPTH: usize = 128;
+
+/// Errors constructing taproot script tree
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display_from(Debug)]
+pub enum TaptreeError {
+    /// Script tree must contain at least a single leaf
+    NoLeaves,
+
+    /// Leaf version must be even, since its lowest bit is used in the control
+    /// block for the output key parity
+    InvalidLeafVersion(u8),
+
+    /// Weights of the leaves produce the tree deeper than allowed by BIP-341
+    MaxDepthExceeded(usize),
+}
+
+/// Leaf of the taproot script tree
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct TapScriptLeaf {
+    pub script: Script,
+    pub leaf_version: u8,
+    /// Relative probability of spending with this leaf
+    pub weight: u32,
+}
+
+impl TapScriptLeaf {
+    /// Constructs BIP-342 tapscript leaf
+    #[inline]
+    pub fn with_tapscript(script: Script, weight: u32) -> Self {
+        Self {
+            script,
+            leaf_version: super::TAPSCRIPT_LEAF_VERSION,
+            weight,
+        }
+    }
+
+    /// Computes hash of the leaf, which is a node of the script tree
+    #[inline]
+    pub fn node_hash(&self) -> sha256::Hash {
+        sha256::Hash::from_inner(
+            TapLeafHash::with_script(&self.script, self.leaf_version).into_inner(),
+        )
+    }
+}
+
+/// BIP-341 script tree with merkle paths for each of its leaves
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display_from(Debug)]
+pub struct TaprootScriptTree {
+    leaves: Vec<TapScriptLeaf>,
+    /// Merkle paths for each of the leaves, listing sibling node hashes from
+    /// the leaf towards the root
+    paths: Vec<Vec<sha256::Hash>>,
+    script_root: sha256::Hash,
+}
+
+impl TaprootScriptTree {
+    /// Builds tree out of BIP-342 tapscripts with the given weights
+    pub fn with_tapscripts(
+        scripts: impl IntoIterator<Item = (Script, u32)>,
+    ) -> Result<Self, TaptreeError> {
+        Self::with_leaves(
+            scripts
+                .into_iter()
+                .map(|(script, weight)| TapScriptLeaf::with_tapscript(script, weight))
+                .collect(),
+        )
+    }
+
+    /// Builds tree out of the leaves, combining the two nodes with the
+    /// smallest weights until a single root node remains
+    pub fn with_leaves(leaves: Vec<TapScriptLeaf>) -> Result<Self, TaptreeError> {
+        if leaves.is_empty() {
+            Err(TaptreeError::NoLeaves)?
+        }
+        if let Some(leaf) = leaves.iter().find(|leaf| leaf.leaf_version & 1 != 0) {
+            Err(TaptreeError::InvalidLeafVersion(leaf.leaf_version))?
+        }
+
+        let mut paths = vec![Vec::<sha256::Hash>::new(); leaves.len()];
+        // Nodes are ordered by weight and then by the creation sequence
+        let mut heap = BinaryHeap::new();
+        let mut nodes = Vec::with_capacity(leaves.len() * 2 - 1);
+        for (index, leaf) in leaves.iter().enumerate() {
+            heap.push(Reverse((leaf.weight as u64, nodes.len())));
+            nodes.push((leaf.node_hash(), vec![index]));
+        }
+        while heap.len() > 1 {
+            let Reverse((weight_a, a)) = heap.pop().expect("heap has more than one node");
+            let Reverse((weight_b, b)) = heap.pop().expect("heap has more than one node");
+            let (hash_a, leaves_a) = nodes[a].clone();
+            let (hash_b, leaves_b) = nodes[b].clone();
+            leaves_a.iter().for_each(|index| paths[*index].push(hash_b));
+            leaves_b.iter().for_each(|index| paths[*index].push(hash_a));
+            let hash =
+                sha256::Hash::from_inner(TapBranchHash::with_nodes(&hash_a, &hash_b).into_inner());
+            heap.push(Reverse((weight_a + weight_b, nodes.len())));
+            nodes.push((hash, [leaves_a, leaves_b].concat()));
+        }
+
+        let depth = paths.iter().map(Vec::len).max().unwrap_or_default();
+        if depth > TAPROOT_MAX_DEPTH {
+            Err(TaptreeError::MaxDepthExceeded(depth))?
+        }
+        let Reverse((_, root)) = heap.pop().expect("heap always contains root node");
+        Ok(Self {
+            leaves,
+            paths,
+            script_root: nodes[root].0,
+        })
+    }
+
+    /// Returns merkle root of the script tree
+    #[inline]
+    pub fn script_root(&self) -> sha256::Hash {
+        self.script_root
+    }
+
+    /// Returns leaves in the order they were provided to the builder
+    #[inline]
+    pub fn leaves(&self) -> &[TapScriptLeaf] {
+        &self.leaves
+    }
+
+    /// Returns index of the first leaf with the given script
+    pub fn leaf_index(&self, script: &Script) -> Option<usize> {
+        self.leaves.iter().position(|leaf| leaf.script == *script)
+    }
+
+    /// Returns merkle path for the leaf with the given index
+    #[inline]
+    pub fn merkle_path(&self, index: usize) -> Option<&[sha256::Hash]> {
+        self.paths.get(index).map(Vec::as_slice)
+    }
+
+    /// Constructs DBC container committing to the tree
+    #[inline]
+    pub fn container(
+        &self,
+        intermediate_key: secp256k1::PublicKey,
+        tag: sha256::Hash,
+    ) -> TaprootContainer {
+        TaprootContainer {
+            script_root: self.script_root,
+            intermediate_key,
+            tag,
+        }
+    }
+
+    /// Constructs control block for spending the output created with the
+    /// commitment using the leaf with the given index. Returns `None` if the
+    /// index is out of range or the commitment is not made to this tree.
+    pub fn control_block(&self, commitment: &TaprootCommitment, index: usize) -> Option<Vec<u8>> {
+        if commitment.script_root != self.script_root {
+            return None;
+        }
+        let leaf = self.leaves.get(index)?;
+        Some(commitment.control_block(leaf.leaf_version, &self.paths[index]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::dbc::{tapscript_root, TAPSCRIPT_LEAF_VERSION};
+    use crate::bp::test::gen_secp_pubkeys;
+    use crate::commit_verify::EmbedCommitVerify;
+
+    fn scripts(n: u8) -> Vec<Script> {
+        (1..=n).map(|no| Script::from(vec![0x50 + no])).collect()
+    }
+
+    #[test]
+    fn test_tree_structure() {
+        let scripts = scripts(4);
+        let tree = TaprootScriptTree::with_tapscripts(vec![(scripts[0].clone(), 7)]).unwrap();
+        assert_eq!(tree.merkle_path(0), Some(&[][..]));
+        assert_eq!(tree.script_root(), tree.leaves()[0].node_hash());
+        assert_eq!(
+            tree.script_root(),
+            tapscript_root(&scripts[0], TAPSCRIPT_LEAF_VERSION, &[])
+        );
+
+        let tree = TaprootScriptTree::with_tapscripts(vec![
+            (scripts[0].clone(), 1),
+            (scripts[1].clone(), 1),
+            (scripts[2].clone(), 2),
+        ])
+        .unwrap();
+        let hashes = tree
+            .leaves()
+            .iter()
+            .map(TapScriptLeaf::node_hash)
+            .collect::<Vec<_>>();
+        let branch = sha256::Hash::from_inner(
+            TapBranchHash::with_nodes(&hashes[0], &hashes[1]).into_inner(),
+        );
+        assert_eq!(tree.merkle_path(0), Some(&[hashes[1], hashes[2]][..]));
+        assert_eq!(tree.merkle_path(1), Some(&[hashes[0], hashes[2]][..]));
+        assert_eq!(tree.merkle_path(2), Some(&[branch][..]));
+        assert_eq!(tree.merkle_path(3), None);
+        assert_eq!(tree.leaf_index(&scripts[2]), Some(2));
+        assert_eq!(tree.leaf_index(&scripts[3]), None);
+
+        let weights = [100u32, 1, 1, 10];
+        let tree = TaprootScriptTree::with_tapscripts(
+            scripts.iter().cloned().zip(weights.iter().cloned()),
+        )
+        .unwrap();
+        for (index, leaf) in tree.leaves().iter().enumerate() {
+            let path = tree.merkle_path(index).unwrap();
+            assert_eq!(
+                tapscript_root(&leaf.script, leaf.leaf_version, path),
+                tree.script_root()
+            );
+        }
+        assert_eq!(tree.merkle_path(0).unwrap().len(), 1);
+        assert_eq!(tree.merkle_path(3).unwrap().len(), 2);
+        assert_eq!(tree.merkle_path(1).unwrap().len(), 3);
+        assert_eq!(
+            TaprootScriptTree::with_tapscripts(
+                scripts.iter().cloned().zip(weights.iter().cloned())
+            )
+            .unwrap(),
+            tree
+        );
+    }
+
+    #[test]
+    fn test_tree_errors() {
+        assert_eq!(
+            TaprootScriptTree::with_tapscripts(vec![]),
+            Err(TaptreeError::NoLeaves)
+        );
+        assert_eq!(
+            TaprootScriptTree::with_leaves(vec![TapScriptLeaf {
+                script: Script::new(),
+                leaf_version: 0xc1,
+                weight: 1,
+            }]),
+            Err(TaptreeError::InvalidLeafVersion(0xc1))
+        );
+
+        // Fibonacci weights produce maximally unbalanced tree
+        let mut weights = vec![1u32, 1];
+        while weights.len() < 40 {
+            weights.push(weights[weights.len() - 1] + weights[weights.len() - 2]);
+        }
+        let leaves = (0..40u32).map(|no| Script::from(no.to_le_bytes().to_vec()));
+        let tree = TaprootScriptTree::with_tapscripts(leaves.zip(weights)).unwrap();
+        assert_eq!(tree.merkle_path(0).unwrap().len(), 39);
+    }
+
+    #[test]
+    fn test_tree_commitment() {
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let scripts = scripts(3);
+        let tree =
+            TaprootScriptTree::with_tapscripts(scripts.iter().cloned().zip(vec![1, 2, 3])).unwrap();
+        let other = TaprootScriptTree::with_tapscripts(vec![(scripts[0].clone(), 1)]).unwrap();
+
+        for intermediate_key in gen_secp_pubkeys(3) {
+            let container = tree.container(intermediate_key, tag);
+            let index = tree.leaf_index(&scripts[1]).unwrap();
+            assert_eq!(
+                container,
+                TaprootContainer::with_script_path(
+                    intermediate_key,
+                    &scripts[1],
+                    TAPSCRIPT_LEAF_VERSION,
+                    tree.merkle_path(index).unwrap(),
+                    tag,
+                )
+            );
+
+            let commitment = TaprootCommitment::embed_commit(&container, &b"msg".to_vec()).unwrap();
+            let control_block = tree.control_block(&commitment, index).unwrap();
+            let path = tree.merkle_path(index).unwrap();
+            assert_eq!(control_block.len(), 33 + 32 * path.len());
+            assert_eq!(
+                control_block,
+                commitment.control_block(TAPSCRIPT_LEAF_VERSION, path)
+            );
+            assert_eq!(tree.control_block(&commitment, 3), None);
+            assert_eq!(other.control_block(&commitment, 0), None);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! LNPBP-2 commitments made by a party controlling only some of the keys in
+//! the keyset (m-of-n multisig). Only the host key is tweaked by LNPBP-2, so
+//! the rest of the keys, including the ones controlled by other parties,
+//! remain untweaked.
+//!
+//! Host keys aggregated from the keys of several participants are not
+//! supported: plain summation of the participant keys is vulnerable to
+//! rogue-key attacks, and MuSig key aggregation is not available yet.
+
+use bitcoin::secp256k1;
+use std::collections::HashSet;
+
+use super::{Error, KeysetContainer, LNPBP2Commitment, PreparedTweak};
+use crate::bp::LockScript;
+use crate::commit_verify::{InvalidReason, Verification};
+
+/// Record of the threshold LNPBP-2 commitment, tracking which key has
+/// received the tweak
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display_from(Debug)]
+pub struct ThresholdTweak {
+    /// Tweak of the host key
+    pub prepared: PreparedTweak,
+    /// Keys from the keyset which are left untweaked
+    pub untweaked: HashSet<secp256k1::PublicKey>,
+    /// Keys from the keyset which are not controlled by the committing party
+    pub foreign: HashSet<secp256k1::PublicKey>,
+    /// Host key which private key has received the tweak, or `None` if the
+    /// tweak is not applied yet
+    pub tweaked_key: Option<secp256k1::PublicKey>,
+}
+
+impl KeysetContainer {
+    /// Prepares LNPBP-2 commitment by a party controlling the host key and
+    /// some of the keyset keys. Fails if the host key is not controlled or
+    /// if the number of controlled keys is below the `threshold`.
+    pub fn prepare_threshold_commit<MSG>(
+        &self,
+        msg: &MSG,
+        controlled: &HashSet<secp256k1::PublicKey>,
+        threshold: usize,
+    ) -> Result<ThresholdTweak, Error>
+    where
+        MSG: AsRef<[u8]>,
+    {
+        if !controlled.contains(&self.pubkey) {
+            Err(Error::HostKeyNotControlled)?
+        }
+        // Host key may be present in the keyset as well, but it is counted
+        // only once
+        let count = 1 + self
+            .keyset
+            .intersection(controlled)
+            .filter(|key| **key != self.pubkey)
+            .count();
+        if count < threshold {
+            Err(Error::ThresholdNotMet(count, threshold))?
+        }
+        Ok(ThresholdTweak {
+            prepared: self.prepare_commit(msg)?,
+            untweaked: self.keyset.clone(),
+            foreign: self.keyset.difference(controlled).cloned().collect(),
+            tweaked_key: None,
+        })
+    }
+}
+
+impl ThresholdTweak {
+    /// Returns commitment produced by the tweak
+    #[inline]
+    pub fn commitment(&self) -> LNPBP2Commitment {
+        LNPBP2Commitment::from_inner(self.prepared.tweaked_pubkey)
+    }
+
+    /// Applies tweak to the private key of the host key, recording the
+    /// tweaked key. Fails if the tweak was already applied.
+    pub fn apply(&mut self, seckey: &mut secp256k1::SecretKey) -> Result<(), Error> {
+        if self.tweaked_key.is_some() {
+            Err(Error::TweakAlreadyApplied)?
+        }
+        self.prepared.apply(seckey)?;
+        self.tweaked_key = Some(self.prepared.pubkey);
+        Ok(())
+    }
+
+    /// Verifies set of keys used by the final script against the commitment.
+    /// The set must contain the tweaked host key and must not contain the
+    /// original one; the rest of the keys must come from the keyset in their
+    /// untweaked form. Foreign keys absent from the script are tolerated,
+    /// since not all of the keyset keys are required to be present in the
+    /// threshold scripts.
+    pub fn verify_keys(&self, keys: &HashSet<secp256k1::PublicKey>) -> Verification {
+        if !keys.contains(&self.prepared.tweaked_pubkey) || keys.contains(&self.prepared.pubkey) {
+            return Verification::Invalid(InvalidReason::CommitmentMismatch);
+        }
+        if keys
+            .iter()
+            .filter(|key| **key != self.prepared.tweaked_pubkey)
+            .all(|key| self.untweaked.contains(key))
+        {
+            Verification::Valid
+        } else {
+            Verification::Invalid(InvalidReason::ContainerMismatch)
+        }
+    }
+
+    /// Verifies commitment against the keys extracted from the final
+    /// lockscript
+    pub fn verify_script(&self, script: &LockScript) -> Result<Verification, Error> {
+        Ok(self.verify_keys(&script.extract_pubkeyset()?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commit_verify::EmbedCommitVerify;
+    use bitcoin::hashes::{sha256, Hash};
+    use bitcoin::secp256k1::Secp256k1;
+
+    fn keys(no: u8) -> (secp256k1::SecretKey, secp256k1::PublicKey) {
+        let ec = Secp256k1::<secp256k1::SignOnly>::signing_only();
+        let seckey = secp256k1::SecretKey::from_slice(&[no; 32]).unwrap();
+        (seckey, secp256k1::PublicKey::from_secret_key(&ec, &seckey))
+    }
+
+    #[test]
+    fn test_threshold_commit() {
+        let msg = b"message".to_vec();
+        let (mut seckey, pubkey) = keys(1);
+        let container = KeysetContainer {
+            pubkey,
+            keyset: (2..4).map(|no| keys(no).1).collect(),
+            tag: sha256::Hash::hash(b"TEST_TAG2"),
+        };
+        let controlled: HashSet<_> = (1..3).map(|no| keys(no).1).collect();
+
+        assert_eq!(
+            container.prepare_threshold_commit(&msg, &controlled, 3),
+            Err(Error::ThresholdNotMet(2, 3))
+        );
+        assert_eq!(
+            container.prepare_threshold_commit(&msg, &(2..4).map(|no| keys(no).1).collect(), 2),
+            Err(Error::HostKeyNotControlled)
+        );
+        let with_host = KeysetContainer {
+            keyset: (1..4).map(|no| keys(no).1).collect(),
+            ..container.clone()
+        };
+        assert_eq!(
+            with_host.prepare_threshold_commit(&msg, &controlled, 3),
+            Err(Error::ThresholdNotMet(2, 3))
+        );
+
+        let mut tweak = container
+            .prepare_threshold_commit(&msg, &controlled, 2)
+            .unwrap();
+        assert_eq!(tweak.foreign, (3..4).map(|no| keys(no).1).collect());
+        assert_eq!(
+            tweak.commitment(),
+            LNPBP2Commitment::embed_commit(&container, &msg).unwrap()
+        );
+
+        tweak.apply(&mut seckey).unwrap();
+        assert_eq!(tweak.tweaked_key, Some(pubkey));
+        assert_eq!(tweak.apply(&mut seckey), Err(Error::TweakAlreadyApplied));
+
+        let tweaked = *tweak.commitment();
+        let (_, key2) = keys(2);
+        let (_, key3) = keys(3);
+        let (_, key4) = keys(4);
+        assert!(tweak
+            .verify_keys(&vec![tweaked, key2, key3].into_iter().collect())
+            .is_valid());
+        assert!(tweak
+            .verify_keys(&vec![tweaked, key2].into_iter().collect())
+            .is_valid());
+        assert_eq!(
+            tweak
+                .verify_keys(&vec![tweaked, key2, key4].into_iter().collect())
+                .reason(),
+            Some(InvalidReason::ContainerMismatch)
+        );
+        assert_eq!(
+            tweak
+                .verify_keys(&vec![pubkey, key2, key3].into_iter().collect())
+                .reason(),
+            Some(InvalidReason::CommitmentMismatch)
+        );
+    }
+}
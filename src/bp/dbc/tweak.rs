@@ -0,0 +1,432 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Two-phase embedding of LNPBP-1 and LNPBP-2 commitments. During the
+//! *prepare* phase the host computes tweaking factor and all public data of
+//! the commitment; during the *apply* phase the tweak is added to the private
+//! key, which may happen on a hardware device exposing only "add scalar to
+//! private key" operation. The prepared tweak is strict-encodable, so it can
+//! be passed between the phases without revealing any secret data.
+//!
+//! For the lockscript commitments the prepared tweak is accompanied with the
+//! byte offsets of the tweaked keys and key hashes inside the committed
+//! script, so external signers can check which parts of the script were
+//! changed by the commitment.
+
+use bitcoin::blockdata::opcodes::all::{OP_PUSHDATA1, OP_PUSHDATA2, OP_PUSHDATA4};
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::{self, Secp256k1};
+use bitcoin::Script;
+
+use super::keyset::keyset_tweaking_factor;
+use super::lockscript::pubkey_hash;
+use super::pubkey::tweaking_factor;
+use super::{
+    ChangeKind, Error, KeyOrigin, KeysetContainer, LNPBP1Commitment, LNPBP1Container,
+    LockscriptCommitment, LockscriptContainer,
+};
+use crate::commit_verify::EmbedCommitVerify;
+
+/// Public data of the commitment prepared by the host, which has to be
+/// applied to the private key corresponding to the original public key
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct PreparedTweak {
+    /// The original public key
+    pub pubkey: secp256k1::PublicKey,
+    /// BIP-32 origin of the original public key, if known, allowing device to
+    /// find the private key
+    pub key_origin: Option<KeyOrigin>,
+    /// Tweaking factor which must be added to the private key
+    pub tweak: [u8; 32],
+    /// Resulting tweaked public key containing the commitment
+    pub tweaked_pubkey: secp256k1::PublicKey,
+}
+
+impl PreparedTweak {
+    fn with(
+        pubkey: secp256k1::PublicKey,
+        key_origin: Option<KeyOrigin>,
+        tweak: [u8; 32],
+    ) -> Result<Self, secp256k1::Error> {
+        let ec = Secp256k1::<secp256k1::VerifyOnly>::verification_only();
+        let mut tweaked_pubkey = pubkey;
+        tweaked_pubkey.add_exp_assign(&ec, &tweak)?;
+        Ok(Self {
+            pubkey,
+            key_origin,
+            tweak,
+            tweaked_pubkey,
+        })
+    }
+
+    /// Returns commitment produced by the tweak. Since all of the commitment
+    /// data are public, it is known to the host without applying the tweak.
+    #[inline]
+    pub fn commitment(&self) -> LNPBP1Commitment {
+        LNPBP1Commitment::from_inner(self.tweaked_pubkey)
+    }
+
+    /// Applies tweak to the private key corresponding to the original public
+    /// key. Fails if the private key does not match the original public key,
+    /// leaving the key unmodified.
+    pub fn apply(&self, seckey: &mut secp256k1::SecretKey) -> Result<(), Error> {
+        let ec = Secp256k1::<secp256k1::SignOnly>::signing_only();
+        if secp256k1::PublicKey::from_secret_key(&ec, seckey) != self.pubkey {
+            Err(Error::SecretKeyMismatch)?
+        }
+        let mut tweaked = *seckey;
+        tweaked.add_assign(&self.tweak)?;
+        if secp256k1::PublicKey::from_secret_key(&ec, &tweaked) != self.tweaked_pubkey {
+            Err(Error::TweakMismatch)?
+        }
+        *seckey = tweaked;
+        Ok(())
+    }
+
+    /// Checks that the public key reported by the device after applying
+    /// the tweak on its side matches the prepared commitment
+    #[inline]
+    pub fn verify_applied(&self, tweaked_pubkey: &secp256k1::PublicKey) -> bool {
+        *tweaked_pubkey == self.tweaked_pubkey
+    }
+}
+
+impl LNPBP1Container {
+    /// Prepares LNPBP-1 commitment to the message without tweaking the
+    /// private key
+    pub fn prepare_commit<MSG>(&self, msg: &MSG) -> Result<PreparedTweak, secp256k1::Error>
+    where
+        MSG: AsRef<[u8]>,
+    {
+        let factor = tweaking_factor(&self.pubkey, &self.tag, msg.as_ref());
+        PreparedTweak::with(self.pubkey, self.key_origin.clone(), factor.into_inner())
+    }
+
+    /// Exports raw scalar tweak for the signers which can only add a scalar
+    /// to the private key
+    #[inline]
+    pub fn scalar_tweak<MSG>(&self, msg: &MSG) -> Result<[u8; 32], secp256k1::Error>
+    where
+        MSG: AsRef<[u8]>,
+    {
+        Ok(self.prepare_commit(msg)?.tweak)
+    }
+}
+
+impl KeysetContainer {
+    /// Prepares LNPBP-2 commitment to the message without tweaking the
+    /// private key
+    pub fn prepare_commit<MSG>(&self, msg: &MSG) -> Result<PreparedTweak, secp256k1::Error>
+    where
+        MSG: AsRef<[u8]>,
+    {
+        let factor = keyset_tweaking_factor(self, msg.as_ref())?;
+        PreparedTweak::with(self.pubkey, None, factor.into_inner())
+    }
+}
+
+/// Location of the tweaked public key or public key hash inside the
+/// committed lockscript
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct TweakedPosition {
+    /// Either [ChangeKind::Pubkey] or [ChangeKind::PubkeyHash]
+    pub kind: ChangeKind,
+    /// Byte offset of the pushed key or hash data (not including the push
+    /// opcode) in the committed script
+    pub offset: usize,
+}
+
+/// Prepared LNPBP-2 commitment into the lockscript
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct LockscriptTweak {
+    /// Tweak which has to be applied to the private key corresponding to the
+    /// container public key
+    pub prepared: PreparedTweak,
+    /// Lockscript with the tweaked key and key hashes
+    pub commitment: LockscriptCommitment,
+    /// All occurrences of the tweaked key and its hash in the committed
+    /// script, in the order of their appearance
+    pub positions: Vec<TweakedPosition>,
+}
+
+impl LockscriptContainer {
+    /// Prepares LNPBP-2 commitment to the message without tweaking the
+    /// private key, reporting which parts of the script were tweaked
+    pub fn prepare_commit<MSG>(&self, msg: &MSG) -> Result<LockscriptTweak, Error>
+    where
+        MSG: AsRef<[u8]>,
+    {
+        let commitment = LockscriptCommitment::embed_commit(self, msg)?;
+        let prepared = KeysetContainer {
+            pubkey: self.pubkey,
+            keyset: self.script.extract_pubkey_hash_set()?.0,
+            tag: self.tag,
+        }
+        .prepare_commit(msg)?;
+
+        let tweaked_key = prepared.tweaked_pubkey.serialize();
+        let tweaked_key_uncompressed = prepared.tweaked_pubkey.serialize_uncompressed();
+        let tweaked_hash = pubkey_hash(prepared.tweaked_pubkey, true);
+        let tweaked_hash_uncompressed = pubkey_hash(prepared.tweaked_pubkey, false);
+        let positions = push_offsets(&*commitment)?
+            .into_iter()
+            .filter_map(|(offset, data)| {
+                if data == &tweaked_key[..] || data == &tweaked_key_uncompressed[..] {
+                    Some(TweakedPosition {
+                        kind: ChangeKind::Pubkey,
+                        offset,
+                    })
+                } else if data == &tweaked_hash[..] || data == &tweaked_hash_uncompressed[..] {
+                    Some(TweakedPosition {
+                        kind: ChangeKind::PubkeyHash,
+                        offset,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        Ok(LockscriptTweak {
+            prepared,
+            commitment,
+            positions,
+        })
+    }
+
+    /// Exports raw scalar tweak for the signers which can only add a scalar
+    /// to the private key
+    #[inline]
+    pub fn scalar_tweak<MSG>(&self, msg: &MSG) -> Result<[u8; 32], Error>
+    where
+        MSG: AsRef<[u8]>,
+    {
+        Ok(self.prepare_commit(msg)?.prepared.tweak)
+    }
+}
+
+/// Item of the parsed script
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum ScriptItem<'a> {
+    /// Opcode which is not a data push
+    Opcode(u8),
+    /// Data push with the byte offset of the pushed data (not including the
+    /// push opcode and length prefix) in the script
+    Push(usize, &'a [u8]),
+}
+
+/// Splits the script into opcodes and data pushes; fails on a truncated push,
+/// including push with truncated `OP_PUSHDATA*` length prefix
+pub(super) fn script_items(script: &Script) -> Result<Vec<ScriptItem>, Error> {
+    let bytes = script.as_bytes();
+    let mut items = vec![];
+    let mut pos = 0usize;
+    while pos < bytes.len() {
+        let opcode = bytes[pos];
+        let len_size = match opcode {
+            0x01..=0x4b => 0,
+            op if op == OP_PUSHDATA1.into_u8() => 1,
+            op if op == OP_PUSHDATA2.into_u8() => 2,
+            op if op == OP_PUSHDATA4.into_u8() => 4,
+            _ => {
+                items.push(ScriptItem::Opcode(opcode));
+                pos += 1;
+                continue;
+            }
+        };
+        let len = if len_size == 0 {
+            opcode as usize
+        } else {
+            bytes
+                .get(pos + 1..pos + 1 + len_size)
+                .ok_or(Error::TruncatedPush(pos))?
+                .iter()
+                .rev()
+                .fold(0usize, |len, byte| (len << 8) | *byte as usize)
+        };
+        let start = pos + 1 + len_size;
+        let end = start.saturating_add(len);
+        let data = bytes.get(start..end).ok_or(Error::TruncatedPush(pos))?;
+        items.push(ScriptItem::Push(start, data));
+        pos = end;
+    }
+    Ok(items)
+}
+
+/// Returns byte offsets and data of all data pushes in the script; fails on
+/// a truncated push
+pub(super) fn push_offsets(script: &Script) -> Result<Vec<(usize, &[u8])>, Error> {
+    Ok(script_items(script)?
+        .into_iter()
+        .filter_map(|item| match item {
+            ScriptItem::Push(offset, data) => Some((offset, data)),
+            ScriptItem::Opcode(_) => None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::dbc::LNPBP2Commitment;
+    use crate::bp::LockScript;
+    use crate::strict_encoding::{strict_decode, strict_encode};
+    use bitcoin::hashes::sha256;
+    use miniscript::Miniscript;
+    use std::collections::HashSet;
+    use std::str::FromStr;
+
+    fn keys(no: u8) -> (secp256k1::SecretKey, secp256k1::PublicKey) {
+        let ec = Secp256k1::<secp256k1::SignOnly>::signing_only();
+        let seckey = secp256k1::SecretKey::from_slice(&[no; 32]).unwrap();
+        (seckey, secp256k1::PublicKey::from_secret_key(&ec, &seckey))
+    }
+
+    #[test]
+    fn test_lnpbp1_two_phase() {
+        let ec = Secp256k1::<secp256k1::SignOnly>::signing_only();
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let msg = b"message".to_vec();
+        for no in 1..9 {
+            let (mut seckey, pubkey) = keys(no);
+            let container = LNPBP1Container {
+                pubkey,
+                tag,
+                key_origin: None,
+            };
+
+            // Host side
+            let prepared = container.prepare_commit(&msg).unwrap();
+            let commitment = LNPBP1Commitment::embed_commit(&container, &msg).unwrap();
+            assert_eq!(prepared.commitment(), commitment);
+            assert!(commitment.verify(&container, &msg).unwrap().is_valid());
+
+            // Device side
+            let prepared: PreparedTweak =
+                strict_decode(&strict_encode(&prepared).unwrap()).unwrap();
+            prepared.apply(&mut seckey).unwrap();
+            let tweaked_pubkey = secp256k1::PublicKey::from_secret_key(&ec, &seckey);
+            assert_eq!(tweaked_pubkey, *commitment);
+            assert!(prepared.verify_applied(&tweaked_pubkey));
+
+            // Repeated application is detected
+            assert_eq!(prepared.apply(&mut seckey), Err(Error::SecretKeyMismatch));
+        }
+    }
+
+    #[test]
+    fn test_lnpbp2_two_phase() {
+        let tag = sha256::Hash::hash(b"TEST_TAG2");
+        let msg = b"message".to_vec();
+        let (mut seckey, pubkey) = keys(1);
+        let container = KeysetContainer {
+            pubkey,
+            keyset: (2..5).map(|no| keys(no).1).collect::<HashSet<_>>(),
+            tag,
+        };
+        let prepared = container.prepare_commit(&msg).unwrap();
+        assert_eq!(
+            *prepared.commitment(),
+            *LNPBP2Commitment::embed_commit(&container, &msg).unwrap()
+        );
+        prepared.apply(&mut seckey).unwrap();
+        assert!(
+            prepared.verify_applied(&secp256k1::PublicKey::from_secret_key(
+                &Secp256k1::signing_only(),
+                &seckey
+            ))
+        );
+    }
+
+    #[test]
+    fn test_lockscript_tweak() {
+        let ec = Secp256k1::<secp256k1::SignOnly>::signing_only();
+        let msg = b"message".to_vec();
+        let (mut seckey, pubkey) = keys(1);
+        let key = bitcoin::PublicKey {
+            compressed: true,
+            key: pubkey,
+        };
+        let other = bitcoin::PublicKey {
+            compressed: true,
+            key: keys(2).1,
+        };
+        let script = Miniscript::<bitcoin::PublicKey>::from_str(&format!(
+            "or_d(c:pk_k({}),and_v(vc:pk_k({}),c:pk_h({})))",
+            key,
+            other,
+            key.pubkey_hash()
+        ))
+        .unwrap()
+        .encode();
+        let container = LockscriptContainer {
+            script: LockScript::from(script),
+            pubkey,
+            tag: sha256::Hash::hash(b"TEST_TAG"),
+        };
+
+        let tweak = container.prepare_commit(&msg).unwrap();
+        assert_eq!(
+            tweak.commitment,
+            LockscriptCommitment::embed_commit(&container, &msg).unwrap()
+        );
+        assert_eq!(container.scalar_tweak(&msg).unwrap(), tweak.prepared.tweak);
+
+        let bytes = tweak.commitment.as_bytes();
+        assert_eq!(
+            tweak
+                .positions
+                .iter()
+                .map(|position| position.kind)
+                .collect::<Vec<_>>(),
+            vec![ChangeKind::Pubkey, ChangeKind::PubkeyHash]
+        );
+        let tweaked_key = tweak.positions[0].offset;
+        assert_eq!(
+            &bytes[tweaked_key..tweaked_key + 33],
+            &tweak.prepared.tweaked_pubkey.serialize()[..]
+        );
+        let tweaked_hash = tweak.positions[1].offset;
+        assert_eq!(
+            &bytes[tweaked_hash..tweaked_hash + 20],
+            &bitcoin::PublicKey {
+                compressed: true,
+                key: tweak.prepared.tweaked_pubkey
+            }
+            .pubkey_hash()[..]
+        );
+
+        seckey.add_assign(&tweak.prepared.tweak).unwrap();
+        assert_eq!(
+            secp256k1::PublicKey::from_secret_key(&ec, &seckey),
+            tweak.prepared.tweaked_pubkey
+        );
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let container = LNPBP1Container {
+            pubkey: keys(1).1,
+            tag: sha256::Hash::hash(b"TEST_TAG"),
+            key_origin: None,
+        };
+        let prepared = container.prepare_commit(&b"message".to_vec()).unwrap();
+        let (mut seckey, _) = keys(2);
+        let original = seckey;
+        assert_eq!(prepared.apply(&mut seckey), Err(Error::SecretKeyMismatch));
+        assert_eq!(seckey, original);
+    }
+}
@@ -11,7 +11,7 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use bitcoin::{hashes::sha256, secp256k1, Transaction, TxOut};
+use bitcoin::{hashes::sha256, secp256k1, Transaction};
 
 use super::{
     Container, Error, Proof, ScriptInfo, ScriptPubkeyComposition, TxoutCommitment, TxoutContainer,
@@ -27,19 +27,28 @@ pub struct TxContainer {
     pub tx: Transaction,
 }
 
-pub fn compute_lnpbp3_vout(tx: &Transaction, supplement: &TxSupplement) -> usize {
-    compute_vout(supplement.fee, supplement.protocol_factor, tx)
+/// Computes number of the transaction output containing LNPBP-3 commitment
+/// using the parameters from the supplement; see [lnpbp3_vout].
+/// Returns `None` if the transaction has no outputs.
+#[inline]
+pub fn compute_lnpbp3_vout(tx: &Transaction, supplement: &TxSupplement) -> Option<usize> {
+    lnpbp3_vout(tx, supplement.fee, supplement.protocol_factor)
 }
 
-fn compute_vout(fee: u64, entropy: u32, tx: &Transaction) -> usize {
-    let nouts = tx.output.len() as u16;
-    let vout = ((fee + (entropy as u64)) % (nouts as u64)) as u16;
-    vout as usize
-}
-
-fn get_mut_txout(fee: u64, entropy: u32, tx: &mut Transaction) -> &mut TxOut {
-    let tx2 = tx.clone();
-    &mut tx.output[compute_vout(fee, entropy, &tx2)]
+/// Selects transaction output containing LNPBP-3 commitment. The output is
+/// selected deterministically with the entropy derived from the transaction
+/// fee and protocol-specific factor: `vout = (fee + factor) mod nouts`,
+/// where the sum is computed without overflow. Both the committing wallet
+/// and the validator must use this function to agree on the output.
+/// Returns `None` if the transaction has no outputs.
+// #[consensus_critical]
+// #[standard_critical("LNPBP-3")]
+pub fn lnpbp3_vout(tx: &Transaction, fee: u64, protocol_factor: u32) -> Option<usize> {
+    let nouts = tx.output.len() as u128;
+    if nouts == 0 {
+        return None;
+    }
+    Some(((fee as u128 + protocol_factor as u128) % nouts) as usize)
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Display)]
@@ -60,9 +69,10 @@ impl TxContainer {
         pubkey: secp256k1::PublicKey,
         script_info: ScriptInfo,
         scriptpubkey_composition: ScriptPubkeyComposition,
-    ) -> Self {
-        let txout = &tx.output[compute_vout(fee, protocol_factor, &tx)];
-        Self {
+    ) -> Result<Self, Error> {
+        let vout = lnpbp3_vout(&tx, fee, protocol_factor).ok_or(Error::TxHasNoOutputs)?;
+        let txout = &tx.output[vout];
+        Ok(Self {
             tx: tx.clone(),
             fee,
             protocol_factor,
@@ -73,7 +83,7 @@ impl TxContainer {
                 script_info,
                 scriptpubkey_composition,
             ),
-        }
+        })
     }
 }
 
@@ -86,7 +96,9 @@ impl Container for TxContainer {
         supplement: &Self::Supplement,
         host: &Self::Host,
     ) -> Result<Self, Error> {
-        let txout = &host.output[compute_vout(supplement.fee, supplement.protocol_factor, host)];
+        let vout = lnpbp3_vout(host, supplement.fee, supplement.protocol_factor)
+            .ok_or(Error::TxHasNoOutputs)?;
+        let txout = &host.output[vout];
         Ok(Self {
             protocol_factor: supplement.protocol_factor,
             fee: supplement.fee,
@@ -131,12 +143,11 @@ where
 
     fn embed_commit(container: &Self::Container, msg: &MSG) -> Result<Self, Self::Error> {
         let mut tx = container.tx.clone();
-        let fee = container.fee;
-        let entropy = container.protocol_factor;
+        let vout = lnpbp3_vout(&tx, container.fee, container.protocol_factor)
+            .ok_or(Error::TxHasNoOutputs)?;
 
-        let txout_commitment =
-            TxoutCommitment::embed_commit(&container.txout_container.clone(), msg)?;
-        *get_mut_txout(fee, entropy, &mut tx) = txout_commitment.into_inner();
+        let txout_commitment = TxoutCommitment::embed_commit(&container.txout_container, msg)?;
+        tx.output[vout] = txout_commitment.into_inner();
 
         Ok(tx.into())
     }
@@ -145,14 +156,17 @@ where
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::bp::dbc::{ScriptInfo, ScriptPubkeyComposition, ScriptPubkeyContainer};
+    use crate::bp::dbc::{
+        ScriptInfo, ScriptPubkeyCommitment, ScriptPubkeyComposition, ScriptPubkeyContainer,
+    };
+    use crate::commit_verify::InvalidReason;
+    use crate::strict_encoding::{strict_decode, strict_encode};
     use bitcoin::consensus::encode::deserialize;
-    use bitcoin::hashes::hex::FromHex;
+    use bitcoin::hashes::{hex::FromHex, Hash};
     use std::str::FromStr;
 
-    #[test]
-    fn test_ability_to_commit() {
-        let tx = deserialize(Vec::from_hex(
+    fn tx() -> Transaction {
+        deserialize(Vec::from_hex(
             "020000000001031cfbc8f54fbfa4a33a30068841371f80dbfe166211242213188428f437445c9100000000\
             6a47304402206fbcec8d2d2e740d824d3d36cc345b37d9f65d665a99f5bd5c9e8d42270a03a802201395963\
             2492332200c2908459547bf8dbf97c65ab1a28dec377d6f1d41d3d63e012103d7279dfb90ce17fe139ba60a\
@@ -170,8 +184,19 @@ mod test {
             a6269e5fc20b6a1cb8beb92130cc52c67e46ef40aaa5cac5f0220644dd1b049727d991aece98a105563416e\
             10a5ac4221abac7d16931842d5c322012103960b87412d6e169f30e12106bdf70122aabb9eb61f455518322\
             a18b920a4dfa887d30700")
-            .unwrap().as_slice()).unwrap();
+            .unwrap().as_slice()).unwrap()
+    }
 
+    fn pubkey() -> secp256k1::PublicKey {
+        secp256k1::PublicKey::from_str(
+            "0218845781f631c48f1c9709e23092067d06837f30aa0cd0544ac887fe91ddd166",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_ability_to_commit() {
+        let tx = tx();
         let container = TxContainer {
             tx,
             fee: 0,
@@ -179,13 +204,11 @@ mod test {
             txout_container: TxoutContainer {
                 value: 0,
                 script_container: ScriptPubkeyContainer {
-                    pubkey: secp256k1::PublicKey::from_str(
-                        "0218845781f631c48f1c9709e23092067d06837f30aa0cd0544ac887fe91ddd166",
-                    )
-                    .unwrap(),
+                    pubkey: pubkey(),
                     script_info: ScriptInfo::None,
                     scriptpubkey_composition: ScriptPubkeyComposition::PublicKey,
                     tag: Default::default(),
+                    key_origin: None,
                 },
             },
         };
@@ -193,6 +216,236 @@ mod test {
         let msg = "message to commit to";
 
         let commitment = TxCommitment::embed_commit(&container, &msg).unwrap();
-        assert_eq!(commitment.verify(&container, &msg).unwrap(), true);
+        assert!(commitment.verify(&container, &msg).unwrap().is_valid());
+    }
+
+    #[test]
+    fn test_lnpbp3_vout() {
+        let tx = tx();
+        assert_eq!(tx.output.len(), 6);
+        for (fee, protocol_factor, vout) in vec![
+            (0, 0, 0),
+            (5, 0, 5),
+            (6, 0, 0),
+            (1000, 7, 5),
+            (u64::MAX, u32::MAX, 0),
+        ] {
+            let supplement = TxSupplement {
+                protocol_factor,
+                fee,
+                tag: Default::default(),
+            };
+            assert_eq!(compute_lnpbp3_vout(&tx, &supplement), Some(vout));
+        }
+
+        let mut empty = tx;
+        empty.output = vec![];
+        let supplement = TxSupplement {
+            protocol_factor: 1,
+            fee: 1,
+            tag: Default::default(),
+        };
+        assert_eq!(compute_lnpbp3_vout(&empty, &supplement), None);
+        assert_eq!(
+            TxContainer::construct(
+                1,
+                &Default::default(),
+                1,
+                empty,
+                pubkey(),
+                ScriptInfo::None,
+                ScriptPubkeyComposition::WPubkeyHash,
+            ),
+            Err(Error::TxHasNoOutputs)
+        );
+    }
+
+    #[test]
+    fn test_lnpbp3_vout_exhaustive() {
+        let mut tx = tx();
+        let txout = tx.output[0].clone();
+        tx.output = vec![];
+        assert_eq!(lnpbp3_vout(&tx, 0, 0), None);
+        assert_eq!(lnpbp3_vout(&tx, u64::MAX, u32::MAX), None);
+
+        for nouts in 1..=32usize {
+            tx.output = vec![txout.clone(); nouts];
+            for fee in 0..(nouts as u64 * 3) {
+                for protocol_factor in 0..(nouts as u32 * 3) {
+                    let vout = lnpbp3_vout(&tx, fee, protocol_factor).unwrap();
+                    assert!(vout < nouts);
+                    assert_eq!(vout, (fee as usize + protocol_factor as usize) % nouts);
+                    // Fee and factor contribute to the entropy symmetrically
+                    assert_eq!(
+                        lnpbp3_vout(&tx, protocol_factor as u64, fee as u32),
+                        Some(vout)
+                    );
+                }
+            }
+
+            // Sum of the maximal fee and factor does not overflow
+            let sum = u64::MAX as u128 + u32::MAX as u128;
+            assert_eq!(
+                lnpbp3_vout(&tx, u64::MAX, u32::MAX),
+                Some((sum % nouts as u128) as usize)
+            );
+            assert_eq!(
+                lnpbp3_vout(&tx, u64::MAX, 0),
+                Some((u64::MAX % nouts as u64) as usize)
+            );
+
+            // Selection is the same for the supplement-based API
+            let supplement = TxSupplement {
+                protocol_factor: 7,
+                fee: 1000,
+                tag: Default::default(),
+            };
+            assert_eq!(
+                compute_lnpbp3_vout(&tx, &supplement),
+                lnpbp3_vout(&tx, 1000, 7)
+            );
+        }
+    }
+
+    #[test]
+    fn test_fixture_commitment() {
+        let tx = tx();
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let msg = "message to commit to";
+        let container = TxContainer::construct(
+            7,
+            &tag,
+            1000,
+            tx.clone(),
+            pubkey(),
+            ScriptInfo::None,
+            ScriptPubkeyComposition::WPubkeyHash,
+        )
+        .unwrap();
+
+        let commitment = TxCommitment::embed_commit(&container, &msg).unwrap();
+        assert!(commitment.verify(&container, &msg).unwrap().is_valid());
+        assert!(commitment
+            .verify(&container, &"other message")
+            .unwrap()
+            .is_invalid());
+
+        // Only the output selected by LNPBP-3 procedure is modified, keeping
+        // its value
+        for (vout, (committed, original)) in commitment.output.iter().zip(&tx.output).enumerate() {
+            if vout == 5 {
+                assert_eq!(committed.value, original.value);
+                assert_ne!(committed.script_pubkey, original.script_pubkey);
+                assert!(committed.script_pubkey.is_v0_p2wpkh());
+            } else {
+                assert_eq!(committed, original);
+            }
+        }
+        assert_eq!(commitment.input, tx.input);
+
+        // The commitment is verifiable from the proof, supplement and the
+        // commitment transaction
+        let (proof, supplement) = container.deconstruct();
+        let reconstructed =
+            TxContainer::reconstruct(&proof, &supplement, commitment.as_inner()).unwrap();
+        assert!(commitment.verify(&reconstructed, &msg).unwrap().is_valid());
+    }
+
+    #[test]
+    fn test_proof_verify() {
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let msg = "message to commit to";
+        for composition in vec![
+            ScriptPubkeyComposition::PublicKey,
+            ScriptPubkeyComposition::PubkeyHash,
+            ScriptPubkeyComposition::WPubkeyHash,
+            ScriptPubkeyComposition::SHWPubkeyHash,
+            ScriptPubkeyComposition::TapRoot,
+        ] {
+            let container = TxContainer::construct(
+                7,
+                &tag,
+                1000,
+                tx(),
+                pubkey(),
+                ScriptInfo::None,
+                composition.clone(),
+            )
+            .unwrap();
+            let commitment = TxCommitment::embed_commit(&container, &msg).unwrap();
+
+            // Proof is transferable on its own
+            let proof = Proof::from(&container);
+            assert_eq!(proof.composition, Some(composition));
+            let proof: Proof = strict_decode(&strict_encode(&proof).unwrap()).unwrap();
+
+            assert!(proof.verify(&commitment, 5, &msg, &tag).is_valid());
+            assert!(proof.verify_bool(&commitment, 5, &msg, &tag));
+            assert!(proof
+                .verify(&commitment, 5, &"other message", &tag)
+                .is_invalid());
+            assert!(proof
+                .verify(&commitment, 5, &msg, &sha256::Hash::hash(b"OTHER_TAG"))
+                .is_invalid());
+            assert!(proof.verify(&commitment, 4, &msg, &tag).is_invalid());
+            assert!(proof.verify(&commitment, 6, &msg, &tag).is_invalid());
+            assert!(proof.verify(&tx(), 5, &msg, &tag).is_invalid());
+        }
+    }
+
+    #[test]
+    fn test_verify_in_tx() {
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let msg = "message to commit to";
+        let container = TxContainer::construct(
+            7,
+            &tag,
+            1000,
+            tx(),
+            pubkey(),
+            ScriptInfo::None,
+            ScriptPubkeyComposition::WPubkeyHash,
+        )
+        .unwrap();
+        let tx = TxCommitment::embed_commit(&container, &msg)
+            .unwrap()
+            .into_inner();
+
+        let txout_commitment =
+            TxoutCommitment::embed_commit(&container.txout_container, &msg).unwrap();
+        let script_commitment =
+            ScriptPubkeyCommitment::embed_commit(&container.txout_container.script_container, &msg)
+                .unwrap();
+        assert!(txout_commitment.verify_in_tx(&tx, 5).is_valid());
+        assert!(script_commitment.verify_in_tx(&tx, 5).is_valid());
+        for vout in 0..5 {
+            assert!(txout_commitment.verify_in_tx(&tx, vout).is_invalid());
+            assert!(script_commitment.verify_in_tx(&tx, vout).is_invalid());
+        }
+        assert_eq!(
+            txout_commitment.verify_in_tx(&tx, 6).reason(),
+            Some(InvalidReason::ContainerMismatch)
+        );
+        assert_eq!(
+            script_commitment.verify_in_tx(&tx, 6).reason(),
+            Some(InvalidReason::ContainerMismatch)
+        );
+
+        // Value is checked for the transaction output commitments only
+        let mut other_value = tx.clone();
+        other_value.output[5].value += 1;
+        assert_eq!(
+            txout_commitment.verify_in_tx(&other_value, 5).reason(),
+            Some(InvalidReason::ContainerMismatch)
+        );
+        assert!(script_commitment.verify_in_tx(&other_value, 5).is_valid());
+
+        // Commitment to other message does not match on-chain data
+        let other =
+            TxoutCommitment::embed_commit(&container.txout_container, &"other message").unwrap();
+        assert_eq!(
+            other.verify_in_tx(&tx, 5).reason(),
+            Some(InvalidReason::CommitmentMismatch)
+        );
     }
 }
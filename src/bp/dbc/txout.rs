@@ -11,14 +11,14 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use bitcoin::{hashes::sha256, secp256k1, TxOut};
+use bitcoin::{hashes::sha256, secp256k1, Transaction, TxOut};
 
 use super::{
     Container, Error, Proof, ScriptInfo, ScriptPubkeyCommitment, ScriptPubkeyComposition,
     ScriptPubkeyContainer,
 };
 use crate::bp::PubkeyScript;
-use crate::commit_verify::EmbedCommitVerify;
+use crate::commit_verify::{EmbedCommitVerify, InvalidReason, Verification};
 
 #[derive(Clone, PartialEq, Eq, Debug, Display)]
 #[display_from(Debug)]
@@ -87,6 +87,21 @@ wrapper!(
     derive = [PartialEq, Eq, Hash]
 );
 
+impl TxoutCommitment {
+    /// Verifies that the transaction output `vout` has the same
+    /// `scriptPubkey` and value as the commitment, so the commitment can be
+    /// checked against the blockchain data
+    pub fn verify_in_tx(&self, tx: &Transaction, vout: usize) -> Verification {
+        match tx.output.get(vout) {
+            Some(txout) if txout.value != self.value => {
+                Verification::Invalid(InvalidReason::ContainerMismatch)
+            }
+            Some(txout) => Verification::with_match(txout.script_pubkey == self.script_pubkey),
+            None => Verification::Invalid(InvalidReason::ContainerMismatch),
+        }
+    }
+}
+
 impl<MSG> EmbedCommitVerify<MSG> for TxoutCommitment
 where
     MSG: AsRef<[u8]>,
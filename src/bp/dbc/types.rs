@@ -11,9 +11,15 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use super::Error;
-use crate::bp::LockScript;
-use bitcoin::{hashes::sha256, secp256k1};
+use super::{
+    Error, LNPBP1Container, LockscriptContainer, RedeemScriptContainer, ScriptPubkeyCommitment,
+    ScriptPubkeyComposition, ScriptPubkeyContainer, SignatureContainer, TaprootContainer,
+    TxContainer, TxoutContainer, WPubkeyHashContainer, WitnessScriptContainer,
+};
+use crate::bp::{LockScript, PubkeyScript};
+use crate::commit_verify::{EmbedCommitVerify, InvalidReason, Verification};
+use bitcoin::util::bip32;
+use bitcoin::{hashes::sha256, secp256k1, Transaction};
 
 pub trait Container: Sized {
     type Supplement;
@@ -31,11 +37,72 @@ pub trait Container: Sized {
     fn into_proof(self) -> Proof;
 }
 
+/// Proof of the deterministic bitcoin commitment, which, together with the
+/// protocol tag, is sufficient to verify the commitment against the
+/// transaction hosting it
 #[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
 #[display_from(Debug)]
 pub struct Proof {
+    /// Original public key before the commitment
     pub pubkey: secp256k1::PublicKey,
     pub script_info: ScriptInfo,
+    /// Type of the output hosting the commitment; `None` for the containers
+    /// not bound to a transaction output
+    pub composition: Option<ScriptPubkeyComposition>,
+}
+
+#[cfg(all(test, feature = "property_tests"))]
+impl quickcheck::Arbitrary for Proof {
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use quickcheck::Arbitrary;
+        Self {
+            pubkey: crate::bp::arbitrary::public_key(g),
+            script_info: ScriptInfo::arbitrary(g),
+            composition: Option::<ScriptPubkeyComposition>::arbitrary(g),
+        }
+    }
+}
+
+impl Proof {
+    /// Verifies that the output `vout` of the transaction contains commitment
+    /// to the message made with the given protocol tag
+    pub fn verify<MSG>(
+        &self,
+        tx: &Transaction,
+        vout: u32,
+        msg: &MSG,
+        protocol_tag: &sha256::Hash,
+    ) -> Verification
+    where
+        MSG: AsRef<[u8]>,
+    {
+        let host = match tx.output.get(vout as usize) {
+            Some(txout) => PubkeyScript::from(txout.script_pubkey.clone()),
+            None => return Verification::Invalid(InvalidReason::ContainerMismatch),
+        };
+        let container = match ScriptPubkeyContainer::reconstruct(self, protocol_tag, &host) {
+            Ok(container) => container,
+            Err(_) => return Verification::Invalid(InvalidReason::ContainerMismatch),
+        };
+        ScriptPubkeyCommitment::from_inner(host)
+            .verify(&container, msg)
+            .unwrap_or(Verification::Invalid(InvalidReason::ContainerMismatch))
+    }
+
+    /// Adaptor for the [`Proof::verify`] returning `bool` value
+    #[inline]
+    pub fn verify_bool<MSG>(
+        &self,
+        tx: &Transaction,
+        vout: u32,
+        msg: &MSG,
+        protocol_tag: &sha256::Hash,
+    ) -> bool
+    where
+        MSG: AsRef<[u8]>,
+    {
+        self.verify(tx, vout, msg, protocol_tag).into()
+    }
 }
 
 impl From<secp256k1::PublicKey> for Proof {
@@ -43,10 +110,70 @@ impl From<secp256k1::PublicKey> for Proof {
         Self {
             pubkey,
             script_info: ScriptInfo::None,
+            composition: None,
         }
     }
 }
 
+macro_rules! impl_proof_from_container {
+    ($($container:ty),+) => {
+        $(
+            impl From<&$container> for Proof {
+                #[inline]
+                fn from(container: &$container) -> Self {
+                    container.to_proof()
+                }
+            }
+        )+
+    };
+}
+
+impl_proof_from_container!(
+    LNPBP1Container,
+    LockscriptContainer,
+    WitnessScriptContainer,
+    RedeemScriptContainer,
+    WPubkeyHashContainer,
+    TaprootContainer,
+    ScriptPubkeyContainer,
+    TxoutContainer,
+    TxContainer,
+    SignatureContainer
+);
+
+/// BIP-32 origin of the original public key used by the container, allowing
+/// hardware wallets to derive the key and apply the commitment tweak to it.
+/// Origin is known only to the key owner and is never included in the
+/// [Proof].
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct KeyOrigin {
+    /// Fingerprint of the master key
+    pub fingerprint: bip32::Fingerprint,
+    /// Derivation path from the master key
+    pub derivation: bip32::DerivationPath,
+}
+
+impl KeyOrigin {
+    /// Composes PSBT key path entry (`hd_keypaths` field of inputs and
+    /// outputs) for the given public key
+    pub fn psbt_keypath(
+        &self,
+        pubkey: secp256k1::PublicKey,
+    ) -> (
+        bitcoin::PublicKey,
+        (bip32::Fingerprint, bip32::DerivationPath),
+    ) {
+        (
+            bitcoin::PublicKey {
+                compressed: true,
+                key: pubkey,
+            },
+            (self.fingerprint, self.derivation.clone()),
+        )
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
 #[display_from(Debug)]
 #[non_exhaustive]
@@ -55,3 +182,18 @@ pub enum ScriptInfo {
     LockScript(LockScript),
     Taproot(sha256::Hash),
 }
+
+#[cfg(all(test, feature = "property_tests"))]
+impl quickcheck::Arbitrary for ScriptInfo {
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use bitcoin::hashes::Hash;
+        use quickcheck::Arbitrary;
+        match u8::arbitrary(g) % 3 {
+            0 => ScriptInfo::None,
+            1 => ScriptInfo::LockScript(LockScript::from(bitcoin::Script::from(
+                Vec::<u8>::arbitrary(g),
+            ))),
+            _ => ScriptInfo::Taproot(sha256::Hash::hash(&Vec::<u8>::arbitrary(g))),
+        }
+    }
+}
@@ -16,14 +16,18 @@ use bitcoin::hashes::{sha256d, Hash};
 #[macro_use]
 pub mod tagged256;
 pub mod blind;
+pub mod chain;
 pub mod dbc;
 pub mod network;
+pub mod outpoint_set;
 pub mod scripts;
 mod seals;
 pub mod short_id;
 mod strict_encoding;
 
+pub use chain::{AssetId, Chain, ChainParams};
 pub use network::{MagicNumber, Network};
+pub use outpoint_set::OutpointSet;
 pub use scripts::*;
 pub use seals::*;
 pub use short_id::*;
@@ -45,6 +49,28 @@ pub enum Challenge {
     Custom(LockScript),
 }
 
+/// Generators of secp256k1 keys for the `quickcheck::Arbitrary`
+/// implementations of the data structures containing them
+#[cfg(all(test, feature = "property_tests"))]
+pub(crate) mod arbitrary {
+    use bitcoin::secp256k1;
+    use quickcheck::{Arbitrary, Gen};
+
+    pub fn secret_key<G: Gen>(g: &mut G) -> secp256k1::SecretKey {
+        loop {
+            let bytes: Vec<u8> = (0..32).map(|_| u8::arbitrary(g)).collect();
+            if let Ok(seckey) = secp256k1::SecretKey::from_slice(&bytes) {
+                return seckey;
+            }
+        }
+    }
+
+    pub fn public_key<G: Gen>(g: &mut G) -> secp256k1::PublicKey {
+        let secp = secp256k1::Secp256k1::signing_only();
+        secp256k1::PublicKey::from_secret_key(&secp, &secret_key(g))
+    }
+}
+
 #[cfg(test)]
 pub mod test {
     use bitcoin::secp256k1;
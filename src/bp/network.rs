@@ -50,13 +50,13 @@ impl Network {
     }
 
     pub fn as_magic(&self) -> MagicNumber {
-        // FIXME: Something is going wrong here
-        use std::mem;
-        let m;
-        unsafe {
-            m = mem::transmute::<Self, u64>(self.clone());
+        match self {
+            Network::Mainnet => 0xD9B4BEF9,
+            Network::Testnet => 0x0709110B,
+            Network::Regtest => 0xDAB5BFFA,
+            Network::Signet => 0xA553C67E,
+            Network::Other(magic) => *magic,
         }
-        m as u32
     }
 }
 
@@ -119,6 +119,16 @@ impl fmt::Debug for Network {
     }
 }
 
+#[cfg(all(test, feature = "property_tests"))]
+impl quickcheck::Arbitrary for Network {
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use quickcheck::Arbitrary;
+        let mut magics = Self::all_magic();
+        magics.push(MagicNumber::arbitrary(g));
+        Self::from_magic(magics[usize::arbitrary(g) % magics.len()])
+    }
+}
+
 #[derive(Debug, Display)]
 #[display_from(Debug)]
 pub struct ParseError;
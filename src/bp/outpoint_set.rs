@@ -0,0 +1,183 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use bitcoin::{OutPoint, Transaction, Txid};
+use std::collections::BTreeSet;
+use std::iter::FromIterator;
+
+wrapper!(
+    OutpointSet,
+    BTreeSet<OutPoint>,
+    doc = "Ordered set of unique transaction outpoints",
+    derive = [Default, PartialEq, Eq, Hash]
+);
+
+impl OutpointSet {
+    /// Constructs empty outpoint set
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns set of all distinct transaction ids referenced by the
+    /// outpoints from the set
+    pub fn txids(&self) -> BTreeSet<Txid> {
+        self.0.iter().map(|outpoint| outpoint.txid).collect()
+    }
+
+    /// Iterates over output numbers of all outpoints from the set belonging
+    /// to the given transaction
+    pub fn vouts<'a>(&'a self, txid: &'a Txid) -> impl Iterator<Item = u32> + 'a {
+        self.0
+            .range(OutPoint::new(*txid, 0)..=OutPoint::new(*txid, std::u32::MAX))
+            .map(|outpoint| outpoint.vout)
+    }
+
+    /// Returns new set containing outpoints present in both `self` and `other`
+    pub fn intersect(&self, other: &OutpointSet) -> OutpointSet {
+        self.0.intersection(&other.0).cloned().collect()
+    }
+
+    /// Returns new set containing outpoints from `self` that are not present
+    /// in `other`
+    pub fn subtract(&self, other: &OutpointSet) -> OutpointSet {
+        self.0.difference(&other.0).cloned().collect()
+    }
+
+    /// Returns new set containing outpoints present either in `self` or in
+    /// `other`
+    pub fn unite(&self, other: &OutpointSet) -> OutpointSet {
+        self.0.union(&other.0).cloned().collect()
+    }
+
+    /// Returns subset of outpoints that are spent by the inputs of the
+    /// provided transaction
+    pub fn spent_by(&self, tx: &Transaction) -> OutpointSet {
+        tx.input
+            .iter()
+            .map(|txin| txin.previous_output)
+            .filter(|outpoint| self.0.contains(outpoint))
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "property_tests"))]
+impl quickcheck::Arbitrary for OutpointSet {
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use crate::bp::blind::OutpointReveal;
+        use quickcheck::Arbitrary;
+        // Generating outpoints sharing the same txid to test compact encoding
+        Vec::<(OutpointReveal, Vec<u32>)>::arbitrary(g)
+            .into_iter()
+            .flat_map(|(reveal, vouts)| {
+                vouts
+                    .into_iter()
+                    .map(move |vout| OutPoint::new(reveal.txid, vout))
+            })
+            .collect()
+    }
+}
+
+impl FromIterator<OutPoint> for OutpointSet {
+    fn from_iter<I: IntoIterator<Item = OutPoint>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl Extend<OutPoint> for OutpointSet {
+    fn extend<I: IntoIterator<Item = OutPoint>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+impl IntoIterator for OutpointSet {
+    type Item = OutPoint;
+    type IntoIter = std::collections::btree_set::IntoIter<OutPoint>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl From<Vec<OutPoint>> for OutpointSet {
+    #[inline]
+    fn from(outpoints: Vec<OutPoint>) -> Self {
+        outpoints.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::strict_encoding::{strict_decode, strict_encode};
+    use bitcoin::hashes::hex::FromHex;
+
+    fn gen_outpoints() -> Vec<OutPoint> {
+        let txid1 = Txid::from_hex(
+            "f5a6ae8e8e7f7e8ad5ee2fe5dc6d5f3a9b8c1bb1f8b5ad3d23deb1b7f2b05c0c",
+        )
+        .unwrap();
+        let txid2 = Txid::from_hex(
+            "0e4b1e3b24c1b9e8b3a5c6d2f1e0d9c8b7a6f5e4d3c2b1a09f8e7d6c5b4a3928",
+        )
+        .unwrap();
+        vec![
+            OutPoint::new(txid1, 2),
+            OutPoint::new(txid2, 0),
+            OutPoint::new(txid1, 0),
+            OutPoint::new(txid2, 0),
+            OutPoint::new(txid1, 0xFFFF_FFFF),
+        ]
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let outpoints = gen_outpoints();
+        let set = OutpointSet::from(outpoints.clone());
+        assert_eq!(set.len(), 4);
+        assert_eq!(set.txids().len(), 2);
+        assert_eq!(
+            set.vouts(&outpoints[0].txid).collect::<Vec<_>>(),
+            vec![0, 2, 0xFFFF_FFFF]
+        );
+
+        let other = OutpointSet::from(vec![outpoints[0], outpoints[1]]);
+        assert_eq!(set.intersect(&other), other);
+        assert_eq!(set.subtract(&other).len(), 2);
+        assert!(set.subtract(&other).intersect(&other).is_empty());
+        assert_eq!(set.subtract(&other).unite(&other), set);
+    }
+
+    #[test]
+    fn test_strict_encoding() {
+        let set = OutpointSet::from(gen_outpoints());
+        let data = strict_encode(&set).unwrap();
+        // 2 bytes for txid count, 2 x (32 bytes txid + 2 bytes vout count)
+        // and 4 vouts per 4 bytes
+        assert_eq!(data.len(), 2 + 2 * 34 + 4 * 4);
+        assert_eq!(strict_decode::<OutpointSet>(&data).unwrap(), set);
+
+        // Repeated outpoint must fail
+        let txid = gen_outpoints()[0].txid;
+        let mut malformed = vec![1u8, 0];
+        malformed.extend(&txid[..]);
+        malformed.extend(&[2u8, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(strict_decode::<OutpointSet>(&malformed).is_err());
+
+        // Non-canonical ordering must fail
+        let mut malformed = vec![1u8, 0];
+        malformed.extend(&txid[..]);
+        malformed.extend(&[2u8, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(strict_decode::<OutpointSet>(&malformed).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Compatibility layer over the `rust-miniscript` API used by the library
+//! (parsing, iteration over keys and key hashes, key replacement and
+//! encoding). All of the consensus-critical code works with [LockscriptAst]
+//! and [MiniscriptError] only, so upgrading the miniscript dependency
+//! requires changes in this module alone.
+
+use bitcoin::{PubkeyHash, Script};
+use miniscript::miniscript::iter::PubkeyOrHash;
+use miniscript::Miniscript;
+
+/// Errors of the miniscript operations, independent from the version of the
+/// miniscript library
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display_from(Debug)]
+pub enum MiniscriptError {
+    /// Script is not a valid miniscript or does not pass miniscript type
+    /// checks; the details are provided as a string
+    Parse(String),
+
+    /// Key processor has provided public key hash for the position which
+    /// requires plain public key
+    UnexpectedPubkeyHash,
+
+    /// Key processor has failed
+    ProcessorFailure,
+
+    /// Other miniscript error; the details are provided as a string
+    Other(String),
+}
+
+impl From<miniscript::Error> for MiniscriptError {
+    fn from(err: miniscript::Error) -> Self {
+        match err {
+            miniscript::Error::UnexpectedPubkeyHash => MiniscriptError::UnexpectedPubkeyHash,
+            miniscript::Error::PubkeyProcessorFailure => MiniscriptError::ProcessorFailure,
+            err => MiniscriptError::Other(err.to_string()),
+        }
+    }
+}
+
+/// Public key or public key hash met in the script
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum ScriptKey {
+    Pubkey(bitcoin::PublicKey),
+    PubkeyHash(PubkeyHash),
+}
+
+impl From<PubkeyOrHash<bitcoin::PublicKey>> for ScriptKey {
+    fn from(item: PubkeyOrHash<bitcoin::PublicKey>) -> Self {
+        match item {
+            PubkeyOrHash::PlainPubkey(pubkey) => ScriptKey::Pubkey(pubkey),
+            PubkeyOrHash::HashedPubkey(hash) => ScriptKey::PubkeyHash(hash.into()),
+        }
+    }
+}
+
+impl From<ScriptKey> for PubkeyOrHash<bitcoin::PublicKey> {
+    fn from(key: ScriptKey) -> Self {
+        match key {
+            ScriptKey::Pubkey(pubkey) => PubkeyOrHash::PlainPubkey(pubkey),
+            ScriptKey::PubkeyHash(hash) => PubkeyOrHash::HashedPubkey(hash.into()),
+        }
+    }
+}
+
+/// Abstract syntax tree of the script parsed with miniscript
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display_from(Debug)]
+pub struct LockscriptAst(Miniscript<bitcoin::PublicKey>);
+
+impl LockscriptAst {
+    /// Parses the script, running miniscript type checks
+    pub fn parse(script: &Script) -> Result<Self, MiniscriptError> {
+        Miniscript::parse(script)
+            .map(LockscriptAst)
+            .map_err(|err| MiniscriptError::Parse(err.to_string()))
+    }
+
+    /// Returns all public keys and public key hashes in the order they
+    /// appear in the script, including repeated occurrences
+    pub fn keys(&self) -> Vec<ScriptKey> {
+        self.0
+            .iter_pubkeys_and_hashes()
+            .map(ScriptKey::from)
+            .collect()
+    }
+
+    /// Replaces public keys and public key hashes with the ones returned by
+    /// the processor; `None` returned by the processor leaves the item
+    /// unchanged
+    pub fn replace_keys(
+        &self,
+        processor: impl Fn(ScriptKey) -> Option<ScriptKey>,
+    ) -> Result<Self, MiniscriptError> {
+        Ok(LockscriptAst(self.0.replace_pubkeys_and_hashes(
+            &|item: PubkeyOrHash<bitcoin::PublicKey>| processor(item.into()).map(ScriptKey::into),
+        )?))
+    }
+
+    /// Encodes the tree back into the script
+    #[inline]
+    pub fn encode(&self) -> Script {
+        self.0.encode()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::test::gen_bitcoin_pubkeys;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_parse_encode() {
+        let keys = gen_bitcoin_pubkeys(2, true);
+        let script = Miniscript::<bitcoin::PublicKey>::from_str(&format!(
+            "or_d(c:pk_k({}),c:pk_h({}))",
+            keys[0],
+            keys[1].pubkey_hash()
+        ))
+        .unwrap()
+        .encode();
+
+        let ast = LockscriptAst::parse(&script).unwrap();
+        assert_eq!(ast.encode(), script);
+        assert_eq!(
+            ast.keys(),
+            vec![
+                ScriptKey::Pubkey(keys[0]),
+                ScriptKey::PubkeyHash(keys[1].pubkey_hash())
+            ]
+        );
+
+        let replaced = ast
+            .replace_keys(|key| match key {
+                ScriptKey::Pubkey(_) => Some(ScriptKey::Pubkey(keys[1])),
+                ScriptKey::PubkeyHash(_) => None,
+            })
+            .unwrap();
+        assert_eq!(
+            replaced.keys(),
+            vec![
+                ScriptKey::Pubkey(keys[1]),
+                ScriptKey::PubkeyHash(keys[1].pubkey_hash())
+            ]
+        );
+
+        assert!(matches!(
+            LockscriptAst::parse(&Script::from(vec![0xff, 0xff])),
+            Err(MiniscriptError::Parse(_))
+        ));
+    }
+}
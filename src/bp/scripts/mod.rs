@@ -11,11 +11,13 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+mod compat;
 //mod error;
 mod pubkey_parser;
 mod script_pubkey;
 mod types;
 
+pub use compat::{LockscriptAst, MiniscriptError, ScriptKey};
 //pub use error::Error;
 pub use pubkey_parser::*;
 pub use script_pubkey::Error;
@@ -12,25 +12,24 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 use super::LockScript;
+use super::{LockscriptAst, MiniscriptError, ScriptKey};
 use bitcoin::{secp256k1, PubkeyHash};
-use miniscript::miniscript::iter::PubkeyOrHash;
-use miniscript::{Miniscript, MiniscriptKey};
 use std::collections::HashSet;
 use std::iter::FromIterator;
 
 /// Errors that may happen during LockScript parsing process
-#[derive(Debug, Display, Error)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
 #[display_from(Debug)]
 pub enum PubkeyParseError {
     /// Unexpected pubkey hash when enumerating in "keys only" mode
-    PubkeyHash(<bitcoin::PublicKey as MiniscriptKey>::Hash),
+    PubkeyHash(PubkeyHash),
 
     /// Miniscript-level error
-    Miniscript(miniscript::Error),
+    Miniscript(MiniscriptError),
 }
 
-impl From<miniscript::Error> for PubkeyParseError {
-    fn from(miniscript_error: miniscript::Error) -> Self {
+impl From<MiniscriptError> for PubkeyParseError {
+    fn from(miniscript_error: MiniscriptError) -> Self {
         Self::Miniscript(miniscript_error)
     }
 }
@@ -42,6 +41,26 @@ impl LockScript {
         Ok(HashSet::from_iter(self.extract_pubkeys()?))
     }
 
+    /// Returns set of unique public keys present in the script, traversing
+    /// all branches of the script AST in the same way as LNPBP-2 commitment
+    /// procedure does. Unlike [LockScript::extract_pubkeyset] does not fail on
+    /// public key hashes, which are skipped; use
+    /// [LockScript::extract_pubkey_hashes] to get them. Keys present in both
+    /// compressed and uncompressed serialization are returned once.
+    pub fn extract_pubkey_set(&self) -> Result<HashSet<secp256k1::PublicKey>, PubkeyParseError> {
+        Ok(self.extract_pubkey_hash_set()?.0)
+    }
+
+    /// Returns set of unique public key hashes present in the script,
+    /// traversing all branches of the script AST in the same way as LNPBP-2
+    /// commitment procedure does. Public keys are skipped; use
+    /// [LockScript::extract_pubkey_set] to get them. To check whether the
+    /// script contains a given key in hashed form both compressed and
+    /// uncompressed key hashes must be checked against the returned set.
+    pub fn extract_pubkey_hashes(&self) -> Result<HashSet<PubkeyHash>, PubkeyParseError> {
+        Ok(self.extract_pubkey_hash_set()?.1)
+    }
+
     /// Returns tuple of two sets: one for unique public keys and one for
     /// unique hash values, extracted from the script
     pub fn extract_pubkey_hash_set(
@@ -57,78 +76,67 @@ impl LockScript {
     pub fn extract_pubkeys_and_hashes(
         &self,
     ) -> Result<(Vec<secp256k1::PublicKey>, Vec<PubkeyHash>), PubkeyParseError> {
-        Miniscript::parse(&*self.clone())?
-            .iter_pubkeys_and_hashes()
-            .try_fold(
-                (Vec::<secp256k1::PublicKey>::new(), Vec::<PubkeyHash>::new()),
-                |(mut keys, mut hashes), item| {
-                    match item {
-                        PubkeyOrHash::HashedPubkey(hash) => hashes.push(hash.into()),
-                        PubkeyOrHash::PlainPubkey(key) => keys.push(key.key),
-                    }
-                    Ok((keys, hashes))
-                },
-            )
+        let mut keys = Vec::<secp256k1::PublicKey>::new();
+        let mut hashes = Vec::<PubkeyHash>::new();
+        for item in LockscriptAst::parse(&*self)?.keys() {
+            match item {
+                ScriptKey::PubkeyHash(hash) => hashes.push(hash),
+                ScriptKey::Pubkey(key) => keys.push(key.key),
+            }
+        }
+        Ok((keys, hashes))
     }
 
     /// Returns all public keys found in the script; fails on public key hash.
     /// If the key present multiple times in the script it returns all
     /// occurrences.
     pub fn extract_pubkeys(&self) -> Result<Vec<secp256k1::PublicKey>, PubkeyParseError> {
-        Miniscript::parse(&*self.clone())?
-            .iter_pubkeys_and_hashes()
-            .try_fold(
-                Vec::<secp256k1::PublicKey>::new(),
-                |mut keys, item| match item {
-                    PubkeyOrHash::HashedPubkey(hash) => Err(PubkeyParseError::PubkeyHash(hash)),
-                    PubkeyOrHash::PlainPubkey(key) => {
-                        keys.push(key.key);
-                        Ok(keys)
-                    }
-                },
-            )
+        LockscriptAst::parse(&*self)?
+            .keys()
+            .into_iter()
+            .map(|item| match item {
+                ScriptKey::PubkeyHash(hash) => Err(PubkeyParseError::PubkeyHash(hash)),
+                ScriptKey::Pubkey(key) => Ok(key.key),
+            })
+            .collect()
     }
 
     /// Replaces pubkeys using provided matching function; does not fail on
-    /// public key hashes.
+    /// public key hashes. Replaced keys keep compressed or uncompressed
+    /// serialization of the original keys.
     pub fn replace_pubkeys(
         &self,
         processor: impl Fn(secp256k1::PublicKey) -> Option<secp256k1::PublicKey>,
     ) -> Result<Self, PubkeyParseError> {
-        let result = Miniscript::parse(&*self.clone())?.replace_pubkeys_and_hashes(
-            &|item: PubkeyOrHash<bitcoin::PublicKey>| match item {
-                PubkeyOrHash::PlainPubkey(pubkey) => processor(pubkey.key).map(|key| {
-                    PubkeyOrHash::PlainPubkey(bitcoin::PublicKey {
-                        compressed: true,
-                        key,
-                    })
-                }),
-                PubkeyOrHash::HashedPubkey(_) => None,
-            },
-        )?;
+        let result = LockscriptAst::parse(&*self)?.replace_keys(|item| match item {
+            ScriptKey::Pubkey(pubkey) => processor(pubkey.key).map(|key| {
+                ScriptKey::Pubkey(bitcoin::PublicKey {
+                    compressed: pubkey.compressed,
+                    key,
+                })
+            }),
+            ScriptKey::PubkeyHash(_) => None,
+        })?;
         Ok(LockScript::from(result.encode()))
     }
 
     /// Replaces public kes and public key hashes using provided matching
-    /// functions.
+    /// functions. Replaced keys keep compressed or uncompressed serialization
+    /// of the original keys.
     pub fn replace_pubkeys_and_hashes(
         &self,
         key_processor: impl Fn(secp256k1::PublicKey) -> Option<secp256k1::PublicKey>,
         hash_processor: impl Fn(PubkeyHash) -> Option<PubkeyHash>,
     ) -> Result<Self, PubkeyParseError> {
-        let result = Miniscript::parse(&*self.clone())?.replace_pubkeys_and_hashes(
-            &|item: PubkeyOrHash<bitcoin::PublicKey>| match item {
-                PubkeyOrHash::PlainPubkey(pubkey) => key_processor(pubkey.key).map(|key| {
-                    PubkeyOrHash::PlainPubkey(bitcoin::PublicKey {
-                        compressed: true,
-                        key,
-                    })
-                }),
-                PubkeyOrHash::HashedPubkey(hash) => {
-                    hash_processor(hash.into()).map(|hash| PubkeyOrHash::HashedPubkey(hash.into()))
-                }
-            },
-        )?;
+        let result = LockscriptAst::parse(&*self)?.replace_keys(|item| match item {
+            ScriptKey::Pubkey(pubkey) => key_processor(pubkey.key).map(|key| {
+                ScriptKey::Pubkey(bitcoin::PublicKey {
+                    compressed: pubkey.compressed,
+                    key,
+                })
+            }),
+            ScriptKey::PubkeyHash(hash) => hash_processor(hash).map(ScriptKey::PubkeyHash),
+        })?;
         Ok(LockScript::from(result.encode()))
     }
 }
@@ -139,6 +147,7 @@ pub(crate) mod test {
     use crate::bp::test::*;
     use bitcoin::hashes::{hash160, sha256, Hash};
     use bitcoin::{PubkeyHash, PublicKey};
+    use miniscript::Miniscript;
     use std::collections::HashSet;
     use std::iter::FromIterator;
     use std::str::FromStr;
@@ -315,7 +324,7 @@ pub(crate) mod test {
     fn test_script_parse_singlehash() {
         single_keyhash_suite(|lockscript, hash| {
             if let Err(PubkeyParseError::PubkeyHash(found_hash)) = lockscript.extract_pubkeyset() {
-                assert_eq!(hash, found_hash.into())
+                assert_eq!(hash, found_hash)
             } else {
                 panic!("extract_pubkeyset must return error")
             }
@@ -351,6 +360,40 @@ pub(crate) mod test {
         });
     }
 
+    #[test]
+    fn test_extract_pubkeys_and_hashes() {
+        let (keys, hashes) = gen_pubkeys_and_hashes(4);
+        let lockscript = ms_str!(
+            "andor(c:pk_k({}),c:pk_k({}),or_d(c:pk_k({}),c:pk_h({})))",
+            keys[0],
+            keys[1],
+            keys[0],
+            hashes[2]
+        );
+        assert!(lockscript.extract_pubkeyset().is_err());
+        assert!(lockscript.extract_pubkeys().is_err());
+        assert_eq!(
+            lockscript.extract_pubkey_set().unwrap(),
+            HashSet::from_iter(vec![keys[0].key, keys[1].key])
+        );
+        assert_eq!(
+            lockscript.extract_pubkey_hashes().unwrap(),
+            HashSet::from_iter(vec![hashes[2]])
+        );
+
+        no_keys_or_hashes_suite(|lockscript| {
+            assert!(lockscript.extract_pubkey_set().unwrap().is_empty());
+            assert!(lockscript.extract_pubkey_hashes().unwrap().is_empty());
+        });
+        complex_suite(|lockscript, keys| {
+            assert_eq!(
+                lockscript.extract_pubkey_set().unwrap(),
+                HashSet::from_iter(keys)
+            );
+            assert!(lockscript.extract_pubkey_hashes().unwrap().is_empty());
+        });
+    }
+
     #[test]
     fn test_script_parse_complex_script() {
         complex_suite(|lockscript, keys| {
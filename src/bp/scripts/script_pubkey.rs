@@ -15,7 +15,9 @@
 //! ```text
 //! Template -> Descriptor -> Structure -> PubkeyScript -> TxOut
 //!
-//! TxOut -> PubkeyScript -> Descriptor -> Structure -> Format
+//! TxOut -> PubkeyScript -> Descriptor -> Structure
+//!
+//! PubkeyScript -> Format
 //! ```
 
 use bitcoin::{blockdata::script::*, hash_types::*, hashes::Hash, secp256k1};
@@ -55,28 +57,76 @@ pub enum ScriptPubkeyStructure {
     Custom(Script),
 }
 
+/// Standard type of the script pubkey, detected from the script with
+/// [ScriptPubkeyFormat::classify]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[display_from(Debug)]
+#[non_exhaustive]
 pub enum ScriptPubkeyFormat {
-    /// Initial standard used by Bitcoin Core (also codenamed "P2PK")
-    /// that uses uncompressed public key serialization followed with
-    /// `OP_CHECKSIG` code
-    KeyChecksig,
+    /// Public key (compressed or uncompressed) followed with `OP_CHECKSIG`
+    P2PK,
 
-    /// Script pubkey serialization according to widely accepted standard
-    KeyHash,
+    /// Public key hash according to widely accepted standard
+    P2PKH,
 
-    /// Script pubkey serialization according to BIP-16
-    ScriptHash,
+    /// Script hash according to BIP-16
+    P2SH,
 
-    /// Segwit script pubkey serialization according to BIP-141
-    /// <https://github.com/bitcoin/bips/blob/master/bip-0141.mediawiki#Witness_program>
-    Witness,
+    /// Segwit v0 public key hash according to BIP-141
+    P2WPKH,
 
-    /// Outputs containing OP_RETURN serialized according to
-    /// [Bitcoin Core-defined rules](https://github.com/bitcoin/bitcoin/blob/master/doc/release-notes/release-notes-0.12.0.md#relay-any-sequence-of-pushdatas-in-op_return-outputs-now-allowed)
+    /// Segwit v0 script hash according to BIP-141
+    P2WSH,
+
+    /// Segwit v1 taproot output key according to BIP-341
+    P2TR,
+
+    /// Output starting with `OP_RETURN`
     OpReturn,
 
-    /// Custom (i.e. non-standard) output with arbitrary script
-    Custom,
+    /// Any other script, including witness programs of the unknown versions
+    Bare,
+}
+
+impl ScriptPubkeyFormat {
+    /// Detects type of the script pubkey
+    pub fn classify(script: &Script) -> Self {
+        use bitcoin::blockdata::opcodes::all::OP_PUSHNUM_1;
+
+        match script {
+            s if s.is_p2pk() => ScriptPubkeyFormat::P2PK,
+            s if s.is_p2pkh() => ScriptPubkeyFormat::P2PKH,
+            s if s.is_p2sh() => ScriptPubkeyFormat::P2SH,
+            s if s.is_v0_p2wpkh() => ScriptPubkeyFormat::P2WPKH,
+            s if s.is_v0_p2wsh() => ScriptPubkeyFormat::P2WSH,
+            s if s.is_witness_program()
+                && s.as_bytes()[0] == OP_PUSHNUM_1.into_u8()
+                && s.len() == 34 =>
+            {
+                ScriptPubkeyFormat::P2TR
+            }
+            s if s.is_op_return() => ScriptPubkeyFormat::OpReturn,
+            _ => ScriptPubkeyFormat::Bare,
+        }
+    }
+
+    /// Detects whether the script pubkey is a witness program of a known
+    /// version
+    #[inline]
+    pub fn is_witness(self) -> bool {
+        matches!(
+            self,
+            ScriptPubkeyFormat::P2WPKH | ScriptPubkeyFormat::P2WSH | ScriptPubkeyFormat::P2TR
+        )
+    }
+}
+
+impl PubkeyScript {
+    /// Detects type of the script pubkey; see [ScriptPubkeyFormat::classify]
+    #[inline]
+    pub fn format(&self) -> ScriptPubkeyFormat {
+        ScriptPubkeyFormat::classify(self.as_inner())
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Display)]
@@ -126,8 +176,8 @@ impl TryFrom<PubkeyScript> for ScriptPubkeyDescriptor {
 
         let script = &*script_pubkey;
         let p = script.as_bytes();
-        Ok(match script {
-            s if s.is_p2pk() => {
+        Ok(match ScriptPubkeyFormat::classify(script) {
+            ScriptPubkeyFormat::P2PK => {
                 let key = match p[0].into() {
                     OP_PUSHBYTES_65 => bitcoin::PublicKey::from_slice(&p[1..66]),
                     OP_PUSHBYTES_33 => bitcoin::PublicKey::from_slice(&p[1..34]),
@@ -136,20 +186,25 @@ impl TryFrom<PubkeyScript> for ScriptPubkeyDescriptor {
                 .map_err(|_| Error::InvalidKeyData)?;
                 P2PK(key)
             }
-            s if s.is_p2pkh() => P2PKH(
-                PubkeyHash::from_slice(&p[2..23]).expect("Reading hash from fixed slice failed"),
+            ScriptPubkeyFormat::P2PKH => P2PKH(
+                PubkeyHash::from_slice(&p[3..23]).expect("Reading hash from fixed slice failed"),
             ),
-            s if s.is_p2sh() => P2SH(
-                ScriptHash::from_slice(&p[1..22]).expect("Reading hash from fixed slice failed"),
+            ScriptPubkeyFormat::P2SH => P2SH(
+                ScriptHash::from_slice(&p[2..22]).expect("Reading hash from fixed slice failed"),
             ),
-            s if s.is_v0_p2wpkh() => P2WPKH(
-                WPubkeyHash::from_slice(&p[2..23]).expect("Reading hash from fixed slice failed"),
+            ScriptPubkeyFormat::P2WPKH => P2WPKH(
+                WPubkeyHash::from_slice(&p[2..22]).expect("Reading hash from fixed slice failed"),
             ),
-            s if s.is_v0_p2wsh() => P2WSH(
+            ScriptPubkeyFormat::P2WSH => P2WSH(
                 WScriptHash::from_slice(&p[2..34]).expect("Reading hash from fixed slice failed"),
             ),
-            s if s.is_witness_program() => Err(Error::UnsupportedWitnessVersion)?,
-            s if s.is_op_return() => P2OR(
+            ScriptPubkeyFormat::P2TR => {
+                // BIP-341 output keys are x-only and imply even y-coordinate
+                let mut key = [2u8; 33];
+                key[1..].copy_from_slice(&p[2..]);
+                P2TR(secp256k1::PublicKey::from_slice(&key).map_err(|_| Error::InvalidKeyData)?)
+            }
+            ScriptPubkeyFormat::OpReturn => P2OR(
                 Script::from(p[1..].to_vec())
                     .iter(false)
                     .map(|instr| {
@@ -161,11 +216,30 @@ impl TryFrom<PubkeyScript> for ScriptPubkeyDescriptor {
                     })
                     .collect(),
             ),
-            _ => P2S(script_pubkey),
+            ScriptPubkeyFormat::Bare if script.is_witness_program() => {
+                Err(Error::UnsupportedWitnessVersion)?
+            }
+            ScriptPubkeyFormat::Bare => P2S(script_pubkey),
         })
     }
 }
 
+impl From<&ScriptPubkeyDescriptor> for ScriptPubkeyFormat {
+    fn from(descr: &ScriptPubkeyDescriptor) -> Self {
+        use ScriptPubkeyDescriptor::*;
+        match descr {
+            P2S(_) => ScriptPubkeyFormat::Bare,
+            P2PK(_) => ScriptPubkeyFormat::P2PK,
+            P2PKH(_) => ScriptPubkeyFormat::P2PKH,
+            P2SH(_) => ScriptPubkeyFormat::P2SH,
+            P2OR(_) => ScriptPubkeyFormat::OpReturn,
+            P2WPKH(_) => ScriptPubkeyFormat::P2WPKH,
+            P2WSH(_) => ScriptPubkeyFormat::P2WSH,
+            P2TR(_) => ScriptPubkeyFormat::P2TR,
+        }
+    }
+}
+
 impl From<ScriptPubkeyDescriptor> for PubkeyScript {
     fn from(spkt: ScriptPubkeyDescriptor) -> PubkeyScript {
         use ScriptPubkeyDescriptor::*;
@@ -183,7 +257,10 @@ impl From<ScriptPubkeyDescriptor> for PubkeyScript {
             }
             P2WPKH(wpubkey_hash) => Builder::gen_v0_p2wpkh(&wpubkey_hash).into_script(),
             P2WSH(wscript_hash) => Builder::gen_v0_p2wsh(&wscript_hash).into_script(),
-            P2TR(_) => unimplemented!(),
+            P2TR(pubkey) => Builder::new()
+                .push_int(1)
+                .push_slice(&pubkey.serialize()[1..])
+                .into_script(),
         })
     }
 }
@@ -206,3 +283,103 @@ impl From<ScriptPubkeyDescriptor> for ScriptPubkeyStructure {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::test::gen_secp_pubkeys;
+
+    #[test]
+    fn test_classify() {
+        let key = bitcoin::PublicKey {
+            compressed: true,
+            key: gen_secp_pubkeys(1)[0],
+        };
+        let lock_script = LockScript::from(Builder::gen_p2pk(&key).into_script());
+        let witness_script = WitnessScript::from(lock_script.clone());
+        let redeem_script = RedeemScript::from(lock_script.clone());
+        let witness_v2 = PubkeyScript::from(
+            Builder::new()
+                .push_int(2)
+                .push_slice(&[0u8; 32])
+                .into_script(),
+        );
+
+        let cases = vec![
+            (
+                key.gen_script_pubkey(Strategy::Exposed),
+                ScriptPubkeyFormat::P2PK,
+            ),
+            (
+                key.gen_script_pubkey(Strategy::LegacyHashed),
+                ScriptPubkeyFormat::P2PKH,
+            ),
+            (redeem_script.to_p2sh(), ScriptPubkeyFormat::P2SH),
+            (
+                key.gen_script_pubkey(Strategy::WitnessV0),
+                ScriptPubkeyFormat::P2WPKH,
+            ),
+            (witness_script.to_p2wsh(), ScriptPubkeyFormat::P2WSH),
+            (
+                PubkeyScript::from(ScriptPubkeyDescriptor::P2TR(key.key)),
+                ScriptPubkeyFormat::P2TR,
+            ),
+            (
+                PubkeyScript::from(Builder::gen_op_return(b"data").into_script()),
+                ScriptPubkeyFormat::OpReturn,
+            ),
+            (
+                PubkeyScript::from(Script::from(vec![0x51])),
+                ScriptPubkeyFormat::Bare,
+            ),
+        ];
+        for (script, format) in cases {
+            assert_eq!(script.format(), format);
+            assert_eq!(format.is_witness(), script.is_witness_program());
+            let descriptor = ScriptPubkeyDescriptor::try_from(script.clone()).unwrap();
+            assert_eq!(ScriptPubkeyFormat::from(&descriptor), format);
+            assert_eq!(PubkeyScript::from(descriptor), script);
+        }
+        assert_eq!(witness_v2.format(), ScriptPubkeyFormat::Bare);
+        assert_eq!(
+            ScriptPubkeyDescriptor::try_from(witness_v2),
+            Err(Error::UnsupportedWitnessVersion)
+        );
+    }
+
+    #[test]
+    fn test_script_conversions() {
+        let key = bitcoin::PublicKey {
+            compressed: true,
+            key: gen_secp_pubkeys(1)[0],
+        };
+        let lock_script = LockScript::from(Builder::gen_p2pk(&key).into_script());
+        let witness_script = WitnessScript::from(lock_script.clone());
+
+        assert_eq!(LockScript::from(witness_script.clone()), lock_script);
+        assert_eq!(
+            RedeemScript::from(lock_script.clone()).to_p2sh(),
+            lock_script.gen_script_pubkey(Strategy::LegacyHashed)
+        );
+        assert_eq!(
+            witness_script.to_p2wsh(),
+            lock_script.gen_script_pubkey(Strategy::WitnessV0)
+        );
+        assert_eq!(
+            witness_script.to_redeem_script().to_p2sh(),
+            lock_script.gen_script_pubkey(Strategy::WitnessScriptHash)
+        );
+        assert_eq!(
+            key.gen_script_pubkey(Strategy::WitnessV0)
+                .to_redeem_script()
+                .unwrap()
+                .to_p2sh(),
+            key.gen_script_pubkey(Strategy::WitnessScriptHash)
+        );
+        assert_eq!(
+            key.gen_script_pubkey(Strategy::LegacyHashed)
+                .to_redeem_script(),
+            None
+        );
+    }
+}
@@ -143,6 +143,12 @@ impl RedeemScript {
     pub fn script_hash(&self) -> ScriptHash {
         self.as_inner().script_hash()
     }
+
+    /// Generates P2SH script pubkey for the redeem script
+    #[inline]
+    pub fn to_p2sh(&self) -> PubkeyScript {
+        Builder::gen_p2sh(&self.script_hash()).into_script().into()
+    }
 }
 
 impl From<LockScript> for RedeemScript {
@@ -166,6 +172,21 @@ impl WitnessScript {
     pub fn script_hash(&self) -> WScriptHash {
         self.as_inner().wscript_hash()
     }
+
+    /// Generates P2WSH script pubkey for the witness script
+    #[inline]
+    pub fn to_p2wsh(&self) -> PubkeyScript {
+        Builder::gen_v0_p2wsh(&self.script_hash())
+            .into_script()
+            .into()
+    }
+
+    /// Generates redeem script nesting the witness script into P2SH
+    /// (P2SH-P2WSH scheme)
+    #[inline]
+    pub fn to_redeem_script(&self) -> RedeemScript {
+        RedeemScript::from_inner(self.to_p2wsh().into_inner())
+    }
 }
 
 impl From<LockScript> for WitnessScript {
@@ -174,6 +195,25 @@ impl From<LockScript> for WitnessScript {
     }
 }
 
+impl From<WitnessScript> for LockScript {
+    fn from(witness_script: WitnessScript) -> Self {
+        LockScript(witness_script.to_inner())
+    }
+}
+
+impl PubkeyScript {
+    /// Converts segwit script pubkey into the redeem script nesting it into
+    /// P2SH (P2SH-P2WPKH and P2SH-P2WSH schemes). Returns `None` for the
+    /// scripts which are not witness programs.
+    pub fn to_redeem_script(&self) -> Option<RedeemScript> {
+        if self.is_witness_program() {
+            Some(RedeemScript::from_inner(self.to_inner()))
+        } else {
+            None
+        }
+    }
+}
+
 wrapper!(
     TapScript,
     Script,
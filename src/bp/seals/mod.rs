@@ -15,8 +15,10 @@ mod error;
 mod tx_graph;
 mod txout_seal;
 mod txout_witness;
+mod watch;
 
 pub use error::Error;
 pub use tx_graph::{SpendingStatus, TxGraph};
 pub use txout_seal::{TxResolve, TxoutSeal};
 pub use txout_witness::*;
+pub use watch::{ClosingConstraint, SealViolation, SealWatch};
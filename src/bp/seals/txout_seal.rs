@@ -16,7 +16,7 @@ use bitcoin::{OutPoint, Transaction};
 use super::{Error, SpendingStatus, TxGraph, Witness};
 use crate::bp::dbc::{Container, TxCommitment, TxContainer, TxSupplement};
 use crate::bp::ShortId;
-use crate::commit_verify::EmbedCommitVerify;
+use crate::commit_verify::{EmbedCommitVerify, Verification};
 use crate::single_use_seals::{Message, SealMedium, SealStatus, SingleUseSeal};
 
 pub struct TxoutSeal<'a, RESOLVER>
@@ -56,7 +56,7 @@ where
         Ok(Witness(tx_commitment, container.to_proof()))
     }
 
-    fn verify(&self, msg: &Message, witness: &Self::Witness) -> Result<bool, Self::Error> {
+    fn verify(&self, msg: &Message, witness: &Self::Witness) -> Result<Verification, Self::Error> {
         let (host, supplement) = self
             .resolver
             .tx_and_data(self.seal_definition)
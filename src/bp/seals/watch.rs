@@ -0,0 +1,201 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Delegation of seal monitoring to a third-party service. The wallet hands
+//! out [SealWatch] containing the set of seal outpoints and the commitment
+//! the closing transaction is expected to contain. The blob contains no
+//! private keys or blinding factors, so the service is able only to detect
+//! seal closings and check them against the expectations, reporting
+//! violations to the wallet when it comes back online.
+
+use bitcoin::hashes::sha256;
+use bitcoin::{OutPoint, Transaction, Txid};
+
+use crate::bp::dbc::Proof;
+use crate::bp::OutpointSet;
+
+/// Commitment which must be present in the transaction closing the seals
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct ClosingConstraint {
+    /// Single SHA256 hash of the protocol-specific tag
+    pub protocol_tag: sha256::Hash,
+    /// Hash of the message the closing transaction must commit to
+    pub message_hash: sha256::Hash,
+    /// Proof for the reconstruction of the commitment container
+    pub proof: Proof,
+    /// Number of the closing transaction output hosting the commitment, if
+    /// it is known in advance; otherwise any output may host it
+    pub vout: Option<u32>,
+}
+
+/// Seals watched on behalf of the wallet together with the expected closing
+/// commitment
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct SealWatch {
+    pub seals: OutpointSet,
+    pub constraint: ClosingConstraint,
+}
+
+/// Seal closing not matching the delegated expectations
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display_from(Debug)]
+pub enum SealViolation {
+    /// Watched seals (second field) were closed by a transaction (first
+    /// field) which does not contain the expected commitment
+    CommitmentMismatch(Txid, OutpointSet),
+
+    /// Only part of the watched seals (second field) were closed by the
+    /// transaction (first field) containing the expected commitment
+    PartialClosing(Txid, OutpointSet),
+}
+
+impl SealWatch {
+    /// Checks whether the outpoint is one of the watched seals
+    #[inline]
+    pub fn is_watched(&self, outpoint: &OutPoint) -> bool {
+        self.seals.contains(outpoint)
+    }
+
+    /// Verifies the transaction against the delegated constraints. Returns
+    /// set of the watched seals closed by the transaction (which is empty
+    /// when the transaction is unrelated) or the detected violation.
+    pub fn verify(&self, tx: &Transaction) -> Result<OutpointSet, SealViolation> {
+        let closed = self.seals.spent_by(tx);
+        if closed.is_empty() {
+            return Ok(closed);
+        }
+
+        let constraint = &self.constraint;
+        let committed = match constraint.vout {
+            Some(vout) => constraint.proof.verify_bool(
+                tx,
+                vout,
+                &constraint.message_hash,
+                &constraint.protocol_tag,
+            ),
+            None => (0..tx.output.len() as u32).any(|vout| {
+                constraint.proof.verify_bool(
+                    tx,
+                    vout,
+                    &constraint.message_hash,
+                    &constraint.protocol_tag,
+                )
+            }),
+        };
+        if !committed {
+            Err(SealViolation::CommitmentMismatch(tx.txid(), closed))?
+        }
+        if closed != self.seals {
+            Err(SealViolation::PartialClosing(tx.txid(), closed))?
+        }
+        Ok(closed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::dbc::{
+        Container, ScriptInfo, ScriptPubkeyCommitment, ScriptPubkeyComposition,
+        ScriptPubkeyContainer,
+    };
+    use crate::bp::test::gen_secp_pubkeys;
+    use crate::commit_verify::EmbedCommitVerify;
+    use crate::strict_encoding::{strict_decode, strict_encode};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{Script, TxIn, TxOut};
+
+    fn seal(vout: u32) -> OutPoint {
+        OutPoint::new(Txid::hash(b"seal"), vout)
+    }
+
+    fn closing_tx(seals: &[OutPoint], script_pubkey: Script) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time: 0,
+            input: seals
+                .iter()
+                .map(|outpoint| TxIn {
+                    previous_output: *outpoint,
+                    ..TxIn::default()
+                })
+                .collect(),
+            output: vec![
+                TxOut {
+                    value: 1000,
+                    script_pubkey: Script::new(),
+                },
+                TxOut {
+                    value: 1000,
+                    script_pubkey,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_seal_watch() {
+        let protocol_tag = sha256::Hash::hash(b"TEST_TAG");
+        let message_hash = sha256::Hash::hash(b"message");
+        let container = ScriptPubkeyContainer::construct(
+            &protocol_tag,
+            gen_secp_pubkeys(1)[0],
+            ScriptInfo::None,
+            ScriptPubkeyComposition::WPubkeyHash,
+        );
+        let script_pubkey = ScriptPubkeyCommitment::embed_commit(&container, &message_hash)
+            .unwrap()
+            .into_inner()
+            .into_inner();
+        let mut watch = SealWatch {
+            seals: vec![seal(0), seal(1)].into_iter().collect(),
+            constraint: ClosingConstraint {
+                protocol_tag,
+                message_hash,
+                proof: container.into_proof(),
+                vout: None,
+            },
+        };
+        assert!(watch.is_watched(&seal(1)));
+        assert!(!watch.is_watched(&seal(2)));
+
+        let tx = closing_tx(&[seal(0), seal(1)], script_pubkey.clone());
+        assert_eq!(watch.verify(&tx), Ok(watch.seals.clone()));
+
+        let unrelated = closing_tx(&[seal(2)], Script::new());
+        assert!(watch.verify(&unrelated).unwrap().is_empty());
+
+        let forged = closing_tx(&[seal(0), seal(1)], Script::new());
+        assert!(matches!(
+            watch.verify(&forged),
+            Err(SealViolation::CommitmentMismatch(..))
+        ));
+
+        let partial = closing_tx(&[seal(1)], script_pubkey);
+        assert!(matches!(
+            watch.verify(&partial),
+            Err(SealViolation::PartialClosing(..))
+        ));
+
+        watch.constraint.vout = Some(0);
+        assert!(matches!(
+            watch.verify(&tx),
+            Err(SealViolation::CommitmentMismatch(..))
+        ));
+
+        let decoded: SealWatch = strict_decode(&strict_encode(&watch).unwrap()).unwrap();
+        assert_eq!(decoded, watch);
+    }
+}
@@ -507,6 +507,14 @@ impl TryFrom<Descriptor> for ShortId {
     }
 }
 
+#[cfg(all(test, feature = "property_tests"))]
+impl quickcheck::Arbitrary for ShortId {
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use quickcheck::Arbitrary;
+        Self::from(u64::arbitrary(g))
+    }
+}
+
 impl From<u64> for ShortId {
     fn from(val: u64) -> Self {
         Self(val)
@@ -11,11 +11,17 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use super::{blind::OutpointHash, blind::OutpointReveal, Network, ShortId};
+use super::{
+    blind::OutpointHash, blind::OutpointReveal, dbc, AssetId, Chain, ClosingConstraint, LockScript,
+    Network, OutpointSet, SealWatch, ShortId,
+};
 use crate::strict_encoding::{self, Error, StrictDecode, StrictEncode};
 use bitcoin::hashes::{hash160, sha256, sha256d};
-use bitcoin::{secp256k1, util::bip32, Txid};
+use bitcoin::{secp256k1, util::bip32, BlockHash, OutPoint, Txid};
+use miniscript::{Descriptor, Miniscript};
+use std::collections::BTreeMap;
 use std::io;
+use std::str::FromStr;
 
 impl strict_encoding::Strategy for Txid {
     type Strategy = strict_encoding::strategies::HashFixedBytes;
@@ -23,6 +29,15 @@ impl strict_encoding::Strategy for Txid {
 impl strict_encoding::Strategy for OutpointHash {
     type Strategy = strict_encoding::strategies::HashFixedBytes;
 }
+impl strict_encoding::Strategy for BlockHash {
+    type Strategy = strict_encoding::strategies::HashFixedBytes;
+}
+impl strict_encoding::Strategy for AssetId {
+    type Strategy = strict_encoding::strategies::HashFixedBytes;
+}
+impl strict_encoding::Strategy for OutPoint {
+    type Strategy = strict_encoding::strategies::BitcoinConsensus;
+}
 
 impl strict_encoding::Strategy for sha256::Hash {
     type Strategy = strict_encoding::strategies::HashFixedBytes;
@@ -162,6 +177,31 @@ impl StrictDecode for Network {
     }
 }
 
+/// Chain is encoded as its genesis block hash
+impl StrictEncode for Chain {
+    type Error = Error;
+
+    #[inline]
+    fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+        self.genesis_hash().strict_encode(e)
+    }
+}
+
+impl StrictDecode for Chain {
+    type Error = Error;
+
+    #[inline]
+    fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+        let genesis_hash = BlockHash::strict_decode(d)?;
+        Chain::from_genesis_hash(genesis_hash).ok_or_else(|| {
+            Error::UnsupportedDataStructure(format!(
+                "unknown chain with genesis block {}",
+                genesis_hash
+            ))
+        })
+    }
+}
+
 impl StrictEncode for ShortId {
     type Error = Error;
 
@@ -195,7 +235,7 @@ impl StrictDecode for OutpointReveal {
     #[inline]
     fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
         Ok(Self {
-            blinding: u32::strict_decode(&mut d)?,
+            blinding: u64::strict_decode(&mut d)?,
             txid: Txid::strict_decode(&mut d)?,
             vout: u16::strict_decode(&mut d)?,
         })
@@ -322,3 +362,474 @@ impl StrictDecode for bip32::ExtendedPubKey {
         })
     }
 }
+
+impl StrictEncode for LockScript {
+    type Error = Error;
+
+    #[inline]
+    fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+        self.as_inner().as_bytes().strict_encode(e)
+    }
+}
+
+impl StrictDecode for LockScript {
+    type Error = Error;
+
+    #[inline]
+    fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+        Ok(Self::from(bitcoin::Script::from(Vec::<u8>::strict_decode(d)?)))
+    }
+}
+
+/// Miniscript is encoded in its script form, which is parsed back with the
+/// full miniscript type checks during decoding
+impl StrictEncode for Miniscript<bitcoin::PublicKey> {
+    type Error = Error;
+
+    #[inline]
+    fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+        self.encode().as_bytes().strict_encode(e)
+    }
+}
+
+impl StrictDecode for Miniscript<bitcoin::PublicKey> {
+    type Error = Error;
+
+    #[inline]
+    fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+        let script = bitcoin::Script::from(Vec::<u8>::strict_decode(d)?);
+        Ok(Miniscript::parse(&script).map_err(|err| {
+            Error::DataIntegrityError(format!("Invalid miniscript data: {}", err))
+        })?)
+    }
+}
+
+/// Output descriptor is encoded as its string representation, which is
+/// parsed and validated during decoding
+impl StrictEncode for Descriptor<bitcoin::PublicKey> {
+    type Error = Error;
+
+    #[inline]
+    fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+        self.to_string().strict_encode(e)
+    }
+}
+
+impl StrictDecode for Descriptor<bitcoin::PublicKey> {
+    type Error = Error;
+
+    #[inline]
+    fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+        Ok(
+            Descriptor::from_str(&String::strict_decode(d)?).map_err(|err| {
+                Error::DataIntegrityError(format!("Invalid output descriptor: {}", err))
+            })?,
+        )
+    }
+}
+
+impl StrictEncode for dbc::KeyOrigin {
+    type Error = Error;
+
+    #[inline]
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        Ok(strict_encode_list!(e; self.fingerprint, self.derivation))
+    }
+}
+
+impl StrictDecode for dbc::KeyOrigin {
+    type Error = Error;
+
+    #[inline]
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(Self {
+            fingerprint: bip32::Fingerprint::strict_decode(&mut d)?,
+            derivation: bip32::DerivationPath::strict_decode(&mut d)?,
+        })
+    }
+}
+
+impl StrictEncode for dbc::LNPBP1Container {
+    type Error = Error;
+
+    #[inline]
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        Ok(strict_encode_list!(e; self.pubkey, self.tag, self.key_origin))
+    }
+}
+
+impl StrictDecode for dbc::LNPBP1Container {
+    type Error = Error;
+
+    #[inline]
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(Self {
+            pubkey: secp256k1::PublicKey::strict_decode(&mut d)?,
+            tag: sha256::Hash::strict_decode(&mut d)?,
+            key_origin: Option::<dbc::KeyOrigin>::strict_decode(&mut d)?,
+        })
+    }
+}
+
+impl StrictEncode for dbc::PreparedTweak {
+    type Error = Error;
+
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        let len = strict_encode_list!(e; self.pubkey, self.key_origin);
+        e.write_all(&self.tweak)?;
+        Ok(len + self.tweak.len() + self.tweaked_pubkey.strict_encode(&mut e)?)
+    }
+}
+
+impl StrictDecode for dbc::PreparedTweak {
+    type Error = Error;
+
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        let pubkey = secp256k1::PublicKey::strict_decode(&mut d)?;
+        let key_origin = Option::<dbc::KeyOrigin>::strict_decode(&mut d)?;
+        let mut tweak = [0u8; 32];
+        d.read_exact(&mut tweak)?;
+        Ok(Self {
+            pubkey,
+            key_origin,
+            tweak,
+            tweaked_pubkey: secp256k1::PublicKey::strict_decode(&mut d)?,
+        })
+    }
+}
+
+impl StrictEncode for dbc::LockscriptContainer {
+    type Error = Error;
+
+    #[inline]
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        Ok(strict_encode_list!(e; self.script, self.pubkey, self.tag))
+    }
+}
+
+impl StrictDecode for dbc::LockscriptContainer {
+    type Error = Error;
+
+    #[inline]
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(Self {
+            script: LockScript::strict_decode(&mut d)?,
+            pubkey: secp256k1::PublicKey::strict_decode(&mut d)?,
+            tag: sha256::Hash::strict_decode(&mut d)?,
+        })
+    }
+}
+
+impl StrictEncode for dbc::ScriptInfo {
+    type Error = Error;
+
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        Ok(match self {
+            dbc::ScriptInfo::None => 0u8.strict_encode(&mut e)?,
+            dbc::ScriptInfo::LockScript(script) => strict_encode_list!(e; 1u8, script),
+            dbc::ScriptInfo::Taproot(hash) => strict_encode_list!(e; 2u8, hash),
+        })
+    }
+}
+
+impl StrictDecode for dbc::ScriptInfo {
+    type Error = Error;
+
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(match u8::strict_decode(&mut d)? {
+            0u8 => dbc::ScriptInfo::None,
+            1u8 => dbc::ScriptInfo::LockScript(LockScript::strict_decode(&mut d)?),
+            2u8 => dbc::ScriptInfo::Taproot(sha256::Hash::strict_decode(&mut d)?),
+            invalid => Err(Error::EnumValueNotKnown(
+                "dbc::ScriptInfo".to_string(),
+                invalid,
+            ))?,
+        })
+    }
+}
+
+impl StrictEncode for dbc::ScriptPubkeyComposition {
+    type Error = Error;
+
+    fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+        use dbc::ScriptPubkeyComposition::*;
+        match self {
+            PublicKey => 0u8,
+            PubkeyHash => 1u8,
+            ScriptHash => 2u8,
+            WPubkeyHash => 3u8,
+            WScriptHash => 4u8,
+            SHWPubkeyHash => 5u8,
+            SHWScriptHash => 6u8,
+            TapRoot => 7u8,
+            OpReturn => 8u8,
+            PlainScript => 9u8,
+        }
+        .strict_encode(e)
+    }
+}
+
+impl StrictDecode for dbc::ScriptPubkeyComposition {
+    type Error = Error;
+
+    fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+        use dbc::ScriptPubkeyComposition::*;
+        Ok(match u8::strict_decode(d)? {
+            0u8 => PublicKey,
+            1u8 => PubkeyHash,
+            2u8 => ScriptHash,
+            3u8 => WPubkeyHash,
+            4u8 => WScriptHash,
+            5u8 => SHWPubkeyHash,
+            6u8 => SHWScriptHash,
+            7u8 => TapRoot,
+            8u8 => OpReturn,
+            9u8 => PlainScript,
+            invalid => Err(Error::EnumValueNotKnown(
+                "dbc::ScriptPubkeyComposition".to_string(),
+                invalid,
+            ))?,
+        })
+    }
+}
+
+impl StrictEncode for dbc::ScriptPubkeyContainer {
+    type Error = Error;
+
+    #[inline]
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        Ok(strict_encode_list!(e;
+            self.pubkey,
+            self.script_info,
+            self.scriptpubkey_composition,
+            self.tag,
+            self.key_origin
+        ))
+    }
+}
+
+impl StrictDecode for dbc::ScriptPubkeyContainer {
+    type Error = Error;
+
+    #[inline]
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(Self {
+            pubkey: secp256k1::PublicKey::strict_decode(&mut d)?,
+            script_info: dbc::ScriptInfo::strict_decode(&mut d)?,
+            scriptpubkey_composition: dbc::ScriptPubkeyComposition::strict_decode(&mut d)?,
+            tag: sha256::Hash::strict_decode(&mut d)?,
+            key_origin: Option::<dbc::KeyOrigin>::strict_decode(&mut d)?,
+        })
+    }
+}
+
+impl StrictEncode for dbc::Proof {
+    type Error = Error;
+
+    #[inline]
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        Ok(strict_encode_list!(e; self.pubkey, self.script_info, self.composition))
+    }
+}
+
+impl StrictDecode for dbc::Proof {
+    type Error = Error;
+
+    #[inline]
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(Self {
+            pubkey: secp256k1::PublicKey::strict_decode(&mut d)?,
+            script_info: dbc::ScriptInfo::strict_decode(&mut d)?,
+            composition: Option::<dbc::ScriptPubkeyComposition>::strict_decode(&mut d)?,
+        })
+    }
+}
+
+impl StrictEncode for dbc::HdContainer {
+    type Error = Error;
+
+    #[inline]
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        Ok(strict_encode_list!(e; self.xpub, self.xpub_origin, self.derivation, self.tag))
+    }
+}
+
+impl StrictDecode for dbc::HdContainer {
+    type Error = Error;
+
+    #[inline]
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(Self {
+            xpub: bip32::ExtendedPubKey::strict_decode(&mut d)?,
+            xpub_origin: Option::<dbc::KeyOrigin>::strict_decode(&mut d)?,
+            derivation: bip32::DerivationPath::strict_decode(&mut d)?,
+            tag: sha256::Hash::strict_decode(&mut d)?,
+        })
+    }
+}
+
+impl StrictEncode for dbc::CommitmentReveal {
+    type Error = Error;
+
+    #[inline]
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        Ok(strict_encode_list!(e; self.outpoint, self.proof, self.protocol_tag, self.message_hash))
+    }
+}
+
+impl StrictDecode for dbc::CommitmentReveal {
+    type Error = Error;
+
+    #[inline]
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(Self {
+            outpoint: OutPoint::strict_decode(&mut d)?,
+            proof: dbc::Proof::strict_decode(&mut d)?,
+            protocol_tag: sha256::Hash::strict_decode(&mut d)?,
+            message_hash: sha256::Hash::strict_decode(&mut d)?,
+        })
+    }
+}
+
+impl StrictEncode for dbc::BatchProof {
+    type Error = Error;
+
+    #[inline]
+    fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+        self.proofs.strict_encode(e)
+    }
+}
+
+impl StrictDecode for dbc::BatchProof {
+    type Error = Error;
+
+    #[inline]
+    fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+        Ok(Self {
+            proofs: BTreeMap::<u32, dbc::Proof>::strict_decode(d)?,
+        })
+    }
+}
+
+impl StrictEncode for ClosingConstraint {
+    type Error = Error;
+
+    #[inline]
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        Ok(strict_encode_list!(e; self.protocol_tag, self.message_hash, self.proof, self.vout))
+    }
+}
+
+impl StrictDecode for ClosingConstraint {
+    type Error = Error;
+
+    #[inline]
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(Self {
+            protocol_tag: sha256::Hash::strict_decode(&mut d)?,
+            message_hash: sha256::Hash::strict_decode(&mut d)?,
+            proof: dbc::Proof::strict_decode(&mut d)?,
+            vout: Option::<u32>::strict_decode(&mut d)?,
+        })
+    }
+}
+
+impl StrictEncode for SealWatch {
+    type Error = Error;
+
+    #[inline]
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        Ok(strict_encode_list!(e; self.seals, self.constraint))
+    }
+}
+
+impl StrictDecode for SealWatch {
+    type Error = Error;
+
+    #[inline]
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(Self {
+            seals: OutpointSet::strict_decode(&mut d)?,
+            constraint: ClosingConstraint::strict_decode(&mut d)?,
+        })
+    }
+}
+
+/// Outpoint set is encoded in a compact form, where outpoints are grouped by
+/// their txid: the number of distinct txids is followed by each of txids in
+/// ascending order, each of them followed by the ascending list of `u32` output
+/// numbers.
+impl StrictEncode for OutpointSet {
+    type Error = Error;
+
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        let txids = self.txids();
+        let mut len = txids.len().strict_encode(&mut e)?;
+        for txid in txids {
+            len += txid.strict_encode(&mut e)?;
+            len += self.vouts(&txid).collect::<Vec<_>>().strict_encode(&mut e)?;
+        }
+        Ok(len)
+    }
+}
+
+/// Decoding requires txids and output numbers to follow strict ascending order,
+/// so each outpoint set has the only valid encoding
+impl StrictDecode for OutpointSet {
+    type Error = Error;
+
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        let mut set = OutpointSet::new();
+        let mut last: Option<OutPoint> = None;
+        for _ in 0..usize::strict_decode(&mut d)? {
+            let txid = Txid::strict_decode(&mut d)?;
+            let vouts = Vec::<u32>::strict_decode(&mut d)?;
+            if vouts.is_empty() {
+                Err(Error::DataIntegrityError(format!(
+                    "Outpoint set encoding contains txid {} without outputs",
+                    txid
+                )))?
+            }
+            for vout in vouts {
+                let outpoint = OutPoint::new(txid, vout);
+                match last {
+                    Some(prev) if prev == outpoint => {
+                        Err(Error::RepeatedValue(format!("{:?}", outpoint)))?
+                    }
+                    Some(prev) if prev > outpoint => Err(Error::DataIntegrityError(format!(
+                        "Outpoint {:?} violates ascending order of outpoint set encoding",
+                        outpoint
+                    )))?,
+                    _ => {}
+                }
+                last = Some(outpoint);
+                set.insert(outpoint);
+            }
+        }
+        Ok(set)
+    }
+}
+
+#[cfg(all(test, feature = "property_tests"))]
+mod test {
+    use super::*;
+    use crate::strict_encoding::test::encoding_roundtrip;
+
+    quickcheck! {
+        fn prop_network(val: Network) -> bool { encoding_roundtrip(&val) }
+        fn prop_short_id(val: ShortId) -> bool { encoding_roundtrip(&val) }
+        fn prop_outpoint_reveal(val: OutpointReveal) -> bool { encoding_roundtrip(&val) }
+        fn prop_outpoint_hash(val: OutpointReveal) -> bool {
+            encoding_roundtrip(&val.outpoint_hash())
+        }
+        fn prop_outpoint(val: OutpointReveal) -> bool {
+            encoding_roundtrip(&OutPoint::from(val))
+        }
+        fn prop_outpoint_set(val: OutpointSet) -> bool { encoding_roundtrip(&val) }
+        fn prop_dbc_script_info(val: dbc::ScriptInfo) -> bool { encoding_roundtrip(&val) }
+        fn prop_dbc_composition(val: dbc::ScriptPubkeyComposition) -> bool {
+            encoding_roundtrip(&val)
+        }
+        fn prop_dbc_proof(val: dbc::Proof) -> bool { encoding_roundtrip(&val) }
+    }
+}
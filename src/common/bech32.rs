@@ -0,0 +1,189 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Bech32 encoding of arbitrary binary data according to BIP-173. Unlike
+//! bitcoin addresses, the encoded strings are not limited to 90 characters,
+//! so the encoding may be used for transferring larger data structures (like
+//! commitment proofs) in a human-readable form.
+
+/// Bech32 alphabet
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Separator between human-readable part and data
+pub const BECH32_SEPARATOR: char = '1';
+
+const CHECKSUM_LEN: usize = 6;
+
+const GENERATOR: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display_from(Debug)]
+pub enum Bech32Error {
+    /// String does not contain separator between human-readable part and
+    /// data
+    NoSeparator,
+
+    /// Human-readable part is empty or contains invalid characters
+    InvalidHrp,
+
+    /// Data part contains character outside of Bech32 alphabet
+    InvalidChar(char),
+
+    /// String contains both lower- and upper-case characters
+    MixedCase,
+
+    InvalidChecksum,
+
+    /// Data part has non-zero or excessive padding bits
+    InvalidPadding,
+}
+
+/// Encodes data into Bech32 string with the given human-readable part, which
+/// must be in lower case
+pub fn encode(hrp: &str, data: &[u8]) -> String {
+    let data = convert_bits(data, 8, 5, true).expect("padded conversion can't fail");
+    let mut values = hrp_expand(hrp);
+    values.extend(&data);
+    values.extend(&[0u8; CHECKSUM_LEN]);
+    let checksum = polymod(&values) ^ 1;
+
+    let mut s = String::with_capacity(hrp.len() + 1 + data.len() + CHECKSUM_LEN);
+    s.push_str(hrp);
+    s.push(BECH32_SEPARATOR);
+    s.extend(data.iter().map(|value| CHARSET[*value as usize] as char));
+    s.extend(
+        (0..CHECKSUM_LEN).map(|i| CHARSET[((checksum >> (5 * (5 - i))) & 0x1f) as usize] as char),
+    );
+    s
+}
+
+/// Decodes Bech32 string into lower-case human-readable part and data
+pub fn decode(s: &str) -> Result<(String, Vec<u8>), Bech32Error> {
+    if s.chars().any(|c| c.is_ascii_lowercase()) && s.chars().any(|c| c.is_ascii_uppercase()) {
+        Err(Bech32Error::MixedCase)?
+    }
+    let s = s.to_ascii_lowercase();
+    let pos = s.rfind(BECH32_SEPARATOR).ok_or(Bech32Error::NoSeparator)?;
+    let (hrp, data) = (&s[..pos], &s[pos + 1..]);
+    if hrp.is_empty() || hrp.bytes().any(|b| b < 33 || b > 126) {
+        Err(Bech32Error::InvalidHrp)?
+    }
+    if data.len() < CHECKSUM_LEN {
+        Err(Bech32Error::InvalidChecksum)?
+    }
+
+    let data = data
+        .chars()
+        .map(|c| {
+            CHARSET
+                .iter()
+                .position(|b| *b as char == c)
+                .map(|value| value as u8)
+                .ok_or(Bech32Error::InvalidChar(c))
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+    let mut values = hrp_expand(hrp);
+    values.extend(&data);
+    if polymod(&values) != 1 {
+        Err(Bech32Error::InvalidChecksum)?
+    }
+
+    let data = convert_bits(&data[..data.len() - CHECKSUM_LEN], 5, 8, false)?;
+    Ok((hrp.to_owned(), data))
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk: u32 = 1;
+    for value in values {
+        let top = chk >> 25;
+        chk = (chk & 0x01ff_ffff) << 5 ^ *value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut values = hrp.bytes().map(|b| b >> 5).collect::<Vec<_>>();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 0x1f));
+    values
+}
+
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, Bech32Error> {
+    let max_value = (1u32 << to) - 1;
+    let max_acc = (1u32 << (from + to - 1)) - 1;
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let mut converted = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for value in data {
+        acc = ((acc << from) | *value as u32) & max_acc;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            converted.push(((acc >> bits) & max_value) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            converted.push(((acc << (to - bits)) & max_value) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max_value) != 0 {
+        Err(Bech32Error::InvalidPadding)?
+    }
+    Ok(converted)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bip173_vectors() {
+        for s in &[
+            "A12UEL5L",
+            "a12uel5l",
+            "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+            "split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+        ] {
+            let (hrp, data) = decode(s).unwrap();
+            assert_eq!(encode(&hrp, &data), s.to_ascii_lowercase());
+        }
+        assert_eq!(decode("A1G7SGD8"), Err(Bech32Error::InvalidChecksum));
+        assert_eq!(decode("pzry9x0s0muk"), Err(Bech32Error::NoSeparator));
+        assert_eq!(decode("1pzry9x0s0muk"), Err(Bech32Error::InvalidHrp));
+        assert_eq!(decode("x1b4n0q5v"), Err(Bech32Error::InvalidChar('b')));
+        assert_eq!(decode("A12uEL5L"), Err(Bech32Error::MixedCase));
+    }
+
+    #[test]
+    fn test_long_data() {
+        let data = (0u8..=255).collect::<Vec<_>>();
+        let s = encode("test", &data);
+        assert!(s.len() > 90);
+        assert_eq!(decode(&s), Ok((s!("test"), data)));
+        assert_eq!(
+            decode(&s.to_ascii_uppercase()),
+            Ok((s!("test"), (0u8..=255).collect()))
+        );
+    }
+}
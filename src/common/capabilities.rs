@@ -0,0 +1,146 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Runtime description of the library build: compiled features, supported
+//! LNPBP standards and encoding format versions, which node software may log
+//! on start and negotiate with counterparties.
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+
+/// Numbers of LNPBP standards implemented by the library
+pub const SUPPORTED_LNPBPS: &[u16] = &[1, 2, 3, 4];
+
+/// Version of the strict encoding format produced by the library
+pub const STRICT_ENCODING_VERSION: u16 = 1;
+
+/// Optional features the library was compiled with
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(crate = "serde_crate"))]
+pub struct FeatureSet {
+    pub serde: bool,
+    pub zmq: bool,
+    pub tokio: bool,
+    /// SPV proof verification; not provided by this version of the library
+    pub spv: bool,
+    /// Electrum server connectivity; not provided by this version of the
+    /// library
+    pub electrum: bool,
+    pub tor: bool,
+    pub lightning: bool,
+    pub rgb: bool,
+    pub bulletproofs: bool,
+}
+
+impl FeatureSet {
+    /// Returns features enabled at compile time
+    pub fn compiled() -> Self {
+        Self {
+            serde: cfg!(feature = "serde"),
+            zmq: cfg!(feature = "zmq"),
+            tokio: cfg!(feature = "tokio"),
+            spv: cfg!(feature = "spv"),
+            electrum: cfg!(feature = "electrum"),
+            tor: cfg!(feature = "tor"),
+            lightning: cfg!(feature = "lightning"),
+            rgb: cfg!(feature = "rgb"),
+            bulletproofs: cfg!(feature = "bulletproofs"),
+        }
+    }
+
+    /// Returns names of the enabled features
+    pub fn names(&self) -> Vec<&'static str> {
+        vec![
+            ("serde", self.serde),
+            ("zmq", self.zmq),
+            ("tokio", self.tokio),
+            ("spv", self.spv),
+            ("electrum", self.electrum),
+            ("tor", self.tor),
+            ("lightning", self.lightning),
+            ("rgb", self.rgb),
+            ("bulletproofs", self.bulletproofs),
+        ]
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name)
+        .collect()
+    }
+}
+
+/// Structured description of the library build
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(crate = "serde_crate"))]
+pub struct Capabilities {
+    /// Version of the library crate
+    pub version: &'static str,
+    pub features: FeatureSet,
+    /// Numbers of supported LNPBP standards
+    pub lnpbps: &'static [u16],
+    /// Version of the strict encoding format
+    pub strict_encoding: u16,
+}
+
+impl Capabilities {
+    /// Checks whether the given LNPBP standard is supported
+    #[inline]
+    pub fn supports_lnpbp(&self, no: u16) -> bool {
+        self.lnpbps.contains(&no)
+    }
+}
+
+impl Display for Capabilities {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "lnpbp v{}; features: {}; LNPBPs: {}; strict encoding v{}",
+            self.version,
+            self.features.names().join(", "),
+            self.lnpbps
+                .iter()
+                .map(u16::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.strict_encoding
+        )
+    }
+}
+
+/// Returns description of the library build
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        features: FeatureSet::compiled(),
+        lnpbps: SUPPORTED_LNPBPS,
+        strict_encoding: STRICT_ENCODING_VERSION,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_capabilities() {
+        let capabilities = capabilities();
+        assert!(capabilities.supports_lnpbp(1));
+        assert!(!capabilities.supports_lnpbp(5));
+        assert_eq!(capabilities.features.serde, cfg!(feature = "serde"));
+        assert!(!capabilities.features.spv);
+
+        let description = capabilities.to_string();
+        assert!(description.starts_with(&format!("lnpbp v{}", env!("CARGO_PKG_VERSION"))));
+        assert!(description.contains("LNPBPs: 1, 2, 3, 4"));
+    }
+}
@@ -0,0 +1,129 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Persistent monotonic counters providing indexes for deterministic
+//! derivation of blinding factors and keys, which must never be reused,
+//! including after application crash and restore
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Source of the unique indexes
+pub trait MonotonicCounter {
+    type Error;
+
+    /// Returns next index. Implementations must persist the counter state
+    /// before returning the index, so the same index is never returned twice.
+    fn next_index(&mut self) -> Result<u64, Self::Error>;
+}
+
+/// Errors of the file-based counter
+#[derive(Debug, Display, From, Error)]
+#[display_from(Debug)]
+pub enum CounterError {
+    /// I/O error accessing counter file
+    #[derive_from]
+    Io(io::Error),
+
+    /// Counter file has invalid size
+    Corrupted,
+
+    /// Counter has reached its maximum value
+    Overflow,
+}
+
+/// Monotonic counter stored in a file. The counter value is updated by
+/// writing a temporary file, syncing it to the disk and renaming it over the
+/// counter file, so the update is atomic: after a crash the file contains
+/// either the previous or the new counter value. Exclusive access from a
+/// single process is assumed.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FileCounter {
+    path: PathBuf,
+}
+
+impl FileCounter {
+    /// Opens counter stored in the file, creating the file with zero counter
+    /// value if it does not exist
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, CounterError> {
+        let counter = Self {
+            path: path.as_ref().to_path_buf(),
+        };
+        if !counter.path.exists() {
+            counter.store(0)?;
+        }
+        Ok(counter)
+    }
+
+    /// Returns index which will be provided by the next call to
+    /// [MonotonicCounter::next_index]
+    pub fn current(&self) -> Result<u64, CounterError> {
+        let data = fs::read(&self.path)?;
+        if data.len() != 8 {
+            Err(CounterError::Corrupted)?
+        }
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(&data);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn store(&self, value: u64) -> Result<(), CounterError> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(&value.to_le_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl MonotonicCounter for FileCounter {
+    type Error = CounterError;
+
+    fn next_index(&mut self) -> Result<u64, Self::Error> {
+        let index = self.current()?;
+        self.store(index.checked_add(1).ok_or(CounterError::Overflow)?)?;
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_file_counter() {
+        let path = std::env::temp_dir().join(format!("lnpbp-counter-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut counter = FileCounter::open(&path).unwrap();
+        assert_eq!(counter.current().unwrap(), 0);
+        assert_eq!(counter.next_index().unwrap(), 0);
+        assert_eq!(counter.next_index().unwrap(), 1);
+
+        let mut restored = FileCounter::open(&path).unwrap();
+        assert_eq!(restored.next_index().unwrap(), 2);
+
+        fs::write(&path, u64::MAX.to_le_bytes()).unwrap();
+        assert!(matches!(restored.next_index(), Err(CounterError::Overflow)));
+        fs::write(&path, [0u8; 3]).unwrap();
+        assert!(matches!(
+            restored.next_index(),
+            Err(CounterError::Corrupted)
+        ));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
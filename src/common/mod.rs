@@ -13,7 +13,11 @@
 
 //! Common data types, structures and functions for LNPBPs
 
+pub mod bech32;
+mod capabilities;
+pub mod counter;
 pub mod strategy;
+pub mod tagged_hash;
 #[macro_use]
 mod macros;
 #[macro_use]
@@ -28,4 +32,7 @@ pub mod service;
 
 #[cfg(feature = "node")]
 pub use service::*;
+pub use capabilities::{
+    capabilities, Capabilities, FeatureSet, STRICT_ENCODING_VERSION, SUPPORTED_LNPBPS,
+};
 pub use wrapper::*;
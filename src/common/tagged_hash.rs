@@ -0,0 +1,48 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Derivation of SHA256 midstates for BIP-340-style tagged hashes, which are
+//! used as the `MIDSTATE_*` constants provided to `tagged_hash!` macro
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+
+/// Computes SHA256 midstate after hashing `SHA256(tag) || SHA256(tag)`,
+/// i.e. the state of the engine used by the tagged hash with the given tag
+/// before it consumes any of the message data. Crates defining their own
+/// tagged hashes must use this function to derive (and test) the midstate
+/// constants from the tag strings.
+pub fn midstate_for_tag(tag: &str) -> sha256::Midstate {
+    let tag_hash = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(&tag_hash[..]);
+    engine.input(&tag_hash[..]);
+    engine.midstate()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bip340_midstate() {
+        // Midstate for BIP-341 "TapLeaf" tag
+        assert_eq!(
+            midstate_for_tag("TapLeaf").into_inner(),
+            [
+                0x9c, 0xe0, 0xe4, 0xe6, 0x7c, 0x11, 0x6c, 0x39, 0x38, 0xb3, 0xca, 0xf2, 0xc3, 0x0f,
+                0x50, 0x89, 0xd3, 0xf3, 0x93, 0x6c, 0x47, 0x63, 0x6e, 0x60, 0x7d, 0xb3, 0x3e, 0xea,
+                0xdd, 0xc6, 0xf0, 0xc9
+            ]
+        );
+    }
+}
@@ -38,8 +38,12 @@
 // TODO: when we will be ready for the release #![deny(missing_docs)]
 // TODO: when we will be ready for the release #![deny(dead_code)]
 
+// Allows `::lnpbp` paths generated by `lnpbp_derive` macros inside the crate
+extern crate self as lnpbp;
 #[macro_use]
 pub extern crate derive_wrapper;
+#[macro_use]
+pub extern crate lnpbp_derive;
 extern crate num_derive;
 extern crate num_traits;
 // We need to export this specific version which is supported by secp256k1zkp
@@ -53,7 +57,7 @@ pub extern crate bitcoin;
 extern crate log;
 
 // Async IO, IPC & networking
-#[cfg(not(feature = "tokio"))]
+#[cfg(feature = "futures")]
 extern crate futures;
 #[cfg(feature = "tokio")]
 extern crate tokio;
@@ -79,6 +83,11 @@ pub extern crate secp256k1zkp;
 #[cfg(feature = "serde")]
 extern crate serde_crate as serde;
 
+// Property-based testing
+#[cfg(all(test, feature = "property_tests"))]
+#[macro_use]
+extern crate quickcheck;
+
 #[macro_use]
 mod paradigms;
 #[macro_use]
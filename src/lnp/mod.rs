@@ -16,6 +16,8 @@
 ///! `lightning` library into layered & modular design
 
 mod peer;
+pub mod relay;
+pub mod rpc;
 mod transport;
 
 pub use peer::*;
@@ -0,0 +1,253 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Store-and-forward relay protocol for the delivery of consignments to the
+//! receivers which are not online at the time of the transfer. Sender submits
+//! a parcel into the receiver mailbox on the relay; receiver polls the
+//! mailbox when online and acknowledges received parcels, after which the
+//! relay may delete them.
+//!
+//! Relay does not need to understand the parcel payload, which normally is a
+//! strict-encoded consignment encrypted to the receiver transport key, so the
+//! payload is kept as opaque data. Messages are strict-encoded and are
+//! expected to be transmitted as [crate::strict_encoding::Frame]s.
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1;
+use num_derive::{FromPrimitive, ToPrimitive};
+
+use crate::strict_encoding::LargeVec;
+
+/// Identifier of the receiver mailbox on the relay
+pub type MailboxId = sha256::Hash;
+
+/// Identifier of the parcel, committing both to the mailbox and the payload
+pub type ParcelId = sha256::Hash;
+
+/// Tag used for the derivation of the mailbox id from the transport key
+const MAILBOX_TAG: &[u8] = b"LNPBP:relay:mailbox";
+
+/// Derives id of the mailbox from the receiver transport key (the one given
+/// in the invoice), so the sender and the receiver can find the same mailbox
+/// without any further interaction. The id is a tagged SHA256 hash of the
+/// compressed key serialization.
+pub fn mailbox_id(transport_key: &secp256k1::PublicKey) -> MailboxId {
+    let tag = sha256::Hash::hash(MAILBOX_TAG);
+    let mut engine = sha256::Hash::engine();
+    engine.input(&tag[..]);
+    engine.input(&tag[..]);
+    engine.input(&transport_key.serialize());
+    sha256::Hash::from_engine(engine)
+}
+
+/// Data submitted by the sender into the receiver mailbox
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct Parcel {
+    pub mailbox: MailboxId,
+    /// Opaque payload, normally an encrypted consignment
+    pub payload: LargeVec<u8>,
+}
+
+impl Parcel {
+    /// Constructs parcel for the given mailbox
+    pub fn with(mailbox: MailboxId, payload: Vec<u8>) -> Self {
+        Self {
+            mailbox,
+            payload: payload.into(),
+        }
+    }
+
+    /// Computes parcel id as SHA256 hash of the mailbox id followed by the
+    /// payload
+    pub fn id(&self) -> ParcelId {
+        let mut engine = sha256::Hash::engine();
+        engine.input(&self.mailbox[..]);
+        engine.input(&self.payload);
+        sha256::Hash::from_engine(engine)
+    }
+}
+
+/// Reason for the rejection of the request by the relay or the receiver
+#[derive(
+    Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display, ToPrimitive, FromPrimitive,
+)]
+#[display_from(Debug)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum RejectReason {
+    /// Message can't be decoded or is not expected by the protocol
+    Malformed = 1,
+    /// Relay does not serve the mailbox
+    UnknownMailbox = 2,
+    /// Acknowledged parcel is not known to the relay
+    UnknownParcel = 3,
+    /// Parcel payload exceeds the size accepted by the relay
+    PayloadTooLarge = 4,
+    /// Mailbox has no more space for new parcels
+    MailboxFull = 5,
+    /// Request rate limit was exceeded; the request may be repeated later
+    RateLimited = 6,
+    /// Relay has failed to process the request
+    InternalError = 7,
+}
+
+/// Messages of the relay protocol
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+#[non_exhaustive]
+pub enum Message {
+    /// Sender to relay: stores the parcel in the mailbox. Relay replies with
+    /// [Message::Ack] or [Message::Reject].
+    Submit(Parcel),
+
+    /// Receiver to relay: requests parcels stored in the mailbox. Relay
+    /// replies with [Message::Deliver] (which may contain no parcels) or
+    /// [Message::Reject].
+    Poll { mailbox: MailboxId },
+
+    /// Relay to receiver: parcels stored in the polled mailbox
+    Deliver { parcels: Vec<Parcel> },
+
+    /// Confirms the parcel with the given id. Sent by the relay to the sender
+    /// when the parcel is stored, and by the receiver to the relay when the
+    /// parcel is received, allowing the relay to delete it.
+    Ack { parcel_id: ParcelId },
+
+    /// Rejects the request, optionally referring to a specific parcel, with
+    /// the typed reason and human-readable details
+    Reject {
+        parcel_id: Option<ParcelId>,
+        reason: RejectReason,
+        details: String,
+    },
+}
+
+impl Message {
+    /// Constructs rejection message with no details
+    #[inline]
+    pub fn reject(parcel_id: Option<ParcelId>, reason: RejectReason) -> Self {
+        Message::Reject {
+            parcel_id,
+            reason,
+            details: s!(""),
+        }
+    }
+}
+
+mod strict_encoding {
+    use super::*;
+    use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
+    use num_traits::{FromPrimitive, ToPrimitive};
+    use std::io;
+
+    impl_enum_strict_encoding!(RejectReason);
+
+    impl StrictEncode for Parcel {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(strict_encode_list!(e; self.mailbox, self.payload))
+        }
+    }
+
+    impl StrictDecode for Parcel {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(Self {
+                mailbox: MailboxId::strict_decode(&mut d)?,
+                payload: LargeVec::strict_decode(&mut d)?,
+            })
+        }
+    }
+
+    impl_tagged_enum_strict_encoding!(Message {
+        Submit(parcel: Parcel) = 1,
+        Poll { mailbox: MailboxId } = 2,
+        Deliver { parcels: Vec<Parcel> } = 3,
+        Ack { parcel_id: ParcelId } = 4,
+        Reject {
+            parcel_id: Option<ParcelId>,
+            reason: RejectReason,
+            details: String
+        } = 5,
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::test::gen_secp_pubkeys;
+    use crate::strict_encoding::{strict_decode, strict_encode};
+
+    fn roundtrip(msg: Message) -> Vec<u8> {
+        let encoded = strict_encode(&msg).unwrap();
+        let decoded: Message = strict_decode(&encoded).unwrap();
+        assert_eq!(decoded, msg);
+        encoded
+    }
+
+    #[test]
+    fn test_mailbox_id() {
+        let keys = gen_secp_pubkeys(2);
+        assert_eq!(mailbox_id(&keys[0]), mailbox_id(&keys[0]));
+        assert_ne!(mailbox_id(&keys[0]), mailbox_id(&keys[1]));
+        assert_ne!(
+            mailbox_id(&keys[0]),
+            sha256::Hash::hash(&keys[0].serialize())
+        );
+    }
+
+    #[test]
+    fn test_relay_messages() {
+        let mailbox = mailbox_id(&gen_secp_pubkeys(1)[0]);
+        let parcel = Parcel::with(mailbox, vec![0xA5; 0x10001]);
+        let other = Parcel::with(mailbox, vec![0x5A; 3]);
+        assert_ne!(parcel.id(), other.id());
+        assert_ne!(
+            parcel.id(),
+            Parcel::with(sha256::Hash::hash(b"other"), parcel.payload.to_vec()).id()
+        );
+
+        let encoded = roundtrip(Message::Submit(parcel.clone()));
+        assert_eq!(encoded[0], 1);
+        assert_eq!(encoded[1..33], mailbox[..]);
+        assert_eq!(encoded.len(), 1 + 32 + 4 + 0x10001);
+
+        assert_eq!(roundtrip(Message::Poll { mailbox })[0], 2);
+        let deliver = Message::Deliver {
+            parcels: vec![parcel.clone(), other],
+        };
+        assert_eq!(roundtrip(deliver)[0], 3);
+        assert_eq!(
+            roundtrip(Message::Ack {
+                parcel_id: parcel.id()
+            }),
+            [&[4u8][..], &parcel.id()[..]].concat()
+        );
+
+        let encoded = roundtrip(Message::Reject {
+            parcel_id: Some(parcel.id()),
+            reason: RejectReason::PayloadTooLarge,
+            details: s!("payload exceeds 65536 bytes"),
+        });
+        assert_eq!(encoded[..2], [5u8, 1]);
+        assert_eq!(encoded[34], 4);
+        let encoded = roundtrip(Message::reject(None, RejectReason::UnknownMailbox));
+        assert_eq!(encoded, vec![5u8, 0, 2, 0, 0]);
+
+        assert!(strict_decode::<Message>(&[6u8]).is_err());
+        assert!(strict_decode::<Message>(&[5u8, 0, 0, 0, 0]).is_err());
+    }
+}
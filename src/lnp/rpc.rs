@@ -0,0 +1,257 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Shared RPC envelope used by LNP/BP node daemons for request/response
+//! communications. Both requests and responses are strict-encoded; the method
+//! payload is kept in strict-encoded form, so each daemon may define its own
+//! set of methods and payload types while using the same wire envelope.
+
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+use crate::strict_encoding::{
+    strict_decode, strict_encode, Error as EncodingError, LargeVec, StrictDecode, StrictEncode,
+};
+
+/// Identifier of the request, used to match it with the response
+pub type RequestId = u64;
+
+/// Type of the requested RPC method, defined by the daemon API
+pub type MethodType = u16;
+
+/// RPC request envelope
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct Request {
+    pub id: RequestId,
+    pub method: MethodType,
+    /// Strict-encoded method arguments
+    pub payload: LargeVec<u8>,
+}
+
+/// RPC response envelope
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct Response {
+    /// Id of the request this response is given for
+    pub id: RequestId,
+    /// Either strict-encoded method result or typed error
+    pub result: Result<LargeVec<u8>, RpcError>,
+}
+
+/// Typed RPC error with numeric code and human-readable message
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Error)]
+pub struct RpcError {
+    pub code: u16,
+    pub message: String,
+}
+
+impl RpcError {
+    /// Request envelope can't be parsed
+    pub const MALFORMED_REQUEST: u16 = 1;
+    /// Method type is not known to the daemon
+    pub const UNKNOWN_METHOD: u16 = 2;
+    /// Method payload can't be decoded or has wrong values
+    pub const INVALID_PAYLOAD: u16 = 3;
+    /// Daemon has failed to process the request
+    pub const INTERNAL_ERROR: u16 = 4;
+    /// Minimal error code value which may be used by the daemons for their
+    /// own method-specific errors
+    pub const APPLICATION_ERRORS_START: u16 = 0x100;
+
+    pub fn new(code: u16, message: &str) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for RpcError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "RPC error {}: {}", self.code, self.message)
+    }
+}
+
+impl From<EncodingError> for RpcError {
+    fn from(err: EncodingError) -> Self {
+        RpcError::new(RpcError::INVALID_PAYLOAD, &err.to_string())
+    }
+}
+
+impl Request {
+    /// Constructs request with strict-encoded payload
+    pub fn with<T>(id: RequestId, method: MethodType, payload: &T) -> Result<Self, T::Error>
+    where
+        T: StrictEncode,
+    {
+        Ok(Self {
+            id,
+            method,
+            payload: strict_encode(payload)?.into(),
+        })
+    }
+
+    /// Decodes request payload
+    #[inline]
+    pub fn payload<T>(&self) -> Result<T, T::Error>
+    where
+        T: StrictDecode,
+    {
+        strict_decode(&*self.payload)
+    }
+}
+
+impl Response {
+    /// Constructs successful response with strict-encoded result
+    pub fn success<T>(id: RequestId, result: &T) -> Result<Self, T::Error>
+    where
+        T: StrictEncode,
+    {
+        Ok(Self {
+            id,
+            result: Ok(strict_encode(result)?.into()),
+        })
+    }
+
+    /// Constructs response reporting an error
+    #[inline]
+    pub fn failure(id: RequestId, error: RpcError) -> Self {
+        Self {
+            id,
+            result: Err(error),
+        }
+    }
+
+    /// Decodes result of the method call, returning [RpcError] if the
+    /// response reports failure or the result can't be decoded
+    pub fn into_result<T>(self) -> Result<T, RpcError>
+    where
+        T: StrictDecode,
+        T::Error: Into<EncodingError>,
+    {
+        let data = self.result?;
+        strict_decode(&*data).map_err(|err: T::Error| RpcError::from(err.into()))
+    }
+}
+
+mod strict_encoding {
+    use super::*;
+    use crate::strict_encoding::Error;
+
+    impl StrictEncode for RpcError {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(strict_encode_list!(e; self.code, self.message))
+        }
+    }
+
+    impl StrictDecode for RpcError {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(Self {
+                code: u16::strict_decode(&mut d)?,
+                message: String::strict_decode(&mut d)?,
+            })
+        }
+    }
+
+    impl StrictEncode for Request {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(strict_encode_list!(e; self.id, self.method, self.payload))
+        }
+    }
+
+    impl StrictDecode for Request {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(Self {
+                id: RequestId::strict_decode(&mut d)?,
+                method: MethodType::strict_decode(&mut d)?,
+                payload: LargeVec::strict_decode(&mut d)?,
+            })
+        }
+    }
+
+    /// Response result is encoded with a single byte significator: `0` for
+    /// the successful result, followed by the result data, or `1` for the
+    /// failure, followed by the encoded [RpcError]
+    impl StrictEncode for Response {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(match self.result {
+                Ok(ref data) => strict_encode_list!(e; self.id, 0u8, data),
+                Err(ref err) => strict_encode_list!(e; self.id, 1u8, err),
+            })
+        }
+    }
+
+    impl StrictDecode for Response {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            let id = RequestId::strict_decode(&mut d)?;
+            let result = match u8::strict_decode(&mut d)? {
+                0u8 => Ok(LargeVec::strict_decode(&mut d)?),
+                1u8 => Err(RpcError::strict_decode(&mut d)?),
+                invalid => Err(Error::EnumValueNotKnown("Response".to_string(), invalid))?,
+            };
+            Ok(Self { id, result })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_request_envelope() {
+        let request = Request::with(7, 0x10, &"some argument".to_string()).unwrap();
+        let encoded = strict_encode(&request).unwrap();
+        assert_eq!(encoded[..10], [7u8, 0, 0, 0, 0, 0, 0, 0, 0x10, 0]);
+        assert_eq!(encoded[10..14], [15u8, 0, 0, 0]);
+        let decoded: Request = strict_decode(&encoded).unwrap();
+        assert_eq!(decoded, request);
+        assert_eq!(decoded.payload::<String>().unwrap(), "some argument");
+        assert!(decoded.payload::<u64>().is_err());
+    }
+
+    #[test]
+    fn test_response_envelope() {
+        let response = Response::success(7, &vec![1u64, 2, 3]).unwrap();
+        let decoded: Response = strict_decode(&strict_encode(&response).unwrap()).unwrap();
+        assert_eq!(decoded, response);
+        assert_eq!(decoded.into_result::<Vec<u64>>().unwrap(), vec![1, 2, 3]);
+
+        let error = RpcError::new(RpcError::UNKNOWN_METHOD, "unknown method 0x10");
+        let response = Response::failure(8, error.clone());
+        let encoded = strict_encode(&response).unwrap();
+        assert_eq!(encoded[8], 1);
+        let decoded: Response = strict_decode(&encoded).unwrap();
+        assert_eq!(decoded, response);
+        assert_eq!(decoded.into_result::<Vec<u64>>(), Err(error));
+
+        let response = Response::success(9, &0u8).unwrap();
+        assert_eq!(
+            response.into_result::<String>().unwrap_err().code,
+            RpcError::INVALID_PAYLOAD
+        );
+    }
+}
@@ -15,7 +15,7 @@ use bitcoin::hashes::sha256;
 use bitcoin::secp256k1;
 
 use crate::bp::dbc::{self, Container, LNPBP1Commitment, LNPBP1Container, Proof};
-use crate::commit_verify::EmbedCommitVerify;
+use crate::commit_verify::{EmbedCommitVerify, Verification};
 
 /// Auxillary structure that can be used for keeping LNPBP-1 commitment-related
 /// information
@@ -43,6 +43,7 @@ pub fn lnpbp1_commit(
         &LNPBP1Container {
             pubkey: pubkey.clone(),
             tag: protocol_tag.clone(),
+            key_origin: None,
         },
         &message,
     )?;
@@ -59,7 +60,7 @@ pub fn lnpbp1_verify(
     proof: secp256k1::PublicKey,
     protocol_tag: sha256::Hash,
     message: &[u8],
-) -> Result<bool, dbc::Error> {
+) -> Result<Verification, dbc::Error> {
     Ok(LNPBP1Commitment::from_inner(commitment).verify(
         &LNPBP1Container::reconstruct(&Proof::from(proof), &protocol_tag, &None)?,
         &message,
@@ -95,7 +96,10 @@ mod test {
             // Here we save only proof
 
             // Later
-            assert_eq!(lnpbp1_verify(commitment, proof, tag, msg), Ok(true));
+            assert_eq!(
+                lnpbp1_verify(commitment, proof, tag, msg),
+                Ok(Verification::Valid)
+            );
         });
     }
 
@@ -11,65 +11,246 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use crate::commit_verify::CommitVerify;
-use bitcoin::hashes::{sha256, sha256d, Hash, HashEngine};
+//! LNPBP-4 multi-message commitments, allowing to commit to messages of
+//! different protocols within a single commitment container.
+
+use crate::commit_verify::{TryCommitVerify, Verification};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
 use rand::Rng;
 use std::collections::BTreeMap;
 
-/// Source data for creation of multimessage commitments according to LNPBP-4 procedure
-type MultiMsg = BTreeMap<u64, sha256::Hash>;
+/// Protocol identifier defining placement of the protocol-specific message
+/// within LNPBP-4 multi-message commitment
+pub type ProtocolId = sha256::Hash;
+
+/// Source data for LNPBP-4 multi-message commitment: message digests under
+/// the identifiers of the protocols they belong to
+pub type MultiCommitMsgs = BTreeMap<ProtocolId, sha256::Hash>;
 
-/// Multimessage commitment data according to LNPBP-4 specification
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Display)]
+/// Errors happening during creation of LNPBP-4 multi-message commitment
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
 #[display_from(Debug)]
-pub struct MultimsgCommitment {
-    data: Vec<u8>,
+pub enum MultiCommitError {
+    /// Protocol ids can't be placed into distinct slots without exceeding
+    /// [MultiCommitBlock::MAX_SLOTS] slots
+    SlotLimitExceeded,
+}
+
+/// Multi-message commitment block according to LNPBP-4. Each message is
+/// placed into the slot defined by its protocol id modulo the number of
+/// slots, which is the smallest number allowing to place all messages into
+/// distinct slots. Remaining slots are filled with digests derived from the
+/// commitment entropy, so the block does not reveal how many messages it
+/// commits to.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct MultiCommitBlock {
+    slots: Vec<sha256::Hash>,
     entropy: u64,
 }
 
-impl CommitVerify<MultiMsg> for MultimsgCommitment {
-    fn commit(multimsg: &MultiMsg) -> Self {
-        const SORT_LIMIT: u64 = 2 << 16;
-
-        let mut n = multimsg.len() as u64;
-        let ordered = loop {
-            let mut ordered = MultiMsg::new();
-            // TODO: Modify arythmetics in LNPBP-4 spec
-            if multimsg
-                .into_iter()
-                .all(|(sort_code, digest)| ordered.insert(sort_code % n, digest.clone()).is_none())
-            {
-                break ordered;
+impl MultiCommitBlock {
+    /// Maximal number of slots in the commitment block, limited by the
+    /// maximal length of strict-encoded vector
+    pub const MAX_SLOTS: usize = 0xFFFF;
+
+    /// Creates commitment block with the given entropy used to fill the
+    /// slots not occupied by the messages
+    pub fn with_entropy(msgs: &MultiCommitMsgs, entropy: u64) -> Result<Self, MultiCommitError> {
+        let mut n = msgs.len();
+        let placement = loop {
+            let mut placement = BTreeMap::<usize, sha256::Hash>::new();
+            if msgs.iter().all(|(protocol_id, msg)| {
+                placement
+                    .insert(Self::slot_index(protocol_id, n), *msg)
+                    .is_none()
+            }) {
+                break placement;
             }
             n += 1;
-            if n > SORT_LIMIT {
-                panic!(
-                    "Memory allocation limit exceeded while trying to sort multimessage commitment"
-                );
+            if n > Self::MAX_SLOTS {
+                Err(MultiCommitError::SlotLimitExceeded)?
             }
         };
 
-        let entropy = {
-            let mut rng = rand::thread_rng();
-            rng.gen::<u64>()
-        };
-        let entropy_digest = {
-            let mut engine = sha256::Hash::engine();
-            engine.input(&entropy.to_le_bytes());
-            sha256::Hash::from_engine(engine)
-        };
-        let mut data: Vec<u8> = vec![];
-        for i in 1..=n {
-            match ordered.get(&i) {
-                Some(digest) => data.extend_from_slice(&digest[..]),
+        let entropy_digest = sha256::Hash::hash(&entropy.to_le_bytes());
+        let slots = (0..n)
+            .map(|index| match placement.get(&index) {
+                Some(msg) => *msg,
                 None => {
-                    let mut engine = sha256d::Hash::engine();
-                    engine.input(&i.to_le_bytes());
+                    let mut engine = sha256::Hash::engine();
+                    engine.input(&(index as u64).to_le_bytes());
                     engine.input(&entropy_digest[..]);
-                    data.extend_from_slice(&sha256d::Hash::from_engine(engine)[..])
+                    sha256::Hash::from_engine(engine)
                 }
-            }
+            })
+            .collect();
+        Ok(Self { slots, entropy })
+    }
+
+    /// Computes index of the slot for the protocol id, which is interpreted
+    /// as a big-endian 256-bit number, for the block with the given number
+    /// of slots
+    pub fn slot_index(protocol_id: &ProtocolId, slots: usize) -> usize {
+        protocol_id[..].iter().fold(0u128, |acc, byte| {
+            ((acc << 8) | *byte as u128) % slots as u128
+        }) as usize
+    }
+
+    /// Number of slots in the commitment block
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    #[inline]
+    pub fn entropy(&self) -> u64 {
+        self.entropy
+    }
+
+    /// Checks that the block contains the message in the slot defined by
+    /// the protocol id
+    pub fn verify_message(&self, protocol_id: &ProtocolId, msg: sha256::Hash) -> bool {
+        if self.slots.is_empty() {
+            return false;
         }
-        Self { data, entropy }
+        self.slots[Self::slot_index(protocol_id, self.slots.len())] == msg
+    }
+}
+
+impl TryCommitVerify<MultiCommitMsgs> for MultiCommitBlock {
+    type Error = MultiCommitError;
+
+    /// Creates commitment block using random entropy
+    fn try_commit(msgs: &MultiCommitMsgs) -> Result<Self, Self::Error> {
+        Self::with_entropy(msgs, rand::thread_rng().gen())
+    }
+
+    /// Verifies that the block commits to exactly the given set of messages
+    /// by repeating the commitment with the block entropy
+    #[inline]
+    fn try_verify(&self, msgs: &MultiCommitMsgs) -> Result<Verification, Self::Error> {
+        Ok(Verification::with_match(
+            Self::with_entropy(msgs, self.entropy)? == *self,
+        ))
+    }
+}
+
+mod strict_encoding {
+    use super::*;
+    use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
+    use std::io;
+
+    impl StrictEncode for MultiCommitBlock {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(self.slots.strict_encode(&mut e)? + self.entropy.strict_encode(&mut e)?)
+        }
+    }
+
+    impl StrictDecode for MultiCommitBlock {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(Self {
+                slots: Vec::strict_decode(&mut d)?,
+                entropy: u64::strict_decode(&mut d)?,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commit_verify::test::gen_messages;
+    use crate::strict_encoding::{strict_decode, strict_encode};
+
+    #[test]
+    fn test_multi_commit_block() {
+        let msgs = gen_messages()
+            .into_iter()
+            .map(|msg| {
+                (
+                    sha256::Hash::hash(&msg),
+                    sha256::Hash::hash(&[&msg[..], &b"message"[..]].concat()),
+                )
+            })
+            .collect::<MultiCommitMsgs>();
+        let block = MultiCommitBlock::with_entropy(&msgs, 1).unwrap();
+        assert!(block.len() >= msgs.len());
+        assert_eq!(MultiCommitBlock::with_entropy(&msgs, 1).unwrap(), block);
+        for (protocol_id, msg) in &msgs {
+            assert!(block.verify_message(protocol_id, *msg));
+            assert!(!block.verify_message(protocol_id, *protocol_id));
+        }
+        assert_eq!(block.try_verify(&msgs), Ok(Verification::Valid));
+
+        let mut other = msgs.clone();
+        other.remove(&sha256::Hash::hash(b""));
+        assert_eq!(block.try_verify_bool(&other), Ok(false));
+        assert_ne!(MultiCommitBlock::with_entropy(&msgs, 2).unwrap(), block);
+
+        let block = MultiCommitBlock::try_commit(&msgs).unwrap();
+        assert_eq!(block.try_verify(&msgs), Ok(Verification::Valid));
+        assert_eq!(
+            strict_decode::<MultiCommitBlock>(&strict_encode(&block).unwrap()).unwrap(),
+            block
+        );
+
+        let empty = MultiCommitBlock::try_commit(&MultiCommitMsgs::new()).unwrap();
+        assert!(empty.is_empty());
+        assert!(!empty.verify_message(&sha256::Hash::hash(b""), sha256::Hash::hash(b"")));
+    }
+
+    #[test]
+    fn test_message_slots() {
+        let protocol_id = |no: u8| {
+            let mut id = [0u8; 32];
+            id[31] = no;
+            ProtocolId::from_inner(id)
+        };
+        let digest = |no: u8| sha256::Hash::from_inner([no; 32]);
+        let msgs: MultiCommitMsgs = bmap! {
+            protocol_id(0) => digest(0),
+            protocol_id(3) => digest(3),
+            protocol_id(6) => digest(6)
+        };
+        let block = MultiCommitBlock::with_entropy(&msgs, 7).unwrap();
+        assert_eq!(block.len(), 4);
+        for (protocol_id, msg) in &msgs {
+            assert!(block.verify_message(protocol_id, *msg));
+        }
+        assert!(!block.verify_message(&protocol_id(1), digest(0)));
+        assert!(!block.verify_message(&protocol_id(0), digest(3)));
+
+        // Slots are numbered from zero and the ones not occupied by messages
+        // are filled with single sha256 digests of the slot index and the
+        // entropy digest
+        let mut engine = sha256::Hash::engine();
+        engine.input(&1u64.to_le_bytes());
+        engine.input(&sha256::Hash::hash(&7u64.to_le_bytes())[..]);
+        assert!(block.verify_message(&protocol_id(1), sha256::Hash::from_engine(engine)));
+    }
+
+    #[test]
+    fn test_slot_limit() {
+        let mut encoded =
+            strict_encode(&MultiCommitBlock::with_entropy(&bmap! {}, 0).unwrap()).unwrap();
+        encoded[..2].copy_from_slice(&[0xFF, 0xFF]);
+        encoded.splice(
+            2..2,
+            vec![0u8; MultiCommitBlock::MAX_SLOTS * sha256::Hash::LEN],
+        );
+        assert_eq!(
+            strict_decode::<MultiCommitBlock>(&encoded).unwrap().len(),
+            MultiCommitBlock::MAX_SLOTS
+        );
     }
 }
@@ -11,10 +11,10 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use super::commit_verify::{self, CommitVerify};
 use super::strict_encoding;
 use bitcoin::hashes::{sha256, sha256d, Hash, HashEngine};
 use std::io;
+use std::ops::Range;
 
 pub trait CommitEncode {
     fn commit_encode<E: io::Write>(self, e: E) -> usize;
@@ -29,6 +29,7 @@ pub mod commit_strategy {
     use super::*;
     use crate::strategy;
     use bitcoin::hashes::Hash;
+    use std::marker::PhantomData;
 
     // Defining strategies:
     pub struct UsingStrict;
@@ -36,6 +37,18 @@ pub mod commit_strategy {
     pub struct FixedBytes;
     pub struct Merklization;
 
+    /// Merklization with the tree shape defined by [MerkleConfig::SETTINGS]
+    pub struct MerklizationWith<C: MerkleConfig>(PhantomData<C>);
+
+    /// Provides settings for [MerklizationWith] strategy
+    pub trait MerkleConfig {
+        const SETTINGS: MerkleSettings;
+    }
+
+    impl MerkleConfig for Merklization {
+        const SETTINGS: MerkleSettings = MerkleSettings::DEFAULT;
+    }
+
     impl<T> CommitEncode for strategy::Holder<T, UsingStrict>
     where
         T: strict_encoding::StrictEncode,
@@ -50,8 +63,8 @@ pub mod commit_strategy {
 
     impl<T> CommitEncode for strategy::Holder<T, UsingConceal>
     where
-        T: Conceal,
-        <T as Conceal>::Confidential: CommitEncode,
+        T: CommitConceal,
+        <T as CommitConceal>::Confidential: CommitEncode,
     {
         fn commit_encode<E: io::Write>(self, e: E) -> usize {
             self.into_inner().conceal().commit_encode(e)
@@ -63,17 +76,27 @@ pub mod commit_strategy {
         T: IntoIterator,
         <T as IntoIterator>::Item: CommitEncode,
     {
+        #[inline]
         fn commit_encode<E: io::Write>(self, e: E) -> usize {
-            merklize(
+            strategy::Holder::<T, MerklizationWith<Merklization>>::new(self.into_inner())
+                .commit_encode(e)
+        }
+    }
+
+    impl<T, C> CommitEncode for strategy::Holder<T, MerklizationWith<C>>
+    where
+        T: IntoIterator,
+        <T as IntoIterator>::Item: CommitEncode,
+        C: MerkleConfig,
+    {
+        fn commit_encode<E: io::Write>(self, e: E) -> usize {
+            merklize_with(
+                &C::SETTINGS,
                 "",
                 &self
                     .into_inner()
                     .into_iter()
-                    .map(|item| {
-                        let mut encoder = io::Cursor::new(vec![]);
-                        item.commit_encode(&mut encoder);
-                        MerkleNode::hash(&encoder.into_inner())
-                    })
+                    .map(merkle_leaf)
                     .collect::<Vec<MerkleNode>>(),
                 0,
             )
@@ -145,26 +168,39 @@ pub mod commit_strategy {
     }
 }
 
-pub trait Conceal {
+/// Data which have revealed and confidential (hashed or committed) forms,
+/// where the confidential form is used in client-side commitments instead of
+/// the revealed data
+pub trait CommitConceal {
+    /// Confidential counterpart of the data
     type Confidential;
+
+    /// Converts revealed data into their confidential form
     fn conceal(&self) -> Self::Confidential;
 }
 
+/// Data which are committed to with a tagged hash of their commitment
+/// encoding. The tag is defined by the commitment type, which must be a
+/// tagged hash (see `tagged_hash!`): the hash engine of the commitment starts
+/// from the tag midstate, so data committing to different commitment types
+/// can't produce the same commitment even if their encodings coincide.
 pub trait ConsensusCommit: Sized + CommitEncode {
-    type Commitment: commit_verify::CommitVerify<Vec<u8>> + bitcoin::hashes::Hash;
+    /// Type of the commitment. It must be unique for each of the committed
+    /// data types, and is shared only by different forms of the same data
+    /// (like revealed and concealed ones), which must produce the same
+    /// commitment.
+    type Commitment: Hash<Engine = sha256::HashEngine>;
 
     #[inline]
     fn consensus_commit(self) -> Self::Commitment {
-        let mut encoder = io::Cursor::new(vec![]);
-        self.commit_encode(&mut encoder);
-        Self::Commitment::commit(&encoder.into_inner())
+        let mut engine = Self::Commitment::engine();
+        self.commit_encode(&mut engine);
+        Self::Commitment::from_engine(engine)
     }
 
     #[inline]
     fn consensus_verify(self, commitment: &Self::Commitment) -> bool {
-        let mut encoder = io::Cursor::new(vec![]);
-        self.commit_encode(&mut encoder);
-        commitment.verify(&encoder.into_inner())
+        self.consensus_commit() == *commitment
     }
 }
 
@@ -214,47 +250,320 @@ mod strict_encode {
             )
         }
     }
-}
 
-pub fn merklize(prefix: &str, data: &[MerkleNode], depth: u16) -> MerkleNode {
-    let len = data.len();
+    impl StrictEncode for MerkleProof {
+        type Error = Error;
+
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(self.position.strict_encode(&mut e)?
+                + self.len.strict_encode(&mut e)?
+                + self.path.strict_encode(&mut e)?)
+        }
+    }
+
+    impl StrictDecode for MerkleProof {
+        type Error = Error;
 
-    let mut height: usize = 0;
-    while ((len + (1 << height) - 1) >> height) > 1 {
-        height += 1;
+        #[inline]
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(Self {
+                position: usize::strict_decode(&mut d)?,
+                len: usize::strict_decode(&mut d)?,
+                path: Vec::<MerkleNode>::strict_decode(&mut d)?,
+            })
+        }
     }
+}
+
+/// Computes Merkle tree leaf for the commitment-encoded item, in the same way
+/// as it is done by [commit_strategy::Merklization]
+pub fn merkle_leaf(item: impl CommitEncode) -> MerkleNode {
+    let mut encoder = io::Cursor::new(vec![]);
+    item.commit_encode(&mut encoder);
+    MerkleNode::hash(&encoder.into_inner())
+}
+
+fn merkle_engine(prefix: &str, depth: u16) -> <MerkleNode as Hash>::Engine {
+    tagged_merkle_engine(&format!("{}:merkle:{}", prefix, depth))
+}
 
+fn tagged_merkle_engine(tag: &str) -> <MerkleNode as Hash>::Engine {
     let mut engine = MerkleNode::engine();
-    let tag = format!("{}:merkle:{}", prefix, depth);
     let tag_hash = sha256::Hash::hash(tag.as_bytes());
     engine.input(&tag_hash[..]);
     engine.input(&tag_hash[..]);
-    match len {
-        0 => {
-            0u8.commit_encode(&mut engine);
-            0u8.commit_encode(&mut engine);
+    engine
+}
+
+/// Way of filling the missing children of the Merkle tree nodes having less
+/// than [MerkleSettings::fanout] children
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display)]
+#[display_from(Debug)]
+pub enum MerklePadding {
+    /// Each missing child is committed to as a single zero byte
+    ZeroByte,
+
+    /// Missing children are replaced by the last present child; nodes
+    /// without children are padded with [MerklePadding::EmptyHash]
+    Duplicate,
+
+    /// Missing children are replaced by the all-zero hash
+    EmptyHash,
+}
+
+/// Parameters defining the shape of the Merkle trees constructed by
+/// [merklize_with] and [commit_strategy::MerklizationWith]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display)]
+#[display_from(Debug)]
+pub struct MerkleSettings {
+    /// Maximum number of children of each tree node; values below 2 are
+    /// treated as 2
+    pub fanout: u8,
+
+    /// Whether leaves are hashed with a separate tag before being committed
+    /// to, distinguishing them from the internal tree nodes
+    pub leaf_tagging: bool,
+
+    /// Padding of the nodes with missing children
+    pub padding: MerklePadding,
+}
+
+impl MerkleSettings {
+    /// Binary tree without leaf tagging and padded with zero bytes, used by
+    /// [merklize] and [commit_strategy::Merklization]
+    pub const DEFAULT: Self = Self {
+        fanout: 2,
+        leaf_tagging: false,
+        padding: MerklePadding::ZeroByte,
+    };
+
+    #[inline]
+    fn fanout(&self) -> usize {
+        self.fanout.max(2) as usize
+    }
+
+    /// Splits `len` items into `fanout` ranges of (almost) equal size
+    fn split(&self, len: usize) -> Vec<Range<usize>> {
+        let fanout = self.fanout();
+        (0..fanout)
+            .map(|no| len * no / fanout..len * (no + 1) / fanout)
+            .collect()
+    }
+
+    /// Commits to the lowest-level tree node having leaves as its children
+    fn bottom_node(&self, prefix: &str, leaves: &[MerkleNode], depth: u16) -> MerkleNode {
+        let mut engine = merkle_engine(prefix, depth);
+        let leaves = leaves
+            .iter()
+            .map(|leaf| {
+                if !self.leaf_tagging {
+                    return *leaf;
+                }
+                let mut engine = tagged_merkle_engine(&format!("{}:merkle:leaf", prefix));
+                leaf.commit_encode(&mut engine);
+                MerkleNode::from_engine(engine)
+            })
+            .collect::<Vec<_>>();
+        for leaf in &leaves {
+            leaf.commit_encode(&mut engine);
         }
-        1 => {
-            data.first()
-                .expect("We know that we have one element")
-                .commit_encode(&mut engine);
-            0u8.commit_encode(&mut engine);
+        for _ in leaves.len()..self.fanout() {
+            match (self.padding, leaves.last()) {
+                (MerklePadding::ZeroByte, _) => 0u8.commit_encode(&mut engine),
+                (MerklePadding::Duplicate, Some(last)) => last.commit_encode(&mut engine),
+                (MerklePadding::Duplicate, None) | (MerklePadding::EmptyHash, _) => {
+                    MerkleNode::from_inner([0u8; 32]).commit_encode(&mut engine)
+                }
+            };
         }
-        2 => {
-            data.first()
-                .expect("We know that we have at least two elements")
-                .commit_encode(&mut engine);
-            data.last()
-                .expect("We know that we have at least two elements")
-                .commit_encode(&mut engine);
+        MerkleNode::from_engine(engine)
+    }
+}
+
+impl Default for MerkleSettings {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+pub fn merklize(prefix: &str, data: &[MerkleNode], depth: u16) -> MerkleNode {
+    merklize_with(&MerkleSettings::DEFAULT, prefix, data, depth)
+}
+
+/// Computes root of the Merkle tree over the data with the shape defined by
+/// the settings
+pub fn merklize_with(
+    settings: &MerkleSettings,
+    prefix: &str,
+    data: &[MerkleNode],
+    depth: u16,
+) -> MerkleNode {
+    if data.len() <= settings.fanout() {
+        return settings.bottom_node(prefix, data, depth);
+    }
+    let mut engine = merkle_engine(prefix, depth);
+    for range in settings.split(data.len()) {
+        merklize_with(settings, prefix, &data[range], depth + 1).commit_encode(&mut engine);
+    }
+    MerkleNode::from_engine(engine)
+}
+
+/// Proof of inclusion of a single leaf into the Merkle tree constructed by
+/// [merklize] (or [merklize_with]), allowing to verify the inclusion against
+/// the tree root without the knowledge of the rest of the tree leaves
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display_from(Debug)]
+pub struct MerkleProof {
+    /// Position of the leaf in the merklized data
+    pub position: usize,
+
+    /// Total number of the merklized leaves, defining the tree structure
+    pub len: usize,
+
+    /// Hashes of the sibling nodes on the path from the leaf to the root,
+    /// starting from the leaf level. Siblings of the same level are listed
+    /// in the order of their position in the tree.
+    pub path: Vec<MerkleNode>,
+}
+
+impl MerkleProof {
+    /// Generates proof of inclusion of the leaf at the given position into
+    /// the tree constructed by [merklize] with the same prefix over the
+    /// same data. Returns `None` if the position is outside of the data.
+    #[inline]
+    pub fn with(prefix: &str, data: &[MerkleNode], position: usize) -> Option<Self> {
+        Self::with_settings(&MerkleSettings::DEFAULT, prefix, data, position)
+    }
+
+    /// Generates proof of inclusion for the tree constructed by
+    /// [merklize_with] with the same settings
+    pub fn with_settings(
+        settings: &MerkleSettings,
+        prefix: &str,
+        data: &[MerkleNode],
+        position: usize,
+    ) -> Option<Self> {
+        if position >= data.len() {
+            return None;
         }
-        _ => {
-            let div = len / 2;
-            merklize(prefix, &data[0..div], depth + 1).commit_encode(&mut engine);
-            merklize(prefix, &data[div..], depth + 1).commit_encode(&mut engine);
+        let mut levels = vec![];
+        let (mut slice, mut index, mut depth) = (data, position, 0u16);
+        while slice.len() > settings.fanout() {
+            let ranges = settings.split(slice.len());
+            let child = ranges
+                .iter()
+                .position(|range| range.contains(&index))
+                .expect("Ranges cover all of the slice items");
+            levels.push(
+                ranges
+                    .iter()
+                    .enumerate()
+                    .filter(|(no, _)| *no != child)
+                    .map(|(_, range)| {
+                        merklize_with(settings, prefix, &slice[range.clone()], depth + 1)
+                    })
+                    .collect::<Vec<_>>(),
+            );
+            index -= ranges[child].start;
+            slice = &slice[ranges[child].clone()];
+            depth += 1;
         }
+        let mut path = slice
+            .iter()
+            .enumerate()
+            .filter(|(no, _)| *no != index)
+            .map(|(_, node)| *node)
+            .collect::<Vec<_>>();
+        path.extend(levels.into_iter().rev().flatten());
+        Some(Self {
+            position,
+            len: data.len(),
+            path,
+        })
     }
-    MerkleNode::from_engine(engine)
+
+    /// Computes Merkle tree root from the leaf and the proof. Returns `None`
+    /// if the proof structure does not match the number of the leaves.
+    #[inline]
+    pub fn root(&self, prefix: &str, leaf: MerkleNode) -> Option<MerkleNode> {
+        self.root_with(&MerkleSettings::DEFAULT, prefix, leaf)
+    }
+
+    /// Computes root of the Merkle tree constructed with the given settings
+    /// from the leaf and the proof
+    pub fn root_with(
+        &self,
+        settings: &MerkleSettings,
+        prefix: &str,
+        leaf: MerkleNode,
+    ) -> Option<MerkleNode> {
+        if self.position >= self.len {
+            return None;
+        }
+        let mut levels = vec![];
+        let (mut len, mut index, mut depth) = (self.len, self.position, 0u16);
+        while len > settings.fanout() {
+            let ranges = settings.split(len);
+            let child = ranges.iter().position(|range| range.contains(&index))?;
+            index -= ranges[child].start;
+            len = ranges[child].len();
+            levels.push((child, depth));
+            depth += 1;
+        }
+
+        let mut path = self.path.iter();
+        let leaves = (0..len)
+            .map(|no| {
+                if no == index {
+                    Some(leaf)
+                } else {
+                    path.next().copied()
+                }
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let mut node = settings.bottom_node(prefix, &leaves, depth);
+        for (child, depth) in levels.into_iter().rev() {
+            let mut engine = merkle_engine(prefix, depth);
+            for no in 0..settings.fanout() {
+                if no == child {
+                    node.commit_encode(&mut engine);
+                } else {
+                    path.next()?.commit_encode(&mut engine);
+                }
+            }
+            node = MerkleNode::from_engine(engine);
+        }
+        match path.next() {
+            None => Some(node),
+            Some(_) => None,
+        }
+    }
+}
+
+/// Verifies that the leaf is included into the Merkle tree with the given
+/// root, constructed by [merklize] with the same prefix
+#[inline]
+pub fn verify_inclusion(
+    prefix: &str,
+    root: MerkleNode,
+    leaf: MerkleNode,
+    proof: &MerkleProof,
+) -> bool {
+    verify_inclusion_with(&MerkleSettings::DEFAULT, prefix, root, leaf, proof)
+}
+
+/// Verifies that the leaf is included into the Merkle tree with the given
+/// root, constructed by [merklize_with] with the same settings and prefix
+pub fn verify_inclusion_with(
+    settings: &MerkleSettings,
+    prefix: &str,
+    root: MerkleNode,
+    leaf: MerkleNode,
+    proof: &MerkleProof,
+) -> bool {
+    proof.root_with(settings, prefix, leaf) == Some(root)
 }
 
 /*
@@ -323,3 +632,143 @@ mod test {
     }
 }
 */
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::strict_encoding::{strict_decode, strict_encode};
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_merkle_proof() {
+        for len in 1..=9usize {
+            let data = (0..len)
+                .map(|no| merkle_leaf(no as u64))
+                .collect::<Vec<MerkleNode>>();
+            let root = merklize("test", &data, 0);
+            assert_eq!(MerkleProof::with("test", &data, len), None);
+            for position in 0..len {
+                let proof = MerkleProof::with("test", &data, position).unwrap();
+                assert!(verify_inclusion("test", root, data[position], &proof));
+                assert!(!verify_inclusion("", root, data[position], &proof));
+                assert_eq!(
+                    strict_decode::<MerkleProof>(&strict_encode(&proof).unwrap()).unwrap(),
+                    proof
+                );
+
+                let mut extended = proof.clone();
+                extended.path.push(root);
+                assert!(!verify_inclusion("test", root, data[position], &extended));
+
+                if len > 1 {
+                    let other = data[(position + 1) % len];
+                    assert!(!verify_inclusion("test", root, other, &proof));
+                    let mut truncated = proof.clone();
+                    truncated.path.pop();
+                    assert!(!verify_inclusion("test", root, data[position], &truncated));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_merkle_settings() {
+        let data = (0..17u64).map(merkle_leaf).collect::<Vec<MerkleNode>>();
+        assert_eq!(
+            merklize_with(&MerkleSettings::default(), "test", &data, 0),
+            merklize("test", &data, 0)
+        );
+
+        let variants = vec![
+            MerkleSettings::DEFAULT,
+            MerkleSettings {
+                leaf_tagging: true,
+                ..MerkleSettings::DEFAULT
+            },
+            MerkleSettings {
+                padding: MerklePadding::Duplicate,
+                ..MerkleSettings::DEFAULT
+            },
+            MerkleSettings {
+                fanout: 4,
+                leaf_tagging: true,
+                padding: MerklePadding::EmptyHash,
+            },
+            MerkleSettings {
+                fanout: 16,
+                leaf_tagging: false,
+                padding: MerklePadding::Duplicate,
+            },
+        ];
+        let roots = variants
+            .iter()
+            .map(|settings| merklize_with(settings, "test", &data[..3], 0))
+            .collect::<BTreeSet<_>>();
+        assert_eq!(roots.len(), variants.len());
+
+        for settings in &variants {
+            for len in 1..=data.len() {
+                let data = &data[..len];
+                let root = merklize_with(settings, "test", data, 0);
+                for position in 0..len {
+                    let proof =
+                        MerkleProof::with_settings(settings, "test", data, position).unwrap();
+                    assert!(verify_inclusion_with(
+                        settings,
+                        "test",
+                        root,
+                        data[position],
+                        &proof
+                    ));
+                    if len > 1 {
+                        let other = data[(position + 1) % len];
+                        assert!(!verify_inclusion_with(
+                            settings, "test", root, other, &proof
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    // Skipped fields are never read, since they are not committed to
+    #[allow(dead_code)]
+    #[derive(CommitEncode)]
+    #[commit_encode(strategy = "strict")]
+    struct Cached {
+        data: Vec<u8>,
+        #[commit_encode(skip)]
+        cache: Option<MerkleNode>,
+        nonce: u64,
+    }
+
+    #[allow(dead_code)]
+    #[derive(CommitEncode)]
+    #[commit_encode(strategy = "strict")]
+    struct Wrapper(#[commit_encode(skip)] usize, String);
+
+    fn commit_serialize(item: impl CommitEncode) -> Vec<u8> {
+        let mut encoded = vec![];
+        let len = item.commit_encode(&mut encoded);
+        assert_eq!(len, encoded.len());
+        encoded
+    }
+
+    #[test]
+    fn test_derive_commit_encode() {
+        let item = |cache| Cached {
+            data: vec![0xA5; 7],
+            cache,
+            nonce: 42,
+        };
+        let mut expected = strict_encode(&vec![0xA5u8; 7]).unwrap();
+        expected.extend(strict_encode(&42u64).unwrap());
+        assert_eq!(commit_serialize(item(None)), expected);
+        assert_eq!(commit_serialize(item(Some(merkle_leaf(1u64)))), expected);
+
+        assert_eq!(
+            commit_serialize(Wrapper(1, "test".to_string())),
+            strict_encode(&"test".to_string()).unwrap()
+        );
+    }
+}
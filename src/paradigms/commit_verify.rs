@@ -15,6 +15,69 @@
 //! embedded commitments (commit-embed-verify), required for detarministic
 //! bitcoin commitments (LNPBP1-3 standards).
 
+use bitcoin::hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
+
+/// Reason for the commitment verification failure
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum InvalidReason {
+    /// Commitment does not correspond to the message
+    CommitmentMismatch,
+    /// Message can't be committed into the container, so the commitment
+    /// can't correspond to it
+    ContainerMismatch,
+}
+
+/// Result of the commitment verification. Unlike plain `bool` it can't be
+/// silently ignored and tells why the verification has failed.
+#[must_use]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum Verification {
+    /// Commitment corresponds to the message
+    Valid,
+    /// Commitment does not correspond to the message
+    Invalid(InvalidReason),
+}
+
+impl Verification {
+    /// Converts comparison of the commitments into verification result
+    #[inline]
+    pub fn with_match(matches: bool) -> Self {
+        if matches {
+            Verification::Valid
+        } else {
+            Verification::Invalid(InvalidReason::CommitmentMismatch)
+        }
+    }
+
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        *self == Verification::Valid
+    }
+
+    #[inline]
+    pub fn is_invalid(&self) -> bool {
+        !self.is_valid()
+    }
+
+    /// Returns reason of the verification failure, if any
+    #[inline]
+    pub fn reason(&self) -> Option<InvalidReason> {
+        match self {
+            Verification::Valid => None,
+            Verification::Invalid(reason) => Some(*reason),
+        }
+    }
+}
+
+impl From<Verification> for bool {
+    #[inline]
+    fn from(verification: Verification) -> Self {
+        verification.is_valid()
+    }
+}
+
 /// Trait for commit-verify scheme. A message for the commitment may be any
 /// structure that can be represented as a byte array (i.e. implements
 /// `AsRef<[u8]>`).
@@ -28,8 +91,14 @@ where
     /// Verifies commitment against the message; default implementation just
     /// repeats the commitment to the message and check it against the `self`.
     #[inline]
-    fn verify(&self, msg: &MSG) -> bool {
-        Self::commit(msg) == *self
+    fn verify(&self, msg: &MSG) -> Verification {
+        Verification::with_match(Self::commit(msg) == *self)
+    }
+
+    /// Compatibility adapter for [CommitVerify::verify] returning `bool`
+    #[inline]
+    fn verify_bool(&self, msg: &MSG) -> bool {
+        self.verify(msg).is_valid()
     }
 }
 
@@ -49,8 +118,15 @@ where
     /// Tries to verify commitment against the message; default implementation just
     /// repeats the commitment to the message and check it against the `self`.
     #[inline]
-    fn try_verify(&self, msg: &MSG) -> Result<bool, Self::Error> {
-        Ok(Self::try_commit(msg)? == *self)
+    fn try_verify(&self, msg: &MSG) -> Result<Verification, Self::Error> {
+        Ok(Verification::with_match(Self::try_commit(msg)? == *self))
+    }
+
+    /// Compatibility adapter for [TryCommitVerify::try_verify] returning
+    /// `bool`
+    #[inline]
+    fn try_verify_bool(&self, msg: &MSG) -> Result<bool, Self::Error> {
+        Ok(self.try_verify(msg)?.is_valid())
     }
 }
 
@@ -89,22 +165,91 @@ where
     /// reconstructs the original container with [container] function,
     /// repeats the commitment to the message and check it against the `self`.
     ///
-    /// Verification is a failable procedure returning bool. The difference
-    /// between returning `Ok(false)` and `Err(_)` is the following:
+    /// Verification is a failable procedure returning [Verification]. The
+    /// difference between returning `Ok(Verification::Invalid(_))` and
+    /// `Err(_)` is the following:
     /// * `Err(_)`: validation was not possible due to container data structure-
     ///   related error or some internal error during the validation process.
     ///   It is undefined whether the message corresponds to the commitment.
-    /// * `Ok(false)`: validation was performed completely; the message does
-    ///   not correspond to the commitment
-    /// * `Ok(true)`: validation was performed completely; the message does
-    ///   correspond to the commitment
+    /// * `Ok(Verification::Invalid(_))`: validation was performed completely;
+    ///   the message does not correspond to the commitment
+    /// * `Ok(Verification::Valid)`: validation was performed completely; the
+    ///   message does correspond to the commitment
     #[inline]
-    fn verify(&self, container: &Self::Container, msg: &MSG) -> Result<bool, Self::Error> {
+    fn verify(&self, container: &Self::Container, msg: &MSG) -> Result<Verification, Self::Error> {
         Ok(match Self::embed_commit(container, msg) {
-            Ok(commitment) => commitment == *self,
-            Err(_) => false,
+            Ok(commitment) => Verification::with_match(commitment == *self),
+            Err(_) => Verification::Invalid(InvalidReason::ContainerMismatch),
         })
     }
+
+    /// Compatibility adapter for [EmbedCommitVerify::verify] returning `bool`
+    #[inline]
+    fn verify_bool(&self, container: &Self::Container, msg: &MSG) -> Result<bool, Self::Error> {
+        Ok(self.verify(container, msg)?.is_valid())
+    }
+}
+
+/// Commitment to a message bound to a shared secret key: HMAC-SHA256 of the
+/// message with the key. Unlike hash commitments, it can be produced and
+/// verified only by the parties knowing the key, which makes it suitable for
+/// things like LN channel state hints. The key acts as a container for
+/// [EmbedCommitVerify], so the commitment is verified with the same API as
+/// the rest of the commitments.
+///
+/// Commitments are compared in constant time, so the timing of the
+/// comparison does not leak the HMAC value.
+#[derive(Clone, Copy, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct KeyedCommitment(Hmac<sha256::Hash>);
+
+impl PartialEq for KeyedCommitment {
+    fn eq(&self, other: &Self) -> bool {
+        self.0
+            .into_inner()
+            .iter()
+            .zip(other.0.into_inner().iter())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+    }
+}
+
+impl Eq for KeyedCommitment {}
+
+impl KeyedCommitment {
+    /// Creates commitment to the message with the shared secret key
+    pub fn with(key: &[u8; 32], msg: impl AsRef<[u8]>) -> Self {
+        let mut engine = HmacEngine::<sha256::Hash>::new(key);
+        engine.input(msg.as_ref());
+        Self(Hmac::from_engine(engine))
+    }
+
+    /// Returns HMAC value of the commitment
+    #[inline]
+    pub fn as_inner(&self) -> &Hmac<sha256::Hash> {
+        &self.0
+    }
+}
+
+impl<MSG> EmbedCommitVerify<MSG> for KeyedCommitment
+where
+    MSG: AsRef<[u8]>,
+{
+    /// Shared secret key
+    type Container = [u8; 32];
+    type Error = std::convert::Infallible;
+
+    #[inline]
+    fn embed_commit(key: &Self::Container, msg: &MSG) -> Result<Self, Self::Error> {
+        Ok(Self::with(key, msg))
+    }
+
+    /// Verifies commitment in constant time, so the timing of the
+    /// verification does not leak the expected HMAC value
+    #[inline]
+    fn verify(&self, key: &Self::Container, msg: &MSG) -> Result<Verification, Self::Error> {
+        Ok(Verification::with_match(*self == Self::with(key, msg)))
+    }
 }
 
 #[cfg(test)]
@@ -191,17 +336,20 @@ pub(crate) mod test {
                 });
 
                 // Testing verification
-                assert!(commitment.verify(msg));
+                assert_eq!(commitment.verify(msg), Verification::Valid);
 
                 messages.iter().for_each(|m| {
                     // Testing that commitment verification succeeds only
                     // for the original message and fails for the rest
-                    assert_eq!(commitment.verify(m), m == msg);
+                    assert_eq!(commitment.verify_bool(m), m == msg);
                 });
 
                 acc.iter().for_each(|cmt| {
                     // Testing that verification against other commitments returns `false`
-                    assert_eq!(cmt.verify(msg), false);
+                    assert_eq!(
+                        cmt.verify(msg),
+                        Verification::Invalid(InvalidReason::CommitmentMismatch)
+                    );
                 });
 
                 // Detecting collision
@@ -231,17 +379,20 @@ pub(crate) mod test {
                 });
 
                 // Testing verification
-                assert!(commitment.verify(container, msg).unwrap());
+                assert_eq!(
+                    commitment.verify(container, msg).unwrap(),
+                    Verification::Valid
+                );
 
                 messages.iter().for_each(|m| {
                     // Testing that commitment verification succeeds only
                     // for the original message and fails for the rest
-                    assert_eq!(commitment.verify(container, m).unwrap(), m == msg);
+                    assert_eq!(commitment.verify_bool(container, m).unwrap(), m == msg);
                 });
 
                 acc.iter().for_each(|cmt| {
                     // Testing that verification against other commitments returns `false`
-                    assert_eq!(cmt.verify(container, msg).unwrap(), false);
+                    assert!(cmt.verify(container, msg).unwrap().is_invalid());
                 });
 
                 // Detecting collision
@@ -261,4 +412,40 @@ pub(crate) mod test {
     fn test_embed_commit() {
         embed_commit_verify_suite::<Vec<u8>, DummyVec>(gen_messages(), &DummyVec(vec![]));
     }
+
+    #[test]
+    fn test_keyed_commitment() {
+        let key = [0x42u8; 32];
+        embed_commit_verify_suite::<Vec<u8>, KeyedCommitment>(gen_messages(), &key);
+
+        let commitment = KeyedCommitment::with(&key, b"state hint");
+        assert!(commitment.verify(&key, &b"state hint").unwrap().is_valid());
+        assert_eq!(
+            commitment
+                .verify(&[0x43u8; 32], &b"state hint")
+                .unwrap()
+                .reason(),
+            Some(InvalidReason::CommitmentMismatch)
+        );
+        assert_ne!(
+            commitment,
+            KeyedCommitment::with(&[0x43u8; 32], b"state hint")
+        );
+    }
+
+    #[test]
+    fn test_keyed_commitment_eq() {
+        use bitcoin::hashes::Hash as _;
+
+        let key = [0x42u8; 32];
+        let commitment = KeyedCommitment::with(&key, b"state hint");
+        assert_eq!(commitment, KeyedCommitment::with(&key, b"state hint"));
+
+        // Comparison must take into account all of the HMAC bytes
+        for index in 0..32 {
+            let mut hmac = commitment.as_inner().into_inner();
+            hmac[index] ^= 0x01;
+            assert_ne!(commitment, KeyedCommitment(Hmac::from_inner(hmac)));
+        }
+    }
 }
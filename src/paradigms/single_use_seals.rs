@@ -96,6 +96,8 @@
 //!   and Proof-of-Publication. 1. Single-Use-Seal Definition.
 //!   <https://petertodd.org/2017/scalable-single-use-seal-asset-transfer>
 
+use crate::commit_verify::Verification;
+
 /// Message type that can be used to close the seal over it
 pub type Message = dyn AsRef<[u8]>;
 
@@ -122,7 +124,7 @@ pub trait SingleUseSeal {
     /// seal itself; all the data produced by the process must be placed
     /// into the returned Witness type
     fn close(&self, over: &Message) -> Result<Self::Witness, Self::Error>;
-    fn verify(&self, msg: &Message, witness: &Self::Witness) -> Result<bool, Self::Error>;
+    fn verify(&self, msg: &Message, witness: &Self::Witness) -> Result<Verification, Self::Error>;
 }
 
 /// Trait for proof-of-publication medium on which the seals are defined and
@@ -11,6 +11,184 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+//! Strict encoding (LNPBP-5/6) of the data structures used in client-side
+//! validation and network protocols.
+//!
+//! # Implementing strict encoding for custom types
+//!
+//! Structures are encoded as a sequence of their fields; use
+//! [strict_encode_list!] to encode them and to compute the encoded length:
+//!
+//! ```
+//! use lnpbp::strict_encode_list;
+//! use lnpbp::strict_encoding::{self, strict_decode, strict_encode};
+//! use lnpbp::strict_encoding::{StrictDecode, StrictEncode};
+//! use std::io;
+//!
+//! #[derive(Clone, PartialEq, Debug)]
+//! struct Coin {
+//!     amount: u64,
+//!     memo: Option<String>,
+//! }
+//!
+//! impl StrictEncode for Coin {
+//!     type Error = strict_encoding::Error;
+//!
+//!     fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+//!         Ok(strict_encode_list!(e; self.amount, self.memo))
+//!     }
+//! }
+//!
+//! impl StrictDecode for Coin {
+//!     type Error = strict_encoding::Error;
+//!
+//!     fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+//!         Ok(Self {
+//!             amount: u64::strict_decode(&mut d)?,
+//!             memo: Option::<String>::strict_decode(&mut d)?,
+//!         })
+//!     }
+//! }
+//!
+//! let coin = Coin {
+//!     amount: 1000,
+//!     memo: None,
+//! };
+//! let data = strict_encode(&coin).unwrap();
+//! assert_eq!(data, vec![0xE8, 0x03, 0, 0, 0, 0, 0, 0, 0]);
+//! assert_eq!(strict_decode::<Coin>(&data).unwrap(), coin);
+//! ```
+//!
+//! C-like enums with `u8` representation are encoded as a single byte with
+//! [impl_enum_strict_encoding!]; enums with associated data are encoded as a
+//! `u8` tag followed by the variant fields with
+//! [impl_tagged_enum_strict_encoding!]:
+//!
+//! ```
+//! use lnpbp::strict_encoding::{self, strict_decode, strict_encode};
+//! use lnpbp::strict_encoding::{Error, StrictDecode, StrictEncode};
+//! use lnpbp::{impl_enum_strict_encoding, impl_tagged_enum_strict_encoding};
+//! use num_derive::{FromPrimitive, ToPrimitive};
+//! use num_traits::{FromPrimitive, ToPrimitive};
+//!
+//! #[derive(Clone, Copy, PartialEq, Debug, FromPrimitive, ToPrimitive)]
+//! #[repr(u8)]
+//! enum Color {
+//!     Red = 1,
+//!     Green = 2,
+//! }
+//! impl_enum_strict_encoding!(Color);
+//!
+//! #[derive(Clone, PartialEq, Debug)]
+//! enum Shape {
+//!     Point,
+//!     Circle(u16),
+//!     Rect { width: u16, height: u16 },
+//! }
+//! impl_tagged_enum_strict_encoding!(Shape {
+//!     Point = 0,
+//!     Circle(radius: u16) = 1,
+//!     Rect { width: u16, height: u16 } = 2,
+//! });
+//!
+//! assert_eq!(strict_encode(&Color::Green).unwrap(), vec![2]);
+//! assert!(strict_decode::<Color>(&[3u8]).is_err());
+//!
+//! let rect = Shape::Rect {
+//!     width: 4,
+//!     height: 3,
+//! };
+//! assert_eq!(strict_encode(&rect).unwrap(), vec![2, 4, 0, 3, 0]);
+//! assert_eq!(strict_decode::<Shape>(&[1u8, 7, 0]).unwrap(), Shape::Circle(7));
+//! assert!(matches!(
+//!     strict_decode::<Shape>(&[3u8]),
+//!     Err(strict_encoding::Error::EnumValueNotKnown(_, 3))
+//! ));
+//! ```
+//!
+//! Wrapper types delegate to the wrapped value, so their encoding is
+//! indistinguishable from the encoding of the inner type. Types with
+//! additional invariants may use their own error type, which must implement
+//! [std::error::Error] and `From<strict_encoding::Error>`, so the errors of
+//! the nested data can be propagated with `?`:
+//!
+//! ```
+//! use lnpbp::strict_encoding::{self, strict_decode, strict_encode};
+//! use lnpbp::strict_encoding::{StrictDecode, StrictEncode};
+//! use std::{fmt, io};
+//!
+//! #[derive(Debug)]
+//! enum TitleError {
+//!     Encoding(strict_encoding::Error),
+//!     Empty,
+//! }
+//!
+//! impl fmt::Display for TitleError {
+//!     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+//!         write!(f, "{:?}", self)
+//!     }
+//! }
+//!
+//! impl std::error::Error for TitleError {}
+//!
+//! impl From<strict_encoding::Error> for TitleError {
+//!     fn from(err: strict_encoding::Error) -> Self {
+//!         TitleError::Encoding(err)
+//!     }
+//! }
+//!
+//! #[derive(Clone, PartialEq, Debug)]
+//! struct Title(String);
+//!
+//! impl StrictEncode for Title {
+//!     type Error = TitleError;
+//!
+//!     fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+//!         Ok(self.0.strict_encode(e)?)
+//!     }
+//! }
+//!
+//! impl StrictDecode for Title {
+//!     type Error = TitleError;
+//!
+//!     fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+//!         let title = String::strict_decode(d)?;
+//!         if title.is_empty() {
+//!             return Err(TitleError::Empty);
+//!         }
+//!         Ok(Title(title))
+//!     }
+//! }
+//!
+//! let title = Title("RGB".to_string());
+//! let data = strict_encode(&title).unwrap();
+//! assert_eq!(data, strict_encode(&"RGB".to_string()).unwrap());
+//! assert_eq!(strict_decode::<Title>(&data).unwrap(), title);
+//! assert!(matches!(
+//!     strict_decode::<Title>(&[0u8, 0]),
+//!     Err(TitleError::Empty)
+//! ));
+//! assert!(matches!(
+//!     strict_decode::<Title>(&[1u8]),
+//!     Err(TitleError::Encoding(_))
+//! ));
+//! ```
+//!
+//! # Common mistakes
+//!
+//! The following misuses of the API are rejected by the compiler; the
+//! expected compiler errors are checked by the tests in `tests/ui`:
+//!
+//! * error type which can't be constructed from [Error], since the library
+//!   encoders of the primitive and collection types must be able to report
+//!   their failures (`error_without_from.rs`);
+//! * error type which does not implement [std::error::Error]
+//!   (`error_not_std_error.rs`);
+//! * decoding of nested data with a custom error type as a part of a type
+//!   using [Error], since the custom error can't be converted into it; the
+//!   outer type must use the custom error type (or another type convertible
+//!   from it) instead (`nested_custom_error.rs`).
+
 use core::ops::Range;
 use std::fmt::{self, Display, Formatter};
 use std::io;
@@ -35,6 +213,212 @@ pub trait StrictEncode {
     /// with either amount of bytes encoded – or implementation-specific
     /// error type.
     fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error>;
+
+    /// Computes the length of the data structure strict encoding without
+    /// allocating memory for the encoded data. Can be used for buffer
+    /// preallocation and checking message size limits before serialization.
+    fn strict_serialized_len(&self) -> Result<usize, Self::Error> {
+        let mut counter = CountingWriter::new(io::sink());
+        self.strict_encode(&mut counter)?;
+        Ok(counter.count())
+    }
+}
+
+/// Writer adapter counting the number of bytes actually written to the
+/// underlying writer. Used by [strict_encode_list!] and manual [StrictEncode]
+/// implementations to report the encoded data length.
+#[derive(Clone, Debug)]
+pub struct CountingWriter<W: io::Write> {
+    inner: W,
+    count: usize,
+}
+
+impl<W: io::Write> CountingWriter<W> {
+    #[inline]
+    pub fn new(inner: W) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    /// Returns number of bytes written so far
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: io::Write> io::Write for CountingWriter<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written;
+        Ok(written)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reader adapter counting the number of bytes actually read from the
+/// underlying reader
+#[derive(Clone, Debug)]
+pub struct CountingReader<R: io::Read> {
+    inner: R,
+    count: usize,
+}
+
+impl<R: io::Read> CountingReader<R> {
+    #[inline]
+    pub fn new(inner: R) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    /// Returns number of bytes read so far
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: io::Read> io::Read for CountingReader<R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read;
+        Ok(read)
+    }
+}
+
+/// Reader adapter tracking the offset of the data read from the underlying
+/// reader and remembering the offset at which the first read error has
+/// happened. Used by [strict_decode_traced] to report the byte position of
+/// decoding failures which have happened deep inside nested data structures.
+#[derive(Clone, Debug)]
+pub struct TrackingReader<R: io::Read> {
+    inner: R,
+    offset: usize,
+    failed_at: Option<usize>,
+}
+
+impl<R: io::Read> TrackingReader<R> {
+    #[inline]
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            offset: 0,
+            failed_at: None,
+        }
+    }
+
+    /// Returns offset of the next byte to be read
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns offset at which the first failed read has happened, if any
+    #[inline]
+    pub fn failed_at(&self) -> Option<usize> {
+        self.failed_at
+    }
+
+    /// Returns offset at which decoding has stopped: either the offset of
+    /// the first failed read or, if there were no read errors, the offset of
+    /// the next byte to be read
+    #[inline]
+    pub fn error_offset(&self) -> usize {
+        self.failed_at.unwrap_or(self.offset)
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: io::Read> io::Read for TrackingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner.read(buf) {
+            Ok(read) => {
+                self.offset += read;
+                Ok(read)
+            }
+            Err(err) => {
+                self.failed_at = self.failed_at.or(Some(self.offset));
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Reader adapter failing once more than the given number of bytes is
+/// requested from it. Used to enforce limits on the size of the decoded
+/// data while decoding, before memory for the data is allocated.
+#[derive(Clone, Debug)]
+pub struct BoundedReader<R: io::Read> {
+    inner: R,
+    limit: usize,
+    count: usize,
+    exceeded_by: Option<usize>,
+}
+
+impl<R: io::Read> BoundedReader<R> {
+    #[inline]
+    pub fn new(inner: R, limit: usize) -> Self {
+        Self {
+            inner,
+            limit,
+            count: 0,
+            exceeded_by: None,
+        }
+    }
+
+    /// Returns number of bytes read so far
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// If the reader has failed because of the limit, returns the amount of
+    /// bytes which were requested from it (read so far plus the failed
+    /// request)
+    #[inline]
+    pub fn exceeded_by(&self) -> Option<usize> {
+        self.exceeded_by
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: io::Read> io::Read for BoundedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.limit - self.count;
+        if available == 0 && !buf.is_empty() {
+            self.exceeded_by = Some(self.count + buf.len());
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "data size limit exceeded",
+            ));
+        }
+        let len = buf.len().min(available);
+        let read = self.inner.read(&mut buf[..len])?;
+        self.count += read;
+        Ok(read)
+    }
 }
 
 /// Binary decoding according to the strict rules that usually apply to
@@ -77,16 +461,44 @@ where
     T: StrictDecode,
     T::Error: std::error::Error + From<Error>,
 {
-    let mut decoder = io::Cursor::new(data);
-    let rv = T::strict_decode(&mut decoder)?;
-    let consumed = decoder.position() as usize;
+    DecodeContext::current().apply(|| -> Result<T, T::Error> {
+        let mut decoder = io::Cursor::new(data);
+        let rv = T::strict_decode(&mut decoder)?;
+        let consumed = decoder.position() as usize;
 
-    // Fail if data are not consumed entirely.
-    if consumed == data.as_ref().len() {
-        Ok(rv)
-    } else {
-        Err(Error::DataNotEntirelyConsumed)?
-    }
+        // Fail if data are not consumed entirely.
+        if consumed == data.as_ref().len() {
+            Ok(rv)
+        } else {
+            Err(Error::DataNotEntirelyConsumed)?
+        }
+    })
+}
+
+/// Strict decoding of data structures which reports the name of the decoded
+/// type and byte offset at which the decoding has failed as a part of
+/// [Error::WithContext]. Otherwise the function works exactly as
+/// [strict_decode].
+pub fn strict_decode_traced<T>(data: &impl AsRef<[u8]>) -> Result<T, Error>
+where
+    T: StrictDecode,
+    T::Error: Into<Error>,
+{
+    DecodeContext::current().apply(|| -> Result<T, Error> {
+        let mut decoder = TrackingReader::new(data.as_ref());
+        let rv = T::strict_decode(&mut decoder).map_err(|err| {
+            err.into()
+                .in_field(std::any::type_name::<T>())
+                .at_offset(decoder.error_offset())
+        })?;
+        let consumed = decoder.offset();
+
+        if consumed == data.as_ref().len() {
+            Ok(rv)
+        } else {
+            Err(Error::DataNotEntirelyConsumed.at_offset(consumed))
+        }
+    })
 }
 
 /// Possible errors during strict encoding and decoding process
@@ -136,6 +548,105 @@ pub enum Error {
 
     /// Convenience type never for data structures using StrictDecode
     DataIntegrityError(String),
+
+    /// Error wrapped with the information on the place in the data where it
+    /// has happened
+    WithContext(ErrorContext, Box<Error>),
+
+    /// Nesting of the decoded compositional data types (options and
+    /// collections) exceeds maximum depth set by the [DecodeContext]
+    DepthLimitExceeded(usize),
+
+    /// Total number of the collection items in the decoded data exceeds the
+    /// budget set by the [DecodeContext]
+    SizeLimitExceeded(usize),
+
+    /// Checksum of the framed data does not match the data, indicating data
+    /// corruption
+    ChecksumMismatch,
+
+    /// Text representation of the strict-encoded data (hex or base64
+    /// string) is invalid
+    InvalidText(String),
+}
+
+/// Context of a decoding error: the path to the data field which has failed
+/// to decode and the byte offset at which the failure has happened
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct ErrorContext {
+    /// Offset of the byte at which decoding has failed, counting from the
+    /// beginning of the data
+    pub offset: Option<usize>,
+
+    /// Sequence of type and field names, starting from the top-level type
+    /// and ending with the innermost field which has failed to decode
+    pub path: Vec<String>,
+}
+
+impl Display for ErrorContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "decoding `{}`", self.path.join("."))?;
+        if let Some(offset) = self.offset {
+            write!(f, " at byte offset {}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error {
+    /// Adds the name of the field (or type) which decoding has failed to the
+    /// beginning of the error context path. Should be used by the decoders of
+    /// nested data structures, like
+    /// `Metadata::strict_decode(&mut d).map_err(|e| e.in_field("metadata"))?`
+    pub fn in_field(self, name: &str) -> Self {
+        match self {
+            Error::WithContext(mut context, err) => {
+                context.path.insert(0, name.to_string());
+                Error::WithContext(context, err)
+            }
+            err => Error::WithContext(
+                ErrorContext {
+                    offset: None,
+                    path: vec![name.to_string()],
+                },
+                Box::new(err),
+            ),
+        }
+    }
+
+    /// Sets byte offset at which the error has happened, if it was not
+    /// set before
+    pub fn at_offset(self, offset: usize) -> Self {
+        match self {
+            Error::WithContext(mut context, err) => {
+                context.offset = context.offset.or(Some(offset));
+                Error::WithContext(context, err)
+            }
+            err => Error::WithContext(
+                ErrorContext {
+                    offset: Some(offset),
+                    path: vec![],
+                },
+                Box::new(err),
+            ),
+        }
+    }
+
+    /// Returns error context, if any
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::WithContext(context, _) => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Returns the original error stripped of the context information
+    pub fn root_cause(&self) -> &Error {
+        match self {
+            Error::WithContext(_, err) => err.root_cause(),
+            err => err,
+        }
+    }
 }
 
 impl Display for Error {
@@ -193,6 +704,24 @@ impl Display for Error {
                 "Data were not consumed entirely during strict decoding procedure"
             ),
             DataIntegrityError(str) => write!(f, "Data integrity error: {}", str),
+            WithContext(context, err) => write!(f, "{} ({})", err, context),
+            DepthLimitExceeded(max) => write!(
+                f,
+                "Nesting of the decoded data structures exceeds maximum \
+                allowed depth of {}",
+                max
+            ),
+            SizeLimitExceeded(max) => write!(
+                f,
+                "Decoded data structures contain more than {} collection items",
+                max
+            ),
+            ChecksumMismatch => write!(f, "Data checksum mismatch"),
+            InvalidText(msg) => write!(
+                f,
+                "Invalid text representation of strict-encoded data: {}",
+                msg
+            ),
         }
     }
 }
@@ -201,11 +730,11 @@ impl Display for Error {
 macro_rules! strict_encode_list {
     ( $encoder:ident; $($item:expr),+ ) => {
         {
-            let mut len = 0usize;
+            let mut writer = $crate::strict_encoding::CountingWriter::new(&mut $encoder);
             $(
-                len += $item.strict_encode(&mut $encoder)?;
+                $item.strict_encode(&mut writer)?;
             )+
-            len
+            writer.count()
         }
     }
 }
@@ -245,7 +774,278 @@ macro_rules! impl_enum_strict_encoding {
     };
 }
 
+/// Implements strict encoding for enums with associated data (tagged unions):
+/// each variant is encoded as its `u8` tag followed by the variant fields in
+/// the order of their declaration. Tags may be integer literals or variants
+/// of a C-like enum with `u8` representation. Decoding of an unknown tag fails
+/// with [Error::EnumValueNotKnown].
+///
+/// Fields of tuple variants must be named in the same way as the fields of
+/// struct variants:
+///
+/// ```ignore
+/// impl_tagged_enum_strict_encoding!(Shape {
+///     Point = 0,
+///     Circle(radius: u16) = 1,
+///     Rect { width: u16, height: u16 } = 2,
+/// });
+/// ```
+#[macro_export]
+macro_rules! impl_tagged_enum_strict_encoding {
+    ($type:ident { $(
+        $variant:ident
+        $( ( $($tfield:ident : $tty:ty),* ) )?
+        $( { $($nfield:ident : $nty:ty),* } )?
+        = $tag:expr
+    ),+ $(,)? }) => {
+        impl $crate::strict_encoding::StrictEncode for $type {
+            type Error = $crate::strict_encoding::Error;
+
+            #[allow(clippy::unnecessary_cast)]
+            fn strict_encode<E: ::std::io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+                let mut writer = $crate::strict_encoding::CountingWriter::new(&mut e);
+                match self {
+                    $(
+                        $type::$variant $( ( $($tfield),* ) )? $( { $($nfield),* } )? => {
+                            $crate::strict_encoding::StrictEncode::strict_encode(
+                                &(($tag) as u8),
+                                &mut writer,
+                            )?;
+                            $($(
+                                $crate::strict_encoding::StrictEncode::strict_encode(
+                                    $tfield,
+                                    &mut writer,
+                                )?;
+                            )*)?
+                            $($(
+                                $crate::strict_encoding::StrictEncode::strict_encode(
+                                    $nfield,
+                                    &mut writer,
+                                )?;
+                            )*)?
+                        }
+                    )+
+                }
+                Ok(writer.count())
+            }
+        }
+
+        impl $crate::strict_encoding::StrictDecode for $type {
+            type Error = $crate::strict_encoding::Error;
+
+            #[allow(clippy::unnecessary_cast)]
+            fn strict_decode<D: ::std::io::Read>(mut d: D) -> Result<Self, Self::Error> {
+                let tag = <u8 as $crate::strict_encoding::StrictDecode>::strict_decode(&mut d)?;
+                $(
+                    if tag == ($tag) as u8 {
+                        return Ok($type::$variant
+                            $( ( $(
+                                <$tty as $crate::strict_encoding::StrictDecode>::strict_decode(
+                                    &mut d,
+                                )?
+                            ),* ) )?
+                            $( { $(
+                                $nfield: <$nty as $crate::strict_encoding::StrictDecode>::strict_decode(
+                                    &mut d,
+                                )?
+                            ),* } )?
+                        );
+                    }
+                )+
+                Err($crate::strict_encoding::Error::EnumValueNotKnown(
+                    stringify!($type).to_string(),
+                    tag,
+                ))
+            }
+        }
+    };
+}
+
+use decode_context::requires_canonical_floats;
 /// Implemented after concept by Martin Habovštiak <martin.habovstiak@gmail.com>
+pub use decode_context::DecodeContext;
+pub(crate) use decode_context::{consume_items, DepthGuard};
+
+/// Limits applied to the decoding of compositional data types, protecting
+/// from stack exhaustion by deeply nested attacker-controlled data (like
+/// `Option<Vec<Option<...>>>`) and from memory exhaustion by the data
+/// containing too many collection items, and validation rules applied to the
+/// decoded data. Since [StrictDecode] API does not pass any state between
+/// nested decoders, the context is kept in thread-local storage and applies
+/// to all decoding happening in the current thread.
+mod decode_context {
+    use super::{strict_decode, Error, StrictDecode};
+    use std::cell::Cell;
+
+    thread_local! {
+        static DEPTH: Cell<usize> = Cell::new(0);
+        static MAX_DEPTH: Cell<usize> = Cell::new(DecodeContext::DEFAULT_MAX_DEPTH);
+        static CANONICAL_FLOATS: Cell<bool> = Cell::new(false);
+        static ITEMS: Cell<usize> = Cell::new(0);
+        static MAX_ITEMS: Cell<usize> = Cell::new(DecodeContext::DEFAULT_MAX_ITEMS);
+    }
+
+    /// Configuration of decoding limits and validation rules
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+    #[display_from(Debug)]
+    pub struct DecodeContext {
+        /// Maximum nesting depth of the decoded compositional types
+        pub max_depth: usize,
+
+        /// Budget for the total number of items in all collections decoded
+        /// as a part of a single top-level data structure, including byte
+        /// strings
+        pub max_items: usize,
+
+        /// Whether floating-point numbers with non-canonical bit patterns
+        /// (NaN other than the canonical one and negative zero) must be
+        /// rejected
+        pub canonical_floats: bool,
+    }
+
+    impl Default for DecodeContext {
+        fn default() -> Self {
+            Self {
+                max_depth: Self::DEFAULT_MAX_DEPTH,
+                max_items: Self::DEFAULT_MAX_ITEMS,
+                canonical_floats: false,
+            }
+        }
+    }
+
+    impl DecodeContext {
+        /// Maximum depth applied when no other context is set
+        pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+        /// Items budget applied when no other context is set. The budget is
+        /// renewed each time decoding of a top-level data structure starts
+        /// and is shared by all data nested in it, including the data decoded
+        /// with nested [strict_decode] calls, so it limits the amount of
+        /// memory which may be allocated while decoding a single piece of
+        /// data.
+        pub const DEFAULT_MAX_ITEMS: usize = 0x0400_0000;
+
+        #[inline]
+        pub fn with_max_depth(max_depth: usize) -> Self {
+            Self {
+                max_depth,
+                ..Self::default()
+            }
+        }
+
+        /// Returns the same context with the budget for the total number of
+        /// the decoded collection items
+        #[inline]
+        pub fn max_items(self, max_items: usize) -> Self {
+            Self { max_items, ..self }
+        }
+
+        /// Returns the same context which additionally rejects
+        /// non-canonical floating-point numbers
+        #[inline]
+        pub fn canonical_floats(self) -> Self {
+            Self {
+                canonical_floats: true,
+                ..self
+            }
+        }
+
+        /// Returns context currently applied to the decoding in this thread
+        pub fn current() -> Self {
+            Self {
+                max_depth: MAX_DEPTH.with(|max| max.get()),
+                max_items: MAX_ITEMS.with(|max| max.get()),
+                canonical_floats: CANONICAL_FLOATS.with(|canonical| canonical.get()),
+            }
+        }
+
+        /// Runs the closure with the context applied to all decoding it
+        /// performs; the previous context is restored afterwards. The items
+        /// budget is not renewed by this call, so it can't be used to escape
+        /// the limits of the decoding already in progress.
+        pub fn apply<R>(&self, f: impl FnOnce() -> R) -> R {
+            struct Restore(DecodeContext);
+            impl Drop for Restore {
+                fn drop(&mut self) {
+                    MAX_DEPTH.with(|max| max.set(self.0.max_depth));
+                    MAX_ITEMS.with(|max| max.set(self.0.max_items));
+                    CANONICAL_FLOATS.with(|canonical| canonical.set(self.0.canonical_floats));
+                }
+            }
+
+            let _restore = Restore(DecodeContext::current());
+            MAX_DEPTH.with(|max| max.set(self.max_depth));
+            MAX_ITEMS.with(|max| max.set(self.max_items));
+            CANONICAL_FLOATS.with(|canonical| canonical.set(self.canonical_floats));
+            f()
+        }
+
+        /// Works as [strict_decode] applying the limits from this context
+        #[inline]
+        pub fn strict_decode<T>(&self, data: &impl AsRef<[u8]>) -> Result<T, T::Error>
+        where
+            T: StrictDecode,
+        {
+            self.apply(|| strict_decode(data))
+        }
+    }
+
+    /// Returns whether the context applied in this thread requires canonical
+    /// floating-point numbers
+    #[inline]
+    pub(super) fn requires_canonical_floats() -> bool {
+        CANONICAL_FLOATS.with(|canonical| canonical.get())
+    }
+
+    /// Accounts for the given number of collection items against the items
+    /// budget of the context applied in this thread. Must be called by
+    /// collection decoders before allocating memory for the items.
+    pub(crate) fn consume_items(count: usize) -> Result<(), Error> {
+        let max_items = MAX_ITEMS.with(|max| max.get());
+        // Collection decoded outside of any compositional type is a
+        // top-level data structure on its own
+        let top_level = DEPTH.with(|depth| depth.get() == 0);
+        ITEMS.with(|items| {
+            let spent = if top_level { 0 } else { items.get() };
+            let total = spent.saturating_add(count);
+            if total > max_items {
+                Err(Error::SizeLimitExceeded(max_items))
+            } else {
+                items.set(total);
+                Ok(())
+            }
+        })
+    }
+
+    /// Tracks nesting depth of the compositional types being decoded; must
+    /// be held for the whole time of the nested data decoding. Entering the
+    /// top-level data structure renews the items budget.
+    pub(crate) struct DepthGuard;
+
+    impl DepthGuard {
+        pub fn enter() -> Result<Self, Error> {
+            let max_depth = MAX_DEPTH.with(|max| max.get());
+            DEPTH.with(|depth| {
+                if depth.get() >= max_depth {
+                    Err(Error::DepthLimitExceeded(max_depth))
+                } else {
+                    if depth.get() == 0 {
+                        ITEMS.with(|items| items.set(0));
+                    }
+                    depth.set(depth.get() + 1);
+                    Ok(DepthGuard)
+                }
+            })
+        }
+    }
+
+    impl Drop for DepthGuard {
+        fn drop(&mut self) {
+            DEPTH.with(|depth| depth.set(depth.get() - 1));
+        }
+    }
+}
+
 pub mod strategies {
     use super::{Error, StrictDecode, StrictEncode};
     use crate::strategy;
@@ -254,6 +1054,11 @@ pub mod strategies {
     // Defining strategies:
     pub struct HashFixedBytes;
     pub struct BitcoinConsensus;
+    /// Strategy for `Box<T>`, which is encoded exactly as the value it owns.
+    /// Since `Box` is a fundamental type, it can't be given a direct
+    /// implementation alongside the blanket strategy-based one, so the boxed
+    /// value has to be `Clone`.
+    pub struct Transparent;
 
     pub trait Strategy {
         type Strategy;
@@ -336,6 +1141,34 @@ pub mod strategies {
         }
     }
 
+    impl<T> Strategy for Box<T> {
+        type Strategy = Transparent;
+    }
+
+    impl<T> StrictEncode for strategy::Holder<Box<T>, Transparent>
+    where
+        T: StrictEncode,
+    {
+        type Error = T::Error;
+
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+            (**self.as_inner()).strict_encode(e)
+        }
+    }
+
+    impl<T> StrictDecode for strategy::Holder<Box<T>, Transparent>
+    where
+        T: StrictDecode,
+    {
+        type Error = T::Error;
+
+        #[inline]
+        fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+            Ok(Self::new(Box::new(T::strict_decode(d)?)))
+        }
+    }
+
     impl From<bitcoin::hashes::Error> for Error {
         #[inline]
         fn from(_: bitcoin::hashes::Error) -> Self {
@@ -358,7 +1191,9 @@ pub use strategies::Strategy;
 
 /// Taking implementation of little-endian integer encoding
 mod number_little_endian {
-    use super::{strategies, Error, Strategy, StrictDecode, StrictEncode};
+    use super::{
+        requires_canonical_floats, strategies, Error, Strategy, StrictDecode, StrictEncode,
+    };
     use bitcoin::util::uint::{Uint128, Uint256};
     use std::io;
 
@@ -411,10 +1246,40 @@ mod number_little_endian {
         }
     }
 
+    // Floating-point numbers are encoded in a canonical form, so equal values
+    // always have the same encoding (which is required for deterministic
+    // commitments): all NaN values are replaced with a single quiet NaN with
+    // zero payload and negative zero is replaced with positive zero
+
+    /// Bit pattern of the canonical `f32` NaN value
+    const CANONICAL_NAN_F32: u32 = 0x7FC0_0000;
+    /// Bit pattern of the canonical `f64` NaN value
+    const CANONICAL_NAN_F64: u64 = 0x7FF8_0000_0000_0000;
+
+    fn canonical_f32(val: f32) -> u32 {
+        if val.is_nan() {
+            CANONICAL_NAN_F32
+        } else if val == 0.0 {
+            0
+        } else {
+            val.to_bits()
+        }
+    }
+
+    fn canonical_f64(val: f64) -> u64 {
+        if val.is_nan() {
+            CANONICAL_NAN_F64
+        } else if val == 0.0 {
+            0
+        } else {
+            val.to_bits()
+        }
+    }
+
     impl StrictEncode for f32 {
         type Error = Error;
         fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Error> {
-            e.write_all(&self.to_le_bytes())?;
+            e.write_all(&canonical_f32(*self).to_le_bytes())?;
             Ok(4)
         }
     }
@@ -424,14 +1289,21 @@ mod number_little_endian {
         fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Error> {
             let mut buf: [u8; 4] = [0; 4];
             d.read_exact(&mut buf)?;
-            Ok(Self::from_le_bytes(buf))
+            let val = Self::from_le_bytes(buf);
+            if requires_canonical_floats() && canonical_f32(val) != val.to_bits() {
+                Err(Error::DataIntegrityError(format!(
+                    "Non-canonical f32 value encoding {:#010x}",
+                    val.to_bits()
+                )))?
+            }
+            Ok(val)
         }
     }
 
     impl StrictEncode for f64 {
         type Error = Error;
         fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Error> {
-            e.write_all(&self.to_le_bytes())?;
+            e.write_all(&canonical_f64(*self).to_le_bytes())?;
             Ok(8)
         }
     }
@@ -441,24 +1313,88 @@ mod number_little_endian {
         fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Error> {
             let mut buf: [u8; 8] = [0; 8];
             d.read_exact(&mut buf)?;
-            Ok(Self::from_le_bytes(buf))
+            let val = Self::from_le_bytes(buf);
+            if requires_canonical_floats() && canonical_f64(val) != val.to_bits() {
+                Err(Error::DataIntegrityError(format!(
+                    "Non-canonical f64 value encoding {:#018x}",
+                    val.to_bits()
+                )))?
+            }
+            Ok(val)
         }
     }
 }
 
-mod byte_strings {
+/// Integers encoded in network (big-endian) byte order. Strict encoding
+/// represents integers in little-endian order; these types allow message
+/// definitions to opt into the network byte order required by some
+/// LNP/BOLT-compatible specifications, like `be::u16(0x0102)` being encoded
+/// as `[0x01, 0x02]`.
+pub mod be {
     use super::{Error, StrictDecode, StrictEncode};
+    use core::primitive;
     use std::io;
+
+    macro_rules! impl_be_int {
+        ($ty:ident, $len:literal) => {
+            #[allow(non_camel_case_types)]
+            #[derive(
+                Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display, Default,
+            )]
+            #[display_from(Debug)]
+            pub struct $ty(pub primitive::$ty);
+
+            impl From<primitive::$ty> for $ty {
+                #[inline]
+                fn from(val: primitive::$ty) -> Self {
+                    Self(val)
+                }
+            }
+
+            impl From<$ty> for primitive::$ty {
+                #[inline]
+                fn from(val: $ty) -> Self {
+                    val.0
+                }
+            }
+
+            impl StrictEncode for $ty {
+                type Error = Error;
+                fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Error> {
+                    e.write_all(&self.0.to_be_bytes())?;
+                    Ok($len)
+                }
+            }
+
+            impl StrictDecode for $ty {
+                type Error = Error;
+                fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Error> {
+                    let mut buf = [0u8; $len];
+                    d.read_exact(&mut buf)?;
+                    Ok(Self(primitive::$ty::from_be_bytes(buf)))
+                }
+            }
+        };
+    }
+
+    impl_be_int!(u16, 2);
+    impl_be_int!(u32, 4);
+    impl_be_int!(u64, 8);
+}
+
+mod byte_strings {
+    use super::{consume_items, CountingWriter, Error, StrictDecode, StrictEncode};
+    use std::io::{self, Write};
     use std::ops::Deref;
 
     impl StrictEncode for &[u8] {
         type Error = Error;
-        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Error> {
-            let mut len = self.len();
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Error> {
+            let mut writer = CountingWriter::new(e);
             // We handle oversize problems at the level of `usize` value serializaton
-            len += len.strict_encode(&mut e)?;
-            e.write_all(self)?;
-            Ok(len)
+            self.len().strict_encode(&mut writer)?;
+            writer.write_all(self)?;
+            Ok(writer.count())
         }
     }
 
@@ -473,6 +1409,7 @@ mod byte_strings {
         type Error = Error;
         fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Error> {
             let len = usize::strict_decode(&mut d)?;
+            consume_items(len)?;
             let mut ret = vec![0u8; len];
             d.read_exact(&mut ret)?;
             Ok(ret.into_boxed_slice())
@@ -501,25 +1438,89 @@ mod byte_strings {
     }
 }
 
-mod compositional_types {
-    use super::{Error, StrictDecode, StrictEncode};
-    use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
-    use std::fmt::Debug;
-    use std::hash::Hash;
-    use std::io;
+pub use borrowed::{strict_decode_borrowed, StrictDecodeBorrowed};
 
-    /// In terms of strict encoding, `Option` (optional values) are  
-    /// represented by a *significator byte*, which MUST be either `0` (for no
-    /// value present) or `1`, followed by the value strict encoding.
-    impl<T> StrictEncode for Option<T>
+/// Zero-copy decoding of byte strings, which returns views into the original
+/// data instead of allocating memory for the decoded values
+mod borrowed {
+    use super::{Error, StrictDecode};
+    use std::str;
+
+    /// Strict decoding of data structures borrowing data from the input slice.
+    /// Produces the same values as [StrictDecode] applied to the owned
+    /// counterparts of the types (like `Box<[u8]>` and `String`), but without
+    /// copying the data. Implementations for types combining borrowed and
+    /// owned data may decode owned fields with [StrictDecode] using the same
+    /// slice, since `&mut &[u8]` implements [std::io::Read].
+    pub trait StrictDecodeBorrowed<'a>: Sized {
+        /// Implementation-dependent error type
+        type Error: std::error::Error + From<Error>;
+
+        /// Decodes the data from the beginning of the slice advancing it past
+        /// the consumed bytes
+        fn strict_decode_borrowed(data: &mut &'a [u8]) -> Result<Self, Self::Error>;
+    }
+
+    /// Convenience method for zero-copy strict decoding, which fails if the
+    /// data are not consumed entirely
+    pub fn strict_decode_borrowed<'a, T>(mut data: &'a [u8]) -> Result<T, T::Error>
     where
-        T: StrictEncode,
-        T::Error: From<Error>,
+        T: StrictDecodeBorrowed<'a>,
     {
-        type Error = T::Error;
-        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
-            Ok(match self {
-                None => strict_encode_list!(e; 0u8),
+        let rv = T::strict_decode_borrowed(&mut data)?;
+        if data.is_empty() {
+            Ok(rv)
+        } else {
+            Err(Error::DataNotEntirelyConsumed)?
+        }
+    }
+
+    impl<'a> StrictDecodeBorrowed<'a> for &'a [u8] {
+        type Error = Error;
+
+        fn strict_decode_borrowed(data: &mut &'a [u8]) -> Result<Self, Error> {
+            let len = usize::strict_decode(&mut *data)?;
+            if len > data.len() {
+                Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof))?
+            }
+            let (view, rest) = data.split_at(len);
+            *data = rest;
+            Ok(view)
+        }
+    }
+
+    impl<'a> StrictDecodeBorrowed<'a> for &'a str {
+        type Error = Error;
+
+        #[inline]
+        fn strict_decode_borrowed(data: &mut &'a [u8]) -> Result<Self, Error> {
+            Ok(str::from_utf8(<&[u8]>::strict_decode_borrowed(data)?)?)
+        }
+    }
+}
+
+mod compositional_types {
+    use super::{consume_items, DepthGuard, Error, StrictDecode, StrictEncode};
+    use std::borrow::Cow;
+    use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+    use std::fmt::Debug;
+    use std::hash::Hash;
+    use std::io;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    /// In terms of strict encoding, `Option` (optional values) are  
+    /// represented by a *significator byte*, which MUST be either `0` (for no
+    /// value present) or `1`, followed by the value strict encoding.
+    impl<T> StrictEncode for Option<T>
+    where
+        T: StrictEncode,
+        T::Error: From<Error>,
+    {
+        type Error = T::Error;
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(match self {
+                None => strict_encode_list!(e; 0u8),
                 Some(val) => strict_encode_list!(e; 1u8, val),
             })
         }
@@ -537,6 +1538,7 @@ mod compositional_types {
     {
         type Error = T::Error;
         fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            let _depth = DepthGuard::enter()?;
             let len = u8::strict_decode(&mut d)?;
             match len {
                 0 => Ok(None),
@@ -582,7 +1584,9 @@ mod compositional_types {
     {
         type Error = T::Error;
         fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            let _depth = DepthGuard::enter()?;
             let len = usize::strict_decode(&mut d)?;
+            consume_items(len)?;
             let mut data = Vec::<T>::with_capacity(len as usize);
             for _ in 0..len {
                 data.push(T::strict_decode(&mut d)?);
@@ -592,17 +1596,23 @@ mod compositional_types {
     }
 
     /// Strict encoding for a unique value collection represented by a rust
-    /// `HashSet` type is performed in the same way as `Vec` encoding.
+    /// `HashSet` type is performed in the same way as `Vec` encoding. Since
+    /// the iteration order of `HashSet` is random, the items are sorted
+    /// according to their `Ord` implementation before being encoded, which
+    /// makes the encoding canonical and equal to the encoding of the same
+    /// items put into `BTreeSet`.
     impl<T> StrictEncode for HashSet<T>
     where
-        T: StrictEncode + Eq + Hash + Debug,
+        T: StrictEncode + Eq + Ord + Hash + Debug,
         T::Error: From<Error>,
     {
         type Error = T::Error;
         fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
             let len = self.len() as usize;
             let mut encoded = len.strict_encode(&mut e)?;
-            for item in self {
+            let mut items = self.iter().collect::<Vec<_>>();
+            items.sort();
+            for item in items {
                 encoded += item.strict_encode(&mut e)?;
             }
             Ok(encoded)
@@ -620,7 +1630,9 @@ mod compositional_types {
     {
         type Error = T::Error;
         fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            let _depth = DepthGuard::enter()?;
             let len = usize::strict_decode(&mut d)?;
+            consume_items(len)?;
             let mut data = HashSet::<T>::with_capacity(len as usize);
             for _ in 0..len {
                 let val = T::strict_decode(&mut d)?;
@@ -663,7 +1675,9 @@ mod compositional_types {
     {
         type Error = T::Error;
         fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            let _depth = DepthGuard::enter()?;
             let len = usize::strict_decode(&mut d)?;
+            consume_items(len)?;
             let mut data = BTreeSet::<T>::new();
             for _ in 0..len {
                 let val = T::strict_decode(&mut d)?;
@@ -772,7 +1786,9 @@ mod compositional_types {
     {
         type Error = V::Error;
         fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            let _depth = DepthGuard::enter()?;
             let len = usize::strict_decode(&mut d)?;
+            consume_items(len)?;
             let mut map = BTreeMap::<K, V>::new();
             for _ in 0..len {
                 map.insert(K::strict_decode(&mut d)?, V::strict_decode(&mut d)?);
@@ -780,187 +1796,1804 @@ mod compositional_types {
             Ok(map)
         }
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    /// Shared pointers are encoded transparently, i.e. exactly as the value
+    /// they point to, so data models may use shared ownership without
+    /// changing their encoding
+    impl<T> StrictEncode for Rc<T>
+    where
+        T: StrictEncode,
+    {
+        type Error = T::Error;
 
-    fn gen_strings() -> Vec<&'static str> {
-        vec![
-            "",
-            "0",
-            " ",
-            "A string slice (&str) is made of bytes (u8), and a byte slice \
-            (&[u8]) is made of bytes, so this function converts between the two.\
-             Not all byte slices are valid string slices, however: &str requires \
-             that it is valid UTF-8. from_utf8() checks to ensure that the bytes \
-             are valid UTF-8, and then does the conversion.",
-        ]
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+            (**self).strict_encode(e)
+        }
     }
 
-    #[test]
-    fn test_encode_decode() {
-        gen_strings().into_iter().for_each(|s| {
-            let r = strict_encode(&s).unwrap();
-            let p: String = strict_decode(&r).unwrap();
-            assert_eq!(s, p);
-        })
-    }
+    impl<T> StrictDecode for Rc<T>
+    where
+        T: StrictDecode,
+    {
+        type Error = T::Error;
 
-    #[test]
-    #[should_panic(expected = "DataNotEntirelyConsumed")]
-    fn test_consumation() {
-        gen_strings().into_iter().for_each(|s| {
-            let mut r = strict_encode(&s).unwrap();
-            r.extend_from_slice("data".as_ref());
-            let _: String = strict_decode(&r).unwrap();
-        })
+        #[inline]
+        fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+            Ok(Rc::new(T::strict_decode(d)?))
+        }
     }
 
-    #[test]
-    fn test_error_propagation() {
-        gen_strings().into_iter().for_each(|s| {
-            let r = strict_encode(&s).unwrap();
-            let p: Result<String, _> = strict_decode(&r[..1].to_vec());
-            assert!(p.is_err());
-        })
+    /// Shared pointers are encoded transparently, i.e. exactly as the value
+    /// they point to, so data models may use shared ownership without
+    /// changing their encoding
+    impl<T> StrictEncode for Arc<T>
+    where
+        T: StrictEncode,
+    {
+        type Error = T::Error;
+
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+            (**self).strict_encode(e)
+        }
     }
 
-    /// Checking that byte encoding and decoding works correctly for the most common
-    /// marginal and middle-probability cases
-    #[test]
-    fn test_u8_encode() {
-        let zero: u8 = 0;
-        let one: u8 = 1;
-        let thirteen: u8 = 13;
-        let confusing: u8 = 0xEF;
-        let nearly_full: u8 = 0xFE;
-        let full: u8 = 0xFF;
+    impl<T> StrictDecode for Arc<T>
+    where
+        T: StrictDecode,
+    {
+        type Error = T::Error;
 
-        let byte_0 = &[0u8][..];
-        let byte_1 = &[1u8][..];
-        let byte_13 = &[13u8][..];
-        let byte_ef = &[0xEFu8][..];
-        let byte_fe = &[0xFEu8][..];
-        let byte_ff = &[0xFFu8][..];
+        #[inline]
+        fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+            Ok(Arc::new(T::strict_decode(d)?))
+        }
+    }
 
-        assert_eq!(strict_encode(&zero).unwrap(), byte_0);
-        assert_eq!(strict_encode(&one).unwrap(), byte_1);
-        assert_eq!(strict_encode(&thirteen).unwrap(), byte_13);
-        assert_eq!(strict_encode(&confusing).unwrap(), byte_ef);
-        assert_eq!(strict_encode(&nearly_full).unwrap(), byte_fe);
-        assert_eq!(strict_encode(&full).unwrap(), byte_ff);
+    /// Clone-on-write values are encoded as the value they borrow or own; the
+    /// decoding always produces owned value
+    impl<'a, T> StrictEncode for Cow<'a, T>
+    where
+        T: StrictEncode + ToOwned + ?Sized,
+    {
+        type Error = T::Error;
 
-        assert_eq!(u8::strict_decode(byte_0).unwrap(), zero);
-        assert_eq!(u8::strict_decode(byte_1).unwrap(), one);
-        assert_eq!(u8::strict_decode(byte_13).unwrap(), thirteen);
-        assert_eq!(u8::strict_decode(byte_ef).unwrap(), confusing);
-        assert_eq!(u8::strict_decode(byte_fe).unwrap(), nearly_full);
-        assert_eq!(u8::strict_decode(byte_ff).unwrap(), full);
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+            (**self).strict_encode(e)
+        }
     }
 
-    /// Test for checking the following rule from LNPBP-5:
-    ///
-    /// `Option<T>` of any type T, which are set to `Option::None` value MUST encode as two
-    /// zero bytes and it MUST be possible to decode optional of any type from two zero bytes
-    /// which MUST result in `Option::None` value.
-    #[test]
-    fn test_option_encode_none() {
-        let o1: Option<u8> = None;
-        let o2: Option<u64> = None;
+    impl<'a, T> StrictDecode for Cow<'a, T>
+    where
+        T: ToOwned + ?Sized,
+        T::Owned: StrictDecode,
+    {
+        type Error = <T::Owned as StrictDecode>::Error;
 
-        let two_zero_bytes = &vec![0u8][..];
+        #[inline]
+        fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+            Ok(Cow::Owned(T::Owned::strict_decode(d)?))
+        }
+    }
+}
 
-        assert_eq!(strict_encode(&o1).unwrap(), two_zero_bytes);
-        assert_eq!(strict_encode(&o2).unwrap(), two_zero_bytes);
+/// Strict encoding of network addresses. IPv4 and IPv6 addresses are encoded
+/// as 4 and 16 bytes of their octets, in network byte order. `IpAddr` is
+/// prefixed with a single byte specifying address version (`0` for IPv4, `1`
+/// for IPv6), and `SocketAddr` is encoded as `IpAddr` followed by port number.
+/// IPv6 flow information and scope id are not encoded.
+mod net_addresses {
+    use super::{Error, StrictDecode, StrictEncode};
+    use std::io;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 
-        assert_eq!(Option::<u8>::strict_decode(two_zero_bytes).unwrap(), None);
-        assert_eq!(Option::<u64>::strict_decode(two_zero_bytes).unwrap(), None);
+    impl StrictEncode for Ipv4Addr {
+        type Error = Error;
+
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            e.write_all(&self.octets())?;
+            Ok(4)
+        }
     }
 
-    /// Test for checking the following rule from LNPBP-5:
-    ///
-    /// `Option<T>` of any type T, which are set to `Option::Some<T>` value MUST encode as a
-    /// `Vec<T>` structure containing a single item equal to the `Option::unwrap()` value.
-    #[test]
-    fn test_option_encode_some() {
-        let o1: Option<u8> = Some(0);
-        let o2: Option<u8> = Some(13);
-        let o3: Option<u8> = Some(0xFF);
-        let o4: Option<u64> = Some(13);
-        let o5: Option<u64> = Some(0x1FF);
-        let o6: Option<u64> = Some(0xFFFFFFFFFFFFFFFF);
-        let o7: Option<usize> = Some(13);
-        let o8: Option<usize> = Some(0xFFFFFFFFFFFFFFFF);
+    impl StrictDecode for Ipv4Addr {
+        type Error = Error;
 
-        let byte_0 = &[1u8, 0u8][..];
-        let byte_13 = &[1u8, 13u8][..];
-        let byte_255 = &[1u8, 0xFFu8][..];
-        let word_13 = &[1u8, 13u8, 0u8][..];
-        let qword_13 = &[1u8, 13u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8][..];
-        let qword_256 = &[1u8, 0xFFu8, 0x01u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8][..];
-        let qword_max = &[
-            1u8, 0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8,
-        ][..];
+        #[inline]
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            let mut buf = [0u8; 4];
+            d.read_exact(&mut buf)?;
+            Ok(Self::from(buf))
+        }
+    }
 
-        assert_eq!(strict_encode(&o1).unwrap(), byte_0);
-        assert_eq!(strict_encode(&o2).unwrap(), byte_13);
-        assert_eq!(strict_encode(&o3).unwrap(), byte_255);
-        assert_eq!(strict_encode(&o4).unwrap(), qword_13);
-        assert_eq!(strict_encode(&o5).unwrap(), qword_256);
-        assert_eq!(strict_encode(&o6).unwrap(), qword_max);
-        assert_eq!(strict_encode(&o7).unwrap(), word_13);
-        assert!(strict_encode(&o8).err().is_some());
+    impl StrictEncode for Ipv6Addr {
+        type Error = Error;
 
-        assert_eq!(Option::<u8>::strict_decode(byte_0).unwrap(), Some(0));
-        assert_eq!(Option::<u8>::strict_decode(byte_13).unwrap(), Some(13));
-        assert_eq!(Option::<u8>::strict_decode(byte_255).unwrap(), Some(0xFF));
-        assert_eq!(Option::<u64>::strict_decode(qword_13).unwrap(), Some(13));
-        assert_eq!(
-            Option::<u64>::strict_decode(qword_256).unwrap(),
-            Some(0x1FF)
-        );
-        assert_eq!(
-            Option::<u64>::strict_decode(qword_max).unwrap(),
-            Some(0xFFFFFFFFFFFFFFFF)
-        );
-        assert_eq!(Option::<usize>::strict_decode(word_13).unwrap(), Some(13));
-        assert_eq!(
-            Option::<usize>::strict_decode(qword_max).unwrap(),
-            Some(0xFFFF)
-        );
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            e.write_all(&self.octets())?;
+            Ok(16)
+        }
     }
 
-    /// Test trying decoding of non-zero and non-single item vector structures, which MUST
-    /// fail with a specific error.
-    #[test]
-    fn test_option_decode_vec() {
-        assert!(Option::<u8>::strict_decode(&[2u8, 0u8, 0u8, 0u8][..])
-            .err()
-            .is_some());
-        assert!(Option::<u8>::strict_decode(&[3u8, 0u8, 0u8, 0u8][..])
-            .err()
-            .is_some());
-        assert!(Option::<u8>::strict_decode(&[0xFFu8, 0u8, 0u8, 0u8][..])
-            .err()
-            .is_some());
+    impl StrictDecode for Ipv6Addr {
+        type Error = Error;
+
+        #[inline]
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            let mut buf = [0u8; 16];
+            d.read_exact(&mut buf)?;
+            Ok(Self::from(buf))
+        }
     }
 
-    /// Test for checking the following rule from LNPBP-5:
-    ///
-    /// Array of any commitment-serializable type T MUST contain strictly less than `0x10000` items
-    /// and must encode as 16-bit little-endian value corresponding to the number of items
-    /// followed by a direct encoding of each of the items.
-    #[test]
-    fn test_vec_encode() {
-        let v1: Vec<u8> = vec![0, 13, 0xFF];
-        let v2: Vec<u8> = vec![13];
-        let v3: Vec<u64> = vec![0, 13, 13, 0x1FF, 0xFFFFFFFFFFFFFFFF];
-        let v4: Vec<u8> = (0..0x1FFFF).map(|item| (item % 0xFF) as u8).collect();
+    impl StrictEncode for IpAddr {
+        type Error = Error;
 
-        let s1 = &[3u8, 0u8, 0u8, 13u8, 0xFFu8][..];
-        let s2 = &[1u8, 0u8, 13u8][..];
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(match self {
+                IpAddr::V4(addr) => strict_encode_list!(e; 0u8, addr),
+                IpAddr::V6(addr) => strict_encode_list!(e; 1u8, addr),
+            })
+        }
+    }
+
+    impl StrictDecode for IpAddr {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(match u8::strict_decode(&mut d)? {
+                0u8 => IpAddr::V4(Ipv4Addr::strict_decode(&mut d)?),
+                1u8 => IpAddr::V6(Ipv6Addr::strict_decode(&mut d)?),
+                invalid => Err(Error::EnumValueNotKnown("IpAddr".to_string(), invalid))?,
+            })
+        }
+    }
+
+    impl StrictEncode for SocketAddr {
+        type Error = Error;
+
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(strict_encode_list!(e; self.ip(), self.port()))
+        }
+    }
+
+    impl StrictDecode for SocketAddr {
+        type Error = Error;
+
+        #[inline]
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(SocketAddr::new(
+                IpAddr::strict_decode(&mut d)?,
+                u16::strict_decode(&mut d)?,
+            ))
+        }
+    }
+}
+
+pub use time::Timestamp;
+
+/// Time types are encoded with deterministic little-endian layout: durations
+/// as `u64` number of seconds followed by `u32` number of nanoseconds (which
+/// must be less than a second), timestamps as `i64` number of seconds since
+/// the Unix epoch. [SystemTime] is encoded as a duration since the Unix
+/// epoch and can't represent moments preceding it.
+mod time {
+    use super::{Error, StrictDecode, StrictEncode};
+    use std::convert::TryFrom;
+    use std::io;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+    /// Unix timestamp with one second precision, limited to the years from 1
+    /// to 9999 AD
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display, Default)]
+    #[display_from(Debug)]
+    pub struct Timestamp(i64);
+
+    impl Timestamp {
+        /// Beginning of 1 AD
+        pub const MIN: Timestamp = Timestamp(-62_135_596_800);
+        /// End of 9999 AD
+        pub const MAX: Timestamp = Timestamp(253_402_300_799);
+
+        /// Constructs timestamp from the number of seconds since the Unix
+        /// epoch, returning `None` if the value is out of the supported range
+        pub fn from_unix_secs(secs: i64) -> Option<Self> {
+            if secs < Self::MIN.0 || secs > Self::MAX.0 {
+                None
+            } else {
+                Some(Self(secs))
+            }
+        }
+
+        #[inline]
+        pub fn unix_secs(&self) -> i64 {
+            self.0
+        }
+
+        /// Returns current time truncated to seconds
+        pub fn now() -> Self {
+            Self::try_from(SystemTime::now()).expect("System time is out of the supported range")
+        }
+    }
+
+    impl TryFrom<SystemTime> for Timestamp {
+        type Error = Error;
+
+        fn try_from(time: SystemTime) -> Result<Self, Error> {
+            let secs = match time.duration_since(UNIX_EPOCH) {
+                Ok(duration) => i64::try_from(duration.as_secs()).ok(),
+                Err(err) => i64::try_from(err.duration().as_secs())
+                    .ok()
+                    .map(|secs| -secs),
+            };
+            secs.and_then(Self::from_unix_secs).ok_or_else(|| {
+                Error::DataIntegrityError("System time is out of timestamp range".to_string())
+            })
+        }
+    }
+
+    impl From<Timestamp> for SystemTime {
+        fn from(timestamp: Timestamp) -> Self {
+            let offset = Duration::from_secs(timestamp.0.abs() as u64);
+            if timestamp.0 < 0 {
+                UNIX_EPOCH - offset
+            } else {
+                UNIX_EPOCH + offset
+            }
+        }
+    }
+
+    impl StrictEncode for Timestamp {
+        type Error = Error;
+
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Error> {
+            self.0.strict_encode(e)
+        }
+    }
+
+    impl StrictDecode for Timestamp {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(d: D) -> Result<Self, Error> {
+            let secs = i64::strict_decode(d)?;
+            Self::from_unix_secs(secs).ok_or_else(|| {
+                Error::DataIntegrityError(format!(
+                    "Timestamp value {} is out of the supported range",
+                    secs
+                ))
+            })
+        }
+    }
+
+    impl StrictEncode for Duration {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Error> {
+            Ok(strict_encode_list!(e; self.as_secs(), self.subsec_nanos()))
+        }
+    }
+
+    impl StrictDecode for Duration {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Error> {
+            let secs = u64::strict_decode(&mut d)?;
+            let nanos = u32::strict_decode(&mut d)?;
+            if nanos >= NANOS_PER_SEC {
+                Err(Error::ValueOutOfRange(
+                    "Duration nanoseconds".to_string(),
+                    0..NANOS_PER_SEC as u64,
+                    nanos as u64,
+                ))?
+            }
+            Ok(Duration::new(secs, nanos))
+        }
+    }
+
+    impl StrictEncode for SystemTime {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Error> {
+            self.duration_since(UNIX_EPOCH)
+                .map_err(|_| {
+                    Error::DataIntegrityError(
+                        "System time preceding Unix epoch can't be encoded".to_string(),
+                    )
+                })?
+                .strict_encode(e)
+        }
+    }
+
+    impl StrictDecode for SystemTime {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(d: D) -> Result<Self, Error> {
+            UNIX_EPOCH
+                .checked_add(Duration::strict_decode(d)?)
+                .ok_or_else(|| {
+                    Error::DataIntegrityError(
+                        "System time exceeds platform-supported range".to_string(),
+                    )
+                })
+        }
+    }
+}
+
+pub use framing::{decode_frame, encode_frame, Frame};
+
+/// Framing of strict-encoded data for storage and transmission. Frame
+/// consists of `u32` little-endian payload length, the strict-encoded
+/// payload and 4-byte checksum, which is the first four bytes of double
+/// SHA256 hash of the payload. This allows to detect truncated and corrupted
+/// data before attempting to decode the payload.
+mod framing {
+    use super::{strict_decode, strict_encode, Error, StrictDecode, StrictEncode};
+    use bitcoin::hashes::{sha256d, Hash};
+    use std::io::{self, Read};
+    use std::ops::Deref;
+
+    const CHECKSUM_LEN: usize = 4;
+
+    /// Wrapper adding length prefix and checksum to the strict encoding of
+    /// the inner data
+    #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+    pub struct Frame<T>(T);
+
+    impl<T> Frame<T> {
+        #[inline]
+        pub fn new(data: T) -> Self {
+            Self(data)
+        }
+
+        #[inline]
+        pub fn as_inner(&self) -> &T {
+            &self.0
+        }
+
+        #[inline]
+        pub fn into_inner(self) -> T {
+            self.0
+        }
+    }
+
+    impl<T> Deref for Frame<T> {
+        type Target = T;
+
+        #[inline]
+        fn deref(&self) -> &T {
+            &self.0
+        }
+    }
+
+    impl<T> From<T> for Frame<T> {
+        #[inline]
+        fn from(data: T) -> Self {
+            Self(data)
+        }
+    }
+
+    fn checksum(payload: &[u8]) -> [u8; CHECKSUM_LEN] {
+        let mut checksum = [0u8; CHECKSUM_LEN];
+        checksum.copy_from_slice(&sha256d::Hash::hash(payload)[..CHECKSUM_LEN]);
+        checksum
+    }
+
+    impl<T> StrictEncode for Frame<T>
+    where
+        T: StrictEncode,
+    {
+        type Error = T::Error;
+
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+            Ok(write_frame(&strict_encode(&self.0)?, e)?)
+        }
+    }
+
+    fn write_frame(payload: &[u8], mut e: impl io::Write) -> Result<usize, Error> {
+        if payload.len() > u32::MAX as usize {
+            Err(Error::ExceedMaxItems(payload.len()))?
+        }
+        let len = (payload.len() as u32).strict_encode(&mut e)?;
+        e.write_all(payload)?;
+        e.write_all(&checksum(payload))?;
+        Ok(len + payload.len() + CHECKSUM_LEN)
+    }
+
+    impl<T> StrictDecode for Frame<T>
+    where
+        T: StrictDecode,
+    {
+        type Error = T::Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            let len = u32::strict_decode(&mut d)? as u64;
+            // Reading through `take` prevents memory exhaustion from the
+            // forged length values
+            let mut payload = vec![];
+            (&mut d)
+                .take(len)
+                .read_to_end(&mut payload)
+                .map_err(Error::from)?;
+            if (payload.len() as u64) < len {
+                Err(Error::Io(io::ErrorKind::UnexpectedEof.into()))?
+            }
+            let mut expected = [0u8; CHECKSUM_LEN];
+            d.read_exact(&mut expected).map_err(Error::from)?;
+            if checksum(&payload) != expected {
+                Err(Error::ChecksumMismatch)?
+            }
+            Ok(Self(strict_decode(&payload)?))
+        }
+    }
+
+    /// Strict-encodes the data wrapped into a [Frame]
+    #[inline]
+    pub fn encode_frame<T>(data: &T) -> Result<Vec<u8>, T::Error>
+    where
+        T: StrictEncode,
+    {
+        let mut encoder = io::Cursor::new(vec![]);
+        write_frame(&strict_encode(data)?, &mut encoder)?;
+        Ok(encoder.into_inner())
+    }
+
+    /// Decodes the data from a [Frame], checking data length and checksum
+    /// before decoding the payload
+    #[inline]
+    pub fn decode_frame<T>(data: &impl AsRef<[u8]>) -> Result<T, T::Error>
+    where
+        T: StrictDecode,
+    {
+        Ok(strict_decode::<Frame<T>>(data)?.into_inner())
+    }
+}
+
+pub use text::{StrictTextDecode, StrictTextEncode};
+
+/// Text representations (hex and base64) of the strict-encoded data, which
+/// may be used to dump and restore arbitrary data structures from command
+/// line tools, logs and debuggers
+mod text {
+    use super::{strict_decode, strict_encode, Error, StrictDecode, StrictEncode};
+    use bitcoin::hashes::hex::{FromHex, ToHex};
+
+    /// Extension methods representing strict encoding of the data in text
+    /// form. Implemented for all types implementing [StrictEncode].
+    pub trait StrictTextEncode: StrictEncode {
+        /// Returns lowercase hex string of the strict-encoded data
+        fn to_strict_hex(&self) -> Result<String, Self::Error>;
+
+        /// Returns base64 string (standard alphabet with padding) of the
+        /// strict-encoded data
+        fn to_strict_base64(&self) -> Result<String, Self::Error>;
+    }
+
+    /// Extension methods decoding the data from their strict encoding
+    /// represented in text form. Implemented for all types implementing
+    /// [StrictDecode].
+    pub trait StrictTextDecode: StrictDecode {
+        /// Decodes data from the hex string of their strict encoding
+        fn from_strict_hex(s: &str) -> Result<Self, Self::Error>;
+
+        /// Decodes data from the base64 string of their strict encoding
+        fn from_strict_base64(s: &str) -> Result<Self, Self::Error>;
+    }
+
+    impl<T> StrictTextEncode for T
+    where
+        T: StrictEncode,
+    {
+        #[inline]
+        fn to_strict_hex(&self) -> Result<String, Self::Error> {
+            Ok(strict_encode(self)?.to_hex())
+        }
+
+        #[inline]
+        fn to_strict_base64(&self) -> Result<String, Self::Error> {
+            Ok(base64::encode(&strict_encode(self)?))
+        }
+    }
+
+    impl<T> StrictTextDecode for T
+    where
+        T: StrictDecode,
+    {
+        fn from_strict_hex(s: &str) -> Result<Self, Self::Error> {
+            let data = Vec::<u8>::from_hex(s).map_err(|err| Error::InvalidText(err.to_string()))?;
+            strict_decode(&data)
+        }
+
+        fn from_strict_base64(s: &str) -> Result<Self, Self::Error> {
+            let data = base64::decode(s).map_err(|err| Error::InvalidText(err.to_string()))?;
+            strict_decode(&data)
+        }
+    }
+}
+
+pub use large_collections::{LargeMap, LargeVec};
+
+/// Collections which may hold more than 2^16 items. Unlike collections
+/// encoded according to LNPBP-6 rules, their size is encoded as `u32` value.
+/// Data structures that have to hold large amount of data (like consignments)
+/// must opt in by explicitly using these types, so the existing commitments
+/// using LNPBP-6 encoding rules are not affected.
+mod large_collections {
+    use super::{consume_items, DepthGuard, Error, StrictDecode, StrictEncode};
+    use std::collections::BTreeMap;
+    use std::fmt::Debug;
+    use std::io;
+    use std::iter::FromIterator;
+    use std::ops::{Deref, DerefMut};
+
+    /// Maximum number of items which is pre-allocated during decoding;
+    /// protects from memory exhaustion with maliciously-crafted size values
+    const MAX_PREALLOCATED_ITEMS: usize = 0x10000;
+
+    /// Vector which length is encoded as `u32` value
+    #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+    pub struct LargeVec<T>(Vec<T>);
+
+    /// Map which length is encoded as `u32` value
+    #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+    pub struct LargeMap<K: Ord, V>(BTreeMap<K, V>);
+
+    // We can't use wrapper; in the current version it does not support
+    // generics, so we have to implement manually
+    impl<T> LargeVec<T> {
+        #[inline]
+        pub fn new() -> Self {
+            Self(vec![])
+        }
+
+        #[inline]
+        pub fn into_inner(self) -> Vec<T> {
+            self.0
+        }
+    }
+
+    impl<K: Ord, V> LargeMap<K, V> {
+        #[inline]
+        pub fn new() -> Self {
+            Self(BTreeMap::new())
+        }
+
+        #[inline]
+        pub fn into_inner(self) -> BTreeMap<K, V> {
+            self.0
+        }
+    }
+
+    impl<T> Deref for LargeVec<T> {
+        type Target = Vec<T>;
+        #[inline]
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl<T> DerefMut for LargeVec<T> {
+        #[inline]
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.0
+        }
+    }
+
+    impl<K: Ord, V> Deref for LargeMap<K, V> {
+        type Target = BTreeMap<K, V>;
+        #[inline]
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl<K: Ord, V> DerefMut for LargeMap<K, V> {
+        #[inline]
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.0
+        }
+    }
+
+    impl<T> From<Vec<T>> for LargeVec<T> {
+        #[inline]
+        fn from(vec: Vec<T>) -> Self {
+            Self(vec)
+        }
+    }
+
+    impl<K: Ord, V> From<BTreeMap<K, V>> for LargeMap<K, V> {
+        #[inline]
+        fn from(map: BTreeMap<K, V>) -> Self {
+            Self(map)
+        }
+    }
+
+    impl<T> FromIterator<T> for LargeVec<T> {
+        fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+            Self(iter.into_iter().collect())
+        }
+    }
+
+    impl<K: Ord, V> FromIterator<(K, V)> for LargeMap<K, V> {
+        fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+            Self(iter.into_iter().collect())
+        }
+    }
+
+    impl<T> IntoIterator for LargeVec<T> {
+        type Item = T;
+        type IntoIter = std::vec::IntoIter<T>;
+        fn into_iter(self) -> Self::IntoIter {
+            self.0.into_iter()
+        }
+    }
+
+    impl<K: Ord, V> IntoIterator for LargeMap<K, V> {
+        type Item = (K, V);
+        type IntoIter = std::collections::btree_map::IntoIter<K, V>;
+        fn into_iter(self) -> Self::IntoIter {
+            self.0.into_iter()
+        }
+    }
+
+    #[cfg(all(test, feature = "property_tests"))]
+    impl<T> quickcheck::Arbitrary for LargeVec<T>
+    where
+        T: quickcheck::Arbitrary,
+    {
+        fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+            Self(Vec::arbitrary(g))
+        }
+    }
+
+    #[cfg(all(test, feature = "property_tests"))]
+    impl<K, V> quickcheck::Arbitrary for LargeMap<K, V>
+    where
+        K: quickcheck::Arbitrary + Ord,
+        V: quickcheck::Arbitrary,
+    {
+        fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+            Self(BTreeMap::arbitrary(g))
+        }
+    }
+
+    fn encode_len<E: io::Write>(len: usize, e: E) -> Result<usize, Error> {
+        if len > std::u32::MAX as usize {
+            Err(Error::ExceedMaxItems(len))?;
+        }
+        (len as u32).strict_encode(e)
+    }
+
+    /// `LargeVec` is encoded as `u32`-based length, followed by
+    /// consequently-encoded vec items, according to their type.
+    impl<T> StrictEncode for LargeVec<T>
+    where
+        T: StrictEncode,
+        T::Error: From<Error>,
+    {
+        type Error = T::Error;
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            let mut encoded = encode_len(self.len(), &mut e)?;
+            for item in self.iter() {
+                encoded += item.strict_encode(&mut e)?;
+            }
+            Ok(encoded)
+        }
+    }
+
+    impl<T> StrictDecode for LargeVec<T>
+    where
+        T: StrictDecode,
+        T::Error: From<Error>,
+    {
+        type Error = T::Error;
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            let _depth = DepthGuard::enter()?;
+            let len = u32::strict_decode(&mut d)? as usize;
+            consume_items(len)?;
+            let mut data = Vec::<T>::with_capacity(len.min(MAX_PREALLOCATED_ITEMS));
+            for _ in 0..len {
+                data.push(T::strict_decode(&mut d)?);
+            }
+            Ok(Self(data))
+        }
+    }
+
+    /// `LargeMap` is encoded as `u32`-based length, followed by
+    /// consequently-encoded key-value pairs ordered by the key value
+    impl<K, V> StrictEncode for LargeMap<K, V>
+    where
+        K: StrictEncode + Ord,
+        V: StrictEncode,
+        K::Error: From<Error>,
+        V::Error: From<Error> + From<K::Error>,
+    {
+        type Error = V::Error;
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            let mut encoded = encode_len(self.len(), &mut e)?;
+            for (key, val) in self.iter() {
+                encoded += key.strict_encode(&mut e)?;
+                encoded += val.strict_encode(&mut e)?;
+            }
+            Ok(encoded)
+        }
+    }
+
+    /// Decoding of `LargeMap` fails with [Error::RepeatedValue] if the same
+    /// key is met twice
+    impl<K, V> StrictDecode for LargeMap<K, V>
+    where
+        K: StrictDecode + Ord + Debug,
+        V: StrictDecode,
+        K::Error: From<Error>,
+        V::Error: From<Error> + From<K::Error>,
+    {
+        type Error = V::Error;
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            let _depth = DepthGuard::enter()?;
+            let len = u32::strict_decode(&mut d)?;
+            consume_items(len as usize)?;
+            let mut map = BTreeMap::<K, V>::new();
+            for _ in 0..len {
+                let key = K::strict_decode(&mut d)?;
+                if map.contains_key(&key) {
+                    Err(Error::RepeatedValue(format!("{:?}", key)))?;
+                }
+                let val = V::strict_decode(&mut d)?;
+                map.insert(key, val);
+            }
+            Ok(Self(map))
+        }
+    }
+}
+
+pub use confined_types::{
+    AlphanumericAscii, AsciiCharset, AsciiString, ConfinedVec, MaxLenString, PrintableAscii,
+    String255, String256,
+};
+
+/// String and collection types which length is confined within the given
+/// bounds. The bounds are checked both at construction time and during
+/// decoding, so the constraints (like the ones defined by a schema for a
+/// state field) can be represented at the type system level. Encoding of the
+/// confined types is identical to the encoding of their unconfined
+/// counterparts.
+mod confined_types {
+    use super::{consume_items, DepthGuard, Error, StrictDecode, StrictEncode};
+    use std::convert::TryFrom;
+    use std::fmt::{self, Display, Formatter};
+    use std::io;
+    use std::marker::PhantomData;
+    use std::ops::Deref;
+    use std::str::FromStr;
+
+    /// String which length in bytes does not exceed `MAX`
+    #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+    pub struct MaxLenString<const MAX: usize>(String);
+
+    /// String which length in bytes does not exceed 255
+    pub type String255 = MaxLenString<255>;
+
+    /// String which length in bytes does not exceed 256
+    pub type String256 = MaxLenString<256>;
+
+    /// Set of ASCII characters allowed in an [AsciiString]
+    pub trait AsciiCharset {
+        /// Charset name used in error messages
+        const NAME: &'static str;
+
+        /// Checks whether the character belongs to the charset
+        fn contains(c: u8) -> bool;
+    }
+
+    /// Printable ASCII characters, i.e. space and characters from `!` to `~`
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+    pub struct PrintableAscii;
+
+    impl AsciiCharset for PrintableAscii {
+        const NAME: &'static str = "printable ASCII";
+
+        #[inline]
+        fn contains(c: u8) -> bool {
+            (b' '..=b'~').contains(&c)
+        }
+    }
+
+    /// ASCII letters and digits, suitable for tickers and identifiers
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+    pub struct AlphanumericAscii;
+
+    impl AsciiCharset for AlphanumericAscii {
+        const NAME: &'static str = "alphanumeric ASCII";
+
+        #[inline]
+        fn contains(c: u8) -> bool {
+            c.is_ascii_alphanumeric()
+        }
+    }
+
+    /// String of at most `MAX` characters, each of which belongs to the
+    /// charset `C`
+    #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+    pub struct AsciiString<C: AsciiCharset, const MAX: usize>(String, PhantomData<C>);
+
+    /// Vector which number of items lies within `MIN..=MAX` range
+    #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+    pub struct ConfinedVec<T, const MIN: usize, const MAX: usize>(Vec<T>);
+
+    fn check_len(type_name: &str, min: usize, max: usize, len: usize) -> Result<(), Error> {
+        if len < min || len > max {
+            Err(Error::ValueOutOfRange(
+                type_name.to_string(),
+                min as u64..max as u64 + 1,
+                len as u64,
+            ))?
+        }
+        Ok(())
+    }
+
+    impl<const MAX: usize> MaxLenString<MAX> {
+        #[inline]
+        pub fn as_str(&self) -> &str {
+            &self.0
+        }
+
+        #[inline]
+        pub fn into_inner(self) -> String {
+            self.0
+        }
+    }
+
+    impl<const MAX: usize> TryFrom<String> for MaxLenString<MAX> {
+        type Error = Error;
+
+        fn try_from(s: String) -> Result<Self, Self::Error> {
+            check_len("MaxLenString", 0, MAX, s.len())?;
+            Ok(Self(s))
+        }
+    }
+
+    impl<const MAX: usize> FromStr for MaxLenString<MAX> {
+        type Err = Error;
+
+        #[inline]
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Self::try_from(s.to_string())
+        }
+    }
+
+    impl<const MAX: usize> Deref for MaxLenString<MAX> {
+        type Target = str;
+        #[inline]
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl<const MAX: usize> Display for MaxLenString<MAX> {
+        #[inline]
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            f.write_str(&self.0)
+        }
+    }
+
+    impl<const MAX: usize> From<MaxLenString<MAX>> for String {
+        #[inline]
+        fn from(s: MaxLenString<MAX>) -> Self {
+            s.0
+        }
+    }
+
+    impl<C: AsciiCharset, const MAX: usize> AsciiString<C, MAX> {
+        #[inline]
+        pub fn as_str(&self) -> &str {
+            &self.0
+        }
+
+        #[inline]
+        pub fn into_inner(self) -> String {
+            self.0
+        }
+    }
+
+    impl<C: AsciiCharset, const MAX: usize> TryFrom<String> for AsciiString<C, MAX> {
+        type Error = Error;
+
+        fn try_from(s: String) -> Result<Self, Self::Error> {
+            check_len("AsciiString", 0, MAX, s.len())?;
+            if let Some(c) = s.bytes().find(|c| !C::contains(*c)) {
+                Err(Error::DataIntegrityError(format!(
+                    "AsciiString contains character {:#04x} which is not {}",
+                    c,
+                    C::NAME
+                )))?
+            }
+            Ok(Self(s, PhantomData))
+        }
+    }
+
+    impl<C: AsciiCharset, const MAX: usize> FromStr for AsciiString<C, MAX> {
+        type Err = Error;
+
+        #[inline]
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Self::try_from(s.to_string())
+        }
+    }
+
+    impl<C: AsciiCharset, const MAX: usize> Deref for AsciiString<C, MAX> {
+        type Target = str;
+        #[inline]
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl<C: AsciiCharset, const MAX: usize> Display for AsciiString<C, MAX> {
+        #[inline]
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            f.write_str(&self.0)
+        }
+    }
+
+    impl<C: AsciiCharset, const MAX: usize> From<AsciiString<C, MAX>> for String {
+        #[inline]
+        fn from(s: AsciiString<C, MAX>) -> Self {
+            s.0
+        }
+    }
+
+    /// ASCII strings are valid strings of the same maximum length
+    impl<C: AsciiCharset, const MAX: usize> From<AsciiString<C, MAX>> for MaxLenString<MAX> {
+        #[inline]
+        fn from(s: AsciiString<C, MAX>) -> Self {
+            MaxLenString(s.0)
+        }
+    }
+
+    impl<T, const MIN: usize, const MAX: usize> ConfinedVec<T, MIN, MAX> {
+        #[inline]
+        pub fn into_inner(self) -> Vec<T> {
+            self.0
+        }
+
+        /// Adds an item to the end of the vector; fails if the vector already
+        /// contains `MAX` items
+        pub fn push(&mut self, item: T) -> Result<(), Error> {
+            check_len("ConfinedVec", MIN, MAX, self.0.len() + 1)?;
+            self.0.push(item);
+            Ok(())
+        }
+
+        /// Removes the last item from the vector; returns `None` if the vector
+        /// is empty or contains only `MIN` items
+        pub fn pop(&mut self) -> Option<T> {
+            if self.0.len() <= MIN {
+                return None;
+            }
+            self.0.pop()
+        }
+    }
+
+    impl<T, const MIN: usize, const MAX: usize> TryFrom<Vec<T>> for ConfinedVec<T, MIN, MAX> {
+        type Error = Error;
+
+        fn try_from(vec: Vec<T>) -> Result<Self, Self::Error> {
+            check_len("ConfinedVec", MIN, MAX, vec.len())?;
+            Ok(Self(vec))
+        }
+    }
+
+    impl<T, const MIN: usize, const MAX: usize> Deref for ConfinedVec<T, MIN, MAX> {
+        type Target = [T];
+        #[inline]
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl<T, const MIN: usize, const MAX: usize> From<ConfinedVec<T, MIN, MAX>> for Vec<T> {
+        #[inline]
+        fn from(vec: ConfinedVec<T, MIN, MAX>) -> Self {
+            vec.0
+        }
+    }
+
+    impl<const MAX: usize> StrictEncode for MaxLenString<MAX> {
+        type Error = Error;
+
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Error> {
+            self.0.strict_encode(e)
+        }
+    }
+
+    impl<const MAX: usize> StrictDecode for MaxLenString<MAX> {
+        type Error = Error;
+
+        #[inline]
+        fn strict_decode<D: io::Read>(d: D) -> Result<Self, Error> {
+            Self::try_from(String::strict_decode(d)?)
+        }
+    }
+
+    impl<C: AsciiCharset, const MAX: usize> StrictEncode for AsciiString<C, MAX> {
+        type Error = Error;
+
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Error> {
+            self.0.strict_encode(e)
+        }
+    }
+
+    impl<C: AsciiCharset, const MAX: usize> StrictDecode for AsciiString<C, MAX> {
+        type Error = Error;
+
+        #[inline]
+        fn strict_decode<D: io::Read>(d: D) -> Result<Self, Error> {
+            Self::try_from(String::strict_decode(d)?)
+        }
+    }
+
+    impl<T, const MIN: usize, const MAX: usize> StrictEncode for ConfinedVec<T, MIN, MAX>
+    where
+        T: StrictEncode,
+        T::Error: From<Error>,
+    {
+        type Error = T::Error;
+
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+            self.0.strict_encode(e)
+        }
+    }
+
+    /// The number of items is checked before decoding the items themselves
+    impl<T, const MIN: usize, const MAX: usize> StrictDecode for ConfinedVec<T, MIN, MAX>
+    where
+        T: StrictDecode,
+        T::Error: From<Error>,
+    {
+        type Error = T::Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            let _depth = DepthGuard::enter()?;
+            let len = usize::strict_decode(&mut d)?;
+            consume_items(len)?;
+            check_len("ConfinedVec", MIN, MAX, len)?;
+            let mut data = Vec::<T>::with_capacity(len);
+            for _ in 0..len {
+                data.push(T::strict_decode(&mut d)?);
+            }
+            Ok(Self(data))
+        }
+    }
+}
+
+/// Helpers for testing strict encoding implementations against test vectors
+/// and the general LNPBP-5 requirements, which may be used by downstream
+/// crates implementing [StrictEncode] and [StrictDecode] manually:
+/// `test_vector(&value, &[0x01, 0x00, 0x2A]).unwrap();`
+pub mod test_helpers {
+    use super::{strict_decode, strict_encode, StrictDecode, StrictEncode};
+    use core::fmt::Debug;
+
+    /// Failures detected by the strict encoding test helpers. Encoding and
+    /// decoding errors are represented by their description, since the
+    /// error types are implementation-specific.
+    #[derive(Clone, PartialEq, Debug, Display)]
+    #[display_from(Debug)]
+    pub enum DataEncodingTestFailure<T>
+    where
+        T: Clone + PartialEq + Debug,
+    {
+        /// Failure to encode the value
+        EncoderFailure(T, String),
+
+        /// Failure to decode the data
+        DecoderFailure(Vec<u8>, String),
+
+        /// Decoded value does not match the encoded one
+        DataMismatch { original: T, decoded: T },
+
+        /// Value encoding does not match the test vector
+        VectorMismatch { expected: Vec<u8>, found: Vec<u8> },
+
+        /// Length returned by [StrictEncode::strict_serialized_len] does not
+        /// match the length of the actual encoding
+        LengthMismatch { reported: usize, actual: usize },
+
+        /// Decoder has accepted truncated data
+        TruncatedDataAccepted(Vec<u8>),
+
+        /// Enum variants provided for the exhaustive coverage test do not
+        /// cover the value decoded from the given byte
+        VariantNotCovered(u8, T),
+    }
+
+    /// Checks that the value is encoded into the data which are decoded back
+    /// into the same value, that the decoder does not accept truncated
+    /// encoding and that [StrictEncode::strict_serialized_len] is correct.
+    /// Returns the encoded data.
+    pub fn test_encode_roundtrip<T>(value: &T) -> Result<Vec<u8>, DataEncodingTestFailure<T>>
+    where
+        T: StrictEncode + StrictDecode + Clone + PartialEq + Debug,
+    {
+        let encoded = strict_encode(value).map_err(|err| {
+            DataEncodingTestFailure::EncoderFailure(value.clone(), err.to_string())
+        })?;
+        let decoded = strict_decode::<T>(&encoded).map_err(|err| {
+            DataEncodingTestFailure::DecoderFailure(encoded.clone(), err.to_string())
+        })?;
+        if &decoded != value {
+            Err(DataEncodingTestFailure::DataMismatch {
+                original: value.clone(),
+                decoded,
+            })?
+        }
+        let reported = value.strict_serialized_len().map_err(|err| {
+            DataEncodingTestFailure::EncoderFailure(value.clone(), err.to_string())
+        })?;
+        if reported != encoded.len() {
+            Err(DataEncodingTestFailure::LengthMismatch {
+                reported,
+                actual: encoded.len(),
+            })?
+        }
+        if !encoded.is_empty() && strict_decode::<T>(&&encoded[..encoded.len() - 1]).is_ok() {
+            Err(DataEncodingTestFailure::TruncatedDataAccepted(
+                encoded[..encoded.len() - 1].to_vec(),
+            ))?
+        }
+        Ok(encoded)
+    }
+
+    /// Checks that the value is encoded exactly into the test vector data,
+    /// which are decoded back into the same value
+    pub fn test_vector<T>(value: &T, expected: &[u8]) -> Result<(), DataEncodingTestFailure<T>>
+    where
+        T: StrictEncode + StrictDecode + Clone + PartialEq + Debug,
+    {
+        let encoded = test_encode_roundtrip(value)?;
+        if encoded != expected {
+            Err(DataEncodingTestFailure::VectorMismatch {
+                expected: expected.to_vec(),
+                found: encoded,
+            })?
+        }
+        Ok(())
+    }
+
+    /// Checks `u8`-based enum encoding (like the one provided by
+    /// `impl_enum_strict_encoding!`) for the complete list of the enum
+    /// variants: each of the variants must pass [test_encode_roundtrip] and
+    /// each single-byte value must either be rejected by the decoder or
+    /// decoded into one of the variants having exactly this encoding.
+    pub fn test_enum_u8_exhaustive<T>(variants: &[T]) -> Result<(), DataEncodingTestFailure<T>>
+    where
+        T: StrictEncode + StrictDecode + Clone + PartialEq + Debug,
+    {
+        for variant in variants {
+            test_encode_roundtrip(variant)?;
+        }
+        for byte in 0..=core::u8::MAX {
+            let decoded = match strict_decode::<T>(&[byte]) {
+                Ok(decoded) => decoded,
+                Err(_) => continue,
+            };
+            if !variants.contains(&decoded) {
+                Err(DataEncodingTestFailure::VariantNotCovered(byte, decoded))?
+            }
+            test_vector(&decoded, &[byte])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "futures")]
+pub use async_io::{AsyncStrictDecode, AsyncStrictEncode, DecodeFuture, EncodeFuture};
+
+/// Asynchronous counterparts of [StrictEncode] and [StrictDecode] traits
+/// working with non-blocking [futures::io::AsyncWrite] and
+/// [futures::io::AsyncRead] streams, so strict-encoded data may be
+/// sent and received over async sockets. `async-std` streams implement these
+/// traits directly, while Tokio-based streams can be used via compatibility
+/// layer from `tokio-util`.
+///
+/// Since the size of the strict-encoded data is not known to the reader in
+/// advance, each value is transferred as a frame prefixed with the 32-bit
+/// little-endian length of its strict encoding. The encoder streams the data
+/// directly into the writer without buffering them; the decoder reads the
+/// frame incrementally, growing its buffer only as the data arrive, and
+/// decodes the value once with the synchronous decoder. Frames longer than
+/// [DecodeFuture::DEFAULT_MAX_FRAME_LEN] (or the limit set with
+/// [DecodeFuture::max_frame_len]) are rejected before reading their data.
+#[cfg(feature = "futures")]
+mod async_io {
+    use super::{Error, StrictDecode, StrictEncode};
+    use futures::io::{AsyncRead, AsyncWrite};
+    use std::future::Future;
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// Size of the chunks in which frame data are read from the stream, so
+    /// the memory is allocated only for the data which has actually arrived
+    const READ_CHUNK_LEN: usize = 0x10000;
+
+    /// Strict encoding into non-blocking [futures::io::AsyncWrite] stream.
+    /// Blanket implementation is provided for all types implementing
+    /// [StrictEncode].
+    pub trait AsyncStrictEncode: StrictEncode {
+        /// Encode into the provided async writer as a length-prefixed frame;
+        /// the future resolves into either amount of bytes written
+        /// (including frame length prefix) – or implementation-specific
+        /// error type.
+        fn strict_encode_async<'a, E>(&'a self, e: &'a mut E) -> EncodeFuture<'a, Self, E>
+        where
+            E: AsyncWrite + Unpin;
+    }
+
+    /// Strict decoding from non-blocking [futures::io::AsyncRead] stream.
+    /// Blanket implementation is provided for all types implementing
+    /// [StrictDecode].
+    pub trait AsyncStrictDecode: StrictDecode {
+        /// Decode a length-prefixed frame from the provided async reader;
+        /// the future resolves into either decoded instance or
+        /// implementation-specific error type. Never reads from the stream
+        /// more data than contained in the frame.
+        fn strict_decode_async<D>(d: &mut D) -> DecodeFuture<'_, Self, D>
+        where
+            D: AsyncRead + Unpin;
+    }
+
+    impl<T> AsyncStrictEncode for T
+    where
+        T: StrictEncode,
+    {
+        fn strict_encode_async<'a, E>(&'a self, e: &'a mut E) -> EncodeFuture<'a, Self, E>
+        where
+            E: AsyncWrite + Unpin,
+        {
+            EncodeFuture {
+                value: self,
+                writer: e,
+                prefix: None,
+                written: 0,
+            }
+        }
+    }
+
+    impl<T> AsyncStrictDecode for T
+    where
+        T: StrictDecode,
+    {
+        fn strict_decode_async<D>(d: &mut D) -> DecodeFuture<'_, Self, D>
+        where
+            D: AsyncRead + Unpin,
+        {
+            DecodeFuture {
+                reader: d,
+                prefix: [0u8; 4],
+                data: Vec::new(),
+                len: None,
+                max_len: DecodeFuture::<Self, D>::DEFAULT_MAX_FRAME_LEN,
+                fetched: 0,
+                _phantom: Default::default(),
+            }
+        }
+    }
+
+    /// Future returned by [AsyncStrictEncode::strict_encode_async]
+    pub struct EncodeFuture<'a, T: ?Sized, E> {
+        value: &'a T,
+        writer: &'a mut E,
+        /// Encoded frame length prefix, computed on the first poll
+        prefix: Option<[u8; 4]>,
+        /// Number of bytes (including length prefix) which were already
+        /// accepted by the writer
+        written: usize,
+    }
+
+    impl<T, E> Future for EncodeFuture<'_, T, E>
+    where
+        T: StrictEncode + ?Sized,
+        E: AsyncWrite + Unpin,
+    {
+        type Output = Result<usize, T::Error>;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let this = self.get_mut();
+            let prefix = match this.prefix {
+                Some(prefix) => prefix,
+                None => {
+                    let len = this.value.strict_serialized_len()?;
+                    if len > u32::MAX as usize {
+                        Err(Error::ExceedMaxItems(len))?
+                    }
+                    let prefix = (len as u32).to_le_bytes();
+                    this.prefix = Some(prefix);
+                    prefix
+                }
+            };
+
+            // Synchronous encoder can't be suspended, so if the writer is
+            // not ready to accept more data we abort the encoding and, once
+            // woken up, repeat it from the start, skipping the part of the
+            // data which was already written.
+            let value = this.value;
+            let mut sink = PollWriter {
+                writer: Pin::new(&mut *this.writer),
+                cx,
+                skip: this.written,
+                pos: 0,
+                pending: false,
+            };
+            let result = io::Write::write_all(&mut sink, &prefix)
+                .map_err(Error::from)
+                .map_err(T::Error::from)
+                .and_then(|_| value.strict_encode(&mut sink));
+            let pending = sink.pending;
+            this.written = this.written.max(sink.pos);
+            match result {
+                Err(_) if pending => Poll::Pending,
+                Err(err) => Poll::Ready(Err(err)),
+                Ok(len) => match Pin::new(&mut *this.writer).poll_flush(cx) {
+                    Poll::Pending => Poll::Pending,
+                    Poll::Ready(Err(err)) => Poll::Ready(Err(Error::from(err).into())),
+                    Poll::Ready(Ok(())) => Poll::Ready(Ok(prefix.len() + len)),
+                },
+            }
+        }
+    }
+
+    /// Synchronous writer adapter passing the data directly to the async
+    /// writer. Fails with [io::ErrorKind::WouldBlock] and sets `pending`
+    /// flag when the async writer is not ready.
+    struct PollWriter<'a, 'b, E> {
+        writer: Pin<&'a mut E>,
+        cx: &'a mut Context<'b>,
+        /// Number of bytes written during previous polls, which must be
+        /// skipped
+        skip: usize,
+        /// Current position in the encoded data
+        pos: usize,
+        pending: bool,
+    }
+
+    impl<E> io::Write for PollWriter<'_, '_, E>
+    where
+        E: AsyncWrite + Unpin,
+    {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let skipped = self.skip.saturating_sub(self.pos).min(buf.len());
+            if skipped == buf.len() {
+                self.pos += skipped;
+                return Ok(skipped);
+            }
+            match self.writer.as_mut().poll_write(self.cx, &buf[skipped..]) {
+                Poll::Ready(Ok(0)) => Err(io::ErrorKind::WriteZero.into()),
+                Poll::Ready(Ok(len)) => {
+                    self.pos += skipped + len;
+                    Ok(skipped + len)
+                }
+                Poll::Ready(Err(err)) => Err(err),
+                Poll::Pending if skipped > 0 => {
+                    self.pos += skipped;
+                    Ok(skipped)
+                }
+                Poll::Pending => {
+                    self.pending = true;
+                    Err(io::ErrorKind::WouldBlock.into())
+                }
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Future returned by [AsyncStrictDecode::strict_decode_async]
+    pub struct DecodeFuture<'a, T, D> {
+        reader: &'a mut D,
+        prefix: [u8; 4],
+        data: Vec<u8>,
+        /// Frame length, known once the prefix is read
+        len: Option<usize>,
+        /// Maximum accepted frame length
+        max_len: usize,
+        /// Number of bytes read for the current part of the frame (length
+        /// prefix or data)
+        fetched: usize,
+        _phantom: std::marker::PhantomData<fn() -> T>,
+    }
+
+    impl<T, D> DecodeFuture<'_, T, D> {
+        /// Maximum frame length accepted when no other limit is set
+        pub const DEFAULT_MAX_FRAME_LEN: usize = 0x0100_0000;
+
+        /// Returns the same future accepting frames up to the given length
+        #[inline]
+        pub fn max_frame_len(self, max_len: usize) -> Self {
+            Self { max_len, ..self }
+        }
+    }
+
+    impl<T, D> Future for DecodeFuture<'_, T, D>
+    where
+        T: StrictDecode,
+        D: AsyncRead + Unpin,
+    {
+        type Output = Result<T, T::Error>;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let this = self.get_mut();
+            loop {
+                let buf = match this.len {
+                    None if this.fetched == this.prefix.len() => {
+                        let len = u32::from_le_bytes(this.prefix) as usize;
+                        if len > this.max_len {
+                            return Poll::Ready(Err(Error::ValueOutOfRange(
+                                "frame length".to_string(),
+                                0..this.max_len as u64 + 1,
+                                len as u64,
+                            )
+                            .into()));
+                        }
+                        this.len = Some(len);
+                        this.fetched = 0;
+                        continue;
+                    }
+                    None => &mut this.prefix[this.fetched..],
+                    Some(len) if this.fetched == len => {
+                        let data = std::mem::take(&mut this.data);
+                        return Poll::Ready(super::strict_decode(&data));
+                    }
+                    Some(len) => {
+                        if this.data.len() == this.fetched {
+                            let chunk = (len - this.fetched).min(READ_CHUNK_LEN);
+                            this.data.resize(this.fetched + chunk, 0);
+                        }
+                        &mut this.data[this.fetched..]
+                    }
+                };
+                match Pin::new(&mut *this.reader).poll_read(cx, buf) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok(0)) => {
+                        let err = io::Error::from(io::ErrorKind::UnexpectedEof);
+                        return Poll::Ready(Err(Error::from(err).into()));
+                    }
+                    Poll::Ready(Ok(read)) => this.fetched += read,
+                    Poll::Ready(Err(err)) => return Poll::Ready(Err(Error::from(err).into())),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    #[cfg(feature = "property_tests")]
+    use std::collections::{BTreeMap, BTreeSet};
+
+    /// Property checked for all strict-encodable types: data must be decoded
+    /// into the value having exactly the same encoding, while truncated or
+    /// extended encodings must be rejected by the decoder. Values which are
+    /// not encodable (like collections exceeding maximum size) are skipped.
+    #[cfg(feature = "property_tests")]
+    pub(crate) fn encoding_roundtrip<T>(val: &T) -> bool
+    where
+        T: StrictEncode<Error = Error> + StrictDecode<Error = Error>,
+    {
+        let encoded = match strict_encode(val) {
+            Ok(encoded) => encoded,
+            Err(Error::ExceedMaxItems(_)) => return true,
+            Err(_) => return false,
+        };
+        let decoded = match strict_decode::<T>(&encoded) {
+            Ok(decoded) => decoded,
+            Err(_) => return false,
+        };
+        if strict_encode(&decoded).ok() != Some(encoded.clone()) {
+            return false;
+        }
+        if !encoded.is_empty() && strict_decode::<T>(&&encoded[..encoded.len() - 1]).is_ok() {
+            return false;
+        }
+        let mut extended = encoded;
+        extended.push(0);
+        strict_decode::<T>(&extended).is_err()
+    }
+
+    fn gen_strings() -> Vec<&'static str> {
+        vec![
+            "",
+            "0",
+            " ",
+            "A string slice (&str) is made of bytes (u8), and a byte slice \
+            (&[u8]) is made of bytes, so this function converts between the two.\
+             Not all byte slices are valid string slices, however: &str requires \
+             that it is valid UTF-8. from_utf8() checks to ensure that the bytes \
+             are valid UTF-8, and then does the conversion.",
+        ]
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        gen_strings().into_iter().for_each(|s| {
+            let r = strict_encode(&s).unwrap();
+            let p: String = strict_decode(&r).unwrap();
+            assert_eq!(s, p);
+        })
+    }
+
+    #[test]
+    fn test_borrowed_decoding() {
+        gen_strings().into_iter().for_each(|s| {
+            let data = strict_encode(&s).unwrap();
+            let view: &str = strict_decode_borrowed(&data).unwrap();
+            assert_eq!(view, s);
+            assert_eq!(view.as_ptr(), data[2..].as_ptr());
+        });
+
+        let mut data = strict_encode(&"abc").unwrap();
+        data.extend(strict_encode(&&[1u8, 2, 3][..]).unwrap());
+        data.extend(strict_encode(&0xABCDu16).unwrap());
+        let mut cursor = &data[..];
+        assert_eq!(<&str>::strict_decode_borrowed(&mut cursor).unwrap(), "abc");
+        assert_eq!(
+            <&[u8]>::strict_decode_borrowed(&mut cursor).unwrap(),
+            &[1u8, 2, 3]
+        );
+        assert_eq!(u16::strict_decode(&mut cursor).unwrap(), 0xABCD);
+        assert!(cursor.is_empty());
+
+        assert!(matches!(
+            strict_decode_borrowed::<&[u8]>(&data[..4]),
+            Err(Error::Io(_))
+        ));
+        assert!(matches!(
+            strict_decode_borrowed::<&str>(&data),
+            Err(Error::DataNotEntirelyConsumed)
+        ));
+        assert!(matches!(
+            strict_decode_borrowed::<&str>(&[1u8, 0, 0xFF]),
+            Err(Error::Utf8Conversion)
+        ));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        type Nested = Option<Vec<Option<Vec<u8>>>>;
+        let data = strict_encode(&Some(vec![Some(vec![1u8, 2]), None])).unwrap();
+        assert!(strict_decode::<Nested>(&data).is_ok());
+        assert!(DecodeContext::with_max_depth(4)
+            .strict_decode::<Nested>(&data)
+            .is_ok());
+        assert!(matches!(
+            DecodeContext::with_max_depth(3).strict_decode::<Nested>(&data),
+            Err(Error::DepthLimitExceeded(3))
+        ));
+        assert_eq!(DecodeContext::current(), DecodeContext::default());
+        assert!(strict_decode::<Nested>(&data).is_ok());
+
+        #[derive(Debug)]
+        struct Tree(Vec<Tree>);
+        impl StrictDecode for Tree {
+            type Error = Error;
+            fn strict_decode<D: io::Read>(d: D) -> Result<Self, Error> {
+                Ok(Tree(Vec::strict_decode(d)?))
+            }
+        }
+
+        let nested = |depth: usize| {
+            let mut data = [1u8, 0].repeat(depth - 1);
+            data.extend(&[0u8, 0]);
+            data
+        };
+        assert!(strict_decode::<Tree>(&nested(DecodeContext::DEFAULT_MAX_DEPTH)).is_ok());
+        assert!(matches!(
+            strict_decode::<Tree>(&nested(DecodeContext::DEFAULT_MAX_DEPTH + 1)),
+            Err(Error::DepthLimitExceeded(DecodeContext::DEFAULT_MAX_DEPTH))
+        ));
+        assert!(DecodeContext::with_max_depth(1000)
+            .strict_decode::<Tree>(&nested(1000))
+            .is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "DataNotEntirelyConsumed")]
+    fn test_consumation() {
+        gen_strings().into_iter().for_each(|s| {
+            let mut r = strict_encode(&s).unwrap();
+            r.extend_from_slice("data".as_ref());
+            let _: String = strict_decode(&r).unwrap();
+        })
+    }
+
+    #[test]
+    fn test_error_propagation() {
+        gen_strings().into_iter().for_each(|s| {
+            let r = strict_encode(&s).unwrap();
+            let p: Result<String, _> = strict_decode(&r[..1].to_vec());
+            assert!(p.is_err());
+        })
+    }
+
+    /// Checking that byte encoding and decoding works correctly for the most common
+    /// marginal and middle-probability cases
+    #[test]
+    fn test_u8_encode() {
+        let zero: u8 = 0;
+        let one: u8 = 1;
+        let thirteen: u8 = 13;
+        let confusing: u8 = 0xEF;
+        let nearly_full: u8 = 0xFE;
+        let full: u8 = 0xFF;
+
+        let byte_0 = &[0u8][..];
+        let byte_1 = &[1u8][..];
+        let byte_13 = &[13u8][..];
+        let byte_ef = &[0xEFu8][..];
+        let byte_fe = &[0xFEu8][..];
+        let byte_ff = &[0xFFu8][..];
+
+        assert_eq!(strict_encode(&zero).unwrap(), byte_0);
+        assert_eq!(strict_encode(&one).unwrap(), byte_1);
+        assert_eq!(strict_encode(&thirteen).unwrap(), byte_13);
+        assert_eq!(strict_encode(&confusing).unwrap(), byte_ef);
+        assert_eq!(strict_encode(&nearly_full).unwrap(), byte_fe);
+        assert_eq!(strict_encode(&full).unwrap(), byte_ff);
+
+        assert_eq!(u8::strict_decode(byte_0).unwrap(), zero);
+        assert_eq!(u8::strict_decode(byte_1).unwrap(), one);
+        assert_eq!(u8::strict_decode(byte_13).unwrap(), thirteen);
+        assert_eq!(u8::strict_decode(byte_ef).unwrap(), confusing);
+        assert_eq!(u8::strict_decode(byte_fe).unwrap(), nearly_full);
+        assert_eq!(u8::strict_decode(byte_ff).unwrap(), full);
+    }
+
+    /// Test for checking the following rule from LNPBP-5:
+    ///
+    /// `Option<T>` of any type T, which are set to `Option::None` value MUST encode as two
+    /// zero bytes and it MUST be possible to decode optional of any type from two zero bytes
+    /// which MUST result in `Option::None` value.
+    #[test]
+    fn test_option_encode_none() {
+        let o1: Option<u8> = None;
+        let o2: Option<u64> = None;
+
+        let two_zero_bytes = &vec![0u8][..];
+
+        assert_eq!(strict_encode(&o1).unwrap(), two_zero_bytes);
+        assert_eq!(strict_encode(&o2).unwrap(), two_zero_bytes);
+
+        assert_eq!(Option::<u8>::strict_decode(two_zero_bytes).unwrap(), None);
+        assert_eq!(Option::<u64>::strict_decode(two_zero_bytes).unwrap(), None);
+    }
+
+    /// Test for checking the following rule from LNPBP-5:
+    ///
+    /// `Option<T>` of any type T, which are set to `Option::Some<T>` value MUST encode as a
+    /// `Vec<T>` structure containing a single item equal to the `Option::unwrap()` value.
+    #[test]
+    fn test_option_encode_some() {
+        let o1: Option<u8> = Some(0);
+        let o2: Option<u8> = Some(13);
+        let o3: Option<u8> = Some(0xFF);
+        let o4: Option<u64> = Some(13);
+        let o5: Option<u64> = Some(0x1FF);
+        let o6: Option<u64> = Some(0xFFFFFFFFFFFFFFFF);
+        let o7: Option<usize> = Some(13);
+        let o8: Option<usize> = Some(0xFFFFFFFFFFFFFFFF);
+
+        let byte_0 = &[1u8, 0u8][..];
+        let byte_13 = &[1u8, 13u8][..];
+        let byte_255 = &[1u8, 0xFFu8][..];
+        let word_13 = &[1u8, 13u8, 0u8][..];
+        let qword_13 = &[1u8, 13u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8][..];
+        let qword_256 = &[1u8, 0xFFu8, 0x01u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8][..];
+        let qword_max = &[
+            1u8, 0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8,
+        ][..];
+
+        assert_eq!(strict_encode(&o1).unwrap(), byte_0);
+        assert_eq!(strict_encode(&o2).unwrap(), byte_13);
+        assert_eq!(strict_encode(&o3).unwrap(), byte_255);
+        assert_eq!(strict_encode(&o4).unwrap(), qword_13);
+        assert_eq!(strict_encode(&o5).unwrap(), qword_256);
+        assert_eq!(strict_encode(&o6).unwrap(), qword_max);
+        assert_eq!(strict_encode(&o7).unwrap(), word_13);
+        assert!(strict_encode(&o8).err().is_some());
+
+        assert_eq!(Option::<u8>::strict_decode(byte_0).unwrap(), Some(0));
+        assert_eq!(Option::<u8>::strict_decode(byte_13).unwrap(), Some(13));
+        assert_eq!(Option::<u8>::strict_decode(byte_255).unwrap(), Some(0xFF));
+        assert_eq!(Option::<u64>::strict_decode(qword_13).unwrap(), Some(13));
+        assert_eq!(
+            Option::<u64>::strict_decode(qword_256).unwrap(),
+            Some(0x1FF)
+        );
+        assert_eq!(
+            Option::<u64>::strict_decode(qword_max).unwrap(),
+            Some(0xFFFFFFFFFFFFFFFF)
+        );
+        assert_eq!(Option::<usize>::strict_decode(word_13).unwrap(), Some(13));
+        assert_eq!(
+            Option::<usize>::strict_decode(qword_max).unwrap(),
+            Some(0xFFFF)
+        );
+    }
+
+    /// Test trying decoding of non-zero and non-single item vector structures, which MUST
+    /// fail with a specific error.
+    #[test]
+    fn test_option_decode_vec() {
+        assert!(Option::<u8>::strict_decode(&[2u8, 0u8, 0u8, 0u8][..])
+            .err()
+            .is_some());
+        assert!(Option::<u8>::strict_decode(&[3u8, 0u8, 0u8, 0u8][..])
+            .err()
+            .is_some());
+        assert!(Option::<u8>::strict_decode(&[0xFFu8, 0u8, 0u8, 0u8][..])
+            .err()
+            .is_some());
+    }
+
+    #[test]
+    fn test_pointers_transparent() {
+        use std::borrow::Cow;
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        let v: Vec<u64> = vec![0, 13, 0x1FF];
+        let encoded = strict_encode(&v).unwrap();
+
+        assert_eq!(strict_encode(&Box::new(v.clone())).unwrap(), encoded);
+        assert_eq!(strict_encode(&Rc::new(v.clone())).unwrap(), encoded);
+        assert_eq!(strict_encode(&Arc::new(v.clone())).unwrap(), encoded);
+        assert_eq!(strict_encode(&Cow::Borrowed(&v)).unwrap(), encoded);
+        assert_eq!(
+            strict_encode(&Cow::<Vec<u64>>::Owned(v.clone())).unwrap(),
+            encoded
+        );
+
+        assert_eq!(*strict_decode::<Box<Vec<u64>>>(&encoded).unwrap(), v);
+        assert_eq!(*strict_decode::<Rc<Vec<u64>>>(&encoded).unwrap(), v);
+        assert_eq!(*strict_decode::<Arc<Vec<u64>>>(&encoded).unwrap(), v);
+        assert_eq!(
+            strict_decode::<Cow<Vec<u64>>>(&encoded)
+                .unwrap()
+                .into_owned(),
+            v
+        );
+        assert!(strict_decode::<Arc<Vec<u64>>>(&&encoded[1..]).is_err());
+    }
+
+    /// Test for checking the following rule from LNPBP-5:
+    ///
+    /// Array of any commitment-serializable type T MUST contain strictly less than `0x10000` items
+    /// and must encode as 16-bit little-endian value corresponding to the number of items
+    /// followed by a direct encoding of each of the items.
+    #[test]
+    fn test_vec_encode() {
+        let v1: Vec<u8> = vec![0, 13, 0xFF];
+        let v2: Vec<u8> = vec![13];
+        let v3: Vec<u64> = vec![0, 13, 13, 0x1FF, 0xFFFFFFFFFFFFFFFF];
+        let v4: Vec<u8> = (0..0x1FFFF).map(|item| (item % 0xFF) as u8).collect();
+
+        let s1 = &[3u8, 0u8, 0u8, 13u8, 0xFFu8][..];
+        let s2 = &[1u8, 0u8, 13u8][..];
         let s3 = &[
             5u8, 0u8, 0, 0, 0, 0, 0, 0, 0, 0, 13, 0, 0, 0, 0, 0, 0, 0, 13, 0, 0, 0, 0, 0, 0, 0,
             0xFF, 1, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
@@ -975,4 +3608,723 @@ mod test {
         assert_eq!(Vec::<u8>::strict_decode(s2).unwrap(), v2);
         assert_eq!(Vec::<u64>::strict_decode(s3).unwrap(), v3);
     }
+
+    #[test]
+    fn test_large_vec_encode() {
+        let v1: Vec<u8> = (0..0x1FFFF).map(|item| (item % 0xFF) as u8).collect();
+        let v2: LargeVec<u8> = LargeVec::from(v1.clone());
+
+        let encoded = strict_encode(&v2).unwrap();
+        assert_eq!(encoded[..4], [0xFF, 0xFF, 0x01, 0x00]);
+        assert_eq!(encoded.len(), 4 + 0x1FFFF);
+        assert_eq!(strict_decode::<LargeVec<u8>>(&encoded).unwrap(), v2);
+        assert_eq!(
+            strict_decode::<LargeVec<u8>>(&encoded)
+                .unwrap()
+                .into_inner(),
+            v1
+        );
+
+        let s1 = &[3u8, 0, 0, 0, 0, 13, 0xFF][..];
+        assert_eq!(
+            strict_decode::<LargeVec<u8>>(&s1).unwrap().into_inner(),
+            vec![0, 13, 0xFF]
+        );
+        assert!(strict_decode::<LargeVec<u8>>(&[0xFFu8, 0xFF, 0xFF, 0xFF, 0]).is_err());
+    }
+
+    #[test]
+    fn test_large_map_encode() {
+        let map: LargeMap<u16, u8> = (0..0x1FFFFu32)
+            .map(|item| ((item % 0xFFFF) as u16, (item % 0xFF) as u8))
+            .collect();
+        assert_eq!(map.len(), 0xFFFF);
+        let encoded = strict_encode(&map).unwrap();
+        assert_eq!(encoded.len(), 4 + 0xFFFF * 3);
+        assert_eq!(strict_decode::<LargeMap<u16, u8>>(&encoded).unwrap(), map);
+
+        let s1 = &[2u8, 0, 0, 0, 1, 0, 13, 1, 0, 14][..];
+        assert!(strict_decode::<LargeMap<u16, u8>>(&s1).is_err());
+    }
+
+    #[cfg(feature = "property_tests")]
+    quickcheck! {
+        fn prop_u8(val: u8) -> bool { encoding_roundtrip(&val) }
+        fn prop_u16(val: u16) -> bool { encoding_roundtrip(&val) }
+        fn prop_u32(val: u32) -> bool { encoding_roundtrip(&val) }
+        fn prop_u64(val: u64) -> bool { encoding_roundtrip(&val) }
+        fn prop_i8(val: i8) -> bool { encoding_roundtrip(&val) }
+        fn prop_i16(val: i16) -> bool { encoding_roundtrip(&val) }
+        fn prop_i32(val: i32) -> bool { encoding_roundtrip(&val) }
+        fn prop_i64(val: i64) -> bool { encoding_roundtrip(&val) }
+        fn prop_usize(val: usize) -> bool { encoding_roundtrip(&val) }
+        fn prop_f32(val: f32) -> bool { encoding_roundtrip(&val) }
+        fn prop_f64(val: f64) -> bool { encoding_roundtrip(&val) }
+        fn prop_string(val: String) -> bool { encoding_roundtrip(&val) }
+        fn prop_bytes(val: Vec<u8>) -> bool {
+            encoding_roundtrip(&val.into_boxed_slice())
+        }
+        fn prop_option(val: Option<u32>) -> bool { encoding_roundtrip(&val) }
+        fn prop_vec(val: Vec<u64>) -> bool { encoding_roundtrip(&val) }
+        fn prop_nested_vec(val: Vec<Vec<String>>) -> bool { encoding_roundtrip(&val) }
+        fn prop_btree_set(val: BTreeSet<u16>) -> bool { encoding_roundtrip(&val) }
+        fn prop_btree_map(val: BTreeMap<u32, Option<String>>) -> bool {
+            encoding_roundtrip(&val)
+        }
+        fn prop_large_vec(val: LargeVec<u32>) -> bool { encoding_roundtrip(&val) }
+        fn prop_large_map(val: LargeMap<u8, Vec<u8>>) -> bool { encoding_roundtrip(&val) }
+    }
+
+    #[test]
+    fn test_confined_string() {
+        use std::convert::TryFrom;
+        use std::str::FromStr;
+
+        for s in gen_strings() {
+            let s1 = MaxLenString::<1024>::from_str(s).unwrap();
+            assert_eq!(strict_encode(&s1).unwrap(), strict_encode(&s).unwrap());
+            assert_eq!(
+                strict_decode::<MaxLenString<1024>>(&strict_encode(&s).unwrap()).unwrap(),
+                s1
+            );
+        }
+        let long = gen_strings()[3];
+        assert!(String255::from_str(long).is_err());
+        assert!(strict_decode::<String255>(&strict_encode(&long).unwrap()).is_err());
+        assert_eq!(
+            String255::try_from("a".repeat(255)).unwrap().as_str(),
+            "a".repeat(255)
+        );
+        assert!(matches!(
+            String255::try_from("a".repeat(256)),
+            Err(Error::ValueOutOfRange(_, range, 256)) if range == (0..256)
+        ));
+    }
+
+    #[test]
+    fn test_ascii_string() {
+        use std::convert::TryFrom;
+        use std::str::FromStr;
+
+        type Ticker = AsciiString<AlphanumericAscii, 8>;
+        type Name = AsciiString<PrintableAscii, 32>;
+
+        let ticker = Ticker::from_str("USDT").unwrap();
+        assert_eq!(
+            strict_encode(&ticker).unwrap(),
+            strict_encode(&"USDT").unwrap()
+        );
+        assert_eq!(
+            strict_decode::<Ticker>(&strict_encode(&ticker).unwrap()).unwrap(),
+            ticker
+        );
+        assert_eq!(
+            MaxLenString::<8>::from(ticker.clone()).as_str(),
+            ticker.as_str()
+        );
+        assert!(Name::from_str("Tether USD ~ stablecoin!").is_ok());
+
+        assert!(Ticker::from_str("USD T").is_err());
+        assert!(Ticker::from_str("TOOLONGTICKER").is_err());
+        assert!(Name::from_str("Tab\tseparated").is_err());
+        assert!(Name::try_from("Ünicode".to_string()).is_err());
+        assert!(matches!(
+            strict_decode::<Ticker>(&strict_encode(&"US-D").unwrap()),
+            Err(Error::DataIntegrityError(_))
+        ));
+        assert!(matches!(
+            strict_decode::<Ticker>(&strict_encode(&"ABCDEFGHI").unwrap()),
+            Err(Error::ValueOutOfRange(_, range, 9)) if range == (0..9)
+        ));
+    }
+
+    #[test]
+    fn test_confined_vec() {
+        use std::convert::TryFrom;
+
+        let mut v1 = ConfinedVec::<u8, 1, 3>::try_from(vec![1u8, 2]).unwrap();
+        assert!(v1.push(3).is_ok());
+        assert!(v1.push(4).is_err());
+        assert_eq!(&v1[..], &[1, 2, 3]);
+        assert_eq!(v1.pop(), Some(3));
+        assert_eq!(v1.pop(), Some(2));
+        assert_eq!(v1.pop(), None);
+        assert!(ConfinedVec::<u8, 1, 3>::try_from(vec![]).is_err());
+
+        let encoded = strict_encode(&v1).unwrap();
+        assert_eq!(encoded, &[1u8, 0, 1]);
+        assert_eq!(
+            strict_decode::<ConfinedVec<u8, 1, 3>>(&encoded).unwrap(),
+            v1
+        );
+        assert!(strict_decode::<ConfinedVec<u8, 2, 3>>(&encoded).is_err());
+        assert!(strict_decode::<ConfinedVec<u8, 0, 3>>(&[4u8, 0, 1, 2, 3, 4]).is_err());
+    }
+
+    #[test]
+    fn test_time() {
+        use std::convert::TryFrom;
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+        let duration = Duration::new(0x0102, 0x0A0B0C);
+        let data = strict_encode(&duration).unwrap();
+        assert_eq!(data, &[2u8, 1, 0, 0, 0, 0, 0, 0, 0x0C, 0x0B, 0x0A, 0]);
+        assert_eq!(strict_decode::<Duration>(&data).unwrap(), duration);
+        assert!(matches!(
+            strict_decode::<Duration>(&[0u8, 0, 0, 0, 0, 0, 0, 0, 0x00, 0xCA, 0x9A, 0x3B]),
+            Err(Error::ValueOutOfRange(_, _, 1_000_000_000))
+        ));
+
+        let time = UNIX_EPOCH + duration;
+        assert_eq!(strict_encode(&time).unwrap(), data);
+        assert_eq!(strict_decode::<SystemTime>(&data).unwrap(), time);
+        assert!(strict_encode(&(UNIX_EPOCH - duration)).is_err());
+
+        let timestamp = Timestamp::from_unix_secs(-1).unwrap();
+        let data = strict_encode(&timestamp).unwrap();
+        assert_eq!(data, &[0xFFu8; 8]);
+        assert_eq!(strict_decode::<Timestamp>(&data).unwrap(), timestamp);
+        assert_eq!(
+            Timestamp::try_from(SystemTime::from(timestamp)).unwrap(),
+            timestamp
+        );
+        assert_eq!(Timestamp::try_from(time).unwrap().unix_secs(), 0x0102);
+        assert!(Timestamp::from_unix_secs(Timestamp::MAX.unix_secs() + 1).is_none());
+        assert!(strict_decode::<Timestamp>(&strict_encode(&i64::MIN).unwrap()).is_err());
+        assert!(Timestamp::now() > Timestamp::default());
+    }
+
+    #[test]
+    fn test_framing() {
+        let data = encode_frame(&"framed").unwrap();
+        assert_eq!(&data[..4], &[8u8, 0, 0, 0]);
+        assert_eq!(data.len(), 4 + 8 + 4);
+        assert_eq!(decode_frame::<String>(&data).unwrap(), "framed");
+
+        assert!(matches!(
+            decode_frame::<String>(&&data[..data.len() - 1]),
+            Err(Error::Io(_))
+        ));
+        let mut corrupted = data.clone();
+        corrupted[6] ^= 0x01;
+        assert!(matches!(
+            decode_frame::<String>(&corrupted),
+            Err(Error::ChecksumMismatch)
+        ));
+        let mut forged = data;
+        forged[3] = 0xFF;
+        assert!(matches!(decode_frame::<String>(&forged), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let data = strict_encode(&vec![vec![1u8, 2], vec![3u8]]).unwrap();
+        let context = DecodeContext::default().max_items(5);
+        assert!(context.strict_decode::<Vec<Vec<u8>>>(&data).is_ok());
+        // Budget is renewed for each application of the context
+        assert!(context.strict_decode::<Vec<Vec<u8>>>(&data).is_ok());
+        assert!(matches!(
+            DecodeContext::default()
+                .max_items(4)
+                .strict_decode::<Vec<Vec<u8>>>(&data),
+            Err(Error::SizeLimitExceeded(4))
+        ));
+        assert!(matches!(
+            DecodeContext::default()
+                .max_items(0xFF)
+                .strict_decode::<Box<[u8]>>(&[0x00u8, 0x01]),
+            Err(Error::SizeLimitExceeded(0xFF))
+        ));
+
+        // Plain strict decoding uses the budget of the context applied in the
+        // thread, renewing it for each call
+        DecodeContext::default().max_items(5).apply(|| {
+            assert!(strict_decode::<Vec<Vec<u8>>>(&data).is_ok());
+            assert!(strict_decode::<Vec<Vec<u8>>>(&data).is_ok());
+        });
+        DecodeContext::default().max_items(4).apply(|| {
+            assert!(matches!(
+                strict_decode::<Vec<Vec<u8>>>(&data),
+                Err(Error::SizeLimitExceeded(4))
+            ));
+        });
+        // Direct decoding of the top-level data structures renews the budget
+        // as well, while the data decoded with nested calls share it
+        DecodeContext::default().max_items(5).apply(|| {
+            assert!(Vec::<Vec<u8>>::strict_decode(&data[..]).is_ok());
+            assert!(Vec::<Vec<u8>>::strict_decode(&data[..]).is_ok());
+            assert!(Box::<[u8]>::strict_decode(&[5u8, 0, 1, 2, 3, 4, 5][..]).is_ok());
+            assert!(Box::<[u8]>::strict_decode(&[5u8, 0, 1, 2, 3, 4, 5][..]).is_ok());
+        });
+
+        #[derive(Debug)]
+        struct Blob(Vec<u8>);
+        impl StrictDecode for Blob {
+            type Error = Error;
+            fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Error> {
+                let mut data = [0u8; 4];
+                d.read_exact(&mut data)?;
+                Ok(Blob(strict_decode(&data)?))
+            }
+        }
+
+        let blobs = [2u8, 0, 2, 0, 0xA, 0xB, 2, 0, 0xC, 0xD];
+        assert!(DecodeContext::default()
+            .max_items(6)
+            .strict_decode::<Vec<Blob>>(&blobs)
+            .is_ok());
+        assert!(matches!(
+            DecodeContext::default()
+                .max_items(5)
+                .strict_decode::<Vec<Blob>>(&blobs),
+            Err(Error::SizeLimitExceeded(5))
+        ));
+
+        // Default budget is finite, so the length prefix can't make the
+        // decoder allocate arbitrary amount of memory
+        assert!(matches!(
+            strict_decode::<LargeVec<u8>>(&[0xFFu8, 0xFF, 0xFF, 0xFF]),
+            Err(Error::SizeLimitExceeded(DecodeContext::DEFAULT_MAX_ITEMS))
+        ));
+        assert_eq!(DecodeContext::current(), DecodeContext::default());
+    }
+
+    #[test]
+    fn test_helpers() {
+        use test_helpers::*;
+
+        assert_eq!(
+            test_encode_roundtrip(&Some(vec![1u16, 2])).unwrap(),
+            &[1u8, 0, 2, 0, 1, 0, 2, 0]
+        );
+        assert_eq!(
+            test_vector(&String::from("ok"), &[2u8, 0, b'o', b'k']),
+            Ok(())
+        );
+        assert_eq!(
+            test_vector(&0x0102u16, &[0x01u8, 0x02]),
+            Err(DataEncodingTestFailure::VectorMismatch {
+                expected: vec![0x01, 0x02],
+                found: vec![0x02, 0x01]
+            })
+        );
+
+        #[derive(Clone, Copy, PartialEq, Debug)]
+        enum Sample {
+            A = 0,
+            B = 2,
+            C = 3,
+        }
+        impl StrictEncode for Sample {
+            type Error = Error;
+            fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Error> {
+                (*self as u8).strict_encode(e)
+            }
+        }
+        impl StrictDecode for Sample {
+            type Error = Error;
+            fn strict_decode<D: io::Read>(d: D) -> Result<Self, Error> {
+                match u8::strict_decode(d)? {
+                    0 => Ok(Sample::A),
+                    2 => Ok(Sample::B),
+                    3 | 4 => Ok(Sample::C),
+                    no => Err(Error::EnumValueNotKnown("Sample".to_string(), no)),
+                }
+            }
+        }
+        assert_eq!(
+            test_enum_u8_exhaustive(&[Sample::A, Sample::B]),
+            Err(DataEncodingTestFailure::VariantNotCovered(3, Sample::C))
+        );
+        assert_eq!(
+            test_enum_u8_exhaustive(&[Sample::A, Sample::B, Sample::C]),
+            Err(DataEncodingTestFailure::VectorMismatch {
+                expected: vec![4],
+                found: vec![3]
+            })
+        );
+    }
+
+    #[test]
+    fn test_float_canonicalization() {
+        let nan = f32::from_bits(0x7FC0_0001);
+        assert!(nan.is_nan());
+        assert_eq!(strict_encode(&nan).unwrap(), &[0x00u8, 0x00, 0xC0, 0x7F]);
+        assert_eq!(
+            strict_encode(&-0.0f32).unwrap(),
+            strict_encode(&0.0f32).unwrap()
+        );
+        assert_eq!(
+            strict_encode(&-f64::NAN).unwrap(),
+            strict_encode(&f64::from_bits(0x7FF8_0000_0000_0000)).unwrap()
+        );
+        assert_eq!(strict_encode(&-0.0f64).unwrap(), &[0u8; 8]);
+        assert_eq!(
+            strict_decode::<f64>(&strict_encode(&-1.5f64).unwrap()).unwrap(),
+            -1.5
+        );
+
+        let strict = DecodeContext::default().canonical_floats();
+        let negative_zero = (-0.0f32).to_bits().to_le_bytes();
+        assert!(strict_decode::<f32>(&negative_zero).is_ok());
+        assert!(strict.strict_decode::<f32>(&negative_zero).is_err());
+        assert!(strict
+            .strict_decode::<f32>(&0x7FC0_0001u32.to_le_bytes())
+            .is_err());
+        assert!(strict
+            .strict_decode::<f32>(&0x7FC0_0000u32.to_le_bytes())
+            .is_ok());
+        assert!(strict
+            .strict_decode::<f64>(&0xFFF8_0000_0000_0000u64.to_le_bytes())
+            .is_err());
+        assert!(strict
+            .strict_decode::<f64>(&1.5f64.to_bits().to_le_bytes())
+            .is_ok());
+        assert_eq!(DecodeContext::current(), DecodeContext::default());
+    }
+
+    #[test]
+    fn test_big_endian() {
+        assert_eq!(strict_encode(&be::u16(0x0102)).unwrap(), &[0x01u8, 0x02]);
+        assert_eq!(
+            strict_encode(&be::u32(0x01020304)).unwrap(),
+            &[0x01u8, 0x02, 0x03, 0x04]
+        );
+        assert_eq!(
+            strict_encode(&be::u64(0x0102030405060708)).unwrap(),
+            &[0x01u8, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]
+        );
+        assert_eq!(
+            strict_decode::<be::u32>(&[0x01u8, 0x02, 0x03, 0x04]).unwrap(),
+            be::u32::from(0x01020304)
+        );
+        assert_eq!(
+            u16::from(strict_decode::<be::u16>(&[0x01u8, 0x02]).unwrap()),
+            0x0102
+        );
+        assert!(strict_decode::<be::u64>(&[0x01u8, 0x02, 0x03, 0x04]).is_err());
+    }
+
+    #[test]
+    fn test_text_codecs() {
+        let val = vec![0xdeadu16, 0xbeef];
+        assert_eq!(val.to_strict_hex().unwrap(), "0200addeefbe");
+        assert_eq!(val.to_strict_base64().unwrap(), "AgCt3u++");
+        assert_eq!(Vec::<u16>::from_strict_hex("0200addeefbe").unwrap(), val);
+        assert_eq!(Vec::<u16>::from_strict_base64("AgCt3u++").unwrap(), val);
+
+        assert!(matches!(
+            Vec::<u16>::from_strict_hex("0200addeefb"),
+            Err(Error::InvalidText(_))
+        ));
+        assert!(matches!(
+            Vec::<u16>::from_strict_base64("AgCt3u+"),
+            Err(Error::InvalidText(_))
+        ));
+        assert!(matches!(
+            Vec::<u16>::from_strict_hex("0200addeefbe00"),
+            Err(Error::DataNotEntirelyConsumed)
+        ));
+    }
+
+    #[test]
+    fn test_net_addresses() {
+        use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+        let ip4 = Ipv4Addr::new(127, 0, 0, 1);
+        let ip6 = Ipv6Addr::LOCALHOST;
+        assert_eq!(strict_encode(&ip4).unwrap(), &[127u8, 0, 0, 1]);
+        assert_eq!(strict_encode(&ip6).unwrap(), &ip6.octets()[..]);
+        assert_eq!(strict_decode::<Ipv4Addr>(&[127u8, 0, 0, 1]).unwrap(), ip4);
+        assert_eq!(strict_decode::<Ipv6Addr>(&ip6.octets()).unwrap(), ip6);
+
+        let addr4 = IpAddr::V4(ip4);
+        let addr6 = IpAddr::V6(ip6);
+        assert_eq!(strict_encode(&addr4).unwrap(), &[0u8, 127, 0, 0, 1]);
+        assert_eq!(strict_encode(&addr6).unwrap().len(), 17);
+        assert_eq!(
+            strict_decode::<IpAddr>(&strict_encode(&addr6).unwrap()).unwrap(),
+            addr6
+        );
+        assert!(strict_decode::<IpAddr>(&[2u8, 127, 0, 0, 1]).is_err());
+
+        let socket = SocketAddr::new(addr4, 9735);
+        assert_eq!(
+            strict_encode(&socket).unwrap(),
+            &[0u8, 127, 0, 0, 1, 0x07, 0x26]
+        );
+        assert_eq!(
+            strict_decode::<SocketAddr>(&strict_encode(&socket).unwrap()).unwrap(),
+            socket
+        );
+    }
+
+    #[test]
+    fn test_hash_set_canonical() {
+        use std::collections::{BTreeSet, HashSet};
+
+        let items = (0u16..0x200).rev().collect::<Vec<_>>();
+        let hash_set = items.iter().cloned().collect::<HashSet<u16>>();
+        let btree_set = items.iter().cloned().collect::<BTreeSet<u16>>();
+        assert_eq!(
+            strict_encode(&hash_set).unwrap(),
+            strict_encode(&btree_set).unwrap()
+        );
+        assert_eq!(
+            strict_decode::<HashSet<u16>>(&strict_encode(&hash_set).unwrap()).unwrap(),
+            hash_set
+        );
+    }
+
+    #[test]
+    fn test_error_context() {
+        let data = &[3u8, 0, 0, 13][..];
+        let err = strict_decode_traced::<Vec<u8>>(&data).unwrap_err();
+        assert_eq!(err.context().unwrap().offset, Some(4));
+        assert_eq!(
+            err.context().unwrap().path,
+            vec![std::any::type_name::<Vec<u8>>()]
+        );
+        assert!(matches!(err.root_cause(), Error::Io(_)));
+
+        let err = strict_decode_traced::<Vec<u8>>(&[1u8, 0, 0, 13]).unwrap_err();
+        assert_eq!(err.context().unwrap().offset, Some(3));
+        assert!(matches!(err.root_cause(), Error::DataNotEntirelyConsumed));
+
+        let err =
+            strict_decode_traced::<Vec<Option<Vec<u8>>>>(&[2u8, 0, 1, 2, 0, 7, 8, 2]).unwrap_err();
+        assert_eq!(err.context().unwrap().offset, Some(8));
+        assert!(matches!(err.root_cause(), Error::WrongOptionalEncoding(2)));
+
+        let err =
+            strict_decode_traced::<Vec<Option<Vec<u8>>>>(&[1u8, 0, 1, 4, 0, 7, 8]).unwrap_err();
+        assert_eq!(err.context().unwrap().offset, Some(7));
+        assert!(matches!(err.root_cause(), Error::Io(_)));
+
+        let err = Error::DataIntegrityError("test".to_string())
+            .in_field("inner")
+            .at_offset(12)
+            .in_field("outer")
+            .at_offset(24);
+        assert_eq!(
+            err.context(),
+            Some(&ErrorContext {
+                offset: Some(12),
+                path: vec!["outer".to_string(), "inner".to_string()]
+            })
+        );
+        assert_eq!(
+            err.to_string(),
+            "Data integrity error: test (decoding `outer.inner` at byte offset 12)"
+        );
+    }
+
+    #[test]
+    fn test_tagged_enum() {
+        use test_helpers::test_vector;
+
+        #[derive(Clone, PartialEq, Eq, Debug)]
+        enum Shape {
+            Point,
+            Circle(u16),
+            Rect { width: u16, height: u8 },
+        }
+        impl_tagged_enum_strict_encoding!(Shape {
+            Point = 0,
+            Circle(radius: u16) = 1,
+            Rect { width: u16, height: u8 } = 0x10,
+        });
+
+        test_vector(&Shape::Point, &[0u8]).unwrap();
+        test_vector(&Shape::Circle(0x0102), &[1u8, 2, 1]).unwrap();
+        test_vector(
+            &Shape::Rect {
+                width: 3,
+                height: 4,
+            },
+            &[0x10u8, 3, 0, 4],
+        )
+        .unwrap();
+        assert!(matches!(
+            strict_decode::<Shape>(&[2u8]),
+            Err(Error::EnumValueNotKnown(name, 2)) if name == "Shape"
+        ));
+    }
+
+    #[test]
+    fn test_counting_adapters() {
+        struct Misreported;
+        impl StrictEncode for Misreported {
+            type Error = Error;
+            fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Error> {
+                e.write_all(&[1u8, 2, 3])?;
+                Ok(0)
+            }
+        }
+
+        let mut encoder = vec![];
+        let len = (|| -> Result<usize, Error> {
+            Ok(strict_encode_list!(encoder; Misreported, &b"ab"[..], 7u8))
+        })()
+        .unwrap();
+        assert_eq!(len, 8);
+        assert_eq!(encoder, vec![1u8, 2, 3, 2, 0, 0x61, 0x62, 7]);
+
+        for data in &[&b""[..], &b"a"[..], &[0xFFu8; 300][..]] {
+            let mut writer = CountingWriter::new(vec![]);
+            assert_eq!(data.strict_encode(&mut writer).unwrap(), data.len() + 2);
+            assert_eq!(writer.count(), data.len() + 2);
+            assert_eq!(writer.into_inner().len(), data.len() + 2);
+        }
+
+        let data = strict_encode(&vec![1u32, 2]).unwrap();
+        let mut reader = CountingReader::new(&data[..]);
+        assert_eq!(Vec::<u32>::strict_decode(&mut reader).unwrap(), vec![1, 2]);
+        assert_eq!(reader.count(), 10);
+    }
+
+    #[test]
+    fn test_serialized_len() {
+        let v1: Vec<u64> = vec![0, 13, 13, 0x1FF, 0xFFFFFFFFFFFFFFFF];
+        let v2: Vec<u8> = (0..0x1FFFF).map(|item| (item % 0xFF) as u8).collect();
+
+        assert_eq!(v1.strict_serialized_len().unwrap(), 42);
+        assert_eq!(
+            v1.strict_serialized_len().unwrap(),
+            strict_encode(&v1).unwrap().len()
+        );
+        for s in gen_strings() {
+            assert_eq!(
+                s.strict_serialized_len().unwrap(),
+                strict_encode(&s).unwrap().len()
+            );
+        }
+        assert!(v2.strict_serialized_len().is_err());
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn test_async_encoding() {
+        use futures::executor::block_on;
+        use futures::io::Cursor;
+
+        let v1: Vec<u64> = vec![0, 13, 13, 0x1FF, 0xFFFFFFFFFFFFFFFF];
+        let s1 = "Some string".to_string();
+
+        let mut cursor = Cursor::new(vec![]);
+        let len = block_on(v1.strict_encode_async(&mut cursor)).unwrap()
+            + block_on(s1.strict_encode_async(&mut cursor)).unwrap();
+        let data = cursor.into_inner();
+        assert_eq!(len, data.len());
+        assert_eq!(data[..4], [42u8, 0, 0, 0]);
+        assert_eq!(data[4..46], strict_encode(&v1).unwrap()[..]);
+
+        let mut cursor = Cursor::new(data.clone());
+        assert_eq!(
+            block_on(Vec::<u64>::strict_decode_async(&mut cursor)).unwrap(),
+            v1
+        );
+        assert_eq!(cursor.position(), 46);
+        assert_eq!(
+            block_on(String::strict_decode_async(&mut cursor)).unwrap(),
+            s1
+        );
+        assert!(block_on(u8::strict_decode_async(&mut cursor)).is_err());
+
+        // Frame must be consumed entirely
+        let mut cursor = Cursor::new(data.clone());
+        assert!(block_on(u32::strict_decode_async(&mut cursor)).is_err());
+
+        // Frames exceeding the limit are rejected without reading their data
+        let mut cursor = Cursor::new(data.clone());
+        assert!(matches!(
+            block_on(Vec::<u64>::strict_decode_async(&mut cursor).max_frame_len(41)),
+            Err(Error::ValueOutOfRange(_, _, 42))
+        ));
+        assert_eq!(cursor.position(), 4);
+        let mut cursor = Cursor::new(data);
+        assert!(block_on(Vec::<u64>::strict_decode_async(&mut cursor).max_frame_len(42)).is_ok());
+        let mut cursor = Cursor::new(vec![0xFFu8; 8]);
+        assert!(matches!(
+            block_on(Vec::<u8>::strict_decode_async(&mut cursor)),
+            Err(Error::ValueOutOfRange(_, _, 0xFFFF_FFFF))
+        ));
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn test_async_trickle() {
+        use futures::executor::block_on;
+        use futures::io::{AsyncRead, AsyncWrite};
+        use std::pin::Pin;
+        use std::task::{Context, Poll};
+
+        /// Stream which is ready only on every second poll and transfers a
+        /// single byte at a time
+        #[derive(Default)]
+        struct Trickle {
+            data: Vec<u8>,
+            pos: usize,
+            ready: bool,
+        }
+
+        impl Trickle {
+            fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+                self.ready = !self.ready;
+                if self.ready {
+                    Poll::Ready(())
+                } else {
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            }
+        }
+
+        impl AsyncWrite for Trickle {
+            fn poll_write(
+                mut self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &[u8],
+            ) -> Poll<io::Result<usize>> {
+                futures::ready!(self.poll_ready(cx));
+                self.data.push(buf[0]);
+                Poll::Ready(Ok(1))
+            }
+
+            fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        impl AsyncRead for Trickle {
+            fn poll_read(
+                mut self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                futures::ready!(self.poll_ready(cx));
+                if self.pos == self.data.len() {
+                    return Poll::Ready(Ok(0));
+                }
+                buf[0] = self.data[self.pos];
+                self.pos += 1;
+                Poll::Ready(Ok(1))
+            }
+        }
+
+        let v1: Vec<u64> = (0..100).collect();
+        let mut stream = Trickle::default();
+        let len = block_on(v1.strict_encode_async(&mut stream)).unwrap();
+        assert_eq!(len, 4 + 2 + 800);
+        assert_eq!(stream.data[4..], strict_encode(&v1).unwrap()[..]);
+        assert_eq!(
+            block_on(Vec::<u64>::strict_decode_async(&mut stream)).unwrap(),
+            v1
+        );
+        assert_eq!(stream.pos, len);
+    }
 }
@@ -11,4 +11,119 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-pub struct Anchor {}
+use bitcoin::Txid;
+
+use super::interfaces::TxRefResolver;
+use crate::bp::{dbc, ShortId};
+use crate::lnpbp4::MultiCommitBlock;
+
+/// Reference to a transaction, which may be given either by its full id or,
+/// for mined transactions, by compact short id (block height and transaction
+/// index within the block), which has to be resolved by the receiving party
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum TxRef {
+    Txid(Txid),
+    ShortId(ShortId),
+}
+
+impl TxRef {
+    /// Returns transaction id if it is known without resolving
+    #[inline]
+    pub fn txid(&self) -> Option<Txid> {
+        match self {
+            TxRef::Txid(txid) => Some(*txid),
+            TxRef::ShortId(_) => None,
+        }
+    }
+
+    /// Resolves transaction id using the provided resolver, if required
+    pub fn resolve(&self, resolver: &impl TxRefResolver) -> Option<Txid> {
+        match self {
+            TxRef::Txid(txid) => Some(*txid),
+            TxRef::ShortId(short_id) => resolver.txid(*short_id),
+        }
+    }
+
+    /// Converts reference into the short id form, if it is known to the
+    /// resolver; otherwise keeps the full transaction id
+    pub fn compact(&self, resolver: &impl TxRefResolver) -> TxRef {
+        match self {
+            TxRef::Txid(txid) => resolver
+                .short_id(*txid)
+                .map(TxRef::ShortId)
+                .unwrap_or(*self),
+            short_ref => *short_ref,
+        }
+    }
+}
+
+impl From<Txid> for TxRef {
+    #[inline]
+    fn from(txid: Txid) -> Self {
+        TxRef::Txid(txid)
+    }
+}
+
+/// Anchor links LNPBP-4 multimessage commitment to a transaction containing
+/// deterministic bitcoin commitment to it
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display_from(Debug)]
+pub struct Anchor {
+    /// Transaction containing the commitment
+    pub tx_ref: TxRef,
+    /// Multimessage commitment, which may commit to state transitions under
+    /// different contracts
+    pub commitment: MultiCommitBlock,
+    /// Proof of the deterministic bitcoin commitment within the transaction
+    pub dbc_proof: dbc::Proof,
+}
+
+mod strict_encoding {
+    use super::*;
+    use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
+    use std::io;
+
+    impl StrictEncode for TxRef {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(match self {
+                TxRef::Txid(txid) => strict_encode_list!(e; 0u8, txid),
+                TxRef::ShortId(short_id) => strict_encode_list!(e; 1u8, short_id),
+            })
+        }
+    }
+
+    impl StrictDecode for TxRef {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(match u8::strict_decode(&mut d)? {
+                0u8 => TxRef::Txid(Txid::strict_decode(&mut d)?),
+                1u8 => TxRef::ShortId(ShortId::strict_decode(&mut d)?),
+                invalid => Err(Error::EnumValueNotKnown("TxRef".to_string(), invalid))?,
+            })
+        }
+    }
+
+    impl StrictEncode for Anchor {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(strict_encode_list!(e; self.tx_ref, self.commitment, self.dbc_proof))
+        }
+    }
+
+    impl StrictDecode for Anchor {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(Self {
+                tx_ref: TxRef::strict_decode(&mut d)?,
+                commitment: MultiCommitBlock::strict_decode(&mut d)?,
+                dbc_proof: dbc::Proof::strict_decode(&mut d)?,
+            })
+        }
+    }
+}
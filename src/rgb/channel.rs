@@ -0,0 +1,552 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Off-chain updates of the RGB state held inside a payment channel. Channel
+//! parties exchange state transitions without anchoring them; each set of
+//! transitions is wrapped into a [StateUpdate] with an increasing sequence
+//! number and signed by both parties. Only the latest update gets anchored,
+//! into the transaction spending the channel funding output on the channel
+//! close with [ChannelClose].
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1;
+use bitcoin::{OutPoint, Transaction};
+use std::sync::Arc;
+
+use super::{
+    Anchor, Consignment, ContractId, Genesis, TransferBatch, Transition, TransitionId, TxRef,
+};
+use crate::bp::dbc::{self, lnpbp3_vout, Container, TxCommitment, TxContainer};
+use crate::commit_verify::EmbedCommitVerify;
+use crate::lnpbp4::{MultiCommitBlock, MultiCommitError};
+use crate::strict_encoding::strict_encode;
+use crate::tagged_hash::midstate_for_tag;
+
+/// Tag used for the signed digest of the state update
+pub const STATE_UPDATE_TAG: &str = "rgb:channel:update";
+
+/// Errors of the state update signing and verification
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display_from(Debug)]
+pub enum UpdateError {
+    /// Key is not one of the channel parties
+    UnknownSigner(secp256k1::PublicKey),
+
+    /// Channel party has not signed the update yet
+    SignatureMissing(secp256k1::PublicKey),
+
+    /// Signature of the channel party does not match the update
+    InvalidSignature(secp256k1::PublicKey),
+
+    /// Updates belong to different channels or contracts
+    ChannelMismatch,
+
+    /// Update does not supersede the previous one
+    StaleSequence { previous: u64, current: u64 },
+}
+
+/// Errors of the channel close settlement
+#[derive(Clone, PartialEq, Debug, Display, From, Error)]
+#[display_from(Debug)]
+pub enum CloseError {
+    /// State update is not signed by both channel parties
+    #[derive_from]
+    Update(UpdateError),
+
+    /// Genesis does not define the contract of the state update
+    ContractMismatch,
+
+    /// Closing transaction does not spend the channel funding output
+    FundingNotSpent,
+
+    /// Commitment can't be embedded into the closing transaction
+    #[derive_from]
+    Commitment(dbc::Error),
+
+    /// State transitions can't be placed into the multimessage commitment
+    #[derive_from]
+    MultiCommit(MultiCommitError),
+}
+
+/// Off-chain update of the contract state within the channel
+#[derive(Clone, Debug, Display)]
+#[display_from(Debug)]
+pub struct StateUpdate {
+    pub contract_id: ContractId,
+
+    /// Channel funding output, which is used as the seal for the channel
+    /// state and is spent by the transaction anchoring the final update
+    pub funding: OutPoint,
+
+    /// Number of the update, which must grow with each next update
+    pub sequence: u64,
+
+    /// State transitions which are not anchored yet
+    pub transitions: Vec<Transition>,
+
+    /// Keys of both channel parties
+    pub parties: [secp256k1::PublicKey; 2],
+
+    /// Signatures of the channel parties, in the same order as their keys
+    pub signatures: [Option<secp256k1::Signature>; 2],
+}
+
+impl StateUpdate {
+    /// Constructs update with no signatures
+    pub fn new(
+        contract_id: ContractId,
+        funding: OutPoint,
+        sequence: u64,
+        transitions: Vec<Transition>,
+        parties: [secp256k1::PublicKey; 2],
+    ) -> Self {
+        Self {
+            contract_id,
+            funding,
+            sequence,
+            transitions,
+            parties,
+            signatures: [None, None],
+        }
+    }
+
+    #[inline]
+    pub fn transition_ids(&self) -> Vec<TransitionId> {
+        self.transitions
+            .iter()
+            .map(Transition::transition_id)
+            .collect()
+    }
+
+    /// Returns digest signed by the channel parties: tagged hash (with
+    /// [STATE_UPDATE_TAG]) of contract id, funding outpoint, little-endian
+    /// sequence number, party keys and ids of the transitions prefixed with
+    /// their `u32` little-endian count
+    pub fn digest(&self) -> secp256k1::Message {
+        let mut engine = sha256::HashEngine::from_midstate(midstate_for_tag(STATE_UPDATE_TAG), 64);
+        engine.input(&self.contract_id[..]);
+        engine.input(&self.funding.txid[..]);
+        engine.input(&self.funding.vout.to_le_bytes());
+        engine.input(&self.sequence.to_le_bytes());
+        for party in &self.parties {
+            engine.input(&party.serialize());
+        }
+        engine.input(&(self.transitions.len() as u32).to_le_bytes());
+        for id in self.transition_ids() {
+            engine.input(&id[..]);
+        }
+        secp256k1::Message::from_slice(&sha256::Hash::from_engine(engine).into_inner())
+            .expect("SHA256 hash is always a valid message")
+    }
+
+    /// Signs the update by the channel party, replacing its previous
+    /// signature
+    pub fn sign<C: secp256k1::Signing>(
+        &mut self,
+        secp: &secp256k1::Secp256k1<C>,
+        seckey: &secp256k1::SecretKey,
+    ) -> Result<(), UpdateError> {
+        let signer = secp256k1::PublicKey::from_secret_key(secp, seckey);
+        let index = self
+            .parties
+            .iter()
+            .position(|party| *party == signer)
+            .ok_or(UpdateError::UnknownSigner(signer))?;
+        self.signatures[index] = Some(secp.sign(&self.digest(), seckey));
+        Ok(())
+    }
+
+    /// Detects whether both channel parties have signed the update
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.signatures.iter().all(Option::is_some)
+    }
+
+    /// Verifies that both channel parties have signed the update. Callers
+    /// must check that the parties are the ones of the channel themselves.
+    pub fn verify<C: secp256k1::Verification>(
+        &self,
+        secp: &secp256k1::Secp256k1<C>,
+    ) -> Result<(), UpdateError> {
+        let digest = self.digest();
+        for (party, signature) in self.parties.iter().zip(self.signatures.iter()) {
+            let signature = signature
+                .as_ref()
+                .ok_or(UpdateError::SignatureMissing(*party))?;
+            secp.verify(&digest, signature, party)
+                .map_err(|_| UpdateError::InvalidSignature(*party))?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the update supersedes the `previous` one: it must belong
+    /// to the same contract and channel and have greater sequence number
+    pub fn verify_successor(&self, previous: &StateUpdate) -> Result<(), UpdateError> {
+        if self.contract_id != previous.contract_id
+            || self.funding != previous.funding
+            || self.parties != previous.parties
+        {
+            Err(UpdateError::ChannelMismatch)?
+        }
+        if self.sequence <= previous.sequence {
+            Err(UpdateError::StaleSequence {
+                previous: previous.sequence,
+                current: self.sequence,
+            })?
+        }
+        Ok(())
+    }
+}
+
+/// Builder for the channel close settlement, anchoring the state transitions
+/// of the latest agreed [StateUpdate] in the transaction spending the channel
+/// funding output. The same procedure is used for the cooperative close
+/// transaction and for the commitment transaction of the forced
+/// (unilateral) close.
+///
+/// The workflow is the following:
+/// 1. Construct the builder with [ChannelClose::with] from the latest
+///    update signed by both parties;
+/// 2. Prepare [TxContainer] for the closing transaction, which selects the
+///    output hosting the commitment according to LNPBP-3;
+/// 3. Call [ChannelClose::settle], receiving [Settlement] with the closing
+///    transaction containing the commitment, which has to be signed and
+///    published, and the consignment for both parties.
+#[derive(Clone, Debug, Display)]
+#[display_from(Debug)]
+pub struct ChannelClose {
+    genesis: Arc<Genesis>,
+    update: StateUpdate,
+}
+
+/// Result of the channel close settlement
+#[derive(Clone, Debug, Display)]
+#[display_from(Debug)]
+pub struct Settlement {
+    /// Closing transaction with the embedded commitment
+    pub tx: Transaction,
+    /// Number of the closing transaction output hosting the commitment
+    pub vout: u32,
+    pub anchor: Anchor,
+    genesis: Arc<Genesis>,
+    transitions: Vec<Transition>,
+}
+
+impl ChannelClose {
+    /// Constructs the builder for the state update, which must be signed by
+    /// both of the channel parties
+    pub fn with<C: secp256k1::Verification>(
+        secp: &secp256k1::Secp256k1<C>,
+        genesis: Arc<Genesis>,
+        update: StateUpdate,
+    ) -> Result<Self, CloseError> {
+        if genesis.contract_id() != update.contract_id {
+            Err(CloseError::ContractMismatch)?
+        }
+        update.verify(secp)?;
+        Ok(Self { genesis, update })
+    }
+
+    #[inline]
+    pub fn update(&self) -> &StateUpdate {
+        &self.update
+    }
+
+    /// Transfer batch with all state transitions of the update
+    pub fn batch(&self) -> TransferBatch {
+        let mut batch = TransferBatch::new();
+        for transition in &self.update.transitions {
+            batch.add(&self.genesis, transition.clone());
+        }
+        batch
+    }
+
+    /// Embeds commitment to the state transitions into the output of the
+    /// closing transaction selected according to LNPBP-3. Fails if the
+    /// transaction does not spend the channel funding output.
+    pub fn settle(self, container: TxContainer) -> Result<Settlement, CloseError> {
+        if !container
+            .tx
+            .input
+            .iter()
+            .any(|txin| txin.previous_output == self.update.funding)
+        {
+            Err(CloseError::FundingNotSpent)?
+        }
+
+        let batch = self.batch();
+        let commitment = batch.commit()?;
+        let vout = lnpbp3_vout(&container.tx, container.fee, container.protocol_factor)
+            .ok_or(dbc::Error::TxHasNoOutputs)?;
+        let tx =
+            TxCommitment::embed_commit(&container, &commitment_message(&commitment))?.into_inner();
+        let update = batch
+            .finalize(commitment, TxRef::Txid(tx.txid()), container.into_proof())
+            .expect("Commitment is created for the same batch");
+
+        Ok(Settlement {
+            tx,
+            vout: vout as u32,
+            anchor: update.anchor,
+            genesis: self.genesis,
+            transitions: self.update.transitions,
+        })
+    }
+}
+
+impl Settlement {
+    /// Message embedded into the closing transaction
+    #[inline]
+    pub fn message(&self) -> Vec<u8> {
+        commitment_message(&self.anchor.commitment)
+    }
+
+    /// Creates consignment with all state transitions of the channel, which
+    /// is the same for both parties: each of them has to be able to validate
+    /// the whole channel state. Consignment does not contain the history
+    /// preceding the channel, which has to be added from the stash.
+    pub fn consignment(&self) -> Consignment {
+        Consignment {
+            flags: 0,
+            genesis: self.genesis.clone(),
+            transitions: vec![],
+            endpoints: self.transitions.clone(),
+            anchors: vec![self.anchor.clone()],
+        }
+    }
+}
+
+/// Multimessage commitment is embedded into the transaction in its
+/// strict-encoded form
+fn commitment_message(commitment: &MultiCommitBlock) -> Vec<u8> {
+    strict_encode(commitment).expect("Strict encoding of multimessage commitment can't fail")
+}
+
+mod strict_encoding {
+    use super::*;
+    use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
+    use std::io;
+
+    impl StrictEncode for StateUpdate {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(strict_encode_list!(e;
+                self.contract_id,
+                self.funding,
+                self.sequence,
+                self.transitions,
+                self.parties[0],
+                self.parties[1],
+                self.signatures[0],
+                self.signatures[1]))
+        }
+    }
+
+    impl StrictDecode for StateUpdate {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(Self {
+                contract_id: ContractId::strict_decode(&mut d)?,
+                funding: OutPoint::strict_decode(&mut d)?,
+                sequence: u64::strict_decode(&mut d)?,
+                transitions: Vec::strict_decode(&mut d)?,
+                parties: [
+                    secp256k1::PublicKey::strict_decode(&mut d)?,
+                    secp256k1::PublicKey::strict_decode(&mut d)?,
+                ],
+                signatures: [
+                    Option::strict_decode(&mut d)?,
+                    Option::strict_decode(&mut d)?,
+                ],
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp;
+    use crate::rgb::{Metadata, SchemaId};
+    use crate::strict_encoding::strict_decode;
+    use bitcoin::{Script, TxIn, TxOut, Txid};
+
+    fn update(sequence: u64, parties: [secp256k1::PublicKey; 2]) -> StateUpdate {
+        StateUpdate::new(
+            ContractId::from_slice(&[1u8; 32]).unwrap(),
+            OutPoint::new(Txid::hash(b"funding"), 0),
+            sequence,
+            vec![Transition::with(
+                sequence as usize,
+                Metadata::new(),
+                bmap! {},
+                vec![],
+            )],
+            parties,
+        )
+    }
+
+    #[test]
+    fn test_state_update() {
+        let secp = secp256k1::Secp256k1::new();
+        let keys = [
+            secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap(),
+            secp256k1::SecretKey::from_slice(&[2u8; 32]).unwrap(),
+            secp256k1::SecretKey::from_slice(&[3u8; 32]).unwrap(),
+        ];
+        let pubkey = |no: usize| secp256k1::PublicKey::from_secret_key(&secp, &keys[no]);
+        let parties = [pubkey(0), pubkey(1)];
+
+        let mut first = update(1, parties);
+        assert_eq!(
+            first.verify(&secp),
+            Err(UpdateError::SignatureMissing(parties[0]))
+        );
+        first.sign(&secp, &keys[0]).unwrap();
+        assert!(!first.is_complete());
+        assert_eq!(
+            first.verify(&secp),
+            Err(UpdateError::SignatureMissing(parties[1]))
+        );
+        assert_eq!(
+            first.sign(&secp, &keys[2]),
+            Err(UpdateError::UnknownSigner(pubkey(2)))
+        );
+        first.sign(&secp, &keys[1]).unwrap();
+        assert!(first.is_complete());
+        assert_eq!(first.verify(&secp), Ok(()));
+
+        let encoded = strict_encode(&first).unwrap();
+        let decoded: StateUpdate = strict_decode(&encoded).unwrap();
+        assert_eq!(strict_encode(&decoded).unwrap(), encoded);
+        assert_eq!(decoded.verify(&secp), Ok(()));
+
+        let mut replayed = first.clone();
+        replayed.sequence = 2;
+        assert_eq!(
+            replayed.verify(&secp),
+            Err(UpdateError::InvalidSignature(parties[0]))
+        );
+        let mut forged = first.clone();
+        forged.transitions = update(2, parties).transitions;
+        assert!(forged.verify(&secp).is_err());
+
+        let second = update(2, parties);
+        assert_eq!(second.verify_successor(&first), Ok(()));
+        assert_eq!(
+            first.verify_successor(&second),
+            Err(UpdateError::StaleSequence {
+                previous: 2,
+                current: 1
+            })
+        );
+        assert_eq!(
+            update(2, [parties[0], pubkey(2)]).verify_successor(&first),
+            Err(UpdateError::ChannelMismatch)
+        );
+    }
+
+    #[test]
+    fn test_channel_close() {
+        let secp = secp256k1::Secp256k1::new();
+        let keys = [
+            secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap(),
+            secp256k1::SecretKey::from_slice(&[2u8; 32]).unwrap(),
+        ];
+        let parties = [
+            secp256k1::PublicKey::from_secret_key(&secp, &keys[0]),
+            secp256k1::PublicKey::from_secret_key(&secp, &keys[1]),
+        ];
+        let genesis = Arc::new(Genesis::with(
+            SchemaId::default(),
+            bp::Chain::Testnet3,
+            Metadata::new(),
+            bmap! {},
+            vec![],
+        ));
+
+        let mut state = update(3, parties);
+        assert_eq!(
+            ChannelClose::with(&secp, genesis.clone(), state.clone()).unwrap_err(),
+            CloseError::ContractMismatch
+        );
+        state.contract_id = genesis.contract_id();
+        assert_eq!(
+            ChannelClose::with(&secp, genesis.clone(), state.clone()).unwrap_err(),
+            CloseError::Update(UpdateError::SignatureMissing(parties[0]))
+        );
+        for key in &keys {
+            state.sign(&secp, key).unwrap();
+        }
+        let close = ChannelClose::with(&secp, genesis.clone(), state.clone()).unwrap();
+
+        let tag = sha256::Hash::hash(b"TEST_TAG");
+        let container = |tx: Transaction| {
+            TxContainer::construct(
+                0,
+                &tag,
+                1000,
+                tx,
+                parties[0],
+                dbc::ScriptInfo::None,
+                dbc::ScriptPubkeyComposition::WPubkeyHash,
+            )
+            .unwrap()
+        };
+        let txout = TxOut {
+            value: 10_000,
+            script_pubkey: Script::new(),
+        };
+        let mut tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                sequence: 0xFFFFFFFF,
+                ..Default::default()
+            }],
+            output: vec![txout.clone(), txout],
+        };
+        assert_eq!(
+            close.clone().settle(container(tx.clone())).unwrap_err(),
+            CloseError::FundingNotSpent
+        );
+
+        tx.input[0].previous_output = state.funding;
+        let settlement = close.settle(container(tx.clone())).unwrap();
+        assert_eq!(settlement.vout, 0);
+        assert_ne!(settlement.tx.output[0], tx.output[0]);
+        assert_eq!(settlement.tx.output[1], tx.output[1]);
+        assert_eq!(settlement.anchor.tx_ref, TxRef::Txid(settlement.tx.txid()));
+        assert!(settlement.anchor.dbc_proof.verify_bool(
+            &settlement.tx,
+            settlement.vout,
+            &settlement.message(),
+            &tag
+        ));
+
+        let consignment = settlement.consignment();
+        assert_eq!(consignment.contract_id(), genesis.contract_id());
+        assert_eq!(consignment.anchors, vec![settlement.anchor.clone()]);
+        assert_eq!(
+            consignment
+                .endpoints
+                .iter()
+                .map(Transition::transition_id)
+                .collect::<Vec<_>>(),
+            state.transition_ids()
+        );
+    }
+}
@@ -0,0 +1,356 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! ECIES encryption of consignments sent to the receiver through untrusted
+//! relays, so the relays do not learn the asset history. The receiver
+//! provides a public key (for instance, with [crate::rgb::Invoice]), and the
+//! sender encrypts strict-encoded consignment to it:
+//!
+//! 1. Random ephemeral key is generated and ECDH shared point is computed
+//!    for it and the receiver key;
+//! 2. Encryption key is derived from the compressed shared point with
+//!    HKDF-SHA256, using the compressed ephemeral public key as a salt;
+//! 3. Consignment data are encrypted with ChaCha20-Poly1305, authenticating
+//!    the header (version and ephemeral key) as associated data.
+//!
+//! Each ephemeral key is used for a single encryption, so the derived key is
+//! never reused and the AEAD nonce is fixed to zero.
+
+use bitcoin::secp256k1;
+use chacha20poly1305::aead::{generic_array::GenericArray, Aead, NewAead, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::Hkdf;
+use rand::RngCore;
+use sha2::Sha256;
+
+use super::Consignment;
+use crate::rgb::{validation, Schema};
+use crate::strict_encoding::{strict_decode, strict_encode, LargeVec};
+
+/// Version of the consignment encryption scheme
+pub const ENCRYPTED_CONSIGNMENT_VERSION: u8 = 1;
+
+/// Length of the authentication tag appended to the ciphertext
+pub const ENCRYPTED_CONSIGNMENT_TAG_LEN: usize = 16;
+
+const LABEL_ENCRYPTION: &[u8] = b"rgb:consignment:encryption";
+
+/// Consignment encrypted to the receiver public key
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct EncryptedConsignment {
+    /// Version of the encryption scheme
+    pub version: u8,
+    /// Ephemeral public key used for ECDH with the receiver key
+    pub ephemeral_key: secp256k1::PublicKey,
+    /// Encrypted strict-encoded consignment followed by the authentication
+    /// tag
+    pub ciphertext: LargeVec<u8>,
+}
+
+/// Errors happening during consignment encryption
+#[derive(Debug, Display, From, Error)]
+#[display_from(Debug)]
+pub enum EncryptionError {
+    #[derive_from]
+    Encoding(crate::strict_encoding::Error),
+
+    /// Data of the given size are too large to be encrypted into a single
+    /// consignment
+    DataTooLarge(usize),
+}
+
+/// Errors happening during consignment decryption
+#[derive(Debug, Display, From, Error)]
+#[display_from(Debug)]
+pub enum DecryptionError {
+    /// Encryption scheme version is not supported
+    UnsupportedVersion(u8),
+
+    #[derive_from]
+    Secp256k1(secp256k1::Error),
+
+    /// Authentication tag does not match: the consignment was encrypted to
+    /// some other key or was corrupted
+    MacMismatch,
+
+    #[derive_from]
+    Encoding(crate::strict_encoding::Error),
+
+    #[derive_from]
+    Validation(validation::Failure),
+}
+
+impl Consignment {
+    /// Encrypts consignment to the receiver public key using random
+    /// ephemeral key
+    pub fn encrypt(
+        &self,
+        receiver: &secp256k1::PublicKey,
+    ) -> Result<EncryptedConsignment, EncryptionError> {
+        let mut rng = rand::thread_rng();
+        let ephemeral = loop {
+            let mut data = [0u8; 32];
+            rng.fill_bytes(&mut data);
+            if let Ok(key) = secp256k1::SecretKey::from_slice(&data) {
+                break key;
+            }
+        };
+        EncryptedConsignment::with(&strict_encode(self)?, receiver, &ephemeral)
+    }
+}
+
+impl EncryptedConsignment {
+    /// Encrypts data to the receiver public key with the given ephemeral
+    /// key, which must not be reused
+    pub fn with(
+        data: &[u8],
+        receiver: &secp256k1::PublicKey,
+        ephemeral: &secp256k1::SecretKey,
+    ) -> Result<Self, EncryptionError> {
+        if data.len() > std::u32::MAX as usize - ENCRYPTED_CONSIGNMENT_TAG_LEN {
+            Err(EncryptionError::DataTooLarge(data.len()))?
+        }
+        let secp = secp256k1::Secp256k1::new();
+        let ephemeral_key = secp256k1::PublicKey::from_secret_key(&secp, ephemeral);
+        let mut shared_point = *receiver;
+        shared_point
+            .mul_assign(&secp, &ephemeral[..])
+            .expect("Multiplication by a valid secret key can't fail");
+
+        let version = ENCRYPTED_CONSIGNMENT_VERSION;
+        let ciphertext = cipher(&shared_point, &ephemeral_key)
+            .encrypt(
+                GenericArray::from_slice(&[0u8; 12]),
+                Payload {
+                    msg: data,
+                    aad: &header(version, &ephemeral_key),
+                },
+            )
+            .map_err(|_| EncryptionError::DataTooLarge(data.len()))?;
+        Ok(Self {
+            version,
+            ephemeral_key,
+            ciphertext: ciphertext.into(),
+        })
+    }
+
+    /// Decrypts data with the receiver private key, checking their
+    /// authenticity
+    pub fn decrypt_data(
+        &self,
+        receiver: &secp256k1::SecretKey,
+    ) -> Result<Vec<u8>, DecryptionError> {
+        if self.version != ENCRYPTED_CONSIGNMENT_VERSION {
+            Err(DecryptionError::UnsupportedVersion(self.version))?
+        }
+        let secp = secp256k1::Secp256k1::new();
+        let mut shared_point = self.ephemeral_key;
+        shared_point.mul_assign(&secp, &receiver[..])?;
+
+        cipher(&shared_point, &self.ephemeral_key)
+            .decrypt(
+                GenericArray::from_slice(&[0u8; 12]),
+                Payload {
+                    msg: &self.ciphertext[..],
+                    aad: &header(self.version, &self.ephemeral_key),
+                },
+            )
+            .map_err(|_| DecryptionError::MacMismatch)
+    }
+
+    /// Decrypts consignment with the receiver private key
+    pub fn decrypt(&self, receiver: &secp256k1::SecretKey) -> Result<Consignment, DecryptionError> {
+        Ok(strict_decode(&self.decrypt_data(receiver)?)?)
+    }
+
+    /// Decrypts consignment with the receiver private key and validates it
+    /// against the schema with the given validation policy
+    pub fn decrypt_validate(
+        &self,
+        receiver: &secp256k1::SecretKey,
+        schema: &Schema,
+        policy: &validation::Policy,
+    ) -> Result<Consignment, DecryptionError> {
+        let consignment = self.decrypt(receiver)?;
+        consignment.validate_with_policy(schema, policy)?;
+        Ok(consignment)
+    }
+}
+
+/// Returns cipher keyed with the key derived from the ECDH shared point
+fn cipher(
+    shared_point: &secp256k1::PublicKey,
+    ephemeral_key: &secp256k1::PublicKey,
+) -> ChaCha20Poly1305 {
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&ephemeral_key.serialize()), &shared_point.serialize())
+        .expand(LABEL_ENCRYPTION, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    ChaCha20Poly1305::new(GenericArray::from_slice(&key))
+}
+
+/// Header data authenticated together with the ciphertext
+fn header(version: u8, ephemeral_key: &secp256k1::PublicKey) -> Vec<u8> {
+    let mut header = vec![version];
+    header.extend_from_slice(&ephemeral_key.serialize());
+    header
+}
+
+mod strict_encoding {
+    use super::*;
+    use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
+    use std::io;
+
+    impl StrictEncode for EncryptedConsignment {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(strict_encode_list!(e;
+                self.version,
+                self.ephemeral_key,
+                self.ciphertext
+            ))
+        }
+    }
+
+    impl StrictDecode for EncryptedConsignment {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(Self {
+                version: u8::strict_decode(&mut d)?,
+                ephemeral_key: secp256k1::PublicKey::strict_decode(&mut d)?,
+                ciphertext: LargeVec::strict_decode(&mut d)?,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp;
+    use crate::rgb::schema::test::schema;
+    use crate::rgb::{data, seal, Assignment, AssignmentsVariant, Genesis, Metadata, Transition};
+
+    fn seckey(byte: u8) -> secp256k1::SecretKey {
+        secp256k1::SecretKey::from_slice(&[byte; 32]).unwrap()
+    }
+
+    fn pubkey(byte: u8) -> secp256k1::PublicKey {
+        let secp = secp256k1::Secp256k1::new();
+        secp256k1::PublicKey::from_secret_key(&secp, &seckey(byte))
+    }
+
+    fn consignment() -> Consignment {
+        let schema = schema();
+        Consignment {
+            flags: 0,
+            genesis: Genesis::with(
+                schema.schema_id(),
+                bp::Chain::Testnet3,
+                bmap! { 0 => bset! { data::Revealed::String(s!("Asset")) } },
+                bmap! {
+                    0 => AssignmentsVariant::Void(bset! {
+                        Assignment::Revealed {
+                            seal_definition: seal::Revealed::WitnessVout { vout: 0, blinding: 0 },
+                            assigned_state: data::Void,
+                        }
+                    })
+                },
+                vec![],
+            )
+            .into(),
+            transitions: vec![Transition::with(0, Metadata::new(), bmap! {}, vec![])],
+            endpoints: vec![],
+            anchors: vec![],
+        }
+    }
+
+    #[test]
+    fn test_encryption_roundtrip() {
+        let data = b"consignment data spanning over more than a single cipher block".to_vec();
+        let encrypted = EncryptedConsignment::with(&data, &pubkey(1), &seckey(2)).unwrap();
+        assert_eq!(encrypted.version, ENCRYPTED_CONSIGNMENT_VERSION);
+        assert_eq!(encrypted.ephemeral_key, pubkey(2));
+        assert_eq!(
+            encrypted.ciphertext.len(),
+            data.len() + ENCRYPTED_CONSIGNMENT_TAG_LEN
+        );
+        assert_ne!(encrypted.ciphertext[..data.len()], data[..]);
+        assert_eq!(encrypted.decrypt_data(&seckey(1)).unwrap(), data);
+
+        // Other ephemeral key produces different ciphertext
+        let other = EncryptedConsignment::with(&data, &pubkey(1), &seckey(3)).unwrap();
+        assert_ne!(other.ciphertext, encrypted.ciphertext);
+
+        assert!(matches!(
+            encrypted.decrypt_data(&seckey(3)),
+            Err(DecryptionError::MacMismatch)
+        ));
+        let mut corrupted = encrypted.clone();
+        corrupted.ciphertext[0] ^= 1;
+        assert!(matches!(
+            corrupted.decrypt_data(&seckey(1)),
+            Err(DecryptionError::MacMismatch)
+        ));
+        let mut corrupted = encrypted.clone();
+        corrupted.ephemeral_key = pubkey(3);
+        assert!(matches!(
+            corrupted.decrypt_data(&seckey(1)),
+            Err(DecryptionError::MacMismatch)
+        ));
+        let mut unknown = encrypted.clone();
+        unknown.version = 2;
+        assert!(matches!(
+            unknown.decrypt_data(&seckey(1)),
+            Err(DecryptionError::UnsupportedVersion(2))
+        ));
+
+        let decoded: EncryptedConsignment =
+            strict_decode(&strict_encode(&encrypted).unwrap()).unwrap();
+        assert_eq!(decoded, encrypted);
+    }
+
+    #[test]
+    fn test_encrypted_consignment() {
+        let schema = schema();
+        let policy = validation::Policy::default();
+        let consignment = consignment();
+        let encrypted = consignment.encrypt(&pubkey(1)).unwrap();
+
+        let decrypted = encrypted
+            .decrypt_validate(&seckey(1), &schema, &policy)
+            .unwrap();
+        assert_eq!(decrypted.consignment_id(), consignment.consignment_id());
+        assert!(matches!(
+            encrypted.decrypt(&seckey(2)),
+            Err(DecryptionError::MacMismatch)
+        ));
+
+        let consignment = Consignment {
+            transitions: vec![Transition::with(1, Metadata::new(), bmap! {}, vec![])],
+            ..consignment
+        };
+        assert!(matches!(
+            consignment
+                .encrypt(&pubkey(1))
+                .unwrap()
+                .decrypt_validate(&seckey(1), &schema, &policy),
+            Err(DecryptionError::Validation(
+                validation::Failure::SchemaUnknownTransitionType(..)
+            ))
+        ));
+    }
+}
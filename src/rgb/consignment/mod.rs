@@ -11,13 +11,1190 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use super::{Anchor, Genesis, Transition};
+mod encryption;
+mod payment_proof;
+
+pub use encryption::{
+    DecryptionError, EncryptedConsignment, EncryptionError, ENCRYPTED_CONSIGNMENT_TAG_LEN,
+    ENCRYPTED_CONSIGNMENT_VERSION,
+};
+pub use payment_proof::{PaymentProof, PaymentProofError};
+
+use super::{
+    validation, Anchor, BlockInfo, ChainResolver, ContractId, Genesis, Node, Schema, SchemaId,
+    Transition, TransitionId, TxRef, TxRefResolver, TxResolver,
+};
+use crate::bp::ShortId;
+use crate::rgb::contract::nodes::Assignments;
+use crate::rgb::stash::transition_slot;
+use crate::strict_encoding::StrictEncode;
+use bitcoin::hashes::{sha256t, Hash};
+use std::collections::BTreeSet;
+use std::iter;
+use std::sync::Arc;
+
+/// Midstate of the tagged hash with "rgb:consignment" tag
+static MIDSTATE_CONSIGNMENT_ID: [u8; 32] = [
+    8, 36, 37, 167, 51, 70, 76, 241, 171, 132, 169, 56, 76, 108, 174, 226, 197, 98, 75, 254, 29,
+    125, 170, 233, 184, 121, 13, 183, 90, 51, 134, 6,
+];
+
+tagged_hash!(
+    ConsignmentId,
+    ConsignmentIdTag,
+    MIDSTATE_CONSIGNMENT_ID,
+    doc = "Consignment identifier equivalent to the tagged hash of the consignment strict \
+           encoding. Identifiers are ordered lexicographically over their hash bytes, in the \
+           same order as they are serialized."
+);
 
 pub struct Consignment {
-    pub genesis: Genesis,
+    /// Set of `FLAG_*` bits defining consignment encoding options
+    pub flags: u16,
+    /// Contract genesis, which may be shared with other consignments and the
+    /// stash
+    pub genesis: Arc<Genesis>,
     pub transitions: Vec<Transition>,
     pub endpoints: Vec<Transition>,
     pub anchors: Vec<Anchor>,
 }
 
-impl Consignment {}
+impl Consignment {
+    /// Anchors in the consignment may reference witness transactions by
+    /// short ids, which must be resolved by the receiver before validation
+    pub const FLAG_SHORT_TX_REFS: u16 = 0x0001;
+
+    #[inline]
+    pub fn has_short_tx_refs(&self) -> bool {
+        self.flags & Self::FLAG_SHORT_TX_REFS != 0
+    }
+
+    /// Replaces transaction ids in the anchors with short ids for all mined
+    /// transactions known to the resolver. Transactions unknown to the
+    /// resolver keep being referenced by their full ids.
+    pub fn compact_tx_refs(&mut self, resolver: &impl TxRefResolver) {
+        for anchor in &mut self.anchors {
+            anchor.tx_ref = anchor.tx_ref.compact(resolver);
+        }
+        self.flags |= Self::FLAG_SHORT_TX_REFS;
+    }
+
+    /// Resolves all short id references in the anchors into full
+    /// transaction ids. Fails with the first short id which can't be
+    /// resolved, leaving the consignment in a compact form.
+    pub fn expand_tx_refs(&mut self, resolver: &impl TxRefResolver) -> Result<(), ShortId> {
+        let txids = self
+            .anchors
+            .iter()
+            .map(|anchor| match anchor.tx_ref {
+                TxRef::ShortId(short_id) => resolver.txid(short_id).ok_or(short_id),
+                TxRef::Txid(txid) => Ok(txid),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (anchor, txid) in self.anchors.iter_mut().zip(txids) {
+            anchor.tx_ref = TxRef::Txid(txid);
+        }
+        self.flags &= !Self::FLAG_SHORT_TX_REFS;
+        Ok(())
+    }
+
+    #[inline]
+    pub fn contract_id(&self) -> ContractId {
+        self.genesis.contract_id()
+    }
+
+    #[inline]
+    pub fn schema_id(&self) -> SchemaId {
+        self.genesis.schema_id()
+    }
+
+    /// Identifier of the consignment, which may be used by the receiver for
+    /// acknowledging it. Since the identifier commits to the serialized data,
+    /// compacting or expanding transaction references changes it.
+    pub fn consignment_id(&self) -> ConsignmentId {
+        let mut engine = ConsignmentId::engine();
+        self.strict_encode(&mut engine)
+            .expect("Consignment encoding into hash engine must not fail");
+        ConsignmentId::from_engine(engine)
+    }
+
+    /// Validates consignment against the provided schema, which must match
+    /// the schema the consignment genesis commits to, using default
+    /// validation policy
+    #[inline]
+    pub fn validate(&self, schema: &Schema) -> Result<(), validation::Failure> {
+        self.validate_with_policy(schema, &validation::Policy::default())
+    }
+
+    /// Validates consignment against the provided schema applying limits
+    /// from the given validation policy
+    pub fn validate_with_policy(
+        &self,
+        schema: &Schema,
+        policy: &validation::Policy,
+    ) -> Result<(), validation::Failure> {
+        self.check_transitions_count(policy)?;
+        self.validate_genesis(schema, policy)?;
+        for transition in self.transitions.iter().chain(self.endpoints.iter()) {
+            self.validate_transition(schema, policy, transition)?;
+        }
+        Ok(())
+    }
+
+    /// Performs the same checks as [Consignment::validate_with_policy],
+    /// reporting each validated node and checked anchor as a separate event.
+    /// The stream is lazy, so the validation proceeds as the events are
+    /// consumed, and ends with [validation::Event::Failure] if some check
+    /// has failed.
+    pub fn validation_events<'a>(
+        &'a self,
+        schema: &'a Schema,
+        policy: &'a validation::Policy,
+    ) -> impl Iterator<Item = validation::Event> + 'a {
+        validation::events(self.validation_steps(schema, policy))
+    }
+
+    /// Event stream for [Consignment::validate_as_of], which in addition to
+    /// the events from [Consignment::validation_events] checks conditions of
+    /// the closed conditionally-owned assignments and reports resolution of
+    /// the witness transaction for each of the anchors
+    pub fn validation_events_as_of<'a, R>(
+        &'a self,
+        schema: &'a Schema,
+        policy: &'a validation::Policy,
+        resolver: &'a R,
+        cutoff: validation::Cutoff,
+    ) -> impl Iterator<Item = validation::Event> + 'a
+    where
+        R: ChainResolver + TxResolver,
+    {
+        let conditions = self
+            .transitions
+            .iter()
+            .chain(self.endpoints.iter())
+            .filter_map(move |transition| {
+                self.validate_conditions(schema, resolver, transition).err()
+            })
+            .map(Err);
+        let seals = self.anchors.iter().map(move |anchor| {
+            Self::resolve_witness(anchor, resolver, cutoff)
+                .map(|block| validation::Event::SealResolved(anchor.tx_ref, block))
+        });
+        validation::events(
+            self.validation_steps(schema, policy)
+                .chain(conditions)
+                .chain(seals),
+        )
+    }
+
+    fn validation_steps<'a>(
+        &'a self,
+        schema: &'a Schema,
+        policy: &'a validation::Policy,
+    ) -> impl Iterator<Item = Result<validation::Event, validation::Failure>> + 'a {
+        let count = iter::once_with(move || self.check_transitions_count(policy).err())
+            .flatten()
+            .map(Err);
+        let genesis = iter::once_with(move || {
+            self.validate_genesis(schema, policy)
+                .map(|_| validation::Event::NodeValidated(None))
+        });
+        let transitions = self.transitions.iter().chain(self.endpoints.iter());
+        let transitions = transitions.map(move |transition| {
+            let node_id = Some(transition.transition_id());
+            self.validate_transition(schema, policy, transition)
+                .map(|_| validation::Event::NodeValidated(node_id))
+        });
+        let anchors = self.anchors.iter().map(move |anchor| {
+            Ok(validation::Event::AnchorChecked(
+                anchor.tx_ref,
+                self.committed_transitions(anchor),
+            ))
+        });
+        count.chain(genesis).chain(transitions).chain(anchors)
+    }
+
+    fn check_transitions_count(
+        &self,
+        policy: &validation::Policy,
+    ) -> Result<(), validation::Failure> {
+        let transitions_count = self.transitions.len() + self.endpoints.len();
+        if transitions_count > policy.max_transitions {
+            Err(validation::Failure::LimitExceeded(
+                None,
+                validation::LimitViolation {
+                    limit: validation::Limit::TransitionsCount,
+                    max: policy.max_transitions,
+                    actual: transitions_count,
+                },
+            ))?
+        }
+        Ok(())
+    }
+
+    fn validate_genesis(
+        &self,
+        schema: &Schema,
+        policy: &validation::Policy,
+    ) -> Result<(), validation::Failure> {
+        policy
+            .node_limits
+            .check(self.genesis.as_ref())
+            .map_err(|violation| validation::Failure::LimitExceeded(None, violation))?;
+        schema.validate_genesis_structure(&self.genesis)?;
+        if !policy.allow_dust {
+            schema.validate_min_allocations(None, self.genesis.assignments())?;
+        }
+        Ok(())
+    }
+
+    fn validate_transition(
+        &self,
+        schema: &Schema,
+        policy: &validation::Policy,
+        transition: &Transition,
+    ) -> Result<(), validation::Failure> {
+        policy.node_limits.check(transition).map_err(|violation| {
+            validation::Failure::LimitExceeded(Some(transition.transition_id()), violation)
+        })?;
+        schema.validate_transition_structure(transition)?;
+        if !policy.allow_dust {
+            schema.validate_min_allocations(
+                Some(transition.transition_id()),
+                transition.assignments(),
+            )?;
+        }
+        schema.validate_locks(transition, &self.closed_assignments(transition)?)?;
+        Ok(())
+    }
+
+    /// Looks up assignments closed by the state transition among the
+    /// assignments defined by the consignment genesis and state transitions.
+    /// Fails if some of the seals closed by the transition are not defined
+    /// by any of the consignment nodes.
+    pub fn closed_assignments(
+        &self,
+        transition: &Transition,
+    ) -> Result<Assignments, validation::Failure> {
+        let nodes = iter::once(self.genesis.as_ref() as &dyn Node)
+            .chain(
+                self.transitions
+                    .iter()
+                    .chain(self.endpoints.iter())
+                    .map(|transition| transition as &dyn Node),
+            )
+            .collect::<Vec<_>>();
+        let mut closed = Assignments::new();
+        for (assignment_type, seals) in transition.closes() {
+            let mut found = BTreeSet::new();
+            for variant in nodes
+                .iter()
+                .filter_map(|node| node.assignments().get(assignment_type))
+            {
+                let variant = variant.with_seals(seals);
+                found.extend(variant.seals());
+                match closed.get_mut(assignment_type) {
+                    Some(assignments) => {
+                        assignments.merge(variant);
+                    }
+                    None => {
+                        closed.insert(*assignment_type, variant);
+                    }
+                }
+            }
+            if found != *seals {
+                Err(validation::Failure::ClosedSealUnknown(
+                    transition.transition_id(),
+                    *assignment_type,
+                ))?
+            }
+        }
+        Ok(closed)
+    }
+
+    /// Checks conditions of the conditionally-owned assignments closed by the
+    /// state transition against its witness transaction and the height of
+    /// the block containing it, both provided by the resolver
+    fn validate_conditions(
+        &self,
+        schema: &Schema,
+        resolver: &(impl ChainResolver + TxResolver),
+        transition: &Transition,
+    ) -> Result<(), validation::Failure> {
+        let closed = self.closed_assignments(transition)?;
+        let conditional = schema
+            .transitions
+            .get(&transition.type_id())
+            .map(|transition_schema| {
+                transition_schema
+                    .conditions
+                    .keys()
+                    .any(|assignment_type| closed.contains_key(assignment_type))
+            })
+            .unwrap_or_default();
+        if !conditional {
+            return Ok(());
+        }
+
+        let transition_id = transition.transition_id();
+        let (protocol_id, digest) = transition_slot(transition_id);
+        let tx_ref = self
+            .anchors
+            .iter()
+            .find(|anchor| anchor.commitment.verify_message(&protocol_id, digest))
+            .map(|anchor| anchor.tx_ref)
+            .ok_or(validation::Failure::WitnessUnknown(transition_id))?;
+        let witness = resolver
+            .transaction(tx_ref)
+            .ok_or(validation::Failure::WitnessUnknown(transition_id))?;
+        let witness_height = resolver.block_info(tx_ref).map(|block| block.height);
+        schema.validate_conditions(transition, &closed, &witness, witness_height)
+    }
+
+    /// Ids of the consignment state transitions committed by the anchor
+    fn committed_transitions(&self, anchor: &Anchor) -> Vec<TransitionId> {
+        self.transitions
+            .iter()
+            .chain(self.endpoints.iter())
+            .map(Transition::transition_id)
+            .filter(|transition_id| {
+                let (protocol_id, digest) = transition_slot(*transition_id);
+                anchor.commitment.verify_message(&protocol_id, digest)
+            })
+            .collect()
+    }
+
+    fn resolve_witness(
+        anchor: &Anchor,
+        resolver: &impl ChainResolver,
+        cutoff: validation::Cutoff,
+    ) -> Result<BlockInfo, validation::Failure> {
+        let block = resolver
+            .block_info(anchor.tx_ref)
+            .ok_or(validation::Failure::WitnessUnconfirmed(anchor.tx_ref))?;
+        if !cutoff.includes(&block) {
+            Err(validation::Failure::WitnessAfterCutoff(
+                anchor.tx_ref,
+                block,
+            ))?
+        }
+        Ok(block)
+    }
+
+    /// Reports allocations within the consignment which were revoked by the
+    /// contract issuer under the revocation policy defined by the schema.
+    /// Since revocations do not affect consensus validity, they are not
+    /// checked by [Consignment::validate] and are reported only on request.
+    pub fn warnings(&self, schema: &Schema) -> Vec<validation::Warning> {
+        schema
+            .revoked_allocations(
+                &self.genesis,
+                self.transitions.iter().chain(self.endpoints.iter()),
+            )
+            .into_iter()
+            .map(validation::Warning::AllocationRevoked)
+            .collect()
+    }
+
+    /// Validates consignment as of the given point in the blockchain history:
+    /// in addition to the checks performed by [Consignment::validate_with_policy]
+    /// requires all anchors to be mined no later than the cutoff. Allows to
+    /// check whether the consignment was valid at the time it was accepted,
+    /// provided that the resolver answers according to the historical chain
+    /// state.
+    ///
+    /// Conditions of the conditionally-owned assignments depend on the
+    /// witness transactions, so they are checked only here, using the
+    /// witness transactions and their blocks provided by the resolver.
+    pub fn validate_as_of(
+        &self,
+        schema: &Schema,
+        policy: &validation::Policy,
+        resolver: &(impl ChainResolver + TxResolver),
+        cutoff: validation::Cutoff,
+    ) -> Result<(), validation::Failure> {
+        self.validate_with_policy(schema, policy)?;
+        for transition in self.transitions.iter().chain(self.endpoints.iter()) {
+            self.validate_conditions(schema, resolver, transition)?;
+        }
+        for anchor in &self.anchors {
+            Self::resolve_witness(anchor, resolver, cutoff)?;
+        }
+        Ok(())
+    }
+}
+
+mod strict_encoding {
+    use super::*;
+    use crate::strict_encoding::{Error, StrictDecode};
+    use std::io;
+
+    impl StrictEncode for ConsignmentId {
+        type Error = Error;
+
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+            self.into_inner().to_vec().strict_encode(e)
+        }
+    }
+
+    impl StrictDecode for ConsignmentId {
+        type Error = Error;
+
+        #[inline]
+        fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+            Ok(
+                Self::from_slice(&Vec::<u8>::strict_decode(d)?).map_err(|_| {
+                    Error::DataIntegrityError("Wrong consignment id data size".to_string())
+                })?,
+            )
+        }
+    }
+
+    impl StrictEncode for Consignment {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(strict_encode_list!(e;
+                self.flags,
+                self.genesis,
+                self.transitions,
+                self.endpoints,
+                self.anchors))
+        }
+    }
+
+    impl StrictDecode for Consignment {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            let flags = u16::strict_decode(&mut d)?;
+            let consignment = Self {
+                flags,
+                genesis: Arc::new(Genesis::strict_decode(&mut d)?),
+                transitions: Vec::<Transition>::strict_decode(&mut d)?,
+                endpoints: Vec::<Transition>::strict_decode(&mut d)?,
+                anchors: Vec::<Anchor>::strict_decode(&mut d)?,
+            };
+            if !consignment.has_short_tx_refs()
+                && consignment
+                    .anchors
+                    .iter()
+                    .any(|anchor| anchor.tx_ref.txid().is_none())
+            {
+                Err(Error::DataIntegrityError(
+                    "Consignment anchors use short transaction ids while the \
+                     corresponding flag is not set"
+                        .to_string(),
+                ))?
+            }
+            Ok(consignment)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::{self, dbc};
+    use crate::client_side_validation::CommitConceal;
+    use crate::commit_verify::TryCommitVerify;
+    use crate::lnpbp4::MultiCommitBlock;
+    use crate::rgb::schema::{
+        elliptic_curve, test::schema, Bits, ConditionFields, DataFormat, EllipticCurve, Occurences,
+        OccurencesError, StateFormat,
+    };
+    use crate::rgb::{data, seal, Assignment, AssignmentsVariant, HashTimeLock, Metadata};
+    use crate::strict_encoding::{strict_decode, strict_encode, Error, Timestamp};
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1;
+    use bitcoin::Txid;
+
+    struct Resolver;
+
+    impl TxRefResolver for Resolver {
+        fn short_id(&self, txid: Txid) -> Option<ShortId> {
+            match txid.into_inner()[0] {
+                0 => None,
+                no => Some(ShortId::from(no as u64)),
+            }
+        }
+
+        fn txid(&self, short_id: ShortId) -> Option<Txid> {
+            match u64::from(short_id) {
+                no if no > 0 && no < 0x100 => Some(Txid::from_slice(&[no as u8; 32]).unwrap()),
+                _ => None,
+            }
+        }
+    }
+
+    fn anchor(txno: u8) -> Anchor {
+        let secp = secp256k1::Secp256k1::new();
+        let seckey = secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap();
+        Anchor {
+            tx_ref: TxRef::Txid(Txid::from_slice(&[txno; 32]).unwrap()),
+            commitment: MultiCommitBlock::try_commit(&bmap! {}).unwrap(),
+            dbc_proof: dbc::Proof::from(secp256k1::PublicKey::from_secret_key(&secp, &seckey)),
+        }
+    }
+
+    fn genesis(schema_id: SchemaId) -> Genesis {
+        Genesis::with(
+            schema_id,
+            bp::Chain::Testnet3,
+            bmap! { 0 => bset! { data::Revealed::String(s!("Asset")) } },
+            bmap! {
+                0 => AssignmentsVariant::Void(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal::Revealed::WitnessVout { vout: 0, blinding: 0 },
+                        assigned_state: data::Void,
+                    }
+                })
+            },
+            vec![],
+        )
+    }
+
+    fn consignment(schema_id: SchemaId) -> Consignment {
+        Consignment {
+            flags: 0,
+            genesis: genesis(schema_id).into(),
+            transitions: vec![Transition::with(0, Metadata::new(), bmap! {}, vec![])],
+            endpoints: vec![],
+            anchors: vec![],
+        }
+    }
+
+    #[test]
+    fn test_policy_limits() {
+        let schema = schema();
+        let mut consignment = consignment(schema.schema_id());
+        let policy = validation::Policy {
+            node_limits: validation::NodeLimits {
+                max_script_size: 4,
+                ..validation::NodeLimits::default()
+            },
+            max_transitions: 2,
+            allow_dust: false,
+        };
+        assert_eq!(consignment.validate_with_policy(&schema, &policy), Ok(()));
+
+        consignment.endpoints = vec![
+            Transition::with(0, Metadata::new(), bmap! {}, vec![]),
+            Transition::with(0, Metadata::new(), bmap! {}, vec![]),
+        ];
+        assert_eq!(
+            consignment.validate_with_policy(&schema, &policy),
+            Err(validation::Failure::LimitExceeded(
+                None,
+                validation::LimitViolation {
+                    limit: validation::Limit::TransitionsCount,
+                    max: 2,
+                    actual: 3
+                }
+            ))
+        );
+
+        let transition = Transition::with(0, Metadata::new(), bmap! {}, vec![0u8; 5]);
+        consignment.endpoints = vec![transition.clone()];
+        assert_eq!(
+            consignment.validate_with_policy(&schema, &policy),
+            Err(validation::Failure::LimitExceeded(
+                Some(transition.transition_id()),
+                validation::LimitViolation {
+                    limit: validation::Limit::ScriptSize,
+                    max: 4,
+                    actual: 5
+                }
+            ))
+        );
+        assert_eq!(consignment.validate(&schema), Ok(()));
+    }
+
+    #[test]
+    fn test_schema_binding() {
+        let schema = schema();
+        let consignment = consignment(schema.schema_id());
+        assert_eq!(consignment.schema_id(), schema.schema_id());
+        assert_eq!(consignment.validate(&schema), Ok(()));
+
+        let mut other_schema = schema.clone();
+        other_schema.field_types.insert(1, DataFormat::u8());
+        assert_eq!(
+            consignment.validate(&other_schema),
+            Err(validation::Failure::SchemaMismatch {
+                expected: schema.schema_id(),
+                actual: other_schema.schema_id(),
+            })
+        );
+
+        let consignment = Consignment {
+            transitions: vec![Transition::with(1, Metadata::new(), bmap! {}, vec![])],
+            ..consignment
+        };
+        let transition_id = consignment.transitions[0].transition_id();
+        assert_eq!(
+            consignment.validate(&schema),
+            Err(validation::Failure::SchemaUnknownTransitionType(
+                transition_id,
+                1
+            ))
+        );
+    }
+
+    #[test]
+    fn test_consignment_id() {
+        use crate::tagged_hash::midstate_for_tag;
+
+        assert_eq!(
+            midstate_for_tag("rgb:consignment").into_inner(),
+            MIDSTATE_CONSIGNMENT_ID
+        );
+        let mut consignment = consignment(schema().schema_id());
+        let id = consignment.consignment_id();
+        assert_eq!(
+            id,
+            ConsignmentId::hash(&strict_encode(&consignment).unwrap())
+        );
+        assert_eq!(
+            strict_decode::<ConsignmentId>(&strict_encode(&id).unwrap()).unwrap(),
+            id
+        );
+        consignment.anchors.push(anchor(1));
+        assert_ne!(consignment.consignment_id(), id);
+    }
+
+    #[test]
+    fn test_short_tx_refs() {
+        let mut consignment = consignment(schema().schema_id());
+        consignment.anchors = vec![anchor(1), anchor(0), anchor(2)];
+        let original = consignment.anchors.clone();
+
+        consignment.compact_tx_refs(&Resolver);
+        assert!(consignment.has_short_tx_refs());
+        assert_eq!(
+            consignment
+                .anchors
+                .iter()
+                .map(|anchor| anchor.tx_ref)
+                .collect::<Vec<_>>(),
+            vec![
+                TxRef::ShortId(ShortId::from(1)),
+                original[1].tx_ref,
+                TxRef::ShortId(ShortId::from(2))
+            ]
+        );
+
+        let data = strict_encode(&consignment).unwrap();
+        let mut decoded: Consignment = strict_decode(&data).unwrap();
+        assert!(decoded.has_short_tx_refs());
+        assert_eq!(decoded.anchors, consignment.anchors);
+
+        decoded.expand_tx_refs(&Resolver).unwrap();
+        assert!(!decoded.has_short_tx_refs());
+        assert_eq!(decoded.anchors, original);
+
+        consignment.anchors.push(Anchor {
+            tx_ref: TxRef::ShortId(ShortId::from(0x1000)),
+            ..anchor(3)
+        });
+        assert_eq!(
+            consignment.expand_tx_refs(&Resolver),
+            Err(ShortId::from(0x1000))
+        );
+        assert!(consignment.has_short_tx_refs());
+
+        consignment.flags = 0;
+        let data = strict_encode(&consignment).unwrap();
+        assert!(matches!(
+            strict_decode::<Consignment>(&data),
+            Err(Error::DataIntegrityError(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_as_of() {
+        struct Chain;
+
+        impl ChainResolver for Chain {
+            fn block_info(&self, tx_ref: TxRef) -> Option<BlockInfo> {
+                match tx_ref.txid()?.into_inner()[0] {
+                    0 => None,
+                    no => Some(BlockInfo {
+                        height: no as u32 * 100,
+                        timestamp: Timestamp::from_unix_secs(no as i64 * 60_000).unwrap(),
+                    }),
+                }
+            }
+        }
+
+        impl TxResolver for Chain {
+            fn transaction(&self, _: TxRef) -> Option<bitcoin::Transaction> {
+                None
+            }
+        }
+
+        let schema = schema();
+        let policy = validation::Policy::default();
+        let mut consignment = consignment(schema.schema_id());
+        consignment.anchors = vec![anchor(1), anchor(2)];
+
+        for cutoff in vec![
+            validation::Cutoff::Height(200),
+            validation::Cutoff::Timestamp(Timestamp::from_unix_secs(120_000).unwrap()),
+        ] {
+            assert_eq!(
+                consignment.validate_as_of(&schema, &policy, &Chain, cutoff),
+                Ok(())
+            );
+        }
+        assert_eq!(
+            consignment.validate_as_of(&schema, &policy, &Chain, validation::Cutoff::Height(199)),
+            Err(validation::Failure::WitnessAfterCutoff(
+                anchor(2).tx_ref,
+                Chain.block_info(anchor(2).tx_ref).unwrap()
+            ))
+        );
+
+        let events = consignment
+            .validation_events_as_of(&schema, &policy, &Chain, validation::Cutoff::Height(199))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events[events.len() - 2..],
+            [
+                validation::Event::SealResolved(
+                    anchor(1).tx_ref,
+                    Chain.block_info(anchor(1).tx_ref).unwrap()
+                ),
+                validation::Event::Failure(validation::Failure::WitnessAfterCutoff(
+                    anchor(2).tx_ref,
+                    Chain.block_info(anchor(2).tx_ref).unwrap()
+                ))
+            ]
+        );
+
+        consignment.anchors.push(anchor(0));
+        assert_eq!(
+            consignment.validate_as_of(&schema, &policy, &Chain, validation::Cutoff::Height(200)),
+            Err(validation::Failure::WitnessUnconfirmed(anchor(0).tx_ref))
+        );
+    }
+
+    #[test]
+    fn test_validation_events() {
+        let schema = schema();
+        let policy = validation::Policy::default();
+        let mut consignment = consignment(schema.schema_id());
+        consignment.anchors = vec![anchor(1)];
+        let transition_id = consignment.transitions[0].transition_id();
+        assert_eq!(
+            consignment
+                .validation_events(&schema, &policy)
+                .collect::<Vec<_>>(),
+            vec![
+                validation::Event::NodeValidated(None),
+                validation::Event::NodeValidated(Some(transition_id)),
+                validation::Event::AnchorChecked(anchor(1).tx_ref, vec![]),
+            ]
+        );
+
+        consignment.transitions = vec![Transition::with(1, Metadata::new(), bmap! {}, vec![])];
+        let transition_id = consignment.transitions[0].transition_id();
+        let events = consignment
+            .validation_events(&schema, &policy)
+            .collect::<Vec<_>>();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[1].failure(),
+            Some(&validation::Failure::SchemaUnknownTransitionType(
+                transition_id,
+                1
+            ))
+        );
+        assert_eq!(
+            events[1].failure().cloned(),
+            consignment.validate(&schema).err()
+        );
+    }
+
+    #[test]
+    fn test_locked_assignments() {
+        let mut schema = schema();
+        schema.field_types.insert(
+            1,
+            DataFormat::Signature(elliptic_curve::SignatureAlgorithm::Ecdsa),
+        );
+        schema.assignment_types.insert(
+            1,
+            StateFormat::Hashed(DataFormat::PublicKey(
+                EllipticCurve::Secp256k1,
+                elliptic_curve::PointSerialization::Compressed,
+            )),
+        );
+        schema
+            .genesis
+            .defines
+            .insert(1, Occurences::NoneOrUpTo(None));
+        let transition_schema = schema.transitions.get_mut(&0).unwrap();
+        transition_schema
+            .metadata
+            .insert(1, Occurences::NoneOrUpTo(None));
+        transition_schema.locks.insert(1, 1);
+
+        let secp = secp256k1::Secp256k1::new();
+        let seckey = secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let locked = seal::Revealed::WitnessVout {
+            vout: 1,
+            blinding: 1,
+        };
+        let genesis = Genesis::with(
+            schema.schema_id(),
+            bp::Chain::Testnet3,
+            bmap! { 0 => bset! { data::Revealed::String(s!("locked")) } },
+            bmap! {
+                0 => AssignmentsVariant::Void(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal::Revealed::WitnessVout { vout: 0, blinding: 0 },
+                        assigned_state: data::Void,
+                    }
+                }),
+                1 => AssignmentsVariant::Hashed(bset! {
+                    Assignment::Revealed {
+                        seal_definition: locked.clone(),
+                        assigned_state: data::Revealed::Secp256k1Pubkey(
+                            secp256k1::PublicKey::from_secret_key(&secp, &seckey)
+                        ),
+                    }
+                })
+            },
+            vec![],
+        );
+        let mut transition = Transition::with(0, Metadata::new(), bmap! {}, vec![]);
+        transition.close_seal(1, locked.conceal());
+        let mut consignment = Consignment {
+            flags: 0,
+            genesis: genesis.into(),
+            transitions: vec![transition.clone()],
+            endpoints: vec![],
+            anchors: vec![],
+        };
+        assert_eq!(
+            consignment.validate(&schema),
+            Err(validation::Failure::LockSignatureMissing(
+                transition.transition_id(),
+                1
+            ))
+        );
+
+        let mut signed = transition.clone();
+        signed.sign_lock(&secp, 1, &seckey);
+        consignment.transitions = vec![signed];
+        assert_eq!(consignment.validate(&schema), Ok(()));
+
+        let mut unknown = transition;
+        let other = seal::Revealed::WitnessVout {
+            vout: 2,
+            blinding: 2,
+        };
+        unknown.close_seal(1, other.conceal());
+        unknown.sign_lock(&secp, 1, &seckey);
+        consignment.transitions = vec![unknown.clone()];
+        assert_eq!(
+            consignment.validate(&schema),
+            Err(validation::Failure::ClosedSealUnknown(
+                unknown.transition_id(),
+                1
+            ))
+        );
+    }
+
+    #[test]
+    fn test_assignment_conditions() {
+        struct Chain(bitcoin::Transaction);
+
+        impl ChainResolver for Chain {
+            fn block_info(&self, _: TxRef) -> Option<BlockInfo> {
+                Some(BlockInfo {
+                    height: 100,
+                    timestamp: Timestamp::from_unix_secs(60_000).unwrap(),
+                })
+            }
+        }
+
+        impl TxResolver for Chain {
+            fn transaction(&self, _: TxRef) -> Option<bitcoin::Transaction> {
+                Some(self.0.clone())
+            }
+        }
+
+        let mut schema = schema();
+        schema.field_types.insert(1, DataFormat::Bytes(32));
+        schema.field_types.insert(
+            2,
+            DataFormat::Signature(elliptic_curve::SignatureAlgorithm::Ecdsa),
+        );
+        schema
+            .assignment_types
+            .insert(1, StateFormat::Hashed(DataFormat::Bytes(0xFF)));
+        schema
+            .genesis
+            .defines
+            .insert(1, Occurences::NoneOrUpTo(None));
+        let fields = ConditionFields {
+            preimage: 1,
+            signature: 2,
+        };
+        let transition_schema = schema.transitions.get_mut(&0).unwrap();
+        transition_schema
+            .metadata
+            .insert(1, Occurences::NoneOrUpTo(None));
+        transition_schema
+            .metadata
+            .insert(2, Occurences::NoneOrUpTo(None));
+        transition_schema.conditions.insert(1, fields);
+
+        let secp = secp256k1::Secp256k1::new();
+        let receiver = secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let sender = secp256k1::SecretKey::from_slice(&[2u8; 32]).unwrap();
+        let preimage = [7u8; 32];
+        let lock = HashTimeLock::with(
+            &preimage,
+            1000,
+            secp256k1::PublicKey::from_secret_key(&secp, &receiver),
+            secp256k1::PublicKey::from_secret_key(&secp, &sender),
+        );
+        let locked = seal::Revealed::WitnessVout {
+            vout: 1,
+            blinding: 1,
+        };
+        let genesis = Genesis::with(
+            schema.schema_id(),
+            bp::Chain::Testnet3,
+            bmap! { 0 => bset! { data::Revealed::String(s!("conditional")) } },
+            bmap! {
+                0 => AssignmentsVariant::Void(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal::Revealed::WitnessVout { vout: 0, blinding: 0 },
+                        assigned_state: data::Void,
+                    }
+                }),
+                1 => AssignmentsVariant::Hashed(bset! {
+                    Assignment::Revealed {
+                        seal_definition: locked.clone(),
+                        assigned_state: lock.into(),
+                    }
+                })
+            },
+            vec![],
+        );
+        let mut transition = Transition::with(0, Metadata::new(), bmap! {}, vec![]);
+        transition.close_seal(1, locked.conceal());
+        let anchored = |transition: &Transition| {
+            let (protocol_id, digest) = transition_slot(transition.transition_id());
+            Anchor {
+                commitment: MultiCommitBlock::try_commit(&bmap! { protocol_id => digest }).unwrap(),
+                ..anchor(1)
+            }
+        };
+        let witness = bitcoin::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![],
+        };
+        let policy = validation::Policy::default();
+        let cutoff = validation::Cutoff::Height(100);
+
+        let mut consignment = Consignment {
+            flags: 0,
+            genesis: genesis.into(),
+            transitions: vec![transition.clone()],
+            endpoints: vec![],
+            anchors: vec![anchored(&transition)],
+        };
+        assert_eq!(consignment.validate(&schema), Ok(()));
+        assert_eq!(
+            consignment.validate_as_of(&schema, &policy, &Chain(witness.clone()), cutoff),
+            Err(validation::Failure::ConditionNotSatisfied(
+                transition.transition_id(),
+                1
+            ))
+        );
+
+        let mut claim = transition;
+        claim.sign_claim(&secp, fields, &preimage, &receiver);
+        consignment.transitions = vec![claim.clone()];
+        consignment.anchors = vec![anchored(&claim)];
+        assert_eq!(
+            consignment.validate_as_of(&schema, &policy, &Chain(witness.clone()), cutoff),
+            Ok(())
+        );
+        assert_eq!(
+            consignment
+                .validation_events_as_of(&schema, &policy, &Chain(witness.clone()), cutoff)
+                .last(),
+            Some(validation::Event::SealResolved(
+                anchor(1).tx_ref,
+                Chain(witness.clone()).block_info(anchor(1).tx_ref).unwrap()
+            ))
+        );
+
+        consignment.anchors = vec![anchor(1)];
+        assert_eq!(
+            consignment.validate_as_of(&schema, &policy, &Chain(witness), cutoff),
+            Err(validation::Failure::WitnessUnknown(claim.transition_id()))
+        );
+    }
+
+    #[test]
+    fn test_schema_structure() {
+        let schema = schema();
+        let mut consignment = consignment(schema.schema_id());
+        assert_eq!(consignment.validate(&schema), Ok(()));
+
+        consignment.genesis = Genesis::with(
+            schema.schema_id(),
+            bp::Chain::Testnet3,
+            Metadata::new(),
+            consignment.genesis.assignments().clone(),
+            vec![],
+        )
+        .into();
+        assert_eq!(
+            consignment.validate(&schema),
+            Err(validation::Failure::SchemaMetaOccurencesError(
+                None,
+                0,
+                OccurencesError {
+                    expected: Occurences::Once,
+                    found: 0
+                }
+            ))
+        );
+
+        consignment.genesis = genesis(schema.schema_id()).into();
+        consignment.endpoints = vec![Transition::with(
+            0,
+            bmap! { 0 => bset! { data::Revealed::String(s!("Asset")) } },
+            bmap! {},
+            vec![],
+        )];
+        let transition_id = consignment.endpoints[0].transition_id();
+        let failure = validation::Failure::SchemaUnknownFieldType(Some(transition_id), 0);
+        assert_eq!(consignment.validate(&schema), Err(failure.clone()));
+        let policy = validation::Policy::default();
+        assert_eq!(
+            consignment
+                .validation_events(&schema, &policy)
+                .last()
+                .unwrap(),
+            validation::Event::Failure(failure)
+        );
+
+        consignment.endpoints = vec![Transition::with(
+            0,
+            Metadata::new(),
+            bmap! {
+                0 => AssignmentsVariant::Hashed(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal::Revealed::WitnessVout { vout: 1, blinding: 1 },
+                        assigned_state: data::Revealed::U8(1),
+                    }
+                })
+            },
+            vec![],
+        )];
+        let transition_id = consignment.endpoints[0].transition_id();
+        assert_eq!(
+            consignment.validate(&schema),
+            Err(validation::Failure::SchemaStateFormatMismatch(
+                Some(transition_id),
+                0
+            ))
+        );
+    }
+
+    #[test]
+    fn test_schema_driven_formats() {
+        // Synthetic schema with formats not used by any known contract type:
+        // consignment validation relies on the declared formats only
+        let mut schema = schema();
+        schema.field_types = bmap! {
+            0 => DataFormat::String(255),
+            7 => DataFormat::Unsigned(Bits::Bit16, 10, 100),
+            9 => DataFormat::Integer(Bits::Bit32, -50, -1)
+        };
+        schema
+            .assignment_types
+            .insert(3, StateFormat::Hashed(DataFormat::Bytes(3)));
+        schema.genesis.metadata = bmap! {
+            0 => Occurences::Once,
+            7 => Occurences::OnceOrUpTo(Some(2))
+        };
+        schema.genesis.defines = bmap! { 3 => Occurences::Once };
+        schema
+            .transitions
+            .get_mut(&0)
+            .unwrap()
+            .metadata
+            .insert(9, Occurences::NoneOrOnce);
+
+        let consignment = |schema: &Schema, state: Vec<u8>, value: data::Revealed| Consignment {
+            flags: 0,
+            genesis: Genesis::with(
+                schema.schema_id(),
+                bp::Chain::Testnet3,
+                bmap! {
+                    0 => bset! { data::Revealed::String(s!("synthetic")) },
+                    7 => bset! { data::Revealed::U16(10), data::Revealed::U16(100) }
+                },
+                bmap! {
+                    3 => AssignmentsVariant::Hashed(bset! {
+                        Assignment::Revealed {
+                            seal_definition: seal::Revealed::WitnessVout { vout: 0, blinding: 0 },
+                            assigned_state: data::Revealed::Bytes(state),
+                        }
+                    })
+                },
+                vec![],
+            )
+            .into(),
+            transitions: vec![Transition::with(
+                0,
+                bmap! { 9 => bset! { value } },
+                bmap! {},
+                vec![],
+            )],
+            endpoints: vec![],
+            anchors: vec![],
+        };
+        assert_eq!(
+            consignment(&schema, vec![1, 2, 3], data::Revealed::I32(-50)).validate(&schema),
+            Ok(())
+        );
+        assert_eq!(
+            consignment(&schema, vec![0; 4], data::Revealed::I32(-50)).validate(&schema),
+            Err(validation::Failure::SchemaStateFormatMismatch(None, 3))
+        );
+        for value in vec![data::Revealed::I32(0), data::Revealed::I16(-10)] {
+            let consignment = consignment(&schema, vec![1, 2, 3], value);
+            assert_eq!(
+                consignment.validate(&schema),
+                Err(validation::Failure::SchemaFieldFormatMismatch(
+                    Some(consignment.transitions[0].transition_id()),
+                    9
+                ))
+            );
+        }
+
+        let mut unsupported = schema.clone();
+        unsupported
+            .field_types
+            .insert(7, DataFormat::Unsigned(Bits::Bit16, 100, 10));
+        assert_eq!(
+            consignment(&unsupported, vec![1, 2, 3], data::Revealed::I32(-50))
+                .validate(&unsupported),
+            Err(validation::Failure::SchemaUnsupportedFieldFormat(None, 7))
+        );
+    }
+}
@@ -0,0 +1,289 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Payment proofs, which can be handed by the sender to third parties to
+//! prove that some amount was assigned to a particular blinded seal
+
+use super::Consignment;
+use crate::client_side_validation::CommitConceal;
+use crate::rgb::schema::AssignmentsType;
+use crate::rgb::stash::transition_slot;
+use crate::rgb::{
+    amount, seal, Anchor, Assignment, AssignmentsVariant, ConcealedTransition, ContractId, Node,
+    Transition, TransitionId,
+};
+
+/// Proof that a state transition has assigned an amount to a blinded seal.
+/// The proof contains the state transition with all of its assignments
+/// concealed and reveals only the amount (with its blinding factor) assigned
+/// to the seal, so it does not disclose the data of other recipients.
+///
+/// The proof does not link the state transition to the contract genesis, so
+/// the contract id is a claim which must be checked against the contract
+/// history known to the verifier. The DBC proof within the anchor may be
+/// checked against the witness transaction referenced by the anchor.
+#[derive(Clone, Debug, Display)]
+#[display_from(Debug)]
+pub struct PaymentProof {
+    pub contract_id: ContractId,
+    pub transition: ConcealedTransition,
+    pub assignment_type: AssignmentsType,
+    pub seal: seal::Confidential,
+    pub amount: amount::Revealed,
+    pub anchor: Anchor,
+}
+
+/// Reasons for the payment proof verification failure
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display_from(Debug)]
+pub enum PaymentProofError {
+    /// Anchor does not commit to the state transition
+    TransitionNotAnchored(TransitionId),
+
+    /// State transition does not contain assignment of the amount to the
+    /// seal under the given assignment type
+    AssignmentNotFound(AssignmentsType),
+}
+
+impl PaymentProof {
+    /// Constructs proof for the amount assigned to the blinded seal by the
+    /// state transition, which is committed to by the anchor. Returns `None`
+    /// if the transition does not contain revealed homomorphic assignment to
+    /// the seal.
+    pub fn with(
+        contract_id: ContractId,
+        transition: &Transition,
+        anchor: Anchor,
+        seal: seal::Confidential,
+    ) -> Option<Self> {
+        transition
+            .assignments()
+            .iter()
+            .find_map(|(assignment_type, variant)| match variant {
+                AssignmentsVariant::Homomorphic(_, set) => {
+                    set.iter().find_map(|assignment| match assignment {
+                        Assignment::Revealed {
+                            seal_definition,
+                            assigned_state,
+                        } if *seal_definition == seal => {
+                            Some((*assignment_type, assigned_state.clone()))
+                        }
+                        _ => None,
+                    })
+                }
+                _ => None,
+            })
+            .map(|(assignment_type, amount)| Self {
+                contract_id,
+                transition: transition.conceal(),
+                assignment_type,
+                seal,
+                amount,
+                anchor,
+            })
+    }
+
+    #[inline]
+    pub fn transition_id(&self) -> TransitionId {
+        self.transition.transition_id()
+    }
+
+    #[inline]
+    pub fn amount(&self) -> amount::Amount {
+        self.amount.amount
+    }
+
+    /// Verifies that the anchor commits to the state transition and that the
+    /// transition assigns the amount to the seal
+    pub fn verify(&self) -> Result<(), PaymentProofError> {
+        let transition_id = self.transition_id();
+        let (protocol_id, digest) = transition_slot(transition_id);
+        if !self.anchor.commitment.verify_message(&protocol_id, digest) {
+            Err(PaymentProofError::TransitionNotAnchored(transition_id))?
+        }
+        let amount = self.amount.conceal();
+        let assigned = match self.transition.assignments().get(&self.assignment_type) {
+            Some(AssignmentsVariant::Homomorphic(_, set)) => {
+                set.iter().any(|assignment| match assignment {
+                    Assignment::Confidential {
+                        seal_definition,
+                        assigned_state,
+                    } => *seal_definition == self.seal && *assigned_state == amount,
+                    Assignment::Revealed { .. } => false,
+                })
+            }
+            _ => false,
+        };
+        if !assigned {
+            Err(PaymentProofError::AssignmentNotFound(self.assignment_type))?
+        }
+        Ok(())
+    }
+}
+
+impl Consignment {
+    /// Creates payment proof for the amount assigned to the blinded seal by
+    /// any of the consignment state transitions. Returns `None` if there is
+    /// no such assignment or if the transition is not anchored.
+    pub fn payment_proof(&self, seal: seal::Confidential) -> Option<PaymentProof> {
+        let contract_id = self.contract_id();
+        self.endpoints
+            .iter()
+            .chain(self.transitions.iter())
+            .find_map(|transition| {
+                let (protocol_id, digest) = transition_slot(transition.transition_id());
+                let anchor = self
+                    .anchors
+                    .iter()
+                    .find(|anchor| anchor.commitment.verify_message(&protocol_id, digest))?;
+                PaymentProof::with(contract_id, transition, anchor.clone(), seal)
+            })
+    }
+}
+
+mod strict_encoding {
+    use super::*;
+    use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
+    use std::io;
+
+    impl StrictEncode for PaymentProof {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(strict_encode_list!(e;
+                self.contract_id,
+                self.transition,
+                self.assignment_type,
+                self.seal,
+                self.amount,
+                self.anchor
+            ))
+        }
+    }
+
+    impl StrictDecode for PaymentProof {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(Self {
+                contract_id: ContractId::strict_decode(&mut d)?,
+                transition: ConcealedTransition::strict_decode(&mut d)?,
+                assignment_type: AssignmentsType::strict_decode(&mut d)?,
+                seal: seal::Confidential::strict_decode(&mut d)?,
+                amount: amount::Revealed::strict_decode(&mut d)?,
+                anchor: Anchor::strict_decode(&mut d)?,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::{self, blind::OutpointReveal, dbc};
+    use crate::commit_verify::TryCommitVerify;
+    use crate::lnpbp4::MultiCommitBlock;
+    use crate::rgb::{Genesis, Metadata, SchemaId, TxRef};
+    use crate::strict_encoding::{strict_decode, strict_encode};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{secp256k1, Txid};
+
+    fn seal(blinding: u32) -> seal::Revealed {
+        seal::Revealed::TxOutpoint(OutpointReveal {
+            blinding,
+            txid: Txid::from_slice(&[1u8; 32]).unwrap(),
+            vout: 0,
+        })
+    }
+
+    #[test]
+    fn test_payment_proof() {
+        let mut rng = rand::thread_rng();
+        let transition = Transition::with(
+            0,
+            Metadata::new(),
+            bmap! {
+                1 => AssignmentsVariant::Homomorphic(0, bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal(1),
+                        assigned_state: amount::Revealed::with_amount(10, &mut rng),
+                    },
+                    Assignment::Revealed {
+                        seal_definition: seal(2),
+                        assigned_state: amount::Revealed::with_amount(15, &mut rng),
+                    }
+                })
+            },
+            vec![],
+        );
+        let (protocol_id, digest) = transition_slot(transition.transition_id());
+        let secp = secp256k1::Secp256k1::new();
+        let seckey = secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let anchor = Anchor {
+            tx_ref: TxRef::Txid(Txid::from_slice(&[2u8; 32]).unwrap()),
+            commitment: MultiCommitBlock::try_commit(&bmap! { protocol_id => digest }).unwrap(),
+            dbc_proof: dbc::Proof::from(secp256k1::PublicKey::from_secret_key(&secp, &seckey)),
+        };
+        let consignment = Consignment {
+            flags: 0,
+            genesis: Genesis::with(
+                SchemaId::default(),
+                bp::Chain::Testnet3,
+                Metadata::new(),
+                bmap! {},
+                vec![],
+            )
+            .into(),
+            transitions: vec![],
+            endpoints: vec![transition],
+            anchors: vec![anchor],
+        };
+
+        assert!(consignment.payment_proof(seal(3).conceal()).is_none());
+        let proof = consignment.payment_proof(seal(2).conceal()).unwrap();
+        assert_eq!(proof.contract_id, consignment.contract_id());
+        assert_eq!(proof.amount(), 15);
+        assert_eq!(proof.verify(), Ok(()));
+        assert!(proof
+            .transition
+            .assignments()
+            .values()
+            .all(AssignmentsVariant::is_concealed));
+
+        let proof = strict_decode::<PaymentProof>(&strict_encode(&proof).unwrap()).unwrap();
+        assert_eq!(proof.verify(), Ok(()));
+
+        let mut forged = proof.clone();
+        forged.amount = amount::Revealed::with_amount(16, &mut rng);
+        assert_eq!(
+            forged.verify(),
+            Err(PaymentProofError::AssignmentNotFound(1))
+        );
+
+        let mut forged = proof.clone();
+        forged.seal = seal(1).conceal();
+        assert_eq!(
+            forged.verify(),
+            Err(PaymentProofError::AssignmentNotFound(1))
+        );
+
+        let mut forged = proof.clone();
+        forged.anchor.commitment = MultiCommitBlock::try_commit(&bmap! {}).unwrap();
+        assert_eq!(
+            forged.verify(),
+            Err(PaymentProofError::TransitionNotAnchored(
+                proof.transition_id()
+            ))
+        );
+    }
+}
@@ -21,7 +21,7 @@ pub use secp256k1zkp::pedersen;
 use secp256k1zkp::*;
 
 use super::data;
-use crate::client_side_validation::{commit_strategy, CommitEncodeWithStrategy, Conceal};
+use crate::client_side_validation::CommitConceal;
 use crate::commit_verify::CommitVerify;
 
 pub type Amount = u64;
@@ -29,8 +29,9 @@ pub type Amount = u64;
 /// Proof for Pedersen commitment: a blinding key
 pub type BlindingFactor = secp256k1zkp::key::SecretKey;
 
-#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, CommitEncode)]
 #[display_from(Debug)]
+#[commit_encode(strategy = "conceal")]
 pub struct Revealed {
     pub amount: Amount,
     pub blinding: BlindingFactor,
@@ -47,16 +48,13 @@ impl Revealed {
     }
 }
 
-impl Conceal for Revealed {
+impl CommitConceal for Revealed {
     type Confidential = Confidential;
 
     fn conceal(&self) -> Confidential {
         Confidential::commit(self)
     }
 }
-impl CommitEncodeWithStrategy for Revealed {
-    type Strategy = commit_strategy::UsingConceal;
-}
 
 impl PartialOrd for Revealed {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
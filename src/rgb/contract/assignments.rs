@@ -14,11 +14,12 @@
 use std::collections::BTreeSet;
 
 use super::{super::schema, amount, data, seal, Amount, SealDefinition};
-use crate::client_side_validation::{commit_strategy, CommitEncodeWithStrategy, Conceal};
+use crate::client_side_validation::{commit_strategy, CommitConceal, CommitEncodeWithStrategy};
 use crate::strict_encoding::{Error as EncodingError, StrictDecode, StrictEncode};
 
-#[derive(Clone, Debug, Display)]
+#[derive(Clone, Debug, Display, CommitEncode)]
 #[display_from(Debug)]
+#[commit_encode(strategy = "strict")]
 pub enum AssignmentsVariant {
     Void(BTreeSet<Assignment<VoidStrategy>>),
     Homomorphic(u64, BTreeSet<Assignment<HomomorphStrategy>>),
@@ -26,6 +27,143 @@ pub enum AssignmentsVariant {
 }
 
 impl AssignmentsVariant {
+    /// Returns number of assignments of the given type
+    pub fn len(&self) -> usize {
+        match self {
+            AssignmentsVariant::Void(set) => set.len(),
+            AssignmentsVariant::Homomorphic(_, set) => set.len(),
+            AssignmentsVariant::Hashed(set) => set.len(),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the same assignments with all of them in the confidential
+    /// form, i.e. without revealed seals, blinding factors and state
+    pub fn conceal_all(&self) -> Self {
+        match self {
+            AssignmentsVariant::Void(set) => {
+                AssignmentsVariant::Void(set.iter().map(CommitConceal::conceal).collect())
+            }
+            AssignmentsVariant::Homomorphic(factor, set) => AssignmentsVariant::Homomorphic(
+                *factor,
+                set.iter().map(CommitConceal::conceal).collect(),
+            ),
+            AssignmentsVariant::Hashed(set) => {
+                AssignmentsVariant::Hashed(set.iter().map(CommitConceal::conceal).collect())
+            }
+        }
+    }
+
+    /// Returns only revealed assignments
+    pub fn revealed_only(&self) -> Self {
+        match self {
+            AssignmentsVariant::Void(set) => AssignmentsVariant::Void(
+                set.iter().filter(|a| a.is_revealed()).cloned().collect(),
+            ),
+            AssignmentsVariant::Homomorphic(factor, set) => AssignmentsVariant::Homomorphic(
+                *factor,
+                set.iter().filter(|a| a.is_revealed()).cloned().collect(),
+            ),
+            AssignmentsVariant::Hashed(set) => AssignmentsVariant::Hashed(
+                set.iter().filter(|a| a.is_revealed()).cloned().collect(),
+            ),
+        }
+    }
+
+    /// Returns seals of all assignments in their confidential form
+    pub fn seals(&self) -> Vec<seal::Confidential> {
+        match self {
+            AssignmentsVariant::Void(set) => set.iter().map(Assignment::seal).collect(),
+            AssignmentsVariant::Homomorphic(_, set) => set.iter().map(Assignment::seal).collect(),
+            AssignmentsVariant::Hashed(set) => set.iter().map(Assignment::seal).collect(),
+        }
+    }
+
+    /// Returns only assignments which seals, in their confidential form,
+    /// are present in the given set
+    pub fn with_seals(&self, seals: &BTreeSet<seal::Confidential>) -> Self {
+        match self {
+            AssignmentsVariant::Void(set) => AssignmentsVariant::Void(
+                set.iter()
+                    .filter(|a| seals.contains(&a.seal()))
+                    .cloned()
+                    .collect(),
+            ),
+            AssignmentsVariant::Homomorphic(factor, set) => AssignmentsVariant::Homomorphic(
+                *factor,
+                set.iter()
+                    .filter(|a| seals.contains(&a.seal()))
+                    .cloned()
+                    .collect(),
+            ),
+            AssignmentsVariant::Hashed(set) => AssignmentsVariant::Hashed(
+                set.iter()
+                    .filter(|a| seals.contains(&a.seal()))
+                    .cloned()
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Adds assignments from the other variant of the same kind. Returns
+    /// `false` and leaves the assignments unchanged if the variants do not
+    /// match.
+    pub fn merge(&mut self, other: AssignmentsVariant) -> bool {
+        match (self, other) {
+            (AssignmentsVariant::Void(set), AssignmentsVariant::Void(other)) => set.extend(other),
+            (
+                AssignmentsVariant::Homomorphic(_, set),
+                AssignmentsVariant::Homomorphic(_, other),
+            ) => set.extend(other),
+            (AssignmentsVariant::Hashed(set), AssignmentsVariant::Hashed(other)) => {
+                set.extend(other)
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Detects whether all assignments are in the confidential form
+    pub fn is_concealed(&self) -> bool {
+        match self {
+            AssignmentsVariant::Void(set) => set.iter().all(|a| !a.is_revealed()),
+            AssignmentsVariant::Homomorphic(_, set) => set.iter().all(|a| !a.is_revealed()),
+            AssignmentsVariant::Hashed(set) => set.iter().all(|a| !a.is_revealed()),
+        }
+    }
+
+    /// Detects whether all assignments are in the revealed form
+    pub fn is_revealed(&self) -> bool {
+        match self {
+            AssignmentsVariant::Void(set) => set.iter().all(Assignment::is_revealed),
+            AssignmentsVariant::Homomorphic(_, set) => set.iter().all(Assignment::is_revealed),
+            AssignmentsVariant::Hashed(set) => set.iter().all(Assignment::is_revealed),
+        }
+    }
+
+    /// Replaces confidential assignments with their revealed counterparts.
+    /// Returns `None` if the variants do not match or some of the revealed
+    /// assignments do not correspond to any of the existing assignments.
+    pub fn reveal(&self, revealed: &AssignmentsVariant) -> Option<Self> {
+        Some(match (self, revealed) {
+            (AssignmentsVariant::Void(set), AssignmentsVariant::Void(revealed)) => {
+                AssignmentsVariant::Void(reveal_set(set, revealed)?)
+            }
+            (
+                AssignmentsVariant::Homomorphic(factor, set),
+                AssignmentsVariant::Homomorphic(_, revealed),
+            ) => AssignmentsVariant::Homomorphic(*factor, reveal_set(set, revealed)?),
+            (AssignmentsVariant::Hashed(set), AssignmentsVariant::Hashed(revealed)) => {
+                AssignmentsVariant::Hashed(reveal_set(set, revealed)?)
+            }
+            _ => return None,
+        })
+    }
+
     pub fn zero_balanced(
         allocations: Vec<(SealDefinition, Amount)>,
         homomorphic_factor: u64,
@@ -70,13 +208,9 @@ impl AssignmentsVariant {
     }
 }
 
-impl CommitEncodeWithStrategy for AssignmentsVariant {
-    type Strategy = commit_strategy::UsingStrict;
-}
-
 pub trait StateTypes: core::fmt::Debug {
     type Confidential: StrictEncode + StrictDecode + core::fmt::Debug + Eq + Ord + Clone;
-    type Revealed: StrictEncode + StrictDecode + core::fmt::Debug + Eq + Ord + Conceal + Clone;
+    type Revealed: StrictEncode + StrictDecode + core::fmt::Debug + Eq + Ord + CommitConceal + Clone;
 }
 
 #[derive(Clone, Debug, PartialOrd, Ord, PartialEq, Eq)]
@@ -120,10 +254,10 @@ where
     },
 }
 
-impl<STATE> Conceal for Assignment<STATE>
+impl<STATE> CommitConceal for Assignment<STATE>
 where
     STATE: StateTypes,
-    STATE::Confidential: From<<STATE::Revealed as Conceal>::Confidential>,
+    STATE::Confidential: From<<STATE::Revealed as CommitConceal>::Confidential>,
     EncodingError: From<<STATE::Confidential as StrictEncode>::Error>
         + From<<STATE::Confidential as StrictDecode>::Error>
         + From<<STATE::Revealed as StrictEncode>::Error>
@@ -151,6 +285,57 @@ where
     }
 }
 
+impl<STATE> Assignment<STATE>
+where
+    STATE: StateTypes,
+    EncodingError: From<<STATE::Confidential as StrictEncode>::Error>
+        + From<<STATE::Confidential as StrictDecode>::Error>
+        + From<<STATE::Revealed as StrictEncode>::Error>
+        + From<<STATE::Revealed as StrictDecode>::Error>,
+{
+    #[inline]
+    pub fn is_revealed(&self) -> bool {
+        match self {
+            Assignment::Revealed { .. } => true,
+            Assignment::Confidential { .. } => false,
+        }
+    }
+
+    /// Returns assignment seal in its confidential form
+    pub fn seal(&self) -> seal::Confidential {
+        match self {
+            Assignment::Confidential {
+                seal_definition, ..
+            } => *seal_definition,
+            Assignment::Revealed {
+                seal_definition, ..
+            } => seal_definition.conceal(),
+        }
+    }
+}
+
+fn reveal_set<STATE>(
+    set: &BTreeSet<Assignment<STATE>>,
+    revealed: &BTreeSet<Assignment<STATE>>,
+) -> Option<BTreeSet<Assignment<STATE>>>
+where
+    STATE: StateTypes,
+    STATE::Confidential: From<<STATE::Revealed as CommitConceal>::Confidential>,
+    EncodingError: From<<STATE::Confidential as StrictEncode>::Error>
+        + From<<STATE::Confidential as StrictDecode>::Error>
+        + From<<STATE::Revealed as StrictEncode>::Error>
+        + From<<STATE::Revealed as StrictDecode>::Error>,
+{
+    let mut result = set.clone();
+    for assignment in revealed {
+        if !result.remove(&assignment.conceal()) && !result.contains(assignment) {
+            return None;
+        }
+        result.insert(assignment.clone());
+    }
+    Some(result)
+}
+
 impl<STATE> CommitEncodeWithStrategy for Assignment<STATE>
 where
     STATE: StateTypes,
@@ -164,9 +349,47 @@ where
 
 mod strict_encoding {
     use super::*;
-    use crate::strict_encoding::Error;
+    use crate::strict_encoding::{strict_encode, Error};
     use data::strict_encoding::EncodingTag;
-    use std::io;
+    use std::io::{self, Read};
+
+    impl AssignmentsVariant {
+        /// Decodes assignments, calling `check` with their number before
+        /// memory for them is allocated, so the caller can enforce the limits
+        pub(crate) fn strict_decode_checked<D: io::Read>(
+            mut d: D,
+            check: impl FnOnce(usize) -> Result<(), Error>,
+        ) -> Result<Self, Error> {
+            let format = schema::StateType::strict_decode(&mut d)?;
+            let mut homomorphic_factor = 0u64;
+            if format == schema::StateType::Homomorphic {
+                match EncodingTag::strict_decode(&mut d)? {
+                    EncodingTag::U64 => homomorphic_factor = u64::strict_decode(&mut d)?,
+                    _ => Err(Error::UnsupportedDataStructure(
+                        "We support only homomorphic commitments to U64 data".to_string(),
+                    ))?,
+                }
+            }
+            let len = usize::strict_decode(&mut d)?;
+            check(len)?;
+            // Length of the assignments set is already consumed, so we feed
+            // it back to the set decoder
+            let prefix = strict_encode(&len)?;
+            let mut d = prefix.as_slice().chain(d);
+            Ok(match format {
+                schema::StateType::Void => {
+                    AssignmentsVariant::Void(BTreeSet::strict_decode(&mut d)?)
+                }
+                schema::StateType::Homomorphic => AssignmentsVariant::Homomorphic(
+                    homomorphic_factor,
+                    BTreeSet::strict_decode(&mut d)?,
+                ),
+                schema::StateType::Hashed => {
+                    AssignmentsVariant::Hashed(BTreeSet::strict_decode(&mut d)?)
+                }
+            })
+        }
+    }
 
     impl StrictEncode for AssignmentsVariant {
         type Error = Error;
@@ -177,7 +400,7 @@ mod strict_encoding {
                     strict_encode_list!(e; schema::StateType::Void, tree)
                 }
                 AssignmentsVariant::Homomorphic(homomorphic_factor, tree) => {
-                    strict_encode_list!(e; EncodingTag::U64, homomorphic_factor, schema::StateType::Homomorphic, tree)
+                    strict_encode_list!(e; schema::StateType::Homomorphic, EncodingTag::U64, homomorphic_factor, tree)
                 }
                 AssignmentsVariant::Hashed(tree) => {
                     strict_encode_list!(e; schema::StateType::Hashed, tree)
@@ -189,23 +412,9 @@ mod strict_encoding {
     impl StrictDecode for AssignmentsVariant {
         type Error = Error;
 
-        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
-            let format = schema::StateType::strict_decode(&mut d)?;
-            Ok(match format {
-                schema::StateType::Void => AssignmentsVariant::Void(BTreeSet::strict_decode(d)?),
-                schema::StateType::Homomorphic => match EncodingTag::strict_decode(&mut d)? {
-                    EncodingTag::U64 => AssignmentsVariant::Homomorphic(
-                        u64::strict_decode(&mut d)?,
-                        BTreeSet::strict_decode(&mut d)?,
-                    ),
-                    _ => Err(Error::UnsupportedDataStructure(
-                        "We support only homomorphic commitments to U64 data".to_string(),
-                    ))?,
-                },
-                schema::StateType::Hashed => {
-                    AssignmentsVariant::Hashed(BTreeSet::strict_decode(d)?)
-                }
-            })
+        #[inline]
+        fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+            Self::strict_decode_checked(d, |_| Ok(()))
         }
     }
 
@@ -259,3 +468,50 @@ mod strict_encoding {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::strict_encoding::{strict_decode, strict_encode};
+
+    #[test]
+    fn test_variant_encoding() {
+        let mut rng = rand::thread_rng();
+        let seal = |vout| seal::Revealed::WitnessVout { vout, blinding: 1 };
+        let variants = vec![
+            AssignmentsVariant::Void(bset! {
+                Assignment::Revealed {
+                    seal_definition: seal(0),
+                    assigned_state: data::Void,
+                }
+            }),
+            AssignmentsVariant::Homomorphic(
+                5,
+                bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal(1),
+                        assigned_state: amount::Revealed::with_amount(10, &mut rng),
+                    }
+                },
+            ),
+            AssignmentsVariant::Hashed(bset! {
+                Assignment::Revealed {
+                    seal_definition: seal(2),
+                    assigned_state: data::Revealed::U8(1),
+                }
+            }),
+        ];
+        for variant in variants
+            .iter()
+            .cloned()
+            .chain(variants.iter().map(AssignmentsVariant::conceal_all))
+        {
+            let encoded = strict_encode(&variant).unwrap();
+            let decoded = strict_decode::<AssignmentsVariant>(&encoded).unwrap();
+            assert_eq!(decoded.len(), variant.len());
+            assert_eq!(decoded.is_revealed(), variant.is_revealed());
+            assert_eq!(strict_encode(&decoded).unwrap(), encoded);
+        }
+        assert!(strict_decode::<AssignmentsVariant>(&[3u8]).is_err());
+    }
+}
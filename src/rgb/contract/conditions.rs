@@ -0,0 +1,182 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Conditional ownership of the assigned state, used as a groundwork for
+//! atomic swaps of RGB assets
+
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1;
+use core::convert::TryFrom;
+
+use super::data;
+use crate::strict_encoding::{strict_decode, strict_encode};
+
+/// Lock time values starting from this one are interpreted as UNIX
+/// timestamps and not block heights
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+/// Input sequence number disabling lock time enforcement
+pub const SEQUENCE_FINAL: u32 = 0xFFFF_FFFF;
+
+/// Hash- and time-locked conditional ownership of an assignment: the state
+/// may be claimed by the receiver revealing preimage of the hash before the
+/// timeout, or returned to the sender after it. The lock is kept as the
+/// assignment state in the form of strict-encoded bytes, so the schema must
+/// define [crate::rgb::schema::StateFormat::Hashed] state with
+/// [crate::rgb::schema::DataFormat::Bytes] format for the assignment type.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct HashTimeLock {
+    /// SHA256 hash of the preimage known to the sender
+    pub hash: sha256::Hash,
+    /// Block height starting from which the state may be returned to the
+    /// sender and can't be claimed by the receiver anymore
+    pub timeout: u32,
+    /// Key of the receiver which must sign the claiming transition
+    pub receiver: secp256k1::PublicKey,
+    /// Key of the sender which must sign the refunding transition
+    pub refund: secp256k1::PublicKey,
+}
+
+impl HashTimeLock {
+    /// Creates lock for the given preimage
+    pub fn with(
+        preimage: &[u8; 32],
+        timeout: u32,
+        receiver: secp256k1::PublicKey,
+        refund: secp256k1::PublicKey,
+    ) -> Self {
+        Self {
+            hash: sha256::Hash::hash(preimage),
+            timeout,
+            receiver,
+            refund,
+        }
+    }
+
+    /// Checks whether the data are the preimage of the lock hash
+    #[inline]
+    pub fn matches_preimage(&self, preimage: &[u8]) -> bool {
+        sha256::Hash::hash(preimage) == self.hash
+    }
+
+    /// Checks whether the witness transaction can't be mined before the
+    /// timeout, i.e. its lock time is a block height not less than the
+    /// timeout and the lock time is enforced by some of the inputs
+    pub fn is_refund_witness(&self, witness: &bitcoin::Transaction) -> bool {
+        witness.lock_time >= self.timeout
+            && witness.lock_time < LOCKTIME_THRESHOLD
+            && witness
+                .input
+                .iter()
+                .any(|txin| txin.sequence != SEQUENCE_FINAL)
+    }
+
+    /// Prepares witness transaction for the refund: sets its lock time to
+    /// the timeout and makes sure the lock time is enforced
+    pub fn prepare_refund_witness(&self, witness: &mut bitcoin::Transaction) {
+        witness.lock_time = self.timeout;
+        if !self.is_refund_witness(witness) {
+            if let Some(txin) = witness.input.first_mut() {
+                txin.sequence = SEQUENCE_FINAL - 1;
+            }
+        }
+    }
+}
+
+impl From<HashTimeLock> for data::Revealed {
+    fn from(lock: HashTimeLock) -> Self {
+        data::Revealed::Bytes(
+            strict_encode(&lock).expect("Strict encoding of hash time lock can't fail"),
+        )
+    }
+}
+
+impl TryFrom<&data::Revealed> for HashTimeLock {
+    type Error = crate::strict_encoding::Error;
+
+    fn try_from(state: &data::Revealed) -> Result<Self, Self::Error> {
+        match state {
+            data::Revealed::Bytes(bytes) => strict_decode(bytes),
+            _ => Err(crate::strict_encoding::Error::DataIntegrityError(
+                "Hash time lock must be represented by bytes".to_string(),
+            )),
+        }
+    }
+}
+
+mod strict_encoding {
+    use super::*;
+    use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
+    use std::io;
+
+    impl StrictEncode for HashTimeLock {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(strict_encode_list!(e; self.hash, self.timeout, self.receiver, self.refund))
+        }
+    }
+
+    impl StrictDecode for HashTimeLock {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(Self {
+                hash: sha256::Hash::strict_decode(&mut d)?,
+                timeout: u32::strict_decode(&mut d)?,
+                receiver: secp256k1::PublicKey::strict_decode(&mut d)?,
+                refund: secp256k1::PublicKey::strict_decode(&mut d)?,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::test::gen_secp_pubkeys;
+    use crate::strict_encoding::test_helpers::test_encode_roundtrip;
+
+    #[test]
+    fn test_hash_time_lock() {
+        let keys = gen_secp_pubkeys(2);
+        let lock = HashTimeLock::with(&[7u8; 32], 1000, keys[0], keys[1]);
+        test_encode_roundtrip(&lock).unwrap();
+        assert!(lock.matches_preimage(&[7u8; 32]));
+        assert!(!lock.matches_preimage(&[8u8; 32]));
+
+        let state = data::Revealed::from(lock);
+        assert_eq!(HashTimeLock::try_from(&state).unwrap(), lock);
+        assert!(HashTimeLock::try_from(&data::Revealed::U8(1)).is_err());
+
+        let mut witness = bitcoin::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![bitcoin::TxIn {
+                previous_output: bitcoin::OutPoint::default(),
+                script_sig: bitcoin::Script::new(),
+                sequence: SEQUENCE_FINAL,
+                witness: vec![],
+            }],
+            output: vec![],
+        };
+        assert!(!lock.is_refund_witness(&witness));
+        witness.lock_time = 1000;
+        assert!(!lock.is_refund_witness(&witness));
+        lock.prepare_refund_witness(&mut witness);
+        assert!(lock.is_refund_witness(&witness));
+        witness.lock_time = 999;
+        assert!(!lock.is_refund_witness(&witness));
+    }
+}
@@ -13,6 +13,7 @@
 
 use super::{Genesis, Transition};
 use bitcoin::hashes::{sha256t, Hash};
+use std::sync::Arc;
 
 // TODO: Check the data
 static MIDSTATE_CONTRACT_ID: [u8; 32] = [
@@ -30,14 +31,18 @@ tagged_hash!(
     ContractId,
     ContractIdTag,
     MIDSTATE_CONTRACT_ID,
-    doc = "Unique contract identifier equivalent to the contract genesis commitment hash"
+    doc = "Unique contract identifier equivalent to the contract genesis commitment hash. \
+           Identifiers are ordered lexicographically over their hash bytes, in the same order \
+           as they are serialized."
 );
 
 tagged_hash!(
     TransitionId,
     TransitionIdTag,
     MIDSTATE_TRANSITION_ID,
-    doc = "Unique transition identifier equivalent to the state transition commitment hash"
+    doc = "Unique transition identifier equivalent to the state transition commitment hash. \
+           Identifiers are ordered lexicographically over their hash bytes, in the same order \
+           as they are serialized."
 );
 
 /// The structure for a specific contract. Contract always have a part of the
@@ -52,8 +57,182 @@ tagged_hash!(
 /// the same Transition data structures for both, but use generic polymorphism
 /// with associated types to clearly distinguish transitions with partial and
 /// revealed data underneath.
+///
+/// Genesis is kept behind [Arc], so it can be shared with consignments and
+/// transfer batches without copying.
 pub struct Contract {
-    pub genesis: Genesis,
+    pub genesis: Arc<Genesis>,
     pub revealed: Vec<Transition>,
     pub partial: Vec<Transition>,
 }
+
+impl Contract {
+    /// Creates contract without known state transitions
+    pub fn with(genesis: impl Into<Arc<Genesis>>) -> Self {
+        Self {
+            genesis: genesis.into(),
+            revealed: vec![],
+            partial: vec![],
+        }
+    }
+
+    #[inline]
+    pub fn genesis(&self) -> &Genesis {
+        &self.genesis
+    }
+
+    /// Returns handle to the contract genesis, which may be cheaply cloned
+    #[inline]
+    pub fn shared_genesis(&self) -> Arc<Genesis> {
+        self.genesis.clone()
+    }
+
+    #[inline]
+    pub fn contract_id(&self) -> ContractId {
+        self.genesis.contract_id()
+    }
+}
+
+mod strict_encoding {
+    use super::*;
+    use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
+    use std::io;
+
+    impl StrictEncode for ContractId {
+        type Error = Error;
+
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+            self.into_inner().to_vec().strict_encode(e)
+        }
+    }
+
+    impl StrictDecode for ContractId {
+        type Error = Error;
+
+        #[inline]
+        fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+            Ok(
+                Self::from_slice(&Vec::<u8>::strict_decode(d)?).map_err(|_| {
+                    Error::DataIntegrityError("Wrong contract id data size".to_string())
+                })?,
+            )
+        }
+    }
+
+    impl StrictEncode for TransitionId {
+        type Error = Error;
+
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+            self.into_inner().to_vec().strict_encode(e)
+        }
+    }
+
+    impl StrictDecode for TransitionId {
+        type Error = Error;
+
+        #[inline]
+        fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+            Ok(
+                Self::from_slice(&Vec::<u8>::strict_decode(d)?).map_err(|_| {
+                    Error::DataIntegrityError("Wrong transition id data size".to_string())
+                })?,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::strict_encoding::strict_encode;
+    use std::collections::{BTreeSet, HashSet};
+
+    #[test]
+    fn test_id_commitments() {
+        use crate::bp;
+        use crate::client_side_validation::CommitEncode;
+        use crate::rgb::schema::test::schema;
+        use crate::rgb::{Metadata, SchemaId};
+
+        fn commit_encoded(data: impl CommitEncode) -> Vec<u8> {
+            let mut encoder = vec![];
+            data.commit_encode(&mut encoder);
+            encoder
+        }
+
+        // Each commitment must be the tagged hash of the commitment encoding,
+        // with the tag defined by the commitment type
+        let schema = schema();
+        let genesis = Genesis::with(
+            schema.schema_id(),
+            bp::Chain::Testnet3,
+            Metadata::new(),
+            bmap! {},
+            vec![],
+        );
+        let transition = Transition::with(0, Metadata::new(), bmap! {}, vec![]);
+        let (concealed_genesis, _) = genesis.split();
+        let (concealed_transition, _) = transition.split();
+
+        assert_eq!(
+            schema.schema_id(),
+            SchemaId::hash(&commit_encoded(schema.clone()))
+        );
+        assert_eq!(
+            genesis.contract_id(),
+            ContractId::hash(&commit_encoded(genesis.clone()))
+        );
+        assert_eq!(
+            concealed_genesis.contract_id(),
+            ContractId::hash(&commit_encoded(concealed_genesis.clone()))
+        );
+        assert_eq!(
+            transition.transition_id(),
+            TransitionId::hash(&commit_encoded(transition.clone()))
+        );
+        assert_eq!(
+            concealed_transition.transition_id(),
+            TransitionId::hash(&commit_encoded(concealed_transition.clone()))
+        );
+    }
+
+    #[test]
+    fn test_id_ordering() {
+        let mut bytes = [0u8; 32];
+        bytes[31] = 0xFF;
+        let low = ContractId::from_inner(bytes);
+        bytes[0] = 0x01;
+        bytes[31] = 0x00;
+        let high = ContractId::from_inner(bytes);
+        assert!(low < high);
+        assert_eq!(low.cmp(&high), low.into_inner().cmp(&high.into_inner()));
+
+        let ordered = strict_encode(&bset! { high, low }).unwrap();
+        assert_eq!(ordered, strict_encode(&vec![low, high]).unwrap());
+        assert_eq!(
+            ordered,
+            strict_encode(&vec![high, low].into_iter().collect::<HashSet<_>>()).unwrap()
+        );
+
+        let ids = (0u8..16)
+            .map(|no| TransitionId::hash(&[no]))
+            .collect::<Vec<_>>();
+        let mut sorted = ids.iter().map(|id| id.into_inner()).collect::<Vec<_>>();
+        sorted.sort();
+        assert_eq!(
+            ids.iter()
+                .cloned()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|id| id.into_inner())
+                .collect::<Vec<_>>(),
+            sorted
+        );
+        assert_eq!(
+            strict_encode(&ids.iter().cloned().collect::<HashSet<_>>()).unwrap(),
+            strict_encode(&ids.iter().cloned().collect::<BTreeSet<_>>()).unwrap()
+        );
+    }
+}
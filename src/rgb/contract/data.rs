@@ -11,30 +11,29 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use crate::client_side_validation::{commit_strategy, CommitEncodeWithStrategy, Conceal};
-use crate::strict_encoding::strict_encode;
+use crate::client_side_validation::{commit_strategy, CommitConceal, CommitEncodeWithStrategy};
+use crate::strict_encoding::{strict_encode, AsciiCharset, AsciiString, MaxLenString};
 use bitcoin::hashes::{hash160, sha256, Hash};
 use bitcoin::secp256k1;
 use core::cmp::Ordering;
 
 /// Struct using for storing Void (i.e. absent) state
-#[derive(Clone, Debug, PartialOrd, Ord, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialOrd, Ord, PartialEq, Eq, CommitEncode)]
+#[commit_encode(strategy = "conceal")]
 pub struct Void;
 
-impl Conceal for Void {
+impl CommitConceal for Void {
     type Confidential = Void;
 
     fn conceal(&self) -> Self::Confidential {
         self.clone()
     }
 }
-impl CommitEncodeWithStrategy for Void {
-    type Strategy = commit_strategy::UsingConceal;
-}
 
-#[derive(Clone, Debug, Display)]
+#[derive(Clone, Debug, Display, CommitEncode)]
 #[display_from(Debug)]
 #[non_exhaustive]
+#[commit_encode(strategy = "conceal")]
 pub enum Revealed {
     U8(u8),
     U16(u16),
@@ -62,7 +61,38 @@ pub enum Revealed {
     Secp256k1Signature(secp256k1::Signature),
 }
 
-impl Conceal for Revealed {
+#[cfg(all(test, feature = "property_tests"))]
+impl quickcheck::Arbitrary for Revealed {
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use crate::bp::arbitrary::{public_key, secret_key};
+        use quickcheck::Arbitrary;
+        match u8::arbitrary(g) % 16 {
+            0 => Revealed::U8(u8::arbitrary(g)),
+            1 => Revealed::U16(u16::arbitrary(g)),
+            2 => Revealed::U32(u32::arbitrary(g)),
+            3 => Revealed::U64(u64::arbitrary(g)),
+            4 => Revealed::I8(i8::arbitrary(g)),
+            5 => Revealed::I16(i16::arbitrary(g)),
+            6 => Revealed::I32(i32::arbitrary(g)),
+            7 => Revealed::I64(i64::arbitrary(g)),
+            8 => Revealed::F32(f32::arbitrary(g)),
+            9 => Revealed::F64(f64::arbitrary(g)),
+            10 => Revealed::Bytes(Vec::arbitrary(g)),
+            11 => Revealed::String(String::arbitrary(g)),
+            12 => Revealed::Hash160(hash160::Hash::hash(&Vec::<u8>::arbitrary(g))),
+            13 => Revealed::Sha256(sha256::Hash::hash(&Vec::<u8>::arbitrary(g))),
+            14 => Revealed::Secp256k1Pubkey(public_key(g)),
+            _ => {
+                let secp = secp256k1::Secp256k1::signing_only();
+                let msg = sha256::Hash::hash(&Vec::<u8>::arbitrary(g));
+                let msg = secp256k1::Message::from_slice(&msg[..]).expect("hash is 32 bytes");
+                Revealed::Secp256k1Signature(secp.sign(&msg, &secret_key(g)))
+            }
+        }
+    }
+}
+
+impl CommitConceal for Revealed {
     type Confidential = Confidential;
 
     fn conceal(&self) -> Self::Confidential {
@@ -71,9 +101,6 @@ impl Conceal for Revealed {
         )
     }
 }
-impl CommitEncodeWithStrategy for Revealed {
-    type Strategy = commit_strategy::UsingConceal;
-}
 
 impl PartialEq for Revealed {
     fn eq(&self, other: &Self) -> bool {
@@ -105,6 +132,20 @@ impl Ord for Revealed {
     }
 }
 
+impl<const MAX: usize> From<MaxLenString<MAX>> for Revealed {
+    #[inline]
+    fn from(s: MaxLenString<MAX>) -> Self {
+        Revealed::String(s.into_inner())
+    }
+}
+
+impl<C: AsciiCharset, const MAX: usize> From<AsciiString<C, MAX>> for Revealed {
+    #[inline]
+    fn from(s: AsciiString<C, MAX>) -> Self {
+        Revealed::String(s.into_inner())
+    }
+}
+
 /// Confidential representation of data
 ///
 /// # Security analysis
@@ -126,7 +167,6 @@ impl CommitEncodeWithStrategy for Confidential {
     type Strategy = commit_strategy::UsingStrict;
 }
 
-// TODO: Automate this with #derive macros
 pub(super) mod strict_encoding {
     use super::*;
     use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
@@ -172,66 +212,24 @@ pub(super) mod strict_encoding {
         }
     }
 
-    impl StrictEncode for Revealed {
-        type Error = Error;
-
-        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
-            Ok(match self {
-                Revealed::U8(val) => strict_encode_list!(e; EncodingTag::U8, val),
-                Revealed::U16(val) => strict_encode_list!(e; EncodingTag::U16, val),
-                Revealed::U32(val) => strict_encode_list!(e; EncodingTag::U32, val),
-                Revealed::U64(val) => strict_encode_list!(e; EncodingTag::U64, val),
-                // Value::U128(val) => strict_encode_list!(e; EncodingTag::U128, val),
-                Revealed::I8(val) => strict_encode_list!(e; EncodingTag::I8, val),
-                Revealed::I16(val) => strict_encode_list!(e; EncodingTag::I16, val),
-                Revealed::I32(val) => strict_encode_list!(e; EncodingTag::I32, val),
-                Revealed::I64(val) => strict_encode_list!(e; EncodingTag::I64, val),
-                // Value::I128(val) => strict_encode_list!(e; EncodingTag::I128, val),
-                Revealed::F32(val) => strict_encode_list!(e; EncodingTag::F32, val),
-                Revealed::F64(val) => strict_encode_list!(e; EncodingTag::F64, val),
-                Revealed::Bytes(val) => strict_encode_list!(e; EncodingTag::Bytes, val),
-                Revealed::String(val) => strict_encode_list!(e; EncodingTag::String, val),
-                Revealed::Hash160(val) => strict_encode_list!(e; EncodingTag::Ripemd160, val),
-                Revealed::Sha256(val) => strict_encode_list!(e; EncodingTag::Sha256, val),
-                Revealed::Secp256k1Pubkey(val) => {
-                    strict_encode_list!(e; EncodingTag::Secp256k1Pubkey, val)
-                }
-                Revealed::Secp256k1Signature(val) => {
-                    strict_encode_list!(e; EncodingTag::Secp256k1Signature, val)
-                }
-            })
-        }
-    }
-
-    impl StrictDecode for Revealed {
-        type Error = Error;
-
-        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
-            let format = EncodingTag::strict_decode(&mut d)?;
-            Ok(match format {
-                EncodingTag::U8 => Revealed::U8(u8::strict_decode(&mut d)?),
-                EncodingTag::U16 => Revealed::U16(u16::strict_decode(&mut d)?),
-                EncodingTag::U32 => Revealed::U32(u32::strict_decode(&mut d)?),
-                EncodingTag::U64 => Revealed::U64(u64::strict_decode(&mut d)?),
-                // EncodingTag::U128 => Value::U128(u128::strict_decode(&mut d)?),
-                EncodingTag::I8 => Revealed::I8(i8::strict_decode(&mut d)?),
-                EncodingTag::I16 => Revealed::I16(i16::strict_decode(&mut d)?),
-                EncodingTag::I32 => Revealed::I32(i32::strict_decode(&mut d)?),
-                EncodingTag::I64 => Revealed::I64(i64::strict_decode(&mut d)?),
-                // EncodingTag::I128 => Value::I128(i128::strict_decode(&mut d)?),
-                EncodingTag::F32 => Revealed::F32(f32::strict_decode(&mut d)?),
-                EncodingTag::F64 => Revealed::F64(f64::strict_decode(&mut d)?),
-                EncodingTag::Bytes => Revealed::Bytes(Vec::strict_decode(&mut d)?),
-                EncodingTag::String => Revealed::String(String::strict_decode(&mut d)?),
-                EncodingTag::Ripemd160 => Revealed::Hash160(hash160::Hash::strict_decode(&mut d)?),
-                EncodingTag::Sha256 => Revealed::Sha256(sha256::Hash::strict_decode(&mut d)?),
-                EncodingTag::Secp256k1Pubkey => {
-                    Revealed::Secp256k1Pubkey(secp256k1::PublicKey::strict_decode(&mut d)?)
-                }
-                EncodingTag::Secp256k1Signature => {
-                    Revealed::Secp256k1Signature(secp256k1::Signature::strict_decode(&mut d)?)
-                }
-            })
-        }
-    }
+    impl_tagged_enum_strict_encoding!(Revealed {
+        U8(val: u8) = EncodingTag::U8,
+        U16(val: u16) = EncodingTag::U16,
+        U32(val: u32) = EncodingTag::U32,
+        U64(val: u64) = EncodingTag::U64,
+        // U128(val: u128) = EncodingTag::U128,
+        I8(val: i8) = EncodingTag::I8,
+        I16(val: i16) = EncodingTag::I16,
+        I32(val: i32) = EncodingTag::I32,
+        I64(val: i64) = EncodingTag::I64,
+        // I128(val: i128) = EncodingTag::I128,
+        F32(val: f32) = EncodingTag::F32,
+        F64(val: f64) = EncodingTag::F64,
+        Bytes(val: Vec<u8>) = EncodingTag::Bytes,
+        String(val: String) = EncodingTag::String,
+        Hash160(val: hash160::Hash) = EncodingTag::Ripemd160,
+        Sha256(val: sha256::Hash) = EncodingTag::Sha256,
+        Secp256k1Pubkey(val: secp256k1::PublicKey) = EncodingTag::Secp256k1Pubkey,
+        Secp256k1Signature(val: secp256k1::Signature) = EncodingTag::Secp256k1Signature,
+    });
 }
@@ -13,6 +13,7 @@
 
 pub mod amount;
 mod assignments;
+mod conditions;
 mod contract;
 pub mod data;
 #[macro_use]
@@ -22,7 +23,29 @@ pub mod seal;
 
 pub use amount::Amount;
 pub use assignments::{Assignment, AssignmentsVariant};
+pub use conditions::HashTimeLock;
 pub use contract::{Contract, ContractId, TransitionId};
 pub use field::{FieldData, Metadata};
-pub use nodes::{Genesis, Node, Transition};
+pub use nodes::{
+    ConcealedGenesis, ConcealedTransition, Genesis, Node, NodeReveal, RevealMismatch, Transition,
+};
 pub use seal::SealDefinition;
+
+#[cfg(all(test, feature = "property_tests"))]
+mod test {
+    use super::*;
+    use crate::strict_encoding::test::encoding_roundtrip;
+
+    quickcheck! {
+        fn prop_seal(val: seal::Revealed) -> bool { encoding_roundtrip(&val) }
+        fn prop_data(val: data::Revealed) -> bool { encoding_roundtrip(&val) }
+        fn prop_genesis(val: Genesis) -> bool { encoding_roundtrip(&val) }
+        fn prop_transition(val: Transition) -> bool { encoding_roundtrip(&val) }
+        fn prop_contract_id(val: Genesis) -> bool {
+            encoding_roundtrip(&val.contract_id())
+        }
+        fn prop_transition_id(val: Transition) -> bool {
+            encoding_roundtrip(&val.transition_id())
+        }
+    }
+}
@@ -11,11 +11,16 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
-use super::{data, AssignmentsVariant, SealDefinition};
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1;
+
+use super::{data, seal, AssignmentsVariant, SealDefinition};
 use crate::bp;
-use crate::client_side_validation::{commit_strategy, CommitEncodeWithStrategy, ConsensusCommit};
+use crate::client_side_validation::{
+    commit_strategy, CommitConceal, CommitEncodeWithStrategy, ConsensusCommit,
+};
 use crate::rgb::{
     schema, Assignment, ContractId, FieldData, Metadata, SchemaId, SimplicityScript, TransitionId,
 };
@@ -25,6 +30,11 @@ impl CommitEncodeWithStrategy for Assignments {
     type Strategy = commit_strategy::Merklization;
 }
 
+/// Seals of the assignments defined by the previous nodes, which are closed
+/// by the state transition, per assignment type. Seals are always kept in
+/// their confidential form, so the transition does not reveal them.
+pub type ClosedSeals = BTreeMap<schema::AssignmentsType, BTreeSet<seal::Confidential>>;
+
 pub trait Node {
     fn metadata(&self) -> &Metadata;
     fn assignments(&self) -> &Assignments;
@@ -118,11 +128,12 @@ pub trait Node {
     }
 }
 
-#[derive(Clone, Debug, Display)]
+#[derive(Clone, Debug, Display, CommitEncode)]
 #[display_from(Debug)]
+#[commit_encode(strategy = "conceal")]
 pub struct Genesis {
     schema_id: SchemaId,
-    network: bp::Network,
+    chain: bp::Chain,
     metadata: Metadata,
     // Since genesis relies on the same data structure as Transition, it uses
     // `homorphic_factor` value for homomorphic state data; which does not make
@@ -132,45 +143,213 @@ pub struct Genesis {
     script: SimplicityScript,
 }
 
-#[derive(Clone, Debug, Display, Default)]
+#[derive(Clone, Debug, Display, Default, CommitEncode)]
 #[display_from(Debug)]
+#[commit_encode(strategy = "conceal")]
 pub struct Transition {
     type_id: schema::TransitionType,
     metadata: Metadata,
+    closes: ClosedSeals,
+    assignments: Assignments,
+    script: SimplicityScript,
+}
+
+/// Commitment-relevant data of the genesis, which are used for computing
+/// [ContractId]. All assignments are kept in their confidential form, so the
+/// structure never contains revealed seals, blinding factors or state.
+#[derive(Clone, Debug, Display, CommitEncode)]
+#[display_from(Debug)]
+#[commit_encode(strategy = "strict")]
+pub struct ConcealedGenesis {
+    schema_id: SchemaId,
+    chain: bp::Chain,
+    metadata: Metadata,
+    assignments: Assignments,
+    script: SimplicityScript,
+}
+
+/// Commitment-relevant data of the state transition, which are used for
+/// computing [TransitionId]. All assignments are kept in their confidential
+/// form, so the structure never contains revealed seals, blinding factors
+/// or state.
+#[derive(Clone, Debug, Display, CommitEncode)]
+#[display_from(Debug)]
+#[commit_encode(strategy = "strict")]
+pub struct ConcealedTransition {
+    type_id: schema::TransitionType,
+    metadata: Metadata,
+    closes: ClosedSeals,
     assignments: Assignments,
     script: SimplicityScript,
 }
 
+/// Reveal-only companion data of a node: revealed seals together with their
+/// blinding factors and revealed assigned state. The data are never
+/// committed to and have to be disclosed only to the parties which need
+/// them.
+#[derive(Clone, Debug, Display, Default)]
+#[display_from(Debug)]
+pub struct NodeReveal {
+    assignments: Assignments,
+}
+
+/// Revealed data do not match node assignments of the given type
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display_from(Debug)]
+pub struct RevealMismatch(pub schema::AssignmentsType);
+
+impl NodeReveal {
+    #[inline]
+    pub fn assignments(&self) -> &Assignments {
+        &self.assignments
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.assignments.is_empty()
+    }
+
+    fn extract(assignments: &Assignments) -> Self {
+        Self {
+            assignments: assignments
+                .iter()
+                .map(|(assignment_type, variant)| (*assignment_type, variant.revealed_only()))
+                .filter(|(_, variant)| !variant.is_empty())
+                .collect(),
+        }
+    }
+
+    fn apply(&self, assignments: &Assignments) -> Result<Assignments, RevealMismatch> {
+        let mut assignments = assignments.clone();
+        for (assignment_type, revealed) in &self.assignments {
+            let variant = assignments
+                .get_mut(assignment_type)
+                .ok_or(RevealMismatch(*assignment_type))?;
+            *variant = variant
+                .reveal(revealed)
+                .ok_or(RevealMismatch(*assignment_type))?;
+        }
+        Ok(assignments)
+    }
+}
+
+fn conceal_assignments(assignments: &Assignments) -> Assignments {
+    assignments
+        .iter()
+        .map(|(assignment_type, variant)| (*assignment_type, variant.conceal_all()))
+        .collect()
+}
+
 impl Genesis {
     #[inline]
     pub fn contract_id(&self) -> ContractId {
         self.clone().consensus_commit()
     }
+
+    /// Splits genesis into commitment-relevant data and reveal-only data
+    pub fn split(&self) -> (ConcealedGenesis, NodeReveal) {
+        (self.conceal(), NodeReveal::extract(&self.assignments))
+    }
+
+    /// Reconstructs genesis from its commitment-relevant and reveal-only
+    /// parts
+    pub fn join(concealed: ConcealedGenesis, reveal: &NodeReveal) -> Result<Self, RevealMismatch> {
+        Ok(Self {
+            assignments: reveal.apply(&concealed.assignments)?,
+            schema_id: concealed.schema_id,
+            chain: concealed.chain,
+            metadata: concealed.metadata,
+            script: concealed.script,
+        })
+    }
 }
 
-impl CommitEncodeWithStrategy for Genesis {
-    type Strategy = commit_strategy::UsingStrict;
+impl CommitConceal for Genesis {
+    type Confidential = ConcealedGenesis;
+
+    fn conceal(&self) -> ConcealedGenesis {
+        ConcealedGenesis {
+            schema_id: self.schema_id,
+            chain: self.chain,
+            metadata: self.metadata.clone(),
+            assignments: conceal_assignments(&self.assignments),
+            script: self.script.clone(),
+        }
+    }
 }
 
 impl ConsensusCommit for Genesis {
     type Commitment = ContractId;
 }
 
+impl ConcealedGenesis {
+    #[inline]
+    pub fn contract_id(&self) -> ContractId {
+        self.clone().consensus_commit()
+    }
+}
+
+impl ConsensusCommit for ConcealedGenesis {
+    type Commitment = ContractId;
+}
+
 impl Transition {
     #[inline]
     pub fn transition_id(&self) -> TransitionId {
         self.clone().consensus_commit()
     }
+
+    /// Splits state transition into commitment-relevant data and reveal-only
+    /// data
+    pub fn split(&self) -> (ConcealedTransition, NodeReveal) {
+        (self.conceal(), NodeReveal::extract(&self.assignments))
+    }
+
+    /// Reconstructs state transition from its commitment-relevant and
+    /// reveal-only parts
+    pub fn join(
+        concealed: ConcealedTransition,
+        reveal: &NodeReveal,
+    ) -> Result<Self, RevealMismatch> {
+        Ok(Self {
+            assignments: reveal.apply(&concealed.assignments)?,
+            type_id: concealed.type_id,
+            metadata: concealed.metadata,
+            closes: concealed.closes,
+            script: concealed.script,
+        })
+    }
 }
 
-impl CommitEncodeWithStrategy for Transition {
-    type Strategy = commit_strategy::UsingStrict;
+impl CommitConceal for Transition {
+    type Confidential = ConcealedTransition;
+
+    fn conceal(&self) -> ConcealedTransition {
+        ConcealedTransition {
+            type_id: self.type_id,
+            metadata: self.metadata.clone(),
+            closes: self.closes.clone(),
+            assignments: conceal_assignments(&self.assignments),
+            script: self.script.clone(),
+        }
+    }
 }
 
 impl ConsensusCommit for Transition {
     type Commitment = TransitionId;
 }
 
+impl ConcealedTransition {
+    #[inline]
+    pub fn transition_id(&self) -> TransitionId {
+        self.clone().consensus_commit()
+    }
+}
+
+impl ConsensusCommit for ConcealedTransition {
+    type Commitment = TransitionId;
+}
+
 impl Node for Genesis {
     #[inline]
     fn metadata(&self) -> &Metadata {
@@ -201,33 +380,57 @@ impl Node for Transition {
     }
 }
 
+impl Node for ConcealedTransition {
+    #[inline]
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+    #[inline]
+    fn assignments(&self) -> &Assignments {
+        &self.assignments
+    }
+    #[inline]
+    fn script(&self) -> &SimplicityScript {
+        &self.script
+    }
+}
+
 impl Genesis {
     pub fn with(
         schema_id: SchemaId,
-        network: bp::Network,
+        chain: bp::Chain,
         metadata: Metadata,
         assignments: Assignments,
         script: SimplicityScript,
     ) -> Self {
         Self {
             schema_id,
-            network,
+            chain,
             metadata,
             assignments,
             script,
         }
     }
 
+    /// Returns id of the schema the genesis commits to. Since the schema id
+    /// is a part of genesis consensus commitment, it is also committed into
+    /// the [ContractId]
     #[inline]
-    #[allow(dead_code)]
     pub fn schema_id(&self) -> SchemaId {
         self.schema_id
     }
 
+    /// Returns the chain the contract lives on
     #[inline]
-    #[allow(dead_code)]
-    pub fn network(&self) -> bp::Network {
-        self.network
+    pub fn chain(&self) -> bp::Chain {
+        self.chain
+    }
+
+    /// Returns P2P network of the contract chain, if the chain has one (see
+    /// [bp::Chain::network])
+    #[inline]
+    pub fn network(&self) -> Option<bp::Network> {
+        self.chain.network()
     }
 }
 
@@ -241,6 +444,7 @@ impl Transition {
         Self {
             type_id,
             metadata,
+            closes: ClosedSeals::new(),
             assignments,
             script,
         }
@@ -251,12 +455,254 @@ impl Transition {
     pub fn type_id(&self) -> schema::TransitionType {
         self.type_id
     }
+
+    /// Returns seals of the previous assignments closed by the transition
+    #[inline]
+    pub fn closes(&self) -> &ClosedSeals {
+        &self.closes
+    }
+
+    /// Adds seal of the previous assignment of the given type to the set of
+    /// seals closed by the transition. Must be called before the transition
+    /// is signed, since it changes the transition id.
+    pub fn close_seal(
+        &mut self,
+        assignment_type: schema::AssignmentsType,
+        seal: seal::Confidential,
+    ) {
+        self.closes
+            .entry(assignment_type)
+            .or_insert_with(BTreeSet::new)
+            .insert(seal);
+    }
+
+    /// Message signed by the owners of locked assignments closed by this
+    /// transition. It commits to the transition with all data of the
+    /// metadata field holding lock signatures removed, so the signatures may
+    /// be added in any order and do not commit to each other.
+    pub fn lock_sighash(&self, signature_field: schema::FieldType) -> secp256k1::Message {
+        let mut transition = self.clone();
+        transition.metadata.remove(&signature_field);
+        secp256k1::Message::from_slice(&transition.transition_id().into_inner())
+            .expect("Transition id is a 32-byte hash and is always a valid message")
+    }
+
+    /// Returns all signatures present in the metadata field used for lock
+    /// signatures
+    pub fn lock_signatures(&self, signature_field: schema::FieldType) -> Vec<secp256k1::Signature> {
+        self.metadata
+            .get(&signature_field)
+            .map(|set| {
+                set.iter()
+                    .filter_map(|data| match data {
+                        data::Revealed::Secp256k1Signature(sig) => Some(*sig),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Signs the transition with the key locking one of the closed
+    /// assignments and adds the signature to the provided metadata field.
+    /// Must be called after all other transition data are finalized, since
+    /// any change to them invalidates the signature.
+    pub fn sign_lock<C: secp256k1::Signing>(
+        &mut self,
+        secp: &secp256k1::Secp256k1<C>,
+        signature_field: schema::FieldType,
+        seckey: &secp256k1::SecretKey,
+    ) -> secp256k1::Signature {
+        let signature = secp.sign(&self.lock_sighash(signature_field), seckey);
+        self.metadata
+            .entry(signature_field)
+            .or_insert_with(BTreeSet::new)
+            .insert(data::Revealed::Secp256k1Signature(signature));
+        signature
+    }
+
+    /// Returns all byte strings present in the metadata field used for hash
+    /// lock preimages
+    pub fn condition_preimages(&self, preimage_field: schema::FieldType) -> Vec<Vec<u8>> {
+        self.metadata
+            .get(&preimage_field)
+            .map(|set| {
+                set.iter()
+                    .filter_map(|data| match data {
+                        data::Revealed::Bytes(bytes) => Some(bytes.clone()),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Claims conditionally-owned assignment closed by the transition: adds
+    /// the hash lock preimage and signs the transition with the receiver
+    /// key. Must be called after all other transition data are finalized.
+    pub fn sign_claim<C: secp256k1::Signing>(
+        &mut self,
+        secp: &secp256k1::Secp256k1<C>,
+        fields: schema::ConditionFields,
+        preimage: &[u8; 32],
+        seckey: &secp256k1::SecretKey,
+    ) -> secp256k1::Signature {
+        self.metadata
+            .entry(fields.preimage)
+            .or_insert_with(BTreeSet::new)
+            .insert(data::Revealed::Bytes(preimage.to_vec()));
+        self.sign_lock(secp, fields.signature, seckey)
+    }
+
+    /// Refunds conditionally-owned assignment closed by the transition by
+    /// signing it with the sender key. The witness transaction must be
+    /// time-locked with [super::HashTimeLock::prepare_refund_witness].
+    #[inline]
+    pub fn sign_refund<C: secp256k1::Signing>(
+        &mut self,
+        secp: &secp256k1::Secp256k1<C>,
+        fields: schema::ConditionFields,
+        seckey: &secp256k1::SecretKey,
+    ) -> secp256k1::Signature {
+        self.sign_lock(secp, fields.signature, seckey)
+    }
+}
+
+#[cfg(all(test, feature = "property_tests"))]
+mod arbitrary {
+    use super::*;
+    use crate::bp::blind::OutpointReveal;
+    use crate::rgb::data::Void;
+    use quickcheck::{Arbitrary, Gen};
+
+    fn metadata<G: Gen>(g: &mut G) -> Metadata {
+        Vec::<(u16, Vec<data::Revealed>)>::arbitrary(g)
+            .into_iter()
+            .map(|(field_type, values)| (field_type as usize, values.into_iter().collect()))
+            .collect()
+    }
+
+    fn assignments<G: Gen>(g: &mut G) -> Assignments {
+        Vec::<(u16, Vec<(bool, OutpointReveal)>)>::arbitrary(g)
+            .into_iter()
+            .map(|(assignment_type, seals)| {
+                let set = seals
+                    .into_iter()
+                    .map(|(revealed, outpoint)| {
+                        if revealed {
+                            Assignment::Revealed {
+                                seal_definition: SealDefinition::arbitrary(g),
+                                assigned_state: Void,
+                            }
+                        } else {
+                            Assignment::Confidential {
+                                seal_definition: outpoint.conceal(),
+                                assigned_state: Void,
+                            }
+                        }
+                    })
+                    .collect();
+                (assignment_type as usize, AssignmentsVariant::Void(set))
+            })
+            .collect()
+    }
+
+    impl Arbitrary for Genesis {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            Genesis::with(
+                SchemaId::hash(&Vec::<u8>::arbitrary(g)),
+                {
+                    let chains = bp::Chain::all_known();
+                    chains[usize::arbitrary(g) % chains.len()]
+                },
+                metadata(g),
+                assignments(g),
+                Vec::arbitrary(g),
+            )
+        }
+    }
+
+    impl Arbitrary for Transition {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            let mut transition = Transition::with(
+                u16::arbitrary(g) as usize,
+                metadata(g),
+                assignments(g),
+                Vec::arbitrary(g),
+            );
+            for (assignment_type, outpoint) in Vec::<(u16, OutpointReveal)>::arbitrary(g) {
+                transition.close_seal(assignment_type as usize, outpoint.conceal());
+            }
+            transition
+        }
+    }
 }
 
 mod strict_encoding {
     use super::*;
-    use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
-    use std::io;
+    use crate::rgb::validation::{Limit, LimitViolation, NodeLimits};
+    use crate::strict_encoding::{
+        consume_items, strict_encode, BoundedReader, DepthGuard, Error, StrictDecode, StrictEncode,
+    };
+    use std::io::{self, Read};
+
+    // Node data are decoded enforcing `NodeLimits::CEILING` before memory for
+    // the data is allocated: metadata are read from the reader bounded by
+    // the maximum metadata size, while the number of assignments and script
+    // length are checked against their length prefixes.
+
+    fn decode_metadata<D: io::Read>(d: D) -> Result<Metadata, Error> {
+        let max = NodeLimits::CEILING.max_metadata_size;
+        let mut reader = BoundedReader::new(d, max);
+        Metadata::strict_decode(&mut reader).map_err(|err| match reader.exceeded_by() {
+            Some(actual) => LimitViolation {
+                limit: Limit::MetadataSize,
+                max,
+                actual,
+            }
+            .into(),
+            None => err,
+        })
+    }
+
+    fn decode_assignments<D: io::Read>(mut d: D) -> Result<Assignments, Error> {
+        let max = NodeLimits::CEILING.max_assignments;
+        let _depth = DepthGuard::enter()?;
+        let len = usize::strict_decode(&mut d)?;
+        consume_items(len)?;
+        let mut assignments = Assignments::new();
+        let mut count = 0usize;
+        for _ in 0..len {
+            let assignments_type = schema::AssignmentsType::strict_decode(&mut d)?;
+            let variant = AssignmentsVariant::strict_decode_checked(&mut d, |len| {
+                count += len;
+                if count > max {
+                    Err(LimitViolation {
+                        limit: Limit::AssignmentsCount,
+                        max,
+                        actual: count,
+                    })?
+                }
+                Ok(())
+            })?;
+            assignments.insert(assignments_type, variant);
+        }
+        Ok(assignments)
+    }
+
+    fn decode_script<D: io::Read>(mut d: D) -> Result<SimplicityScript, Error> {
+        let max = NodeLimits::CEILING.max_script_size;
+        let len = usize::strict_decode(&mut d)?;
+        if len > max {
+            Err(LimitViolation {
+                limit: Limit::ScriptSize,
+                max,
+                actual: len,
+            })?
+        }
+        let prefix = strict_encode(&len)?;
+        SimplicityScript::strict_decode(prefix.as_slice().chain(d))
+    }
 
     impl StrictEncode for Genesis {
         type Error = Error;
@@ -264,7 +710,7 @@ mod strict_encoding {
         fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
             Ok(strict_encode_list!(e;
                     self.schema_id,
-                    self.network,
+                    self.chain,
                     self.metadata,
                     self.assignments,
                     self.script))
@@ -276,11 +722,11 @@ mod strict_encoding {
 
         fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
             Ok(Self {
-                schema_id: SchemaId::strict_decode(&mut d)?,
-                network: bp::Network::strict_decode(&mut d)?,
-                metadata: Metadata::strict_decode(&mut d)?,
-                assignments: Assignments::strict_decode(&mut d)?,
-                script: SimplicityScript::strict_decode(&mut d)?,
+                schema_id: SchemaId::strict_decode(&mut d).map_err(|e| e.in_field("schema_id"))?,
+                chain: bp::Chain::strict_decode(&mut d).map_err(|e| e.in_field("chain"))?,
+                metadata: decode_metadata(&mut d).map_err(|e| e.in_field("metadata"))?,
+                assignments: decode_assignments(&mut d).map_err(|e| e.in_field("assignments"))?,
+                script: decode_script(&mut d).map_err(|e| e.in_field("script"))?,
             })
         }
     }
@@ -292,6 +738,7 @@ mod strict_encoding {
             Ok(strict_encode_list!(e;
                     self.type_id,
                     self.metadata,
+                    self.closes,
                     self.assignments,
                     self.script))
         }
@@ -302,11 +749,255 @@ mod strict_encoding {
 
         fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
             Ok(Self {
-                type_id: schema::TransitionType::strict_decode(&mut d)?,
-                metadata: Metadata::strict_decode(&mut d)?,
-                assignments: Assignments::strict_decode(&mut d)?,
-                script: SimplicityScript::strict_decode(&mut d)?,
+                type_id: schema::TransitionType::strict_decode(&mut d)
+                    .map_err(|e| e.in_field("type_id"))?,
+                metadata: decode_metadata(&mut d).map_err(|e| e.in_field("metadata"))?,
+                closes: ClosedSeals::strict_decode(&mut d).map_err(|e| e.in_field("closes"))?,
+                assignments: decode_assignments(&mut d).map_err(|e| e.in_field("assignments"))?,
+                script: decode_script(&mut d).map_err(|e| e.in_field("script"))?,
+            })
+        }
+    }
+
+    impl StrictEncode for ConcealedGenesis {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(strict_encode_list!(e;
+                    self.schema_id,
+                    self.chain,
+                    self.metadata,
+                    self.assignments,
+                    self.script))
+        }
+    }
+
+    impl StrictDecode for ConcealedGenesis {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(Self {
+                schema_id: SchemaId::strict_decode(&mut d).map_err(|e| e.in_field("schema_id"))?,
+                chain: bp::Chain::strict_decode(&mut d).map_err(|e| e.in_field("chain"))?,
+                metadata: Metadata::strict_decode(&mut d).map_err(|e| e.in_field("metadata"))?,
+                assignments: decode_concealed(&mut d).map_err(|e| e.in_field("assignments"))?,
+                script: SimplicityScript::strict_decode(&mut d)
+                    .map_err(|e| e.in_field("script"))?,
+            })
+        }
+    }
+
+    impl StrictEncode for ConcealedTransition {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(strict_encode_list!(e;
+                    self.type_id,
+                    self.metadata,
+                    self.closes,
+                    self.assignments,
+                    self.script))
+        }
+    }
+
+    impl StrictDecode for ConcealedTransition {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(Self {
+                type_id: schema::TransitionType::strict_decode(&mut d)
+                    .map_err(|e| e.in_field("type_id"))?,
+                metadata: Metadata::strict_decode(&mut d).map_err(|e| e.in_field("metadata"))?,
+                closes: ClosedSeals::strict_decode(&mut d).map_err(|e| e.in_field("closes"))?,
+                assignments: decode_concealed(&mut d).map_err(|e| e.in_field("assignments"))?,
+                script: SimplicityScript::strict_decode(&mut d)
+                    .map_err(|e| e.in_field("script"))?,
+            })
+        }
+    }
+
+    fn decode_concealed(d: impl io::Read) -> Result<Assignments, Error> {
+        let assignments = Assignments::strict_decode(d)?;
+        if !assignments.values().all(AssignmentsVariant::is_concealed) {
+            Err(Error::DataIntegrityError(
+                "Concealed node data contain revealed assignments".to_string(),
+            ))?
+        }
+        Ok(assignments)
+    }
+
+    impl StrictEncode for NodeReveal {
+        type Error = Error;
+
+        #[inline]
+        fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+            self.assignments.strict_encode(e)
+        }
+    }
+
+    impl StrictDecode for NodeReveal {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+            let assignments = Assignments::strict_decode(d)?;
+            if !assignments.values().all(AssignmentsVariant::is_revealed) {
+                Err(Error::DataIntegrityError(
+                    "Node reveal data contain confidential assignments".to_string(),
+                ))?
+            }
+            Ok(Self { assignments })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::blind::OutpointReveal;
+    use crate::rgb::schema::test::schema;
+    use crate::strict_encoding::{strict_decode, strict_encode};
+    use bitcoin::Txid;
+
+    #[test]
+    fn test_contract_id_commits_to_schema() {
+        let schema_id = schema().schema_id();
+        let genesis = Genesis::with(
+            schema_id,
+            bp::Chain::Testnet3,
+            Metadata::new(),
+            Assignments::new(),
+            vec![],
+        );
+        assert_eq!(genesis.schema_id(), schema_id);
+        assert_eq!(genesis.chain(), bp::Chain::Testnet3);
+        assert_eq!(genesis.network(), Some(bp::Chain::Testnet3));
+
+        // Liquid shares network magic with regtest, but is a distinct chain
+        let liquid = Genesis::with(
+            schema_id,
+            bp::Chain::Liquid,
+            Metadata::new(),
+            Assignments::new(),
+            vec![],
+        );
+        let decoded: Genesis = strict_decode(&strict_encode(&liquid).unwrap()).unwrap();
+        assert_eq!(decoded.chain(), bp::Chain::Liquid);
+        assert_eq!(decoded.network(), None);
+        assert_eq!(decoded.contract_id(), liquid.contract_id());
+        assert_ne!(liquid.contract_id(), genesis.contract_id());
+
+        let other = Genesis::with(
+            SchemaId::default(),
+            bp::Chain::Testnet3,
+            Metadata::new(),
+            Assignments::new(),
+            vec![],
+        );
+        assert_ne!(genesis.contract_id(), other.contract_id());
+    }
+
+    #[test]
+    fn test_reveal_separation() {
+        let seal = SealDefinition::TxOutpoint(OutpointReveal {
+            blinding: 0xDEADBEEF,
+            txid: Txid::default(),
+            vout: 1,
+        });
+        let assignments = bmap! {
+            0 => AssignmentsVariant::Void(bset! {
+                Assignment::Revealed {
+                    seal_definition: seal.clone(),
+                    assigned_state: data::Void,
+                }
+            }),
+            1 => AssignmentsVariant::Hashed(bset! {
+                Assignment::Revealed {
+                    seal_definition: seal,
+                    assigned_state: data::Revealed::U64(42),
+                }
             })
+            .conceal_all()
+        };
+        let transition = Transition::with(0, Metadata::new(), assignments, vec![]);
+        let (concealed, reveal) = transition.split();
+        assert_eq!(concealed.transition_id(), transition.transition_id());
+        assert!(concealed.assignments.values().all(|a| a.is_concealed()));
+        assert_eq!(reveal.assignments().keys().collect::<Vec<_>>(), vec![&0]);
+
+        let data = strict_encode(&concealed).unwrap();
+        assert!(!data
+            .windows(4)
+            .any(|window| window == 0xDEADBEEFu32.to_le_bytes()));
+        let concealed: ConcealedTransition = strict_decode(&data).unwrap();
+        assert!(
+            strict_decode::<ConcealedTransition>(&strict_encode(&transition).unwrap()).is_err()
+        );
+        assert!(
+            strict_decode::<NodeReveal>(&strict_encode(&concealed.assignments).unwrap()).is_err()
+        );
+
+        let reveal: NodeReveal = strict_decode(&strict_encode(&reveal).unwrap()).unwrap();
+        let restored = Transition::join(concealed, &reveal).unwrap();
+        assert_eq!(
+            strict_encode(&restored).unwrap(),
+            strict_encode(&transition).unwrap()
+        );
+
+        let (other, _) = Transition::with(1, Metadata::new(), bmap! {}, vec![]).split();
+        assert_eq!(
+            Transition::join(other, &reveal).unwrap_err(),
+            RevealMismatch(0)
+        );
+
+        let genesis = Genesis::with(
+            SchemaId::default(),
+            bp::Chain::Testnet3,
+            Metadata::new(),
+            transition.assignments().clone(),
+            vec![],
+        );
+        let (concealed, reveal) = genesis.split();
+        assert_eq!(concealed.contract_id(), genesis.contract_id());
+        assert_eq!(
+            Genesis::join(concealed, &reveal).unwrap().contract_id(),
+            genesis.contract_id()
+        );
+    }
+
+    #[test]
+    fn test_decoding_limits() {
+        use crate::rgb::validation::NodeLimits;
+        use crate::strict_encoding::Error;
+
+        // Metadata exceeding the limit; the data after the limit are never
+        // read by the decoder
+        let max = NodeLimits::CEILING.max_metadata_size;
+        let items = (max / 0xFFFF + 1) as u16;
+        let mut data = vec![0u8, 0, 1, 0, 0, 0];
+        data.extend(&items.to_le_bytes());
+        for no in 0..items {
+            data.push(0b_0010_0000);
+            data.extend(&[0xFF, 0xFF]);
+            data.extend(vec![no as u8; 0xFFFF]);
         }
+        let err = strict_decode::<Transition>(&data).unwrap_err();
+        assert_eq!(err.context().unwrap().path, vec!["metadata"]);
+        assert!(matches!(
+            err.root_cause(),
+            Error::ValueOutOfRange(limit, _, actual)
+                if limit == "MetadataSize" && *actual > max as u64
+        ));
+
+        // Two assignment types with 1 and 0xFFFF assignments: the limit is
+        // checked before the second set is decoded, so its data may be absent
+        let mut data = vec![0u8, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 1, 0, 0];
+        data.extend(&[0u8; 32]);
+        data.extend(&[1u8, 0, 0, 0xFF, 0xFF]);
+        let err = strict_decode::<Transition>(&data).unwrap_err();
+        assert_eq!(err.context().unwrap().path, vec!["assignments"]);
+        assert!(matches!(
+            err.root_cause(),
+            Error::ValueOutOfRange(limit, _, 0x10000) if limit == "AssignmentsCount"
+        ));
     }
 }
@@ -11,8 +11,8 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use crate::bp::blind::{OutpointHash, OutpointReveal};
-use crate::client_side_validation::{commit_strategy, CommitEncodeWithStrategy, Conceal};
+use crate::bp::blind::{OutpointHash, OutpointReveal, VoutOverflow};
+use crate::client_side_validation::CommitConceal;
 
 use bitcoin::{OutPoint, Txid};
 use core::convert::TryFrom;
@@ -22,16 +22,32 @@ pub type Confidential = OutpointHash;
 /// Convenience type name useful for defning new seals
 pub type SealDefinition = Revealed;
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Display)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Display, CommitEncode)]
 #[display_from(Debug)]
+#[commit_encode(strategy = "conceal")]
 pub enum Revealed {
     /// Seal that is revealed
     TxOutpoint(OutpointReveal),
     /// Seal contained within the witness transaction
-    WitnessVout { vout: u16, blinding: u32 },
+    WitnessVout { vout: u16, blinding: u64 },
 }
 
-impl Conceal for Revealed {
+#[cfg(all(test, feature = "property_tests"))]
+impl quickcheck::Arbitrary for Revealed {
+    fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        use quickcheck::Arbitrary;
+        if bool::arbitrary(g) {
+            Revealed::TxOutpoint(OutpointReveal::arbitrary(g))
+        } else {
+            Revealed::WitnessVout {
+                vout: u16::arbitrary(g),
+                blinding: u64::arbitrary(g),
+            }
+        }
+    }
+}
+
+impl CommitConceal for Revealed {
     type Confidential = Confidential;
 
     fn conceal(&self) -> Confidential {
@@ -46,8 +62,29 @@ impl Conceal for Revealed {
         }
     }
 }
-impl CommitEncodeWithStrategy for Revealed {
-    type Strategy = commit_strategy::UsingConceal;
+
+/// Seal definition is equal to the confidential seal if it conceals to it
+impl PartialEq<Confidential> for Revealed {
+    #[inline]
+    fn eq(&self, other: &Confidential) -> bool {
+        self.conceal() == *other
+    }
+}
+
+impl PartialEq<Revealed> for Confidential {
+    #[inline]
+    fn eq(&self, other: &Revealed) -> bool {
+        other == self
+    }
+}
+
+impl Revealed {
+    /// Defines seal over the transaction outpoint with random blinding
+    /// factor
+    #[inline]
+    pub fn with_outpoint(outpoint: OutPoint) -> Result<Self, VoutOverflow> {
+        OutpointReveal::with_outpoint(outpoint).map(Revealed::TxOutpoint)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Display, Error)]
@@ -92,7 +129,7 @@ mod strict_encoding {
                 0u8 => Revealed::TxOutpoint(OutpointReveal::strict_decode(d)?),
                 1u8 => Revealed::WitnessVout {
                     vout: u16::strict_decode(&mut d)?,
-                    blinding: u32::strict_decode(&mut d)?,
+                    blinding: u64::strict_decode(&mut d)?,
                 },
                 invalid => Err(Error::EnumValueNotKnown(
                     "seal::Confidential".to_string(),
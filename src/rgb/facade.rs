@@ -0,0 +1,279 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Facade exposing high-level RGB operations as plain functions for command
+//! line tools and FFI layers. All complex inputs and outputs are passed in
+//! their strict-encoded form, so the integrations depend only on the stable
+//! data serialization and not on the internal library APIs.
+
+use bitcoin::Txid;
+use std::sync::Arc;
+
+use super::{
+    schema::TransitionType, stash::transition_slot, validation, Anchor, Consignment, ContractId,
+    Genesis, Schema, SchemaId, SealDefinition, TransferBatch, Transition, TransitionId, TxRef,
+    TxRefResolver,
+};
+use crate::bp::{self, blind::OutpointHash, blind::OutpointReveal, dbc};
+use crate::client_side_validation::CommitConceal;
+use crate::lnpbp4::{MultiCommitBlock, MultiCommitError};
+use crate::strict_encoding::{self, strict_decode, strict_encode};
+
+/// Errors returned by the facade operations
+#[derive(Debug, Display, From, Error)]
+#[display_from(Debug)]
+pub enum Error {
+    /// Input data can't be decoded or output data can't be encoded
+    #[derive_from]
+    Encoding(strict_encoding::Error),
+
+    /// Contract data are not valid
+    #[derive_from]
+    Validation(validation::Failure),
+
+    /// State transitions can't be placed into the multimessage commitment
+    #[derive_from]
+    MultiCommit(MultiCommitError),
+
+    /// Multimessage commitment does not cover all of the transferred state
+    /// transitions
+    CommitmentMismatch,
+
+    /// Consignment references witness transaction by a short id which can't
+    /// be resolved
+    UnresolvedTxRef(bp::ShortId),
+}
+
+/// Creates new blinded outpoint for receiving the state. Returns
+/// strict-encoded [OutpointReveal], which has to be kept by the receiver,
+/// and the outpoint hash to be provided to the sender.
+pub fn blind_outpoint(txid: Txid, vout: u16) -> Result<(Vec<u8>, OutpointHash), Error> {
+    let reveal = OutpointReveal::with_random_blinding(txid, vout);
+    Ok((strict_encode(&reveal)?, reveal.conceal()))
+}
+
+/// Computes outpoint hash from the strict-encoded [OutpointReveal]
+pub fn outpoint_hash(reveal: &[u8]) -> Result<OutpointHash, Error> {
+    Ok(strict_decode::<OutpointReveal>(&reveal)?.conceal())
+}
+
+/// Computes id of the strict-encoded [Schema]
+pub fn schema_id(schema: &[u8]) -> Result<SchemaId, Error> {
+    Ok(strict_decode::<Schema>(&schema)?.schema_id())
+}
+
+/// Computes id of the contract defined by the strict-encoded [Genesis]
+pub fn contract_id(genesis: &[u8]) -> Result<ContractId, Error> {
+    Ok(strict_decode::<Genesis>(&genesis)?.contract_id())
+}
+
+/// Issues new contract under the strict-encoded [Schema] with the provided
+/// strict-encoded metadata and assignments. Returns strict-encoded
+/// [Genesis] after checking its structure against the schema.
+pub fn issue(
+    schema: &[u8],
+    chain: bp::Chain,
+    metadata: &[u8],
+    assignments: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let schema = strict_decode::<Schema>(&schema)?;
+    let genesis = Genesis::with(
+        schema.schema_id(),
+        chain,
+        strict_decode(&metadata)?,
+        strict_decode(&assignments)?,
+        vec![],
+    );
+    schema.validate_genesis_structure(&genesis)?;
+    Ok(strict_encode(&genesis)?)
+}
+
+/// Creates state transition of the given type with the provided
+/// strict-encoded metadata and assignments. Returns strict-encoded
+/// [Transition] after checking its structure against the schema.
+pub fn transition(
+    schema: &[u8],
+    transition_type: TransitionType,
+    metadata: &[u8],
+    assignments: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let schema = strict_decode::<Schema>(&schema)?;
+    let transition = Transition::with(
+        transition_type,
+        strict_decode(&metadata)?,
+        strict_decode(&assignments)?,
+        vec![],
+    );
+    schema.validate_transition_structure(&transition)?;
+    Ok(strict_encode(&transition)?)
+}
+
+fn batch(genesis: &[u8], transitions: &[u8]) -> Result<TransferBatch, Error> {
+    let genesis = Arc::new(strict_decode::<Genesis>(&genesis)?);
+    let mut batch = TransferBatch::new();
+    for transition in strict_decode::<Vec<Transition>>(&transitions)? {
+        batch.add(&genesis, transition);
+    }
+    Ok(batch)
+}
+
+/// Prepares transfer of the state with the strict-encoded list of state
+/// transitions under the contract defined by the strict-encoded genesis.
+/// Returns strict-encoded [MultiCommitBlock], which has to be embedded
+/// into the witness transaction.
+pub fn transfer(genesis: &[u8], transitions: &[u8]) -> Result<Vec<u8>, Error> {
+    Ok(strict_encode(&batch(genesis, transitions)?.commit()?)?)
+}
+
+/// Completes the transfer prepared with [transfer] after the commitment is
+/// embedded into the witness transaction. Takes the same genesis and
+/// transitions, strict-encoded commitment, [TxRef] and DBC proof, and
+/// returns strict-encoded list of consignments for the recipient of the
+/// strict-encoded list of seals.
+pub fn consign(
+    genesis: &[u8],
+    transitions: &[u8],
+    commitment: &[u8],
+    tx_ref: &[u8],
+    dbc_proof: &[u8],
+    seals: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let update = batch(genesis, transitions)?
+        .finalize(
+            strict_decode::<MultiCommitBlock>(&commitment)?,
+            strict_decode::<TxRef>(&tx_ref)?,
+            strict_decode::<dbc::Proof>(&dbc_proof)?,
+        )
+        .map_err(|_| Error::CommitmentMismatch)?;
+    let seals = strict_decode::<Vec<SealDefinition>>(&seals)?;
+    Ok(strict_encode(&update.consign(&seals))?)
+}
+
+/// Validates strict-encoded consignment against the strict-encoded schema
+pub fn validate(schema: &[u8], consignment: &[u8]) -> Result<(), Error> {
+    let schema = strict_decode::<Schema>(&schema)?;
+    strict_decode::<Consignment>(&consignment)?.validate(&schema)?;
+    Ok(())
+}
+
+/// Accepts strict-encoded consignment received from other party: validates
+/// it against the strict-encoded schema and returns the consignment with
+/// all witness transaction references expanded into full transaction ids,
+/// ready to be stored
+pub fn accept(
+    schema: &[u8],
+    consignment: &[u8],
+    resolver: &impl TxRefResolver,
+) -> Result<Vec<u8>, Error> {
+    let schema = strict_decode::<Schema>(&schema)?;
+    let mut consignment = strict_decode::<Consignment>(&consignment)?;
+    consignment.validate(&schema)?;
+    consignment
+        .expand_tx_refs(resolver)
+        .map_err(Error::UnresolvedTxRef)?;
+    Ok(strict_encode(&consignment)?)
+}
+
+/// Verifies that the strict-encoded anchor commits to the state transition
+/// with the given id
+pub fn verify_anchor(anchor: &[u8], transition_id: TransitionId) -> Result<bool, Error> {
+    let anchor = strict_decode::<Anchor>(&anchor)?;
+    let (protocol_id, digest) = transition_slot(transition_id);
+    Ok(anchor.commitment.verify_message(&protocol_id, digest))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rgb::schema::test::schema;
+    use crate::rgb::{seal, AssignmentsVariant, Metadata};
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1;
+
+    #[test]
+    fn test_facade() {
+        let (reveal, hash) = blind_outpoint(Txid::default(), 1).unwrap();
+        assert_eq!(outpoint_hash(&reveal).unwrap(), hash);
+
+        let schema = strict_encode(&schema()).unwrap();
+        let metadata = strict_encode(&bmap! {
+            0usize => bset! { crate::rgb::data::Revealed::String("Asset".to_string()) }
+        })
+        .unwrap();
+        let seal = seal::Revealed::WitnessVout {
+            vout: 0,
+            blinding: 1,
+        };
+        let assignments = strict_encode(&bmap! {
+            0usize => AssignmentsVariant::Void(bset! {
+                crate::rgb::Assignment::Revealed {
+                    seal_definition: seal.clone(),
+                    assigned_state: crate::rgb::data::Void,
+                }
+            })
+        })
+        .unwrap();
+        assert!(matches!(
+            issue(&schema, bp::Chain::Testnet3, &[0xFF], &assignments),
+            Err(Error::Encoding(_))
+        ));
+        let empty = strict_encode(&Metadata::new()).unwrap();
+        assert!(matches!(
+            issue(&schema, bp::Chain::Testnet3, &empty, &assignments),
+            Err(Error::Validation(_))
+        ));
+        let genesis = issue(&schema, bp::Chain::Testnet3, &metadata, &assignments).unwrap();
+        assert_eq!(
+            contract_id(&genesis).unwrap(),
+            strict_decode::<Genesis>(&genesis).unwrap().contract_id()
+        );
+
+        let transition = transition(&schema, 0, &empty, &assignments).unwrap();
+        let transitions =
+            strict_encode(&vec![strict_decode::<Transition>(&transition).unwrap()]).unwrap();
+        let commitment = transfer(&genesis, &transitions).unwrap();
+
+        let secp = secp256k1::Secp256k1::new();
+        let seckey = secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&secp, &seckey);
+        let tx_ref = strict_encode(&TxRef::Txid(Txid::from_slice(&[1u8; 32]).unwrap())).unwrap();
+        let dbc_proof = strict_encode(&dbc::Proof::from(pubkey)).unwrap();
+        let seals = strict_encode(&vec![seal]).unwrap();
+        let consignments = strict_decode::<Vec<Consignment>>(
+            &consign(
+                &genesis,
+                &transitions,
+                &commitment,
+                &tx_ref,
+                &dbc_proof,
+                &seals,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(consignments.len(), 1);
+
+        let anchor = strict_encode(&consignments[0].anchors[0]).unwrap();
+        let transition_id = strict_decode::<Transition>(&transition)
+            .unwrap()
+            .transition_id();
+        assert_eq!(verify_anchor(&anchor, transition_id).unwrap(), true);
+        assert_eq!(
+            verify_anchor(&anchor, TransitionId::default()).unwrap(),
+            false
+        );
+
+        let consignment = strict_encode(&consignments[0]).unwrap();
+        assert!(validate(&schema, &consignment).is_ok());
+    }
+}
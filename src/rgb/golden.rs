@@ -0,0 +1,170 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Regression suite for the consensus-critical serializations. Each fixture
+//! in `tests/golden/` is a text file with two lines: hex of the strict
+//! encoding of the value and hex of the bytes of its expected id (`-` for the
+//! types without id).
+//! The tests decode each fixture, check that re-encoding produces exactly
+//! the same bytes and that the id computed from the decoded value matches
+//! the stored one. Any change to the strict encoding or commitment encoding
+//! of these types makes the tests fail.
+//!
+//! Fixtures are (re)generated by running the tests with
+//! `LNPBP_BLESS_GOLDEN=1` environment variable; this must be done only when
+//! the consensus change is intended.
+
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use bitcoin::Txid;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::bp::{self, blind::OutpointReveal, dbc};
+use crate::lnpbp4::MultiCommitBlock;
+use crate::rgb::schema::test::schema;
+use crate::rgb::{
+    data, seal, Anchor, Assignment, AssignmentsVariant, Genesis, Metadata, Schema, Transition,
+    TxRef,
+};
+use crate::strict_encoding::{self, strict_decode, strict_encode, StrictDecode, StrictEncode};
+
+const BLESS_ENV: &str = "LNPBP_BLESS_GOLDEN";
+const NO_ID: &str = "-";
+
+fn fixture_path(name: &str) -> PathBuf {
+    [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "golden",
+        &format!("{}.hex", name),
+    ]
+    .iter()
+    .collect()
+}
+
+/// Checks fixture with the given name against the current encoding of the
+/// type, or writes the fixture from the `sample` if the tests are run in the
+/// bless mode
+fn check_fixture<T>(name: &str, sample: impl FnOnce() -> T, id: impl Fn(&T) -> String)
+where
+    T: StrictEncode<Error = strict_encoding::Error> + StrictDecode<Error = strict_encoding::Error>,
+{
+    let path = fixture_path(name);
+    if std::env::var_os(BLESS_ENV).is_some() {
+        let value = sample();
+        let data = strict_encode(&value).expect("Strict encoding of the sample has failed");
+        fs::write(&path, format!("{}\n{}\n", data.to_hex(), id(&value)))
+            .expect("Can't write golden fixture");
+        return;
+    }
+
+    let fixture = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "Golden fixture {} is missing; run tests with {}=1 to generate it",
+            path.display(),
+            BLESS_ENV
+        )
+    });
+    let mut lines = fixture.lines();
+    let data = Vec::<u8>::from_hex(lines.next().unwrap_or_default())
+        .expect("Golden fixture data is not a valid hex");
+    let expected_id = lines.next().unwrap_or_default();
+
+    let value: T = strict_decode(&data)
+        .unwrap_or_else(|err| panic!("Golden fixture `{}` can't be decoded: {}", name, err));
+    assert_eq!(
+        strict_encode(&value).unwrap(),
+        data,
+        "Strict encoding of `{}` has changed",
+        name
+    );
+    assert_eq!(
+        id(&value),
+        expected_id,
+        "Commitment id of `{}` has changed",
+        name
+    );
+}
+
+fn genesis() -> Genesis {
+    Genesis::with(
+        schema().schema_id(),
+        bp::Chain::Testnet3,
+        bmap! { 0 => bset! { data::Revealed::String(s!("Golden asset")) } },
+        bmap! {},
+        vec![],
+    )
+}
+
+fn transition() -> Transition {
+    Transition::with(
+        0,
+        Metadata::new(),
+        bmap! {
+            0 => AssignmentsVariant::Void(bset! {
+                Assignment::Revealed {
+                    seal_definition: seal::Revealed::TxOutpoint(OutpointReveal {
+                        blinding: 0x1234_5678,
+                        txid: Txid::default(),
+                        vout: 1,
+                    }),
+                    assigned_state: data::Void,
+                }
+            })
+        },
+        vec![],
+    )
+}
+
+fn proof() -> dbc::Proof {
+    dbc::Proof::from(bp::test::gen_secp_pubkeys(1)[0])
+}
+
+#[test]
+fn golden_schema() {
+    check_fixture("schema", schema, |schema: &Schema| {
+        schema.schema_id()[..].to_hex()
+    });
+}
+
+#[test]
+fn golden_genesis() {
+    check_fixture("genesis", genesis, |genesis: &Genesis| {
+        genesis.contract_id()[..].to_hex()
+    });
+}
+
+#[test]
+fn golden_transition() {
+    check_fixture("transition", transition, |transition: &Transition| {
+        transition.transition_id()[..].to_hex()
+    });
+}
+
+#[test]
+fn golden_proof() {
+    check_fixture("proof", proof, |_: &dbc::Proof| s!(NO_ID));
+}
+
+#[test]
+fn golden_anchor() {
+    check_fixture(
+        "anchor",
+        || Anchor {
+            tx_ref: TxRef::Txid(Txid::default()),
+            commitment: MultiCommitBlock::with_entropy(&bmap! {}, 0).unwrap(),
+            dbc_proof: proof(),
+        },
+        |_: &Anchor| s!(NO_ID),
+    );
+}
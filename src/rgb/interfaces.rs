@@ -13,7 +13,62 @@
 
 //! Module defines interfaces for external data providers used by RGB
 
-pub trait TxResolver {}
+use bitcoin::{OutPoint, Transaction, TxOut, Txid};
+
+use super::TxRef;
+use crate::bp::ShortId;
+use crate::strict_encoding::Timestamp;
+
+/// Provider of the transactions data, like a wallet, a full node or LN
+/// channel transaction graph
+pub trait TxResolver {
+    /// Returns transaction for the given reference, if it is known
+    fn transaction(&self, tx_ref: TxRef) -> Option<Transaction>;
+}
+
+/// Resolver of compact transaction references, which requires access to the
+/// blockchain data (like full node or an indexing service)
+pub trait TxRefResolver {
+    /// Returns short id for a mined transaction, if it is known
+    fn short_id(&self, txid: Txid) -> Option<ShortId>;
+
+    /// Returns transaction id for the given short id, if it is known
+    fn txid(&self, short_id: ShortId) -> Option<Txid>;
+}
+
+/// Information on the block containing some transaction
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct BlockInfo {
+    pub height: u32,
+    pub timestamp: Timestamp,
+}
+
+/// Provider of information on the blocks containing transactions. Used for
+/// validation against historical chain state, in which case the resolver
+/// must answer according to the chain as it was known at the time of
+/// interest (for instance, using an archival index), so later reorgs and
+/// spends do not affect the result.
+pub trait ChainResolver {
+    /// Returns information on the block containing the transaction, or
+    /// `None` if the transaction is not mined
+    fn block_info(&self, tx_ref: TxRef) -> Option<BlockInfo>;
+}
+
+/// Provider of the transaction outputs data, like a wallet or a blockchain
+/// indexing service
+pub trait TxoutResolver {
+    /// Returns transaction output for the given outpoint, if it is known
+    fn txout(&self, outpoint: OutPoint) -> Option<TxOut>;
+}
+
+/// Provider of the information on transactions spending outputs, like a
+/// wallet or a blockchain indexing service
+pub trait SpendResolver {
+    /// Returns id of the transaction spending the output, or `None` if the
+    /// output is not spent
+    fn spending_tx(&self, outpoint: OutPoint) -> Option<Txid>;
+}
 
 pub trait TxConductor {}
 
@@ -0,0 +1,397 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Invoices requesting payment of some amount of an asset (or any amount,
+//! for donations) to a blinded seal, with a list of acceptable assets,
+//! optional bitcoin fallback address for the payers not owning any of them
+//! and optional key for encrypting the consignment sent to the payee
+
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use bitcoin::{secp256k1, Address};
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use super::{seal, Amount, ContractId};
+
+/// Prefix for the invoice string representation
+pub const INVOICE_PREFIX: &str = "rgb:";
+
+/// Request for the payment to a blinded seal
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Invoice {
+    /// Blinded seal which has to receive the assigned amount
+    pub seal: seal::Confidential,
+
+    /// Contracts (assets) accepted for the payment; the payer may use any of
+    /// them. Must not be empty.
+    pub contracts: BTreeSet<ContractId>,
+
+    /// Amount requested; `None` means that the amount is chosen by the payer
+    /// (donation invoice)
+    pub amount: Option<Amount>,
+
+    /// Bitcoin address which may be paid instead if the payer does not own
+    /// any of the accepted assets
+    pub btc_fallback: Option<Address>,
+
+    /// Public key the consignment has to be encrypted to with
+    /// [crate::rgb::Consignment::encrypt] before sending it to the payee
+    pub transport_key: Option<secp256k1::PublicKey>,
+}
+
+/// Errors returned when building payment for an invoice
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display_from(Debug)]
+pub enum InvoiceError {
+    /// Contract is not in the list of the contracts accepted by the invoice
+    ContractNotAccepted(ContractId),
+
+    /// Invoice does not specify amount and payer has not chosen one
+    AmountRequired,
+
+    /// Amount chosen by the payer differs from the invoiced amount
+    AmountMismatch {
+        /// Amount requested by the invoice
+        invoiced: Amount,
+        /// Amount chosen by the payer
+        chosen: Amount,
+    },
+}
+
+/// Errors parsing invoice string representation
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display_from(Debug)]
+pub enum InvoiceParseError {
+    /// Invoice string does not start with `rgb:` prefix
+    WrongPrefix,
+
+    /// Blinded seal is not a valid hex-encoded outpoint hash
+    InvalidSeal,
+
+    /// Contract id is not a valid hex-encoded hash
+    InvalidContractId(String),
+
+    /// Amount is not a valid integer value, or is given more than once
+    InvalidAmount(String),
+
+    /// Bitcoin fallback address is invalid, or is given more than once
+    InvalidFallback(String),
+
+    /// Transport key is not a valid hex-encoded public key, or is given more
+    /// than once
+    InvalidTransportKey(String),
+
+    /// Invoice does not list any accepted contracts
+    NoContracts,
+
+    /// Invoice contains unknown parameter
+    UnknownParameter(String),
+}
+
+impl Invoice {
+    /// Creates invoice for a payment under a single contract. Use `None` for
+    /// the amount to construct a donation invoice.
+    pub fn new(seal: seal::Confidential, contract_id: ContractId, amount: Option<Amount>) -> Self {
+        Self {
+            seal,
+            contracts: bset! { contract_id },
+            amount,
+            btc_fallback: None,
+            transport_key: None,
+        }
+    }
+
+    /// Returns `true` if the payer chooses the amount
+    #[inline]
+    pub fn is_donation(&self) -> bool {
+        self.amount.is_none()
+    }
+
+    /// Returns `true` if the invoice accepts payment under the contract
+    #[inline]
+    pub fn accepts(&self, contract_id: ContractId) -> bool {
+        self.contracts.contains(&contract_id)
+    }
+
+    /// Selects the first of the accepted contracts from the ones owned by
+    /// the payer. If `None` is returned the payer should use
+    /// [Invoice::btc_fallback], if it is present.
+    pub fn select_contract(
+        &self,
+        owned: impl IntoIterator<Item = ContractId>,
+    ) -> Option<ContractId> {
+        owned
+            .into_iter()
+            .find(|contract_id| self.accepts(*contract_id))
+    }
+
+    /// Determines the amount which has to be assigned to the invoice seal by
+    /// the payment under the contract. The amount chosen by the payer is
+    /// required for donation invoices and, if given for invoices with
+    /// amount, must match it.
+    pub fn payment_amount(
+        &self,
+        contract_id: ContractId,
+        chosen: Option<Amount>,
+    ) -> Result<Amount, InvoiceError> {
+        if !self.accepts(contract_id) {
+            Err(InvoiceError::ContractNotAccepted(contract_id))?
+        }
+        match (self.amount, chosen) {
+            (None, None) => Err(InvoiceError::AmountRequired),
+            (None, Some(amount)) => Ok(amount),
+            (Some(invoiced), Some(chosen)) if invoiced != chosen => {
+                Err(InvoiceError::AmountMismatch { invoiced, chosen })
+            }
+            (Some(invoiced), _) => Ok(invoiced),
+        }
+    }
+}
+
+impl Display for Invoice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", INVOICE_PREFIX, self.seal.to_hex())?;
+        let mut separator = '?';
+        for contract_id in &self.contracts {
+            write!(f, "{}asset={}", separator, contract_id.to_hex())?;
+            separator = '&';
+        }
+        if let Some(amount) = self.amount {
+            write!(f, "&amount={}", amount)?;
+        }
+        if let Some(ref address) = self.btc_fallback {
+            write!(f, "&btc={}", address)?;
+        }
+        if let Some(ref key) = self.transport_key {
+            write!(f, "&key={}", key)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Invoice {
+    type Err = InvoiceParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s
+            .strip_prefix(INVOICE_PREFIX)
+            .ok_or(InvoiceParseError::WrongPrefix)?;
+        let mut split = s.splitn(2, '?');
+        let seal = split
+            .next()
+            .and_then(|seal| seal::Confidential::from_hex(seal).ok())
+            .ok_or(InvoiceParseError::InvalidSeal)?;
+
+        let mut invoice = Invoice {
+            seal,
+            contracts: bset! {},
+            amount: None,
+            btc_fallback: None,
+            transport_key: None,
+        };
+        for param in split
+            .next()
+            .into_iter()
+            .flat_map(|params| params.split('&'))
+        {
+            let mut pair = param.splitn(2, '=');
+            let (key, value) = (
+                pair.next().unwrap_or_default(),
+                pair.next().unwrap_or_default(),
+            );
+            match key {
+                "asset" => {
+                    let contract_id = ContractId::from_hex(value)
+                        .map_err(|_| InvoiceParseError::InvalidContractId(value.to_string()))?;
+                    invoice.contracts.insert(contract_id);
+                }
+                "amount" if invoice.amount.is_none() => {
+                    invoice.amount = Some(
+                        value
+                            .parse()
+                            .map_err(|_| InvoiceParseError::InvalidAmount(value.to_string()))?,
+                    );
+                }
+                "amount" => Err(InvoiceParseError::InvalidAmount(value.to_string()))?,
+                "btc" if invoice.btc_fallback.is_none() => {
+                    invoice.btc_fallback = Some(
+                        value
+                            .parse()
+                            .map_err(|_| InvoiceParseError::InvalidFallback(value.to_string()))?,
+                    );
+                }
+                "btc" => Err(InvoiceParseError::InvalidFallback(value.to_string()))?,
+                "key" if invoice.transport_key.is_none() => {
+                    invoice.transport_key =
+                        Some(value.parse().map_err(|_| {
+                            InvoiceParseError::InvalidTransportKey(value.to_string())
+                        })?);
+                }
+                "key" => Err(InvoiceParseError::InvalidTransportKey(value.to_string()))?,
+                unknown => Err(InvoiceParseError::UnknownParameter(unknown.to_string()))?,
+            }
+        }
+        if invoice.contracts.is_empty() {
+            Err(InvoiceParseError::NoContracts)?
+        }
+        Ok(invoice)
+    }
+}
+
+mod strict_encoding {
+    use super::*;
+    use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
+    use std::io;
+
+    impl StrictEncode for Invoice {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(strict_encode_list!(e;
+                self.seal,
+                self.contracts,
+                self.amount,
+                self.btc_fallback.as_ref().map(Address::to_string),
+                self.transport_key
+            ))
+        }
+    }
+
+    impl StrictDecode for Invoice {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            let seal = seal::Confidential::strict_decode(&mut d)?;
+            let contracts = BTreeSet::<ContractId>::strict_decode(&mut d)?;
+            if contracts.is_empty() {
+                Err(Error::DataIntegrityError(
+                    "Invoice does not list any accepted contracts".to_string(),
+                ))?
+            }
+            let amount = Option::<Amount>::strict_decode(&mut d)?;
+            let btc_fallback = Option::<String>::strict_decode(&mut d)?
+                .map(|address| {
+                    Address::from_str(&address).map_err(|err| {
+                        Error::DataIntegrityError(format!("Invalid fallback address: {}", err))
+                    })
+                })
+                .transpose()?;
+            Ok(Self {
+                seal,
+                contracts,
+                amount,
+                btc_fallback,
+                transport_key: Option::strict_decode(&mut d)?,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::strict_encoding::test_helpers::test_encode_roundtrip;
+    use bitcoin::hashes::Hash;
+
+    fn contract_id(no: u8) -> ContractId {
+        ContractId::from_slice(&[no; 32]).unwrap()
+    }
+
+    fn invoice() -> Invoice {
+        let mut invoice = Invoice::new(
+            seal::Confidential::from_slice(&[0xAA; 32]).unwrap(),
+            contract_id(1),
+            Some(100),
+        );
+        invoice.contracts.insert(contract_id(2));
+        invoice.btc_fallback =
+            Some(Address::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap());
+        invoice.transport_key = Some(crate::bp::test::gen_secp_pubkeys(1)[0]);
+        invoice
+    }
+
+    #[test]
+    fn test_invoice_string() {
+        let mut invoice = invoice();
+        assert_eq!(Invoice::from_str(&invoice.to_string()), Ok(invoice.clone()));
+
+        invoice.amount = None;
+        invoice.btc_fallback = None;
+        invoice.transport_key = None;
+        let s = invoice.to_string();
+        assert!(!s.contains("amount") && !s.contains("btc") && !s.contains("key"));
+        assert_eq!(Invoice::from_str(&s), Ok(invoice.clone()));
+
+        let seal = s.splitn(2, '?').next().unwrap();
+        assert_eq!(Invoice::from_str(seal), Err(InvoiceParseError::NoContracts));
+        assert_eq!(
+            Invoice::from_str(&s[4..]),
+            Err(InvoiceParseError::WrongPrefix)
+        );
+        assert_eq!(
+            Invoice::from_str(&format!("{}&amount=1&amount=2", s)),
+            Err(InvoiceParseError::InvalidAmount("2".to_string()))
+        );
+        assert_eq!(
+            Invoice::from_str(&format!("{}&key=02ab", s)),
+            Err(InvoiceParseError::InvalidTransportKey("02ab".to_string()))
+        );
+        assert_eq!(
+            Invoice::from_str(&format!("{}&memo=x", s)),
+            Err(InvoiceParseError::UnknownParameter("memo".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_invoice_encoding() {
+        let mut invoice = invoice();
+        test_encode_roundtrip(&invoice).unwrap();
+        invoice.amount = None;
+        invoice.btc_fallback = None;
+        invoice.transport_key = None;
+        test_encode_roundtrip(&invoice).unwrap();
+    }
+
+    #[test]
+    fn test_payment_amount() {
+        let mut invoice = invoice();
+        assert_eq!(
+            invoice.select_contract(vec![contract_id(3), contract_id(2)]),
+            Some(contract_id(2))
+        );
+        assert_eq!(invoice.select_contract(vec![contract_id(3)]), None);
+
+        assert_eq!(invoice.payment_amount(contract_id(1), None), Ok(100));
+        assert_eq!(invoice.payment_amount(contract_id(2), Some(100)), Ok(100));
+        assert_eq!(
+            invoice.payment_amount(contract_id(2), Some(50)),
+            Err(InvoiceError::AmountMismatch {
+                invoiced: 100,
+                chosen: 50
+            })
+        );
+        assert_eq!(
+            invoice.payment_amount(contract_id(3), Some(100)),
+            Err(InvoiceError::ContractNotAccepted(contract_id(3)))
+        );
+
+        invoice.amount = None;
+        assert!(invoice.is_donation());
+        assert_eq!(
+            invoice.payment_amount(contract_id(1), None),
+            Err(InvoiceError::AmountRequired)
+        );
+        assert_eq!(invoice.payment_amount(contract_id(1), Some(7)), Ok(7));
+    }
+}
@@ -12,24 +12,52 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 mod anchor;
+mod channel;
 mod consignment;
 mod contract;
+pub mod facade;
+#[cfg(test)]
+mod golden;
 mod interfaces;
+mod invoice;
 pub mod schema;
+mod signatures;
 mod stash;
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
+pub mod validation;
 pub mod vm;
 
 pub mod prelude {
     pub use super::*;
-    pub use anchor::Anchor;
-    pub use consignment::Consignment;
+    pub use anchor::{Anchor, TxRef};
+    pub use channel::{
+        ChannelClose, CloseError, Settlement, StateUpdate, UpdateError, STATE_UPDATE_TAG,
+    };
+    pub use consignment::{
+        Consignment, ConsignmentId, DecryptionError, EncryptedConsignment, EncryptionError,
+        PaymentProof, PaymentProofError,
+    };
     pub use contract::{
-        amount, data, seal, Amount, Assignment, AssignmentsVariant, Contract, ContractId,
-        FieldData, Genesis, Metadata, Node, SealDefinition, Transition, TransitionId,
+        amount, data, seal, Amount, Assignment, AssignmentsVariant, ConcealedGenesis,
+        ConcealedTransition, Contract, ContractId, FieldData, Genesis, HashTimeLock, Metadata,
+        Node, NodeReveal, RevealMismatch, SealDefinition, Transition, TransitionId,
+    };
+    pub use interfaces::{
+        BlockInfo, ChainResolver, SpendResolver, TxRefResolver, TxResolver, TxoutResolver,
     };
+    pub use invoice::{Invoice, InvoiceError, InvoiceParseError, INVOICE_PREFIX};
     pub use schema::script;
     pub use schema::{Schema, SchemaId, SimplicityScript};
-    pub use stash::{CoordinatedTransition, CoordinatedUpdate, Stash};
+    pub use signatures::{
+        signature_digest, IdSignature, SignableId, SignaturePurpose, SIGNATURE_TAG_PREFIX,
+    };
+    pub use stash::{
+        AnchorStatus, Annotations, BatchUpdate, ConsumeError, CoordinatedTransition,
+        CoordinatedUpdate, HygieneReport, LabelTarget, Ledger, LedgerDirection, LedgerEntry,
+        LedgerNode, RevocationReport, SpendHazard, Stash, StashDelta, StashStorage, Swap,
+        SwapError, SwapHalf, SwapTerms, TransferBatch, TxoutStatus, WatchList, LEDGER_CSV_HEADER,
+    };
 }
 
 pub use prelude::*;
@@ -0,0 +1,234 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Minimum allocation size for the homomorphic (fungible) assignment types.
+//! Schema may require each allocation to be not less than some amount,
+//! preventing uneconomical dust allocations which cost more in the witness
+//! transaction fees than they are worth. The minimum is enforced during
+//! validation and by the transfer builders; both of them can be overridden
+//! by the caller.
+
+use super::{AssignmentsType, Schema};
+use crate::rgb::contract::nodes::Assignments;
+use crate::rgb::validation;
+use crate::rgb::{Amount, Assignment, AssignmentsVariant, SealDefinition, TransitionId};
+
+/// Allocation of the homomorphic state below the minimum allocation size
+/// defined by the schema
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display_from(Debug)]
+pub struct DustAllocation {
+    pub assignment_type: AssignmentsType,
+    pub amount: Amount,
+    pub minimum: Amount,
+}
+
+/// Suggested handling of the change for a transfer of the homomorphic state
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum ChangeSuggestion {
+    /// Payment uses the whole available amount, so no change allocation is
+    /// required
+    NoChange,
+
+    /// Change of the given amount can be allocated
+    Change(Amount),
+
+    /// Change is below the minimum allocation size. It can be either added
+    /// to the payment, or more allocations worth at least `missing` amount
+    /// have to be aggregated into the transfer to bring the change up to the
+    /// minimum
+    BelowMinimum { change: Amount, missing: Amount },
+
+    /// Available amount is not sufficient for the payment; allocations
+    /// worth at least `missing` amount have to be aggregated into the
+    /// transfer
+    Insufficient { missing: Amount },
+}
+
+impl Schema {
+    /// Returns minimum allocation size for the assignment type, which is
+    /// zero if the schema defines no minimum
+    #[inline]
+    pub fn min_allocation(&self, assignment_type: AssignmentsType) -> Amount {
+        self.min_allocations
+            .get(&assignment_type)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Checks that the allocated amounts are not below the minimum
+    /// allocation size for the assignment type
+    pub fn check_allocations(
+        &self,
+        assignment_type: AssignmentsType,
+        amounts: impl IntoIterator<Item = Amount>,
+    ) -> Result<(), DustAllocation> {
+        let minimum = self.min_allocation(assignment_type);
+        match amounts.into_iter().find(|amount| *amount < minimum) {
+            Some(amount) => Err(DustAllocation {
+                assignment_type,
+                amount,
+                minimum,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that revealed homomorphic allocations of the genesis (when no
+    /// id is given) or state transition are not below the minimum
+    /// allocation size. Confidential allocations can't be checked and are
+    /// skipped.
+    pub fn validate_min_allocations(
+        &self,
+        node_id: Option<TransitionId>,
+        assignments: &Assignments,
+    ) -> Result<(), validation::Failure> {
+        for (assignment_type, variant) in assignments {
+            let set = match variant {
+                AssignmentsVariant::Homomorphic(_, set) => set,
+                _ => continue,
+            };
+            let amounts = set.iter().filter_map(|assignment| match assignment {
+                Assignment::Revealed { assigned_state, .. } => Some(assigned_state.amount),
+                Assignment::Confidential { .. } => None,
+            });
+            self.check_allocations(*assignment_type, amounts)
+                .map_err(|dust| validation::Failure::AllocationBelowMinimum(node_id, dust))?;
+        }
+        Ok(())
+    }
+
+    /// Creates zero-balanced allocations of the homomorphic assignment type
+    /// for a transfer (see [AssignmentsVariant::zero_balanced]), checking
+    /// them against the minimum allocation size unless `allow_dust` is set
+    pub fn zero_balanced(
+        &self,
+        assignment_type: AssignmentsType,
+        allocations: Vec<(SealDefinition, Amount)>,
+        homomorphic_factor: u64,
+        allow_dust: bool,
+    ) -> Result<AssignmentsVariant, DustAllocation> {
+        if !allow_dust {
+            self.check_allocations(
+                assignment_type,
+                allocations.iter().map(|(_, amount)| *amount),
+            )?;
+        }
+        Ok(AssignmentsVariant::zero_balanced(
+            allocations,
+            homomorphic_factor,
+        ))
+    }
+
+    /// Suggests how the change of the transfer paying `payment` out of the
+    /// `available` amount has to be allocated
+    pub fn suggest_change(
+        &self,
+        assignment_type: AssignmentsType,
+        available: Amount,
+        payment: Amount,
+    ) -> ChangeSuggestion {
+        let minimum = self.min_allocation(assignment_type);
+        match available.checked_sub(payment) {
+            None => ChangeSuggestion::Insufficient {
+                missing: payment - available,
+            },
+            Some(0) => ChangeSuggestion::NoChange,
+            Some(change) if change < minimum => ChangeSuggestion::BelowMinimum {
+                change,
+                missing: minimum - change,
+            },
+            Some(change) => ChangeSuggestion::Change(change),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::blind::OutpointReveal;
+    use crate::rgb::schema::test::schema;
+    use crate::rgb::schema::{HomomorphicFormat, StateFormat};
+    use crate::rgb::seal;
+    use bitcoin::hashes::Hash;
+    use bitcoin::Txid;
+
+    fn seal_definition(vout: u32) -> SealDefinition {
+        seal::Revealed::TxOutpoint(OutpointReveal {
+            blinding: 0x1234_5678,
+            txid: Txid::hash(b"seal"),
+            vout,
+        })
+    }
+
+    #[test]
+    fn test_min_allocations() {
+        let mut schema = schema();
+        schema
+            .assignment_types
+            .insert(1, StateFormat::Homomorphic(HomomorphicFormat::Amount));
+        assert_eq!(schema.min_allocation(1), 0);
+        schema.min_allocations.insert(1, 546);
+        assert_eq!(schema.min_allocation(1), 546);
+
+        let dust = DustAllocation {
+            assignment_type: 1,
+            amount: 100,
+            minimum: 546,
+        };
+        assert_eq!(schema.check_allocations(1, vec![1000, 546]), Ok(()));
+        assert_eq!(schema.check_allocations(1, vec![1000, 100]), Err(dust));
+        assert_eq!(schema.check_allocations(0, vec![100]), Ok(()));
+
+        let allocations = vec![(seal_definition(0), 1000), (seal_definition(1), 100)];
+        assert_eq!(
+            schema
+                .zero_balanced(1, allocations.clone(), 0, false)
+                .unwrap_err(),
+            dust
+        );
+        let variant = schema.zero_balanced(1, allocations, 0, true).unwrap();
+        let assignments = bmap! { 1 => variant.clone() };
+        assert_eq!(
+            schema.validate_min_allocations(None, &assignments),
+            Err(validation::Failure::AllocationBelowMinimum(None, dust))
+        );
+        let assignments = bmap! { 1 => variant.conceal_all() };
+        assert_eq!(schema.validate_min_allocations(None, &assignments), Ok(()));
+
+        assert_eq!(
+            schema.suggest_change(1, 1000, 1000),
+            ChangeSuggestion::NoChange
+        );
+        assert_eq!(
+            schema.suggest_change(1, 2000, 1000),
+            ChangeSuggestion::Change(1000)
+        );
+        assert_eq!(
+            schema.suggest_change(1, 1100, 1000),
+            ChangeSuggestion::BelowMinimum {
+                change: 100,
+                missing: 446
+            }
+        );
+        assert_eq!(
+            schema.suggest_change(1, 900, 1000),
+            ChangeSuggestion::Insufficient { missing: 100 }
+        );
+        assert_eq!(
+            schema.suggest_change(0, 1100, 1000),
+            ChangeSuggestion::Change(100)
+        );
+    }
+}
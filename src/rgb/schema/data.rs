@@ -12,6 +12,8 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 use super::{elliptic_curve, Bits, DigestAlgorithm, EllipticCurve};
+use crate::rgb::data;
+use crate::strict_encoding::{AsciiCharset, AsciiString, MaxLenString};
 use num_derive::{FromPrimitive, ToPrimitive};
 use std::collections::BTreeSet;
 use std::io;
@@ -119,6 +121,124 @@ impl DataFormat {
     }
 }
 
+impl StateFormat {
+    /// Checks that the state format can be interpreted by the validator; see
+    /// [DataFormat::is_supported]
+    pub fn is_supported(&self) -> bool {
+        match self {
+            StateFormat::Void | StateFormat::Homomorphic(_) => true,
+            StateFormat::Hashed(format) => format.is_supported(),
+        }
+    }
+}
+
+impl DataFormat {
+    /// Checks that the format is consistent (the allowed range of numbers is
+    /// not empty and fits the bit size, enum has at least one value) and
+    /// describes data representable by [data::Revealed], so the validator
+    /// can interpret it without any knowledge of the specific schema. Values
+    /// never match unsupported formats.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            DataFormat::Unsigned(Bits::Bit128, ..) | DataFormat::Integer(Bits::Bit128, ..) => false,
+            DataFormat::Unsigned(bits, min, max) => min <= max && *max <= bits.max_valu(),
+            DataFormat::Integer(bits, min, max) => {
+                let bound = (bits.max_valu() >> 1) as i128;
+                min <= max && *min >= -bound - 1 && *max <= bound
+            }
+            DataFormat::Float(Bits::Bit32, min, max) => {
+                min <= max && *min >= core::f32::MIN as f64 && *max <= core::f32::MAX as f64
+            }
+            DataFormat::Float(Bits::Bit64, min, max) => min <= max,
+            DataFormat::Float(..) => false,
+            DataFormat::Enum(values) => !values.is_empty(),
+            DataFormat::String(_) | DataFormat::Bytes(_) => true,
+            DataFormat::Digest(DigestAlgorithm::Bitcoin160)
+            | DataFormat::Digest(DigestAlgorithm::Sha256) => true,
+            DataFormat::Digest(_) => false,
+            DataFormat::PublicKey(curve, _) => *curve == EllipticCurve::Secp256k1,
+            DataFormat::Signature(algo) => *algo == elliptic_curve::SignatureAlgorithm::Ecdsa,
+        }
+    }
+
+    /// Checks that the data type and value conform to the format
+    pub fn matches(&self, data: &data::Revealed) -> bool {
+        use data::Revealed;
+        match (self, data) {
+            (DataFormat::Unsigned(Bits::Bit8, min, max), Revealed::U8(val)) => {
+                (*min..=*max).contains(&(*val as u128))
+            }
+            (DataFormat::Unsigned(Bits::Bit16, min, max), Revealed::U16(val)) => {
+                (*min..=*max).contains(&(*val as u128))
+            }
+            (DataFormat::Unsigned(Bits::Bit32, min, max), Revealed::U32(val)) => {
+                (*min..=*max).contains(&(*val as u128))
+            }
+            (DataFormat::Unsigned(Bits::Bit64, min, max), Revealed::U64(val)) => {
+                (*min..=*max).contains(&(*val as u128))
+            }
+            (DataFormat::Integer(Bits::Bit8, min, max), Revealed::I8(val)) => {
+                (*min..=*max).contains(&(*val as i128))
+            }
+            (DataFormat::Integer(Bits::Bit16, min, max), Revealed::I16(val)) => {
+                (*min..=*max).contains(&(*val as i128))
+            }
+            (DataFormat::Integer(Bits::Bit32, min, max), Revealed::I32(val)) => {
+                (*min..=*max).contains(&(*val as i128))
+            }
+            (DataFormat::Integer(Bits::Bit64, min, max), Revealed::I64(val)) => {
+                (*min..=*max).contains(&(*val as i128))
+            }
+            (DataFormat::Float(Bits::Bit32, min, max), Revealed::F32(val)) => {
+                (*min..=*max).contains(&(*val as f64))
+            }
+            (DataFormat::Float(Bits::Bit64, min, max), Revealed::F64(val)) => {
+                (*min..=*max).contains(val)
+            }
+            (DataFormat::Enum(values), Revealed::U8(val)) => values.contains(val),
+            (DataFormat::String(max_len), Revealed::String(val)) => val.len() <= *max_len as usize,
+            (DataFormat::Bytes(max_len), Revealed::Bytes(val)) => val.len() <= *max_len as usize,
+            (DataFormat::Digest(DigestAlgorithm::Bitcoin160), Revealed::Hash160(_)) => true,
+            (DataFormat::Digest(DigestAlgorithm::Sha256), Revealed::Sha256(_)) => true,
+            (DataFormat::PublicKey(EllipticCurve::Secp256k1, _), Revealed::Secp256k1Pubkey(_)) => {
+                true
+            }
+            (
+                DataFormat::Signature(elliptic_curve::SignatureAlgorithm::Ecdsa),
+                Revealed::Secp256k1Signature(_),
+            ) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Types which constraints are expressible by a [DataFormat], so their values
+/// always match the format when converted into [data::Revealed]. Allows to
+/// derive schema field formats from the types used by the contract code.
+pub trait DataFormatted: Into<data::Revealed> {
+    fn data_format() -> DataFormat;
+}
+
+/// Strings longer than `u16::MAX` bytes can't be strict-encoded, so the
+/// format length is saturated
+fn string_format(max_len: usize) -> DataFormat {
+    DataFormat::String(core::cmp::min(max_len, core::u16::MAX as usize) as u16)
+}
+
+impl<const MAX: usize> DataFormatted for MaxLenString<MAX> {
+    #[inline]
+    fn data_format() -> DataFormat {
+        string_format(MAX)
+    }
+}
+
+impl<C: AsciiCharset, const MAX: usize> DataFormatted for AsciiString<C, MAX> {
+    #[inline]
+    fn data_format() -> DataFormat {
+        string_format(MAX)
+    }
+}
+
 mod strict_encoding {
     use super::*;
     use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
@@ -129,34 +249,11 @@ mod strict_encoding {
 
     impl_enum_strict_encoding!(StateType);
 
-    impl StrictEncode for StateFormat {
-        type Error = Error;
-
-        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
-            Ok(match self {
-                StateFormat::Void => StateType::Void.strict_encode(e)?,
-                StateFormat::Homomorphic(data) => {
-                    strict_encode_list!(e; StateType::Homomorphic, data)
-                }
-                StateFormat::Hashed(data) => strict_encode_list!(e; StateType::Hashed, data),
-            })
-        }
-    }
-
-    impl StrictDecode for StateFormat {
-        type Error = Error;
-
-        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
-            let format = StateType::strict_decode(&mut d)?;
-            Ok(match format {
-                StateType::Void => StateFormat::Void,
-                StateType::Homomorphic => {
-                    StateFormat::Homomorphic(HomomorphicFormat::strict_decode(d)?)
-                }
-                StateType::Hashed => StateFormat::Hashed(DataFormat::strict_decode(d)?),
-            })
-        }
-    }
+    impl_tagged_enum_strict_encoding!(StateFormat {
+        Void = StateType::Void,
+        Homomorphic(format: HomomorphicFormat) = StateType::Homomorphic,
+        Hashed(format: DataFormat) = StateType::Hashed,
+    });
 
     #[derive(Debug, Display, FromPrimitive, ToPrimitive)]
     #[display_from(Debug)]
@@ -298,13 +395,15 @@ mod strict_encoding {
                         Bits::Bit64 => (core::u64::MIN as u128)..=(core::u64::MAX as u128),
                         Bits::Bit128 => core::u128::MIN..=core::u128::MAX,
                     };
-                    let (min, max) = get_bounds(min..max, allowed_bounds, true)?;
-                    let (min, max) = (min.to_le_bytes().to_vec(), max.to_le_bytes().to_vec());
+                    let (min, max) = get_bounds(min..=max, allowed_bounds, true)?;
+                    // Little-endian encoding of a value fitting the bit
+                    // size is a prefix of its 128-bit encoding
+                    let size = bits.byte_len();
                     let len = (EncodingTag::Unsigned).strict_encode(&mut e)?
                         + bits.strict_encode(&mut e)?;
-                    e.write_all(&min)?;
-                    e.write_all(&max)?;
-                    len + ::core::mem::size_of_val(&min) * 2
+                    e.write_all(&min.to_le_bytes()[..size])?;
+                    e.write_all(&max.to_le_bytes()[..size])?;
+                    len + size * 2
                 }
 
                 DataFormat::Integer(bits, min, max) => {
@@ -315,13 +414,15 @@ mod strict_encoding {
                         Bits::Bit64 => (core::i64::MIN as i128)..=(core::i64::MAX as i128),
                         Bits::Bit128 => core::i128::MIN..=core::i128::MAX,
                     };
-                    let (min, max) = get_bounds(min..max, allowed_bounds, true)?;
-                    let (min, max) = (min.to_le_bytes().to_vec(), max.to_le_bytes().to_vec());
+                    let (min, max) = get_bounds(min..=max, allowed_bounds, true)?;
+                    // Little-endian encoding of a value fitting the bit
+                    // size is a prefix of its 128-bit encoding
+                    let size = bits.byte_len();
                     let len = (EncodingTag::Integer).strict_encode(&mut e)?
                         + bits.strict_encode(&mut e)?;
-                    e.write_all(&min)?;
-                    e.write_all(&max)?;
-                    len + ::core::mem::size_of_val(&min) * 2
+                    e.write_all(&min.to_le_bytes()[..size])?;
+                    e.write_all(&max.to_le_bytes()[..size])?;
+                    len + size * 2
                 }
 
                 DataFormat::Float(bits, min, max) => {
@@ -336,13 +437,19 @@ mod strict_encoding {
                             unsupported_bits.to_u64().unwrap(),
                         ))?,
                     };
-                    let (min, max) = get_bounds(min..max, allowed_bounds, false)?;
-                    let (min, max) = (min.to_le_bytes().to_vec(), max.to_le_bytes().to_vec());
+                    let (min, max) = get_bounds(min..=max, allowed_bounds, false)?;
+                    let (min, max) = match bits {
+                        Bits::Bit32 => (
+                            (min as f32).to_le_bytes().to_vec(),
+                            (max as f32).to_le_bytes().to_vec(),
+                        ),
+                        _ => (min.to_le_bytes().to_vec(), max.to_le_bytes().to_vec()),
+                    };
                     let len =
                         (EncodingTag::Float).strict_encode(&mut e)? + bits.strict_encode(&mut e)?;
                     e.write_all(&min)?;
                     e.write_all(&max)?;
-                    len + ::core::mem::size_of_val(&min) * 2
+                    len + min.len() * 2
                 }
 
                 DataFormat::Enum(values) => strict_encode_list!(e; EncodingTag::Enum, values),
@@ -510,3 +617,149 @@ mod strict_encoding {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::strict_encoding::{strict_decode, strict_encode, Error, StrictDecode, StrictEncode};
+
+    fn test_roundtrip<T>(value: &T) -> Vec<u8>
+    where
+        T: StrictEncode<Error = Error> + StrictDecode<Error = Error>,
+    {
+        let encoded = strict_encode(value).unwrap();
+        assert_eq!(value.strict_serialized_len().unwrap(), encoded.len());
+        let decoded = strict_decode::<T>(&encoded).unwrap();
+        assert_eq!(strict_encode(&decoded).unwrap(), encoded);
+        assert!(strict_decode::<T>(&&encoded[..encoded.len() - 1]).is_err());
+        encoded
+    }
+
+    #[test]
+    fn test_format_encoding() {
+        let formats = vec![
+            DataFormat::u8(),
+            DataFormat::u16(),
+            DataFormat::u32(),
+            DataFormat::u64(),
+            DataFormat::u128(),
+            DataFormat::Unsigned(Bits::Bit16, 1, 1000),
+            DataFormat::i8(),
+            DataFormat::i16(),
+            DataFormat::i32(),
+            DataFormat::i64(),
+            DataFormat::i128(),
+            DataFormat::Integer(Bits::Bit32, -1, 1),
+            DataFormat::f32(),
+            DataFormat::f64(),
+            DataFormat::Float(Bits::Bit32, -1.5, 1.5),
+            DataFormat::Enum(bset! { 1, 2, 3 }),
+            DataFormat::String(255),
+            DataFormat::Bytes(0xFFFF),
+            DataFormat::Digest(DigestAlgorithm::Sha256),
+            DataFormat::PublicKey(
+                EllipticCurve::Secp256k1,
+                elliptic_curve::PointSerialization::Compressed,
+            ),
+            DataFormat::Signature(elliptic_curve::SignatureAlgorithm::Ecdsa),
+        ];
+        for format in &formats {
+            test_roundtrip(format);
+            test_roundtrip(&StateFormat::Hashed(format.clone()));
+        }
+        assert_eq!(
+            test_roundtrip(&DataFormat::Unsigned(Bits::Bit8, 5, 7)),
+            vec![0, 1, 5, 7]
+        );
+        assert_eq!(
+            test_roundtrip(&DataFormat::Integer(Bits::Bit16, -2, 2)),
+            vec![1, 2, 0xFE, 0xFF, 2, 0]
+        );
+        assert!(strict_decode::<DataFormat>(&[9u8]).is_err());
+
+        assert_eq!(test_roundtrip(&StateFormat::Void), vec![0]);
+        test_roundtrip(&StateFormat::Homomorphic(HomomorphicFormat::Amount));
+        assert!(matches!(
+            strict_decode::<StateFormat>(&[3u8]),
+            Err(Error::EnumValueNotKnown(name, 3)) if name == "StateFormat"
+        ));
+    }
+
+    #[test]
+    fn test_format_support() {
+        use data::Revealed;
+
+        for format in &[
+            DataFormat::u8(),
+            DataFormat::u64(),
+            DataFormat::Unsigned(Bits::Bit16, 7, 7),
+            DataFormat::i64(),
+            DataFormat::Integer(Bits::Bit8, -128, 127),
+            DataFormat::f32(),
+            DataFormat::Float(Bits::Bit64, -1.5, 1.5),
+            DataFormat::Enum(bset! { 0 }),
+            DataFormat::String(0),
+            DataFormat::Bytes(0xFFFF),
+            DataFormat::Digest(DigestAlgorithm::Bitcoin160),
+            DataFormat::PublicKey(
+                EllipticCurve::Secp256k1,
+                elliptic_curve::PointSerialization::Uncompressed,
+            ),
+        ] {
+            assert!(format.is_supported(), "{} must be supported", format);
+            assert!(StateFormat::Hashed(format.clone()).is_supported());
+        }
+
+        for format in &[
+            DataFormat::u128(),
+            DataFormat::i128(),
+            DataFormat::Unsigned(Bits::Bit16, 8, 7),
+            DataFormat::Unsigned(Bits::Bit8, 0, 0x100),
+            DataFormat::Integer(Bits::Bit8, -129, 0),
+            DataFormat::Float(Bits::Bit16, 0.0, 1.0),
+            DataFormat::Float(Bits::Bit32, 0.0, core::f64::MAX),
+            DataFormat::Float(Bits::Bit64, core::f64::NAN, 1.0),
+            DataFormat::Enum(bset! {}),
+            DataFormat::Digest(DigestAlgorithm::Sha512),
+            DataFormat::PublicKey(
+                EllipticCurve::Curve25519,
+                elliptic_curve::PointSerialization::Compressed,
+            ),
+            DataFormat::Signature(elliptic_curve::SignatureAlgorithm::Schnorr),
+        ] {
+            assert!(!format.is_supported(), "{} must not be supported", format);
+            assert!(!StateFormat::Hashed(format.clone()).is_supported());
+        }
+        assert!(StateFormat::Void.is_supported());
+        assert!(StateFormat::Homomorphic(HomomorphicFormat::Amount).is_supported());
+
+        let format = DataFormat::Integer(Bits::Bit16, -300, -100);
+        assert!(format.matches(&Revealed::I16(-300)));
+        assert!(format.matches(&Revealed::I16(-100)));
+        assert!(!format.matches(&Revealed::I16(-99)));
+        assert!(!format.matches(&Revealed::I32(-200)));
+        assert!(!DataFormat::Enum(bset! {}).matches(&Revealed::U8(0)));
+    }
+
+    #[test]
+    fn test_data_formatted() {
+        use crate::strict_encoding::{AlphanumericAscii, String255};
+        use std::str::FromStr;
+
+        type Ticker = AsciiString<AlphanumericAscii, 8>;
+
+        let ticker = Ticker::from_str("BTC").unwrap();
+        let format = Ticker::data_format();
+        assert!(matches!(format, DataFormat::String(8)));
+        assert!(format.matches(&ticker.into()));
+
+        let name = String255::from_str(&"N".repeat(255)).unwrap();
+        assert!(String255::data_format().matches(&name.clone().into()));
+        assert!(!Ticker::data_format().matches(&name.into()));
+
+        assert!(matches!(
+            MaxLenString::<0x20000>::data_format(),
+            DataFormat::String(0xFFFF)
+        ));
+    }
+}
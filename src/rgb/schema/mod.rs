@@ -11,15 +11,24 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+mod allocation;
 mod data;
 mod nodes;
 mod schema;
 pub mod script;
+#[cfg(any(test, feature = "test_harness"))]
+pub mod test_harness;
 mod types;
 
-pub use data::{DataFormat, HomomorphicFormat, StateFormat, StateType};
-pub use nodes::{AssignmentsType, GenesisSchema, TransitionSchema};
+pub use allocation::{ChangeSuggestion, DustAllocation};
+pub use data::{DataFormat, DataFormatted, HomomorphicFormat, StateFormat, StateType};
+pub use nodes::{
+    AssignmentsType, ConditionFields, ConditionsStructure, GenesisSchema, LocksStructure,
+    RevocationSchema, TransitionSchema,
+};
 pub use schema::{FieldType, Schema, SchemaId, TransitionType};
+#[cfg(test)]
+pub(crate) use schema::test;
 pub use script::{Scripting, SimplicityScript};
 pub use types::{
     elliptic_curve, Bits, DigestAlgorithm, EllipticCurve, Occurences, OccurencesError,
@@ -14,11 +14,31 @@
 use std::collections::BTreeMap;
 use std::io;
 
-use super::{FieldType, Occurences, Scripting};
+use super::{FieldType, Occurences, Scripting, TransitionType};
 
 pub type AssignmentsType = usize; // Here we can use usize since encoding/decoding makes sure that it's u16
 pub type MetadataStructure = BTreeMap<FieldType, Occurences<u16>>;
 pub type SealsStructure = BTreeMap<AssignmentsType, Occurences<u16>>;
+/// Maps types of the closed assignments, which state holds a public key
+/// locking the assignment, to the metadata field type of the spending
+/// transition, which must contain signatures made with these keys
+pub type LocksStructure = BTreeMap<AssignmentsType, FieldType>;
+/// Maps types of the closed assignments with conditional ownership, which
+/// state holds a hash time lock, to the metadata fields of the spending
+/// transition containing the condition witness
+pub type ConditionsStructure = BTreeMap<AssignmentsType, ConditionFields>;
+
+/// Metadata fields of the transition closing conditionally-owned assignments
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct ConditionFields {
+    /// Field containing preimages of the hash locks, required to claim the
+    /// assignment
+    pub preimage: FieldType,
+    /// Field containing signatures of the receiver (for the claim) or of the
+    /// sender (for the refund)
+    pub signature: FieldType,
+}
 
 #[derive(Clone, Debug, Display)]
 #[display_from(Debug)]
@@ -34,9 +54,29 @@ pub struct TransitionSchema {
     pub metadata: MetadataStructure,
     pub closes: SealsStructure,
     pub defines: SealsStructure,
+    pub locks: LocksStructure,
+    pub conditions: ConditionsStructure,
     pub scripting: Scripting,
 }
 
+/// Issuer policy allowing to revoke (freeze) particular allocations, for
+/// instance ones resulting from a compromised issuance. Revocation is not a
+/// part of the contract consensus rules: revoked allocations remain valid and
+/// are only reported to the wallets which choose to follow the issuer policy.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct RevocationSchema {
+    /// Type of the state transitions published by the issuer to revoke
+    /// allocations. The transition schema for this type should close some
+    /// assignment controlled by the issuer, which authenticates the
+    /// revocation.
+    pub transition_type: TransitionType,
+
+    /// Metadata field of the revocation transitions listing revoked
+    /// allocations as byte strings with strict-encoded concealed seals
+    pub revoked_seals: FieldType,
+}
+
 mod strict_encoding {
     use super::*;
     use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
@@ -70,6 +110,8 @@ mod strict_encoding {
             self.metadata.strict_encode(&mut e)?;
             self.closes.strict_encode(&mut e)?;
             self.defines.strict_encode(&mut e)?;
+            self.locks.strict_encode(&mut e)?;
+            self.conditions.strict_encode(&mut e)?;
             self.scripting.strict_encode(&mut e)
         }
     }
@@ -82,8 +124,47 @@ mod strict_encoding {
                 metadata: MetadataStructure::strict_decode(&mut d)?,
                 closes: SealsStructure::strict_decode(&mut d)?,
                 defines: SealsStructure::strict_decode(&mut d)?,
+                locks: LocksStructure::strict_decode(&mut d)?,
+                conditions: ConditionsStructure::strict_decode(&mut d)?,
                 scripting: Scripting::strict_decode(&mut d)?,
             })
         }
     }
+    impl StrictEncode for ConditionFields {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Error> {
+            Ok(strict_encode_list!(e; self.preimage, self.signature))
+        }
+    }
+
+    impl StrictDecode for ConditionFields {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Error> {
+            Ok(Self {
+                preimage: FieldType::strict_decode(&mut d)?,
+                signature: FieldType::strict_decode(&mut d)?,
+            })
+        }
+    }
+
+    impl StrictEncode for RevocationSchema {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Error> {
+            Ok(strict_encode_list!(e; self.transition_type, self.revoked_seals))
+        }
+    }
+
+    impl StrictDecode for RevocationSchema {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Error> {
+            Ok(Self {
+                transition_type: TransitionType::strict_decode(&mut d)?,
+                revoked_seals: FieldType::strict_decode(&mut d)?,
+            })
+        }
+    }
 }
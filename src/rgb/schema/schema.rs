@@ -12,13 +12,22 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 use bitcoin::hashes::{sha256t, Hash};
-use std::{collections::BTreeMap, io};
+use bitcoin::secp256k1;
+use core::convert::TryFrom;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
 
+use super::nodes::{MetadataStructure, SealsStructure};
 use super::{
-    script, AssignmentsType, DataFormat, GenesisSchema, SimplicityScript, StateFormat,
-    TransitionSchema,
+    script, AssignmentsType, DataFormat, GenesisSchema, RevocationSchema, SimplicityScript,
+    StateFormat, TransitionSchema,
 };
-use crate::client_side_validation::{commit_strategy, CommitEncodeWithStrategy, ConsensusCommit};
+use crate::client_side_validation::ConsensusCommit;
+use crate::rgb::{
+    contract::nodes::Assignments, data, seal, validation, Amount, Assignment, AssignmentsVariant,
+    Genesis, HashTimeLock, Metadata, Node, Transition, TransitionId,
+};
+use crate::strict_encoding::strict_decode;
 
 pub type FieldType = usize; // Here we can use usize since encoding/decoding makes sure that it's u16
 pub type TransitionType = usize; // Here we can use usize since encoding/decoding makes sure that it's u16
@@ -32,11 +41,14 @@ tagged_hash!(
     SchemaId,
     SchemaIdTag,
     MIDSTATE_SHEMA_ID,
-    doc = "Commitment-based schema identifier used for committing to the schema type"
+    doc = "Commitment-based schema identifier used for committing to the schema type. \
+           Identifiers are ordered lexicographically over their hash bytes, in the same order \
+           as they are serialized."
 );
 
-#[derive(Clone, Debug, Display)]
+#[derive(Clone, Debug, Display, CommitEncode)]
 #[display_from(Debug)]
+#[commit_encode(strategy = "strict")]
 pub struct Schema {
     pub field_types: BTreeMap<FieldType, DataFormat>,
     pub assignment_types: BTreeMap<AssignmentsType, StateFormat>,
@@ -44,6 +56,10 @@ pub struct Schema {
     pub transitions: BTreeMap<TransitionType, TransitionSchema>,
     pub script_library: SimplicityScript,
     pub script_extensions: script::Extensions,
+    pub revocation: Option<RevocationSchema>,
+    /// Minimum amount of each allocation for the homomorphic assignment
+    /// types. Types which are not listed have no minimum.
+    pub min_allocations: BTreeMap<AssignmentsType, Amount>,
 }
 
 impl Schema {
@@ -51,14 +67,326 @@ impl Schema {
     pub fn schema_id(&self) -> SchemaId {
         self.clone().consensus_commit()
     }
+
+    /// Checks that the genesis commits to this schema
+    pub fn validate_genesis(&self, genesis: &Genesis) -> Result<(), validation::Failure> {
+        let schema_id = self.schema_id();
+        if genesis.schema_id() != schema_id {
+            Err(validation::Failure::SchemaMismatch {
+                expected: genesis.schema_id(),
+                actual: schema_id,
+            })?
+        }
+        Ok(())
+    }
+
+    /// Checks that the genesis commits to this schema and that its metadata
+    /// and assignments match the structure defined by the schema
+    pub fn validate_genesis_structure(&self, genesis: &Genesis) -> Result<(), validation::Failure> {
+        self.validate_genesis(genesis)?;
+        self.validate_metadata(None, genesis.metadata(), &self.genesis.metadata)?;
+        self.validate_assignments(None, genesis.assignments(), &self.genesis.defines)
+    }
+
+    /// Checks that the transition type is defined by the schema and that the
+    /// transition metadata and assignments match the structure defined for
+    /// this type
+    pub fn validate_transition_structure(
+        &self,
+        transition: &Transition,
+    ) -> Result<(), validation::Failure> {
+        let transition_id = transition.transition_id();
+        let transition_schema = self.transitions.get(&transition.type_id()).ok_or(
+            validation::Failure::SchemaUnknownTransitionType(transition_id, transition.type_id()),
+        )?;
+        self.validate_metadata(
+            Some(transition_id),
+            transition.metadata(),
+            &transition_schema.metadata,
+        )?;
+        self.validate_assignments(
+            Some(transition_id),
+            transition.assignments(),
+            &transition_schema.defines,
+        )
+    }
+
+    fn validate_metadata(
+        &self,
+        node_id: Option<TransitionId>,
+        metadata: &Metadata,
+        structure: &MetadataStructure,
+    ) -> Result<(), validation::Failure> {
+        if let Some(field_type) = metadata.keys().find(|t| !structure.contains_key(*t)) {
+            Err(validation::Failure::SchemaUnknownFieldType(
+                node_id,
+                *field_type,
+            ))?
+        }
+        for (field_type, occurences) in structure {
+            let values = metadata.get(field_type);
+            // Strict decoding guarantees that the number of values fits `u16`
+            let count = values.map(|set| set.len()).unwrap_or_default() as u16;
+            occurences.check_count(count).map_err(|err| {
+                validation::Failure::SchemaMetaOccurencesError(node_id, *field_type, err)
+            })?;
+            let format = self.field_types.get(field_type).ok_or(
+                validation::Failure::SchemaUnknownFieldType(node_id, *field_type),
+            )?;
+            if !format.is_supported() {
+                Err(validation::Failure::SchemaUnsupportedFieldFormat(
+                    node_id,
+                    *field_type,
+                ))?
+            }
+            if !values
+                .into_iter()
+                .flatten()
+                .all(|data| format.matches(data))
+            {
+                Err(validation::Failure::SchemaFieldFormatMismatch(
+                    node_id,
+                    *field_type,
+                ))?
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_assignments(
+        &self,
+        node_id: Option<TransitionId>,
+        assignments: &Assignments,
+        structure: &SealsStructure,
+    ) -> Result<(), validation::Failure> {
+        if let Some(assignment_type) = assignments.keys().find(|t| !structure.contains_key(*t)) {
+            Err(validation::Failure::SchemaUnknownAssignmentType(
+                node_id,
+                *assignment_type,
+            ))?
+        }
+        for (assignment_type, occurences) in structure {
+            let variant = assignments.get(assignment_type);
+            let count = variant.map(AssignmentsVariant::len).unwrap_or_default() as u16;
+            occurences.check_count(count).map_err(|err| {
+                validation::Failure::SchemaSealsOccurencesError(node_id, *assignment_type, err)
+            })?;
+            let format = self.assignment_types.get(assignment_type).ok_or(
+                validation::Failure::SchemaUnknownAssignmentType(node_id, *assignment_type),
+            )?;
+            if !format.is_supported() {
+                Err(validation::Failure::SchemaUnsupportedStateFormat(
+                    node_id,
+                    *assignment_type,
+                ))?
+            }
+            let matches = match (format, variant) {
+                (_, None) => true,
+                (StateFormat::Void, Some(AssignmentsVariant::Void(_))) => true,
+                (StateFormat::Homomorphic(_), Some(AssignmentsVariant::Homomorphic(..))) => true,
+                (StateFormat::Hashed(data_format), Some(AssignmentsVariant::Hashed(set))) => {
+                    set.iter().all(|assignment| match assignment {
+                        Assignment::Revealed { assigned_state, .. } => {
+                            data_format.matches(assigned_state)
+                        }
+                        Assignment::Confidential { .. } => true,
+                    })
+                }
+                _ => false,
+            };
+            if !matches {
+                Err(validation::Failure::SchemaStateFormatMismatch(
+                    node_id,
+                    *assignment_type,
+                ))?
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the transition provides signatures for all locked
+    /// assignments it closes. Closed assignments must be given in their
+    /// revealed form, since the lock key is a part of the assignment state.
+    pub fn validate_locks(
+        &self,
+        transition: &Transition,
+        closed: &Assignments,
+    ) -> Result<(), validation::Failure> {
+        let transition_id = transition.transition_id();
+        let transition_schema = self.transitions.get(&transition.type_id()).ok_or(
+            validation::Failure::SchemaUnknownTransitionType(transition_id, transition.type_id()),
+        )?;
+        let secp = secp256k1::Secp256k1::verification_only();
+        for (assignment_type, signature_field) in &transition_schema.locks {
+            let set = match closed.get(assignment_type) {
+                None => continue,
+                Some(AssignmentsVariant::Hashed(set)) => set,
+                Some(_) => Err(validation::Failure::LockKeyUnknown(
+                    transition_id,
+                    *assignment_type,
+                ))?,
+            };
+            let sighash = transition.lock_sighash(*signature_field);
+            let signatures = transition.lock_signatures(*signature_field);
+            for assignment in set {
+                let pubkey = match assignment {
+                    Assignment::Revealed {
+                        assigned_state: data::Revealed::Secp256k1Pubkey(pubkey),
+                        ..
+                    } => pubkey,
+                    _ => Err(validation::Failure::LockKeyUnknown(
+                        transition_id,
+                        *assignment_type,
+                    ))?,
+                };
+                if !signatures
+                    .iter()
+                    .any(|sig| secp.verify(&sighash, sig, pubkey).is_ok())
+                {
+                    Err(validation::Failure::LockSignatureMissing(
+                        transition_id,
+                        *assignment_type,
+                    ))?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the transition satisfies conditions of all
+    /// conditionally-owned assignments it closes. Each assignment must be
+    /// either claimed, with the transition providing the hash lock preimage
+    /// and the receiver signature, or refunded, with the transition signed
+    /// by the sender and the witness transaction time-locked till the
+    /// timeout. Claims are rejected when the witness transaction is known to
+    /// be mined at or after the timeout. Closed assignments must be given in
+    /// their revealed form, since the lock is a part of the assignment state.
+    pub fn validate_conditions(
+        &self,
+        transition: &Transition,
+        closed: &Assignments,
+        witness: &bitcoin::Transaction,
+        witness_height: Option<u32>,
+    ) -> Result<(), validation::Failure> {
+        let transition_id = transition.transition_id();
+        let transition_schema = self.transitions.get(&transition.type_id()).ok_or(
+            validation::Failure::SchemaUnknownTransitionType(transition_id, transition.type_id()),
+        )?;
+        let secp = secp256k1::Secp256k1::verification_only();
+        for (assignment_type, fields) in &transition_schema.conditions {
+            let set = match closed.get(assignment_type) {
+                None => continue,
+                Some(AssignmentsVariant::Hashed(set)) => set,
+                Some(_) => Err(validation::Failure::ConditionUnknown(
+                    transition_id,
+                    *assignment_type,
+                ))?,
+            };
+            let sighash = transition.lock_sighash(fields.signature);
+            let signatures = transition.lock_signatures(fields.signature);
+            let preimages = transition.condition_preimages(fields.preimage);
+            let signed_by = |pubkey: &secp256k1::PublicKey| {
+                signatures
+                    .iter()
+                    .any(|sig| secp.verify(&sighash, sig, pubkey).is_ok())
+            };
+            for assignment in set {
+                let lock = match assignment {
+                    Assignment::Revealed { assigned_state, .. } => {
+                        HashTimeLock::try_from(assigned_state).ok()
+                    }
+                    Assignment::Confidential { .. } => None,
+                }
+                .ok_or(validation::Failure::ConditionUnknown(
+                    transition_id,
+                    *assignment_type,
+                ))?;
+                let claimed = preimages
+                    .iter()
+                    .any(|preimage| lock.matches_preimage(preimage))
+                    && witness_height.map_or(true, |height| height < lock.timeout)
+                    && signed_by(&lock.receiver);
+                let refunded = lock.is_refund_witness(witness) && signed_by(&lock.refund);
+                if !claimed && !refunded {
+                    Err(validation::Failure::ConditionNotSatisfied(
+                        transition_id,
+                        *assignment_type,
+                    ))?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns seals revoked by the issuer with the state transition, which
+    /// is empty unless the schema defines revocation policy and the
+    /// transition is of the revocation type. Field values which are not
+    /// strict-encoded concealed seals are ignored.
+    pub fn revoked_seals(&self, transition: &Transition) -> BTreeSet<seal::Confidential> {
+        match self.revocation {
+            Some(revocation) if transition.type_id() == revocation.transition_type => transition
+                .metadata()
+                .get(&revocation.revoked_seals)
+                .into_iter()
+                .flatten()
+                .filter_map(|data| match data {
+                    data::Revealed::Bytes(bytes) => strict_decode(bytes).ok(),
+                    _ => None,
+                })
+                .collect(),
+            _ => BTreeSet::new(),
+        }
+    }
+
+    /// Finds allocations defined by the genesis and state transitions which
+    /// seals were revoked by any of the revocation transitions among the
+    /// provided ones. If the seal is revoked multiple times, the first
+    /// revocation transition is reported.
+    pub fn revoked_allocations<'a>(
+        &self,
+        genesis: &Genesis,
+        transitions: impl IntoIterator<Item = &'a Transition>,
+    ) -> Vec<validation::RevokedAllocation> {
+        let transitions = transitions.into_iter().collect::<Vec<_>>();
+        let mut revoked = BTreeMap::<seal::Confidential, TransitionId>::new();
+        for transition in &transitions {
+            for seal in self.revoked_seals(transition) {
+                revoked
+                    .entry(seal)
+                    .or_insert_with(|| transition.transition_id());
+            }
+        }
+        if revoked.is_empty() {
+            return vec![];
+        }
+
+        let nodes = std::iter::once((None, genesis as &dyn Node)).chain(
+            transitions
+                .iter()
+                .map(|transition| (Some(transition.transition_id()), *transition as &dyn Node)),
+        );
+        let mut allocations = vec![];
+        for (node_id, node) in nodes {
+            for (assignment_type, variant) in node.assignments() {
+                for seal in variant.seals() {
+                    if let Some(revocation) = revoked.get(&seal) {
+                        allocations.push(validation::RevokedAllocation {
+                            node_id,
+                            assignment_type: *assignment_type,
+                            seal,
+                            revocation: *revocation,
+                        });
+                    }
+                }
+            }
+        }
+        allocations
+    }
 }
 
 impl ConsensusCommit for Schema {
     type Commitment = SchemaId;
 }
-impl CommitEncodeWithStrategy for Schema {
-    type Strategy = commit_strategy::UsingStrict;
-}
 
 mod strict_encoding {
     use super::*;
@@ -96,7 +424,9 @@ mod strict_encoding {
                 self.genesis,
                 self.transitions,
                 self.script_library,
-                self.script_extensions
+                self.script_extensions,
+                self.revocation,
+                self.min_allocations
             ))
         }
     }
@@ -112,7 +442,373 @@ mod strict_encoding {
                 transitions: BTreeMap::strict_decode(&mut d)?,
                 script_library: Vec::strict_decode(&mut d)?,
                 script_extensions: script::Extensions::strict_decode(&mut d)?,
+                revocation: Option::strict_decode(&mut d)?,
+                min_allocations: BTreeMap::strict_decode(&mut d)?,
             })
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::bp;
+    use crate::rgb::schema::{
+        elliptic_curve, script::Procedure, Bits, ConditionFields, DigestAlgorithm, EllipticCurve,
+        Occurences, Scripting,
+    };
+    use crate::rgb::{seal, Metadata};
+
+    pub(crate) fn schema() -> Schema {
+        Schema {
+            field_types: bmap! {
+                0 => DataFormat::String(255)
+            },
+            assignment_types: bmap! {
+                0 => StateFormat::Void
+            },
+            genesis: GenesisSchema {
+                metadata: bmap! {
+                    0 => Occurences::Once
+                },
+                defines: bmap! {
+                    0 => Occurences::OnceOrUpTo(None)
+                },
+                scripting: Scripting {
+                    validation: Procedure::NoValidation,
+                    extensions: script::Extensions::ScriptsDenied,
+                },
+            },
+            transitions: bmap! {
+                0 => TransitionSchema {
+                    metadata: bmap! {},
+                    closes: bmap! {
+                        0 => Occurences::OnceOrUpTo(None)
+                    },
+                    defines: bmap! {
+                        0 => Occurences::NoneOrUpTo(None)
+                    },
+                    locks: bmap! {},
+                    conditions: bmap! {},
+                    scripting: Scripting {
+                        validation: Procedure::NoValidation,
+                        extensions: script::Extensions::ScriptsDenied,
+                    },
+                }
+            },
+            script_library: vec![],
+            script_extensions: script::Extensions::ScriptsDenied,
+            revocation: None,
+            min_allocations: bmap! {},
+        }
+    }
+
+    #[test]
+    fn test_schema_id() {
+        use crate::strict_encoding::strict_encode;
+
+        let schema = schema();
+        assert_eq!(
+            schema.schema_id(),
+            SchemaId::hash(&strict_encode(&schema).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_schema_id_ordering() {
+        use crate::strict_encoding::strict_encode;
+
+        let ids = (0u8..16)
+            .map(|no| SchemaId::hash(&[no]))
+            .collect::<Vec<_>>();
+        for a in &ids {
+            for b in &ids {
+                assert_eq!(a.cmp(b), a.into_inner().cmp(&b.into_inner()));
+                assert_eq!(
+                    a.cmp(b),
+                    strict_encode(a).unwrap().cmp(&strict_encode(b).unwrap())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_schema_driven_formats() {
+        // Schema with field and state formats not used by any known
+        // contract type: validation relies on the declared formats only
+        let mut schema = schema();
+        schema.field_types = bmap! {
+            0 => DataFormat::String(255),
+            7 => DataFormat::Unsigned(Bits::Bit16, 10, 100),
+            9 => DataFormat::Integer(Bits::Bit32, -50, -1),
+            11 => DataFormat::Enum(bset! { 2, 4 }),
+            13 => DataFormat::Bytes(2),
+            15 => DataFormat::Float(Bits::Bit64, -1.0, 1.0)
+        };
+        schema
+            .assignment_types
+            .insert(3, StateFormat::Hashed(DataFormat::Bytes(3)));
+        schema.genesis.metadata = bmap! {
+            0 => Occurences::Once,
+            7 => Occurences::OnceOrUpTo(Some(2)),
+            9 => Occurences::Once,
+            11 => Occurences::Once,
+            13 => Occurences::NoneOrOnce,
+            15 => Occurences::Once
+        };
+        schema.genesis.defines = bmap! { 3 => Occurences::Once };
+
+        let metadata: Metadata = bmap! {
+            0 => bset! { data::Revealed::String(s!("synthetic")) },
+            7 => bset! { data::Revealed::U16(10), data::Revealed::U16(100) },
+            9 => bset! { data::Revealed::I32(-50) },
+            11 => bset! { data::Revealed::U8(4) },
+            15 => bset! { data::Revealed::F64(0.5) }
+        };
+        let state = |bytes: Vec<u8>| {
+            bmap! {
+                3 => AssignmentsVariant::Hashed(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal::Revealed::WitnessVout { vout: 0, blinding: 0 },
+                        assigned_state: data::Revealed::Bytes(bytes),
+                    }
+                })
+            }
+        };
+        let genesis = |metadata: Metadata, assignments| {
+            Genesis::with(
+                schema.schema_id(),
+                bp::Chain::Testnet3,
+                metadata,
+                assignments,
+                vec![],
+            )
+        };
+        assert_eq!(
+            schema.validate_genesis_structure(&genesis(metadata.clone(), state(vec![1, 2, 3]))),
+            Ok(())
+        );
+        assert_eq!(
+            schema.validate_genesis_structure(&genesis(metadata.clone(), state(vec![0; 4]))),
+            Err(validation::Failure::SchemaStateFormatMismatch(None, 3))
+        );
+
+        for (field_type, value) in vec![
+            (7, data::Revealed::U16(101)),
+            (7, data::Revealed::U32(50)),
+            (9, data::Revealed::I32(0)),
+            (11, data::Revealed::U8(3)),
+            (13, data::Revealed::Bytes(vec![0; 3])),
+            (15, data::Revealed::F64(1.5)),
+        ] {
+            let mut broken = metadata.clone();
+            broken.insert(field_type, bset! { value });
+            assert_eq!(
+                schema.validate_genesis_structure(&genesis(broken, state(vec![]))),
+                Err(validation::Failure::SchemaFieldFormatMismatch(
+                    None, field_type
+                ))
+            );
+        }
+
+        let mut unsupported = schema.clone();
+        unsupported
+            .field_types
+            .insert(13, DataFormat::Unsigned(Bits::Bit16, 100, 10));
+        let genesis = Genesis::with(
+            unsupported.schema_id(),
+            bp::Chain::Testnet3,
+            metadata.clone(),
+            state(vec![]),
+            vec![],
+        );
+        assert_eq!(
+            unsupported.validate_genesis_structure(&genesis),
+            Err(validation::Failure::SchemaUnsupportedFieldFormat(None, 13))
+        );
+
+        let mut unsupported = schema.clone();
+        unsupported.assignment_types.insert(
+            3,
+            StateFormat::Hashed(DataFormat::Digest(DigestAlgorithm::Sha512)),
+        );
+        let genesis = Genesis::with(
+            unsupported.schema_id(),
+            bp::Chain::Testnet3,
+            metadata,
+            state(vec![]),
+            vec![],
+        );
+        assert_eq!(
+            unsupported.validate_genesis_structure(&genesis),
+            Err(validation::Failure::SchemaUnsupportedStateFormat(None, 3))
+        );
+    }
+
+    #[test]
+    fn test_assignment_locks() {
+        let mut schema = schema();
+        schema.field_types.insert(
+            1,
+            DataFormat::Signature(elliptic_curve::SignatureAlgorithm::Ecdsa),
+        );
+        schema.assignment_types.insert(
+            1,
+            StateFormat::Hashed(DataFormat::PublicKey(
+                EllipticCurve::Secp256k1,
+                elliptic_curve::PointSerialization::Compressed,
+            )),
+        );
+        schema.transitions.get_mut(&0).unwrap().locks.insert(1, 1);
+
+        let secp = secp256k1::Secp256k1::new();
+        let seckey = secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&secp, &seckey);
+        let closed = bmap! {
+            1 => AssignmentsVariant::Hashed(bset! {
+                Assignment::Revealed {
+                    seal_definition: seal::Revealed::WitnessVout { vout: 0, blinding: 0 },
+                    assigned_state: data::Revealed::Secp256k1Pubkey(pubkey),
+                }
+            })
+        };
+
+        let mut transition = Transition::with(0, Metadata::new(), bmap! {}, vec![]);
+        assert_eq!(schema.validate_locks(&transition, &bmap! {}), Ok(()));
+        assert_eq!(
+            schema.validate_locks(&transition, &closed),
+            Err(validation::Failure::LockSignatureMissing(
+                transition.transition_id(),
+                1
+            ))
+        );
+
+        let mut forged = transition.clone();
+        let other_key = secp256k1::SecretKey::from_slice(&[2u8; 32]).unwrap();
+        forged.sign_lock(&secp, 1, &other_key);
+        assert_eq!(
+            schema.validate_locks(&forged, &closed),
+            Err(validation::Failure::LockSignatureMissing(
+                forged.transition_id(),
+                1
+            ))
+        );
+
+        let sighash = transition.lock_sighash(1);
+        transition.sign_lock(&secp, 1, &seckey);
+        assert_eq!(transition.lock_sighash(1), sighash);
+        assert_eq!(schema.validate_locks(&transition, &closed), Ok(()));
+
+        let closed = bmap! { 1 => AssignmentsVariant::Void(bset! {}) };
+        assert_eq!(
+            schema.validate_locks(&transition, &closed),
+            Err(validation::Failure::LockKeyUnknown(
+                transition.transition_id(),
+                1
+            ))
+        );
+    }
+
+    #[test]
+    fn test_assignment_conditions() {
+        let mut schema = schema();
+        schema.field_types.insert(1, DataFormat::Bytes(32));
+        schema.field_types.insert(
+            2,
+            DataFormat::Signature(elliptic_curve::SignatureAlgorithm::Ecdsa),
+        );
+        schema
+            .assignment_types
+            .insert(1, StateFormat::Hashed(DataFormat::Bytes(0xFF)));
+        let fields = ConditionFields {
+            preimage: 1,
+            signature: 2,
+        };
+        schema
+            .transitions
+            .get_mut(&0)
+            .unwrap()
+            .conditions
+            .insert(1, fields);
+
+        let secp = secp256k1::Secp256k1::new();
+        let receiver = secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let sender = secp256k1::SecretKey::from_slice(&[2u8; 32]).unwrap();
+        let preimage = [7u8; 32];
+        let lock = HashTimeLock::with(
+            &preimage,
+            1000,
+            secp256k1::PublicKey::from_secret_key(&secp, &receiver),
+            secp256k1::PublicKey::from_secret_key(&secp, &sender),
+        );
+        let closed = bmap! {
+            1 => AssignmentsVariant::Hashed(bset! {
+                Assignment::Revealed {
+                    seal_definition: seal::Revealed::WitnessVout { vout: 0, blinding: 0 },
+                    assigned_state: lock.into(),
+                }
+            })
+        };
+        let mut witness = bitcoin::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![bitcoin::TxIn {
+                previous_output: bitcoin::OutPoint::default(),
+                script_sig: bitcoin::Script::new(),
+                sequence: 0xFFFF_FFFF,
+                witness: vec![],
+            }],
+            output: vec![],
+        };
+        let unsigned = Transition::with(0, Metadata::new(), bmap! {}, vec![]);
+        let not_satisfied = |transition: &Transition| {
+            Err(validation::Failure::ConditionNotSatisfied(
+                transition.transition_id(),
+                1,
+            ))
+        };
+        assert_eq!(
+            schema.validate_conditions(&unsigned, &closed, &witness, None),
+            not_satisfied(&unsigned)
+        );
+
+        let mut claim = unsigned.clone();
+        claim.sign_claim(&secp, fields, &preimage, &receiver);
+        assert_eq!(
+            schema.validate_conditions(&claim, &closed, &witness, Some(999)),
+            Ok(())
+        );
+        assert_eq!(
+            schema.validate_conditions(&claim, &closed, &witness, Some(1000)),
+            not_satisfied(&claim)
+        );
+
+        let mut forged = unsigned.clone();
+        forged.sign_claim(&secp, fields, &[8u8; 32], &receiver);
+        assert_eq!(
+            schema.validate_conditions(&forged, &closed, &witness, None),
+            not_satisfied(&forged)
+        );
+
+        let mut refund = unsigned.clone();
+        refund.sign_refund(&secp, fields, &sender);
+        assert_eq!(
+            schema.validate_conditions(&refund, &closed, &witness, None),
+            not_satisfied(&refund)
+        );
+        lock.prepare_refund_witness(&mut witness);
+        assert_eq!(
+            schema.validate_conditions(&refund, &closed, &witness, Some(1000)),
+            Ok(())
+        );
+
+        let closed = bmap! { 1 => AssignmentsVariant::Void(bset! {}) };
+        assert_eq!(
+            schema.validate_conditions(&refund, &closed, &witness, None),
+            Err(validation::Failure::ConditionUnknown(
+                refund.transition_id(),
+                1
+            ))
+        );
+    }
+}
@@ -86,28 +86,34 @@ mod strict_encoding {
         }
     }
 
-    impl StrictEncode for Procedure {
-        type Error = Error;
-
-        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Error> {
-            Ok(match self {
-                Self::NoValidation => strict_encode_list!(e; 0u8),
-                Self::Simplicity(code) => strict_encode_list!(e; 1u8, code),
-                Self::Standard(proc_id) => strict_encode_list!(e; 0xFFu8, proc_id),
-            })
-        }
-    }
-
-    impl StrictDecode for Procedure {
-        type Error = Error;
+    impl_tagged_enum_strict_encoding!(Procedure {
+        NoValidation = 0u8,
+        Simplicity(code: SimplicityScript) = 1u8,
+        Standard(proc_id: StandardProcedure) = 0xFFu8,
+    });
+}
 
-        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Error> {
-            Ok(match u8::strict_decode(&mut d)? {
-                0u8 => Self::NoValidation,
-                1u8 => Self::Simplicity(Vec::<u8>::strict_decode(&mut d)?),
-                0xFFu8 => Self::Standard(StandardProcedure::strict_decode(&mut d)?),
-                x => Err(Error::EnumValueNotKnown("script::Procedure".to_string(), x))?,
-            })
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::strict_encoding::{strict_decode, test_helpers::test_vector, Error};
+
+    #[test]
+    fn test_procedure_encoding() {
+        test_vector(&Procedure::NoValidation, &[0]).unwrap();
+        test_vector(
+            &Procedure::Simplicity(vec![0xAA, 0xBB]),
+            &[1, 2, 0, 0xAA, 0xBB],
+        )
+        .unwrap();
+        test_vector(
+            &Procedure::Standard(StandardProcedure::IssueControl),
+            &[0xFF, 2],
+        )
+        .unwrap();
+        assert!(matches!(
+            strict_decode::<Procedure>(&[2u8]),
+            Err(Error::EnumValueNotKnown(name, 2)) if name == "Procedure"
+        ));
     }
 }
@@ -0,0 +1,410 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Conformance test harness for schema authors. For a given [Schema] the
+//! harness generates minimal valid genesis and state transitions, together
+//! with systematically broken variants of them (missing required metadata
+//! field, extra assignment, wrong data format etc), and checks that the
+//! schema validator gives the expected verdict for each of them. Schema
+//! authors may use [check_conformance] as a one-line conformance test:
+//! `assert!(check_conformance(&my_schema()).is_ok())`.
+
+use bitcoin::hashes::{hash160, sha256, Hash};
+use bitcoin::secp256k1;
+
+use super::nodes::{MetadataStructure, SealsStructure};
+use super::{
+    elliptic_curve, AssignmentsType, Bits, DataFormat, DigestAlgorithm, EllipticCurve, FieldType,
+    Occurences, Schema, StateFormat, TransitionType,
+};
+use crate::bp;
+use crate::rgb::contract::nodes::Assignments;
+use crate::rgb::{
+    amount, data, seal, validation, Assignment, AssignmentsVariant, Genesis, Metadata, Transition,
+};
+
+/// Defect introduced into the otherwise valid genesis or state transition
+/// sample
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum Defect {
+    /// Sample has no defects and must pass validation
+    None,
+
+    /// Required metadata field is absent
+    MissingField(FieldType),
+
+    /// Metadata field of the type not defined for the node is present
+    ExtraField(FieldType),
+
+    /// Metadata field value has data format different from the one required
+    /// by the schema
+    WrongDataFormat(FieldType),
+
+    /// Required assignment is absent
+    MissingAssignment(AssignmentsType),
+
+    /// Assignment of the type not defined for the node is present
+    ExtraAssignment(AssignmentsType),
+}
+
+impl Defect {
+    /// Checks whether the validation verdict is the one expected for the
+    /// sample with this defect
+    pub fn expects(&self, verdict: &Result<(), validation::Failure>) -> bool {
+        use validation::Failure::*;
+        match (self, verdict) {
+            (Defect::None, Ok(())) => true,
+            (Defect::MissingField(expected), Err(SchemaMetaOccurencesError(_, found, _)))
+            | (Defect::ExtraField(expected), Err(SchemaUnknownFieldType(_, found)))
+            | (Defect::WrongDataFormat(expected), Err(SchemaFieldFormatMismatch(_, found)))
+            | (Defect::MissingAssignment(expected), Err(SchemaSealsOccurencesError(_, found, _)))
+            | (Defect::ExtraAssignment(expected), Err(SchemaUnknownAssignmentType(_, found))) => {
+                expected == found
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Genesis or state transition sample with a known defect
+#[derive(Clone, Debug, Display)]
+#[display_from(Debug)]
+pub struct Sample<N> {
+    pub defect: Defect,
+    pub node: N,
+}
+
+/// Errors reported by the test harness
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display_from(Debug)]
+pub enum Error {
+    /// Schema requires metadata field which data format is either not
+    /// defined or not supported by the harness, so the field value can't be
+    /// generated
+    UnsupportedFieldFormat(FieldType),
+
+    /// Schema requires assignment which state format is either not defined
+    /// or not supported by the harness, so the assignment can't be generated
+    UnsupportedStateFormat(AssignmentsType),
+
+    /// State transition type is not defined by the schema
+    UnknownTransitionType(TransitionType),
+
+    /// Schema validator has given unexpected verdict for the sample. The
+    /// transition type is absent if the sample is genesis.
+    WrongVerdict {
+        transition_type: Option<TransitionType>,
+        defect: Defect,
+        verdict: Result<(), validation::Failure>,
+    },
+}
+
+impl std::error::Error for Error {}
+
+/// Generates valid genesis sample and genesis samples with all defects
+/// applicable to the schema genesis structure
+pub fn genesis_samples(schema: &Schema) -> Result<Vec<Sample<Genesis>>, Error> {
+    let schema_id = schema.schema_id();
+    samples(
+        schema,
+        &schema.genesis.metadata,
+        &schema.genesis.defines,
+        |metadata, assignments| {
+            Genesis::with(
+                schema_id,
+                bp::Chain::Testnet3,
+                metadata,
+                assignments,
+                vec![],
+            )
+        },
+    )
+}
+
+/// Generates valid state transition sample of the given type and its
+/// samples with all defects applicable to the transition structure
+pub fn transition_samples(
+    schema: &Schema,
+    transition_type: TransitionType,
+) -> Result<Vec<Sample<Transition>>, Error> {
+    let transition_schema = schema
+        .transitions
+        .get(&transition_type)
+        .ok_or(Error::UnknownTransitionType(transition_type))?;
+    samples(
+        schema,
+        &transition_schema.metadata,
+        &transition_schema.defines,
+        |metadata, assignments| Transition::with(transition_type, metadata, assignments, vec![]),
+    )
+}
+
+/// Validates all genesis and state transition samples generated for the
+/// schema, checking that the validator verdict matches the sample defect.
+/// Returns number of the checked samples.
+pub fn check_conformance(schema: &Schema) -> Result<usize, Error> {
+    let mut count = 0;
+    for sample in genesis_samples(schema)? {
+        let verdict = schema.validate_genesis_structure(&sample.node);
+        if !sample.defect.expects(&verdict) {
+            Err(Error::WrongVerdict {
+                transition_type: None,
+                defect: sample.defect,
+                verdict,
+            })?
+        }
+        count += 1;
+    }
+    for transition_type in schema.transitions.keys() {
+        for sample in transition_samples(schema, *transition_type)? {
+            let verdict = schema.validate_transition_structure(&sample.node);
+            if !sample.defect.expects(&verdict) {
+                Err(Error::WrongVerdict {
+                    transition_type: Some(*transition_type),
+                    defect: sample.defect,
+                    verdict,
+                })?
+            }
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+fn samples<N>(
+    schema: &Schema,
+    metadata_structure: &MetadataStructure,
+    seals_structure: &SealsStructure,
+    node: impl Fn(Metadata, Assignments) -> N,
+) -> Result<Vec<Sample<N>>, Error> {
+    let metadata = minimal_metadata(schema, metadata_structure)?;
+    let assignments = minimal_assignments(schema, seals_structure)?;
+    let mut samples = vec![Sample {
+        defect: Defect::None,
+        node: node(metadata.clone(), assignments.clone()),
+    }];
+
+    for (field_type, occurences) in metadata_structure {
+        if is_required(occurences) {
+            let mut broken = metadata.clone();
+            broken.remove(field_type);
+            samples.push(Sample {
+                defect: Defect::MissingField(*field_type),
+                node: node(broken, assignments.clone()),
+            });
+        }
+        if let (Some(format), true) = (
+            schema.field_types.get(field_type),
+            occurences.check_count(1).is_ok(),
+        ) {
+            let mut broken = metadata.clone();
+            broken.insert(*field_type, bset! { mismatching_data(format) });
+            samples.push(Sample {
+                defect: Defect::WrongDataFormat(*field_type),
+                node: node(broken, assignments.clone()),
+            });
+        }
+    }
+    let extra_field = (0..)
+        .find(|field_type| !metadata_structure.contains_key(field_type))
+        .expect("metadata structure can't cover all field types");
+    let mut broken = metadata.clone();
+    broken.insert(extra_field, bset! { data::Revealed::U8(0) });
+    samples.push(Sample {
+        defect: Defect::ExtraField(extra_field),
+        node: node(broken, assignments.clone()),
+    });
+
+    for (assignment_type, occurences) in seals_structure {
+        if is_required(occurences) {
+            let mut broken = assignments.clone();
+            broken.remove(assignment_type);
+            samples.push(Sample {
+                defect: Defect::MissingAssignment(*assignment_type),
+                node: node(metadata.clone(), broken),
+            });
+        }
+    }
+    let extra_assignment = (0..)
+        .find(|assignment_type| !seals_structure.contains_key(assignment_type))
+        .expect("seals structure can't cover all assignment types");
+    let mut broken = assignments;
+    broken.insert(
+        extra_assignment,
+        sample_assignments(&StateFormat::Void).expect("void state is always supported"),
+    );
+    samples.push(Sample {
+        defect: Defect::ExtraAssignment(extra_assignment),
+        node: node(metadata, broken),
+    });
+
+    Ok(samples)
+}
+
+#[inline]
+fn is_required(occurences: &Occurences<u16>) -> bool {
+    occurences.check_count(0).is_err()
+}
+
+fn minimal_metadata(schema: &Schema, structure: &MetadataStructure) -> Result<Metadata, Error> {
+    let mut metadata = Metadata::new();
+    for (field_type, occurences) in structure {
+        if !is_required(occurences) {
+            continue;
+        }
+        let data = schema
+            .field_types
+            .get(field_type)
+            .and_then(sample_data)
+            .ok_or(Error::UnsupportedFieldFormat(*field_type))?;
+        metadata.insert(*field_type, bset! { data });
+    }
+    Ok(metadata)
+}
+
+fn minimal_assignments(schema: &Schema, structure: &SealsStructure) -> Result<Assignments, Error> {
+    let mut assignments = Assignments::new();
+    for (assignment_type, occurences) in structure {
+        if !is_required(occurences) {
+            continue;
+        }
+        let variant = schema
+            .assignment_types
+            .get(assignment_type)
+            .and_then(sample_assignments)
+            .ok_or(Error::UnsupportedStateFormat(*assignment_type))?;
+        assignments.insert(*assignment_type, variant);
+    }
+    Ok(assignments)
+}
+
+fn sample_seal() -> seal::Revealed {
+    seal::Revealed::WitnessVout {
+        vout: 0,
+        blinding: 0,
+    }
+}
+
+fn sample_assignments(format: &StateFormat) -> Option<AssignmentsVariant> {
+    Some(match format {
+        StateFormat::Void => AssignmentsVariant::Void(bset! {
+            Assignment::Revealed {
+                seal_definition: sample_seal(),
+                assigned_state: data::Void,
+            }
+        }),
+        StateFormat::Homomorphic(_) => AssignmentsVariant::Homomorphic(
+            0,
+            bset! {
+                Assignment::Revealed {
+                    seal_definition: sample_seal(),
+                    assigned_state: amount::Revealed::with_amount(0, &mut rand::thread_rng()),
+                }
+            },
+        ),
+        StateFormat::Hashed(format) => AssignmentsVariant::Hashed(bset! {
+            Assignment::Revealed {
+                seal_definition: sample_seal(),
+                assigned_state: sample_data(format)?,
+            }
+        }),
+    })
+}
+
+/// Generates the minimal value conforming to the data format, if the format
+/// is supported by the library data types
+fn sample_data(format: &DataFormat) -> Option<data::Revealed> {
+    if !format.is_supported() {
+        return None;
+    }
+    let seckey = secp256k1::SecretKey::from_slice(&[1u8; 32]).expect("valid secret key");
+    Some(match format {
+        DataFormat::Unsigned(Bits::Bit8, min, _) => data::Revealed::U8(*min as u8),
+        DataFormat::Unsigned(Bits::Bit16, min, _) => data::Revealed::U16(*min as u16),
+        DataFormat::Unsigned(Bits::Bit32, min, _) => data::Revealed::U32(*min as u32),
+        DataFormat::Unsigned(Bits::Bit64, min, _) => data::Revealed::U64(*min as u64),
+        DataFormat::Integer(Bits::Bit8, min, _) => data::Revealed::I8(*min as i8),
+        DataFormat::Integer(Bits::Bit16, min, _) => data::Revealed::I16(*min as i16),
+        DataFormat::Integer(Bits::Bit32, min, _) => data::Revealed::I32(*min as i32),
+        DataFormat::Integer(Bits::Bit64, min, _) => data::Revealed::I64(*min as i64),
+        DataFormat::Float(Bits::Bit32, min, _) => data::Revealed::F32(*min as f32),
+        DataFormat::Float(Bits::Bit64, min, _) => data::Revealed::F64(*min),
+        DataFormat::Enum(values) => data::Revealed::U8(*values.iter().next()?),
+        DataFormat::String(_) => data::Revealed::String(String::new()),
+        DataFormat::Bytes(_) => data::Revealed::Bytes(vec![]),
+        DataFormat::Digest(DigestAlgorithm::Bitcoin160) => {
+            data::Revealed::Hash160(hash160::Hash::hash(&[]))
+        }
+        DataFormat::Digest(DigestAlgorithm::Sha256) => {
+            data::Revealed::Sha256(sha256::Hash::hash(&[]))
+        }
+        DataFormat::PublicKey(EllipticCurve::Secp256k1, _) => {
+            let secp = secp256k1::Secp256k1::signing_only();
+            data::Revealed::Secp256k1Pubkey(secp256k1::PublicKey::from_secret_key(&secp, &seckey))
+        }
+        DataFormat::Signature(elliptic_curve::SignatureAlgorithm::Ecdsa) => {
+            let secp = secp256k1::Secp256k1::signing_only();
+            let msg = secp256k1::Message::from_slice(&[1u8; 32]).expect("valid message");
+            data::Revealed::Secp256k1Signature(secp.sign(&msg, &seckey))
+        }
+        _ => return None,
+    })
+}
+
+/// Generates value which does not conform to the data format
+fn mismatching_data(format: &DataFormat) -> data::Revealed {
+    match format {
+        DataFormat::String(_) => data::Revealed::Bytes(vec![]),
+        _ => data::Revealed::String(String::new()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rgb::schema::test::schema;
+
+    #[test]
+    fn test_conformance() {
+        let schema = schema();
+        let genesis = genesis_samples(&schema).unwrap();
+        assert_eq!(
+            genesis
+                .iter()
+                .map(|sample| sample.defect)
+                .collect::<Vec<_>>(),
+            vec![
+                Defect::None,
+                Defect::MissingField(0),
+                Defect::WrongDataFormat(0),
+                Defect::ExtraField(1),
+                Defect::MissingAssignment(0),
+                Defect::ExtraAssignment(1),
+            ]
+        );
+        assert_eq!(transition_samples(&schema, 0).unwrap().len(), 3);
+        assert_eq!(
+            transition_samples(&schema, 1).unwrap_err(),
+            Error::UnknownTransitionType(1)
+        );
+        assert_eq!(check_conformance(&schema), Ok(9));
+
+        let mut schema = schema;
+        schema.genesis.metadata.insert(2, Occurences::Once);
+        assert_eq!(
+            check_conformance(&schema),
+            Err(Error::UnsupportedFieldFormat(2))
+        );
+    }
+}
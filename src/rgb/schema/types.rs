@@ -216,10 +216,13 @@ mod strict_encoding {
                     let value = u8::strict_decode(&mut d)?;
                     let max: u64 = u64::strict_decode(&mut d)?;
                     let max: Option<$type> = match max {
-                        val if val > 0 && val < ::std::$type::MAX.into() => {
+                        // Occurences without the upper bound are encoded with
+                        // zero maximum
+                        0 if value <= 0x01 => Ok(None),
+                        val if value > 0x01 && val > 0 && val < ::std::$type::MAX.into() => {
                             Ok(Some($type::try_from(max).expect("Can't fail")))
                         }
-                        val if val == ::std::$type::MAX as u64 => Ok(None),
+                        val if value > 0x01 && val == ::std::$type::MAX as u64 => Ok(None),
                         invalid => Err(Error::ValueOutOfRange(
                             stringify!($type).to_string(),
                             0..(::std::$type::MAX as u64),
@@ -345,4 +348,29 @@ mod test {
         let occurence: Occurences<u32> = Occurences::NoneOrUpTo(Some(42));
         occurence.check_count(43).unwrap();
     }
+
+    #[test]
+    fn test_occurences_encoding() {
+        use crate::strict_encoding::{strict_decode, strict_encode};
+
+        let occurences: Vec<Occurences<u16>> = vec![
+            Occurences::NoneOrOnce,
+            Occurences::Once,
+            Occurences::NoneOrUpTo(None),
+            Occurences::NoneOrUpTo(Some(42)),
+            Occurences::OnceOrUpTo(None),
+            Occurences::OnceOrUpTo(Some(42)),
+        ];
+        for occurence in occurences {
+            let data = strict_encode(&occurence).unwrap();
+            assert_eq!(strict_decode::<Occurences<u16>>(&data).unwrap(), occurence);
+        }
+        assert_eq!(
+            strict_encode(&Occurences::<u16>::Once).unwrap(),
+            vec![0x01, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        // Upper bound is not allowed for the occurences which can't have it
+        assert!(strict_decode::<Occurences<u16>>(&[0x01, 1, 0, 0, 0, 0, 0, 0, 0]).is_err());
+        assert!(strict_decode::<Occurences<u16>>(&[0xFE, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+    }
 }
@@ -0,0 +1,211 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Detached signatures over RGB identifiers, like issuer attestations for
+//! contracts, registry listings of schemata and acknowledgements of received
+//! consignments. The signed digest is a tagged hash of the identifier, where
+//! the tag includes both signature purpose and identifier type, so a
+//! signature made for one purpose or one kind of identifier can't be
+//! replayed for another.
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1;
+use num_derive::{FromPrimitive, ToPrimitive};
+
+use super::consignment::ConsignmentId;
+use super::{ContractId, SchemaId, TransitionId};
+use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
+use crate::tagged_hash::midstate_for_tag;
+
+/// Prefix of the tags used for the signed digests
+pub const SIGNATURE_TAG_PREFIX: &str = "rgb:sig";
+
+/// Identifiers which may be signed with [IdSignature]
+pub trait SignableId:
+    Hash<Inner = [u8; 32]> + StrictEncode<Error = Error> + StrictDecode<Error = Error>
+{
+    /// Name of the identifier type used in the signed digest tag
+    const ID_TYPE: &'static str;
+}
+
+impl SignableId for ContractId {
+    const ID_TYPE: &'static str = "contract";
+}
+
+impl SignableId for TransitionId {
+    const ID_TYPE: &'static str = "transition";
+}
+
+impl SignableId for SchemaId {
+    const ID_TYPE: &'static str = "schema";
+}
+
+impl SignableId for ConsignmentId {
+    const ID_TYPE: &'static str = "consignment";
+}
+
+/// Purpose of the signature, providing domain separation for the signed
+/// digests
+#[derive(
+    Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Display, ToPrimitive, FromPrimitive,
+)]
+#[display_from(Debug)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum SignaturePurpose {
+    /// Contract issuer attests the contract
+    IssuerAttestation = 0,
+
+    /// Registry lists the contract or schema
+    RegistryListing = 1,
+
+    /// Receiver acknowledges the consignment
+    ConsignmentAck = 2,
+}
+
+impl SignaturePurpose {
+    /// Name of the purpose used in the signed digest tag
+    pub fn tag_name(self) -> &'static str {
+        match self {
+            SignaturePurpose::IssuerAttestation => "issuer-attestation",
+            SignaturePurpose::RegistryListing => "registry-listing",
+            SignaturePurpose::ConsignmentAck => "consignment-ack",
+        }
+    }
+}
+
+/// Returns digest which has to be signed for the identifier: the tagged hash
+/// of the identifier bytes with `rgb:sig:<purpose>:<id type>` tag
+pub fn signature_digest<T: SignableId>(id: T, purpose: SignaturePurpose) -> secp256k1::Message {
+    let tag = format!(
+        "{}:{}:{}",
+        SIGNATURE_TAG_PREFIX,
+        purpose.tag_name(),
+        T::ID_TYPE
+    );
+    let mut engine = sha256::HashEngine::from_midstate(midstate_for_tag(&tag), 64);
+    engine.input(&id.into_inner());
+    secp256k1::Message::from_slice(&sha256::Hash::from_engine(engine).into_inner())
+        .expect("SHA256 hash is always a valid message")
+}
+
+/// Detached signature over an identifier, carrying the signer public key
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct IdSignature<T: SignableId> {
+    pub id: T,
+    pub purpose: SignaturePurpose,
+    pub signer: secp256k1::PublicKey,
+    pub signature: secp256k1::Signature,
+}
+
+impl<T: SignableId> IdSignature<T> {
+    /// Signs the identifier for the given purpose
+    pub fn sign<C: secp256k1::Signing>(
+        secp: &secp256k1::Secp256k1<C>,
+        id: T,
+        purpose: SignaturePurpose,
+        seckey: &secp256k1::SecretKey,
+    ) -> Self {
+        Self {
+            id,
+            purpose,
+            signer: secp256k1::PublicKey::from_secret_key(secp, seckey),
+            signature: secp.sign(&signature_digest(id, purpose), seckey),
+        }
+    }
+
+    /// Verifies the signature against the signer key. Callers must check
+    /// that the signer is the one they expect (issuer, registry or
+    /// receiver) themselves.
+    pub fn verify<C: secp256k1::Verification>(
+        &self,
+        secp: &secp256k1::Secp256k1<C>,
+    ) -> Result<(), secp256k1::Error> {
+        secp.verify(
+            &signature_digest(self.id, self.purpose),
+            &self.signature,
+            &self.signer,
+        )
+    }
+}
+
+mod strict_encoding {
+    use super::*;
+    use num_traits::{FromPrimitive, ToPrimitive};
+    use std::io;
+
+    impl_enum_strict_encoding!(SignaturePurpose);
+
+    impl<T: SignableId> StrictEncode for IdSignature<T> {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(strict_encode_list!(e;
+                self.id,
+                self.purpose,
+                self.signer,
+                self.signature))
+        }
+    }
+
+    impl<T: SignableId> StrictDecode for IdSignature<T> {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(Self {
+                id: T::strict_decode(&mut d)?,
+                purpose: SignaturePurpose::strict_decode(&mut d)?,
+                signer: secp256k1::PublicKey::strict_decode(&mut d)?,
+                signature: secp256k1::Signature::strict_decode(&mut d)?,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::strict_encoding::test_helpers::test_encode_roundtrip;
+
+    #[test]
+    fn test_id_signature() {
+        let secp = secp256k1::Secp256k1::new();
+        let seckey = secp256k1::SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let contract_id = ContractId::from_slice(&[1u8; 32]).unwrap();
+
+        let attestation = IdSignature::sign(
+            &secp,
+            contract_id,
+            SignaturePurpose::IssuerAttestation,
+            &seckey,
+        );
+        assert_eq!(attestation.verify(&secp), Ok(()));
+        test_encode_roundtrip(&attestation).unwrap();
+
+        let mut replayed = attestation.clone();
+        replayed.purpose = SignaturePurpose::RegistryListing;
+        assert!(replayed.verify(&secp).is_err());
+
+        let mut other = attestation.clone();
+        other.id = ContractId::from_slice(&[2u8; 32]).unwrap();
+        assert!(other.verify(&secp).is_err());
+
+        // The same identifier bytes of a different identifier type produce
+        // different digest
+        let schema_id = SchemaId::from_inner(contract_id.into_inner());
+        assert_ne!(
+            signature_digest(schema_id, SignaturePurpose::RegistryListing),
+            signature_digest(contract_id, SignaturePurpose::RegistryListing)
+        );
+    }
+}
@@ -0,0 +1,274 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Batching of transfers to multiple recipients, which state transitions are
+//! committed to within a single witness transaction
+
+use bitcoin::hashes::{sha256, Hash};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::bp::dbc;
+use crate::commit_verify::TryCommitVerify;
+use crate::lnpbp4::{MultiCommitBlock, MultiCommitError, MultiCommitMsgs, ProtocolId};
+use crate::rgb::{
+    Anchor, Consignment, ContractId, Genesis, Node, SealDefinition, Transition, TransitionId,
+    TxRef,
+};
+
+/// Builder for the transfers to several recipients (under the same or
+/// different contracts) sharing a single witness transaction. All state
+/// transitions added to the batch are committed under a single anchor, each
+/// one occupying a separate LNPBP-4 multimessage commitment slot, so
+/// transitions of the same contract spending different seals may co-exist
+/// within the same anchor.
+///
+/// The workflow is the following:
+/// 1. Add all state transitions with [TransferBatch::add];
+/// 2. Produce multimessage commitment with [TransferBatch::commit] and embed
+///    it into the witness transaction with deterministic bitcoin
+///    commitments;
+/// 3. Call [TransferBatch::finalize] with the witness transaction reference
+///    and DBC proof, receiving [BatchUpdate];
+/// 4. Use [BatchUpdate::consign] to extract consignment for each of the
+///    recipients.
+#[derive(Clone, Debug, Default)]
+pub struct TransferBatch {
+    genesis: BTreeMap<ContractId, Arc<Genesis>>,
+    transitions: BTreeMap<TransitionId, (ContractId, Transition)>,
+}
+
+/// Result of the finalized transfer batch
+#[derive(Clone, Debug)]
+pub struct BatchUpdate {
+    pub anchor: Anchor,
+    genesis: BTreeMap<ContractId, Arc<Genesis>>,
+    transitions: BTreeMap<TransitionId, (ContractId, Transition)>,
+}
+
+/// Returns LNPBP-4 multimessage commitment protocol id and message digest
+/// used for committing to the state transition
+pub fn transition_slot(transition_id: TransitionId) -> (ProtocolId, sha256::Hash) {
+    let id = transition_id.into_inner();
+    (ProtocolId::from_inner(id), sha256::Hash::from_inner(id))
+}
+
+impl TransferBatch {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds state transition under the contract defined by the genesis.
+    /// Returns `false` if the transition is already present in the batch.
+    /// The genesis is shared with the produced consignments, not copied.
+    pub fn add(&mut self, genesis: &Arc<Genesis>, transition: Transition) -> bool {
+        let contract_id = genesis.contract_id();
+        let transition_id = transition.transition_id();
+        if self.transitions.contains_key(&transition_id) {
+            return false;
+        }
+        self.genesis
+            .entry(contract_id)
+            .or_insert_with(|| genesis.clone());
+        self.transitions
+            .insert(transition_id, (contract_id, transition));
+        true
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.transitions.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.transitions.is_empty()
+    }
+
+    /// Source data for the multimessage commitment, with a slot for each of
+    /// the transitions in the batch
+    pub fn multimsg(&self) -> MultiCommitMsgs {
+        self.transitions.keys().cloned().map(transition_slot).collect()
+    }
+
+    /// Creates multimessage commitment to all state transitions in the batch,
+    /// which has to be embedded into the witness transaction. Each call
+    /// produces commitment with a new entropy.
+    #[inline]
+    pub fn commit(&self) -> Result<MultiCommitBlock, MultiCommitError> {
+        MultiCommitBlock::try_commit(&self.multimsg())
+    }
+
+    /// Finalizes the batch with the commitment embedded into the witness
+    /// transaction. Fails returning the batch back if the commitment does
+    /// not cover all of the batch transitions.
+    pub fn finalize(
+        self,
+        commitment: MultiCommitBlock,
+        tx_ref: TxRef,
+        dbc_proof: dbc::Proof,
+    ) -> Result<BatchUpdate, Self> {
+        if !self.transitions.keys().all(|transition_id| {
+            let (protocol_id, digest) = transition_slot(*transition_id);
+            commitment.verify_message(&protocol_id, digest)
+        }) {
+            return Err(self);
+        }
+        Ok(BatchUpdate {
+            anchor: Anchor {
+                tx_ref,
+                commitment,
+                dbc_proof,
+            },
+            genesis: self.genesis,
+            transitions: self.transitions,
+        })
+    }
+}
+
+impl BatchUpdate {
+    /// Returns all state transitions committed by the anchor
+    pub fn transitions(&self) -> Vec<&Transition> {
+        self.transitions
+            .values()
+            .map(|(_, transition)| transition)
+            .collect()
+    }
+
+    /// Creates consignments for the recipient of the provided seals, one per
+    /// each contract having state assigned to these seals. Each consignment
+    /// contains only state transitions defining some of the seals, and does
+    /// not reveal the rest of the batch, which is hidden behind the
+    /// multimessage commitment in the shared anchor. Consignments do not
+    /// contain the history preceding the batch, which has to be added from
+    /// the stash.
+    pub fn consign(&self, seals: &[SealDefinition]) -> Vec<Consignment> {
+        let mut endpoints = BTreeMap::<ContractId, Vec<Transition>>::new();
+        for (contract_id, transition) in self.transitions.values() {
+            let assigns = transition.assignment_types().into_iter().any(|assignment_type| {
+                transition
+                    .defined_seals(assignment_type)
+                    .unwrap_or_default()
+                    .iter()
+                    .any(|seal| seals.contains(seal))
+            });
+            if assigns {
+                endpoints
+                    .entry(*contract_id)
+                    .or_insert_with(Vec::new)
+                    .push(transition.clone());
+            }
+        }
+        endpoints
+            .into_iter()
+            .map(|(contract_id, endpoints)| Consignment {
+                flags: 0,
+                genesis: self.genesis[&contract_id].clone(),
+                transitions: vec![],
+                endpoints,
+                anchors: vec![self.anchor.clone()],
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::{self, blind::OutpointReveal};
+    use crate::rgb::{data, seal, Assignment, AssignmentsVariant, Metadata, SchemaId};
+    use bitcoin::{secp256k1, Txid};
+
+    fn seal(blinding: u64) -> SealDefinition {
+        seal::Revealed::TxOutpoint(OutpointReveal {
+            blinding,
+            txid: Txid::default(),
+            vout: 0,
+        })
+    }
+
+    fn genesis(chain: bp::Chain) -> Arc<Genesis> {
+        Arc::new(Genesis::with(
+            SchemaId::default(),
+            chain,
+            Metadata::new(),
+            bmap! {},
+            vec![],
+        ))
+    }
+
+    fn transition(blinding: u64) -> Transition {
+        Transition::with(
+            0,
+            Metadata::new(),
+            bmap! {
+                0 => AssignmentsVariant::Void(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal(blinding),
+                        assigned_state: data::Void,
+                    }
+                })
+            },
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_batch_transfer() {
+        let asset = genesis(bp::Chain::Testnet3);
+        let other_asset = genesis(bp::Chain::Regtest);
+        let mut batch = TransferBatch::new();
+        assert!(batch.add(&asset, transition(1)));
+        assert!(batch.add(&asset, transition(2)));
+        assert!(batch.add(&other_asset, transition(3)));
+        assert!(!batch.add(&asset, transition(1)));
+        assert_eq!(batch.len(), 3);
+
+        let secp = secp256k1::Secp256k1::new();
+        let seckey = secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&secp, &seckey);
+        let tx_ref = TxRef::Txid(Txid::default());
+
+        let foreign = MultiCommitBlock::try_commit(&bmap! {}).unwrap();
+        let batch = batch
+            .finalize(foreign, tx_ref, dbc::Proof::from(pubkey))
+            .unwrap_err();
+
+        let commitment = batch.commit().unwrap();
+        let update = batch
+            .finalize(commitment.clone(), tx_ref, dbc::Proof::from(pubkey))
+            .unwrap();
+        assert_eq!(update.anchor.commitment, commitment);
+        assert_eq!(update.transitions().len(), 3);
+
+        let consignments = update.consign(&[seal(2)]);
+        assert_eq!(consignments.len(), 1);
+        assert_eq!(consignments[0].contract_id(), asset.contract_id());
+        assert_eq!(
+            consignments[0].endpoints[0].transition_id(),
+            transition(2).transition_id()
+        );
+        assert_eq!(consignments[0].endpoints.len(), 1);
+        assert_eq!(consignments[0].anchors, vec![update.anchor.clone()]);
+
+        let consignments = update.consign(&[seal(1), seal(3)]);
+        assert_eq!(consignments.len(), 2);
+        assert_eq!(
+            consignments.iter().map(|c| c.endpoints.len()).sum::<usize>(),
+            2
+        );
+
+        assert!(update.consign(&[seal(4)]).is_empty());
+    }
+}
@@ -0,0 +1,237 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Commitment-aware coin control, preventing ordinary bitcoin spends from
+//! closing single-use seals which hold RGB state (i.e. from burning assets)
+
+use bitcoin::{OutPoint, Txid};
+use std::collections::HashMap;
+
+use super::batch::transition_slot;
+use super::Stash;
+use crate::bp::OutpointSet;
+use crate::rgb::schema::AssignmentsType;
+use crate::rgb::{seal, Anchor, ContractId, Node, Transition, TransitionId};
+
+/// Reason why spending a transaction output with an ordinary bitcoin
+/// transaction would affect RGB contract state
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum SpendHazard {
+    /// Output is a single-use seal defined by the contract genesis
+    GenesisSeal(ContractId, AssignmentsType),
+
+    /// Output is a single-use seal defined by the state transition
+    TransitionSeal(ContractId, TransitionId, AssignmentsType),
+}
+
+impl Stash {
+    /// Returns the set of hazards for each of the provided candidate outputs
+    /// for an ordinary bitcoin spend. Since the candidate outputs are
+    /// unspent, each of them which is a seal known to the stash is an
+    /// unclosed seal, and spending it without producing a state transition
+    /// will destroy the state assigned to it. Outputs which are not related
+    /// to any known contract state are not present in the returned map.
+    ///
+    /// Seals defined as outputs of the witness transaction are resolved
+    /// using the stash anchors; ones under anchors referencing witness
+    /// transaction by a short id can't be resolved and are ignored.
+    pub fn spend_hazards<'a>(
+        &self,
+        utxos: impl IntoIterator<Item = &'a OutPoint>,
+    ) -> HashMap<OutPoint, Vec<SpendHazard>> {
+        let seals = self.seal_outpoints();
+        utxos
+            .into_iter()
+            .filter_map(|outpoint| {
+                seals
+                    .get(outpoint)
+                    .map(|hazards| (*outpoint, hazards.clone()))
+            })
+            .collect()
+    }
+
+    /// Filters candidate outputs for an ordinary bitcoin spend, leaving only
+    /// those which are not known to hold any contract state
+    pub fn spendable_outputs<'a>(
+        &self,
+        utxos: impl IntoIterator<Item = &'a OutPoint>,
+    ) -> OutpointSet {
+        let seals = self.seal_outpoints();
+        utxos
+            .into_iter()
+            .filter(|outpoint| !seals.contains_key(outpoint))
+            .cloned()
+            .collect()
+    }
+
+    pub(super) fn seal_outpoints(&self) -> HashMap<OutPoint, Vec<SpendHazard>> {
+        let mut seals = HashMap::<OutPoint, Vec<SpendHazard>>::new();
+        for contract in &self.contracts {
+            let contract_id = contract.genesis.contract_id();
+            for assignment_type in contract.genesis.assignment_types() {
+                for seal in contract
+                    .genesis
+                    .defined_seals(assignment_type)
+                    .unwrap_or_default()
+                {
+                    if let seal::Revealed::TxOutpoint(reveal) = seal {
+                        seals
+                            .entry(reveal.into())
+                            .or_insert_with(Vec::new)
+                            .push(SpendHazard::GenesisSeal(contract_id, assignment_type));
+                    }
+                }
+            }
+
+            for transition in contract.revealed.iter().chain(contract.partial.iter()) {
+                let transition_id = transition.transition_id();
+                for assignment_type in transition.assignment_types() {
+                    for seal in transition
+                        .defined_seals(assignment_type)
+                        .unwrap_or_default()
+                    {
+                        let outpoint: OutPoint = match seal {
+                            seal::Revealed::TxOutpoint(reveal) => reveal.into(),
+                            seal::Revealed::WitnessVout { vout, .. } => {
+                                match self.witness_txid(transition) {
+                                    Some(txid) => OutPoint::new(txid, vout as u32),
+                                    None => continue,
+                                }
+                            }
+                        };
+                        seals.entry(outpoint).or_insert_with(Vec::new).push(
+                            SpendHazard::TransitionSeal(
+                                contract_id,
+                                transition_id,
+                                assignment_type,
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+        seals
+    }
+
+    fn witness_txid(&self, transition: &Transition) -> Option<Txid> {
+        self.witness_anchor(transition)
+            .and_then(|anchor| anchor.tx_ref.txid())
+    }
+
+    /// Finds anchor committing to the state transition
+    pub(super) fn witness_anchor(&self, transition: &Transition) -> Option<&Anchor> {
+        let (protocol_id, digest) = transition_slot(transition.transition_id());
+        self.anchors
+            .iter()
+            .find(|anchor| anchor.commitment.verify_message(&protocol_id, digest))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::{self, blind::OutpointReveal, dbc};
+    use crate::commit_verify::TryCommitVerify;
+    use crate::lnpbp4::MultiCommitBlock;
+    use crate::rgb::{
+        data, Annotations, Assignment, AssignmentsVariant, Contract, Genesis, Metadata,
+    };
+    use crate::rgb::{Anchor, SchemaId, TxRef};
+    use bitcoin::hashes::Hash;
+
+    fn txid(no: u8) -> Txid {
+        Txid::from_slice(&[no; 32]).unwrap()
+    }
+
+    fn void(seal_definition: seal::Revealed) -> AssignmentsVariant {
+        AssignmentsVariant::Void(bset! {
+            Assignment::Revealed {
+                seal_definition,
+                assigned_state: data::Void,
+            }
+        })
+    }
+
+    #[test]
+    fn test_spend_hazards() {
+        let genesis = Genesis::with(
+            SchemaId::default(),
+            bp::Chain::Testnet3,
+            Metadata::new(),
+            bmap! {
+                0 => void(seal::Revealed::TxOutpoint(OutpointReveal {
+                    blinding: 1,
+                    txid: txid(1),
+                    vout: 0,
+                }))
+            },
+            vec![],
+        );
+        let transition = Transition::with(
+            0,
+            Metadata::new(),
+            bmap! { 1 => void(seal::Revealed::WitnessVout { vout: 1, blinding: 2 }) },
+            vec![],
+        );
+        let contract_id = genesis.contract_id();
+        let transition_id = transition.transition_id();
+
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let seckey = bitcoin::secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let pubkey = bitcoin::secp256k1::PublicKey::from_secret_key(&secp, &seckey);
+        let (protocol_id, digest) = transition_slot(transition_id);
+        let mut stash = Stash {
+            contracts: vec![Contract {
+                genesis: genesis.into(),
+                revealed: vec![transition],
+                partial: vec![],
+            }],
+            anchors: vec![],
+            txout_status: bmap! {},
+            annotations: Annotations::default(),
+        };
+
+        let utxos = vec![
+            OutPoint::new(txid(1), 0),
+            OutPoint::new(txid(1), 1),
+            OutPoint::new(txid(2), 1),
+        ];
+        let hazards = stash.spend_hazards(&utxos);
+        assert_eq!(hazards.len(), 1);
+        assert_eq!(
+            hazards[&utxos[0]],
+            vec![SpendHazard::GenesisSeal(contract_id, 0)]
+        );
+        assert_eq!(
+            stash.spendable_outputs(&utxos),
+            OutpointSet::from(utxos[1..].to_vec())
+        );
+
+        stash.anchors.push(Anchor {
+            tx_ref: TxRef::Txid(txid(2)),
+            commitment: MultiCommitBlock::try_commit(&bmap! { protocol_id => digest }).unwrap(),
+            dbc_proof: dbc::Proof::from(pubkey),
+        });
+        let hazards = stash.spend_hazards(&utxos);
+        assert_eq!(hazards.len(), 2);
+        assert_eq!(
+            hazards[&utxos[2]],
+            vec![SpendHazard::TransitionSeal(contract_id, transition_id, 1)]
+        );
+        assert_eq!(
+            stash.spendable_outputs(&utxos),
+            OutpointSet::from(vec![utxos[1]])
+        );
+    }
+}
@@ -0,0 +1,324 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Atomic ingestion of consignment batches: all valid consignments from the
+//! batch are either merged into the stash or, if they can't be persisted,
+//! none of them are
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use super::Stash;
+use crate::rgb::{
+    validation, Anchor, Consignment, ConsignmentId, Contract, ContractId, Genesis, Schema,
+    SchemaId, Transition, TransitionId,
+};
+
+/// Reason for a consignment from the batch to be rejected
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display_from(Debug)]
+pub enum ConsumeError {
+    /// Schema of the consignment contract is not known
+    UnknownSchema(SchemaId),
+
+    /// Consignment has failed validation
+    Invalid(validation::Failure),
+}
+
+/// New data added to the stash by a batch of consignments
+#[derive(Clone, Debug, Default)]
+pub struct StashDelta {
+    /// Genesis of the contracts previously unknown to the stash
+    pub genesis: Vec<Arc<Genesis>>,
+
+    /// State transitions together with the ids of their contracts
+    pub transitions: Vec<(ContractId, Transition)>,
+
+    pub anchors: Vec<Anchor>,
+}
+
+impl StashDelta {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.genesis.is_empty() && self.transitions.is_empty() && self.anchors.is_empty()
+    }
+}
+
+/// Persistent storage backing the stash
+pub trait StashStorage {
+    type Error;
+
+    /// Persists all of the changes atomically: if the function fails, none
+    /// of the changes must be stored
+    fn persist(&mut self, delta: &StashDelta) -> Result<(), Self::Error>;
+}
+
+/// Validates consignment against the schema from the provided set,
+/// reporting validation events to the observer
+fn validate_observed(
+    consignment: &Consignment,
+    schemata: &BTreeMap<SchemaId, Schema>,
+    mut observer: impl FnMut(&validation::Event),
+) -> Result<(), ConsumeError> {
+    let schema_id = consignment.schema_id();
+    let schema = schemata
+        .get(&schema_id)
+        .ok_or(ConsumeError::UnknownSchema(schema_id))?;
+    let policy = validation::Policy::default();
+    for event in consignment.validation_events(schema, &policy) {
+        observer(&event);
+        if let validation::Event::Failure(failure) = event {
+            Err(ConsumeError::Invalid(failure))?
+        }
+    }
+    Ok(())
+}
+
+impl Stash {
+    /// Validates a batch of consignments against the known schemata and
+    /// merges all the valid ones into the stash atomically. State
+    /// transitions from the consignments are added to the partial contract
+    /// data. The new data are persisted with the storage first; if it fails,
+    /// the stash is left unmodified and the storage error is returned.
+    /// Otherwise returns validation result for each of the consignments, in
+    /// the same order.
+    #[inline]
+    pub fn consume_all<S: StashStorage>(
+        &mut self,
+        consignments: &[Consignment],
+        schemata: &BTreeMap<SchemaId, Schema>,
+        storage: &mut S,
+    ) -> Result<Vec<Result<ConsignmentId, ConsumeError>>, S::Error> {
+        self.consume_all_observed(consignments, schemata, storage, |_, _| {})
+    }
+
+    /// Works as [Stash::consume_all], additionally reporting validation
+    /// events for each of the consignments to the observer together with the
+    /// id of the consignment
+    pub fn consume_all_observed<S: StashStorage>(
+        &mut self,
+        consignments: &[Consignment],
+        schemata: &BTreeMap<SchemaId, Schema>,
+        storage: &mut S,
+        mut observer: impl FnMut(ConsignmentId, &validation::Event),
+    ) -> Result<Vec<Result<ConsignmentId, ConsumeError>>, S::Error> {
+        let mut known = self
+            .contracts
+            .iter()
+            .flat_map(|contract| contract.revealed.iter().chain(contract.partial.iter()))
+            .map(Transition::transition_id)
+            .collect::<BTreeSet<TransitionId>>();
+        let mut delta = StashDelta::default();
+
+        let results = consignments
+            .iter()
+            .map(|consignment| -> Result<ConsignmentId, ConsumeError> {
+                let consignment_id = consignment.consignment_id();
+                validate_observed(consignment, schemata, |event| {
+                    observer(consignment_id, event)
+                })?;
+
+                let contract_id = consignment.contract_id();
+                if !self.has_contract(contract_id)
+                    && !delta
+                        .genesis
+                        .iter()
+                        .any(|genesis| genesis.contract_id() == contract_id)
+                {
+                    delta.genesis.push(consignment.genesis.clone());
+                }
+                for transition in consignment
+                    .transitions
+                    .iter()
+                    .chain(consignment.endpoints.iter())
+                {
+                    if known.insert(transition.transition_id()) {
+                        delta.transitions.push((contract_id, transition.clone()));
+                    }
+                }
+                for anchor in &consignment.anchors {
+                    if !self.anchors.contains(anchor) && !delta.anchors.contains(anchor) {
+                        delta.anchors.push(anchor.clone());
+                    }
+                }
+                Ok(consignment_id)
+            })
+            .collect::<Vec<_>>();
+
+        if !delta.is_empty() {
+            storage.persist(&delta)?;
+        }
+
+        self.contracts
+            .extend(delta.genesis.into_iter().map(Contract::with));
+        for (contract_id, transition) in delta.transitions {
+            if let Some(contract) = self
+                .contracts
+                .iter_mut()
+                .find(|contract| contract.contract_id() == contract_id)
+            {
+                contract.partial.push(transition);
+            }
+        }
+        self.anchors.extend(delta.anchors);
+
+        Ok(results)
+    }
+
+    fn has_contract(&self, contract_id: ContractId) -> bool {
+        self.contracts
+            .iter()
+            .any(|contract| contract.contract_id() == contract_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::{self, blind::OutpointReveal, dbc};
+    use crate::commit_verify::TryCommitVerify;
+    use crate::lnpbp4::MultiCommitBlock;
+    use crate::rgb::schema::test::schema;
+    use crate::rgb::{data, seal, Annotations, Assignment, AssignmentsVariant, Metadata, TxRef};
+    use bitcoin::Txid;
+
+    struct MockStorage {
+        fail: bool,
+        stored: Vec<StashDelta>,
+    }
+
+    impl StashStorage for MockStorage {
+        type Error = ();
+
+        fn persist(&mut self, delta: &StashDelta) -> Result<(), ()> {
+            if self.fail {
+                return Err(());
+            }
+            self.stored.push(delta.clone());
+            Ok(())
+        }
+    }
+
+    fn consignment(schema_id: SchemaId, blinding: u64) -> Consignment {
+        let genesis = Genesis::with(
+            schema_id,
+            bp::Chain::Testnet3,
+            bmap! { 0 => bset! { data::Revealed::String(s!("Asset")) } },
+            bmap! {
+                0 => AssignmentsVariant::Void(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal::Revealed::WitnessVout { vout: 0, blinding: 0 },
+                        assigned_state: data::Void,
+                    }
+                })
+            },
+            vec![],
+        );
+        let transition = Transition::with(
+            0,
+            Metadata::new(),
+            bmap! {
+                0 => AssignmentsVariant::Void(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal::Revealed::TxOutpoint(OutpointReveal {
+                            blinding,
+                            txid: Txid::default(),
+                            vout: 0,
+                        }),
+                        assigned_state: data::Void,
+                    }
+                })
+            },
+            vec![],
+        );
+        Consignment {
+            flags: 0,
+            genesis: Arc::new(genesis),
+            transitions: vec![],
+            endpoints: vec![transition],
+            anchors: vec![Anchor {
+                tx_ref: TxRef::Txid(Txid::default()),
+                commitment: MultiCommitBlock::try_commit(&bmap! {}).unwrap(),
+                dbc_proof: dbc::Proof::from(bp::test::gen_secp_pubkeys(1)[0]),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_consume_all() {
+        let schema = schema();
+        let schemata = bmap! { schema.schema_id() => schema.clone() };
+        let consignments = vec![
+            consignment(schema.schema_id(), 1),
+            consignment(SchemaId::default(), 2),
+            consignment(schema.schema_id(), 3),
+        ];
+        let mut stash = Stash {
+            contracts: vec![],
+            anchors: vec![],
+            txout_status: bmap! {},
+            annotations: Annotations::default(),
+        };
+
+        let mut storage = MockStorage {
+            fail: true,
+            stored: vec![],
+        };
+        assert_eq!(
+            stash.consume_all(&consignments, &schemata, &mut storage),
+            Err(())
+        );
+        assert!(stash.contracts.is_empty());
+        assert!(stash.anchors.is_empty());
+
+        storage.fail = false;
+        let results = stash
+            .consume_all(&consignments, &schemata, &mut storage)
+            .unwrap();
+        assert_eq!(results[0], Ok(consignments[0].consignment_id()));
+        assert_eq!(
+            results[1],
+            Err(ConsumeError::UnknownSchema(SchemaId::default()))
+        );
+        assert_eq!(results[2], Ok(consignments[2].consignment_id()));
+        assert_eq!(stash.contracts.len(), 1);
+        assert_eq!(stash.contracts[0].partial.len(), 2);
+        assert_eq!(stash.anchors.len(), 2);
+        assert_eq!(storage.stored.len(), 1);
+
+        // Repeated consumption does not duplicate the data
+        stash
+            .consume_all(&consignments, &schemata, &mut storage)
+            .unwrap();
+        assert_eq!(stash.contracts[0].partial.len(), 2);
+        assert_eq!(storage.stored.len(), 1);
+
+        let mut events = vec![];
+        stash
+            .consume_all_observed(&consignments, &schemata, &mut storage, |id, event| {
+                events.push((id, event.clone()))
+            })
+            .unwrap();
+        assert_eq!(events.len(), 6);
+        assert_eq!(
+            events[0],
+            (
+                consignments[0].consignment_id(),
+                validation::Event::NodeValidated(None)
+            )
+        );
+        assert!(events.iter().all(
+            |(id, event)| *id != consignments[1].consignment_id() && event.failure().is_none()
+        ));
+    }
+}
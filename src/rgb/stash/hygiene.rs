@@ -0,0 +1,169 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use bitcoin::secp256k1;
+use std::collections::{BTreeSet, HashMap};
+
+use super::Stash;
+use crate::rgb::{seal, Node, SealDefinition, TxRef};
+
+/// Report on privacy & security hazards found in the stash data
+#[derive(Clone, PartialEq, Eq, Debug, Display, Default)]
+#[display_from(Debug)]
+pub struct HygieneReport {
+    /// Original public keys used by deterministic bitcoin commitments in
+    /// more than a single anchor, with the list of references to the
+    /// transactions containing these commitments. Key reuse allows to link
+    /// different transactions to the same owner and weakens commitment
+    /// security.
+    pub key_reuse: HashMap<secp256k1::PublicKey, Vec<TxRef>>,
+
+    /// Blinding factors used by more than a single seal definition, with the
+    /// set of seals sharing the same factor. Blinding factor reuse
+    /// simplifies brute-force discovery of the concealed seals.
+    pub blinding_reuse: HashMap<u64, BTreeSet<SealDefinition>>,
+}
+
+impl HygieneReport {
+    /// Returns `true` if no hazards were found
+    #[inline]
+    pub fn is_clean(&self) -> bool {
+        self.key_reuse.is_empty() && self.blinding_reuse.is_empty()
+    }
+}
+
+impl Stash {
+    /// Analyzes all known anchors and state transitions for the reuse of
+    /// original public keys in deterministic bitcoin commitments and reuse of
+    /// blinding factors in seal definitions
+    pub fn hygiene_report(&self) -> HygieneReport {
+        let mut keys = HashMap::<secp256k1::PublicKey, Vec<TxRef>>::new();
+        for anchor in &self.anchors {
+            keys.entry(anchor.dbc_proof.pubkey)
+                .or_insert_with(Vec::new)
+                .push(anchor.tx_ref);
+        }
+
+        let mut blindings = HashMap::<u64, BTreeSet<SealDefinition>>::new();
+        for contract in &self.contracts {
+            let nodes = std::iter::once(contract.genesis() as &dyn Node).chain(
+                contract
+                    .revealed
+                    .iter()
+                    .chain(contract.partial.iter())
+                    .map(|transition| transition as &dyn Node),
+            );
+            for node in nodes {
+                for assignment_type in node.assignment_types() {
+                    for seal in node.defined_seals(assignment_type).unwrap_or_default() {
+                        let blinding = match seal {
+                            seal::Revealed::TxOutpoint(ref reveal) => reveal.blinding,
+                            seal::Revealed::WitnessVout { blinding, .. } => blinding,
+                        };
+                        blindings
+                            .entry(blinding)
+                            .or_insert_with(BTreeSet::new)
+                            .insert(seal);
+                    }
+                }
+            }
+        }
+
+        HygieneReport {
+            key_reuse: keys
+                .into_iter()
+                .filter(|(_, txids)| txids.len() > 1)
+                .collect(),
+            blinding_reuse: blindings
+                .into_iter()
+                .filter(|(_, seals)| seals.len() > 1)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::{self, blind::OutpointReveal, dbc};
+    use crate::commit_verify::TryCommitVerify;
+    use crate::lnpbp4::MultiCommitBlock;
+    use crate::rgb::{
+        data, Annotations, Assignment, AssignmentsVariant, Contract, Genesis, Metadata,
+    };
+    use crate::rgb::{Anchor, SchemaId};
+    use bitcoin::hashes::Hash;
+    use bitcoin::Txid;
+
+    fn anchor(pubkey: secp256k1::PublicKey, txno: u8) -> Anchor {
+        Anchor {
+            tx_ref: TxRef::Txid(Txid::from_slice(&[txno; 32]).unwrap()),
+            commitment: MultiCommitBlock::try_commit(&bmap! {}).unwrap(),
+            dbc_proof: dbc::Proof::from(pubkey),
+        }
+    }
+
+    fn contract(blindings: &[u64]) -> Contract {
+        let seals = blindings
+            .iter()
+            .enumerate()
+            .map(|(vout, blinding)| Assignment::Revealed {
+                seal_definition: seal::Revealed::TxOutpoint(OutpointReveal {
+                    blinding: *blinding,
+                    txid: Txid::default(),
+                    vout: vout as u16,
+                }),
+                assigned_state: data::Void,
+            })
+            .collect();
+        Contract {
+            genesis: Genesis::with(
+                SchemaId::default(),
+                bp::Chain::Testnet3,
+                Metadata::new(),
+                bmap! { 0 => AssignmentsVariant::Void(seals) },
+                vec![],
+            )
+            .into(),
+            revealed: vec![],
+            partial: vec![],
+        }
+    }
+
+    #[test]
+    fn test_hygiene_report() {
+        let pubkeys = bp::test::gen_secp_pubkeys(2);
+        let mut stash = Stash {
+            contracts: vec![contract(&[1, 2, 3])],
+            anchors: vec![anchor(pubkeys[0], 1), anchor(pubkeys[1], 2)],
+            txout_status: bmap! {},
+            annotations: Annotations::default(),
+        };
+        assert!(stash.hygiene_report().is_clean());
+
+        stash.anchors.push(anchor(pubkeys[0], 3));
+        stash.contracts.push(contract(&[2, 4]));
+        let report = stash.hygiene_report();
+        assert!(!report.is_clean());
+        assert_eq!(report.key_reuse.len(), 1);
+        assert_eq!(
+            report.key_reuse[&pubkeys[0]],
+            vec![
+                TxRef::Txid(Txid::from_slice(&[1; 32]).unwrap()),
+                TxRef::Txid(Txid::from_slice(&[3; 32]).unwrap())
+            ]
+        );
+        assert_eq!(report.blinding_reuse.len(), 1);
+        assert_eq!(report.blinding_reuse[&2].len(), 2);
+    }
+}
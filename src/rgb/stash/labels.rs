@@ -0,0 +1,167 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! User-defined labels for the contract data kept by the wallet. Labels are
+//! stored aside of the contract data: they never enter commitments or
+//! consignments and are exported and imported separately, so they survive
+//! wallet backups without touching consensus data.
+
+use std::collections::BTreeMap;
+
+use crate::rgb::{seal, ContractId, TransitionId};
+use crate::strict_encoding::{strict_decode, strict_encode};
+
+/// Object a label is attached to
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum LabelTarget {
+    /// Contract genesis
+    Genesis(ContractId),
+    /// State transition
+    Transition(TransitionId),
+    /// Single-use seal, identified by its concealed form, which is known
+    /// both for the own and other parties' allocations
+    Seal(seal::Confidential),
+}
+
+/// Storage of the labels
+#[derive(Clone, PartialEq, Eq, Debug, Display, Default)]
+#[display_from(Debug)]
+pub struct Annotations(BTreeMap<LabelTarget, String>);
+
+impl Annotations {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns label attached to the target, if any
+    #[inline]
+    pub fn label(&self, target: &LabelTarget) -> Option<&str> {
+        self.0.get(target).map(String::as_str)
+    }
+
+    /// Attaches label to the target, returning the label it has replaced
+    #[inline]
+    pub fn set_label(&mut self, target: LabelTarget, label: impl ToString) -> Option<String> {
+        self.0.insert(target, label.to_string())
+    }
+
+    /// Removes label from the target, returning it
+    #[inline]
+    pub fn remove_label(&mut self, target: &LabelTarget) -> Option<String> {
+        self.0.remove(target)
+    }
+
+    /// Iterates over all labels in the order of their targets
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&LabelTarget, &String)> {
+        self.0.iter()
+    }
+
+    /// Exports all labels as strict-encoded data for the backup
+    pub fn export(&self) -> Vec<u8> {
+        strict_encode(&self.0).expect("Strict encoding of labels must not fail")
+    }
+
+    /// Imports labels from the data produced by [Annotations::export]. The
+    /// imported labels replace existing labels for the same targets; other
+    /// existing labels are kept. Returns number of the imported labels.
+    pub fn import(&mut self, data: &[u8]) -> Result<usize, crate::strict_encoding::Error> {
+        let labels: BTreeMap<LabelTarget, String> = strict_decode(data)?;
+        let count = labels.len();
+        self.0.extend(labels);
+        Ok(count)
+    }
+}
+
+mod strict_encoding {
+    use super::*;
+    use crate::strict_encoding::{Error, StrictDecode, StrictEncode};
+    use std::io;
+
+    impl StrictEncode for LabelTarget {
+        type Error = Error;
+
+        fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+            Ok(match self {
+                LabelTarget::Genesis(contract_id) => strict_encode_list!(e; 0u8, contract_id),
+                LabelTarget::Transition(transition_id) => {
+                    strict_encode_list!(e; 1u8, transition_id)
+                }
+                LabelTarget::Seal(seal) => strict_encode_list!(e; 2u8, seal),
+            })
+        }
+    }
+
+    impl StrictDecode for LabelTarget {
+        type Error = Error;
+
+        fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+            Ok(match u8::strict_decode(&mut d)? {
+                0u8 => LabelTarget::Genesis(ContractId::strict_decode(&mut d)?),
+                1u8 => LabelTarget::Transition(TransitionId::strict_decode(&mut d)?),
+                2u8 => LabelTarget::Seal(seal::Confidential::strict_decode(&mut d)?),
+                invalid => Err(Error::EnumValueNotKnown("LabelTarget".to_string(), invalid))?,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::Hash;
+
+    #[test]
+    fn test_annotations() {
+        let genesis = LabelTarget::Genesis(ContractId::hash(b"contract"));
+        let allocation = LabelTarget::Seal(seal::Confidential::hash(b"seal"));
+        let mut annotations = Annotations::new();
+        assert_eq!(
+            annotations.set_label(allocation, "payment from Alice"),
+            None
+        );
+        assert_eq!(annotations.set_label(genesis, "USDT"), None);
+        assert_eq!(annotations.label(&allocation), Some("payment from Alice"));
+        assert_eq!(
+            annotations.set_label(allocation, "refund"),
+            Some(s!("payment from Alice"))
+        );
+        assert_eq!(annotations.len(), 2);
+
+        let backup = annotations.export();
+        assert_eq!(annotations.remove_label(&genesis), Some(s!("USDT")));
+        assert_eq!(annotations.label(&genesis), None);
+
+        let transition = LabelTarget::Transition(TransitionId::hash(b"transition"));
+        let mut restored = Annotations::new();
+        restored.set_label(transition, "change");
+        restored.set_label(allocation, "outdated");
+        assert_eq!(restored.import(&backup).unwrap(), 2);
+        assert_eq!(restored.label(&genesis), Some("USDT"));
+        assert_eq!(restored.label(&allocation), Some("refund"));
+        assert_eq!(restored.label(&transition), Some("change"));
+        assert!(restored.import(&[0xFF]).is_err());
+    }
+}
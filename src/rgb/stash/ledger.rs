@@ -0,0 +1,361 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Export of the contract history known to the stash as a flat ledger, in
+//! the form suitable for accounting reports
+
+use bitcoin::{OutPoint, Txid};
+use core::fmt::Write;
+
+use super::Stash;
+use crate::rgb::interfaces::{ChainResolver, TxRefResolver};
+use crate::rgb::schema::AssignmentsType;
+use crate::rgb::{
+    seal, Amount, Assignment, AssignmentsVariant, BlockInfo, ContractId, Node, TransitionId,
+};
+
+/// Header line of the CSV ledger representation
+pub const LEDGER_CSV_HEADER: &str =
+    "height,timestamp,node_id,assignment_type,direction,amount,seal,counterparty_seal";
+
+/// Contract node producing the ledger entry
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum LedgerNode {
+    Genesis(ContractId),
+    Transition(TransitionId),
+}
+
+impl LedgerNode {
+    /// Returns hex-encoded id of the node
+    pub fn node_id(&self) -> String {
+        match self {
+            LedgerNode::Genesis(contract_id) => contract_id.to_string(),
+            LedgerNode::Transition(transition_id) => transition_id.to_string(),
+        }
+    }
+}
+
+/// Direction of the state assignment. Since the stash keeps in revealed form
+/// only the seals known to the wallet, assignments to revealed seals are
+/// considered incoming and assignments to concealed seals – outgoing.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum LedgerDirection {
+    Incoming,
+    Outgoing,
+}
+
+/// Single state assignment in the ledger
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct LedgerEntry {
+    /// Block containing the witness transaction of the node, if it is mined
+    /// and known to the resolver. Always `None` for genesis.
+    pub block: Option<BlockInfo>,
+    pub node: LedgerNode,
+    pub assignment_type: AssignmentsType,
+    pub direction: LedgerDirection,
+    /// Assigned amount, if the assignment is homomorphic and revealed
+    pub amount: Option<Amount>,
+    /// Own seal, if it can be resolved to a transaction output
+    pub seal: Option<OutPoint>,
+    /// Concealed seal of the counterparty for the outgoing assignments
+    pub counterparty_seal: Option<seal::Confidential>,
+}
+
+/// Flat ledger with the state assignments under a single contract. Entries
+/// start with genesis assignments followed by the state transitions ordered
+/// by the height of the witness transaction; transitions which are not mined
+/// go last.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct Ledger {
+    pub contract_id: ContractId,
+    pub entries: Vec<LedgerEntry>,
+}
+
+impl Ledger {
+    /// Produces CSV representation of the ledger with [LEDGER_CSV_HEADER]
+    /// line. Unknown values are represented by empty fields.
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\n", LEDGER_CSV_HEADER);
+        for entry in &self.entries {
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{}",
+                opt_to_string(entry.block.map(|block| block.height)),
+                opt_to_string(entry.block.map(|block| block.timestamp.unix_secs())),
+                entry.node.node_id(),
+                entry.assignment_type,
+                entry.direction,
+                opt_to_string(entry.amount),
+                opt_to_string(entry.seal),
+                opt_to_string(entry.counterparty_seal),
+            )
+            .expect("writing to string can't fail");
+        }
+        csv
+    }
+
+    /// Produces JSON representation of the ledger as an object with contract
+    /// id and an array of entries. Unknown values are represented by `null`.
+    pub fn to_json(&self) -> String {
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{{\"height\":{},\"timestamp\":{},\"node_id\":\"{}\",\
+                    \"assignment_type\":{},\"direction\":\"{}\",\"amount\":{},\
+                    \"seal\":{},\"counterparty_seal\":{}}}",
+                    opt_to_json(entry.block.map(|block| block.height), false),
+                    opt_to_json(entry.block.map(|block| block.timestamp.unix_secs()), false),
+                    entry.node.node_id(),
+                    entry.assignment_type,
+                    entry.direction,
+                    opt_to_json(entry.amount, false),
+                    opt_to_json(entry.seal, true),
+                    opt_to_json(entry.counterparty_seal, true),
+                )
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "{{\"contract_id\":\"{}\",\"entries\":[{}]}}",
+            self.contract_id,
+            entries.join(",")
+        )
+    }
+}
+
+fn opt_to_string<T: ToString>(val: Option<T>) -> String {
+    val.map(|val| val.to_string()).unwrap_or_default()
+}
+
+fn opt_to_json<T: ToString>(val: Option<T>, quoted: bool) -> String {
+    match val {
+        Some(val) if quoted => format!("\"{}\"", val.to_string()),
+        Some(val) => val.to_string(),
+        None => s!("null"),
+    }
+}
+
+impl Stash {
+    /// Exports all state assignments known for the contract as a flat
+    /// [Ledger]. Blocks of the witness transactions are requested from the
+    /// resolver. Returns `None` if the contract is not known to the stash.
+    pub fn ledger(
+        &self,
+        contract_id: ContractId,
+        resolver: &(impl ChainResolver + TxRefResolver),
+    ) -> Option<Ledger> {
+        let contract = self
+            .contracts
+            .iter()
+            .find(|contract| contract.contract_id() == contract_id)?;
+
+        let mut entries = node_entries(
+            &*contract.genesis,
+            LedgerNode::Genesis(contract_id),
+            None,
+            None,
+        );
+
+        let mut transitions = contract
+            .revealed
+            .iter()
+            .chain(contract.partial.iter())
+            .map(|transition| {
+                let anchor = self.witness_anchor(transition);
+                let block = anchor.and_then(|anchor| resolver.block_info(anchor.tx_ref));
+                let txid = anchor.and_then(|anchor| anchor.tx_ref.resolve(resolver));
+                (transition, block, txid)
+            })
+            .collect::<Vec<_>>();
+        transitions
+            .sort_by_key(|(_, block, _)| block.map(|block| block.height).unwrap_or(u32::MAX));
+
+        for (transition, block, txid) in transitions {
+            entries.extend(node_entries(
+                transition,
+                LedgerNode::Transition(transition.transition_id()),
+                block,
+                txid,
+            ));
+        }
+
+        Some(Ledger {
+            contract_id,
+            entries,
+        })
+    }
+}
+
+fn node_entries(
+    node: &impl Node,
+    ledger_node: LedgerNode,
+    block: Option<BlockInfo>,
+    witness_txid: Option<Txid>,
+) -> Vec<LedgerEntry> {
+    let entry =
+        |assignment_type, amount, seal_definition: Result<&seal::Revealed, &seal::Confidential>| {
+            let (direction, seal, counterparty_seal) = match seal_definition {
+                Ok(seal::Revealed::TxOutpoint(reveal)) => (
+                    LedgerDirection::Incoming,
+                    Some(OutPoint::from(reveal.clone())),
+                    None,
+                ),
+                Ok(seal::Revealed::WitnessVout { vout, .. }) => (
+                    LedgerDirection::Incoming,
+                    witness_txid.map(|txid| OutPoint::new(txid, *vout as u32)),
+                    None,
+                ),
+                Err(concealed) => (LedgerDirection::Outgoing, None, Some(*concealed)),
+            };
+            LedgerEntry {
+                block,
+                node: ledger_node,
+                assignment_type,
+                direction,
+                amount,
+                seal,
+                counterparty_seal,
+            }
+        };
+
+    let mut entries = vec![];
+    for (assignment_type, variant) in node.assignments() {
+        let assignment_type = *assignment_type;
+        match variant {
+            AssignmentsVariant::Void(set) => {
+                entries.extend(set.iter().map(|assignment| match assignment {
+                    Assignment::Revealed {
+                        seal_definition, ..
+                    } => entry(assignment_type, None, Ok(seal_definition)),
+                    Assignment::Confidential {
+                        seal_definition, ..
+                    } => entry(assignment_type, None, Err(seal_definition)),
+                }))
+            }
+            AssignmentsVariant::Homomorphic(_, set) => {
+                entries.extend(set.iter().map(|assignment| match assignment {
+                    Assignment::Revealed {
+                        seal_definition,
+                        assigned_state,
+                    } => entry(
+                        assignment_type,
+                        Some(assigned_state.amount),
+                        Ok(seal_definition),
+                    ),
+                    Assignment::Confidential {
+                        seal_definition, ..
+                    } => entry(assignment_type, None, Err(seal_definition)),
+                }))
+            }
+            AssignmentsVariant::Hashed(set) => {
+                entries.extend(set.iter().map(|assignment| match assignment {
+                    Assignment::Revealed {
+                        seal_definition, ..
+                    } => entry(assignment_type, None, Ok(seal_definition)),
+                    Assignment::Confidential {
+                        seal_definition, ..
+                    } => entry(assignment_type, None, Err(seal_definition)),
+                }))
+            }
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::{self, blind::OutpointReveal};
+    use crate::client_side_validation::CommitConceal;
+    use crate::rgb::testkit::MockChain;
+    use crate::rgb::{data, Annotations, Contract, Genesis, Metadata, SchemaId};
+    use bitcoin::Script;
+
+    #[test]
+    fn test_genesis_ledger() {
+        let mut chain = MockChain::new();
+        let own = chain.fund(10_000, Script::new());
+        let reveal = |outpoint: OutPoint, blinding| OutpointReveal {
+            blinding,
+            txid: outpoint.txid,
+            vout: outpoint.vout as u16,
+        };
+        let counterparty = seal::Revealed::TxOutpoint(reveal(own, 2)).conceal();
+
+        let genesis = Genesis::with(
+            SchemaId::default(),
+            bp::Chain::Testnet3,
+            Metadata::new(),
+            bmap! {
+                0 => AssignmentsVariant::Void(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal::Revealed::TxOutpoint(reveal(own, 1)),
+                        assigned_state: data::Void,
+                    },
+                    Assignment::Confidential {
+                        seal_definition: counterparty,
+                        assigned_state: data::Void,
+                    }
+                })
+            },
+            vec![],
+        );
+        let contract_id = genesis.contract_id();
+        let stash = Stash {
+            contracts: vec![Contract {
+                genesis: genesis.into(),
+                revealed: vec![],
+                partial: vec![],
+            }],
+            anchors: vec![],
+            txout_status: bmap! {},
+            annotations: Annotations::default(),
+        };
+
+        assert!(stash.ledger(ContractId::default(), &chain).is_none());
+        let ledger = stash.ledger(contract_id, &chain).unwrap();
+        assert_eq!(ledger.entries.len(), 2);
+
+        let incoming = ledger
+            .entries
+            .iter()
+            .find(|entry| entry.direction == LedgerDirection::Incoming)
+            .unwrap();
+        assert_eq!(incoming.node, LedgerNode::Genesis(contract_id));
+        assert_eq!(incoming.seal, Some(own));
+        assert_eq!(incoming.counterparty_seal, None);
+
+        let outgoing = ledger
+            .entries
+            .iter()
+            .find(|entry| entry.direction == LedgerDirection::Outgoing)
+            .unwrap();
+        assert_eq!(outgoing.seal, None);
+        assert_eq!(outgoing.counterparty_seal, Some(counterparty));
+
+        let csv = ledger.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some(LEDGER_CSV_HEADER));
+        assert_eq!(lines.count(), 2);
+        assert!(csv.contains(&format!(",,{},0,Incoming,,{},\n", contract_id, own)));
+
+        let json = ledger.to_json();
+        assert!(json.starts_with(&format!("{{\"contract_id\":\"{}\"", contract_id)));
+        assert!(json.contains(&format!("\"counterparty_seal\":\"{}\"", counterparty)));
+    }
+}
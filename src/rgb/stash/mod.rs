@@ -11,11 +11,33 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+mod batch;
+mod coin_control;
+mod consume;
+mod hygiene;
+mod labels;
+mod ledger;
+mod revocation;
+mod status;
+mod swap;
+mod watch;
+
+pub use batch::{transition_slot, BatchUpdate, TransferBatch};
+pub use coin_control::SpendHazard;
+pub use consume::{ConsumeError, StashDelta, StashStorage};
+pub use hygiene::HygieneReport;
+pub use labels::{Annotations, LabelTarget};
+pub use ledger::{Ledger, LedgerDirection, LedgerEntry, LedgerNode, LEDGER_CSV_HEADER};
+pub use revocation::RevocationReport;
+pub use status::{AnchorStatus, TxoutStatus};
+pub use swap::{Swap, SwapError, SwapHalf, SwapTerms};
+pub use watch::WatchList;
+
 use super::interfaces::{Coordinator, TxConductor, TxResolver};
 use super::{Anchor, Consignment, Contract, ContractId, Genesis, SealDefinition, Transition};
-use crate::lnpbp4::MultimsgCommitment;
-use bitcoin::Transaction;
-use std::collections::HashSet;
+use crate::lnpbp4::MultiCommitBlock;
+use bitcoin::{OutPoint, Transaction};
+use std::collections::{BTreeMap, HashSet};
 
 /// Top-level structure used by client wallets to manage all known RGB smart
 /// contracts and related data
@@ -28,6 +50,14 @@ pub struct Stash {
     /// state transitions under multiple contracts at the same time (via
     /// LNPBP-4 multimessage commitments)
     pub anchors: Vec<Anchor>,
+
+    /// Last known on-chain status of the outputs referenced by the stash,
+    /// updated with [Stash::update_txout_status]
+    pub txout_status: BTreeMap<OutPoint, TxoutStatus>,
+
+    /// User-defined labels for the stash data, which are not a part of any
+    /// commitment or consignment
+    pub annotations: Annotations,
 }
 
 /// With `Stash` we define a simple and uniform interface for all low-level
@@ -110,7 +140,7 @@ impl Stash {
 
 pub struct CoordinatedTransition {
     pub transitions: HashSet<ContractId, Transition>,
-    pub multi_commits: HashSet<SealDefinition, MultimsgCommitment>,
+    pub multi_commits: HashSet<SealDefinition, MultiCommitBlock>,
 }
 
 impl CoordinatedTransition {
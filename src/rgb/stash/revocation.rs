@@ -0,0 +1,158 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Reporting of the allocations revoked by contract issuers, for the wallets
+//! which opt in to follow issuer revocation policies
+
+use std::collections::BTreeMap;
+
+use super::Stash;
+use crate::rgb::{validation::RevokedAllocation, ContractId, Schema};
+
+/// Report on the known allocations revoked by contract issuers
+#[derive(Clone, PartialEq, Eq, Debug, Display, Default)]
+#[display_from(Debug)]
+pub struct RevocationReport {
+    /// Revoked allocations for each of the contracts having any
+    pub revoked: BTreeMap<ContractId, Vec<RevokedAllocation>>,
+}
+
+impl RevocationReport {
+    /// Returns `true` if no revoked allocations were found
+    #[inline]
+    pub fn is_clean(&self) -> bool {
+        self.revoked.is_empty()
+    }
+}
+
+impl Stash {
+    /// Finds allocations revoked by the issuers of all known contracts under
+    /// the given schema, using revocation policy defined by the schema.
+    /// Contracts under other schemas are ignored.
+    pub fn revocation_report(&self, schema: &Schema) -> RevocationReport {
+        let schema_id = schema.schema_id();
+        RevocationReport {
+            revoked: self
+                .contracts
+                .iter()
+                .filter(|contract| contract.genesis.schema_id() == schema_id)
+                .filter_map(|contract| {
+                    let revoked = schema.revoked_allocations(
+                        &contract.genesis,
+                        contract.revealed.iter().chain(contract.partial.iter()),
+                    );
+                    if revoked.is_empty() {
+                        None
+                    } else {
+                        Some((contract.genesis.contract_id(), revoked))
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::{self, blind::OutpointReveal};
+    use crate::client_side_validation::CommitConceal;
+    use crate::rgb::schema::{test::schema, RevocationSchema};
+    use crate::rgb::{
+        data, seal, Annotations, Assignment, AssignmentsVariant, Contract, Genesis, Node,
+        Transition,
+    };
+    use crate::strict_encoding::strict_encode;
+    use bitcoin::Txid;
+    use std::collections::BTreeSet;
+
+    fn seal(vout: u16) -> seal::Revealed {
+        seal::Revealed::TxOutpoint(OutpointReveal {
+            blinding: 1,
+            txid: Txid::default(),
+            vout,
+        })
+    }
+
+    fn revocation(transition_type: usize, seals: &[seal::Revealed]) -> Transition {
+        let mut values = seals
+            .iter()
+            .map(|seal| data::Revealed::Bytes(strict_encode(&seal.conceal()).unwrap()))
+            .collect::<BTreeSet<_>>();
+        values.insert(data::Revealed::Bytes(vec![0xFF]));
+        Transition::with(transition_type, bmap! { 1 => values }, bmap! {}, vec![])
+    }
+
+    #[test]
+    fn test_revocation_report() {
+        let mut schema = schema();
+        let genesis = Genesis::with(
+            schema.schema_id(),
+            bp::Chain::Testnet3,
+            bmap! {},
+            bmap! {
+                0 => AssignmentsVariant::Void(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal(0),
+                        assigned_state: data::Void,
+                    },
+                    Assignment::Confidential {
+                        seal_definition: seal(1).conceal(),
+                        assigned_state: data::Void,
+                    }
+                })
+            },
+            vec![],
+        );
+        let mut stash = Stash {
+            contracts: vec![Contract {
+                genesis: genesis.clone().into(),
+                revealed: vec![
+                    revocation(1, &[seal(1), seal(2)]),
+                    revocation(0, &[seal(0)]),
+                ],
+                partial: vec![],
+            }],
+            anchors: vec![],
+            txout_status: bmap! {},
+            annotations: Annotations::default(),
+        };
+        assert!(stash.revocation_report(&schema).is_clean());
+
+        schema.revocation = Some(RevocationSchema {
+            transition_type: 1,
+            revoked_seals: 1,
+        });
+        assert!(stash.revocation_report(&schema).is_clean());
+
+        stash.contracts[0].genesis = Genesis::with(
+            schema.schema_id(),
+            bp::Chain::Testnet3,
+            bmap! {},
+            genesis.assignments().clone(),
+            vec![],
+        )
+        .into();
+        let report = stash.revocation_report(&schema);
+        let contract_id = stash.contracts[0].genesis.contract_id();
+        assert_eq!(
+            report.revoked[&contract_id],
+            vec![RevokedAllocation {
+                node_id: None,
+                assignment_type: 0,
+                seal: seal(1).conceal(),
+                revocation: stash.contracts[0].revealed[0].transition_id(),
+            }]
+        );
+    }
+}
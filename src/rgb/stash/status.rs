@@ -0,0 +1,223 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Tracking of the on-chain status of the transaction outputs referenced by
+//! the stash, allowing wallets to distinguish commitments which are still
+//! live on-chain from the historical ones
+
+use bitcoin::{OutPoint, Txid};
+
+use super::Stash;
+use crate::bp::OutpointSet;
+use crate::rgb::interfaces::{SpendResolver, TxRefResolver, TxoutResolver};
+use crate::rgb::Anchor;
+
+/// On-chain status of a transaction output
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum TxoutStatus {
+    /// Output is not known to the resolver
+    Unknown,
+
+    /// Output exists and is not spent yet
+    Unspent,
+
+    /// Output is spent by the transaction with the given id
+    Spent(Txid),
+}
+
+impl TxoutStatus {
+    /// Requests status of the output from the resolver
+    pub fn resolve(outpoint: OutPoint, resolver: &(impl TxoutResolver + SpendResolver)) -> Self {
+        if resolver.txout(outpoint).is_none() {
+            return TxoutStatus::Unknown;
+        }
+        match resolver.spending_tx(outpoint) {
+            Some(txid) => TxoutStatus::Spent(txid),
+            None => TxoutStatus::Unspent,
+        }
+    }
+}
+
+/// On-chain status of the commitment made by an anchor
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum AnchorStatus {
+    /// Status of the witness transaction outputs is not known
+    Unknown,
+
+    /// Some of the witness transaction outputs are still unspent, so the
+    /// commitment may be live on-chain
+    Live,
+
+    /// All of the witness transaction outputs are spent, so the commitment is
+    /// historical
+    Historical,
+}
+
+impl Stash {
+    /// Updates [Stash::txout_status] for all outputs referenced by the stash:
+    /// outpoints of the known single-use seals and outputs of the anchor
+    /// witness transactions. Returns the set of outputs which status was
+    /// changed by the update.
+    pub fn update_txout_status(
+        &mut self,
+        resolver: &(impl TxoutResolver + SpendResolver + TxRefResolver),
+    ) -> OutpointSet {
+        let mut outpoints = self
+            .seal_outpoints()
+            .keys()
+            .cloned()
+            .collect::<OutpointSet>();
+        for anchor in &self.anchors {
+            let txid = match anchor.tx_ref.resolve(resolver) {
+                Some(txid) => txid,
+                None => continue,
+            };
+            outpoints.extend(
+                (0u32..)
+                    .map(|vout| OutPoint::new(txid, vout))
+                    .take_while(|outpoint| resolver.txout(*outpoint).is_some()),
+            );
+        }
+
+        outpoints
+            .into_iter()
+            .filter(|outpoint| {
+                let status = TxoutStatus::resolve(*outpoint, resolver);
+                self.txout_status.insert(*outpoint, status) != Some(status)
+            })
+            .collect()
+    }
+
+    /// Reports status of the anchor commitment using the data collected by
+    /// the last [Stash::update_txout_status] call. Since anchors do not keep
+    /// the number of the output containing the commitment, anchor is
+    /// considered live while any of its witness transaction outputs remains
+    /// unspent.
+    pub fn anchor_status(&self, anchor: &Anchor, resolver: &impl TxRefResolver) -> AnchorStatus {
+        let txid = match anchor.tx_ref.resolve(resolver) {
+            Some(txid) => txid,
+            None => return AnchorStatus::Unknown,
+        };
+        let mut status = AnchorStatus::Unknown;
+        for (_, txout_status) in self
+            .txout_status
+            .range(OutPoint::new(txid, 0)..=OutPoint::new(txid, u32::MAX))
+        {
+            match txout_status {
+                TxoutStatus::Unspent => return AnchorStatus::Live,
+                TxoutStatus::Spent(_) => status = AnchorStatus::Historical,
+                TxoutStatus::Unknown => {}
+            }
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::{self, blind::OutpointReveal, dbc};
+    use crate::commit_verify::TryCommitVerify;
+    use crate::lnpbp4::MultiCommitBlock;
+    use crate::rgb::testkit::MockChain;
+    use crate::rgb::{
+        data, seal, Annotations, Assignment, AssignmentsVariant, Contract, Genesis, Metadata,
+        SchemaId, TxRef,
+    };
+    use bitcoin::{Script, TxOut};
+
+    fn txout() -> TxOut {
+        TxOut {
+            value: 1000,
+            script_pubkey: Script::new(),
+        }
+    }
+
+    #[test]
+    fn test_txout_status() {
+        let mut chain = MockChain::new();
+        let seal_outpoint = chain.fund(10_000, Script::new());
+        let funding = chain.fund(10_000, Script::new());
+        let witness_txid = chain.spend(&[funding], vec![txout(), txout()]).unwrap();
+
+        let genesis = Genesis::with(
+            SchemaId::default(),
+            bp::Chain::Testnet3,
+            Metadata::new(),
+            bmap! {
+                0 => AssignmentsVariant::Void(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal::Revealed::TxOutpoint(OutpointReveal {
+                            blinding: 1,
+                            txid: seal_outpoint.txid,
+                            vout: seal_outpoint.vout as u16,
+                        }),
+                        assigned_state: data::Void,
+                    }
+                })
+            },
+            vec![],
+        );
+        let anchor = Anchor {
+            tx_ref: TxRef::Txid(witness_txid),
+            commitment: MultiCommitBlock::try_commit(&bmap! {}).unwrap(),
+            dbc_proof: dbc::Proof::from(bp::test::gen_secp_pubkeys(1)[0]),
+        };
+        let mut stash = Stash {
+            contracts: vec![Contract {
+                genesis: genesis.into(),
+                revealed: vec![],
+                partial: vec![],
+            }],
+            anchors: vec![anchor.clone()],
+            txout_status: bmap! {},
+            annotations: Annotations::default(),
+        };
+        assert_eq!(stash.anchor_status(&anchor, &chain), AnchorStatus::Unknown);
+
+        let witness0 = OutPoint::new(witness_txid, 0);
+        let witness1 = OutPoint::new(witness_txid, 1);
+        assert_eq!(
+            stash.update_txout_status(&chain),
+            OutpointSet::from(vec![seal_outpoint, witness0, witness1])
+        );
+        assert_eq!(stash.txout_status[&seal_outpoint], TxoutStatus::Unspent);
+        assert_eq!(stash.anchor_status(&anchor, &chain), AnchorStatus::Live);
+        assert!(stash.update_txout_status(&chain).is_empty());
+
+        let closing_txid = chain
+            .spend(&[seal_outpoint, witness0], vec![txout()])
+            .unwrap();
+        assert_eq!(
+            stash.update_txout_status(&chain),
+            OutpointSet::from(vec![seal_outpoint, witness0])
+        );
+        assert_eq!(
+            stash.txout_status[&seal_outpoint],
+            TxoutStatus::Spent(closing_txid)
+        );
+        assert_eq!(stash.anchor_status(&anchor, &chain), AnchorStatus::Live);
+
+        chain.spend(&[witness1], vec![txout()]).unwrap();
+        assert_eq!(
+            stash.update_txout_status(&chain),
+            OutpointSet::from(vec![witness1])
+        );
+        assert_eq!(
+            stash.anchor_status(&anchor, &chain),
+            AnchorStatus::Historical
+        );
+    }
+}
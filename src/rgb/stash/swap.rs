@@ -0,0 +1,372 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Two-party atomic swaps of assets under different (or the same) contracts.
+//!
+//! Swaps may be done in two ways:
+//! * with a shared anchor, where both state transitions are committed by a
+//!   single witness transaction spending seals of both parties: either the
+//!   transaction is mined and both transfers become valid, or none of them
+//!   is. Use [Swap] for this case.
+//! * with coordinated anchors, where each party commits to its state
+//!   transition in its own witness transaction, and the assets are assigned
+//!   with hash time locks bound to the same preimage. The maker, knowing the
+//!   preimage, claims the taker asset revealing the preimage, which allows
+//!   the taker to claim the maker asset; otherwise both are refunded after
+//!   the timeouts. Use [SwapTerms] to produce the locks.
+
+use bitcoin::hashes::sha256;
+use bitcoin::secp256k1;
+use core::convert::TryFrom;
+use std::sync::Arc;
+
+use super::batch::{transition_slot, BatchUpdate, TransferBatch};
+use crate::bp::dbc;
+use crate::lnpbp4::{MultiCommitBlock, MultiCommitError};
+use crate::rgb::{
+    schema, validation, Anchor, Assignment, AssignmentsVariant, ContractId, Genesis, HashTimeLock,
+    Node, Schema, SealDefinition, Transition, TxRef,
+};
+
+/// Errors detected in the swap proposals
+#[derive(Clone, PartialEq, Eq, Debug, Display, From, Error)]
+#[display_from(Debug)]
+pub enum SwapError {
+    /// Counter-party half does not conform to its contract schema
+    #[derive_from]
+    Validation(validation::Failure),
+
+    /// Counter-party half does not assign state to all of the expected seals
+    SealsNotAssigned,
+
+    /// Counter-party half does not assign the state with the expected hash
+    /// time lock
+    LockMismatch,
+
+    /// Both halves of the swap contain the same state transition
+    SameTransition,
+
+    /// Refund timeout of the taker must precede the maker one, otherwise the
+    /// maker may claim the taker asset and refund its own one
+    TimeoutOrder,
+}
+
+/// One side of the swap: state transition transferring the asset of one of
+/// the parties to the other party
+#[derive(Clone, Debug, Display)]
+#[display_from(Debug)]
+pub struct SwapHalf {
+    pub genesis: Arc<Genesis>,
+    pub transition: Transition,
+}
+
+impl SwapHalf {
+    #[inline]
+    pub fn with(genesis: &Arc<Genesis>, transition: Transition) -> Self {
+        Self {
+            genesis: genesis.clone(),
+            transition,
+        }
+    }
+
+    #[inline]
+    pub fn contract_id(&self) -> ContractId {
+        self.genesis.contract_id()
+    }
+
+    /// Verifies the half proposed by the counter-party: the genesis and the
+    /// state transition must conform to the contract schema, and the
+    /// transition must assign state to all of the expected seals. History
+    /// of the closed seals has to be validated separately with the
+    /// consignment provided by the counter-party.
+    pub fn verify(&self, schema: &Schema, expected: &[SealDefinition]) -> Result<(), SwapError> {
+        schema.validate_genesis_structure(&self.genesis)?;
+        schema.validate_transition_structure(&self.transition)?;
+        let defined = self
+            .transition
+            .assignment_types()
+            .into_iter()
+            .flat_map(|assignment_type| {
+                self.transition
+                    .defined_seals(assignment_type)
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        if !expected.iter().all(|seal| defined.contains(seal)) {
+            Err(SwapError::SealsNotAssigned)?
+        }
+        Ok(())
+    }
+
+    /// Verifies that the half proposed by the counter-party assigns state of
+    /// the given type locked with the expected hash time lock, as required
+    /// for the swaps with coordinated anchors
+    pub fn verify_lock(
+        &self,
+        assignment_type: schema::AssignmentsType,
+        expected: &HashTimeLock,
+    ) -> Result<(), SwapError> {
+        match self.transition.assignments().get(&assignment_type) {
+            Some(AssignmentsVariant::Hashed(set)) if !set.is_empty() => {
+                if set.iter().all(|assignment| match assignment {
+                    Assignment::Revealed { assigned_state, .. } => {
+                        HashTimeLock::try_from(assigned_state).ok().as_ref() == Some(expected)
+                    }
+                    Assignment::Confidential { .. } => false,
+                }) {
+                    Ok(())
+                } else {
+                    Err(SwapError::LockMismatch)
+                }
+            }
+            _ => Err(SwapError::LockMismatch),
+        }
+    }
+
+    /// Checks that the anchor commits to the state transition of this half
+    pub fn is_anchored(&self, anchor: &Anchor) -> bool {
+        let (protocol_id, digest) = transition_slot(self.transition.transition_id());
+        anchor.commitment.verify_message(&protocol_id, digest)
+    }
+}
+
+/// Swap with a shared anchor. The maker proposes its half, the taker
+/// verifies it with [SwapHalf::verify] and adds its own half; then both
+/// state transitions are committed by a single witness transaction, which
+/// has to spend the seals closed by both of the halves.
+#[derive(Clone, Debug, Display)]
+#[display_from(Debug)]
+pub struct Swap {
+    pub maker: SwapHalf,
+    pub taker: SwapHalf,
+}
+
+impl Swap {
+    pub fn with(maker: SwapHalf, taker: SwapHalf) -> Result<Self, SwapError> {
+        if maker.transition.transition_id() == taker.transition.transition_id() {
+            Err(SwapError::SameTransition)?
+        }
+        Ok(Self { maker, taker })
+    }
+
+    /// Transfer batch with both of the state transitions
+    pub fn batch(&self) -> TransferBatch {
+        let mut batch = TransferBatch::new();
+        batch.add(&self.maker.genesis, self.maker.transition.clone());
+        batch.add(&self.taker.genesis, self.taker.transition.clone());
+        batch
+    }
+
+    /// Creates multimessage commitment to both state transitions, which has
+    /// to be embedded into the shared witness transaction
+    #[inline]
+    pub fn commit(&self) -> Result<MultiCommitBlock, MultiCommitError> {
+        self.batch().commit()
+    }
+
+    /// Finalizes the swap with the commitment embedded into the shared
+    /// witness transaction. The resulting update produces consignments for
+    /// each of the parties with [BatchUpdate::consign]. Fails returning the
+    /// swap back if the commitment does not cover both of the halves.
+    pub fn finalize(
+        self,
+        commitment: MultiCommitBlock,
+        tx_ref: TxRef,
+        dbc_proof: dbc::Proof,
+    ) -> Result<BatchUpdate, Self> {
+        let batch = self.batch();
+        batch.finalize(commitment, tx_ref, dbc_proof).or(Err(self))
+    }
+
+    /// Verifies that the anchor commits to both halves of the swap, so both
+    /// transfers are valid or none of them is
+    #[inline]
+    pub fn is_anchored(&self, anchor: &Anchor) -> bool {
+        self.maker.is_anchored(anchor) && self.taker.is_anchored(anchor)
+    }
+}
+
+/// Terms of the swap with coordinated anchors, defining hash time locks for
+/// the assets of both parties
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct SwapTerms {
+    /// Hash of the preimage known to the maker only
+    pub hash: sha256::Hash,
+    /// Block height after which the maker asset is refunded
+    pub maker_timeout: u32,
+    /// Block height after which the taker asset is refunded; must precede
+    /// the maker timeout, leaving the taker time to claim the maker asset
+    /// once the preimage is revealed
+    pub taker_timeout: u32,
+    pub maker_key: secp256k1::PublicKey,
+    pub taker_key: secp256k1::PublicKey,
+}
+
+impl SwapTerms {
+    pub fn with(
+        hash: sha256::Hash,
+        maker_timeout: u32,
+        taker_timeout: u32,
+        maker_key: secp256k1::PublicKey,
+        taker_key: secp256k1::PublicKey,
+    ) -> Result<Self, SwapError> {
+        if taker_timeout >= maker_timeout {
+            Err(SwapError::TimeoutOrder)?
+        }
+        Ok(Self {
+            hash,
+            maker_timeout,
+            taker_timeout,
+            maker_key,
+            taker_key,
+        })
+    }
+
+    /// Lock for the maker asset, claimable by the taker
+    pub fn maker_lock(&self) -> HashTimeLock {
+        HashTimeLock {
+            hash: self.hash,
+            timeout: self.maker_timeout,
+            receiver: self.taker_key,
+            refund: self.maker_key,
+        }
+    }
+
+    /// Lock for the taker asset, claimable by the maker
+    pub fn taker_lock(&self) -> HashTimeLock {
+        HashTimeLock {
+            hash: self.hash,
+            timeout: self.taker_timeout,
+            receiver: self.maker_key,
+            refund: self.taker_key,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::{self, blind::OutpointReveal};
+    use crate::commit_verify::TryCommitVerify;
+    use crate::rgb::schema::test::schema;
+    use crate::rgb::{data, seal, Metadata};
+    use bitcoin::hashes::Hash;
+    use bitcoin::Txid;
+
+    fn seal(blinding: u64) -> SealDefinition {
+        seal::Revealed::TxOutpoint(OutpointReveal {
+            blinding,
+            txid: Txid::default(),
+            vout: 0,
+        })
+    }
+
+    fn half(schema: &Schema, chain: bp::Chain, blinding: u64) -> SwapHalf {
+        let genesis = Arc::new(Genesis::with(
+            schema.schema_id(),
+            chain,
+            bmap! { 0 => bset! { data::Revealed::String(s!("Asset")) } },
+            bmap! {
+                0 => AssignmentsVariant::Void(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal(0),
+                        assigned_state: data::Void,
+                    }
+                })
+            },
+            vec![],
+        ));
+        let transition = Transition::with(
+            0,
+            Metadata::new(),
+            bmap! {
+                0 => AssignmentsVariant::Void(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal(blinding),
+                        assigned_state: data::Void,
+                    }
+                })
+            },
+            vec![],
+        );
+        SwapHalf::with(&genesis, transition)
+    }
+
+    #[test]
+    fn test_swap_shared_anchor() {
+        let schema = schema();
+        let maker = half(&schema, bp::Chain::Testnet3, 1);
+        let taker = half(&schema, bp::Chain::Regtest, 2);
+        assert_eq!(maker.verify(&schema, &[seal(1)]), Ok(()));
+        assert_eq!(
+            maker.verify(&schema, &[seal(2)]),
+            Err(SwapError::SealsNotAssigned)
+        );
+        assert!(Swap::with(maker.clone(), maker.clone()).is_err());
+
+        let swap = Swap::with(maker, taker).unwrap();
+        let foreign = MultiCommitBlock::try_commit(&bmap! {}).unwrap();
+        let pubkey = bp::test::gen_secp_pubkeys(1)[0];
+        let tx_ref = TxRef::Txid(Txid::default());
+        let swap = swap
+            .finalize(foreign, tx_ref, dbc::Proof::from(pubkey))
+            .unwrap_err();
+
+        let update = swap
+            .clone()
+            .finalize(swap.commit().unwrap(), tx_ref, dbc::Proof::from(pubkey))
+            .unwrap();
+        assert!(swap.is_anchored(&update.anchor));
+        assert_eq!(update.consign(&[seal(1)]).len(), 1);
+        assert_eq!(update.consign(&[seal(2)]).len(), 1);
+    }
+
+    #[test]
+    fn test_swap_terms() {
+        let keys = bp::test::gen_secp_pubkeys(2);
+        let hash = sha256::Hash::hash(&[7u8; 32]);
+        assert_eq!(
+            SwapTerms::with(hash, 100, 100, keys[0], keys[1]),
+            Err(SwapError::TimeoutOrder)
+        );
+        let terms = SwapTerms::with(hash, 200, 100, keys[0], keys[1]).unwrap();
+        assert_eq!(terms.maker_lock().receiver, keys[1]);
+        assert_eq!(terms.taker_lock().receiver, keys[0]);
+
+        let schema = schema();
+        let mut offer = half(&schema, bp::Chain::Testnet3, 1);
+        assert_eq!(
+            offer.verify_lock(0, &terms.maker_lock()),
+            Err(SwapError::LockMismatch)
+        );
+        offer.transition = Transition::with(
+            0,
+            Metadata::new(),
+            bmap! {
+                1 => AssignmentsVariant::Hashed(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal(1),
+                        assigned_state: terms.maker_lock().into(),
+                    }
+                })
+            },
+            vec![],
+        );
+        assert_eq!(offer.verify_lock(1, &terms.maker_lock()), Ok(()));
+        assert_eq!(
+            offer.verify_lock(1, &terms.taker_lock()),
+            Err(SwapError::LockMismatch)
+        );
+    }
+}
@@ -0,0 +1,156 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Generation of the list of on-chain data which must be monitored by a
+//! wallet in order to follow changes in the state of the known contracts
+
+use bitcoin::hashes::{hex::ToHex, sha256, Hash};
+use bitcoin::{OutPoint, Txid};
+use std::collections::BTreeSet;
+
+use super::Stash;
+use crate::bp::OutpointSet;
+use crate::rgb::interfaces::TxoutResolver;
+
+/// Set of transaction outputs and transactions which have to be watched by
+/// a chain monitoring service for the wallet using [Stash]
+#[derive(Clone, PartialEq, Eq, Debug, Display, Default)]
+#[display_from(Debug)]
+pub struct WatchList {
+    /// Outpoints of the known single-use seals; spending any of them closes
+    /// the seal and must be followed by a state transition
+    pub outpoints: OutpointSet,
+
+    /// Witness transactions of the known anchors, containing own commitment
+    /// outputs, which confirmation status must be tracked
+    pub txids: BTreeSet<Txid>,
+}
+
+impl WatchList {
+    /// Returns `true` if there is nothing to watch
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.outpoints.is_empty() && self.txids.is_empty()
+    }
+
+    /// Produces list of Electrum script hashes (hex-encoded SHA256 of the
+    /// `scriptPubkey` in reversed byte order) for all watched outpoints,
+    /// ready for `blockchain.scripthash.subscribe` calls. Fails with the
+    /// first outpoint which can't be resolved with the provided resolver.
+    pub fn electrum_scripthashes(
+        &self,
+        resolver: &impl TxoutResolver,
+    ) -> Result<BTreeSet<String>, OutPoint> {
+        self.outpoints
+            .iter()
+            .map(|outpoint| {
+                let txout = resolver.txout(*outpoint).ok_or(*outpoint)?;
+                let mut hash = sha256::Hash::hash(txout.script_pubkey.as_bytes()).into_inner();
+                hash.reverse();
+                Ok(hash.to_hex())
+            })
+            .collect()
+    }
+}
+
+impl Stash {
+    /// Composes [WatchList] for all contracts known to the stash. Seals
+    /// defined as outputs of the witness transaction and witness transactions
+    /// themselves are included only for anchors referencing the transaction
+    /// by its full id.
+    pub fn watch_list(&self) -> WatchList {
+        WatchList {
+            outpoints: self.seal_outpoints().keys().cloned().collect(),
+            txids: self
+                .anchors
+                .iter()
+                .filter_map(|anchor| anchor.tx_ref.txid())
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bp::{self, blind::OutpointReveal};
+    use crate::rgb::{
+        data, seal, Annotations, Assignment, AssignmentsVariant, Contract, Genesis, Metadata,
+        SchemaId,
+    };
+    use bitcoin::{Script, TxOut};
+    use std::collections::BTreeMap;
+
+    struct Utxos(BTreeMap<OutPoint, TxOut>);
+
+    impl TxoutResolver for Utxos {
+        fn txout(&self, outpoint: OutPoint) -> Option<TxOut> {
+            self.0.get(&outpoint).cloned()
+        }
+    }
+
+    #[test]
+    fn test_watch_list() {
+        let txid = Txid::from_slice(&[1u8; 32]).unwrap();
+        let genesis = Genesis::with(
+            SchemaId::default(),
+            bp::Chain::Testnet3,
+            Metadata::new(),
+            bmap! {
+                0 => AssignmentsVariant::Void(bset! {
+                    Assignment::Revealed {
+                        seal_definition: seal::Revealed::TxOutpoint(OutpointReveal {
+                            blinding: 1,
+                            txid,
+                            vout: 2,
+                        }),
+                        assigned_state: data::Void,
+                    }
+                })
+            },
+            vec![],
+        );
+        let stash = Stash {
+            contracts: vec![Contract {
+                genesis: genesis.into(),
+                revealed: vec![],
+                partial: vec![],
+            }],
+            anchors: vec![],
+            txout_status: bmap! {},
+            annotations: Annotations::default(),
+        };
+
+        let outpoint = OutPoint::new(txid, 2);
+        let watch_list = stash.watch_list();
+        assert!(!watch_list.is_empty());
+        assert_eq!(watch_list.outpoints, OutpointSet::from(vec![outpoint]));
+        assert!(watch_list.txids.is_empty());
+
+        let mut utxos = Utxos(bmap! {});
+        assert_eq!(watch_list.electrum_scripthashes(&utxos), Err(outpoint));
+        utxos.0.insert(
+            outpoint,
+            TxOut {
+                value: 1000,
+                script_pubkey: Script::new(),
+            },
+        );
+        assert_eq!(
+            watch_list.electrum_scripthashes(&utxos),
+            Ok(bset! {
+                "55b852781b9995a44c939b64e441ae2724b96f99c8f4fb9a141cfc9842c4b0e3".to_string()
+            })
+        );
+    }
+}
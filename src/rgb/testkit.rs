@@ -0,0 +1,288 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! In-memory blockchain for integration tests. [MockChain] keeps
+//! transactions, spends and blocks and implements resolver interfaces used
+//! by RGB, so the whole issue → transfer → validate workflow can be tested
+//! without a bitcoin node.
+
+use bitcoin::hashes::Hash;
+use bitcoin::{BlockHash, OutPoint, Script, Transaction, TxIn, TxOut, Txid};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use super::interfaces::{
+    BlockInfo, ChainResolver, SpendResolver, TxRefResolver, TxResolver, TxoutResolver,
+};
+use super::TxRef;
+use crate::bp::{BlockChecksum, Descriptor, ShortId};
+use crate::strict_encoding::Timestamp;
+
+/// Timestamp of the first mined block
+pub const GENESIS_TIMESTAMP: i64 = 1_231_006_505;
+
+/// Time between mined blocks, in seconds
+pub const BLOCK_INTERVAL: i64 = 600;
+
+/// Reasons for rejecting transaction by [MockChain]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display_from(Debug)]
+pub enum MockChainError {
+    /// Transaction spends output which does not exist
+    UnknownOutput(OutPoint),
+
+    /// Transaction spends output which is already spent by other
+    /// transaction
+    DoubleSpend(OutPoint, Txid),
+
+    /// Transaction is already known
+    DuplicateTx(Txid),
+}
+
+/// In-memory blockchain with mempool. Blocks are mined on request, starting
+/// from height 1.
+#[derive(Clone, Debug, Default)]
+pub struct MockChain {
+    txs: HashMap<Txid, Transaction>,
+    spends: HashMap<OutPoint, Txid>,
+    mempool: Vec<Txid>,
+    blocks: Vec<Vec<Txid>>,
+    nonce: u32,
+}
+
+impl MockChain {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates transaction without inputs having single output with the
+    /// given value and `scriptPubkey` and puts it into the mempool. Returns
+    /// the created output.
+    pub fn fund(&mut self, value: u64, script_pubkey: Script) -> OutPoint {
+        self.nonce += 1;
+        let tx = Transaction {
+            version: 2,
+            lock_time: self.nonce,
+            input: vec![],
+            output: vec![TxOut {
+                value,
+                script_pubkey,
+            }],
+        };
+        let txid = self
+            .broadcast(tx)
+            .expect("Funding transactions have no inputs and unique lock time");
+        OutPoint::new(txid, 0)
+    }
+
+    /// Creates transaction spending the given outputs and puts it into the
+    /// mempool
+    pub fn spend(
+        &mut self,
+        inputs: &[OutPoint],
+        outputs: Vec<TxOut>,
+    ) -> Result<Txid, MockChainError> {
+        self.broadcast(Transaction {
+            version: 2,
+            lock_time: 0,
+            input: inputs
+                .iter()
+                .map(|previous_output| TxIn {
+                    previous_output: *previous_output,
+                    sequence: 0xFFFFFFFF,
+                    ..Default::default()
+                })
+                .collect(),
+            output: outputs,
+        })
+    }
+
+    /// Puts transaction into the mempool, checking that all of its inputs
+    /// spend existing and unspent outputs. Transactions are not validated
+    /// otherwise.
+    pub fn broadcast(&mut self, tx: Transaction) -> Result<Txid, MockChainError> {
+        let txid = tx.txid();
+        if self.txs.contains_key(&txid) {
+            Err(MockChainError::DuplicateTx(txid))?
+        }
+        for input in &tx.input {
+            let prevout = input.previous_output;
+            if self.txout(prevout).is_none() {
+                Err(MockChainError::UnknownOutput(prevout))?
+            }
+            if let Some(spender) = self.spends.get(&prevout) {
+                Err(MockChainError::DoubleSpend(prevout, *spender))?
+            }
+        }
+        for input in &tx.input {
+            self.spends.insert(input.previous_output, txid);
+        }
+        self.mempool.push(txid);
+        self.txs.insert(txid, tx);
+        Ok(txid)
+    }
+
+    /// Mines all mempool transactions into a new block, returning its height
+    pub fn mine(&mut self) -> u32 {
+        let block = std::mem::take(&mut self.mempool);
+        self.blocks.push(block);
+        self.height()
+    }
+
+    /// Returns height of the last mined block, or zero if no blocks were
+    /// mined
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.blocks.len() as u32
+    }
+
+    /// Returns known (mined or mempool) transaction
+    #[inline]
+    pub fn transaction(&self, txid: Txid) -> Option<&Transaction> {
+        self.txs.get(&txid)
+    }
+
+    /// Returns transaction spending the output, i.e. closing the single-use
+    /// seal defined over it
+    #[inline]
+    pub fn spending_tx(&self, outpoint: OutPoint) -> Option<Txid> {
+        self.spends.get(&outpoint).copied()
+    }
+
+    /// Returns height of the block containing the transaction and its index
+    /// within the block, if the transaction is mined
+    pub fn tx_position(&self, txid: Txid) -> Option<(u32, u16)> {
+        self.blocks.iter().enumerate().find_map(|(index, block)| {
+            block
+                .iter()
+                .position(|id| *id == txid)
+                .map(|tx_index| (index as u32 + 1, tx_index as u16))
+        })
+    }
+
+    /// Returns deterministic hash of the block at the given height
+    pub fn block_hash(height: u32) -> BlockHash {
+        BlockHash::hash(&height.to_le_bytes())
+    }
+}
+
+impl TxResolver for MockChain {
+    fn transaction(&self, tx_ref: TxRef) -> Option<Transaction> {
+        self.txs.get(&tx_ref.resolve(self)?).cloned()
+    }
+}
+
+impl TxRefResolver for MockChain {
+    fn short_id(&self, txid: Txid) -> Option<ShortId> {
+        let (block_height, tx_index) = self.tx_position(txid)?;
+        ShortId::try_from(Descriptor::OnchainTransaction {
+            block_height,
+            block_checksum: BlockChecksum::from(Self::block_hash(block_height)),
+            tx_index,
+        })
+        .ok()
+    }
+
+    fn txid(&self, short_id: ShortId) -> Option<Txid> {
+        let id = short_id.into_u64();
+        if id & (ShortId::FLAG_OFFCHAIN | ShortId::FLAG_INOUT | ShortId::MASK_INOUT) != 0 {
+            return None;
+        }
+        let block_height = ((id & ShortId::MASK_BLOCK) >> ShortId::SHIFT_BLOCK) as u32;
+        let block_checksum = (id & ShortId::MASK_BLOCKCHECK) >> ShortId::SHIFT_BLOCKCHECK;
+        let tx_index = (id & ShortId::MASK_TXIDX) >> ShortId::SHIFT_TXIDX;
+        if block_checksum != *BlockChecksum::from(Self::block_hash(block_height)) as u64 {
+            return None;
+        }
+        self.blocks
+            .get(block_height.checked_sub(1)? as usize)?
+            .get(tx_index as usize)
+            .copied()
+    }
+}
+
+impl ChainResolver for MockChain {
+    fn block_info(&self, tx_ref: TxRef) -> Option<BlockInfo> {
+        let (height, _) = self.tx_position(tx_ref.resolve(self)?)?;
+        Some(BlockInfo {
+            height,
+            timestamp: Timestamp::from_unix_secs(
+                GENESIS_TIMESTAMP + height as i64 * BLOCK_INTERVAL,
+            )?,
+        })
+    }
+}
+
+impl TxoutResolver for MockChain {
+    fn txout(&self, outpoint: OutPoint) -> Option<TxOut> {
+        self.txs
+            .get(&outpoint.txid)?
+            .output
+            .get(outpoint.vout as usize)
+            .cloned()
+    }
+}
+
+impl SpendResolver for MockChain {
+    #[inline]
+    fn spending_tx(&self, outpoint: OutPoint) -> Option<Txid> {
+        self.spends.get(&outpoint).copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_mock_chain() {
+        let mut chain = MockChain::new();
+        let funding = chain.fund(10_000, Script::new());
+        let other = chain.fund(10_000, Script::new());
+        assert_ne!(funding, other);
+        assert_eq!(chain.txout(funding).unwrap().value, 10_000);
+
+        let txout = TxOut {
+            value: 9_000,
+            script_pubkey: Script::new(),
+        };
+        let txid = chain.spend(&[funding], vec![txout.clone()]).unwrap();
+        assert_eq!(chain.spending_tx(funding), Some(txid));
+        assert_eq!(chain.spending_tx(other), None);
+        assert_eq!(
+            chain.spend(&[funding, other], vec![]),
+            Err(MockChainError::DoubleSpend(funding, txid))
+        );
+        let unknown = OutPoint::new(txid, 1);
+        assert_eq!(
+            chain.spend(&[unknown], vec![]),
+            Err(MockChainError::UnknownOutput(unknown))
+        );
+
+        assert_eq!(chain.short_id(txid), None);
+        assert_eq!(chain.block_info(TxRef::Txid(txid)), None);
+        assert_eq!(chain.mine(), 1);
+        assert_eq!(chain.tx_position(txid), Some((1, 2)));
+
+        let short_id = chain.short_id(txid).unwrap();
+        assert_eq!(chain.txid(short_id), Some(txid));
+        let block = chain.block_info(TxRef::ShortId(short_id)).unwrap();
+        assert_eq!(block, chain.block_info(TxRef::Txid(txid)).unwrap());
+        assert_eq!(block.height, 1);
+        assert_eq!(
+            block.timestamp.unix_secs(),
+            GENESIS_TIMESTAMP + BLOCK_INTERVAL
+        );
+    }
+}
@@ -0,0 +1,313 @@
+// LNP/BP Core Library implementing LNPBP specifications & standards
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Validation of RGB contract data against the schema
+
+use super::{schema, seal, BlockInfo, Node, SchemaId, TransitionId, TxRef};
+use crate::strict_encoding::{self, StrictEncode, Timestamp};
+
+/// Failures which may happen during consignment or contract data validation
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display_from(Debug)]
+#[non_exhaustive]
+pub enum Failure {
+    /// Schema provided for the validation does not match the schema which
+    /// genesis commits to
+    SchemaMismatch {
+        /// Schema id committed by the genesis
+        expected: SchemaId,
+        /// Id of the schema provided for the validation
+        actual: SchemaId,
+    },
+
+    /// State transition type is not defined by the schema
+    SchemaUnknownTransitionType(TransitionId, schema::TransitionType),
+
+    /// Genesis (when no id is given) or state transition contains metadata
+    /// field of the type not defined by the schema
+    SchemaUnknownFieldType(Option<TransitionId>, schema::FieldType),
+
+    /// Number of the metadata field values does not match the schema
+    SchemaMetaOccurencesError(
+        Option<TransitionId>,
+        schema::FieldType,
+        schema::OccurencesError,
+    ),
+
+    /// Metadata field value does not conform to the data format required by
+    /// the schema
+    SchemaFieldFormatMismatch(Option<TransitionId>, schema::FieldType),
+
+    /// Schema declares data format for the metadata field, which is either
+    /// inconsistent or describes data not supported by the validator, so no
+    /// field value may conform to it
+    SchemaUnsupportedFieldFormat(Option<TransitionId>, schema::FieldType),
+
+    /// Genesis or state transition defines assignments of the type which is
+    /// not allowed by the schema
+    SchemaUnknownAssignmentType(Option<TransitionId>, schema::AssignmentsType),
+
+    /// Number of the assignments of the given type does not match the schema
+    SchemaSealsOccurencesError(
+        Option<TransitionId>,
+        schema::AssignmentsType,
+        schema::OccurencesError,
+    ),
+
+    /// Assigned state does not conform to the state format required by the
+    /// schema
+    SchemaStateFormatMismatch(Option<TransitionId>, schema::AssignmentsType),
+
+    /// Schema declares state format for the assignment type, which is either
+    /// inconsistent or describes data not supported by the validator
+    SchemaUnsupportedStateFormat(Option<TransitionId>, schema::AssignmentsType),
+
+    /// Consignment data exceed limits set by the validation policy. If the
+    /// limit is violated by a state transition, its id is provided.
+    LimitExceeded(Option<TransitionId>, LimitViolation),
+
+    /// State transition closes seal of the given assignment type, which is
+    /// not defined by any of the consignment nodes
+    ClosedSealUnknown(TransitionId, schema::AssignmentsType),
+
+    /// State transition closes locked assignment of the given type, which
+    /// state is not a revealed public key
+    LockKeyUnknown(TransitionId, schema::AssignmentsType),
+
+    /// State transition closes locked assignment of the given type without
+    /// providing valid signature made with the key from the assignment state
+    LockSignatureMissing(TransitionId, schema::AssignmentsType),
+
+    /// State transition closes conditionally-owned assignment of the given
+    /// type, which state is not a revealed hash time lock
+    ConditionUnknown(TransitionId, schema::AssignmentsType),
+
+    /// State transition closes conditionally-owned assignment of the given
+    /// type satisfying neither the claim nor the refund condition
+    ConditionNotSatisfied(TransitionId, schema::AssignmentsType),
+
+    /// State transition closes conditionally-owned assignments, but it is
+    /// not committed by any of the consignment anchors or its witness
+    /// transaction can't be resolved, so the conditions can't be checked
+    WitnessUnknown(TransitionId),
+
+    /// Genesis or state transition allocates homomorphic state in amount
+    /// below the minimum allocation size defined by the schema
+    AllocationBelowMinimum(Option<TransitionId>, schema::DustAllocation),
+
+    /// Witness transaction of the anchor is not mined
+    WitnessUnconfirmed(TxRef),
+
+    /// Witness transaction of the anchor is mined after the validation
+    /// cutoff
+    WitnessAfterCutoff(TxRef, BlockInfo),
+}
+
+impl std::error::Error for Failure {}
+
+/// Events reported by the validation event streams, allowing to track the
+/// validation progress and log its details
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display_from(Debug)]
+pub enum Event {
+    /// Genesis (when no id is given) or state transition has passed the
+    /// validation
+    NodeValidated(Option<TransitionId>),
+
+    /// Anchor was checked; provides ids of the state transitions from the
+    /// consignment which the anchor commits to
+    AnchorChecked(TxRef, Vec<TransitionId>),
+
+    /// Witness transaction of the anchor, closing the seals, was found mined
+    /// within the validation cutoff
+    SealResolved(TxRef, BlockInfo),
+
+    /// Validation has failed; always the last event in the stream
+    Failure(Failure),
+}
+
+impl Event {
+    /// Returns validation failure if the event reports it
+    #[inline]
+    pub fn failure(&self) -> Option<&Failure> {
+        match self {
+            Event::Failure(failure) => Some(failure),
+            _ => None,
+        }
+    }
+}
+
+/// Converts results of the validation steps into the event stream, which
+/// ends with the first failed step
+pub(crate) fn events<'a>(
+    steps: impl Iterator<Item = Result<Event, Failure>> + 'a,
+) -> impl Iterator<Item = Event> + 'a {
+    steps.scan(false, |failed, step| {
+        if *failed {
+            return None;
+        }
+        Some(step.unwrap_or_else(|failure| {
+            *failed = true;
+            Event::Failure(failure)
+        }))
+    })
+}
+
+/// Issues found in the contract data which do not affect its validity, but
+/// may be of interest to the wallets
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display_from(Debug)]
+#[non_exhaustive]
+pub enum Warning {
+    /// Allocation was revoked by the contract issuer according to the
+    /// revocation policy defined by the schema
+    AllocationRevoked(RevokedAllocation),
+}
+
+/// Allocation revoked by the contract issuer
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct RevokedAllocation {
+    /// State transition defining the allocation, or `None` for the genesis
+    pub node_id: Option<TransitionId>,
+    pub assignment_type: schema::AssignmentsType,
+    pub seal: seal::Confidential,
+    /// Revocation transition published by the issuer
+    pub revocation: TransitionId,
+}
+
+/// Kinds of limits applied to the contract data
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum Limit {
+    /// Size of strict-encoded node metadata, in bytes
+    MetadataSize,
+    /// Total number of assignments defined by a node
+    AssignmentsCount,
+    /// Size of the node script, in bytes
+    ScriptSize,
+    /// Number of state transitions in a consignment
+    TransitionsCount,
+}
+
+/// Information about data exceeding some of the limits
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, Error)]
+#[display_from(Debug)]
+pub struct LimitViolation {
+    pub limit: Limit,
+    pub max: usize,
+    pub actual: usize,
+}
+
+impl From<LimitViolation> for strict_encoding::Error {
+    fn from(violation: LimitViolation) -> Self {
+        strict_encoding::Error::ValueOutOfRange(
+            violation.limit.to_string(),
+            0..violation.max as u64 + 1,
+            violation.actual as u64,
+        )
+    }
+}
+
+/// Upper bounds for the size of data contained in a single node (genesis or
+/// state transition), protecting from memory and CPU exhaustion attacks
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct NodeLimits {
+    pub max_metadata_size: usize,
+    pub max_assignments: usize,
+    pub max_script_size: usize,
+}
+
+impl NodeLimits {
+    /// Hard limits enforced during node decoding irrespectively of the
+    /// validation policy
+    pub const CEILING: NodeLimits = NodeLimits {
+        max_metadata_size: 0x100000,
+        max_assignments: 0xFFFF,
+        max_script_size: 0xFFFF,
+    };
+
+    /// Checks that the node data fit the limits
+    pub fn check(&self, node: &impl Node) -> Result<(), LimitViolation> {
+        let metadata_size = node
+            .metadata()
+            .strict_serialized_len()
+            .unwrap_or(usize::MAX);
+        let assignments = node.assignments().values().map(|a| a.len()).sum::<usize>();
+        let script_size = node.script().len();
+        for (limit, max, actual) in vec![
+            (Limit::MetadataSize, self.max_metadata_size, metadata_size),
+            (Limit::AssignmentsCount, self.max_assignments, assignments),
+            (Limit::ScriptSize, self.max_script_size, script_size),
+        ] {
+            if actual > max {
+                Err(LimitViolation { limit, max, actual })?
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for NodeLimits {
+    fn default() -> Self {
+        NodeLimits {
+            max_metadata_size: 0x10000,
+            max_assignments: 0x1000,
+            max_script_size: 0xFFFF,
+        }
+    }
+}
+
+/// Point in the blockchain history as of which the validation is performed:
+/// only data anchored in transactions mined at or before this point are
+/// considered valid
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub enum Cutoff {
+    /// Height of the last block included in the validation
+    Height(u32),
+    /// Time of the last block included in the validation
+    Timestamp(Timestamp),
+}
+
+impl Cutoff {
+    /// Checks whether the block precedes the cutoff or matches it
+    pub fn includes(&self, block: &BlockInfo) -> bool {
+        match self {
+            Cutoff::Height(height) => block.height <= *height,
+            Cutoff::Timestamp(timestamp) => block.timestamp <= *timestamp,
+        }
+    }
+}
+
+/// Validation policy defining limits applied to the validated data
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct Policy {
+    pub node_limits: NodeLimits,
+    pub max_transitions: usize,
+    /// Accept allocations below the minimum allocation size defined by the
+    /// schema
+    pub allow_dust: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            node_limits: NodeLimits::default(),
+            max_transitions: 0xFFFF,
+            allow_dust: false,
+        }
+    }
+}
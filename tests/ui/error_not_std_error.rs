@@ -0,0 +1,16 @@
+// Error type of the encoder must implement `std::error::Error`
+
+use lnpbp::strict_encoding::StrictEncode;
+use std::io;
+
+struct Flag(u8);
+
+impl StrictEncode for Flag {
+    type Error = String;
+
+    fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+        self.0.strict_encode(e).map_err(|err| err.to_string())
+    }
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+// Error type of the encoder must be constructible from the strict encoding
+// error, since the encoders of the primitive and collection types must be
+// able to report their failures
+
+use lnpbp::strict_encoding::StrictEncode;
+use std::{fmt, io};
+
+#[derive(Debug)]
+struct FlagError;
+
+impl fmt::Display for FlagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("flag error")
+    }
+}
+
+impl std::error::Error for FlagError {}
+
+struct Flag(u8);
+
+impl StrictEncode for Flag {
+    type Error = FlagError;
+
+    fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+        Ok(self.0.strict_encode(e)?)
+    }
+}
+
+fn main() {}
@@ -0,0 +1,51 @@
+// Nested data with a custom error type can't be decoded as a part of a type
+// using the strict encoding error, since the custom error can't be converted
+// into it
+
+use lnpbp::strict_encoding::{self, StrictDecode};
+use std::{fmt, io};
+
+#[derive(Debug)]
+struct TitleError;
+
+impl fmt::Display for TitleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("title error")
+    }
+}
+
+impl std::error::Error for TitleError {}
+
+impl From<strict_encoding::Error> for TitleError {
+    fn from(_: strict_encoding::Error) -> Self {
+        TitleError
+    }
+}
+
+struct Title(String);
+
+impl StrictDecode for Title {
+    type Error = TitleError;
+
+    fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+        Ok(Title(String::strict_decode(d)?))
+    }
+}
+
+struct Article {
+    title: Title,
+    pages: u16,
+}
+
+impl StrictDecode for Article {
+    type Error = strict_encoding::Error;
+
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(Self {
+            title: Title::strict_decode(&mut d)?,
+            pages: u16::strict_decode(&mut d)?,
+        })
+    }
+}
+
+fn main() {}